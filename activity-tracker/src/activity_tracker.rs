@@ -13,7 +13,7 @@ use log::{trace, warn};
 use common::database::Database;
 use common::display::Display;
 use common::platform::{DefaultPlatform, Platform};
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use type_map::TypeMap;
 
 use crate::view::App;
@@ -33,7 +33,7 @@ impl ActivityTracker<DefaultPlatform> {
 
         let mut res = TypeMap::new();
         res.insert(Database::new()?);
-        res.insert(Stylesheet::load()?);
+        res.insert(Styles::load_fitted(display.size().height)?);
         res.insert(Locale::new(&LocaleSettings::load()?.lang));
         res.insert(Into::<geom::Size>::into(display.size()));
         let res = Resources::new(res);
@@ -50,7 +50,7 @@ impl ActivityTracker<DefaultPlatform> {
 
     pub async fn run_event_loop(&mut self) -> Result<()> {
         self.display
-            .clear(self.res.get::<Stylesheet>().background_color)?;
+            .clear(self.res.get::<Styles>().background_color)?;
         self.display.save()?;
 
         #[cfg(unix)]
@@ -63,7 +63,7 @@ impl ActivityTracker<DefaultPlatform> {
             if self.view.should_draw()
                 && self
                     .view
-                    .draw(&mut self.display, &self.res.get::<Stylesheet>())?
+                    .draw(&mut self.display, &self.res.get::<Styles>())?
             {
                 self.display.flush()?;
             }
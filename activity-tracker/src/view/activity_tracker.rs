@@ -3,14 +3,14 @@ use std::collections::{HashMap, VecDeque};
 use anyhow::Result;
 use async_trait::async_trait;
 use common::command::Command;
-use common::constants::{RECENT_GAMES_LIMIT, SELECTION_MARGIN};
+use common::constants::RECENT_GAMES_LIMIT;
 use common::database::{Database, Game};
 use common::display::Display;
 use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, View};
 use embedded_graphics::prelude::OriginDimensions;
 use serde::{Deserialize, Serialize};
@@ -30,13 +30,13 @@ impl ActivityTracker {
     pub fn new(rect: Rect, res: Resources) -> Result<Self> {
         let Rect { x, y, w, h } = rect;
 
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let list = SettingsList::new(
             Rect::new(x + 12, y, w - 24, h - 8 - ButtonIcon::diameter(&styles)),
             Vec::new(),
             Vec::new(),
-            res.get::<Stylesheet>().ui_font.size + SELECTION_MARGIN,
+            res.get::<Styles>().row_layout(),
         );
 
         let button_hints = Row::new(
@@ -131,7 +131,7 @@ impl View for ActivityTracker {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -194,7 +194,7 @@ impl View for ActivityTracker {
         vec![&mut self.list, &mut self.button_hints]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
@@ -9,7 +9,7 @@ use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::{BatteryIndicator, Label, View};
 use tokio::sync::mpsc::Sender;
 
@@ -33,7 +33,7 @@ where
 {
     pub fn new(rect: Rect, res: Resources, battery: B) -> Result<Self> {
         let Rect { x, y, w, h } = rect;
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
         let locale = res.get::<Locale>();
 
         let battery_indicator = BatteryIndicator::new(Point::new(w as i32 - 12, y + 8), battery);
@@ -75,7 +75,7 @@ where
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         if self.dirty {
             display.load(self.bounding_box(styles))?;
@@ -120,7 +120,7 @@ where
         vec![&mut self.battery_indicator, &mut self.view]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
@@ -1,29 +1,54 @@
 use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::process;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use common::command::Command;
-use common::constants::ALLIUM_GAMES_DIR;
+use common::constants::{ALLIUM_GAMES_DIR, ALLIUM_LAUNCH_ENV, ALLIUM_TOAST_ENV};
 use common::display::color::Color;
+use common::filename_rules::FilenameRules;
+use common::game_info::GameInfo;
 use common::geom;
+use common::launch_failure::LaunchFailure;
+use common::legacy_layout::{LegacyFolder, LegacyLayouts, LegacyState};
+use common::list_settings::ListSettings;
 use common::locale::{Locale, LocaleSettings};
+use common::notification::{Notification, Severity};
+use common::profile::{Profile, Profiles};
 use common::resources::Resources;
-use common::view::View;
+use common::splash;
+use common::view::{Toast, View, IMAGE_DECODED};
 use embedded_graphics::prelude::*;
 use enum_map::EnumMap;
 use log::{info, trace, warn};
 
 use common::database::Database;
+use common::diagnostics::{self, Budget, RunningSelfTest, SelfTest};
 use common::display::Display;
-use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use common::stylesheet::Stylesheet;
+use common::platform::{self, DefaultPlatform, Key, KeyEvent, Platform};
+use common::stylesheet::{StyleConfig, Styles};
+use common::theme_schedule::{ThemeChanged, ThemePeriod, ThemeSchedule};
+use common::trash::{self, Trash, UNDO_WINDOW};
+use common::wifi;
 use type_map::TypeMap;
 
 use crate::consoles::ConsoleMapper;
 use crate::entry::directory::Directory;
+use crate::entry::folder_view::FolderViews;
 use crate::entry::game::Game;
-use crate::view::{App, Toast};
+use crate::entry::{art_index, game_index};
+use crate::launcher_config::LauncherConfig;
+use crate::library_filter::LibraryFilter;
+use crate::scraper;
+use crate::setup::SetupState;
+use crate::view::{
+    App, BoxArtScrape, LaunchFailureDialog, LegacyMigration, ProfileChooser, SetupWizard,
+    SuspendedGame, ThemeConfirm,
+};
+
+/// How often to check whether a game is suspended in the background.
+const SUSPENDED_GAME_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Debug)]
 pub struct AlliumLauncher<P: Platform> {
@@ -31,80 +56,262 @@ pub struct AlliumLauncher<P: Platform> {
     display: P::Display,
     res: Resources,
     view: App<P::Battery>,
+    chooser: Option<ProfileChooser>,
+    migration: Option<LegacyMigration>,
+    setup: Option<SetupWizard>,
+    launch_failure: Option<LaunchFailureDialog>,
+    /// Progress of downloading box art for a folder.
+    box_art: Option<BoxArtScrape>,
+    suspended: Option<SuspendedGame>,
+    since_suspended_check: Duration,
+    theme_confirm: Option<ThemeConfirm>,
+    /// Self-test creating a diagnostics bundle in the background.
+    self_test: Option<RunningSelfTest>,
     toast: Option<Toast>,
+    /// Notifications passed on by alliumd, shown one toast at a time.
+    notifications: VecDeque<Notification>,
+    /// Actions that Y undoes until the deadline.
+    undo: Option<(Vec<i64>, Instant)>,
 }
 
 impl AlliumLauncher<DefaultPlatform> {
     pub fn new(mut platform: DefaultPlatform) -> Result<Self> {
-        let display = platform.display()?;
+        let display = platform::open_display(&mut platform)?;
         let battery = platform.battery()?;
 
         let mut console_mapper = ConsoleMapper::new();
         console_mapper.load_config()?;
 
+        let legacy_folders = detect_legacy_folders(&console_mapper);
+
+        // Checked before anything else saves state
+        let needs_setup = SetupState::is_needed()?;
+
+        let profiles = Profiles::load()?;
+
+        art_index::spawn_indexer();
+        game_index::spawn_indexer(false);
+
+        let database = Database::new()?;
+        if database.scrape_progress()?.pending > 0 {
+            info!("resuming scrape queue");
+            scraper::spawn_worker();
+        }
+
+        let trash = Trash::new();
+        if let Err(e) = trash::auto_purge(&database, &trash) {
+            warn!("failed to purge trash: {}", e);
+        }
+
         let mut res = TypeMap::new();
-        res.insert(Database::new()?);
+        res.insert(database);
+        res.insert(trash);
+        res.insert(profiles.active());
         res.insert(console_mapper);
-        res.insert(Stylesheet::load()?);
+        res.insert(FolderViews::load()?);
+        res.insert(ListSettings::load()?);
+        FilenameRules::load()?.apply();
+        match LauncherConfig::load() {
+            Ok(config) => {
+                config.apply();
+            }
+            Err(e) => warn!("failed to load launcher config: {}", e),
+        }
+        res.insert(Styles::load_fitted(display.size().height)?);
         res.insert(Locale::new(&LocaleSettings::load()?.lang));
         res.insert(Into::<geom::Size>::into(display.size()));
         let res = Resources::new(res);
 
         let view = App::load_or_new(display.bounding_box().into(), res.clone(), battery)?;
 
+        let chooser = if profiles.needs_choice() {
+            Some(ProfileChooser::new(
+                display.bounding_box().into(),
+                res.clone(),
+                profiles,
+                Profile::last_name(),
+            ))
+        } else {
+            None
+        };
+
+        let migration = if legacy_folders.is_empty() {
+            None
+        } else {
+            Some(LegacyMigration::new(
+                display.bounding_box().into(),
+                res.clone(),
+                legacy_folders,
+            ))
+        };
+
+        let setup = if needs_setup {
+            info!("starting setup");
+            Some(SetupWizard::new(display.bounding_box().into(), res.clone()))
+        } else {
+            None
+        };
+
+        // Passed on by alliumd when the last game exited right after launching
+        let launch_failure = LaunchFailure::from_env().map(|failure| {
+            LaunchFailureDialog::new(display.bounding_box().into(), res.clone(), failure)
+        });
+
+        // Left by alliumd, e.g. after force quitting a frozen game
+        let toast = std::env::var(ALLIUM_TOAST_ENV)
+            .ok()
+            .map(|text| Toast::new(text, Some(Duration::from_secs(5))));
+
+        // Queued by alliumd while the launcher wasn't running
+        let notifications = Notification::from_env().into();
+
         Ok(AlliumLauncher {
             platform,
             display,
             res,
             view,
-            toast: None,
+            chooser,
+            migration,
+            setup,
+            launch_failure,
+            box_art: None,
+            suspended: None,
+            since_suspended_check: SUSPENDED_GAME_INTERVAL,
+            theme_confirm: None,
+            self_test: None,
+            toast,
+            notifications,
+            undo: None,
         })
     }
 
     pub async fn run_event_loop(&mut self) -> Result<()> {
         self.display
-            .clear(self.res.get::<Stylesheet>().background_color)?;
+            .clear(self.res.get::<Styles>().background_color)?;
         self.display.save()?;
 
         #[cfg(unix)]
         let mut sigterm =
             tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        // Sent by alliumd when the theme schedule switches between day and night
+        #[cfg(unix)]
+        let mut sigusr1 =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined1())?;
 
         let (tx, mut rx) = tokio::sync::mpsc::channel(100);
 
+        if let Some(command) = self.remote_launch() {
+            tx.send(command).await?;
+        }
+
         let mut keys: EnumMap<Key, bool> = EnumMap::default();
 
         let mut frame_interval = tokio::time::interval(tokio::time::Duration::from_micros(166_667));
 
+        let mut is_ready = false;
         let mut last_frame = Instant::now();
         loop {
             let dt = last_frame.elapsed();
             self.view.update(dt);
+            if let Some(box_art) = self.box_art.as_mut() {
+                box_art.update(dt);
+            }
+            if let Some(chooser) = self.chooser.as_mut() {
+                chooser.update(dt);
+            }
+            self.update_suspended_game(dt)?;
+            self.update_theme_confirm()?;
+            self.update_self_test();
             last_frame = Instant::now();
 
-            let mut drawn = self.view.should_draw()
-                && self
-                    .view
-                    .draw(&mut self.display, &self.res.get::<Stylesheet>())?;
+            let mut drawn = if let Some(launch_failure) = self.launch_failure.as_mut() {
+                launch_failure.should_draw()
+                    && launch_failure.draw(&mut self.display, &self.res.get::<Styles>())?
+            } else if let Some(box_art) = self.box_art.as_mut() {
+                box_art.should_draw()
+                    && box_art.draw(&mut self.display, &self.res.get::<Styles>())?
+            } else if let Some(migration) = self.migration.as_mut() {
+                migration.should_draw()
+                    && migration.draw(&mut self.display, &self.res.get::<Styles>())?
+            } else if let Some(chooser) = self.chooser.as_mut() {
+                chooser.should_draw()
+                    && chooser.draw(&mut self.display, &self.res.get::<Styles>())?
+            } else if let Some(setup) = self.setup.as_mut() {
+                setup.should_draw() && setup.draw(&mut self.display, &self.res.get::<Styles>())?
+            } else if self.view.should_draw() {
+                let result = self.view.draw(&mut self.display, &self.res.get::<Styles>());
+                match result {
+                    Ok(drawn) => drawn,
+                    // A theme that can't be drawn is reverted rather than crashing the launcher
+                    Err(e) if self.theme_confirm.is_some() => {
+                        warn!("failed to draw with new theme: {}", e);
+                        self.revert_stylesheet()?;
+                        false
+                    }
+                    Err(e) => return Err(e),
+                }
+            } else {
+                false
+            };
+
+            if let Some(theme_confirm) = self.theme_confirm.as_mut() {
+                if drawn {
+                    theme_confirm.set_should_draw();
+                }
+                drawn |= theme_confirm.should_draw()
+                    && theme_confirm.draw(&mut self.display, &self.res.get::<Styles>())?;
+            }
+
+            if let Some(suspended) = self.suspended.as_mut() {
+                if drawn {
+                    suspended.set_should_draw();
+                }
+                drawn |= suspended.should_draw()
+                    && suspended.draw(&mut self.display, &self.res.get::<Styles>())?;
+            }
+
+            if self.toast.is_none() {
+                self.toast = self.notifications.pop_front().map(|notification| {
+                    Toast::new(
+                        notification.text,
+                        Some(toast_duration(notification.severity)),
+                    )
+                });
+            }
 
             if let Some(toast) = self.toast.as_mut() {
                 if toast.has_expired() {
                     self.toast = None;
                 } else {
-                    drawn |= toast.draw(&mut self.display, &self.res.get::<Stylesheet>())?;
+                    drawn |= toast.draw(&mut self.display, &self.res.get::<Styles>())?;
                 }
             }
 
             if drawn {
                 self.display.flush()?;
+                if !is_ready {
+                    splash::notify_ready();
+                    is_ready = true;
+                }
             }
 
             #[cfg(unix)]
             tokio::select! {
                 _ = frame_interval.tick() => {}
+                // Box art decoded in the background is drawn as soon as it's ready
+                _ = IMAGE_DECODED.notified() => {}
                 _ = sigterm.recv() => {
                     self.handle_command(Command::Exit).await?;
                 }
+                _ = sigusr1.recv() => {
+                    match ThemeChanged::receive() {
+                        Ok(Some(changed)) => {
+                            self.handle_command(Command::ThemeChanged(changed.period)).await?;
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!("failed to read theme change: {}", e),
+                    }
+                }
                 cmd = rx.recv() => {
                     if let Some(cmd) = cmd {
                         self.handle_command(cmd).await?;
@@ -120,11 +327,69 @@ impl AlliumLauncher<DefaultPlatform> {
                             keys[key] = false;
                         }
                         KeyEvent::Autorepeat(_) => {}
+                        // alliumd suspends everything while the lid is closed
+                        KeyEvent::Lid(closed) => {
+                            if !closed {
+                                self.reload_config()?;
+                            }
+                            continue;
+                        }
+                    }
+
+                    if let (Some(self_test), KeyEvent::Pressed(Key::B)) = (self.self_test.as_ref(), event) {
+                        info!("cancelling diagnostics");
+                        self_test.cancel();
+                        continue;
+                    }
+
+                    if let (Some((_, deadline)), KeyEvent::Pressed(Key::Y)) = (self.undo.as_ref(), event) {
+                        if Instant::now() < *deadline {
+                            let (ids, _) = self.undo.take().unwrap();
+                            self.handle_command(Command::Undo(ids)).await?;
+                            continue;
+                        }
+                        self.undo = None;
                     }
 
                     // Ignore menu key presses
                     if !keys[Key::Menu] && !matches!(event, KeyEvent::Released(Key::Menu)) {
-                        self.view.handle_key_event(event, tx.clone(), &mut bubble).await?;
+                        if let Some(suspended) = self.suspended.as_mut() {
+                            if suspended.handle_key_event(event, tx.clone(), &mut bubble).await? {
+                                continue;
+                            }
+                        }
+                        if let Some(theme_confirm) = self.theme_confirm.as_mut() {
+                            theme_confirm.handle_key_event(event, tx.clone(), &mut bubble).await?;
+                            let keep = bubble
+                                .iter()
+                                .any(|c| matches!(c, Command::ValueChanged(_, _)));
+                            if keep {
+                                self.keep_stylesheet()?;
+                            } else if bubble.iter().any(|c| matches!(c, Command::CloseView)) {
+                                self.revert_stylesheet()?;
+                            }
+                        } else if let Some(launch_failure) = self.launch_failure.as_mut() {
+                            launch_failure.handle_key_event(event, tx.clone(), &mut bubble).await?;
+                            if bubble.iter().any(|c| matches!(c, Command::CloseView)) {
+                                self.launch_failure = None;
+                            }
+                        } else if let Some(box_art) = self.box_art.as_mut() {
+                            box_art.handle_key_event(event, tx.clone(), &mut bubble).await?;
+                            if bubble.iter().any(|c| matches!(c, Command::CloseView)) {
+                                self.box_art = None;
+                            }
+                        } else if let Some(migration) = self.migration.as_mut() {
+                            migration.handle_key_event(event, tx.clone(), &mut bubble).await?;
+                        } else if let Some(chooser) = self.chooser.as_mut() {
+                            chooser.handle_key_event(event, tx.clone(), &mut bubble).await?;
+                        } else if let Some(setup) = self.setup.as_mut() {
+                            setup.handle_key_event(event, tx.clone(), &mut bubble).await?;
+                            if bubble.iter().any(|c| matches!(c, Command::CloseView)) {
+                                self.finish_setup()?;
+                            }
+                        } else {
+                            self.view.handle_key_event(event, tx.clone(), &mut bubble).await?;
+                        }
                     }
                 }
                 else => {}
@@ -132,6 +397,7 @@ impl AlliumLauncher<DefaultPlatform> {
 
             #[cfg(not(unix))]
             tokio::select! {
+                _ = IMAGE_DECODED.notified() => {}
                 event = self.platform.poll() => {
                     let mut bubble = VecDeque::new();
                     self.view.handle_key_event(event, tx.clone(), &mut bubble).await?;
@@ -141,6 +407,265 @@ impl AlliumLauncher<DefaultPlatform> {
         }
     }
 
+    /// Shows the suspended game banner while a game is suspended in the background, and removes
+    /// it as soon as the game has exited.
+    fn update_suspended_game(&mut self, dt: Duration) -> Result<()> {
+        self.since_suspended_check += dt;
+        if self.since_suspended_check < SUSPENDED_GAME_INTERVAL {
+            return Ok(());
+        }
+        self.since_suspended_check = Duration::ZERO;
+
+        let game_info = GameInfo::load()?
+            .filter(|game_info| game_info.pid != Some(process::id()) && game_info.is_running());
+        match (game_info, self.suspended.is_some()) {
+            (Some(game_info), false) => {
+                info!("{} is suspended in the background", game_info.name);
+                let text = self.res.get::<Locale>().ta(
+                    "suspended-game-banner",
+                    &[("name".to_string(), game_info.name.into())]
+                        .into_iter()
+                        .collect(),
+                );
+                self.suspended = Some(SuspendedGame::new(text));
+            }
+            (None, true) => {
+                info!("suspended game has exited");
+                self.suspended = None;
+                self.display.load(self.display.bounding_box().into())?;
+                // It was just played, so it goes to the top of the recently played list
+                self.reload_view()?;
+                self.view.set_should_draw();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Reverts a theme that is awaiting confirmation once its countdown runs out.
+    fn update_theme_confirm(&mut self) -> Result<()> {
+        if self.theme_confirm.as_mut().is_some_and(|c| c.tick()) {
+            info!("theme was not kept in time");
+            self.revert_stylesheet()?;
+        }
+        Ok(())
+    }
+
+    /// Command to launch the game asked for through alliumd's companion API, if any.
+    fn remote_launch(&mut self) -> Option<Command> {
+        let path = PathBuf::from(std::env::var_os(ALLIUM_LAUNCH_ENV)?);
+        info!("launching {} for the companion API", path.display());
+        let mut game = Game::new(path);
+        let result = self
+            .res
+            .get::<ConsoleMapper>()
+            .launch_game(&self.res.get(), &mut game);
+        match result {
+            Ok(command) => Some(command),
+            Err(e) => {
+                warn!("failed to launch {}: {}", game.path.display(), e);
+                let toast = self.res.get::<Locale>().ta(
+                    "remote-launch-failed",
+                    &[("name".to_string(), game.name.into())]
+                        .into_iter()
+                        .collect(),
+                );
+                self.toast = Some(Toast::new(toast, Some(Duration::from_secs(5))));
+                None
+            }
+        }
+    }
+
+    /// Reports the outcome of the diagnostics self-test once it has finished.
+    fn update_self_test(&mut self) {
+        if !self.self_test.as_ref().is_some_and(|s| s.is_finished()) {
+            return;
+        }
+        let self_test = self.self_test.take().unwrap();
+        let is_cancelled = self_test.is_cancelled();
+        let locale = self.res.get::<Locale>();
+        let toast = match self_test.join() {
+            Ok(path) => locale.ta(
+                "diagnostics-done",
+                &[("path".to_string(), path.display().to_string().into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            Err(_) if is_cancelled => locale.t("diagnostics-cancelled"),
+            Err(e) => {
+                warn!("failed to create diagnostics: {}", e);
+                locale.t("diagnostics-failed")
+            }
+        };
+        drop(locale);
+        self.toast = Some(Toast::new(toast, Some(Duration::from_secs(5))));
+    }
+
+    /// Loads the fonts and icons of `styles`, with its metrics fitted to the screen.
+    fn load_stylesheet(&self, mut styles: StyleConfig) -> Result<Styles> {
+        styles.clamp_metrics(self.display.size().height);
+        Styles::from_config(styles)
+    }
+
+    /// Makes `styles` the current theme and lays out every view again with it. Doesn't save it.
+    fn apply_stylesheet(&mut self, styles: Styles) -> Result<()> {
+        styles.apply_render_mode();
+        self.display.clear(styles.background_color)?;
+        self.display.save()?;
+        self.res.insert(styles);
+        self.reload_view()
+    }
+
+    /// Checks that `styles` is a valid theme, and shows why not if it isn't.
+    fn check_stylesheet(&mut self, styles: &StyleConfig) -> Result<bool> {
+        let Err(e) = styles.validate() else {
+            return Ok(true);
+        };
+        warn!("refusing invalid theme: {}", e);
+        let toast = self.res.get::<Locale>().ta(
+            "settings-theme-invalid",
+            &[("reason".to_string(), e.to_string().into())]
+                .into_iter()
+                .collect(),
+        );
+        self.toast = Some(Toast::new(toast, Some(Duration::from_secs(3))));
+        // Shows the current theme's values again
+        self.reload_view()?;
+        Ok(false)
+    }
+
+    /// Loads and applies `styles`, going back to `previous` if that fails. Returns whether
+    /// `styles` was applied.
+    fn try_apply_stylesheet(&mut self, styles: StyleConfig, previous: &Styles) -> Result<bool> {
+        if let Err(e) = self
+            .load_stylesheet(styles)
+            .and_then(|styles| self.apply_stylesheet(styles))
+        {
+            warn!("failed to apply theme: {}", e);
+            self.apply_stylesheet(previous.clone())?;
+            let toast = self.res.get::<Locale>().t("settings-theme-failed");
+            self.toast = Some(Toast::new(toast, Some(Duration::from_secs(3))));
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Switches to the theme of the current period if the theme schedule is on.
+    fn apply_theme_schedule(&mut self) -> Result<()> {
+        match ThemeSchedule::load().map(|schedule| schedule.current()) {
+            Ok(Some(period)) => self.apply_theme_period(period),
+            Ok(None) => Ok(()),
+            Err(e) => {
+                warn!("failed to load theme schedule: {}", e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Switches to the day or night theme. Waits while a theme is awaiting confirmation, until
+    /// it's kept.
+    fn apply_theme_period(&mut self, period: ThemePeriod) -> Result<()> {
+        if self.theme_confirm.is_some() {
+            return Ok(());
+        }
+        let schedule = match ThemeSchedule::load() {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                warn!("failed to load theme schedule: {}", e);
+                return Ok(());
+            }
+        };
+        let current = self.res.get::<Styles>().clone();
+        let mut styles = schedule.theme_for(period, current.config().clone());
+        styles.clamp_metrics(self.display.size().height);
+        if styles == *current.config() || !self.check_stylesheet(&styles)? {
+            return Ok(());
+        }
+        info!("switching to {:?} theme", period);
+        self.try_apply_stylesheet(styles, &current)?;
+        Ok(())
+    }
+
+    /// Saves the theme that is awaiting confirmation. While the theme schedule is on, it becomes
+    /// the theme of the current period.
+    fn keep_stylesheet(&mut self) -> Result<()> {
+        if self.theme_confirm.take().is_some() {
+            info!("keeping theme");
+            let styles = self.res.get::<Styles>().clone();
+            styles.save()?;
+            match ThemeSchedule::load() {
+                Ok(mut schedule) => {
+                    if let Some(period) = schedule.current() {
+                        schedule.set_theme(period, styles.config().clone());
+                        schedule.save()?;
+                    }
+                }
+                Err(e) => warn!("failed to load theme schedule: {}", e),
+            }
+            self.display.load(self.display.bounding_box().into())?;
+            self.view.set_should_draw();
+            self.apply_theme_schedule()?;
+        }
+        Ok(())
+    }
+
+    /// Goes back to the theme from before the one awaiting confirmation.
+    fn revert_stylesheet(&mut self) -> Result<()> {
+        if let Some(theme_confirm) = self.theme_confirm.take() {
+            info!("reverting theme");
+            self.apply_stylesheet(theme_confirm.into_previous())?;
+        }
+        Ok(())
+    }
+
+    /// Shows a toast offering to undo the actions with `ids` for a short while.
+    fn offer_undo(&mut self, text: String, ids: Vec<i64>) {
+        trace!("offering to undo: {:?}", ids);
+        let toast = self.res.get::<Locale>().ta(
+            "undo-offer",
+            &[("action".to_string(), text.into())].into_iter().collect(),
+        );
+        self.toast = Some(Toast::new(toast, Some(UNDO_WINDOW)));
+        self.undo = Some((ids, Instant::now() + UNDO_WINDOW));
+    }
+
+    fn reload_view(&mut self) -> Result<()> {
+        self.view.save()?;
+        self.view = App::load_or_new(
+            self.display.bounding_box().into(),
+            self.res.clone(),
+            self.platform.battery()?,
+        )?;
+        if let Some(setup) = self.setup.as_mut() {
+            setup.reload();
+        }
+        Ok(())
+    }
+
+    /// Reads the launcher config again, e.g. after it was edited over FTP, listing the games again
+    /// if what's excluded changed.
+    fn reload_config(&mut self) -> Result<()> {
+        match LauncherConfig::load() {
+            Ok(config) => {
+                if config.apply() {
+                    info!("launcher config changed, reloading");
+                    self.reload_view()?;
+                }
+            }
+            Err(e) => warn!("failed to load launcher config: {}", e),
+        }
+        Ok(())
+    }
+
+    /// Closes the setup wizard, and remembers not to show it again.
+    fn finish_setup(&mut self) -> Result<()> {
+        self.setup = None;
+        SetupState { completed: true }.save()?;
+        self.display.load(self.display.bounding_box().into())?;
+        self.view.set_should_draw();
+        Ok(())
+    }
+
     async fn handle_command(&mut self, command: Command) -> Result<()> {
         match command {
             Command::Exit => {
@@ -174,19 +699,36 @@ impl AlliumLauncher<DefaultPlatform> {
                     process::exit();
                 }
             }
-            Command::SaveStylesheet(mut styles) => {
-                trace!("saving stylesheet");
-                styles.load_fonts()?;
-                styles.save()?;
-                self.display.clear(styles.background_color)?;
-                self.display.save()?;
-                self.res.insert(*styles);
-                self.view.save()?;
-                self.view = App::load_or_new(
-                    self.display.bounding_box().into(),
-                    self.res.clone(),
-                    self.platform.battery()?,
-                )?;
+            Command::SaveStylesheet(styles) => {
+                trace!("applying stylesheet");
+                if !self.check_stylesheet(&styles)? {
+                    return Ok(());
+                }
+                let previous = match self.theme_confirm.take() {
+                    Some(theme_confirm) => theme_confirm.into_previous(),
+                    None => self.res.get::<Styles>().clone(),
+                };
+                if self.try_apply_stylesheet(*styles, &previous)? {
+                    self.theme_confirm = Some(ThemeConfirm::new(
+                        self.display.bounding_box().into(),
+                        self.res.clone(),
+                        previous,
+                    ));
+                }
+            }
+            Command::SaveThemeSchedule(mut schedule) => {
+                trace!("saving theme schedule");
+                // The day and night themes are only changed by keeping a theme
+                if let Ok(saved) = ThemeSchedule::load() {
+                    schedule.day_theme = saved.day_theme;
+                    schedule.night_theme = saved.night_theme;
+                }
+                schedule.save()?;
+                self.apply_theme_schedule()?;
+            }
+            Command::ThemeChanged(period) => {
+                trace!("theme schedule switched to {:?}", period);
+                self.apply_theme_period(period)?;
             }
             Command::SaveDisplaySettings(settings) => {
                 trace!("saving display settings");
@@ -198,12 +740,20 @@ impl AlliumLauncher<DefaultPlatform> {
                 trace!("saving locale settings");
                 settings.save()?;
                 self.res.insert(Locale::new(&settings.lang));
-                self.view.save()?;
-                self.view = App::load_or_new(
-                    self.display.bounding_box().into(),
-                    self.res.clone(),
-                    self.platform.battery()?,
-                )?;
+                self.reload_view()?;
+            }
+            Command::SaveFilenameRules(rules) => {
+                trace!("saving filename rules");
+                rules.save()?;
+                rules.apply();
+                // Sort the lists again by the new rules
+                self.reload_view()?;
+            }
+            Command::SaveListSettings(settings) => {
+                trace!("saving list settings");
+                settings.save()?;
+                self.res.insert(settings);
+                self.reload_view()?;
             }
             Command::Redraw => {
                 trace!("redrawing");
@@ -222,14 +772,183 @@ impl AlliumLauncher<DefaultPlatform> {
                 trace!("showing toast: {:?}", text);
                 self.toast = Some(Toast::new(text, duration));
             }
+            Command::OfferUndo(text, ids) => self.offer_undo(text, ids),
+            Command::ClearRecents => {
+                let id = trash::clear_recents(&self.res.get::<Database>())?;
+                info!("cleared recents");
+                self.reload_view()?;
+                let locale = self.res.get::<Locale>();
+                match id {
+                    Some(id) => {
+                        let text = locale.t("undo-clear-recents");
+                        drop(locale);
+                        self.offer_undo(text, vec![id]);
+                    }
+                    None => {
+                        let toast = locale.t("settings-library-clear-recents-empty");
+                        drop(locale);
+                        self.toast = Some(Toast::new(toast, Some(Duration::from_secs(3))));
+                    }
+                }
+            }
+            Command::Undo(ids) => {
+                self.undo = None;
+                let result = {
+                    let database = self.res.get::<Database>();
+                    let trash = self.res.get::<Trash>();
+                    ids.iter()
+                        .rev()
+                        .try_for_each(|&id| trash::undo(&database, &trash, id))
+                };
+                let toast = match result {
+                    Ok(()) => self.res.get::<Locale>().t("undo-done"),
+                    Err(e) => {
+                        warn!("failed to undo: {}", e);
+                        self.res.get::<Locale>().ta(
+                            "undo-failed",
+                            &[("reason".to_string(), e.to_string().into())]
+                                .into_iter()
+                                .collect(),
+                        )
+                    }
+                };
+                self.reload_view()?;
+                self.toast = Some(Toast::new(toast, Some(Duration::from_secs(3))));
+            }
+            Command::StartSetup => {
+                info!("starting setup");
+                SetupState { completed: false }.save()?;
+                self.setup = Some(SetupWizard::new(
+                    self.display.bounding_box().into(),
+                    self.res.clone(),
+                ));
+            }
+            Command::SelectProfile(name) => {
+                info!("selecting profile: {}", name);
+                Profile::set_active(&name)?;
+                let profile = Profiles::load()?.active();
+                let database = self.res.get::<Database>().with_profile(&profile.name);
+                self.res.insert(database);
+                self.res.insert(profile);
+                self.chooser = None;
+                self.view = App::load_or_new(
+                    self.display.bounding_box().into(),
+                    self.res.clone(),
+                    self.platform.battery()?,
+                )?;
+            }
+            Command::MigrateLegacyFolders(mode) => {
+                let Some(migration) = self.migration.take() else {
+                    return Ok(());
+                };
+                info!("migrating legacy folders: {:?}", mode);
+
+                let result = LegacyState::load().and_then(|mut state| {
+                    let result = state.migrate(&ALLIUM_GAMES_DIR, migration.folders(), mode);
+                    state.save()?;
+                    result
+                });
+                let toast = match result {
+                    Ok(()) => {
+                        let mut console_mapper = ConsoleMapper::new();
+                        console_mapper.load_config()?;
+                        self.res.insert(console_mapper);
+                        self.view = App::load_or_new(
+                            self.display.bounding_box().into(),
+                            self.res.clone(),
+                            self.platform.battery()?,
+                        )?;
+                        if let Some(chooser) = self.chooser.as_mut() {
+                            chooser.set_should_draw();
+                        }
+                        self.res.get::<Locale>().t("legacy-migration-done")
+                    }
+                    Err(e) => {
+                        warn!("failed to migrate legacy folders: {}", e);
+                        self.migration = Some(migration);
+                        self.res.get::<Locale>().t("legacy-migration-failed")
+                    }
+                };
+                self.toast = Some(Toast::new(toast, Some(Duration::from_secs(3))));
+            }
+            Command::ShowLaunchFailure(failure) => {
+                self.launch_failure = Some(LaunchFailureDialog::new(
+                    self.display.bounding_box().into(),
+                    self.res.clone(),
+                    failure,
+                ));
+            }
+            Command::ResumeGame => {
+                info!("resuming suspended game");
+                GameInfo::request_resume();
+            }
+            Command::QuitSuspendedGame => {
+                if let Some(game_info) = GameInfo::load()? {
+                    info!("quitting suspended game: {}", game_info.name);
+                    game_info.request_quit()?;
+                    let toast = self.res.get::<Locale>().ta(
+                        "suspended-game-quitting",
+                        &[("name".to_string(), game_info.name.into())]
+                            .into_iter()
+                            .collect(),
+                    );
+                    self.toast = Some(Toast::new(toast, Some(Duration::from_secs(3))));
+                }
+            }
+            Command::RunDiagnostics => {
+                if self.self_test.is_some() {
+                    return Ok(());
+                }
+                info!("running diagnostics");
+                let mut self_test = SelfTest::new(Budget::new(diagnostics::TIME_LIMIT));
+                self_test.run_device_checks(&mut self.display, &mut self.platform.battery()?);
+                self.self_test = Some(self_test.spawn());
+                let toast = self.res.get::<Locale>().t("diagnostics-running");
+                self.toast = Some(Toast::new(toast, None));
+            }
+            Command::ScrapeBoxArt(folder, redownload) => {
+                if wifi::ip_address().is_none() {
+                    let toast = self.res.get::<Locale>().t("box-art-scrape-no-wifi");
+                    self.toast = Some(Toast::new(toast, Some(Duration::from_secs(3))));
+                    return Ok(());
+                }
+                let paths = {
+                    let database = self.res.get::<Database>();
+                    scraper::enqueue_folder(
+                        &database,
+                        &self.res.get::<ConsoleMapper>(),
+                        &LibraryFilter::new(&database, &self.res.get::<Profile>())?,
+                        &folder,
+                        redownload,
+                    )?
+                };
+                info!(
+                    "downloading box art for {} games in {}",
+                    paths.len(),
+                    folder.display()
+                );
+                if paths.is_empty() {
+                    let toast = self.res.get::<Locale>().t("box-art-scrape-nothing");
+                    self.toast = Some(Toast::new(toast, Some(Duration::from_secs(3))));
+                    return Ok(());
+                }
+                scraper::spawn_worker();
+                self.box_art = Some(BoxArtScrape::new(
+                    self.display.bounding_box().into(),
+                    self.res.clone(),
+                    paths,
+                ));
+            }
             Command::PopulateDb => {
                 let mut queue = VecDeque::with_capacity(10);
-                queue.push_back(Directory::new(ALLIUM_GAMES_DIR.clone()));
+                queue.push_back(Directory::new(self.res.get::<Profile>().games_dir()));
 
                 let database = self.res.get::<Database>();
                 let console_mapper = self.res.get::<ConsoleMapper>();
 
                 database.delete_all_unplayed_games()?;
+                // Names databases may have changed since titles were resolved
+                database.clear_game_titles()?;
 
                 let mut games = database.select_all_games()?;
                 for game in games.iter_mut() {
@@ -247,6 +966,7 @@ impl AlliumLauncher<DefaultPlatform> {
                 }
 
                 database.set_has_indexed(true)?;
+                art_index::spawn_indexer();
 
                 self.view.save()?;
                 self.view = App::load_or_new(
@@ -262,3 +982,39 @@ impl AlliumLauncher<DefaultPlatform> {
         Ok(())
     }
 }
+
+/// How long a notification is shown, longer for more severe ones.
+fn toast_duration(severity: Severity) -> Duration {
+    match severity {
+        Severity::Info => Duration::from_secs(3),
+        Severity::Warning => Duration::from_secs(5),
+        Severity::Error => Duration::from_secs(8),
+    }
+}
+
+/// Looks for ROM folders laid out for other firmware, unless the user has already been asked.
+fn detect_legacy_folders(console_mapper: &ConsoleMapper) -> Vec<LegacyFolder> {
+    match LegacyState::load() {
+        Ok(state) if state.handled => return Vec::new(),
+        Ok(_) => {}
+        Err(e) => warn!("failed to load legacy layout state: {}", e),
+    }
+
+    let folders = LegacyLayouts::load().and_then(|layouts| {
+        layouts.detect(&ALLIUM_GAMES_DIR, |name| {
+            console_mapper.get_console_by_dir(Path::new(name)).is_some()
+        })
+    });
+    match folders {
+        Ok(folders) => {
+            if !folders.is_empty() {
+                warn!("found legacy game folders: {:?}", folders);
+            }
+            folders
+        }
+        Err(e) => {
+            warn!("failed to detect legacy game folders: {}", e);
+            Vec::new()
+        }
+    }
+}
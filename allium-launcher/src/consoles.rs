@@ -1,16 +1,30 @@
+use std::fs;
+use std::iter;
 use std::path::PathBuf;
 use std::{collections::HashMap, path::Path};
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{anyhow, Context, Result};
+use common::archive;
 use common::command::Command;
 use common::database::Database;
+use common::fingerprint::Fingerprint;
 use common::game_info::GameInfo;
+use common::launch_failure::LaunchError;
+use common::legacy_layout::LegacyState;
+use common::rom_header;
+use common::save_state::SaveStates;
 use serde::Deserialize;
 
-use common::constants::{ALLIUM_CONFIG_CONSOLES, ALLIUM_RETROARCH};
-use log::{debug, trace};
+use common::constants::{
+    ALLIUM_CONFIG_CONSOLES, ALLIUM_CONFIG_CORES, ALLIUM_RETROARCH, RETROARCH_CORES_DIR,
+};
+use log::{debug, trace, warn};
 
+use crate::core_config::{core_file, CoreConfig, CoreConfigWarning};
+use crate::entry::folder_config::FolderConfig;
+use crate::entry::folder_view::FolderView;
 use crate::entry::game::Game;
+use crate::entry::game_index;
 
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
 pub struct Console {
@@ -34,6 +48,34 @@ pub struct Console {
     /// e.g. "Doukutsu.exe" for NXEngine
     #[serde(default)]
     pub file_name: Vec<String>,
+    /// System name on the libretro thumbnails server, used to scrape missing box art.
+    /// e.g. "Nintendo - Game Boy Advance"
+    #[serde(default)]
+    pub thumbnails: Option<String>,
+    /// Names database giving games of this console descriptive titles, relative to the config
+    /// folder. A `names.txt` in the games folder takes priority.
+    /// e.g. "names/mame2003-plus.txt"
+    #[serde(default)]
+    pub names: Option<PathBuf>,
+    /// Default view settings of folders of this console, unless the user changed them.
+    /// e.g. `{ full_names = true, box_art = false }` for arcade sets
+    #[serde(default)]
+    pub view: FolderView,
+}
+
+impl Console {
+    /// The files that games of the console are launched with: its program, or else its RetroArch
+    /// cores in `cores_dir` with the default first.
+    pub fn launchers(&self, cores_dir: &Path) -> Vec<PathBuf> {
+        match self.path {
+            Some(ref path) => vec![path.clone()],
+            None => self
+                .cores
+                .iter()
+                .map(|core| core_file(cores_dir, core))
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -46,6 +88,10 @@ struct ConsoleConfig {
 pub struct ConsoleMapper {
     cores: HashMap<String, String>,
     consoles: Vec<Console>,
+    /// Problems with the user's `cores.toml`.
+    warnings: Vec<CoreConfigWarning>,
+    /// Names of the consoles that the user's `cores.toml` changed or added.
+    overridden: Vec<String>,
 }
 
 impl Default for ConsoleMapper {
@@ -59,6 +105,8 @@ impl ConsoleMapper {
         ConsoleMapper {
             cores: HashMap::new(),
             consoles: Vec::new(),
+            warnings: Vec::new(),
+            overridden: Vec::new(),
         }
     }
 
@@ -70,15 +118,106 @@ impl ConsoleMapper {
                 e
             )
         })?;
-        let config: ConsoleConfig =
-            toml::from_str(&config).context("Failed to parse consoles.toml.")?;
+        self.parse_config(&config)?;
+        self.apply_user_config();
+
+        match LegacyState::load() {
+            Ok(state) => self.add_mappings(&state.mappings),
+            Err(e) => warn!("failed to load legacy folder mappings: {}", e),
+        }
 
+        Ok(())
+    }
+
+    /// Replaces the cores and consoles with those in `config`, the contents of `consoles.toml`.
+    pub fn parse_config(&mut self, config: &str) -> Result<()> {
+        let config: ConsoleConfig =
+            toml::from_str(config).context("Failed to parse consoles.toml.")?;
         self.cores = config.cores;
         self.consoles = config.consoles;
-
         Ok(())
     }
 
+    /// Applies the user's `cores.toml` over the consoles, if there is one. Anything wrong with it
+    /// is kept as a warning rather than failing, so that the defaults still work.
+    fn apply_user_config(&mut self) {
+        self.warnings = match CoreConfig::load(&ALLIUM_CONFIG_CORES) {
+            Ok(config) => self.apply_core_config(config, &RETROARCH_CORES_DIR),
+            Err(warning) => vec![warning],
+        };
+        for warning in &self.warnings {
+            warn!("{}", warning);
+        }
+    }
+
+    /// Applies `config` over the consoles, with RetroArch cores in `cores_dir`, and returns what
+    /// was wrong with it.
+    fn apply_core_config(
+        &mut self,
+        config: CoreConfig,
+        cores_dir: &Path,
+    ) -> Vec<CoreConfigWarning> {
+        let mut warnings = config.validate(cores_dir);
+        let changed: Vec<String> = config.consoles.iter().map(|c| c.name.clone()).collect();
+        warnings.extend(config.merge(&mut self.cores, &mut self.consoles));
+        // New consoles that couldn't be added aren't there to mark
+        self.overridden = changed
+            .into_iter()
+            .filter(|name| self.consoles.iter().any(|c| &c.name == name))
+            .collect();
+        warnings
+    }
+
+    /// Problems with the user's `cores.toml`, found when the config was loaded.
+    pub fn warnings(&self) -> &[CoreConfigWarning] {
+        &self.warnings
+    }
+
+    /// Every console, in the order that games are matched against them.
+    pub fn consoles(&self) -> &[Console] {
+        &self.consoles
+    }
+
+    /// Whether the user's `cores.toml` changed or added `console`.
+    pub fn is_overridden(&self, console: &Console) -> bool {
+        self.overridden.contains(&console.name)
+    }
+
+    /// Names of the consoles, with the folder each expects its games in: its first pattern.
+    pub fn console_folders(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.consoles.iter().filter_map(|console| {
+            console
+                .patterns
+                .first()
+                .map(|folder| (console.name.as_str(), folder.as_str()))
+        })
+    }
+
+    /// Adds legacy folder names as patterns of the console whose patterns include the Allium
+    /// folder name they map to, so games in those folders can be launched in place.
+    pub fn add_mappings<'a>(
+        &mut self,
+        mappings: impl IntoIterator<Item = (&'a String, &'a String)>,
+    ) {
+        for (legacy, target) in mappings {
+            match self
+                .consoles
+                .iter_mut()
+                .find(|console| console.patterns.contains(target))
+            {
+                Some(console) => {
+                    if !console.patterns.contains(legacy) {
+                        console.patterns.push(legacy.clone());
+                    }
+                }
+                None => warn!(
+                    "no console matches legacy folder mapping {} -> {}",
+                    legacy, target
+                ),
+            }
+        }
+    }
+
     /// Returns a console that matches the directory name exactly, or none.
     pub fn get_console_by_dir(&self, path: &Path) -> Option<&Console> {
         if let Some(name) = path.file_name().and_then(std::ffi::OsStr::to_str) {
@@ -139,47 +278,247 @@ impl ConsoleMapper {
         None
     }
 
-    pub fn launch_game(&self, database: &Database, game: &mut Game) -> Result<Option<Command>> {
+    /// Like `get_console`, but also looks inside zip archives that don't otherwise map to a
+    /// console, and maps them by the game inside. Other files that don't map to a console, or
+    /// whose extension more than one console takes, are mapped by their header if it's a known
+    /// one. What was found is cached in the database.
+    pub fn console_for_game(&self, database: &Database, path: &Path) -> Result<Option<&Console>> {
+        let console = self.get_console(path);
+        if !archive::is_zip(path) {
+            if console.is_none() || self.is_extension_shared(path) {
+                if let Some(console) = self.console_by_header(database, path)? {
+                    return Ok(Some(console));
+                }
+            }
+            return Ok(console);
+        }
+        if console.is_some() {
+            return Ok(console);
+        }
+
+        let size = fs::metadata(path)?.len();
+        let extension = match database.archive_extension(path, size)? {
+            Some(extension) => extension,
+            None => {
+                let extension = archive::rom_extension(path)?;
+                debug!("{} contains a {:?} game", path.display(), extension);
+                database.set_archive_extension(path, size, extension.as_deref())?;
+                extension
+            }
+        };
+
+        Ok(extension.and_then(|ext| {
+            self.consoles
+                .iter()
+                .find(|core| core.extensions.contains(&ext))
+        }))
+    }
+
+    /// Whether more than one console takes files with the extension of `path`.
+    fn is_extension_shared(&self, path: &Path) -> bool {
+        let Some(ext) = path
+            .extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .map(str::to_ascii_lowercase)
+        else {
+            return false;
+        };
+        self.consoles
+            .iter()
+            .filter(|console| console.extensions.contains(&ext))
+            .nth(1)
+            .is_some()
+    }
+
+    /// The console that the header of the file at `path` says it's for, if it's recognized.
+    fn console_by_header(&self, database: &Database, path: &Path) -> Result<Option<&Console>> {
+        let Some(modified) = fs::metadata(path)
+            .ok()
+            .filter(fs::Metadata::is_file)
+            .and_then(|metadata| game_index::modified(&metadata))
+        else {
+            return Ok(None);
+        };
+        let pattern = match database.header_console(path, modified)? {
+            Some(pattern) => pattern,
+            None => {
+                let pattern = rom_header::console_pattern(path)?.map(str::to_string);
+                debug!("{} has a {:?} header", path.display(), pattern);
+                database.set_header_console(path, modified, pattern.as_deref())?;
+                pattern
+            }
+        };
+
+        Ok(pattern.and_then(|pattern| {
+            self.consoles
+                .iter()
+                .find(|console| console.patterns.contains(&pattern))
+        }))
+    }
+
+    /// Command that launches `game`, recording that it was played. Fails with a [`LaunchError`]
+    /// if there's nothing to launch it with.
+    pub fn launch_game(&self, database: &Database, game: &mut Game) -> Result<Command> {
+        let mut game_info = self.game_info(database, game, &RETROARCH_CORES_DIR)?;
+
+        database.increment_play_count(
+            &game.name,
+            game.path.as_path(),
+            game_info.image.as_deref(),
+        )?;
+        // Lets the game be found again if it's moved
+        if database.fingerprint(&game.path)?.is_none() {
+            match Fingerprint::of(&game.path) {
+                Ok(fingerprint) => database.set_fingerprint(&game.path, fingerprint)?,
+                Err(e) => warn!("failed to fingerprint {}: {}", game.path.display(), e),
+            }
+        }
+
+        // The launcher execs the game, so the game keeps this process ID
+        game_info.pid = Some(std::process::id());
+        debug!("Saving game info: {:?}", game_info);
+        game_info.save()?;
+        Ok(Command::Exec(game_info.command()))
+    }
+
+    /// How `game` is launched, with RetroArch cores in `cores_dir`. Fails with a [`LaunchError`]
+    /// if there's nothing to launch it with, or what it would be launched with isn't installed.
+    fn game_info(
+        &self,
+        database: &Database,
+        game: &mut Game,
+        cores_dir: &Path,
+    ) -> Result<GameInfo> {
         if !game.path.exists() {
             if let Some(old) = Game::resync(&mut game.path)? {
                 database.update_game_path(&old, &game.path)?;
             }
         }
 
+        let Some(console) = self.console_for_game(database, game.path.as_path())? else {
+            return Err(LaunchError::NoConsole {
+                extension: game.extension.clone(),
+                folder: game
+                    .path
+                    .parent()
+                    .and_then(Path::file_name)
+                    .map(|folder| folder.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            }
+            .into());
+        };
+
         let image = game.image().map(Path::to_path_buf);
-        database.increment_play_count(&game.name, game.path.as_path(), image.as_deref())?;
-
-        let core = self.get_console(game.path.as_path());
-        Ok(if let Some(console) = core {
-            let game_info = if let Some(ref path) = console.path {
-                GameInfo::new(
-                    game.name.clone(),
-                    game.path.clone(),
-                    image,
-                    path.display().to_string(),
-                    vec![game.path.display().to_string()],
-                    false,
-                )
-            } else if let Some(retroarch_core) =
-                game.core.clone().or_else(|| console.cores.first().cloned())
-            {
-                GameInfo::new(
-                    game.name.clone(),
-                    game.path.clone(),
-                    image,
-                    ALLIUM_RETROARCH.display().to_string(),
-                    vec![retroarch_core, game.path.display().to_string()],
-                    true,
-                )
-            } else {
-                bail!("Console \"{}\" has no path or cores.", console.name);
-            };
-            debug!("Saving game info: {:?}", game_info);
-            game_info.save()?;
-            Some(Command::Exec(game_info.command()))
+        let folder = FolderConfig::for_game(&game.path)?.unwrap_or_default();
+        let game_info = if let Some(ref path) = console.path {
+            if !path.exists() {
+                return Err(LaunchError::CoreMissing {
+                    console: console.name.clone(),
+                    path: path.clone(),
+                }
+                .into());
+            }
+            let mut args = vec![game.path.display().to_string()];
+            args.extend(folder.args);
+            let mut game_info = GameInfo::new(
+                game.name.clone(),
+                game.path.clone(),
+                image.clone(),
+                path.display().to_string(),
+                args,
+                false,
+            );
+            // Scripts and ports expect to find their files next to them
+            game_info.working_dir = game.path.parent().map(Path::to_path_buf);
+            game_info
+        } else if let Some(retroarch_core) = self.core_for_game(database, game, console)? {
+            let file = core_file(cores_dir, &retroarch_core);
+            if !file.is_file() {
+                return Err(LaunchError::CoreMissing {
+                    console: console.name.clone(),
+                    path: file,
+                }
+                .into());
+            }
+            let mut args = vec![retroarch_core, game.path.display().to_string()];
+            args.extend(folder.args);
+            let mut game_info = GameInfo::new(
+                game.name.clone(),
+                game.path.clone(),
+                image.clone(),
+                ALLIUM_RETROARCH.display().to_string(),
+                args,
+                true,
+            );
+            game_info.state_slot = game.state_slot;
+            game_info
         } else {
-            None
-        })
+            return Err(LaunchError::NoCore {
+                console: console.name.clone(),
+            }
+            .into());
+        };
+
+        Ok(game_info)
+    }
+
+    /// RetroArch cores that can run the game: those of its console, then those of any other
+    /// console taking files with its extension. Empty for consoles that aren't run in RetroArch.
+    pub fn cores_for_game(&self, database: &Database, path: &Path) -> Result<Vec<String>> {
+        let Some(console) = self.console_for_game(database, path)? else {
+            return Ok(Vec::new());
+        };
+        if console.path.is_some() {
+            return Ok(Vec::new());
+        }
+
+        let extension = path
+            .extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+        let others = self.consoles.iter().filter(|other| {
+            other.path.is_none()
+                && extension
+                    .as_ref()
+                    .is_some_and(|ext| other.extensions.contains(ext))
+        });
+
+        let mut cores: Vec<String> = Vec::new();
+        for core in iter::once(console).chain(others).flat_map(|c| &c.cores) {
+            if !cores.contains(core) {
+                cores.push(core.clone());
+            }
+        }
+        Ok(cores)
+    }
+
+    /// The RetroArch core a game of `console` launches with: the core chosen in its options, then
+    /// the one last picked when launching it, then the one set for its folder in a
+    /// `.allium.toml`, then the console's default.
+    pub fn core_for_game(
+        &self,
+        database: &Database,
+        game: &Game,
+        console: &Console,
+    ) -> Result<Option<String>> {
+        if let Some(core) = database
+            .core_override(&game.path)?
+            .or_else(|| game.core.clone())
+        {
+            return Ok(Some(core));
+        }
+        Ok(FolderConfig::for_game(&game.path)?
+            .and_then(|folder| folder.core)
+            .or_else(|| console.cores.first().cloned()))
+    }
+
+    /// Save states of `game`, in the folder of the core it would be launched with.
+    pub fn save_states(&self, database: &Database, game: &Game) -> Result<SaveStates> {
+        let core = match self.console_for_game(database, &game.path)? {
+            Some(console) => self.core_for_game(database, game, console)?,
+            None => None,
+        };
+        Ok(SaveStates::for_game(&game.path, core.as_deref()))
     }
 
     pub fn get_core_name(&self, core: &str) -> String {
@@ -194,6 +533,10 @@ impl ConsoleMapper {
 mod tests {
     use std::env;
 
+    use common::archive::ArchiveError;
+    use common::legacy_layout::LegacyLayouts;
+    use common::test_utils::temp_dir;
+
     use super::*;
 
     #[test]
@@ -206,6 +549,9 @@ mod tests {
             cores: vec![],
             path: None,
             file_name: vec![],
+            thumbnails: None,
+            names: None,
+            view: FolderView::default(),
         }];
 
         assert!(mapper.get_console(Path::new("Roms/POKE/rom.zip")).is_some());
@@ -224,6 +570,284 @@ mod tests {
         assert!(mapper.get_console(Path::new("Roms/rom.gba")).is_none());
     }
 
+    #[test]
+    fn test_console_for_zipped_game() -> Result<()> {
+        let mut mapper = ConsoleMapper::new();
+        mapper.consoles = vec![Console {
+            name: "Test".to_string(),
+            patterns: vec!["GB".to_string()],
+            extensions: vec!["gb".to_string()],
+            cores: vec![],
+            path: None,
+            file_name: vec![],
+            thumbnails: None,
+            names: None,
+            view: FolderView::default(),
+        }];
+        let database = Database::in_memory()?;
+
+        let dir = temp_dir("zipped-game");
+        let path = dir.join("Game.zip");
+        fs::write(&path, b"not a zip")?;
+
+        // Looked into before, so the archive isn't opened again
+        database.set_archive_extension(&path, 9, Some("gb"))?;
+        assert!(mapper.console_for_game(&database, &path)?.is_some());
+        database.set_archive_extension(&path, 9, Some("gba"))?;
+        assert!(mapper.console_for_game(&database, &path)?.is_none());
+
+        // Changed since, so it is
+        fs::write(&path, b"still not a zip")?;
+        let e = mapper.console_for_game(&database, &path).unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<ArchiveError>(),
+            Some(&ArchiveError::Corrupt)
+        );
+
+        // Consoles found without looking inside don't need the archive
+        assert!(mapper
+            .console_for_game(&database, Path::new("Roms/GB/Missing.zip"))?
+            .is_some());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_console_by_header() -> Result<()> {
+        let console = |name: &str, extensions: &[&str]| Console {
+            name: name.to_string(),
+            patterns: vec![name.to_string()],
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            cores: vec![],
+            path: None,
+            file_name: vec![],
+            thumbnails: None,
+            names: None,
+            view: FolderView::default(),
+        };
+        let mut mapper = ConsoleMapper::new();
+        mapper.consoles = vec![
+            console("PS", &[]),
+            console("NES", &["nes", "rom"]),
+            console("MSX", &["rom"]),
+        ];
+        let database = Database::in_memory()?;
+
+        let dir = temp_dir("game-header");
+        let ps = dir.join("PS");
+        fs::create_dir_all(&ps)?;
+        let mut disc = vec![0; 0x8020];
+        disc[0x8008..0x8013].copy_from_slice(b"PLAYSTATION");
+        fs::write(dir.join("Game.bin"), &disc)?;
+        fs::write(dir.join("Game.rom"), b"NES\x1a")?;
+        fs::write(dir.join("Other.rom"), b"AB")?;
+        fs::write(ps.join("Game.bin"), b"")?;
+
+        let name = |path: &Path| -> Result<Option<String>> {
+            Ok(mapper
+                .console_for_game(&database, path)?
+                .map(|console| console.name.clone()))
+        };
+        // Outside of its folder
+        assert_eq!(name(&dir.join("Game.bin"))?, Some("PS".to_string()));
+        // Taken by more than one console
+        assert_eq!(name(&dir.join("Game.rom"))?, Some("NES".to_string()));
+        assert_eq!(name(&dir.join("Other.rom"))?, Some("NES".to_string()));
+        // The folder says, so the header isn't read
+        assert_eq!(name(&ps.join("Game.bin"))?, Some("PS".to_string()));
+        let modified = game_index::modified(&fs::metadata(ps.join("Game.bin"))?).unwrap();
+        assert_eq!(
+            database.header_console(&ps.join("Game.bin"), modified)?,
+            None
+        );
+
+        // Read once, then cached until the file changes
+        let modified = game_index::modified(&fs::metadata(dir.join("Game.bin"))?).unwrap();
+        assert_eq!(
+            database.header_console(&dir.join("Game.bin"), modified)?,
+            Some(Some("PS".to_string()))
+        );
+        database.set_header_console(&dir.join("Game.bin"), modified, None)?;
+        assert_eq!(name(&dir.join("Game.bin"))?, None);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_core_for_game() -> Result<()> {
+        let console = |name: &str, extension: &str, cores: &[&str]| Console {
+            name: name.to_string(),
+            patterns: vec![name.to_string()],
+            extensions: vec![extension.to_string()],
+            cores: cores.iter().map(|c| c.to_string()).collect(),
+            path: None,
+            file_name: vec![],
+            thumbnails: None,
+            names: None,
+            view: FolderView::default(),
+        };
+        let mut mapper = ConsoleMapper::new();
+        mapper.consoles = vec![
+            console("GBA", "gba", &["gpsp", "mgba"]),
+            console("GBA-HACKS", "gba", &["mgba", "vbam"]),
+        ];
+        let database = Database::in_memory()?;
+
+        let path = Path::new("Roms/GBA/Game.gba");
+        assert_eq!(
+            mapper.cores_for_game(&database, path)?,
+            ["gpsp", "mgba", "vbam"]
+        );
+
+        let mut game = Game::new(path.to_path_buf());
+        let gba = mapper.get_console(path).unwrap();
+        assert_eq!(
+            mapper.core_for_game(&database, &game, gba)?.as_deref(),
+            Some("gpsp")
+        );
+
+        // The core last launched with is remembered
+        game.core = Some("mgba".to_string());
+        assert_eq!(
+            mapper.core_for_game(&database, &game, gba)?.as_deref(),
+            Some("mgba")
+        );
+
+        // The core chosen in the game's options takes priority
+        database.set_core_override(path, Some("vbam"))?;
+        assert_eq!(
+            mapper.core_for_game(&database, &game, gba)?.as_deref(),
+            Some("vbam")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_core_for_game_in_configured_folder() -> Result<()> {
+        let mut mapper = ConsoleMapper::new();
+        mapper.consoles = vec![Console {
+            name: "SNES".to_string(),
+            patterns: vec!["SFC".to_string()],
+            extensions: vec!["sfc".to_string()],
+            cores: vec!["mednafen_supafaust".to_string(), "snes9x".to_string()],
+            path: None,
+            file_name: vec![],
+            thumbnails: None,
+            names: None,
+            view: FolderView::default(),
+        }];
+        let database = Database::in_memory()?;
+
+        let dir = temp_dir("folder-core");
+        let msu1 = dir.join("SNES-MSU1");
+        fs::create_dir_all(&msu1)?;
+        fs::write(
+            msu1.join(crate::entry::folder_config::FOLDER_CONFIG),
+            "core = \"snes9x\"",
+        )?;
+
+        let mut game = Game::new(msu1.join("Game.sfc"));
+        let snes = mapper.get_console(&game.path).unwrap();
+        assert_eq!(
+            mapper.core_for_game(&database, &game, snes)?.as_deref(),
+            Some("snes9x")
+        );
+
+        // Cores chosen for the game itself take priority over the folder's
+        game.core = Some("mednafen_supafaust".to_string());
+        assert_eq!(
+            mapper.core_for_game(&database, &game, snes)?.as_deref(),
+            Some("mednafen_supafaust")
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_launch_errors() -> Result<()> {
+        let dir = temp_dir("launch-errors");
+        let cores_dir = dir.join("cores");
+        fs::create_dir_all(&cores_dir)?;
+
+        let console = |name: &str, cores: &[&str], path: Option<PathBuf>| Console {
+            name: name.to_string(),
+            patterns: vec![name.to_string()],
+            extensions: vec![name.to_ascii_lowercase()],
+            cores: cores.iter().map(|c| c.to_string()).collect(),
+            path,
+            file_name: vec![],
+            thumbnails: None,
+            names: None,
+            view: FolderView::default(),
+        };
+        let mut mapper = ConsoleMapper::new();
+        mapper.consoles = vec![
+            console("GB", &["gambatte"], None),
+            console("PORTS", &[], Some(dir.join("run.sh"))),
+            console("NONE", &[], None),
+        ];
+        let database = Database::in_memory()?;
+
+        let launch = |path: &str| -> Result<GameInfo> {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(&path, "")?;
+            mapper.game_info(&database, &mut Game::new(path), &cores_dir)
+        };
+        let error = |path: &str| -> Result<LaunchError> {
+            match launch(path) {
+                Ok(game_info) => panic!("{} launched with {:?}", path, game_info),
+                Err(e) => e.downcast::<LaunchError>(),
+            }
+        };
+
+        assert_eq!(
+            error("Misc/Game.xyz")?,
+            LaunchError::NoConsole {
+                extension: "xyz".to_string(),
+                folder: "Misc".to_string(),
+            }
+        );
+        assert_eq!(
+            error("NONE/Game.none")?,
+            LaunchError::NoCore {
+                console: "NONE".to_string(),
+            }
+        );
+        assert_eq!(
+            error("GB/Game.gb")?,
+            LaunchError::CoreMissing {
+                console: "GB".to_string(),
+                path: cores_dir.join("gambatte_libretro.so"),
+            }
+        );
+        assert_eq!(
+            error("PORTS/Game.ports")?,
+            LaunchError::CoreMissing {
+                console: "PORTS".to_string(),
+                path: dir.join("run.sh"),
+            }
+        );
+
+        // Launches once the core or program is installed
+        fs::write(cores_dir.join("gambatte_libretro.so"), "")?;
+        let game_info = launch("GB/Game.gb")?;
+        assert_eq!(game_info.command, ALLIUM_RETROARCH.display().to_string());
+        assert_eq!(game_info.args[0], "gambatte");
+
+        fs::write(dir.join("run.sh"), "")?;
+        let game_info = launch("PORTS/Game.ports")?;
+        assert_eq!(game_info.command, dir.join("run.sh").display().to_string());
+        assert_eq!(game_info.working_dir, Some(dir.join("PORTS")));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
     #[test]
     fn test_config() {
         env::set_var("ALLIUM_BASE_DIR", "../assets/root/.allium");
@@ -295,6 +919,68 @@ mod tests {
         // NXEngine
         assert!(eq("Cave Story/Doukutsu.exe", "Cave Story", "nxengine"));
         assert!(eq("Cave Story (NXENGINE).m3u", "Cave Story", "nxengine"));
+
+        // Shell scripts run on their own, even among ports
+        for rom in ["doom.sh", "Ports/doom.sh", "PORTS/Doom/doom.sh"] {
+            let console = mapper.get_console(Path::new(rom)).unwrap();
+            assert_eq!(console.path.as_deref(), Some(Path::new("/bin/sh")));
+        }
+    }
+
+    #[test]
+    fn test_overridden_consoles() -> Result<()> {
+        let mut mapper = ConsoleMapper::new();
+        mapper.parse_config(
+            r#"
+            [cores]
+            gambatte = "Gambatte"
+            mgba = "mGBA"
+
+            [[consoles]]
+            name = "Game Boy"
+            cores = ["gambatte"]
+            patterns = ["GB"]
+            extensions = ["gb"]
+
+            [[consoles]]
+            name = "Ports"
+            path = "/mnt/SDCARD/Roms/PORTS/run.sh"
+            patterns = ["PORTS"]
+            "#,
+        )?;
+        let config = CoreConfig::parse(
+            r#"
+            [[consoles]]
+            name = "Game Boy"
+            cores = ["mgba", "gambatte"]
+
+            [[consoles]]
+            name = "Nothing"
+            "#,
+        )
+        .map_err(|e| anyhow!("{}", e))?;
+        let cores_dir = Path::new("/mnt/SDCARD/RetroArch/.retroarch/cores");
+        mapper.apply_core_config(config, cores_dir);
+
+        let [game_boy, ports] = mapper.consoles() else {
+            panic!("expected two consoles: {:?}", mapper.consoles());
+        };
+        assert!(mapper.is_overridden(game_boy));
+        assert!(!mapper.is_overridden(ports));
+        assert_eq!(mapper.overridden, ["Game Boy"]);
+
+        assert_eq!(
+            game_boy.launchers(cores_dir),
+            [
+                cores_dir.join("mgba_libretro.so"),
+                cores_dir.join("gambatte_libretro.so")
+            ]
+        );
+        assert_eq!(
+            ports.launchers(cores_dir),
+            [Path::new("/mnt/SDCARD/Roms/PORTS/run.sh")]
+        );
+        Ok(())
     }
 
     #[test]
@@ -311,4 +997,38 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_legacy_mappings() {
+        env::set_var("ALLIUM_BASE_DIR", "../assets/root/.allium");
+
+        let mut mapper = ConsoleMapper::new();
+        mapper.load_config().unwrap();
+
+        let layouts = LegacyLayouts::load().unwrap();
+        for layout in layouts.layouts {
+            for (legacy, target) in layout.folders.iter() {
+                assert!(
+                    mapper.get_console_by_dir(Path::new(target)).is_some(),
+                    "No console found for {} -> {}",
+                    legacy,
+                    target
+                );
+            }
+            mapper.add_mappings(&layout.folders);
+        }
+
+        assert_eq!(
+            mapper
+                .get_console(Path::new("megadrive/rom.zip"))
+                .map(|c| c.name.as_str()),
+            Some("Genesis")
+        );
+        assert_eq!(
+            mapper
+                .get_console(Path::new("psx/rom.zip"))
+                .map(|c| c.name.as_str()),
+            Some("PlayStation")
+        );
+    }
 }
@@ -0,0 +1,403 @@
+//! The user's changes to which consoles and cores games launch with, kept in `config/cores.toml`
+//! and applied over `consoles.toml` when the launcher starts. Entries of the user's file win:
+//!
+//! ```toml
+//! [cores]
+//! # Names that cores are shown by
+//! snes9x = "Snes9x"
+//!
+//! # Changes the cores of a console, and takes `.sfc2` files and `SNES-HACKS` folders from any
+//! # other console
+//! [[consoles]]
+//! name = "SNES"
+//! cores = ["snes9x", "mednafen_supafaust"]
+//! extensions = ["sfc2"]
+//! patterns = ["SNES-HACKS"]
+//!
+//! # Adds a console, as no console is named this
+//! [[consoles]]
+//! name = "Pokemon Mini"
+//! cores = ["pokemini"]
+//! patterns = ["POKE"]
+//! extensions = ["min"]
+//! ```
+//!
+//! Problems with the file are collected as warnings, which are shown in settings. A file that
+//! can't be read leaves the defaults as they are.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::locale::Locale;
+use serde::Deserialize;
+
+use crate::consoles::Console;
+use crate::entry::folder_view::FolderView;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoreConfig {
+    /// Names of cores, added to or replacing those of the defaults.
+    pub cores: HashMap<String, String>,
+    pub consoles: Vec<ConsoleChange>,
+}
+
+/// Changes to the console with this name, or a new console if there is none.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConsoleChange {
+    pub name: String,
+    /// Replaces the console's RetroArch cores. The first is the default.
+    pub cores: Option<Vec<String>>,
+    /// Replaces the program that runs the console's games.
+    pub path: Option<PathBuf>,
+    /// Folder names added to the console's, which no other console matches anymore.
+    pub patterns: Vec<String>,
+    /// Extensions added to the console's, which no other console matches anymore.
+    pub extensions: Vec<String>,
+}
+
+/// Something wrong with the user's core config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoreConfigWarning {
+    /// The file couldn't be read, so none of it applies.
+    Invalid(String),
+    /// A new console has no way to launch its games, or to tell which games are its.
+    Incomplete { console: String },
+    /// A core isn't installed.
+    MissingCore { console: String, core: String },
+    /// A program isn't there.
+    MissingProgram { console: String, path: PathBuf },
+}
+
+impl CoreConfigWarning {
+    pub fn text(&self, locale: &Locale) -> String {
+        match self {
+            CoreConfigWarning::Invalid(error) => locale.ta(
+                "core-config-invalid",
+                &[("error".to_string(), error.clone().into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            CoreConfigWarning::Incomplete { console } => locale.ta(
+                "core-config-incomplete",
+                &[("console".to_string(), console.clone().into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            CoreConfigWarning::MissingCore { console, core } => locale.ta(
+                "core-config-missing-core",
+                &[
+                    ("console".to_string(), console.clone().into()),
+                    ("core".to_string(), core.clone().into()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            CoreConfigWarning::MissingProgram { console, path } => locale.ta(
+                "core-config-missing-program",
+                &[
+                    ("console".to_string(), console.clone().into()),
+                    ("path".to_string(), path.display().to_string().into()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for CoreConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoreConfigWarning::Invalid(error) => write!(f, "invalid core config: {}", error),
+            CoreConfigWarning::Incomplete { console } => {
+                write!(
+                    f,
+                    "console {} has no cores, patterns or extensions",
+                    console
+                )
+            }
+            CoreConfigWarning::MissingCore { console, core } => {
+                write!(f, "core {} of console {} is not installed", core, console)
+            }
+            CoreConfigWarning::MissingProgram { console, path } => {
+                write!(
+                    f,
+                    "{} of console {} does not exist",
+                    path.display(),
+                    console
+                )
+            }
+        }
+    }
+}
+
+impl CoreConfig {
+    /// Reads the user's config at `path`. A missing file is an empty config.
+    pub fn load(path: &Path) -> Result<Self, CoreConfigWarning> {
+        match fs::read_to_string(path) {
+            Ok(config) => Self::parse(&config),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(CoreConfigWarning::Invalid(e.to_string())),
+        }
+    }
+
+    pub fn parse(config: &str) -> Result<Self, CoreConfigWarning> {
+        toml::from_str(config).map_err(|e| CoreConfigWarning::Invalid(e.message().to_string()))
+    }
+
+    /// Applies the config over the default `cores` and `consoles`. Changes that can't apply are
+    /// skipped, and returned as warnings.
+    pub fn merge(
+        self,
+        cores: &mut HashMap<String, String>,
+        consoles: &mut Vec<Console>,
+    ) -> Vec<CoreConfigWarning> {
+        let mut warnings = Vec::new();
+        cores.extend(self.cores);
+
+        for change in self.consoles {
+            let index = match consoles.iter().position(|c| c.name == change.name) {
+                Some(index) => index,
+                None => {
+                    let launches = change.path.is_some()
+                        || change.cores.as_ref().is_some_and(|c| !c.is_empty());
+                    let matches = !change.patterns.is_empty() || !change.extensions.is_empty();
+                    if !launches || !matches {
+                        warnings.push(CoreConfigWarning::Incomplete {
+                            console: change.name,
+                        });
+                        continue;
+                    }
+                    consoles.push(Console {
+                        name: change.name.clone(),
+                        path: None,
+                        cores: Vec::new(),
+                        patterns: Vec::new(),
+                        extensions: Vec::new(),
+                        file_name: Vec::new(),
+                        thumbnails: None,
+                        names: None,
+                        view: FolderView::default(),
+                    });
+                    consoles.len() - 1
+                }
+            };
+
+            // Folders and extensions the user gave a console are no other's
+            let extensions: Vec<String> = change
+                .extensions
+                .iter()
+                .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+                .collect();
+            for (i, other) in consoles.iter_mut().enumerate() {
+                if i != index {
+                    other.patterns.retain(|p| !change.patterns.contains(p));
+                    other.extensions.retain(|e| !extensions.contains(e));
+                }
+            }
+
+            let console = &mut consoles[index];
+            if let Some(cores) = change.cores {
+                console.cores = cores;
+            }
+            if let Some(path) = change.path {
+                console.path = Some(path);
+            }
+            for pattern in change.patterns {
+                if !console.patterns.contains(&pattern) {
+                    console.patterns.push(pattern);
+                }
+            }
+            for extension in extensions {
+                if !console.extensions.contains(&extension) {
+                    console.extensions.push(extension);
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Checks that the cores and programs that the config refers to are installed, with
+    /// RetroArch cores in `cores_dir`.
+    pub fn validate(&self, cores_dir: &Path) -> Vec<CoreConfigWarning> {
+        let mut warnings = Vec::new();
+        for change in &self.consoles {
+            for core in change.cores.iter().flatten() {
+                if !core_file(cores_dir, core).is_file() {
+                    warnings.push(CoreConfigWarning::MissingCore {
+                        console: change.name.clone(),
+                        core: core.clone(),
+                    });
+                }
+            }
+            if let Some(path) = change.path.as_ref().filter(|path| !path.exists()) {
+                warnings.push(CoreConfigWarning::MissingProgram {
+                    console: change.name.clone(),
+                    path: path.clone(),
+                });
+            }
+        }
+        warnings
+    }
+}
+
+/// The file of the RetroArch core named `core`, in `cores_dir`.
+pub fn core_file(cores_dir: &Path, core: &str) -> PathBuf {
+    cores_dir.join(format!("{}_libretro.so", core))
+}
+
+#[cfg(test)]
+mod tests {
+    use common::test_utils::temp_dir;
+
+    use super::*;
+
+    fn console(name: &str, patterns: &[&str], extensions: &[&str], cores: &[&str]) -> Console {
+        Console {
+            name: name.to_string(),
+            path: None,
+            cores: cores.iter().map(|s| s.to_string()).collect(),
+            patterns: patterns.iter().map(|s| s.to_string()).collect(),
+            extensions: extensions.iter().map(|s| s.to_string()).collect(),
+            file_name: vec![],
+            thumbnails: None,
+            names: None,
+            view: FolderView::default(),
+        }
+    }
+
+    fn defaults() -> (HashMap<String, String>, Vec<Console>) {
+        (
+            HashMap::from([("gpsp".to_string(), "gpSP".to_string())]),
+            vec![
+                console("Game Boy Advance", &["GBA"], &["gba"], &["gpsp", "mgba"]),
+                console("SNES", &["SFC", "SNES"], &["sfc", "smc"], &["snes9x"]),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_merge_changes_existing_console() {
+        let (mut cores, mut consoles) = defaults();
+        let config = CoreConfig::parse(
+            r#"
+            [cores]
+            gpsp = "gpSP (fast)"
+            mgba = "mGBA"
+
+            [[consoles]]
+            name = "Game Boy Advance"
+            cores = ["mgba"]
+            extensions = [".AGB"]
+            patterns = ["SNES"]
+            "#,
+        )
+        .unwrap();
+
+        let warnings = config.merge(&mut cores, &mut consoles);
+        assert!(warnings.is_empty());
+
+        assert_eq!(cores["gpsp"], "gpSP (fast)");
+        assert_eq!(cores["mgba"], "mGBA");
+        assert_eq!(consoles[0].cores, ["mgba"]);
+        assert_eq!(consoles[0].extensions, ["gba", "agb"]);
+        // The user's mapping wins over the default one
+        assert_eq!(consoles[0].patterns, ["GBA", "SNES"]);
+        assert_eq!(consoles[1].patterns, ["SFC"]);
+        // Untouched settings are kept
+        assert_eq!(consoles[1].cores, ["snes9x"]);
+    }
+
+    #[test]
+    fn test_merge_adds_console() {
+        let (mut cores, mut consoles) = defaults();
+        let config = CoreConfig::parse(
+            r#"
+            [[consoles]]
+            name = "Pokemon Mini"
+            cores = ["pokemini"]
+            patterns = ["POKE"]
+            extensions = ["min", "sfc"]
+
+            [[consoles]]
+            name = "Nothing"
+            patterns = ["NOTHING"]
+            "#,
+        )
+        .unwrap();
+
+        let warnings = config.merge(&mut cores, &mut consoles);
+        assert_eq!(
+            warnings,
+            [CoreConfigWarning::Incomplete {
+                console: "Nothing".to_string()
+            }]
+        );
+
+        assert_eq!(consoles.len(), 3);
+        assert_eq!(consoles[2].name, "Pokemon Mini");
+        assert_eq!(consoles[2].cores, ["pokemini"]);
+        assert_eq!(consoles[2].extensions, ["min", "sfc"]);
+        assert_eq!(consoles[1].extensions, ["smc"]);
+    }
+
+    #[test]
+    fn test_invalid_config_keeps_defaults() {
+        for config in [
+            "[[consoles]]\nname = \"SNES\"\ncores = \"snes9x\"",
+            "[[consoles]]\nname = \"SNES\"\ncore = [\"snes9x\"]",
+            "consoles = 1",
+            "not toml",
+        ] {
+            let warning = CoreConfig::parse(config).unwrap_err();
+            assert!(matches!(warning, CoreConfigWarning::Invalid(_)));
+        }
+
+        let missing = temp_dir("core-config-missing").join("cores.toml");
+        assert_eq!(CoreConfig::load(&missing), Ok(CoreConfig::default()));
+
+        let (mut cores, mut consoles) = defaults();
+        let warnings = CoreConfig::default().merge(&mut cores, &mut consoles);
+        assert!(warnings.is_empty());
+        assert_eq!((cores, consoles), defaults());
+    }
+
+    #[test]
+    fn test_validate() {
+        let dir = temp_dir("core-config");
+        fs::write(dir.join("mgba_libretro.so"), "").unwrap();
+
+        let config = CoreConfig::parse(
+            r#"
+            [[consoles]]
+            name = "Game Boy Advance"
+            cores = ["mgba", "vbam"]
+
+            [[consoles]]
+            name = "PICO-8"
+            path = "/nonexistent/pico8.sh"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.validate(&dir),
+            [
+                CoreConfigWarning::MissingCore {
+                    console: "Game Boy Advance".to_string(),
+                    core: "vbam".to_string(),
+                },
+                CoreConfigWarning::MissingProgram {
+                    console: "PICO-8".to_string(),
+                    path: PathBuf::from("/nonexistent/pico8.sh"),
+                },
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,6 +2,7 @@ use std::{fs::File, path::PathBuf};
 
 use anyhow::Result;
 use common::command::Command;
+use common::filename_rules;
 use serde::{Deserialize, Serialize};
 
 /// Corresponds to the config.json file, compatible with stock/OnionOS.
@@ -54,7 +55,8 @@ impl App {
 
 impl Ord for App {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.name.cmp(&other.name)
+        filename_rules::compare_names(&self.name, &other.name)
+            .then_with(|| self.directory.cmp(&other.directory))
     }
 }
 
@@ -0,0 +1,367 @@
+//! Index of the box art in `Imgs` folders.
+//!
+//! Some scrapers fill `Imgs` with art for a whole romset, including games that aren't there, so
+//! probing for every candidate image of every entry gets slow. Instead, a background indexer
+//! reads each `Imgs` folder once and image lookups are answered from the file names in memory.
+//! Folders are only read again when their modification time changes.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
+use std::thread;
+use std::time::SystemTime;
+
+use anyhow::Result;
+use common::constants::ALLIUM_GAMES_DIR;
+use lazy_static::lazy_static;
+use log::{debug, error};
+
+pub const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "gif"];
+
+lazy_static! {
+    static ref ART_INDEX: RwLock<ArtIndex> = RwLock::new(ArtIndex::new());
+}
+
+static IS_INDEXER_RUNNING: AtomicBool = AtomicBool::new(false);
+static IS_INDEX_STALE: AtomicBool = AtomicBool::new(false);
+
+/// The shared index.
+pub fn read() -> RwLockReadGuard<'static, ArtIndex> {
+    ART_INDEX.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Records art that was just saved, e.g. by the scraper.
+pub fn add_art(path: &Path) {
+    ART_INDEX
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(path);
+}
+
+/// Brings the shared index up to date with the games folder in the background. If the indexer is
+/// already running, it goes over the games folder again once it's done.
+pub fn spawn_indexer() {
+    IS_INDEX_STALE.store(true, Ordering::SeqCst);
+    if IS_INDEXER_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    thread::spawn(|| {
+        while IS_INDEX_STALE.swap(false, Ordering::SeqCst) {
+            // Index a copy so that lookups aren't blocked while folders are read
+            let mut index = read().clone();
+            match index.index(&ALLIUM_GAMES_DIR) {
+                Ok(()) => {
+                    debug!("indexed {} art folders", index.dirs.len());
+                    *ART_INDEX.write().unwrap_or_else(PoisonError::into_inner) = index;
+                }
+                Err(e) => error!("failed to index box art: {}", e),
+            }
+        }
+        IS_INDEXER_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ArtIndex {
+    /// Folders that have been indexed. Art folders below them that aren't in `dirs` don't exist.
+    roots: Vec<PathBuf>,
+    dirs: HashMap<PathBuf, ArtDir>,
+}
+
+#[derive(Debug, Clone)]
+struct ArtDir {
+    modified: Option<SystemTime>,
+    files: HashSet<OsString>,
+    subdirs: Vec<OsString>,
+}
+
+impl ArtIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indexes every `Imgs` folder below `root`. Art folders that haven't changed since they were
+    /// last indexed aren't read again.
+    pub fn index(&mut self, root: &Path) -> Result<()> {
+        let mut found = HashSet::new();
+        let mut queue = VecDeque::from([root.to_path_buf()]);
+        while let Some(dir) = queue.pop_front() {
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                if !entry.file_type()?.is_dir() {
+                    continue;
+                }
+                let name = entry.file_name();
+                if name == "Imgs" {
+                    self.index_art_dir(entry.path(), &mut found)?;
+                } else if name != "Guides" {
+                    queue.push_back(entry.path());
+                }
+            }
+        }
+
+        self.dirs
+            .retain(|dir, _| !dir.starts_with(root) || found.contains(dir));
+        if !self.covers(root) {
+            self.roots.push(root.to_path_buf());
+        }
+        Ok(())
+    }
+
+    /// Reads the art folder `dir` and its subfolders, unless they are unchanged.
+    fn index_art_dir(&mut self, dir: PathBuf, found: &mut HashSet<PathBuf>) -> Result<()> {
+        let mut queue = vec![dir];
+        while let Some(dir) = queue.pop() {
+            let modified = fs::metadata(&dir)?.modified().ok();
+            match self.dirs.get(&dir) {
+                Some(art) if modified.is_some() && art.modified == modified => {
+                    queue.extend(art.subdirs.iter().map(|name| dir.join(name)));
+                }
+                _ => {
+                    let mut files = HashSet::new();
+                    let mut subdirs = Vec::new();
+                    for entry in fs::read_dir(&dir)? {
+                        let entry = entry?;
+                        if entry.file_type()?.is_dir() {
+                            subdirs.push(entry.file_name());
+                        } else {
+                            files.insert(entry.file_name());
+                        }
+                    }
+                    queue.extend(subdirs.iter().map(|name| dir.join(name)));
+                    self.dirs.insert(
+                        dir.clone(),
+                        ArtDir {
+                            modified,
+                            files,
+                            subdirs,
+                        },
+                    );
+                }
+            }
+            found.insert(dir);
+        }
+        Ok(())
+    }
+
+    /// Whether lookups in `dir` can be answered by the index.
+    pub fn covers(&self, dir: &Path) -> bool {
+        self.roots.iter().any(|root| dir.starts_with(root))
+    }
+
+    /// Finds the art for `name` in the art folder `dir`, trying each image extension in place of
+    /// the extension of `name`. `name` may be in a subfolder of `dir`.
+    pub fn find(&self, dir: &Path, name: &Path) -> Option<PathBuf> {
+        let dir = match name.parent() {
+            Some(parent) => dir.join(parent),
+            None => dir.to_path_buf(),
+        };
+        let art = self.dirs.get(&dir)?;
+        let file_name = Path::new(name.file_name()?);
+        IMAGE_EXTENSIONS
+            .iter()
+            .map(|ext| file_name.with_extension(ext))
+            .find(|file| art.files.contains(file.as_os_str()))
+            .map(|file| dir.join(file))
+    }
+
+    /// Records art that was saved after its folder was indexed.
+    pub fn insert(&mut self, path: &Path) {
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return;
+        };
+        self.dirs
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| ArtDir {
+                // Read it again on the next index, in case other files were added too
+                modified: None,
+                files: HashSet::new(),
+                subdirs: Vec::new(),
+            })
+            .files
+            .insert(name.to_os_string());
+    }
+
+    /// Art in indexed folders that doesn't belong to any of `games`, or to the folders they are
+    /// in.
+    pub fn orphans<'a>(&self, games: impl IntoIterator<Item = &'a Path>) -> Vec<PathBuf> {
+        // Art paths without extensions that a lookup would find, mirroring `LazyImage::image`
+        let mut wanted = HashSet::new();
+        for game in games {
+            let mut entry = game;
+            while self.covers(entry) {
+                let mut parent = entry;
+                while let Some(dir) = parent.parent() {
+                    parent = dir;
+                    if !self.covers(parent) {
+                        break;
+                    }
+                    let art_dir = parent.join("Imgs");
+                    if let Some(name) = entry.file_name() {
+                        wanted.insert(art_dir.join(name).with_extension(""));
+                    }
+                    if let Ok(relative) = entry.strip_prefix(parent) {
+                        wanted.insert(art_dir.join(relative).with_extension(""));
+                    }
+                }
+                match entry.parent() {
+                    Some(dir) => entry = dir,
+                    None => break,
+                }
+            }
+        }
+
+        let mut orphans: Vec<_> = self
+            .dirs
+            .iter()
+            .flat_map(|(dir, art)| art.files.iter().map(move |file| dir.join(file)))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext))
+            })
+            .filter(|path| !wanted.contains(&path.with_extension("")))
+            .collect();
+        orphans.sort_unstable();
+        orphans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use common::test_utils::temp_dir;
+
+    use super::*;
+    use crate::entry::lazy_image::LazyImage;
+
+    const ART_COUNT: usize = 5000;
+
+    /// A console folder with a few games and art for a whole romset.
+    fn romset(root: &Path) -> PathBuf {
+        let console = root.join("GB");
+        fs::create_dir_all(console.join("Imgs/Hacks")).unwrap();
+        fs::create_dir_all(console.join("Hacks")).unwrap();
+        for i in 0..ART_COUNT {
+            fs::write(console.join(format!("Imgs/Game {}.png", i)), []).unwrap();
+        }
+        fs::write(console.join("Game 1.gb"), []).unwrap();
+        fs::write(console.join("Game 2.gb"), []).unwrap();
+        fs::write(console.join("Hacks/Hack 1.gb"), []).unwrap();
+        fs::write(console.join("Imgs/Hacks/Hack 1.jpg"), []).unwrap();
+        fs::write(console.join("Imgs/Hacks.png"), []).unwrap();
+        console
+    }
+
+    #[test]
+    fn test_find() -> Result<()> {
+        let root = temp_dir("art-find");
+        let console = romset(&root);
+
+        let mut index = ArtIndex::new();
+        assert!(!index.covers(&console));
+        index.index(&root)?;
+        assert!(index.covers(&console.join("Imgs")));
+
+        let art = console.join("Imgs");
+        assert_eq!(
+            index.find(&art, Path::new("Game 1.gb")),
+            Some(art.join("Game 1.png"))
+        );
+        assert_eq!(
+            index.find(&art, Path::new("Hacks/Hack 1.gb")),
+            Some(art.join("Hacks/Hack 1.jpg"))
+        );
+        assert_eq!(index.find(&art, Path::new("Missing.gb")), None);
+        assert_eq!(
+            index.find(&root.join("GBA/Imgs"), Path::new("Game 1.gb")),
+            None
+        );
+
+        // Changed folders are read again
+        fs::write(art.join("New.gif"), [])?;
+        fs::remove_dir_all(art.join("Hacks"))?;
+        index.index(&root)?;
+        assert_eq!(
+            index.find(&art, Path::new("New.gb")),
+            Some(art.join("New.gif"))
+        );
+        assert_eq!(index.find(&art, Path::new("Hacks/Hack 1.gb")), None);
+
+        // Saved art is found without indexing again
+        index.insert(&root.join("GBA/Imgs/Game.png"));
+        assert_eq!(
+            index.find(&root.join("GBA/Imgs"), Path::new("Game.gba")),
+            Some(root.join("GBA/Imgs/Game.png"))
+        );
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookups_do_not_scale_with_art() -> Result<()> {
+        let root = temp_dir("art-scale");
+        let console = romset(&root);
+        let mut index = ArtIndex::new();
+        index.index(&root)?;
+
+        // Lookups never touch the filesystem once indexed
+        fs::remove_dir_all(console.join("Imgs"))?;
+
+        let start = Instant::now();
+        for i in 0..1000 {
+            let game = console.join(format!("Game {}.gb", i));
+            let mut image = LazyImage::Unknown(game.clone());
+            assert_eq!(
+                image.find_with(&index, &root),
+                Some(console.join(format!("Imgs/Game {}.png", i)).as_path())
+            );
+        }
+        let mut image = LazyImage::Unknown(console.join("Hacks/Hack 1.gb"));
+        assert_eq!(
+            image.find_with(&index, &root),
+            Some(console.join("Imgs/Hacks/Hack 1.jpg").as_path())
+        );
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed.as_millis() < 500,
+            "resolving 1000 images took {:?}",
+            elapsed
+        );
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_orphans() -> Result<()> {
+        let root = temp_dir("art-orphans");
+        let console = romset(&root);
+        fs::write(console.join("Imgs/notes.txt"), [])?;
+        let mut index = ArtIndex::new();
+        index.index(&root)?;
+
+        let games = [
+            console.join("Game 1.gb"),
+            console.join("Game 2.gb"),
+            console.join("Hacks/Hack 1.gb"),
+        ];
+        let orphans = index.orphans(games.iter().map(PathBuf::as_path));
+
+        // Art of the games and of the Hacks folder is kept, anything else is orphaned
+        assert_eq!(orphans.len(), ART_COUNT - 2);
+        assert!(!orphans.contains(&console.join("Imgs/Game 1.png")));
+        assert!(!orphans.contains(&console.join("Imgs/Hacks.png")));
+        assert!(!orphans.contains(&console.join("Imgs/Hacks/Hack 1.jpg")));
+        assert!(orphans.contains(&console.join("Imgs/Game 0.png")));
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+//! Lists of games put together by the user, which may span consoles. They are listed in a
+//! Collections folder at the top of the games folder, which doesn't exist on the SD card. Each
+//! collection lists its games in the order they were added, including games whose files are gone,
+//! so that they can be taken out of it.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use common::database::Database;
+use serde::{Deserialize, Serialize};
+
+use crate::entry::directory::Directory;
+use crate::entry::game::Game;
+use crate::entry::{names, Entry};
+
+/// Name of the Collections folder in the games folder.
+pub const COLLECTIONS_DIR: &str = "Collections";
+
+/// What a directory that doesn't exist on the SD card lists.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Listing {
+    /// The user's collections.
+    Collections,
+    /// The games in the collection with this ID.
+    Collection(i64),
+    /// The games in the RetroArch playlist with the directory's file name.
+    RetroArchPlaylist,
+}
+
+/// The Collections folder in `games_dir`, if there are any collections.
+pub fn folder(games_dir: &Path, database: &Database) -> Result<Option<Directory>> {
+    if database.collections()?.is_empty() {
+        return Ok(None);
+    }
+    let mut dir = Directory::new(games_dir.join(COLLECTIONS_DIR));
+    dir.listing = Some(Listing::Collections);
+    Ok(Some(dir))
+}
+
+/// The collections, as directories in the Collections folder at `path`.
+pub fn collections(path: &Path, database: &Database) -> Result<Vec<Entry>> {
+    Ok(database
+        .collections()?
+        .into_iter()
+        .map(|collection| {
+            // Named by ID, so that any name can be given and renaming keeps the path
+            let mut dir = Directory::with_name(
+                path.join(collection.id.to_string()),
+                collection.name.clone(),
+            );
+            dir.full_name = collection.name;
+            dir.listing = Some(Listing::Collection(collection.id));
+            Entry::Directory(dir)
+        })
+        .collect())
+}
+
+/// The games in the collection with ID `id`, in the order they were added.
+pub fn games(id: i64, database: &Database) -> Result<Vec<Entry>> {
+    let paths = database.collection_games(id)?;
+    let games = database.select_games(&paths.iter().map(PathBuf::as_path).collect::<Vec<_>>())?;
+    let mut entries: Vec<_> = paths
+        .into_iter()
+        .zip(games)
+        .map(|(path, game)| {
+            Entry::Game(match game {
+                Some(game) => game.into(),
+                // Added before the games were indexed, or since gone
+                None => Game::new(path),
+            })
+        })
+        .collect();
+    names::apply_renames(&mut entries, database)?;
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use common::database::NewGame;
+
+    use super::*;
+
+    #[test]
+    fn test_collection_entries() -> Result<()> {
+        let database = Database::in_memory()?;
+        let games_dir = PathBuf::from("/mnt/SDCARD/Roms");
+        assert_eq!(folder(&games_dir, &database)?, None);
+
+        let collection = database.create_collection("Couch co-op")?;
+        let folder = folder(&games_dir, &database)?.unwrap();
+        assert_eq!(folder.path, games_dir.join(COLLECTIONS_DIR));
+        assert_eq!(folder.listing, Some(Listing::Collections));
+
+        let entries = collections(&folder.path, &database)?;
+        let [Entry::Directory(dir)] = entries.as_slice() else {
+            panic!("expected one collection: {entries:?}");
+        };
+        assert_eq!(dir.name, "Couch co-op");
+        assert_eq!(dir.listing, Some(Listing::Collection(collection.id)));
+
+        let indexed = games_dir.join("SFC/Bomberman (USA).sfc");
+        let missing = games_dir.join("GBA/Mario Kart (USA).gba");
+        database.update_games(&[NewGame {
+            name: "Super Bomberman".to_string(),
+            path: indexed.clone(),
+            image: None,
+            core: None,
+        }])?;
+        database.add_to_collection(collection.id, &missing)?;
+        database.add_to_collection(collection.id, &indexed)?;
+        let names: Vec<_> = games(collection.id, &database)?
+            .iter()
+            .map(|e| e.name().to_string())
+            .collect();
+        assert_eq!(names, ["Mario Kart", "Super Bomberman"]);
+
+        Ok(())
+    }
+}
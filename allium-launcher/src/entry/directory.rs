@@ -1,18 +1,28 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     ffi::OsStr,
-    fs::File,
+    fs,
     path::{Path, PathBuf},
+    sync::mpsc::{self, TryRecvError},
 };
 
 use anyhow::{anyhow, Result};
-use common::{constants::ALLIUM_GAMES_DIR, database::Database};
-use log::error;
+use common::{constants::ALLIUM_GAMES_DIR, database::Database, filename_rules, playlist};
+use log::warn;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     consoles::ConsoleMapper,
-    entry::{game::Game, gamelist::GameList, lazy_image::LazyImage, short_name, Entry},
+    entry::{
+        collection::{self, Listing},
+        game::Game,
+        game_index,
+        gamelist::{self, GameList},
+        lazy_image::LazyImage,
+        names, retroarch_playlist, short_name, Entry,
+    },
+    launcher_config,
+    library_filter::LibraryFilter,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -23,11 +33,15 @@ pub struct Directory {
     /// image is loaded lazily.
     /// None means image hasn't been looked for, Some(None) means no image was found, Some(Some(path)) means an image was found.
     pub image: LazyImage,
+    /// What the directory lists if it doesn't exist on the SD card, such as a collection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listing: Option<Listing>,
 }
 
 impl Ord for Directory {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.full_name.cmp(&other.full_name)
+        filename_rules::compare_names(&self.full_name, &other.full_name)
+            .then_with(|| self.path.cmp(&other.path))
     }
 }
 
@@ -44,6 +58,7 @@ impl Default for Directory {
             full_name: "Games".to_string(),
             path: ALLIUM_GAMES_DIR.to_owned(),
             image: LazyImage::Unknown(ALLIUM_GAMES_DIR.to_owned()),
+            listing: None,
         }
     }
 }
@@ -62,6 +77,7 @@ impl Directory {
             full_name,
             path,
             image,
+            listing: None,
         }
     }
 
@@ -77,6 +93,7 @@ impl Directory {
             full_name,
             path,
             image,
+            listing: None,
         }
     }
 
@@ -84,12 +101,15 @@ impl Directory {
         self.image.image()
     }
 
-    fn parse_game_list(&self, game_list: &Path) -> Result<Vec<Entry>> {
-        let file = File::open(game_list)?;
-        let gamelist: GameList = serde_xml_rs::from_reader(file)?;
+    /// Entries curated by the game list at `game_list`, for the games and folders it lists that
+    /// exist.
+    fn parse_game_list(&self, game_list: &Path) -> Vec<Entry> {
+        let Some(gamelist) = GameList::load_cached(game_list) else {
+            return Vec::new();
+        };
 
-        let games = gamelist.games.into_iter().filter_map(|game| {
-            let path = self.path.join(&game.path);
+        let games = gamelist.games.iter().filter_map(|game| {
+            let path = gamelist::resolve(&self.path, &game.path);
             if !path.exists() {
                 return None;
             }
@@ -103,41 +123,49 @@ impl Directory {
 
             let full_name = game.name.clone();
 
-            let image = game.image.or(game.thumbnail);
-            let image = match image {
-                Some(image) => {
-                    let path = self.path.join(image);
-                    if path.exists() {
-                        LazyImage::Found(path)
-                    } else {
-                        LazyImage::Unknown(path)
-                    }
-                }
-                None => LazyImage::Unknown(path.clone()),
-            };
+            // Falls back to looking for the game's art as usual
+            let image = game
+                .image
+                .as_ref()
+                .or(game.thumbnail.as_ref())
+                .map(|image| gamelist::resolve(&self.path, image))
+                .filter(|image| image.exists());
+            let image = LazyImage::from_path(&path, image);
 
             Some(Entry::Game(Game {
                 path,
-                name: game.name,
+                name: game.name.clone(),
                 full_name,
                 image,
                 extension,
                 core: None,
+                manufacturer: None,
+                year: None,
+                play_time: None,
+                pinned: false,
             }))
         });
 
-        let folders = gamelist.folders.into_iter().filter_map(|folder| {
-            let path = self.path.join(&folder.path);
-            if !path.exists() {
+        let folders = gamelist.folders.iter().filter_map(|folder| {
+            let path = gamelist::resolve(&self.path, &folder.path);
+            if !path.is_dir() {
                 return None;
             }
 
-            let name = folder.name;
-
-            Some(Entry::Directory(Directory::with_name(path, name)))
+            let mut dir = Directory::with_name(path, folder.name.clone());
+            if let Some(image) = folder
+                .image
+                .as_ref()
+                .or(folder.thumbnail.as_ref())
+                .map(|image| gamelist::resolve(&self.path, image))
+                .filter(|image| image.exists())
+            {
+                dir.image = LazyImage::Found(image);
+            }
+            Some(Entry::Directory(dir))
         });
 
-        Ok(folders.chain(games).collect())
+        folders.chain(games).collect()
     }
 
     pub fn entries(
@@ -145,37 +173,58 @@ impl Directory {
         database: &Database,
         console_mapper: &ConsoleMapper,
     ) -> Result<Vec<Entry>> {
-        let mut entries = vec![];
-
-        let gamelist = self.path.join("gamelist.xml");
-        if gamelist.exists() {
-            match self.parse_game_list(&gamelist) {
-                Ok(res) => entries.extend(res),
-                Err(e) => error!("Failed to parse gamelist.xml: {}", e),
+        match self.listing {
+            Some(Listing::Collections) => return collection::collections(&self.path, database),
+            Some(Listing::Collection(id)) => return collection::games(id, database),
+            Some(Listing::RetroArchPlaylist) => {
+                return retroarch_playlist::games(
+                    &self.path,
+                    &launcher_config::playlists_dir(),
+                    database,
+                )
             }
+            None => {}
         }
 
-        let gamelist = self.path.join("miyoogamelist.xml");
-        if gamelist.exists() {
-            match self.parse_game_list(&gamelist) {
-                Ok(res) => entries.extend(res),
-                Err(e) => error!("Failed to parse gamelist.xml: {}", e),
-            }
+        let mut entries = vec![];
+
+        for name in ["gamelist.xml", "miyoogamelist.xml"] {
+            entries.extend(self.parse_game_list(&self.path.join(name)));
         }
+        let curated: HashSet<PathBuf> = entries.iter().map(|e| e.path().to_path_buf()).collect();
 
-        entries.extend(
-            std::fs::read_dir(&self.path)
-                .map_err(|e| anyhow!("Failed to open directory: {:?}, {}", &self.path, e))?
-                .filter_map(std::result::Result::ok)
-                .filter_map(|entry| match Entry::new(entry.path(), console_mapper) {
-                    Ok(Some(entry)) => Some(entry),
-                    _ => None,
-                }),
-        );
+        match game_index::indexed_files(&self.path, database) {
+            Some(files) => entries.extend(
+                files
+                    .into_iter()
+                    .filter_map(|file| Entry::from_indexed(file, console_mapper).ok().flatten()),
+            ),
+            None => entries.extend(
+                std::fs::read_dir(&self.path)
+                    .map_err(|e| anyhow!("Failed to open directory: {:?}, {}", &self.path, e))?
+                    .filter_map(std::result::Result::ok)
+                    .filter_map(|entry| match Entry::new(entry.path(), console_mapper) {
+                        Ok(Some(entry)) => Some(entry),
+                        _ => None,
+                    }),
+            ),
+        }
 
         let mut uniques = HashSet::new();
         entries.retain(|e| uniques.insert(e.path().to_path_buf()));
 
+        // Discs of multi-disc games are listed once, through their playlist
+        let discs: HashSet<PathBuf> = entries
+            .iter()
+            .filter(|e| matches!(e, Entry::Game(_)) && playlist::is_playlist(e.path()))
+            .filter_map(|e| playlist::discs(e.path()).ok())
+            .flatten()
+            .collect();
+        entries.retain(|e| !discs.contains(e.path()));
+
+        names::apply(&self.path, &mut entries, database, console_mapper, &curated)?;
+        names::apply_renames(&mut entries, database)?;
+
         for entry in entries.iter_mut() {
             if let Entry::Game(game) = entry {
                 if let Some(core) = database.get_core(&game.path)? {
@@ -220,6 +269,143 @@ impl Directory {
 
         Ok(())
     }
+
+    /// The games in this directory and the directories in it that `filter` doesn't hide, leaving
+    /// out files that no console launches.
+    pub fn games(
+        &self,
+        database: &Database,
+        console_mapper: &ConsoleMapper,
+        filter: &LibraryFilter,
+    ) -> Result<Vec<Game>> {
+        let mut games = Vec::new();
+        let mut queue = VecDeque::from([self.clone()]);
+        while let Some(dir) = queue.pop_front() {
+            let mut entries = dir.entries(database, console_mapper)?;
+            filter.retain(&mut entries);
+            for entry in entries {
+                match entry {
+                    Entry::Directory(dir) => queue.push_back(dir),
+                    Entry::Game(game) if console_mapper.get_console(&game.path).is_some() => {
+                        games.push(game)
+                    }
+                    Entry::Game(_) | Entry::App(_) => {}
+                }
+            }
+        }
+        Ok(games)
+    }
+}
+
+/// How many games are in the directory at `path`, including the directories in it. Discs of
+/// multi-disc games are counted once, through their playlist.
+pub fn count_games(path: &Path, console_mapper: &ConsoleMapper) -> usize {
+    let Ok(dir) = fs::read_dir(path) else {
+        return 0;
+    };
+    let mut count = 0;
+    let mut games = HashSet::new();
+    let mut discs = HashSet::new();
+    for entry in dir.filter_map(std::result::Result::ok) {
+        match Entry::new(entry.path(), console_mapper) {
+            Ok(Some(Entry::Game(game))) => {
+                if playlist::is_playlist(&game.path) {
+                    discs.extend(playlist::discs(&game.path).unwrap_or_default());
+                }
+                games.insert(game.path);
+            }
+            Ok(Some(Entry::Directory(dir))) => count += count_games(&dir.path, console_mapper),
+            _ => {}
+        }
+    }
+    count + games.difference(&discs).count()
+}
+
+/// Game counts of directories, for showing next to them. Counting a big folder takes a while, so
+/// counts are worked out on a blocking task, and cached in the database until the directory is
+/// modified.
+#[derive(Debug, Default)]
+pub struct GameCounts {
+    counts: HashMap<PathBuf, usize>,
+    /// Counts from the blocking task, with when the directory was modified.
+    pending: Option<mpsc::Receiver<(PathBuf, i64, usize)>>,
+}
+
+impl GameCounts {
+    /// Looks up the counts of `dirs`, and starts counting the ones that aren't cached. Counting
+    /// started before is given up on.
+    pub fn start<'a>(
+        &mut self,
+        dirs: impl Iterator<Item = &'a Path>,
+        database: &Database,
+        console_mapper: &ConsoleMapper,
+    ) {
+        self.pending = None;
+        let mut uncounted = Vec::new();
+        for dir in dirs {
+            let Some(modified) = modified(dir) else {
+                continue;
+            };
+            match database.directory_count(dir, modified) {
+                Ok(Some(count)) => {
+                    self.counts.insert(dir.to_path_buf(), count);
+                }
+                Ok(None) => uncounted.push((dir.to_path_buf(), modified)),
+                Err(e) => warn!("failed to look up game count of {}: {}", dir.display(), e),
+            }
+        }
+        if uncounted.is_empty() {
+            return;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let console_mapper = console_mapper.clone();
+        tokio::task::spawn_blocking(move || {
+            for (dir, modified) in uncounted {
+                let count = count_games(&dir, &console_mapper);
+                // Nobody is waiting for the rest any more
+                if tx.send((dir, modified, count)).is_err() {
+                    return;
+                }
+            }
+        });
+        self.pending = Some(rx);
+    }
+
+    /// Takes the counts that were worked out since the last poll, caching them in the database.
+    /// Returns whether there were any.
+    pub fn poll(&mut self, database: &Database) -> bool {
+        let Some(rx) = self.pending.as_ref() else {
+            return false;
+        };
+        let mut counted = false;
+        loop {
+            match rx.try_recv() {
+                Ok((dir, modified, count)) => {
+                    if let Err(e) = database.set_directory_count(&dir, modified, count) {
+                        warn!("failed to cache game count of {}: {}", dir.display(), e);
+                    }
+                    self.counts.insert(dir, count);
+                    counted = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.pending = None;
+                    break;
+                }
+            }
+        }
+        counted
+    }
+
+    pub fn get(&self, dir: &Path) -> Option<usize> {
+        self.counts.get(dir).copied()
+    }
+}
+
+/// When the directory at `path` was modified, in nanoseconds since the epoch.
+fn modified(path: &Path) -> Option<i64> {
+    game_index::modified(&fs::metadata(path).ok()?)
 }
 
 impl From<&Path> for Directory {
@@ -227,3 +413,261 @@ impl From<&Path> for Directory {
         Directory::new(path.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::time::{Duration, SystemTime};
+
+    use common::test_utils::temp_dir;
+
+    use super::*;
+    use crate::launcher_config::LauncherConfig;
+
+    fn names(dir: &Path) -> Result<Vec<String>> {
+        let database = Database::in_memory()?;
+        let mut names: Vec<String> = Directory::new(dir.to_path_buf())
+            .entries(&database, &ConsoleMapper::new())?
+            .iter()
+            .map(|e| e.path().file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    #[test]
+    fn test_excluded_files() -> Result<()> {
+        let dir = temp_dir("directory-excluded");
+        for name in [
+            "Tetris.gb",
+            "Tetris.gb.state1",
+            "Tetris.srm",
+            "cache.db",
+            ".hidden.gb",
+            "gamelist.xml",
+            "scph1001.bin",
+            names::NAMES_FILE,
+        ] {
+            File::create(dir.join(name))?;
+        }
+        fs::create_dir(dir.join("Imgs"))?;
+        fs::create_dir(dir.join("Hacks"))?;
+        fs::create_dir(dir.join("BIOS"))?;
+        assert_eq!(names(&dir)?, ["Hacks", "Tetris.gb", "Tetris.gb.state1"]);
+
+        // Other tests don't list save states, so excluding them too leaves those alone
+        let mut config = LauncherConfig::default();
+        config.exclude.files.push("*.state*".to_string());
+        config.apply();
+        assert_eq!(names(&dir)?, ["Hacks", "Tetris.gb"]);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_from_index() -> Result<()> {
+        let dir = temp_dir("directory-index");
+        fs::create_dir(dir.join("Hacks"))?;
+        File::create(dir.join("Tetris.gb"))?;
+        let database = Database::in_memory()?;
+        let console_mapper = ConsoleMapper::new();
+        let names = |database: &Database| -> Result<Vec<String>> {
+            let mut names: Vec<_> = Directory::new(dir.clone())
+                .entries(database, &console_mapper)?
+                .into_iter()
+                .map(|e| match e {
+                    Entry::Directory(dir) => format!("{}/", dir.name),
+                    e => e.name().to_string(),
+                })
+                .collect();
+            names.sort();
+            Ok(names)
+        };
+
+        game_index::index(&dir, &database, false, |_, _| {})?;
+        assert_eq!(names(&database)?, ["Hacks/", "Tetris"]);
+
+        // Listed from the index, without reading the folder again
+        let modified = modified(&dir).unwrap();
+        let mut files = database.indexed_files(&dir, modified)?.unwrap();
+        let mut indexed = files[1].clone();
+        indexed.name = "Indexed.gb".to_string();
+        indexed.path = dir.join(&indexed.name);
+        files.push(indexed);
+        database.index_directory(&dir, modified, &files)?;
+        assert_eq!(names(&database)?, ["Hacks/", "Indexed", "Tetris"]);
+
+        // Read from the SD card once it has changed since
+        File::create(dir.join("Zelda.gb"))?;
+        assert_eq!(names(&database)?, ["Hacks/", "Tetris", "Zelda"]);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_playlist_hides_its_discs() -> Result<()> {
+        let dir = temp_dir("directory-playlist");
+        fs::write(dir.join("Game (Disc 1).cue"), "")?;
+        fs::write(dir.join("Game (Disc 2).cue"), "")?;
+        fs::write(dir.join("Other.cue"), "")?;
+        fs::write(
+            dir.join("Game (USA).m3u"),
+            "Game (Disc 1).cue\nGame (Disc 2).cue\n",
+        )?;
+
+        assert_eq!(names(&dir)?, vec!["Game (USA).m3u", "Other.cue"]);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_games() -> Result<()> {
+        let dir = temp_dir("directory-count");
+        fs::create_dir_all(dir.join("GBA/Hacks"))?;
+        fs::create_dir_all(dir.join("GBA/Imgs"))?;
+        fs::create_dir_all(dir.join("PS"))?;
+        fs::write(dir.join("GBA/One.gba"), "")?;
+        fs::write(dir.join("GBA/Two.gba"), "")?;
+        fs::write(dir.join("GBA/Hacks/Three.gba"), "")?;
+        fs::write(dir.join("GBA/Imgs/One.png"), "")?;
+        fs::write(dir.join("GBA/.hidden.gba"), "")?;
+        fs::write(dir.join("PS/Game (Disc 1).cue"), "")?;
+        fs::write(dir.join("PS/Game (Disc 2).cue"), "")?;
+        fs::write(
+            dir.join("PS/Game.m3u"),
+            "Game (Disc 1).cue\nGame (Disc 2).cue\n",
+        )?;
+
+        let console_mapper = ConsoleMapper::new();
+        assert_eq!(count_games(&dir.join("GBA"), &console_mapper), 3);
+        assert_eq!(count_games(&dir.join("PS"), &console_mapper), 1);
+        assert_eq!(count_games(&dir, &console_mapper), 4);
+        assert_eq!(count_games(&dir.join("Missing"), &console_mapper), 0);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_game_counts_are_cached() -> Result<()> {
+        let dir = temp_dir("directory-counts");
+        fs::create_dir_all(dir.join("GBA"))?;
+        fs::write(dir.join("GBA/One.gba"), "")?;
+        let gba = dir.join("GBA");
+        let database = Database::in_memory()?;
+        let console_mapper = ConsoleMapper::new();
+
+        let mut counts = GameCounts::default();
+        counts.start([gba.as_path()].into_iter(), &database, &console_mapper);
+        assert_eq!(counts.get(&gba), None);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !counts.poll(&database) {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "games weren't counted"
+            );
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert_eq!(counts.get(&gba), Some(1));
+
+        // Cached until the directory changes
+        let mut cached = GameCounts::default();
+        cached.start([gba.as_path()].into_iter(), &database, &console_mapper);
+        assert_eq!(cached.get(&gba), Some(1));
+        assert!(cached.pending.is_none());
+
+        fs::write(gba.join("Two.gba"), "")?;
+        let modified = modified(&gba).unwrap();
+        assert_eq!(database.directory_count(&gba, modified)?, None);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    fn games(dir: &Path) -> Result<Vec<Game>> {
+        let database = Database::in_memory()?;
+        let mut games: Vec<Game> = Directory::new(dir.to_path_buf())
+            .entries(&database, &ConsoleMapper::new())?
+            .into_iter()
+            .filter_map(|e| match e {
+                Entry::Game(game) => Some(game),
+                _ => None,
+            })
+            .collect();
+        games.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        Ok(games)
+    }
+
+    #[test]
+    fn test_gamelist() -> Result<()> {
+        let dir = temp_dir("directory-gamelist");
+        fs::create_dir_all(dir.join("media"))?;
+        fs::write(dir.join("Game.gba"), "")?;
+        fs::write(dir.join("Other (USA).gba"), "")?;
+        fs::write(dir.join("media/box.png"), "")?;
+        let gamelist = dir.join("gamelist.xml");
+        fs::write(
+            &gamelist,
+            r#"<gameList>
+                <game>
+                    <path>./Game.gba</path>
+                    <name>Curated Game</name>
+                    <image>./media/box.png</image>
+                </game>
+                <game>
+                    <path>./Missing.gba</path>
+                    <name>Missing</name>
+                </game>
+            </gameList>"#,
+        )?;
+        let modified = SystemTime::now() - Duration::from_secs(60);
+        File::options()
+            .write(true)
+            .open(&gamelist)?
+            .set_modified(modified)?;
+
+        let listed = games(&dir)?;
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].name, "Curated Game");
+        assert_eq!(listed[0].path, dir.join("Game.gba"));
+        assert_eq!(listed[0].image, LazyImage::Found(dir.join("media/box.png")));
+        assert_eq!(listed[1].name, "Other");
+
+        // Read again only once the file is modified
+        fs::write(&gamelist, "<gameList><game>")?;
+        File::options()
+            .write(true)
+            .open(&gamelist)?
+            .set_modified(modified)?;
+        assert_eq!(games(&dir)?[0].name, "Curated Game");
+
+        File::options()
+            .write(true)
+            .open(&gamelist)?
+            .set_modified(SystemTime::now())?;
+        let listed = games(&dir)?;
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].name, "Game");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_playlist_shows_discs() -> Result<()> {
+        let dir = temp_dir("directory-malformed-playlist");
+        fs::write(dir.join("Game (Disc 1).cue"), "")?;
+        fs::write(
+            dir.join("Game.m3u"),
+            "Game (Disc 1).cue\nGame (Disc 2).cue\n",
+        )?;
+
+        assert_eq!(names(&dir)?, vec!["Game (Disc 1).cue"]);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
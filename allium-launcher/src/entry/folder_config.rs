@@ -0,0 +1,141 @@
+//! Launch settings for all games in a folder, from a `.allium.toml` file in it. e.g. for an
+//! `SNES-MSU1` folder whose games need another core than the rest of the console's:
+//!
+//! ```toml
+//! core = "snes9x"
+//! args = ["--verbose"]
+//! ```
+//!
+//! The nearest file up from a game applies to it, though a core chosen for the game itself takes
+//! priority. Files are read whenever they're needed, so changes to them apply without restarting
+//! the launcher.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use common::constants::ALLIUM_GAMES_DIR;
+use serde::Deserialize;
+
+/// Name of the file in a folder that its settings are read from.
+pub const FOLDER_CONFIG: &str = ".allium.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FolderConfig {
+    /// RetroArch core that games in the folder launch with, instead of the console's default.
+    pub core: Option<String>,
+    /// Extra arguments passed when launching games in the folder, after the game.
+    pub args: Vec<String>,
+}
+
+/// A folder's settings file that couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderConfigError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for FolderConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl std::error::Error for FolderConfigError {}
+
+impl FolderConfig {
+    /// Reads the settings file at `path`. Fails with a [`FolderConfigError`] if it can't be read.
+    pub fn load(path: &Path) -> Result<Self> {
+        let error = |message: String| FolderConfigError {
+            path: path.to_path_buf(),
+            message,
+        };
+        let config = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        Ok(toml::from_str(&config).map_err(|e| error(e.message().to_string()))?)
+    }
+
+    /// The settings that apply to the folder `dir`: those of the nearest settings file in it or
+    /// a folder up from it, within the games folder.
+    pub fn for_dir(dir: &Path) -> Result<Option<Self>> {
+        for dir in dir.ancestors() {
+            let path = dir.join(FOLDER_CONFIG);
+            if path.is_file() {
+                return Self::load(&path).map(Some);
+            }
+            if dir == ALLIUM_GAMES_DIR.as_path() {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    /// The settings that apply to the game at `path`, from the folder it's in.
+    pub fn for_game(path: &Path) -> Result<Option<Self>> {
+        match path.parent() {
+            Some(dir) => Self::for_dir(dir),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::test_utils::temp_dir;
+
+    use super::*;
+
+    #[test]
+    fn test_nearest_config_applies() -> Result<()> {
+        let dir = temp_dir("folder-config");
+        let msu1 = dir.join("SNES-MSU1");
+        let nested = msu1.join("Zelda");
+        fs::create_dir_all(&nested)?;
+
+        assert_eq!(FolderConfig::for_game(&msu1.join("Game.sfc"))?, None);
+
+        fs::write(dir.join(FOLDER_CONFIG), "core = \"snes9x\"")?;
+        fs::write(
+            msu1.join(FOLDER_CONFIG),
+            "core = \"snes9x2010\"\nargs = [\"--verbose\"]",
+        )?;
+        let expected = FolderConfig {
+            core: Some("snes9x2010".to_string()),
+            args: vec!["--verbose".to_string()],
+        };
+        assert_eq!(
+            FolderConfig::for_game(&msu1.join("Game.sfc"))?,
+            Some(expected.clone())
+        );
+        assert_eq!(
+            FolderConfig::for_game(&nested.join("Game.sfc"))?,
+            Some(expected)
+        );
+
+        // Changes apply the next time it's read
+        fs::write(msu1.join(FOLDER_CONFIG), "args = []")?;
+        assert_eq!(
+            FolderConfig::for_game(&msu1.join("Game.sfc"))?,
+            Some(FolderConfig::default())
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() -> Result<()> {
+        let dir = temp_dir("folder-config-bad");
+
+        for config in ["core = snes9x", "cores = \"snes9x\"", "core = 1"] {
+            fs::write(dir.join(FOLDER_CONFIG), config)?;
+            let e = FolderConfig::for_game(&dir.join("Game.sfc")).unwrap_err();
+            let e = e.downcast_ref::<FolderConfigError>().unwrap();
+            assert_eq!(e.path, dir.join(FOLDER_CONFIG));
+        }
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+//! How a games folder is listed: whether box art is shown, whether games are listed by their
+//! full file names, and whether they are shown as a grid of box art instead of a list.
+//!
+//! Each setting comes from, in order of precedence:
+//!
+//! 1. the user's choice for the folder, kept in `state/folder-views.json`,
+//! 2. the default of the console the folder belongs to, set with `view` in `consoles.toml`, e.g.
+//!    `view = { full_names = true, box_art = false }` for arcade sets,
+//! 3. the global default, from the theme for box art.
+//!
+//! A folder belongs to the console of the nearest folder up from it that maps to one, so that
+//! subfolders of a console folder share its defaults. The user's choices only apply to the folder
+//! they were made in.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use common::constants::ALLIUM_FOLDER_VIEWS;
+use common::locale::Locale;
+use common::stylesheet::StyleConfig;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::consoles::ConsoleMapper;
+
+/// View settings of a folder. Settings that are `None` are inherited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FolderView {
+    /// Whether box art is shown next to the list.
+    pub box_art: Option<bool>,
+    /// Whether games are listed by their full name, including tags such as the region, instead
+    /// of a shortened one.
+    pub full_names: Option<bool>,
+    /// Whether games are shown as a grid of box art instead of a list.
+    pub grid: Option<bool>,
+}
+
+impl FolderView {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Where a setting's value comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Global,
+    Console,
+    Folder,
+}
+
+impl Source {
+    pub fn text(&self, locale: &Locale) -> String {
+        match self {
+            Source::Global => locale.t("folder-view-source-global"),
+            Source::Console => locale.t("folder-view-source-console"),
+            Source::Folder => locale.t("folder-view-source-folder"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setting {
+    pub value: bool,
+    pub source: Source,
+}
+
+impl Setting {
+    fn resolve(global: bool, console: Option<bool>, folder: Option<bool>) -> Self {
+        match (folder, console) {
+            (Some(value), _) => Self {
+                value,
+                source: Source::Folder,
+            },
+            (None, Some(value)) => Self {
+                value,
+                source: Source::Console,
+            },
+            (None, None) => Self {
+                value: global,
+                source: Source::Global,
+            },
+        }
+    }
+}
+
+/// View settings that apply to a folder, and where each comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolvedView {
+    pub box_art: Setting,
+    pub full_names: Setting,
+    pub grid: Setting,
+}
+
+impl ResolvedView {
+    /// The global defaults, for lists that aren't a games folder.
+    pub fn global(styles: &StyleConfig) -> Self {
+        Self::resolve(styles, None, None)
+    }
+
+    /// Applies the folder's settings over the console's, and those over the global defaults.
+    pub fn resolve(
+        styles: &StyleConfig,
+        console: Option<&FolderView>,
+        folder: Option<&FolderView>,
+    ) -> Self {
+        Self {
+            box_art: Setting::resolve(
+                styles.enable_box_art,
+                console.and_then(|v| v.box_art),
+                folder.and_then(|v| v.box_art),
+            ),
+            full_names: Setting::resolve(
+                false,
+                console.and_then(|v| v.full_names),
+                folder.and_then(|v| v.full_names),
+            ),
+            grid: Setting::resolve(
+                false,
+                console.and_then(|v| v.grid),
+                folder.and_then(|v| v.grid),
+            ),
+        }
+    }
+}
+
+/// The user's view settings for individual folders.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FolderViews {
+    folders: HashMap<PathBuf, FolderView>,
+}
+
+impl FolderViews {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load() -> Result<Self> {
+        if ALLIUM_FOLDER_VIEWS.exists() {
+            debug!("found state, loading from file");
+            let file = File::open(ALLIUM_FOLDER_VIEWS.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(json);
+            }
+            warn!("failed to read folder view settings, removing");
+            fs::remove_file(ALLIUM_FOLDER_VIEWS.as_path())?;
+        }
+        Ok(Self::new())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_FOLDER_VIEWS.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+
+    pub fn get(&self, dir: &Path) -> Option<&FolderView> {
+        self.folders.get(dir)
+    }
+
+    /// Sets the user's settings for `dir`. Empty settings are forgotten.
+    pub fn set(&mut self, dir: &Path, view: FolderView) {
+        if view.is_empty() {
+            self.folders.remove(dir);
+        } else {
+            self.folders.insert(dir.to_path_buf(), view);
+        }
+    }
+
+    /// The view settings that apply to `dir`.
+    pub fn resolve(
+        &self,
+        dir: &Path,
+        console_mapper: &ConsoleMapper,
+        styles: &StyleConfig,
+    ) -> ResolvedView {
+        let console = dir
+            .ancestors()
+            .find_map(|dir| console_mapper.get_console_by_dir(dir))
+            .map(|console| &console.view);
+        ResolvedView::resolve(styles, console, self.get(dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn console_mapper() -> ConsoleMapper {
+        let mut console_mapper = ConsoleMapper::new();
+        console_mapper
+            .parse_config(include_str!(
+                "../../../assets/root/.allium/config/consoles.toml"
+            ))
+            .unwrap();
+        console_mapper
+    }
+
+    fn styles(enable_box_art: bool) -> StyleConfig {
+        let mut styles = StyleConfig::default();
+        styles.enable_box_art = enable_box_art;
+        styles
+    }
+
+    #[test]
+    fn test_precedence() {
+        let console = FolderView {
+            box_art: Some(false),
+            full_names: Some(true),
+            grid: None,
+        };
+        let folder = FolderView {
+            box_art: Some(true),
+            full_names: None,
+            grid: Some(true),
+        };
+
+        let view = ResolvedView::resolve(&styles(true), None, None);
+        assert_eq!(view.box_art.source, Source::Global);
+        assert!(view.box_art.value);
+        assert_eq!(view.full_names.source, Source::Global);
+        assert!(!view.full_names.value);
+
+        // Console defaults override global ones
+        let view = ResolvedView::resolve(&styles(true), Some(&console), None);
+        assert_eq!(
+            view.box_art,
+            Setting {
+                value: false,
+                source: Source::Console
+            }
+        );
+        assert_eq!(view.full_names.source, Source::Console);
+
+        // The folder's settings override the console's, one setting at a time
+        let view = ResolvedView::resolve(&styles(true), Some(&console), Some(&folder));
+        assert_eq!(
+            view.box_art,
+            Setting {
+                value: true,
+                source: Source::Folder
+            }
+        );
+        assert_eq!(
+            view.full_names,
+            Setting {
+                value: true,
+                source: Source::Console
+            }
+        );
+        assert_eq!(
+            view.grid,
+            Setting {
+                value: true,
+                source: Source::Folder
+            }
+        );
+    }
+
+    #[test]
+    fn test_console_defaults() {
+        let console_mapper = console_mapper();
+        let mut folder_views = FolderViews::new();
+
+        let arcade =
+            folder_views.resolve(Path::new("/Roms/ARCADE"), &console_mapper, &styles(true));
+        assert!(arcade.full_names.value);
+        assert!(!arcade.box_art.value);
+        assert_eq!(arcade.box_art.source, Source::Console);
+
+        // Subfolders inherit their console's defaults
+        let hacks = Path::new("/Roms/ARCADE/Hacks");
+        assert_eq!(
+            folder_views.resolve(hacks, &console_mapper, &styles(true)),
+            arcade
+        );
+
+        // Handhelds show box art even if the theme doesn't
+        let gba = folder_views.resolve(Path::new("/Roms/GBA"), &console_mapper, &styles(false));
+        assert_eq!(
+            gba.box_art,
+            Setting {
+                value: true,
+                source: Source::Console
+            }
+        );
+        assert_eq!(gba.full_names.source, Source::Global);
+
+        // Folders of unknown consoles use the global defaults
+        let other = folder_views.resolve(Path::new("/Roms/Other"), &console_mapper, &styles(false));
+        assert_eq!(other, ResolvedView::global(&styles(false)));
+
+        // The user's choice only applies to the folder it was made in
+        folder_views.set(
+            hacks,
+            FolderView {
+                box_art: Some(true),
+                full_names: None,
+                grid: None,
+            },
+        );
+        let view = folder_views.resolve(hacks, &console_mapper, &styles(true));
+        assert_eq!(view.box_art.source, Source::Folder);
+        assert!(view.box_art.value);
+        assert_eq!(view.full_names.source, Source::Console);
+        assert_eq!(
+            folder_views.resolve(Path::new("/Roms/ARCADE"), &console_mapper, &styles(true)),
+            arcade
+        );
+
+        // Clearing the choice goes back to the defaults
+        folder_views.set(hacks, FolderView::default());
+        assert!(folder_views.get(hacks).is_none());
+        assert_eq!(
+            folder_views.resolve(hacks, &console_mapper, &styles(true)),
+            arcade
+        );
+    }
+}
@@ -5,7 +5,11 @@ use std::{
 };
 
 use anyhow::Result;
+use chrono::Duration;
 use common::constants::ALLIUM_GAMES_DIR;
+use common::database::{self, GameTitle};
+use common::filename_rules::FilenameRules;
+use common::sort_order::{self, SortKey, SortOrder};
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -25,6 +29,50 @@ pub struct Game {
     pub extension: String,
     /// The core to use for this game. If None, the default core will be used.
     pub core: Option<String>,
+    /// Manufacturer, if known from a names database.
+    #[serde(default)]
+    pub manufacturer: Option<String>,
+    /// Release year, if known from a names database.
+    #[serde(default)]
+    pub year: Option<u16>,
+    /// Total time played, if the game came from the games database.
+    #[serde(skip)]
+    pub play_time: Option<Duration>,
+    /// Whether the game is pinned to the top of the recently played list.
+    #[serde(skip)]
+    pub pinned: bool,
+    /// Save state slot to launch the game from, if one was picked.
+    #[serde(skip)]
+    pub state_slot: Option<i8>,
+}
+
+impl From<database::Game> for Game {
+    fn from(game: database::Game) -> Self {
+        let extension = game
+            .path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_owned();
+
+        let full_name = game.name.clone();
+
+        let image = LazyImage::from_path(&game.path, game.image);
+
+        Game {
+            name: game.name,
+            full_name,
+            path: game.path,
+            image,
+            extension,
+            core: game.core,
+            manufacturer: None,
+            year: None,
+            play_time: Some(game.play_time),
+            pinned: false,
+            state_slot: None,
+        }
+    }
 }
 
 impl Game {
@@ -48,9 +96,28 @@ impl Game {
             image,
             extension,
             core: None,
+            manufacturer: None,
+            year: None,
+            play_time: None,
+            pinned: false,
+            state_slot: None,
         }
     }
 
+    /// Shows the game under its title from a names database instead of its file name.
+    pub fn set_title(&mut self, title: GameTitle) {
+        self.name = short_name(&title.name);
+        self.full_name = title.name;
+        self.manufacturer = title.manufacturer;
+        self.year = title.year;
+    }
+
+    /// Shows the game under the name the user gave it, exactly as it was typed.
+    pub fn rename(&mut self, name: String) {
+        self.name = name.clone();
+        self.full_name = name;
+    }
+
     pub fn image(&mut self) -> Option<&Path> {
         self.image.image()
     }
@@ -74,7 +141,12 @@ impl Game {
 
 impl Ord for Game {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.full_name.cmp(&other.full_name)
+        sort_order::compare(
+            SortOrder::Name,
+            &FilenameRules::active(),
+            &SortKey::new(&self.full_name, &self.path),
+            &SortKey::new(&other.full_name, &other.path),
+        )
     }
 }
 
@@ -0,0 +1,257 @@
+//! Index of the files in the games folder, kept in the database.
+//!
+//! Reading a folder with thousands of games from a FAT32 SD card is slow, so a background indexer
+//! records what each folder holds when the launcher starts, and folders are listed from the index
+//! instead. A folder's modification time changes when files are added to, removed from or renamed
+//! in it, so only folders whose time changed are read again. Folders that haven't been indexed
+//! since they last changed are read from the SD card as before.
+
+use std::collections::{HashSet, VecDeque};
+use std::ffi::OsStr;
+use std::fs::{self, Metadata};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+use anyhow::Result;
+use common::constants::ALLIUM_GAMES_DIR;
+use common::database::{Database, IndexedFile};
+use log::{debug, error, warn};
+
+use crate::launcher_config;
+use crate::library_filter;
+
+/// How long the indexer waits for the launcher to finish writing to the database.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+static IS_INDEXER_RUNNING: AtomicBool = AtomicBool::new(false);
+static IS_INDEX_STALE: AtomicBool = AtomicBool::new(false);
+static IS_FULL_RESCAN: AtomicBool = AtomicBool::new(false);
+static FOLDERS_DONE: AtomicUsize = AtomicUsize::new(0);
+static FOLDERS_FOUND: AtomicUsize = AtomicUsize::new(0);
+
+/// How far the indexer has got through the games folder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexProgress {
+    pub running: bool,
+    /// Folders indexed so far, out of the ones found so far.
+    pub done: usize,
+    pub found: usize,
+}
+
+/// Brings the index up to date with the games folder in the background. If `full` is set, every
+/// folder is read again, whether it changed or not. If the indexer is already running, it goes
+/// over the games folder again once it's done.
+pub fn spawn_indexer(full: bool) {
+    if full {
+        IS_FULL_RESCAN.store(true, Ordering::SeqCst);
+    }
+    IS_INDEX_STALE.store(true, Ordering::SeqCst);
+    if IS_INDEXER_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    thread::spawn(|| {
+        if let Err(e) = run_indexer() {
+            error!("failed to index games: {}", e);
+        }
+        IS_INDEXER_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+fn run_indexer() -> Result<()> {
+    let database = Database::new()?;
+    database.set_busy_timeout(BUSY_TIMEOUT)?;
+    while IS_INDEX_STALE.swap(false, Ordering::SeqCst) {
+        let full = IS_FULL_RESCAN.swap(false, Ordering::SeqCst);
+        FOLDERS_DONE.store(0, Ordering::SeqCst);
+        FOLDERS_FOUND.store(1, Ordering::SeqCst);
+        let read = index(&ALLIUM_GAMES_DIR, &database, full, |done, found| {
+            FOLDERS_DONE.store(done, Ordering::SeqCst);
+            FOLDERS_FOUND.store(found, Ordering::SeqCst);
+        })?;
+        debug!("indexed games, {} folders read", read);
+    }
+    Ok(())
+}
+
+/// How far the indexer has got, or got the last time it ran.
+pub fn progress() -> IndexProgress {
+    IndexProgress {
+        running: IS_INDEXER_RUNNING.load(Ordering::SeqCst),
+        done: FOLDERS_DONE.load(Ordering::SeqCst),
+        found: FOLDERS_FOUND.load(Ordering::SeqCst),
+    }
+}
+
+/// Indexes `root` and the folders in it, telling `progress` how many folders are done out of the
+/// ones found so far. Unless `full` is set, folders that haven't changed since they were last
+/// indexed aren't read again. Returns how many folders were read.
+pub fn index(
+    root: &Path,
+    database: &Database,
+    full: bool,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<usize> {
+    if full {
+        database.clear_file_index()?;
+    }
+
+    let mut found = HashSet::new();
+    let mut read = 0;
+    let mut queue = VecDeque::from([root.to_path_buf()]);
+    while let Some(dir) = queue.pop_front() {
+        let Some(modified) = fs::metadata(&dir).ok().as_ref().and_then(modified) else {
+            continue;
+        };
+        let files = match database.indexed_files(&dir, modified)? {
+            Some(files) => files,
+            None => match read_dir(&dir) {
+                Ok(files) => {
+                    database.index_directory(&dir, modified, &files)?;
+                    read += 1;
+                    files
+                }
+                Err(e) => {
+                    warn!("failed to index {}: {}", dir.display(), e);
+                    continue;
+                }
+            },
+        };
+        queue.extend(files.into_iter().filter(is_navigable).map(|file| file.path));
+        found.insert(dir);
+        progress(found.len(), found.len() + queue.len());
+    }
+
+    // Folders that are gone, or no longer listed
+    for (dir, _) in database.indexed_directories()? {
+        if dir.starts_with(root) && !found.contains(&dir) {
+            database.remove_indexed_directory(&dir)?;
+        }
+    }
+    Ok(read)
+}
+
+/// The files and folders in the directory at `path`, if they were indexed since it last changed.
+pub fn indexed_files(path: &Path, database: &Database) -> Option<Vec<IndexedFile>> {
+    let modified = fs::metadata(path).ok().as_ref().and_then(modified)?;
+    database
+        .indexed_files(path, modified)
+        .map_err(|e| warn!("failed to read index of {}: {}", path.display(), e))
+        .ok()
+        .flatten()
+}
+
+/// When a file was last modified, in nanoseconds since the epoch.
+pub fn modified(metadata: &Metadata) -> Option<i64> {
+    let nanos = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+    i64::try_from(nanos).ok()
+}
+
+/// Reads what the directory at `dir` holds, leaving out hidden files, which are never listed.
+fn read_dir(dir: &Path) -> Result<Vec<IndexedFile>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(OsStr::to_str) else {
+            continue;
+        };
+        if library_filter::is_dot_file(name) {
+            continue;
+        }
+        // Follows links, as listing the folder does
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        files.push(IndexedFile {
+            name: name.to_string(),
+            directory: dir.to_path_buf(),
+            extension: path
+                .extension()
+                .and_then(OsStr::to_str)
+                .unwrap_or_default()
+                .to_string(),
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified: modified(&metadata).unwrap_or_default(),
+            path,
+        });
+    }
+    Ok(files)
+}
+
+/// Whether the file is a folder that is listed and can be navigated into.
+fn is_navigable(file: &IndexedFile) -> bool {
+    file.is_dir
+        && file.extension.is_empty()
+        && !launcher_config::is_excluded(&file.name, &file.extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use common::test_utils::temp_dir;
+
+    use super::*;
+
+    fn names(files: Vec<IndexedFile>) -> Vec<String> {
+        files.into_iter().map(|file| file.name).collect()
+    }
+
+    #[test]
+    fn test_index_is_incremental() -> Result<()> {
+        let root = temp_dir("game-index-incremental");
+        let database = Database::in_memory()?;
+        fs::create_dir_all(root.join("GB/Hacks"))?;
+        fs::create_dir_all(root.join(".Trashes/GB"))?;
+        fs::create_dir_all(root.join("Tool.pak"))?;
+        File::create(root.join("GB/Tetris.gb"))?.set_len(32)?;
+        File::create(root.join("GB/Hacks/Tetris DX.gb"))?;
+
+        let mut last = (0, 0);
+        // The games folder and the folders that are listed in it
+        assert_eq!(index(&root, &database, false, |d, f| last = (d, f))?, 3);
+        assert_eq!(last, (3, 3));
+        let files = indexed_files(&root.join("GB"), &database).unwrap();
+        assert_eq!(names(files.clone()), ["Hacks", "Tetris.gb"]);
+        assert!(files[0].is_dir);
+        assert_eq!((files[1].extension.as_str(), files[1].size), ("gb", 32));
+        assert_eq!(
+            names(indexed_files(&root, &database).unwrap()),
+            ["GB", "Tool.pak"]
+        );
+
+        // Nothing changed
+        assert_eq!(index(&root, &database, false, |_, _| {})?, 0);
+
+        // Only the folder a game was added to is read again
+        File::create(root.join("GB/Tetris 2.gb"))?;
+        assert_eq!(indexed_files(&root.join("GB"), &database), None);
+        assert_eq!(index(&root, &database, false, |_, _| {})?, 1);
+        assert_eq!(
+            names(indexed_files(&root.join("GB"), &database).unwrap()),
+            ["Hacks", "Tetris 2.gb", "Tetris.gb"]
+        );
+
+        // Removed folders are forgotten
+        fs::remove_dir_all(root.join("GB/Hacks"))?;
+        assert_eq!(index(&root, &database, false, |_, _| {})?, 1);
+        assert!(!database
+            .indexed_directories()?
+            .iter()
+            .any(|(dir, _)| dir == &root.join("GB/Hacks")));
+
+        // A full rescan reads every folder
+        assert_eq!(index(&root, &database, true, |_, _| {})?, 2);
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
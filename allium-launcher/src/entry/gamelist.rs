@@ -1,7 +1,27 @@
-use std::path::PathBuf;
+//! EmulationStation `gamelist.xml` files, which curate the names and images of the games in a
+//! folder, and the `miyoogamelist.xml` files of Onion and MiniUI, which are much the same but may
+//! give the image as `imgpath`. Paths in them are relative to the folder, usually written like
+//! `./Game.gba`.
 
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use log::warn;
 use serde::{Deserialize, Serialize};
 
+/// A game list that has been read, with when its file was modified. `None` if the file couldn't be
+/// parsed.
+type Cached = (SystemTime, Option<Arc<GameList>>);
+
+lazy_static! {
+    static ref GAME_LISTS: Mutex<HashMap<PathBuf, Cached>> = Mutex::new(HashMap::new());
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct GameList {
@@ -16,7 +36,11 @@ pub struct GameList {
 pub struct Game {
     pub path: PathBuf,
     pub name: String,
-    #[serde(default, deserialize_with = "optional_path_buf_deserializer")]
+    #[serde(
+        default,
+        alias = "imgpath",
+        deserialize_with = "optional_path_buf_deserializer"
+    )]
     pub image: Option<PathBuf>,
     #[serde(default, deserialize_with = "optional_path_buf_deserializer")]
     pub thumbnail: Option<PathBuf>,
@@ -33,6 +57,41 @@ pub struct Folder {
     pub thumbnail: Option<PathBuf>,
 }
 
+impl GameList {
+    /// Reads the game list at `path`, or returns it from memory if the file hasn't been modified
+    /// since. Lists that can't be parsed are logged and left out.
+    pub fn load_cached(path: &Path) -> Option<Arc<GameList>> {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        let mut game_lists = GAME_LISTS.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((read, game_list)) = game_lists.get(path) {
+            if *read == modified {
+                return game_list.clone();
+            }
+        }
+
+        let game_list = match Self::load(path) {
+            Ok(game_list) => Some(Arc::new(game_list)),
+            Err(e) => {
+                warn!("ignoring malformed {}: {}", path.display(), e);
+                None
+            }
+        };
+        game_lists.insert(path.to_path_buf(), (modified, game_list.clone()));
+        game_list
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        Ok(serde_xml_rs::from_reader(file)?)
+    }
+}
+
+/// Resolves `path` from a game list in `dir`, without the `.` components, so that it is the same
+/// path the folder's files are listed under.
+pub fn resolve(dir: &Path, path: &Path) -> PathBuf {
+    dir.join(path).components().collect()
+}
+
 fn optional_path_buf_deserializer<'de, D>(d: D) -> Result<Option<PathBuf>, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -117,4 +176,46 @@ mod tests {
             Some(PathBuf::from("path/to/image"))
         );
     }
+
+    #[test]
+    fn test_deserialize_miyoo_games() {
+        let xml = r#"
+        <gameList>
+            <game>
+                <path>./Legend of Zelda, The - A Link to the Past (USA).zip</path>
+                <name>Legend of Zelda, The - A Link to the Past</name>
+                <imgpath>./Imgs/Legend of Zelda, The - A Link to the Past (USA).png</imgpath>
+            </game>
+        </gameList>
+        "#;
+        let game_list: GameList = serde_xml_rs::from_str(xml).unwrap();
+        assert_eq!(game_list.games.len(), 1);
+        assert_eq!(
+            game_list.games[0].name,
+            "Legend of Zelda, The - A Link to the Past"
+        );
+        assert_eq!(
+            game_list.games[0].image,
+            Some(PathBuf::from(
+                "./Imgs/Legend of Zelda, The - A Link to the Past (USA).png"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = Path::new("/mnt/SDCARD/Roms/GBA");
+        assert_eq!(
+            resolve(dir, Path::new("./media/box.png")),
+            PathBuf::from("/mnt/SDCARD/Roms/GBA/media/box.png")
+        );
+        assert_eq!(
+            resolve(dir, Path::new("Game.gba")),
+            PathBuf::from("/mnt/SDCARD/Roms/GBA/Game.gba")
+        );
+        assert_eq!(
+            resolve(dir, Path::new("/mnt/SDCARD/Imgs/box.png")),
+            PathBuf::from("/mnt/SDCARD/Imgs/box.png")
+        );
+    }
 }
@@ -3,6 +3,8 @@ use std::path::{Path, PathBuf};
 use common::constants::ALLIUM_GAMES_DIR;
 use serde::{Deserialize, Serialize};
 
+use crate::entry::art_index::{self, ArtIndex, IMAGE_EXTENSIONS};
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum LazyImage {
     /// Path to the file
@@ -22,6 +24,12 @@ impl LazyImage {
 
     /// Searches for the image path, caches it, and returns it
     pub fn image(&mut self) -> Option<&Path> {
+        self.find_with(&art_index::read(), &ALLIUM_GAMES_DIR)
+    }
+
+    /// Searches for the image in `Imgs` folders up to `games_dir`. Folders covered by `index`
+    /// are looked up in memory, anything else is probed on disk.
+    pub fn find_with(&mut self, index: &ArtIndex, games_dir: &Path) -> Option<&Path> {
         let path = match self {
             Self::Unknown(path) => path,
             Self::Found(path) => return Some(path.as_path()),
@@ -31,30 +39,20 @@ impl LazyImage {
         // Search for Imgs folder upwards, recursively
         let mut parent = path.clone();
         let mut image = None;
-        let file_name = path.file_name().unwrap();
-        'image: while parent.pop() {
-            let mut image_path = parent.join("Imgs");
-            if image_path.is_dir() {
-                const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "gif"];
-                image_path.push(file_name);
-                for ext in &IMAGE_EXTENSIONS {
-                    image_path.set_extension(ext);
-                    if image_path.is_file() {
-                        image = Some(image_path);
-                        break 'image;
-                    }
-                }
-                image_path.pop();
-                image_path.extend(path.strip_prefix(&parent).unwrap());
-                for ext in &IMAGE_EXTENSIONS {
-                    image_path.set_extension(ext);
-                    if image_path.is_file() {
-                        image = Some(image_path);
-                        break 'image;
-                    }
-                }
-            }
-            if parent.to_str() == ALLIUM_GAMES_DIR.to_str() {
+        let file_name = Path::new(path.file_name().unwrap());
+        while parent.pop() {
+            let image_dir = parent.join("Imgs");
+            let relative = path.strip_prefix(&parent).unwrap();
+            image = if index.covers(&image_dir) {
+                index
+                    .find(&image_dir, file_name)
+                    .or_else(|| index.find(&image_dir, relative))
+            } else if image_dir.is_dir() {
+                probe(&image_dir, file_name).or_else(|| probe(&image_dir, relative))
+            } else {
+                None
+            };
+            if image.is_some() || parent.to_str() == games_dir.to_str() {
                 break;
             }
         }
@@ -77,6 +75,18 @@ impl LazyImage {
     }
 }
 
+/// Looks for `name` with each image extension in `dir` on disk.
+fn probe(dir: &Path, name: &Path) -> Option<PathBuf> {
+    let mut image_path = dir.join(name);
+    for ext in &IMAGE_EXTENSIONS {
+        image_path.set_extension(ext);
+        if image_path.is_file() {
+            return Some(image_path);
+        }
+    }
+    None
+}
+
 impl From<PathBuf> for LazyImage {
     fn from(path: PathBuf) -> Self {
         Self::Found(path)
@@ -1,17 +1,26 @@
 pub mod app;
+pub mod art_index;
+pub mod collection;
 pub mod directory;
+pub mod folder_config;
+pub mod folder_view;
 pub mod game;
+pub mod game_index;
 mod gamelist;
 pub mod lazy_image;
+pub mod names;
+pub mod retroarch_playlist;
 
 use std::ffi::OsStr;
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
-use common::database::Database;
+use common::database::{Database, IndexedFile};
 use common::locale::Locale;
+use common::playlist;
 use lazy_static::lazy_static;
+use log::warn;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
@@ -19,6 +28,8 @@ use crate::consoles::ConsoleMapper;
 use crate::entry::app::App;
 use crate::entry::directory::Directory;
 use crate::entry::game::Game;
+use crate::launcher_config;
+use crate::library_filter::{self, LibraryFilter};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Entry {
@@ -29,12 +40,30 @@ pub enum Entry {
 
 impl Entry {
     pub fn new(path: PathBuf, console_mapper: &ConsoleMapper) -> Result<Option<Entry>> {
+        Self::with_kind(path, None, console_mapper)
+    }
+
+    /// The entry for a file from the game index, which already knows whether it's a folder.
+    pub fn from_indexed(
+        file: IndexedFile,
+        console_mapper: &ConsoleMapper,
+    ) -> Result<Option<Entry>> {
+        Self::with_kind(file.path, Some(file.is_dir), console_mapper)
+    }
+
+    /// The entry for the file at `path`, which is looked up on the SD card to tell whether it's a
+    /// folder unless `is_dir` says.
+    fn with_kind(
+        path: PathBuf,
+        is_dir: Option<bool>,
+        console_mapper: &ConsoleMapper,
+    ) -> Result<Option<Entry>> {
         // Don't add hidden files starting with .
         let file_name = match path.file_name().and_then(OsStr::to_str) {
             Some(file_name) => file_name,
             None => return Ok(None),
         };
-        if file_name.starts_with('.') {
+        if library_filter::is_dot_file(file_name) {
             return Ok(None);
         }
 
@@ -44,22 +73,14 @@ impl Entry {
             .unwrap_or_default()
             .to_owned();
 
-        // Exclude Imgs and Guide directories
-        if file_name == "Imgs"
-            || file_name == "Guides"
-            || file_name == "gamelist.xml"
-            || file_name == "miyoogamelist.xml"
+        // Exclude e.g. Imgs directories, DB files and BIOS files, as configured
+        if launcher_config::is_excluded(file_name, &extension)
+            || launcher_config::is_in_bios_dir(&path)
         {
             return Ok(None);
         }
 
-        // Exclude DB
-        const EXCLUDE_EXTENSIONS: [&str; 1] = ["db"];
-        if EXCLUDE_EXTENSIONS.contains(&extension.as_str()) {
-            return Ok(None);
-        }
-
-        if path.is_dir() {
+        if is_dir.unwrap_or_else(|| path.is_dir()) {
             // Directories without extensions can be navigated into
             if extension.is_empty() {
                 return Ok(Some(Entry::Directory(
@@ -77,6 +98,14 @@ impl Entry {
             }
         }
 
+        // A broken playlist can't be launched, so its discs are listed on their own instead
+        if playlist::is_playlist(&path) {
+            if let Err(e) = playlist::discs(&path) {
+                warn!("ignoring playlist: {}", e);
+                return Ok(None);
+            }
+        }
+
         Ok(Some(Entry::Game(Game::new(path))))
     }
 
@@ -96,6 +125,15 @@ impl Entry {
         }
     }
 
+    /// Name including tags such as the region, which [`Entry::name`] leaves out.
+    pub fn full_name(&self) -> &str {
+        match self {
+            Entry::Game(game) => &game.full_name,
+            Entry::Directory(directory) => &directory.full_name,
+            Entry::App(app) => &app.name,
+        }
+    }
+
     pub fn image(&mut self) -> Option<&Path> {
         match self {
             Entry::Game(game) => game.image(),
@@ -105,29 +143,80 @@ impl Entry {
     }
 }
 
-fn short_name(name: &str) -> String {
+pub fn short_name(name: &str) -> String {
     // Remove numbers
     lazy_static! {
         static ref NUMBERS_RE: Regex = Regex::new(r"^\d+[.\)]").unwrap();
     }
-    let name = NUMBERS_RE.replace(name, "").to_string();
+    let short = NUMBERS_RE.replace(name, "").to_string();
 
     // Remove trailing parenthesis
     lazy_static! {
         static ref PARENTHESIS_RE: Regex = Regex::new(r"[\(\[].+[\)\]]$").unwrap();
     }
-    let name = PARENTHESIS_RE.replace(&name, "").to_string();
+    let short = PARENTHESIS_RE.replace(&short, "").to_string();
 
     // Trim whitespaces
-    let name = name.trim().to_owned();
+    let short = short.trim();
 
-    name
+    // A name that is nothing but a number or tags is better left whole than empty
+    if short.is_empty() {
+        name.trim().to_owned()
+    } else {
+        short.to_owned()
+    }
 }
 
 pub trait Sort: Debug + Clone {
     const HAS_BUTTON_HINTS: bool = true;
+    /// Whether entries can be selected for batch operations.
+    const HAS_MULTI_SELECT: bool = false;
+    /// Whether the list offers to clear the recently played list.
+    const HAS_CLEAR_RECENTS: bool = false;
+    /// Whether games show how long they were played.
+    const HAS_PLAY_TIME: bool = false;
     fn button_hint(&self, locale: &Locale) -> String;
     fn next(&self) -> Self;
     fn with_directory(&self, directory: Directory) -> Self;
-    fn entries(&self, database: &Database, console_mapper: &ConsoleMapper) -> Result<Vec<Entry>>;
+    /// Games folder that is listed, whose view settings apply to the list.
+    fn folder(&self) -> Option<&Path> {
+        None
+    }
+    /// Whether entries are sorted by name, so that L and R jump between letters.
+    fn is_alphabetical(&self) -> bool {
+        false
+    }
+    /// ID of the collection that is listed.
+    fn collection(&self) -> Option<i64> {
+        None
+    }
+    /// How many entries there are to pick from if the list only shows some of them, such as search
+    /// results out of the whole library.
+    fn total(&self, _database: &Database) -> Result<Option<usize>> {
+        Ok(None)
+    }
+    /// Entries to list, without the ones that `filter` hides.
+    fn entries(
+        &self,
+        database: &Database,
+        console_mapper: &ConsoleMapper,
+        filter: &LibraryFilter,
+    ) -> Result<Vec<Entry>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_name() {
+        assert_eq!(short_name("Tetris (USA) [!]"), "Tetris");
+        assert_eq!(
+            short_name("01. Super Mario Land (World)"),
+            "Super Mario Land"
+        );
+        assert_eq!(short_name("1942"), "1942");
+        assert_eq!(short_name("1942."), "1942.");
+        assert_eq!(short_name("(Prototype)"), "(Prototype)");
+    }
 }
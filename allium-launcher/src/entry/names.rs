@@ -0,0 +1,407 @@
+//! Descriptive titles for games with cryptic file names.
+//!
+//! Arcade sets name their games after the short MAME name, e.g. `sf2.zip`, which says little. A
+//! names database maps these to titles, along with the manufacturer and year where known. It is
+//! read from `names.txt` in the games folder, or from the file that the folder's console points to
+//! in `consoles.toml`. Each line holds the file name without extension, the title, and optionally
+//! the manufacturer and year, separated by tabs. Lines starting with `#` are comments.
+//!
+//! Names databases of full sets are megabytes, so a database is only read when a folder has games
+//! whose titles haven't been resolved yet, and kept in memory afterwards. Resolved titles are
+//! stored in the games database, so folders that were visited before don't need it at all.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::Result;
+use common::constants::ALLIUM_CONFIG_CONSOLES;
+use common::database::{Database, GameTitle};
+use common::locale::Locale;
+use lazy_static::lazy_static;
+use log::{debug, error};
+
+use crate::consoles::ConsoleMapper;
+use crate::entry::game::Game;
+use crate::entry::Entry;
+
+/// Names database kept in a games folder.
+pub const NAMES_FILE: &str = "names.txt";
+
+/// Most manufacturers offered as quick filters, picked by number of games.
+const MAX_MANUFACTURER_FILTERS: usize = 8;
+
+lazy_static! {
+    /// Names databases that have been read, or `None` if they couldn't be.
+    static ref NAMES: Mutex<HashMap<PathBuf, Option<Arc<Names>>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Default)]
+pub struct Names {
+    /// Titles by lowercase file name without extension.
+    titles: HashMap<String, GameTitle>,
+}
+
+impl Names {
+    pub fn parse(text: &str) -> Self {
+        let titles = text
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split('\t').map(str::trim);
+                let name = fields.next().filter(|s| !s.is_empty())?;
+                let title = fields.next().filter(|s| !s.is_empty())?;
+                let manufacturer = fields.next().filter(|s| !s.is_empty());
+                let year = fields.next().and_then(parse_year);
+                Some((
+                    name.to_lowercase(),
+                    GameTitle {
+                        name: title.to_string(),
+                        manufacturer: manufacturer.map(str::to_string),
+                        year,
+                    },
+                ))
+            })
+            .collect();
+        Self { titles }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// Title of the game at `path`, looked up by its file name without extension.
+    pub fn get(&self, path: &Path) -> Option<&GameTitle> {
+        let name = path.file_stem()?.to_str()?.to_lowercase();
+        self.titles.get(&name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.titles.len()
+    }
+}
+
+/// Parses a year such as "1991". Unknown digits, as in "199?", count as 0 so that the decade is
+/// still known.
+fn parse_year(year: &str) -> Option<u16> {
+    if year.len() != 4 || !year[..3].bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    year.replace('?', "0").parse().ok()
+}
+
+/// The names database that applies to games in `dir`, if any.
+pub fn source(dir: &Path, console_mapper: &ConsoleMapper) -> Option<PathBuf> {
+    let names = dir.join(NAMES_FILE);
+    if names.is_file() {
+        return Some(names);
+    }
+    console_mapper
+        .get_console(dir)
+        .and_then(|console| console.names.as_ref())
+        .map(|names| ALLIUM_CONFIG_CONSOLES.with_file_name(names))
+}
+
+/// Reads the names database at `path`, or returns it from memory if it was read before.
+fn load_cached(path: &Path) -> Option<Arc<Names>> {
+    NAMES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(path.to_path_buf())
+        .or_insert_with(|| match Names::load(path) {
+            Ok(names) => {
+                debug!("read {} names from {}", names.len(), path.display());
+                Some(Arc::new(names))
+            }
+            Err(e) => {
+                error!("failed to read names database {}: {}", path.display(), e);
+                None
+            }
+        })
+        .clone()
+}
+
+/// Gives games among `entries` their titles from the names database of `dir`. Titles stored in
+/// the games database are used first, and only the remaining games are looked up in the names
+/// database. Games it doesn't know keep their file name. `curated` games already have a name from
+/// a game list, so they only take titles stored in the games database.
+pub fn apply(
+    dir: &Path,
+    entries: &mut [Entry],
+    database: &Database,
+    console_mapper: &ConsoleMapper,
+    curated: &HashSet<PathBuf>,
+) -> Result<()> {
+    let Some(source) = source(dir, console_mapper) else {
+        return Ok(());
+    };
+
+    let mut titles = database.game_titles(dir)?;
+    let missing: Vec<_> = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::Game(game)
+                if !titles.contains_key(&game.path) && !curated.contains(&game.path) =>
+            {
+                Some(game.path.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    if !missing.is_empty() {
+        if let Some(names) = load_cached(&source) {
+            let resolved: Vec<_> = missing
+                .into_iter()
+                .map(|path| {
+                    let title = names.get(&path).cloned();
+                    (path, title)
+                })
+                .collect();
+            database.set_game_titles(&resolved)?;
+            titles.extend(resolved);
+        }
+    }
+
+    for entry in entries.iter_mut() {
+        if let Entry::Game(game) = entry {
+            if let Some(Some(title)) = titles.remove(&game.path) {
+                game.set_title(title);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Shows games among `entries` that the user renamed under their new names. Applied after titles,
+/// since the user's choice wins.
+pub fn apply_renames(entries: &mut [Entry], database: &Database) -> Result<()> {
+    let mut names = database.game_names()?;
+    if names.is_empty() {
+        return Ok(());
+    }
+    for entry in entries.iter_mut() {
+        if let Entry::Game(game) = entry {
+            if let Some(name) = names.remove(&game.path) {
+                game.rename(name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Narrows a list down to the games of one decade or manufacturer, so that large folders can be
+/// browsed without typing a search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuickFilter {
+    All,
+    Decade(u16),
+    Manufacturer(String),
+}
+
+impl QuickFilter {
+    /// Filters offered for `games`: every decade, then the manufacturers with the most games.
+    /// Empty if no game has a year or manufacturer.
+    pub fn for_games<'a>(games: impl IntoIterator<Item = &'a Game>) -> Vec<Self> {
+        let mut decades = Vec::new();
+        let mut manufacturers: HashMap<&str, usize> = HashMap::new();
+        for game in games {
+            if let Some(year) = game.year {
+                decades.push(year / 10 * 10);
+            }
+            if let Some(manufacturer) = game.manufacturer.as_deref() {
+                *manufacturers
+                    .entry(manufacturer_key(manufacturer))
+                    .or_default() += 1;
+            }
+        }
+        if decades.is_empty() && manufacturers.is_empty() {
+            return Vec::new();
+        }
+
+        decades.sort_unstable();
+        decades.dedup();
+        let mut manufacturers: Vec<_> = manufacturers.into_iter().collect();
+        manufacturers.sort_unstable_by(|(a, m), (b, n)| n.cmp(m).then(a.cmp(b)));
+
+        [Self::All]
+            .into_iter()
+            .chain(decades.into_iter().map(Self::Decade))
+            .chain(
+                manufacturers
+                    .into_iter()
+                    .take(MAX_MANUFACTURER_FILTERS)
+                    .map(|(manufacturer, _)| Self::Manufacturer(manufacturer.to_string())),
+            )
+            .collect()
+    }
+
+    /// Whether the filter lets `entry` through. Only `All` lets folders and apps through.
+    pub fn matches(&self, entry: &Entry) -> bool {
+        match (self, entry) {
+            (Self::All, _) => true,
+            (Self::Decade(decade), Entry::Game(game)) => {
+                game.year.is_some_and(|year| year / 10 * 10 == *decade)
+            }
+            (Self::Manufacturer(manufacturer), Entry::Game(game)) => game
+                .manufacturer
+                .as_deref()
+                .is_some_and(|m| manufacturer_key(m) == manufacturer),
+            _ => false,
+        }
+    }
+
+    pub fn text(&self, locale: &Locale) -> String {
+        match self {
+            Self::All => locale.t("quick-filter-all"),
+            Self::Decade(decade) => locale.ta(
+                "quick-filter-decade",
+                &[("decade".to_string(), (*decade).into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            Self::Manufacturer(manufacturer) => manufacturer.clone(),
+        }
+    }
+}
+
+/// Groups licensed releases with their manufacturer, e.g. "Namco (Midway license)" with "Namco".
+fn manufacturer_key(manufacturer: &str) -> &str {
+    manufacturer
+        .split_once(" (")
+        .map_or(manufacturer, |(name, _)| name)
+        .trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use common::test_utils::temp_dir;
+
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../testdata/names/mame.txt");
+
+    fn game(file_name: &str, names: &Names) -> Game {
+        let mut game = Game::new(PathBuf::from("/Roms/ARCADE").join(file_name));
+        if let Some(title) = names.get(&game.path) {
+            game.set_title(title.clone());
+        }
+        game
+    }
+
+    #[test]
+    fn test_parse() {
+        let names = Names::parse(FIXTURE);
+        assert_eq!(names.len(), 10);
+
+        let sf2 = names.get(Path::new("/Roms/ARCADE/sf2.zip")).unwrap();
+        assert_eq!(
+            sf2.name,
+            "Street Fighter II: The World Warrior (World 910522)"
+        );
+        assert_eq!(sf2.manufacturer.as_deref(), Some("Capcom"));
+        assert_eq!(sf2.year, Some(1991));
+
+        // Optional fields and unknown digits
+        let unknown = names.get(Path::new("unkgame.zip")).unwrap();
+        assert_eq!(unknown.manufacturer, None);
+        assert_eq!(unknown.year, None);
+        assert_eq!(names.get(Path::new("xmcota.zip")).unwrap().year, Some(1990));
+    }
+
+    #[test]
+    fn test_lookup_fallback() {
+        let names = Names::parse(FIXTURE);
+
+        // File names are matched without extension or case
+        let game = game("DKONG.7z", &names);
+        assert_eq!(game.name, "Donkey Kong");
+        assert_eq!(game.full_name, "Donkey Kong (US set 1)");
+        assert_eq!(game.year, Some(1981));
+
+        // Unmapped games keep their file name
+        let game = self::game("mygame.zip", &names);
+        assert_eq!(game.name, "mygame");
+        assert_eq!(game.manufacturer, None);
+    }
+
+    #[test]
+    fn test_apply_stores_titles() -> Result<()> {
+        let dir = temp_dir("names");
+        fs::write(dir.join(NAMES_FILE), FIXTURE)?;
+
+        let database = Database::in_memory()?;
+        let console_mapper = ConsoleMapper::new();
+        let mut entries = vec![
+            Entry::Game(Game::new(dir.join("pacman.zip"))),
+            Entry::Game(Game::new(dir.join("mygame.zip"))),
+        ];
+        let mut curated = Game::new(dir.join("dkong.zip"));
+        curated.name = "Donkey Kong Classic".to_string();
+        entries.push(Entry::Game(curated));
+        let curated = HashSet::from([dir.join("dkong.zip")]);
+        apply(&dir, &mut entries, &database, &console_mapper, &curated)?;
+        assert_eq!(entries[0].name(), "Pac-Man");
+        assert_eq!(entries[1].name(), "mygame");
+        // Names from game lists aren't looked up
+        assert_eq!(entries[2].name(), "Donkey Kong Classic");
+
+        // Later visits use the stored titles, even without the names database
+        fs::remove_dir_all(&dir)?;
+        let titles = database.game_titles(&dir)?;
+        assert_eq!(titles.len(), 2);
+        assert_eq!(titles[&dir.join("mygame.zip")], None);
+
+        // but titles stored in the games database take precedence over them
+        database.set_game_titles(&[(
+            dir.join("dkong.zip"),
+            Some(GameTitle {
+                name: "Donkey Kong (Japan)".to_string(),
+                manufacturer: None,
+                year: None,
+            }),
+        )])?;
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(NAMES_FILE), FIXTURE)?;
+        apply(&dir, &mut entries, &database, &console_mapper, &curated)?;
+        assert_eq!(entries[2].name(), "Donkey Kong");
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_quick_filters() {
+        let names = Names::parse(FIXTURE);
+        let games: Vec<_> = ["sf2", "sfiii3", "1942", "dkong", "pacman", "mygame"]
+            .iter()
+            .map(|name| game(&format!("{}.zip", name), &names))
+            .collect();
+
+        let filters = QuickFilter::for_games(&games);
+        assert_eq!(
+            filters,
+            vec![
+                QuickFilter::All,
+                QuickFilter::Decade(1980),
+                QuickFilter::Decade(1990),
+                QuickFilter::Manufacturer("Capcom".to_string()),
+                QuickFilter::Manufacturer("Namco".to_string()),
+                QuickFilter::Manufacturer("Nintendo of America".to_string()),
+            ]
+        );
+
+        let count = |filter: &QuickFilter| {
+            games
+                .iter()
+                .filter(|g| filter.matches(&Entry::Game((*g).clone())))
+                .count()
+        };
+        assert_eq!(count(&QuickFilter::All), 6);
+        assert_eq!(count(&QuickFilter::Decade(1980)), 3);
+        assert_eq!(count(&QuickFilter::Manufacturer("Namco".to_string())), 1);
+
+        assert!(QuickFilter::for_games(&[Game::new(PathBuf::from("rom.gb"))]).is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+//! RetroArch `.lpl` playlists, which list games under labels of their own. Each playlist in the
+//! playlists folder is listed as a folder at the top of the games folder, which doesn't exist on
+//! the SD card. Games whose files are gone are still listed, so that they can be greyed out
+//! instead of hiding the rest of the playlist.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use common::database::Database;
+use common::playlist;
+use serde::Deserialize;
+
+use crate::entry::collection::Listing;
+use crate::entry::directory::Directory;
+use crate::entry::game::Game;
+use crate::entry::{names, short_name, Entry};
+
+/// Extension of RetroArch playlists.
+const PLAYLIST_EXTENSION: &str = "lpl";
+
+/// Extensions of archives that a playlist may point inside of, as in `game.zip#game.gba`.
+const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "7z"];
+
+#[derive(Debug, Deserialize)]
+struct Playlist {
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Item {
+    path: String,
+    #[serde(default)]
+    label: String,
+}
+
+/// The playlists in `playlists_dir`, as folders in `games_dir`.
+pub fn folders(games_dir: &Path, playlists_dir: &Path) -> Vec<Directory> {
+    let Ok(dir) = fs::read_dir(playlists_dir) else {
+        return Vec::new();
+    };
+    dir.filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(OsStr::to_str)
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(PLAYLIST_EXTENSION))
+        })
+        .filter_map(|path| {
+            // Named after the playlist file, so that it's found again from the folder
+            let mut dir = Directory::new(games_dir.join(path.file_name()?));
+            dir.listing = Some(Listing::RetroArchPlaylist);
+            Some(dir)
+        })
+        .collect()
+}
+
+/// The games in the playlist that the folder at `path` stands for, in the order they are listed.
+pub fn games(path: &Path, playlists_dir: &Path, database: &Database) -> Result<Vec<Entry>> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} isn't a playlist", path.display()))?;
+    let mut entries = parse(
+        &fs::read_to_string(playlists_dir.join(file_name))?,
+        playlists_dir,
+    )?;
+    names::apply_renames(&mut entries, database)?;
+    Ok(entries)
+}
+
+/// The games in a playlist, with paths relative to `dir` unless they are absolute.
+fn parse(playlist: &str, dir: &Path) -> Result<Vec<Entry>> {
+    let playlist: Playlist = serde_json::from_str(playlist)?;
+    Ok(playlist
+        .items
+        .into_iter()
+        .filter(|item| !item.path.is_empty())
+        .map(|item| {
+            let mut game = Game::new(resolve(dir, &item.path));
+            if !item.label.is_empty() {
+                game.name = short_name(&item.label);
+                game.full_name = item.label;
+            }
+            Entry::Game(game)
+        })
+        .collect())
+}
+
+/// The file that a playlist path refers to. A game inside an archive is launched through the
+/// archive, so that's what is returned for it.
+fn resolve(dir: &Path, path: &str) -> PathBuf {
+    // Playlists made on Windows may use backslashes
+    let path = path.replace('\\', "/");
+    let path = archive_path(&path).unwrap_or(&path);
+    playlist::normalize(&dir.join(path))
+}
+
+/// The archive in a path to a file inside of it, e.g. `game.zip` of `game.zip#game.gba`.
+fn archive_path(path: &str) -> Option<&str> {
+    path.match_indices('#')
+        .map(|(i, _)| &path[..i])
+        .find(|archive| {
+            Path::new(archive)
+                .extension()
+                .and_then(OsStr::to_str)
+                .is_some_and(|ext| {
+                    ARCHIVE_EXTENSIONS
+                        .iter()
+                        .any(|e| ext.eq_ignore_ascii_case(e))
+                })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use common::test_utils::temp_dir;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let dir = Path::new("/mnt/SDCARD/RetroArch/.retroarch/playlists");
+        let entries = parse(
+            r#"{
+                "version": "1.5",
+                "items": [
+                    {
+                        "path": "/mnt/SDCARD/Roms/GBA/Minish Cap.zip#Minish Cap (USA).gba",
+                        "label": "Zelda - The Minish Cap (USA)",
+                        "core_path": "DETECT",
+                        "crc32": "DETECT"
+                    },
+                    { "path": "../../../Roms/GB/Tetris #2.gb" },
+                    { "path": "", "label": "Nothing" }
+                ]
+            }"#,
+            dir,
+        )?;
+
+        let games: Vec<_> = entries
+            .iter()
+            .map(|e| (e.name(), e.full_name(), e.path()))
+            .collect();
+        assert_eq!(
+            games,
+            [
+                (
+                    "Zelda - The Minish Cap",
+                    "Zelda - The Minish Cap (USA)",
+                    Path::new("/mnt/SDCARD/Roms/GBA/Minish Cap.zip")
+                ),
+                (
+                    "Tetris #2",
+                    "Tetris #2",
+                    Path::new("/mnt/SDCARD/Roms/GB/Tetris #2.gb")
+                ),
+            ]
+        );
+
+        assert!(parse("{ \"items\": 1 }", dir).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_games_are_listed() -> Result<()> {
+        let playlists_dir = temp_dir("retroarch-playlist");
+        let game = playlists_dir.join("Tetris.gb");
+        fs::write(&game, "")?;
+        fs::write(
+            playlists_dir.join("Favorites.lpl"),
+            format!(
+                r#"{{ "items": [{{ "path": "Gone.gb" }}, {{ "path": "{}" }}] }}"#,
+                game.display()
+            ),
+        )?;
+        fs::write(playlists_dir.join("notes.txt"), "")?;
+
+        let games_dir = Path::new("/mnt/SDCARD/Roms");
+        let folders = folders(games_dir, &playlists_dir);
+        let [folder] = folders.as_slice() else {
+            panic!("expected one playlist: {folders:?}");
+        };
+        assert_eq!(folder.name, "Favorites");
+        assert_eq!(folder.listing, Some(Listing::RetroArchPlaylist));
+
+        let database = Database::in_memory()?;
+        let paths: Vec<_> = games(&folder.path, &playlists_dir, &database)?
+            .iter()
+            .map(|e| e.path().to_path_buf())
+            .collect();
+        assert_eq!(paths, [playlists_dir.join("Gone.gb"), game]);
+
+        fs::remove_dir_all(&playlists_dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+//! Launcher settings that are edited by hand, kept in `config/launcher.toml`. A missing file or
+//! setting falls back to the defaults.
+//!
+//! ```toml
+//! [exclude]
+//! # Files with these extensions aren't listed
+//! extensions = ["db", "sav", "srm"]
+//! # Nor are files and folders with these names. `*` matches any run of characters, `?` any one
+//! files = ["Imgs", "Guides", "*.state*"]
+//! # Nor are well-known BIOS files and folders named BIOS, unless this is turned off
+//! bios = false
+//!
+//! [playlists]
+//! # RetroArch playlists in this folder are listed as folders at the top of the games folder
+//! dir = "/mnt/SDCARD/RetroArch/.retroarch/playlists"
+//! ```
+//!
+//! The file is read again whenever the launcher regains focus, so that edits made over FTP take
+//! effect without restarting.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use anyhow::{Context, Result};
+use common::constants::{ALLIUM_CONFIG_LAUNCHER, RETROARCH_PLAYLISTS_DIR};
+use lazy_static::lazy_static;
+use log::debug;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::entry::names;
+
+/// Names of BIOS and firmware files that emulators look for next to the games, in lower case.
+const BIOS_FILES: &[&str] = &[
+    "5200.rom",
+    "7800 bios (u).rom",
+    "bios7.bin",
+    "bios9.bin",
+    "bios_cd_e.bin",
+    "bios_cd_j.bin",
+    "bios_cd_u.bin",
+    "dc_boot.bin",
+    "dc_flash.bin",
+    "disksys.rom",
+    "firmware.bin",
+    "gb_bios.bin",
+    "gba_bios.bin",
+    "gbc_bios.bin",
+    "lynxboot.img",
+    "mpr-17933.bin",
+    "neogeo.zip",
+    "panafz10.bin",
+    "ps1_rom.bin",
+    "psxonpsp660.bin",
+    "scph101.bin",
+    "scph1001.bin",
+    "scph5500.bin",
+    "scph5501.bin",
+    "scph5502.bin",
+    "scph7001.bin",
+    "sega_101.bin",
+    "sgb_bios.bin",
+    "syscard3.pce",
+];
+/// Extensions of files that only hold keys or firmware, such as `prod.keys`.
+const BIOS_EXTENSIONS: &[&str] = &["keys"];
+/// Names of folders that BIOS files are kept in.
+const BIOS_DIRS: &[&str] = &["BIOS", "bios"];
+
+lazy_static! {
+    /// Files that aren't listed. Entries are also made while counting games in the background, so
+    /// the active rules are kept here instead of being passed along.
+    static ref EXCLUDED: RwLock<Excluded> = RwLock::new(Exclude::default().compile());
+    /// Folder that RetroArch playlists are listed from.
+    static ref PLAYLISTS: RwLock<Playlists> = RwLock::new(Playlists::default());
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct LauncherConfig {
+    pub exclude: Exclude,
+    pub playlists: Playlists,
+}
+
+/// Files and folders in the games folder that aren't listed, as well as hidden files.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Exclude {
+    /// Extensions of files that aren't listed, without the dot. Case doesn't matter.
+    pub extensions: Vec<String>,
+    /// Names of files and folders that aren't listed, which may contain `*` and `?` wildcards.
+    pub files: Vec<String>,
+    /// Whether well-known BIOS files, and folders named BIOS, aren't listed.
+    pub bios: bool,
+}
+
+impl Default for Exclude {
+    fn default() -> Self {
+        Self {
+            extensions: ["db", "srm", "sav", "state"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            files: [
+                "Imgs",
+                "Guides",
+                "gamelist.xml",
+                "miyoogamelist.xml",
+                names::NAMES_FILE,
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            bios: true,
+        }
+    }
+}
+
+/// Where RetroArch playlists are imported from.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Playlists {
+    /// Folder with the `.lpl` playlists to list.
+    pub dir: PathBuf,
+}
+
+impl Default for Playlists {
+    fn default() -> Self {
+        Self {
+            dir: RETROARCH_PLAYLISTS_DIR.clone(),
+        }
+    }
+}
+
+impl LauncherConfig {
+    pub fn load() -> Result<Self> {
+        if !ALLIUM_CONFIG_LAUNCHER.exists() {
+            debug!("no launcher config, using defaults");
+            return Ok(Self::default());
+        }
+        let config = fs::read_to_string(ALLIUM_CONFIG_LAUNCHER.as_path())?;
+        Self::parse(&config)
+    }
+
+    pub fn parse(config: &str) -> Result<Self> {
+        toml::from_str(config).context("Failed to parse launcher.toml.")
+    }
+
+    /// Makes these the rules that entries are listed by. Returns whether they changed.
+    pub fn apply(&self) -> bool {
+        let mut playlists = PLAYLISTS.write().unwrap();
+        let changed = *playlists != self.playlists;
+        *playlists = self.playlists.clone();
+
+        let mut active = EXCLUDED.write().unwrap();
+        if active.source == self.exclude {
+            return changed;
+        }
+        *active = self.exclude.compile();
+        true
+    }
+}
+
+impl Exclude {
+    fn compile(&self) -> Excluded {
+        let files = self
+            .files
+            .iter()
+            .map(|pattern| {
+                let regex = regex::escape(pattern)
+                    .replace(r"\*", ".*")
+                    .replace(r"\?", ".");
+                Regex::new(&format!("^{regex}$")).unwrap()
+            })
+            .collect();
+        Excluded {
+            source: self.clone(),
+            files,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Excluded {
+    source: Exclude,
+    files: Vec<Regex>,
+}
+
+impl Excluded {
+    fn contains(&self, file_name: &str, extension: &str) -> bool {
+        self.source
+            .extensions
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case(extension))
+            || self.files.iter().any(|r| r.is_match(file_name))
+            || (self.source.bios && is_bios(file_name, extension))
+    }
+}
+
+/// Whether the file is a well-known BIOS file, or a folder that BIOS files are kept in.
+fn is_bios(file_name: &str, extension: &str) -> bool {
+    BIOS_DIRS.contains(&file_name)
+        || BIOS_EXTENSIONS
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case(extension))
+        || BIOS_FILES
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(file_name))
+}
+
+/// Folder that RetroArch playlists are listed from, by the active config.
+pub fn playlists_dir() -> PathBuf {
+    PLAYLISTS.read().unwrap().dir.clone()
+}
+
+/// Whether the file or folder named `file_name` isn't listed, by the active rules.
+pub fn is_excluded(file_name: &str, extension: &str) -> bool {
+    EXCLUDED.read().unwrap().contains(file_name, extension)
+}
+
+/// Whether the file at `path` is kept in a BIOS folder, so isn't listed even when it's reached
+/// without going through the folder, such as from a search.
+pub fn is_in_bios_dir(path: &Path) -> bool {
+    EXCLUDED.read().unwrap().source.bios
+        && path
+            .parent()
+            .and_then(Path::file_name)
+            .and_then(OsStr::to_str)
+            .is_some_and(|dir| BIOS_DIRS.contains(&dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclude() -> Result<()> {
+        let defaults = LauncherConfig::parse("")?;
+        assert_eq!(defaults, LauncherConfig::default());
+        let excluded = defaults.exclude.compile();
+        assert!(excluded.contains("Imgs", ""));
+        assert!(excluded.contains("games.db", "db"));
+        assert!(!excluded.contains("Imgs.zip", "zip"));
+
+        let config = LauncherConfig::parse(
+            r#"
+            [exclude]
+            extensions = ["db", "SRM"]
+            files = ["*.state*", "save?.dat"]
+            "#,
+        )?;
+        let excluded = config.exclude.compile();
+        assert!(excluded.contains("Tetris.srm", "srm"));
+        assert!(excluded.contains("Tetris.gb.state", "state"));
+        assert!(excluded.contains("Tetris.state12", "state12"));
+        assert!(excluded.contains("save1.dat", "dat"));
+        assert!(!excluded.contains("save10.dat", "dat"));
+        assert!(!excluded.contains("Tetris.gb", "gb"));
+        // Only what's listed, so the defaults can be dropped
+        assert!(!excluded.contains("Imgs", ""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_bios() -> Result<()> {
+        let excluded = LauncherConfig::default().exclude.compile();
+        assert!(excluded.contains("SCPH1001.BIN", "BIN"));
+        assert!(excluded.contains("gba_bios.bin", "bin"));
+        assert!(excluded.contains("prod.keys", "keys"));
+        assert!(excluded.contains("BIOS", ""));
+        assert!(excluded.contains("Tetris.srm", "srm"));
+        assert!(excluded.contains("Tetris.state", "state"));
+        assert!(!excluded.contains("Bios", ""));
+        assert!(!excluded.contains("Crash Bandicoot.bin", "bin"));
+
+        // Odd setups can list them after all
+        let config = LauncherConfig::parse(
+            r#"
+            [exclude]
+            bios = false
+            "#,
+        )?;
+        let excluded = config.exclude.compile();
+        assert!(!excluded.contains("scph1001.bin", "bin"));
+        assert!(!excluded.contains("BIOS", ""));
+        assert!(excluded.contains("Imgs", ""));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,396 @@
+//! Which games and folders of the library are shown to the user.
+//!
+//! Everything that lists games, whether by walking folders or by querying the database, goes
+//! through a `LibraryFilter`, so that a game hidden one way can't show up another way. A path is
+//! hidden if:
+//!
+//! 1. it is outside the active profile's games folder,
+//! 2. its name, or the name of a folder it is in, starts with a dot,
+//! 3. the user hid the game or a folder it is in, unless `include_hidden` is set.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use common::database::{Database, Game};
+use common::profile::Profile;
+
+use crate::entry::Entry;
+
+#[derive(Debug, Clone)]
+pub struct LibraryFilter {
+    games_dir: PathBuf,
+    hidden: HashSet<PathBuf>,
+    /// Shows games and folders the user hid, for views that let them be unhidden. Other rules
+    /// still apply.
+    pub include_hidden: bool,
+}
+
+impl LibraryFilter {
+    pub fn new(database: &Database, profile: &Profile) -> Result<Self> {
+        Ok(Self {
+            games_dir: profile.games_dir(),
+            hidden: database.hidden_games()?,
+            include_hidden: false,
+        })
+    }
+
+    pub fn games_dir(&self) -> &Path {
+        &self.games_dir
+    }
+
+    pub fn is_visible(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.games_dir) else {
+            return false;
+        };
+        if relative
+            .iter()
+            .any(|name| is_dot_file(&name.to_string_lossy()))
+        {
+            return false;
+        }
+        self.include_hidden || !self.is_hidden(path)
+    }
+
+    /// Whether the user hid the game or folder at `path`, or a folder it is in.
+    pub fn is_hidden(&self, path: &Path) -> bool {
+        path.ancestors()
+            .take_while(|path| path.starts_with(&self.games_dir))
+            .any(|path| self.hidden.contains(path))
+    }
+
+    /// Removes the games and folders that are hidden. Apps aren't part of the library, and only
+    /// follow the dot file rule.
+    pub fn retain(&self, entries: &mut Vec<Entry>) {
+        entries.retain(|entry| match entry {
+            Entry::App(_) => !entry
+                .path()
+                .file_name()
+                .is_some_and(|name| is_dot_file(&name.to_string_lossy())),
+            Entry::Directory(_) | Entry::Game(_) => self.is_visible(entry.path()),
+        });
+    }
+
+    /// Runs a database query for up to `limit` games, and removes the hidden ones. The query is
+    /// asked for as many more games as the user hid, so that hiding games doesn't shorten lists.
+    pub fn select_games(
+        &self,
+        limit: i64,
+        query: impl FnOnce(i64) -> Result<Vec<Game>>,
+    ) -> Result<Vec<Game>> {
+        let extra = if self.include_hidden {
+            0
+        } else {
+            self.hidden.len() as i64
+        };
+        let mut games = query(limit + extra)?;
+        games.retain(|game| self.is_visible(&game.path));
+        games.truncate(limit as usize);
+        Ok(games)
+    }
+}
+
+/// Whether a file or folder name is hidden by starting with a dot, like `.DS_Store`.
+pub fn is_dot_file(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use common::database::NewGame;
+    use common::test_utils::temp_dir;
+
+    use super::*;
+    use crate::consoles::ConsoleMapper;
+    use crate::entry::directory::Directory;
+    use crate::entry::Sort;
+    use crate::scraper;
+    use crate::view::games::GamesSort;
+    use crate::view::recents::RecentsSort;
+
+    /// Ways a game can be hidden from the library.
+    #[derive(Debug, Clone, Copy)]
+    enum Mechanism {
+        HiddenFlag,
+        HiddenFolder,
+        DotFile,
+        DotFolder,
+        OtherProfile,
+    }
+
+    const MECHANISMS: [Mechanism; 5] = [
+        Mechanism::HiddenFlag,
+        Mechanism::HiddenFolder,
+        Mechanism::DotFile,
+        Mechanism::DotFolder,
+        Mechanism::OtherProfile,
+    ];
+
+    struct Fixture {
+        dir: PathBuf,
+        database: Database,
+        console_mapper: ConsoleMapper,
+        profile: Profile,
+        visible: PathBuf,
+    }
+
+    impl Fixture {
+        /// A library with one visible game, and one game hidden by `mechanism`. Both games were
+        /// played and are favorites, so that they are in the recents lists.
+        fn new(mechanism: Mechanism) -> Self {
+            let dir = temp_dir(&format!("library-filter-{:?}", mechanism));
+            let profile = Profile {
+                name: "test".to_string(),
+                games_dir: Some(dir.join("Roms")),
+                restricted: false,
+            };
+            let database = Database::in_memory().unwrap();
+            let mut console_mapper = ConsoleMapper::new();
+            console_mapper
+                .parse_config(
+                    r#"
+                    [cores]
+                    [[consoles]]
+                    name = "Game Boy Advance"
+                    patterns = ["GBA"]
+                    extensions = ["gba"]
+                    thumbnails = "Nintendo - Game Boy Advance"
+                    "#,
+                )
+                .unwrap();
+
+            let visible = dir.join("Roms/GBA/Tetris.gba");
+            let hidden = match mechanism {
+                Mechanism::HiddenFlag => dir.join("Roms/GBA/Tetris Hidden.gba"),
+                Mechanism::HiddenFolder => dir.join("Roms/GBA/Hidden/Tetris Hidden.gba"),
+                Mechanism::DotFile => dir.join("Roms/GBA/.Tetris Hidden.gba"),
+                Mechanism::DotFolder => dir.join("Roms/GBA/.hidden/Tetris Hidden.gba"),
+                Mechanism::OtherProfile => dir.join("Other/GBA/Tetris Hidden.gba"),
+            };
+            for path in [&visible, &hidden] {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, b"rom").unwrap();
+                let name = path.file_stem().unwrap().to_string_lossy().to_string();
+                database
+                    .update_games(&[NewGame {
+                        name: name.clone(),
+                        path: path.clone(),
+                        image: None,
+                        core: None,
+                    }])
+                    .unwrap();
+                database.increment_play_count(&name, path, None).unwrap();
+                database.set_favorite(path, true).unwrap();
+            }
+            match mechanism {
+                Mechanism::HiddenFlag => database.set_hidden(&hidden, true).unwrap(),
+                Mechanism::HiddenFolder => database
+                    .set_hidden(&dir.join("Roms/GBA/Hidden"), true)
+                    .unwrap(),
+                Mechanism::DotFile | Mechanism::DotFolder | Mechanism::OtherProfile => {}
+            }
+
+            Self {
+                dir,
+                database,
+                console_mapper,
+                profile,
+                visible,
+            }
+        }
+
+        fn filter(&self) -> LibraryFilter {
+            LibraryFilter::new(&self.database, &self.profile).unwrap()
+        }
+
+        /// Games listed by a sort, in any of the folders the games are in.
+        fn listed<S: Sort>(&self, sort: S) -> Vec<PathBuf> {
+            sort.entries(&self.database, &self.console_mapper, &self.filter())
+                .unwrap()
+                .into_iter()
+                .filter(|entry| matches!(entry, Entry::Game(_)))
+                .map(|entry| entry.path().to_path_buf())
+                .collect()
+        }
+
+        fn folder(&self, path: &str) -> Directory {
+            Directory::new(self.dir.join(path))
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn test_hidden_games_are_excluded_everywhere() {
+        for mechanism in MECHANISMS {
+            let fixture = Fixture::new(mechanism);
+            let visible = vec![fixture.visible.clone()];
+
+            // Walking the folders, including the ones the user can't navigate to
+            for folder in [
+                "Roms/GBA",
+                "Roms/GBA/Hidden",
+                "Roms/GBA/.hidden",
+                "Other/GBA",
+            ] {
+                let folder = fixture.folder(folder);
+                if !folder.path.exists() {
+                    continue;
+                }
+                let listed = fixture.listed(GamesSort::Alphabetical(folder.clone()));
+                assert!(
+                    listed.iter().all(|path| *path == fixture.visible),
+                    "{mechanism:?} leaked into {}: {listed:?}",
+                    folder.path.display()
+                );
+                let listed = fixture.listed(GamesSort::Random(folder));
+                assert!(listed.iter().all(|path| *path == fixture.visible));
+            }
+
+            // The games index
+            for sort in [
+                RecentsSort::LastPlayed,
+                RecentsSort::MostPlayed,
+                RecentsSort::Random,
+                RecentsSort::Favorites,
+                RecentsSort::Search("Tetris".to_string()),
+            ] {
+                assert_eq!(
+                    fixture.listed(sort.clone()),
+                    visible,
+                    "{mechanism:?} leaked into {sort:?}"
+                );
+            }
+
+            // Picking a random game
+            let games = fixture
+                .folder("Roms")
+                .games(
+                    &fixture.database,
+                    &fixture.console_mapper,
+                    &fixture.filter(),
+                )
+                .unwrap();
+            assert_eq!(
+                games.into_iter().map(|g| g.path).collect::<Vec<_>>(),
+                visible,
+                "{mechanism:?} leaked into random games"
+            );
+
+            // Scraping
+            let queued = scraper::enqueue_missing_art(
+                &fixture.database,
+                &fixture.console_mapper,
+                &fixture.filter(),
+            )
+            .unwrap();
+            assert_eq!(queued, 1, "{mechanism:?} leaked into the scraper");
+        }
+    }
+
+    #[test]
+    fn test_include_hidden() {
+        let fixture = Fixture::new(Mechanism::HiddenFlag);
+        let mut filter = fixture.filter();
+        filter.include_hidden = true;
+        let games = filter
+            .select_games(10, |limit| fixture.database.select_last_played(limit))
+            .unwrap();
+        assert_eq!(games.len(), 2);
+
+        // Only the games the user hid are shown
+        let fixture = Fixture::new(Mechanism::DotFile);
+        let mut filter = fixture.filter();
+        filter.include_hidden = true;
+        let games = filter
+            .select_games(10, |limit| fixture.database.select_last_played(limit))
+            .unwrap();
+        assert_eq!(games.len(), 1);
+    }
+
+    #[test]
+    fn test_hidden_folder() {
+        let fixture = Fixture::new(Mechanism::HiddenFolder);
+        let filter = fixture.filter();
+        let folder = fixture.dir.join("Roms/GBA/Hidden");
+        assert!(filter.is_hidden(&folder));
+        assert!(filter.is_hidden(&folder.join("Tetris Hidden.gba")));
+        assert!(!filter.is_hidden(&fixture.visible));
+
+        // The folder itself isn't listed with the other games
+        let entries = GamesSort::Alphabetical(fixture.folder("Roms/GBA"))
+            .entries(&fixture.database, &fixture.console_mapper, &filter)
+            .unwrap();
+        assert!(entries.iter().all(|entry| entry.path() != folder));
+    }
+
+    #[test]
+    fn test_favorites_with_missing_files() {
+        let fixture = Fixture::new(Mechanism::HiddenFlag);
+        let deleted = fixture.dir.join("Roms/GBA/Deleted.gba");
+        fixture.database.set_favorite(&deleted, true).unwrap();
+        // Favorited, but never indexed
+        let unindexed = fixture.dir.join("Roms/GBA/Unindexed.gba");
+        fs::write(&unindexed, b"rom").unwrap();
+        fixture.database.set_favorite(&unindexed, true).unwrap();
+
+        assert_eq!(
+            fixture.listed(RecentsSort::Favorites),
+            vec![fixture.visible.clone(), unindexed]
+        );
+        assert!(fixture.database.is_favorite(&deleted).unwrap());
+    }
+
+    #[test]
+    fn test_recents_with_missing_files() {
+        let fixture = Fixture::new(Mechanism::HiddenFlag);
+        // Played, then deleted
+        let deleted = fixture.dir.join("Roms/GBA/Deleted Played.gba");
+        fixture
+            .database
+            .increment_play_count("Deleted Played", &deleted, None)
+            .unwrap();
+
+        assert_eq!(
+            fixture.listed(RecentsSort::Random),
+            vec![fixture.visible.clone()]
+        );
+        assert!(fixture
+            .database
+            .select_game(&deleted.display().to_string())
+            .unwrap()
+            .is_some());
+
+        // Taken off the recently played lists for good
+        assert_eq!(
+            fixture.listed(RecentsSort::LastPlayed),
+            vec![fixture.visible.clone()]
+        );
+        assert_eq!(
+            fixture
+                .database
+                .select_game(&deleted.display().to_string())
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_hiding_doesnt_shorten_lists() {
+        let fixture = Fixture::new(Mechanism::HiddenFlag);
+        let filter = fixture.filter();
+        let games = filter
+            .select_games(1, |limit| fixture.database.select_last_played(limit))
+            .unwrap();
+        assert_eq!(
+            games.into_iter().map(|game| game.path).collect::<Vec<_>>(),
+            vec![fixture.visible.clone()]
+        );
+    }
+}
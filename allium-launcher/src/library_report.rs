@@ -0,0 +1,401 @@
+//! Printable HTML report of the game library, to browse the collection on any computer.
+//!
+//! The report is a single self-contained file: the styles are inline, and box art is embedded as
+//! small JPEG thumbnails. Games are grouped by system, the top-level folders of the games
+//! directory, and hidden games are left out. The report is written one system at a time, so that
+//! progress can be shown between systems and memory use doesn't grow with the library.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use common::constants::ALLIUM_LIBRARY_REPORT;
+use common::database::{Database, Game};
+use common::format::format_play_time;
+use common::geom::Size;
+use common::view::thumbnail;
+use image::codecs::jpeg::JpegEncoder;
+use image::{ColorType, DynamicImage};
+use log::warn;
+
+use crate::library_filter::LibraryFilter;
+use crate::scraper;
+
+/// Box art is scaled down to fit within this many pixels.
+const THUMBNAIL_SIZE: u32 = 64;
+
+/// Most bytes of JPEG data embedded for one game's box art. Art that can't be made this small is
+/// left out.
+pub const THUMBNAIL_MAX_BYTES: usize = 4 * 1024;
+
+/// JPEG qualities tried in turn until the thumbnail fits within `THUMBNAIL_MAX_BYTES`.
+const THUMBNAIL_QUALITIES: [u8; 3] = [70, 50, 30];
+
+/// Rough size of a game's row in the report, besides its thumbnail.
+const ROW_BYTES: u64 = 200;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+h1{margin-bottom:0}\
+h2{border-bottom:2px solid #ccc;padding-bottom:.2em;margin-top:1.5em;break-after:avoid}\
+h2 small{color:#888;font-weight:normal}\
+table{border-collapse:collapse;width:100%}\
+tr{break-inside:avoid}\
+td{padding:4px 8px;border-bottom:1px solid #eee;vertical-align:middle}\
+td.art{width:64px;text-align:center}\
+td.art img{max-width:64px;max-height:64px}\
+td.time{text-align:right;white-space:nowrap;color:#555}\
+tr.completed td.name{font-weight:bold}\
+tr.completed td.status{color:#2a7}\
+tr.favorite td.name::before{content:'\\2605 ';color:#e90}";
+
+/// Text of the report, in the user's language.
+#[derive(Debug, Clone)]
+pub struct ReportText {
+    pub title: String,
+    pub completed: String,
+    pub favorite: String,
+    /// Heading for games directly in the games directory, outside any system folder.
+    pub other: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct System {
+    /// Folder of the system, or `None` for games outside any system folder.
+    dir: Option<PathBuf>,
+    count: usize,
+}
+
+pub struct LibraryReport<W: Write> {
+    writer: W,
+    root: PathBuf,
+    text: ReportText,
+    filter: LibraryFilter,
+    completed: HashSet<PathBuf>,
+    favorites: HashSet<PathBuf>,
+    systems: Vec<System>,
+    next: usize,
+    written: usize,
+}
+
+impl LibraryReport<BufWriter<File>> {
+    /// Starts a report to the SD card root.
+    pub fn to_file(
+        database: &Database,
+        filter: LibraryFilter,
+        root: &Path,
+        text: ReportText,
+    ) -> Result<Self> {
+        let file = File::create(ALLIUM_LIBRARY_REPORT.as_path())?;
+        Self::new(database, filter, root, text, BufWriter::new(file))
+    }
+}
+
+impl<W: Write> LibraryReport<W> {
+    /// Counts the visible games of each system, and writes the start of the report.
+    pub fn new(
+        database: &Database,
+        filter: LibraryFilter,
+        root: &Path,
+        text: ReportText,
+        mut writer: W,
+    ) -> Result<Self> {
+        let mut systems: Vec<System> = Vec::new();
+        database.for_each_game(|game| {
+            if !filter.is_visible(&game.path) {
+                return Ok(());
+            }
+            let dir = system_dir(root, &game.path);
+            match systems.iter_mut().find(|system| system.dir == dir) {
+                Some(system) => system.count += 1,
+                None => systems.push(System { dir, count: 1 }),
+            }
+            Ok(())
+        })?;
+        systems.sort_by(|a, b| {
+            a.dir
+                .is_none()
+                .cmp(&b.dir.is_none())
+                .then(a.dir.cmp(&b.dir))
+        });
+
+        write!(
+            writer,
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title><style>{STYLE}</style></head><body>\n<h1>{title}</h1>\n",
+            title = escape(&text.title),
+        )?;
+
+        Ok(Self {
+            writer,
+            root: root.to_path_buf(),
+            text,
+            filter,
+            completed: database.completed_games()?,
+            favorites: database.favorites()?.into_iter().collect(),
+            systems,
+            next: 0,
+            written: 0,
+        })
+    }
+
+    /// Number of games in the report.
+    pub fn total(&self) -> usize {
+        self.systems.iter().map(|system| system.count).sum()
+    }
+
+    /// Number of games written so far.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Rough size of the finished report, assuming half of the games have art.
+    pub fn estimated_size(&self) -> u64 {
+        self.total() as u64 * (ROW_BYTES + THUMBNAIL_MAX_BYTES as u64 / 2)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next > self.systems.len()
+    }
+
+    /// Writes the games of the next system, or the end of the report once every system is
+    /// written. Returns whether the report is done.
+    pub fn step(&mut self, database: &Database) -> Result<bool> {
+        if self.is_done() {
+            return Ok(true);
+        }
+        let Some(system) = self.systems.get(self.next).cloned() else {
+            self.writer.write_all(b"</body></html>\n")?;
+            self.writer.flush()?;
+            self.next += 1;
+            return Ok(true);
+        };
+        self.next += 1;
+
+        let name = match system.dir.as_deref().and_then(Path::file_name) {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => self.text.other.clone(),
+        };
+        writeln!(
+            self.writer,
+            "<section><h2>{} <small>({})</small></h2><table>",
+            escape(&name),
+            system.count
+        )?;
+
+        let dir = system.dir.as_deref().unwrap_or(&self.root).to_path_buf();
+        database.for_each_game_in(&dir, |game| {
+            if !self.filter.is_visible(&game.path)
+                || system_dir(&self.root, &game.path) != system.dir
+            {
+                return Ok(());
+            }
+            self.write_game(&game)?;
+            self.written += 1;
+            Ok(())
+        })?;
+
+        self.writer.write_all(b"</table></section>\n")?;
+        Ok(false)
+    }
+
+    fn write_game(&mut self, game: &Game) -> Result<()> {
+        let completed = self.completed.contains(&game.path);
+        let favorite = self.favorites.contains(&game.path);
+
+        let mut classes = Vec::new();
+        let mut status = Vec::new();
+        if completed {
+            classes.push("completed");
+            status.push(escape(&self.text.completed));
+        }
+        if favorite {
+            classes.push("favorite");
+            status.push(escape(&self.text.favorite));
+        }
+
+        if classes.is_empty() {
+            write!(self.writer, "<tr><td class=\"art\">")?;
+        } else {
+            write!(
+                self.writer,
+                "<tr class=\"{}\"><td class=\"art\">",
+                classes.join(" ")
+            )?;
+        }
+        if let Some(jpeg) = art(game) {
+            write!(
+                self.writer,
+                "<img alt=\"\" src=\"data:image/jpeg;base64,{}\">",
+                STANDARD.encode(jpeg)
+            )?;
+        }
+        writeln!(
+            self.writer,
+            "</td><td class=\"name\">{}</td><td class=\"status\">{}</td><td class=\"time\">{}</td></tr>",
+            escape(&game.name),
+            status.join(", "),
+            format_play_time(game.play_time),
+        )?;
+        Ok(())
+    }
+}
+
+/// Top-level folder of the games directory that `path` is in, or `None` if it's directly in it.
+fn system_dir(root: &Path, path: &Path) -> Option<PathBuf> {
+    let relative = path.strip_prefix(root).ok()?;
+    let mut components = relative.components();
+    let first = components.next()?;
+    components.next()?;
+    Some(root.join(first))
+}
+
+/// The game's box art as a JPEG thumbnail, if it has any that fits.
+fn art(game: &Game) -> Option<Vec<u8>> {
+    let path = game
+        .image
+        .clone()
+        .or_else(|| scraper::image_path(&game.path).filter(|path| path.exists()))?;
+    let image = thumbnail(&path, Size::new(THUMBNAIL_SIZE, THUMBNAIL_SIZE))?;
+    let rgb = DynamicImage::ImageRgba8(image.as_ref().clone()).to_rgb8();
+
+    for quality in THUMBNAIL_QUALITIES {
+        let mut jpeg = Vec::new();
+        if let Err(e) = JpegEncoder::new_with_quality(&mut jpeg, quality).encode(
+            rgb.as_raw(),
+            rgb.width(),
+            rgb.height(),
+            ColorType::Rgb8,
+        ) {
+            warn!("failed to encode art of {}: {}", game.path.display(), e);
+            return None;
+        }
+        if jpeg.len() <= THUMBNAIL_MAX_BYTES {
+            return Some(jpeg);
+        }
+    }
+    None
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::Duration;
+    use common::database::NewGame;
+    use common::profile::Profile;
+    use common::test_utils::temp_dir;
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    fn text() -> ReportText {
+        ReportText {
+            title: "My Library".to_string(),
+            completed: "Completed".to_string(),
+            favorite: "Favorite".to_string(),
+            other: "Other".to_string(),
+        }
+    }
+
+    fn report(database: &Database, root: &Path) -> Result<String> {
+        let profile = Profile {
+            name: "test".to_string(),
+            games_dir: Some(root.to_path_buf()),
+            restricted: false,
+        };
+        let filter = LibraryFilter::new(database, &profile)?;
+        let mut report = LibraryReport::new(database, filter, root, text(), Vec::new())?;
+        let mut steps = 0;
+        while !report.step(database)? {
+            steps += 1;
+            assert!(report.written() <= report.total());
+        }
+        assert_eq!(report.written(), report.total());
+        assert!(report.is_done());
+        // One step per system
+        assert_eq!(steps, 3);
+        Ok(String::from_utf8(report.writer)?)
+    }
+
+    #[test]
+    fn test_report() -> Result<()> {
+        let dir = temp_dir("library-report");
+        let root = dir.join("Roms");
+        fs::create_dir_all(root.join("GBA/Imgs"))?;
+        // Noisy art, which compresses badly
+        let art = root.join("GBA/Imgs/Zelda.png");
+        RgbImage::from_fn(400, 400, |x, y| {
+            Rgb([(x * 7 + y * 13) as u8, (x * y) as u8, (x ^ y) as u8])
+        })
+        .save(&art)?;
+
+        let database = Database::in_memory()?;
+        let game = |path: &str, name: &str, image: Option<PathBuf>| NewGame {
+            name: name.to_string(),
+            path: root.join(path),
+            image,
+            core: None,
+        };
+        database.update_games(&[
+            game("GBA/Zelda.gba", "Zelda <Minish Cap>", Some(art)),
+            game("GBA/Metroid.gba", "Metroid", None),
+            game("GBA/.Hidden.gba", "Dot File", None),
+            game("GB/Tetris.gb", "Tetris", None),
+            game("GB/Secret.gb", "Secret", None),
+            game("Loose.nes", "Loose", None),
+        ])?;
+        database.set_hidden(&root.join("GB/Secret.gb"), true)?;
+        database.set_completed(&root.join("GB/Tetris.gb"), true)?;
+        database.set_favorite(&root.join("GB/Tetris.gb"), true)?;
+        database.set_favorite(&root.join("GBA/Metroid.gba"), true)?;
+        database.add_play_time(&root.join("GBA/Metroid.gba"), Duration::minutes(95))?;
+
+        let html = report(&database, &root)?;
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.ends_with("</body></html>\n"));
+        assert!(html.contains("<h1>My Library</h1>"));
+        // Systems in order, with games outside of them last
+        let gb = html.find("<h2>GB <small>(1)</small>").unwrap();
+        let gba = html.find("<h2>GBA <small>(2)</small>").unwrap();
+        let other = html.find("<h2>Other <small>(1)</small>").unwrap();
+        assert!(gb < gba && gba < other);
+
+        assert!(html.contains("Zelda &lt;Minish Cap&gt;"));
+        assert!(html.contains("<td class=\"time\">1h 35m</td>"));
+        assert!(html.contains("<tr class=\"completed favorite\"><td class=\"art\"></td><td class=\"name\">Tetris</td><td class=\"status\">Completed, Favorite</td>"));
+        assert!(html.contains("<tr class=\"favorite\"><td class=\"art\"></td><td class=\"name\">Metroid</td><td class=\"status\">Favorite</td>"));
+        assert!(html.contains("<tr><td class=\"art\"><img"));
+        assert!(!html.contains("Secret"));
+        assert!(!html.contains("Dot File"));
+
+        // The art is embedded within the size budget
+        let start = html.find("base64,").unwrap() + "base64,".len();
+        let end = start + html[start..].find('"').unwrap();
+        let jpeg = STANDARD.decode(&html[start..end])?;
+        assert!(jpeg.len() <= THUMBNAIL_MAX_BYTES);
+        assert_eq!(html.matches("<img").count(), 1);
+
+        assert!(html.len() < 4 * 1024 + 4 * THUMBNAIL_MAX_BYTES);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
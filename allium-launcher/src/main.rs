@@ -4,19 +4,38 @@
 
 mod allium_launcher;
 mod consoles;
+mod core_config;
 mod entry;
+mod launcher_config;
+mod library_filter;
+mod library_report;
+mod scraper;
+mod setup;
 mod view;
 
+use std::env;
+
 use anyhow::Result;
 
 use allium_launcher::AlliumLauncher;
+use common::database::Database;
+use common::library_export::export_library_to_file;
 use common::platform::{DefaultPlatform, Platform};
+use common::profile::Profiles;
 use simple_logger::SimpleLogger;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     SimpleLogger::new().init().unwrap();
 
+    // Headless export for companion tools, e.g. over SSH
+    if env::args().nth(1).as_deref() == Some("--export-library") {
+        let games_dir = Profiles::load()?.active().games_dir();
+        let path = export_library_to_file(&Database::new()?, &games_dir)?;
+        println!("{}", path.display());
+        return Ok(());
+    }
+
     let platform = DefaultPlatform::new()?;
     let mut app = AlliumLauncher::new(platform)?;
     app.run_event_loop().await?;
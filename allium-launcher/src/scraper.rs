@@ -0,0 +1,611 @@
+//! Downloads missing box art from the libretro thumbnails server.
+//!
+//! Work is queued in the database so that scraping resumes after the launcher restarts. A
+//! background worker drains the queue whenever WiFi is connected, backing off on rate limits and
+//! server errors until a job has failed `MAX_RETRIES` times.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use common::database::{Database, ScrapeJob};
+use common::led;
+use common::wifi;
+use common::write_activity;
+use log::{debug, error, info, warn};
+
+use crate::consoles::ConsoleMapper;
+use crate::entry::art_index;
+use crate::entry::lazy_image::LazyImage;
+use crate::entry::short_name;
+use crate::library_filter::LibraryFilter;
+
+const THUMBNAILS_URL: &str = "https://thumbnails.libretro.com";
+
+/// Number of failed attempts after which a job is given up on.
+pub const MAX_RETRIES: i64 = 5;
+
+/// Minimum time between two requests.
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Backoff after the first retryable failure, doubled for every further failure.
+const BASE_BACKOFF: Duration = Duration::from_secs(5);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+/// How often the worker checks whether WiFi has been connected.
+const WIFI_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Largest image that will be downloaded.
+const MAX_IMAGE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Region tags tried in order when there is no thumbnail named after the game's file, as the
+/// thumbnails are named after No-Intro and Redump sets.
+const REGION_FALLBACKS: [&str; 4] = ["USA", "USA, Europe", "Europe", "World"];
+
+static IS_WORKER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchError {
+    /// The server has no image for this game.
+    NotFound,
+    /// Rate limited, server or network error. Worth trying again later.
+    Retryable(String),
+    /// Any other error.
+    Failed(String),
+}
+
+pub trait Fetcher {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError>;
+}
+
+pub struct HttpFetcher {
+    agent: ureq::Agent,
+}
+
+impl HttpFetcher {
+    pub fn new() -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+}
+
+impl Fetcher for HttpFetcher {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        match self.agent.get(url).call() {
+            Ok(response) => {
+                let mut bytes = Vec::new();
+                response
+                    .into_reader()
+                    .take(MAX_IMAGE_SIZE)
+                    .read_to_end(&mut bytes)
+                    .map_err(|e| FetchError::Retryable(e.to_string()))?;
+                Ok(bytes)
+            }
+            Err(ureq::Error::Status(404, _)) => Err(FetchError::NotFound),
+            Err(ureq::Error::Status(code @ (429 | 500..=599), _)) => {
+                Err(FetchError::Retryable(format!("HTTP {}", code)))
+            }
+            Err(ureq::Error::Status(code, _)) => Err(FetchError::Failed(format!("HTTP {}", code))),
+            Err(ureq::Error::Transport(e)) => Err(FetchError::Retryable(e.to_string())),
+        }
+    }
+}
+
+/// Processes the scrape queue one job at a time.
+pub struct Scraper<F: Fetcher> {
+    database: Database,
+    fetcher: F,
+}
+
+impl<F: Fetcher> Scraper<F> {
+    pub fn new(database: Database, fetcher: F) -> Self {
+        Self { database, fetcher }
+    }
+
+    /// Attempts the next job if it is due at `now` (a unix timestamp). Returns how long to wait
+    /// before the next step, or `None` if the queue is empty.
+    pub fn step(&mut self, now: i64) -> Result<Option<Duration>> {
+        let Some(job) = self.database.next_scrape_job()? else {
+            return Ok(None);
+        };
+
+        if job.next_attempt > now {
+            return Ok(Some(Duration::from_secs((job.next_attempt - now) as u64)));
+        }
+
+        debug!("scraping {}", job.url);
+        let mut result = self.fetcher.fetch(&job.url);
+        for url in &job.fallback_urls {
+            if !matches!(result, Err(FetchError::NotFound)) {
+                break;
+            }
+            debug!("not found, trying {}", url);
+            result = self.fetcher.fetch(url);
+        }
+        match result {
+            Ok(bytes) => match save_image(&job.path, &bytes) {
+                Ok(path) => {
+                    info!("saved box art to {}", path.display());
+                    self.database.complete_scrape_job(&job.path)?;
+                }
+                Err(e) => self.fail(&job, &e.to_string())?,
+            },
+            Err(FetchError::NotFound) => self.fail(&job, "not found")?,
+            Err(FetchError::Failed(e)) => self.fail(&job, &e)?,
+            Err(FetchError::Retryable(e)) => {
+                if job.retries + 1 >= MAX_RETRIES {
+                    self.fail(&job, &e)?;
+                } else {
+                    let backoff = backoff(job.retries, rand::random());
+                    warn!(
+                        "failed to scrape {}, retrying in {:?}: {}",
+                        job.path.display(),
+                        backoff,
+                        e
+                    );
+                    self.database.retry_scrape_job(
+                        &job.path,
+                        &e,
+                        now + backoff.as_secs() as i64,
+                    )?;
+                }
+            }
+        }
+
+        Ok(Some(REQUEST_INTERVAL))
+    }
+
+    fn fail(&self, job: &ScrapeJob, error: &str) -> Result<()> {
+        warn!("giving up on scraping {}: {}", job.path.display(), error);
+        self.database.fail_scrape_job(&job.path, error)
+    }
+}
+
+/// Exponential backoff after `retries` failed attempts, scaled by a `jitter` between 0 and 1 so
+/// that retries don't all land at once.
+fn backoff(retries: i64, jitter: f64) -> Duration {
+    let backoff = BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(retries.clamp(0, 16) as u32))
+        .min(MAX_BACKOFF);
+    backoff.mul_f64(0.5 + jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+/// Where the box art for a game is saved, next to the game in an `Imgs` folder.
+pub fn image_path(game: &Path) -> Option<PathBuf> {
+    let mut path = game.parent()?.join("Imgs");
+    path.push(game.file_stem()?);
+    path.set_extension("png");
+    Some(path)
+}
+
+fn save_image(game: &Path, bytes: &[u8]) -> Result<PathBuf> {
+    image::guess_format(bytes).context("not an image")?;
+    let path = image_path(game).context("invalid game path")?;
+    let _guard = write_activity::begin("box art download");
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(&path, bytes)?;
+    art_index::add_art(&path);
+    Ok(path)
+}
+
+/// Builds the thumbnail URLs to try for a game in order: the game's file name, then its cleaned
+/// up name with each of the usual region tags.
+fn thumbnail_urls(system: &str, game: &Path) -> Option<Vec<String>> {
+    let name = game.file_stem()?.to_str()?;
+    let mut names = vec![name.to_string()];
+    let clean = short_name(name);
+    if !clean.is_empty() {
+        for region in REGION_FALLBACKS {
+            let fallback = format!("{} ({})", clean, region);
+            if !names.contains(&fallback) {
+                names.push(fallback);
+            }
+        }
+    }
+    Some(
+        names
+            .iter()
+            .map(|name| thumbnail_url(system, name))
+            .collect(),
+    )
+}
+
+/// Builds the thumbnail URL for a name, following the libretro thumbnail naming rules.
+fn thumbnail_url(system: &str, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '&' | '*' | '/' | ':' | '`' | '<' | '>' | '?' | '\\' | '|' | '"' => '_',
+            c => c,
+        })
+        .collect();
+    format!(
+        "{}/{}/Named_Boxarts/{}.png",
+        THUMBNAILS_URL,
+        encode(system),
+        encode(&name)
+    )
+}
+
+fn encode(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('#', "%23")
+}
+
+/// Queues every visible game without box art on a console that has thumbnails. Returns the
+/// number of queued games.
+pub fn enqueue_missing_art(
+    database: &Database,
+    console_mapper: &ConsoleMapper,
+    filter: &LibraryFilter,
+) -> Result<usize> {
+    let mut count = 0;
+    for game in database.select_all_games()? {
+        if !filter.is_visible(&game.path) {
+            continue;
+        }
+        if LazyImage::from_path(&game.path, game.image.clone())
+            .image()
+            .is_some()
+        {
+            continue;
+        }
+        let Some(urls) = console_thumbnail_urls(console_mapper, &game.path) else {
+            continue;
+        };
+        database.enqueue_scrape(&game.path, &urls)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Queues the visible games in `folder` and its subfolders on consoles that have thumbnails,
+/// including games that failed before. Games that already have box art are skipped unless
+/// `redownload` is set. Returns the queued games.
+pub fn enqueue_folder(
+    database: &Database,
+    console_mapper: &ConsoleMapper,
+    filter: &LibraryFilter,
+    folder: &Path,
+    redownload: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut queued = Vec::new();
+    for game in database.select_all_games()? {
+        if !game.path.starts_with(folder) || !filter.is_visible(&game.path) {
+            continue;
+        }
+        if !redownload
+            && LazyImage::from_path(&game.path, game.image.clone())
+                .image()
+                .is_some()
+        {
+            continue;
+        }
+        let Some(urls) = console_thumbnail_urls(console_mapper, &game.path) else {
+            continue;
+        };
+        database.requeue_scrape(&game.path, &urls)?;
+        queued.push(game.path);
+    }
+    Ok(queued)
+}
+
+/// Whether art for `game` can be scraped: its console has thumbnails on the server.
+pub fn can_scrape(console_mapper: &ConsoleMapper, game: &Path) -> bool {
+    console_thumbnail_urls(console_mapper, game).is_some()
+}
+
+/// Queues a single game, even if it already has box art.
+pub fn enqueue_game(
+    database: &Database,
+    console_mapper: &ConsoleMapper,
+    game: &Path,
+) -> Result<()> {
+    let urls = console_thumbnail_urls(console_mapper, game)
+        .context("no thumbnails are available for this console")?;
+    database.enqueue_scrape(game, &urls)
+}
+
+fn console_thumbnail_urls(console_mapper: &ConsoleMapper, game: &Path) -> Option<Vec<String>> {
+    console_mapper
+        .get_console(game)
+        .and_then(|console| console.thumbnails.as_deref())
+        .and_then(|system| thumbnail_urls(system, game))
+}
+
+/// Starts the background worker if it isn't already running. The worker stops once the queue is
+/// empty.
+pub fn spawn_worker() {
+    if IS_WORKER_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    thread::spawn(|| {
+        if let Err(e) = run_worker() {
+            error!("scraper worker failed: {}", e);
+        }
+        IS_WORKER_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+fn run_worker() -> Result<()> {
+    let mut scraper = Scraper::new(Database::new()?, HttpFetcher::new());
+    loop {
+        if wifi::ip_address().is_none() {
+            debug!("wifi is not connected, waiting to scrape");
+            thread::sleep(WIFI_POLL_INTERVAL);
+            continue;
+        }
+
+        match scraper.step(Utc::now().timestamp())? {
+            Some(wait) => thread::sleep(wait),
+            None => {
+                info!("scrape queue is empty, stopping worker");
+                led::notify_task_completed();
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    use common::database::{ScrapeProgress, ScrapeStatus};
+    use common::test_utils::temp_dir;
+
+    use super::*;
+
+    /// Replies to requests in order, then with `Retryable` once the replies run out.
+    struct MockFetcher {
+        replies: RefCell<VecDeque<Result<Vec<u8>, FetchError>>>,
+        requests: RefCell<Vec<String>>,
+    }
+
+    impl MockFetcher {
+        fn new(replies: Vec<Result<Vec<u8>, FetchError>>) -> Self {
+            Self {
+                replies: RefCell::new(replies.into()),
+                requests: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl Fetcher for &MockFetcher {
+        fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+            self.requests.borrow_mut().push(url.to_string());
+            self.replies
+                .borrow_mut()
+                .pop_front()
+                .unwrap_or_else(|| Err(FetchError::Retryable("offline".to_string())))
+        }
+    }
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    fn setup(name: &str, games: &[&str]) -> Result<(Database, PathBuf)> {
+        let dir = temp_dir(&format!("scraper-{}", name));
+
+        let database = Database::in_memory()?;
+        for game in games {
+            let path = dir.join(game);
+            database.enqueue_scrape(&path, &[format!("http://test/{}", game)])?;
+        }
+        Ok((database, dir))
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0, 1.0), BASE_BACKOFF);
+        assert_eq!(backoff(0, 0.0), BASE_BACKOFF / 2);
+        assert_eq!(backoff(2, 1.0), BASE_BACKOFF * 4);
+        assert_eq!(backoff(100, 1.0), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_thumbnail_url() {
+        assert_eq!(
+            thumbnail_urls(
+                "Nintendo - Game Boy",
+                Path::new("/Roms/GB/Link's Awakening: DX #1 (USA).gb")
+            )
+            .unwrap()
+            .first()
+            .map(String::as_str),
+            Some("https://thumbnails.libretro.com/Nintendo%20-%20Game%20Boy/Named_Boxarts/Link's%20Awakening_%20DX%20%231%20(USA).png")
+        );
+    }
+
+    #[test]
+    fn test_region_fallbacks() {
+        let urls =
+            thumbnail_urls("Nintendo - Game Boy", Path::new("/Roms/GB/01. Tetris.gb")).unwrap();
+        assert_eq!(
+            urls,
+            [
+                "01.%20Tetris",
+                "Tetris%20(USA)",
+                "Tetris%20(USA,%20Europe)",
+                "Tetris%20(Europe)",
+                "Tetris%20(World)",
+            ]
+            .map(|name| format!("https://thumbnails.libretro.com/Nintendo%20-%20Game%20Boy/Named_Boxarts/{name}.png"))
+        );
+
+        // The file's own tags aren't tried twice
+        let urls =
+            thumbnail_urls("Nintendo - Game Boy", Path::new("/Roms/GB/Tetris (USA).gb")).unwrap();
+        assert_eq!(urls.len(), 4);
+        assert!(urls[0].ends_with("/Tetris%20(USA).png"));
+        assert!(urls[1].ends_with("/Tetris%20(USA,%20Europe).png"));
+    }
+
+    #[test]
+    fn test_fallbacks_are_tried_in_order() -> Result<()> {
+        let (database, dir) = setup("fallbacks", &[])?;
+        let (a, b) = (dir.join("A.gb"), dir.join("B.gb"));
+        let urls = |name: &str| {
+            ["", " (USA)", " (Europe)"].map(|region| format!("http://test/{name}{region}"))
+        };
+        database.enqueue_scrape(&a, &urls("A"))?;
+        database.enqueue_scrape(&b, &urls("B"))?;
+        let fetcher = MockFetcher::new(vec![
+            Err(FetchError::NotFound),
+            Ok(PNG.to_vec()),
+            Err(FetchError::NotFound),
+            Err(FetchError::NotFound),
+            Err(FetchError::NotFound),
+        ]);
+        let mut scraper = Scraper::new(database.clone(), &fetcher);
+
+        while scraper.step(0)?.is_some() {}
+
+        assert_eq!(
+            *fetcher.requests.borrow(),
+            vec![
+                "http://test/A",
+                "http://test/A (USA)",
+                "http://test/B",
+                "http://test/B (USA)",
+                "http://test/B (Europe)",
+            ]
+        );
+        assert!(dir.join("Imgs/A.png").is_file());
+        assert_eq!(database.scrape_status(&b)?, Some(ScrapeStatus::Failed));
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_saved_images_are_found() -> Result<()> {
+        let dir = temp_dir("scraper-found");
+        let game = dir.join("GBA/Hacks/Game (USA).gba");
+        fs::create_dir_all(game.parent().unwrap())?;
+        fs::write(&game, [])?;
+
+        let image = image_path(&game).unwrap();
+        assert_eq!(image, dir.join("GBA/Hacks/Imgs/Game (USA).png"));
+        fs::create_dir_all(image.parent().unwrap())?;
+        fs::write(&image, PNG)?;
+        assert_eq!(
+            LazyImage::Unknown(game).find_with(&art_index::ArtIndex::new(), &dir),
+            Some(image.as_path())
+        );
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_queue_drains_and_saves_images() -> Result<()> {
+        let (database, dir) = setup("drain", &["A.gb", "B.gb", "C.gb"])?;
+        let fetcher = MockFetcher::new(vec![
+            Ok(PNG.to_vec()),
+            Err(FetchError::NotFound),
+            Ok(b"<html>".to_vec()),
+        ]);
+        let mut scraper = Scraper::new(database.clone(), &fetcher);
+
+        while scraper.step(0)?.is_some() {}
+
+        assert!(dir.join("Imgs/A.png").is_file());
+        assert!(!dir.join("Imgs/C.png").exists());
+        assert_eq!(
+            database.scrape_progress()?,
+            ScrapeProgress {
+                pending: 0,
+                completed: 1,
+                failed: 2,
+            }
+        );
+        let failed = database.failed_scrape_jobs()?;
+        assert_eq!(failed[0].error.as_deref(), Some("not found"));
+        assert_eq!(failed[1].error.as_deref(), Some("not an image"));
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_retries_back_off_then_fail() -> Result<()> {
+        let (database, dir) = setup("retry", &["A.gb"])?;
+        let fetcher = MockFetcher::new(Vec::new());
+        let mut scraper = Scraper::new(database.clone(), &fetcher);
+
+        let mut now = 0;
+        while let Some(wait) = scraper.step(now)? {
+            let job = database.next_scrape_job()?;
+            if let Some(job) = job {
+                if job.retries > 0 {
+                    assert!(job.next_attempt > now, "job should back off");
+                }
+            }
+            now += wait.as_secs() as i64;
+        }
+
+        assert_eq!(fetcher.requests.borrow().len(), MAX_RETRIES as usize);
+        let failed = database.failed_scrape_jobs()?;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].retries, MAX_RETRIES);
+        assert_eq!(failed[0].error.as_deref(), Some("offline"));
+
+        database.retry_failed_scrape_jobs()?;
+        assert_eq!(database.scrape_progress()?.pending, 1);
+        assert_eq!(database.next_scrape_job()?.unwrap().retries, 0);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_resume_after_interruption() -> Result<()> {
+        let (database, dir) = setup("resume", &["A.gb", "B.gb", "C.gb"])?;
+
+        // The first worker gets through one game and hits a server error, then is stopped
+        let fetcher = MockFetcher::new(vec![
+            Ok(PNG.to_vec()),
+            Err(FetchError::Retryable("HTTP 503".to_string())),
+        ]);
+        let mut scraper = Scraper::new(database.clone(), &fetcher);
+        scraper.step(0)?;
+        scraper.step(0)?;
+        drop(scraper);
+
+        assert_eq!(database.scrape_progress()?.pending, 2);
+
+        // A new worker picks up where the last one left off, trying the failed game last
+        let fetcher = MockFetcher::new(vec![Ok(PNG.to_vec()), Ok(PNG.to_vec())]);
+        let mut scraper = Scraper::new(database.clone(), &fetcher);
+        let mut now = 0;
+        while let Some(wait) = scraper.step(now)? {
+            now += wait.as_secs() as i64;
+        }
+
+        assert_eq!(
+            *fetcher.requests.borrow(),
+            vec!["http://test/C.gb", "http://test/B.gb"]
+        );
+        assert_eq!(database.scrape_progress()?.completed, 3);
+        assert!(dir.join("Imgs/B.png").is_file());
+
+        // Clearing the queue forgets everything
+        database.clear_scrape_queue()?;
+        assert_eq!(database.scrape_progress()?, ScrapeProgress::default());
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
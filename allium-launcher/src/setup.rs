@@ -0,0 +1,233 @@
+//! The first-boot setup wizard's steps, and whether it still has to be shown.
+//!
+//! The wizard is shown when the launcher starts without any state left by a previous run, and
+//! until it has been finished or skipped, which is kept in `state/setup.json`. Resetting it from
+//! the settings shows it again.
+
+use std::fs::{self, File};
+use std::path::Path;
+
+use anyhow::Result;
+use common::constants::{
+    ALLIUM_DISPLAY_SETTINGS, ALLIUM_LAUNCHER_STATE, ALLIUM_LOCALE_SETTINGS, ALLIUM_SETUP_STATE,
+    ALLIUM_STYLESHEET,
+};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::consoles::ConsoleMapper;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetupState {
+    /// Whether the wizard was finished or skipped.
+    #[serde(default)]
+    pub completed: bool,
+}
+
+impl SetupState {
+    /// The saved state, or `None` if the wizard never ran.
+    pub fn load() -> Result<Option<Self>> {
+        if ALLIUM_SETUP_STATE.exists() {
+            debug!("found setup state, loading from file");
+            let file = File::open(ALLIUM_SETUP_STATE.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(Some(json));
+            }
+            warn!("failed to read setup state, removing");
+            fs::remove_file(ALLIUM_SETUP_STATE.as_path())?;
+        }
+        Ok(None)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_SETUP_STATE.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+
+    /// Whether to show the wizard on this boot.
+    pub fn is_needed() -> Result<bool> {
+        let has_prior_state = [
+            ALLIUM_LAUNCHER_STATE.as_path(),
+            ALLIUM_STYLESHEET.as_path(),
+            ALLIUM_LOCALE_SETTINGS.as_path(),
+            ALLIUM_DISPLAY_SETTINGS.as_path(),
+        ]
+        .iter()
+        .any(|path| path.exists());
+        Ok(needs_setup(Self::load()?, has_prior_state))
+    }
+}
+
+/// Whether to show the wizard, given its saved state and whether a previous run left any state.
+/// Devices that were set up before the wizard existed don't get it, unless it was reset.
+fn needs_setup(state: Option<SetupState>, has_prior_state: bool) -> bool {
+    match state {
+        Some(state) => !state.completed,
+        None => !has_prior_state,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Language,
+    Clock,
+    Wifi,
+    Theme,
+    Folders,
+}
+
+impl Step {
+    /// The wizard's steps in order. Wi-Fi is only set up on devices that have it.
+    pub fn all(has_wifi: bool) -> Vec<Step> {
+        [
+            Step::Language,
+            Step::Clock,
+            Step::Wifi,
+            Step::Theme,
+            Step::Folders,
+        ]
+        .into_iter()
+        .filter(|step| has_wifi || *step != Step::Wifi)
+        .collect()
+    }
+}
+
+/// The steps the user went through, the current one on top, so that going back returns to the
+/// step before it.
+#[derive(Debug, Clone)]
+pub struct ScreenStack {
+    steps: Vec<Step>,
+    stack: Vec<usize>,
+}
+
+impl ScreenStack {
+    pub fn new(steps: Vec<Step>) -> Self {
+        assert!(!steps.is_empty(), "setup needs at least one step");
+        Self {
+            steps,
+            stack: vec![0],
+        }
+    }
+
+    pub fn current(&self) -> Step {
+        self.steps[self.index()]
+    }
+
+    /// Position of the current step, from 0.
+    pub fn index(&self) -> usize {
+        *self.stack.last().unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Goes to the next step. Returns false if this was the last one, i.e. setup is complete.
+    pub fn next(&mut self) -> bool {
+        let next = self.index() + 1;
+        if next < self.steps.len() {
+            self.stack.push(next);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Goes back to the previous step. Returns false on the first step.
+    pub fn back(&mut self) -> bool {
+        if self.stack.len() > 1 {
+            self.stack.pop();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Creates an empty folder in `games_dir` for each console that doesn't have one yet, so that the
+/// user can see where to copy games. Returns how many folders were created.
+pub fn create_folder_skeleton(games_dir: &Path, console_mapper: &ConsoleMapper) -> Result<usize> {
+    let mut created = 0;
+    for (_, folder) in console_mapper.console_folders() {
+        let path = games_dir.join(folder);
+        if !path.exists() {
+            fs::create_dir_all(&path)?;
+            created += 1;
+        }
+    }
+    debug!("created {} console folders", created);
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use common::test_utils::temp_dir;
+
+    use super::*;
+
+    #[test]
+    fn test_needs_setup() {
+        // First boot
+        assert!(needs_setup(None, false));
+        // Set up before the wizard existed
+        assert!(!needs_setup(None, true));
+        // Finished or skipped
+        assert!(!needs_setup(Some(SetupState { completed: true }), false));
+        // Reset from the settings, or interrupted
+        assert!(needs_setup(Some(SetupState { completed: false }), true));
+    }
+
+    #[test]
+    fn test_screen_stack() {
+        assert_eq!(
+            Step::all(false),
+            vec![Step::Language, Step::Clock, Step::Theme, Step::Folders]
+        );
+
+        let mut stack = ScreenStack::new(Step::all(true));
+        assert_eq!(stack.len(), 5);
+        assert!(!stack.back());
+        assert_eq!(stack.current(), Step::Language);
+
+        assert!(stack.next());
+        assert!(stack.next());
+        assert_eq!(stack.current(), Step::Wifi);
+        assert!(stack.back());
+        assert_eq!(stack.current(), Step::Clock);
+
+        while stack.next() {}
+        assert_eq!(stack.current(), Step::Folders);
+        assert_eq!(stack.index(), 4);
+        assert!(!stack.next());
+    }
+
+    #[test]
+    fn test_create_folder_skeleton() {
+        let dir = temp_dir("setup");
+        let mut mapper = ConsoleMapper::new();
+        mapper
+            .parse_config(
+                r#"
+                [cores]
+                [[consoles]]
+                name = "Game Boy Advance"
+                patterns = ["GBA", "Game Boy Advance"]
+                [[consoles]]
+                name = "Native"
+                [[consoles]]
+                name = "Super Nintendo"
+                patterns = ["SFC"]
+                "#,
+            )
+            .unwrap();
+        fs::create_dir_all(dir.join("SFC")).unwrap();
+
+        assert_eq!(create_folder_skeleton(&dir, &mapper).unwrap(), 1);
+        assert!(dir.join("GBA").is_dir());
+        assert!(!dir.join("Game Boy Advance").exists());
+        assert_eq!(create_folder_skeleton(&dir, &mapper).unwrap(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,4 @@
 use std::collections::VecDeque;
-use std::fs::{self, File};
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -9,11 +8,13 @@ use common::constants::ALLIUM_LAUNCHER_STATE;
 use common::display::Display;
 use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
+use common::persisted::{self, Versioned};
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::profile::Profile;
 use common::resources::Resources;
-use common::stylesheet::{Stylesheet, StylesheetColor};
-use common::view::{BatteryIndicator, Label, Row, View};
-use log::{trace, warn};
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{BatteryIndicator, Label, Row, Transition, View, WriteIndicator};
+use log::trace;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
@@ -26,24 +27,97 @@ use crate::view::{Apps, Games, Settings};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct AppState {
+    #[serde(default)]
     selected: usize,
     recents: RecentsState,
     games: GamesState,
     apps: AppsState,
+    #[serde(default)]
     settings: SettingsState,
 }
 
+impl Versioned for AppState {
+    const VERSION: u32 = 1;
+}
+
+/// Where the launcher draws its tabs, and the content of the selected tab below them.
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub header: Rect,
+    pub content: Rect,
+    /// Where the write indicator is drawn to the left of.
+    pub write_indicator: Point,
+    /// Where the counter of the listed tab is right-aligned, left of the write indicator.
+    pub counter: Point,
+}
+
+impl Layout {
+    pub fn new(rect: Rect, styles: &Styles) -> Self {
+        let header_height = styles.ui_font.size + 8;
+        let write_indicator = Point::new(
+            rect.x + rect.w as i32 - 12 - styles.ui_font.size as i32 * 2 - 8,
+            rect.y + 8,
+        );
+        Self {
+            header: Rect::new(rect.x, rect.y, rect.w, header_height),
+            content: Rect::new(
+                rect.x,
+                rect.y + header_height as i32,
+                rect.w,
+                rect.h - header_height,
+            ),
+            write_indicator,
+            counter: Point::new(
+                write_indicator.x - styles.ui_font.size as i32 / 3 - 12,
+                write_indicator.y,
+            ),
+        }
+    }
+
+    /// The layout that `content` is the content region of, for tabs to find the header.
+    pub fn for_content(content: Rect, styles: &Styles) -> Self {
+        let header_height = styles.ui_font.size + 8;
+        Self::new(
+            Rect::new(
+                content.x,
+                content.y - header_height as i32,
+                content.w,
+                content.h + header_height,
+            ),
+            styles,
+        )
+    }
+
+    /// The part of the header left of the counter's right edge, holding the tabs and the counter.
+    fn tabs_and_counter(&self) -> Rect {
+        Rect::new(
+            self.header.x,
+            self.header.y,
+            (self.counter.x - self.header.x + 1) as u32,
+            self.header.h,
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct App<B>
 where
     B: Battery + 'static,
 {
     rect: Rect,
+    layout: Layout,
     battery_indicator: BatteryIndicator<B>,
+    write_indicator: WriteIndicator,
     views: (Recents, Games, Apps, Settings),
     selected: usize,
     tabs: Row<Label<String>>,
+    tab_count: usize,
+    profile: Profile,
     dirty: bool,
+    /// Clears the content region when another tab is selected.
+    transition: Transition,
+    /// Tab that a search was started from, to go back to once it's closed.
+    search_from: Option<usize>,
 }
 
 impl<B> App<B>
@@ -57,15 +131,27 @@ where
         selected: usize,
         battery: B,
     ) -> Result<Self> {
-        let Rect { x, y, w, h: _h } = rect;
+        let Rect { y, w, .. } = rect;
 
         let battery_indicator = BatteryIndicator::new(Point::new(w as i32 - 12, y + 8), battery);
+        let (layout, write_indicator) = {
+            let styles = res.get::<Styles>();
+            let layout = Layout::new(rect, &styles);
+            let write_indicator = WriteIndicator::new(layout.write_indicator);
+            (layout, write_indicator)
+        };
+
+        let profile = res.get::<Profile>().clone();
+
+        // Restricted profiles don't get a settings tab
+        let tab_count = if profile.restricted { 3 } else { 4 };
+        let selected = selected.min(tab_count - 1);
 
         let mut tabs = Row::new(
-            Point::new(x + 12, y + 8),
+            Point::new(layout.header.x + 12, layout.header.y + 8),
             {
                 let locale = res.get::<Locale>();
-                vec![
+                let mut tabs = vec![
                     Label::new(
                         Point::zero(),
                         locale.t("tab-recents"),
@@ -74,13 +160,16 @@ where
                     ),
                     Label::new(Point::zero(), locale.t("tab-games"), Alignment::Left, None),
                     Label::new(Point::zero(), locale.t("tab-apps"), Alignment::Left, None),
-                    Label::new(
+                ];
+                if tab_count > 3 {
+                    tabs.push(Label::new(
                         Point::zero(),
                         locale.t("tab-settings"),
                         Alignment::Left,
                         None,
-                    ),
-                ]
+                    ));
+                }
+                tabs
             },
             Alignment::Left,
             12,
@@ -91,49 +180,42 @@ where
 
         Ok(Self {
             rect,
+            layout,
             views,
             selected,
             battery_indicator,
+            write_indicator,
             tabs,
+            tab_count,
+            profile,
             dirty: true,
+            transition: Transition::default(),
+            search_from: None,
         })
     }
 
     pub fn load_or_new(rect: Rect, res: Resources, battery: B) -> Result<Self> {
-        let tab_rect = {
-            let styles = res.get::<Stylesheet>();
-            Rect::new(
-                rect.x,
-                rect.y + styles.ui_font.size as i32 + 8,
-                rect.w,
-                rect.h - styles.ui_font.size - 8,
-            )
-        };
+        let tab_rect = Layout::new(rect, &res.get::<Styles>()).content;
 
-        if ALLIUM_LAUNCHER_STATE.exists() {
-            let file = File::open(ALLIUM_LAUNCHER_STATE.as_path())?;
-            if let Ok(state) = serde_json::from_reader::<_, AppState>(file) {
-                let views = (
-                    Recents::load_or_new(tab_rect, res.clone(), Some(state.recents))?,
-                    Games::load_or_new(tab_rect, res.clone(), Some(state.games)).unwrap_or_else(
-                        |_| Games::load_or_new(tab_rect, res.clone(), None).unwrap(),
-                    ),
-                    Apps::load_or_new(tab_rect, res.clone(), Some(state.apps))?,
-                    Settings::new(
-                        tab_rect,
-                        res.clone(),
-                        if state.selected == 3 {
-                            // Only load settings if it was the last selected tab
-                            state.settings
-                        } else {
-                            Default::default()
-                        },
-                    )?,
-                );
-                return Self::new(rect, res, views, state.selected, battery);
-            }
-            warn!("failed to deserialize state file, deleting");
-            fs::remove_file(ALLIUM_LAUNCHER_STATE.as_path())?;
+        let state_path = res.get::<Profile>().scoped_path(&ALLIUM_LAUNCHER_STATE);
+        if let Some(state) = persisted::load::<AppState>(&state_path)? {
+            let views = (
+                Recents::load_or_new(tab_rect, res.clone(), Some(state.recents))?,
+                Games::load_or_new(tab_rect, res.clone(), Some(state.games))
+                    .unwrap_or_else(|_| Games::load_or_new(tab_rect, res.clone(), None).unwrap()),
+                Apps::load_or_new(tab_rect, res.clone(), Some(state.apps))?,
+                Settings::new(
+                    tab_rect,
+                    res.clone(),
+                    if state.selected == 3 {
+                        // Only load settings if it was the last selected tab
+                        state.settings
+                    } else {
+                        Default::default()
+                    },
+                )?,
+            );
+            return Self::new(rect, res, views, state.selected, battery);
         }
 
         let views = (
@@ -147,7 +229,6 @@ where
     }
 
     pub fn save(&self) -> Result<()> {
-        let file = File::create(ALLIUM_LAUNCHER_STATE.as_path())?;
         let state = AppState {
             selected: self.selected,
             recents: self.views.0.save(),
@@ -155,8 +236,7 @@ where
             apps: self.views.2.save(),
             settings: self.views.3.save(),
         };
-        serde_json::to_writer(file, &state)?;
-        Ok(())
+        persisted::save(&self.profile.scoped_path(&ALLIUM_LAUNCHER_STATE), &state)
     }
 
     fn view(&self) -> &dyn View {
@@ -185,8 +265,9 @@ where
             .unwrap()
             .color(StylesheetColor::Foreground);
         self.selected = selected;
-        self.view_mut().set_should_draw();
-        self.set_should_draw();
+        // What the last tab drew is cleared before the new one is drawn in full, rather than
+        // relying on it to clear everything the last one drew
+        self.transition.start(self.layout.content);
         self.tabs
             .get_mut(self.selected)
             .unwrap()
@@ -194,16 +275,19 @@ where
     }
 
     fn next(&mut self) {
-        let selected = (self.selected + 1).rem_euclid(4);
+        let selected = (self.selected + 1).rem_euclid(self.tab_count);
         self.tab_change(selected)
     }
 
     fn prev(&mut self) {
-        let selected = (self.selected as isize - 1).rem_euclid(4);
+        let selected = (self.selected as isize - 1).rem_euclid(self.tab_count as isize);
         self.tab_change(selected as usize)
     }
 
     pub fn start_search(&mut self) {
+        if self.selected != 0 {
+            self.search_from = Some(self.selected);
+        }
         self.tab_change(0);
         self.views.0.start_search();
     }
@@ -223,18 +307,28 @@ where
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         if self.dirty {
             display.load(self.bounding_box(styles))?;
             self.dirty = false;
         }
+        if self.transition.clear(display)? {
+            // Only lists have a counter, so the last tab's is cleared from the header too
+            display.load(self.layout.tabs_and_counter())?;
+            self.tabs.set_should_draw();
+            self.view_mut().set_should_draw();
+        }
 
         let mut drawn = false;
         if self.battery_indicator.should_draw() && self.battery_indicator.draw(display, styles)? {
             drawn = true;
         }
 
+        if self.write_indicator.should_draw() && self.write_indicator.draw(display, styles)? {
+            drawn = true;
+        }
+
         if self.tabs.should_draw() && self.tabs.draw(display, styles)? {
             drawn = true;
         }
@@ -246,12 +340,17 @@ where
     }
 
     fn should_draw(&self) -> bool {
-        self.battery_indicator.should_draw() || self.view().should_draw() || self.tabs.should_draw()
+        self.transition.is_pending()
+            || self.battery_indicator.should_draw()
+            || self.write_indicator.should_draw()
+            || self.view().should_draw()
+            || self.tabs.should_draw()
     }
 
     fn set_should_draw(&mut self) {
         self.dirty = true;
         self.battery_indicator.set_should_draw();
+        self.write_indicator.set_should_draw();
         self.view_mut().set_should_draw();
         self.tabs.set_should_draw();
     }
@@ -267,17 +366,26 @@ where
             .handle_key_event(event, commands, bubble)
             .await?
         {
+            if self.selected == 0 && bubble.iter().any(|c| matches!(c, Command::CloseView)) {
+                if let Some(selected) = self.search_from.take() {
+                    trace!("closing search");
+                    bubble.retain(|c| !matches!(c, Command::CloseView));
+                    self.tab_change(selected);
+                }
+            }
             return Ok(true);
         }
 
         match event {
             KeyEvent::Pressed(Key::Left) => {
                 trace!("switch state prev");
+                self.search_from = None;
                 self.prev();
                 Ok(true)
             }
             KeyEvent::Pressed(Key::Right) => {
                 trace!("switch state next");
+                self.search_from = None;
                 self.next();
                 Ok(true)
             }
@@ -286,7 +394,12 @@ where
     }
 
     fn children(&self) -> Vec<&dyn View> {
-        vec![&self.battery_indicator, self.view(), &self.tabs]
+        vec![
+            &self.battery_indicator,
+            &self.write_indicator,
+            self.view(),
+            &self.tabs,
+        ]
     }
 
     fn children_mut(&mut self) -> Vec<&mut dyn View> {
@@ -297,10 +410,15 @@ where
             3 => &mut self.views.3,
             _ => unreachable!(),
         };
-        vec![&mut self.battery_indicator, view, &mut self.tabs]
+        vec![
+            &mut self.battery_indicator,
+            &mut self.write_indicator,
+            view,
+            &mut self.tabs,
+        ]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
@@ -308,3 +426,99 @@ where
         unimplemented!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+    use crate::view::entry_list::ListPosition;
+    use crate::view::games::GamesSort;
+    use crate::view::recents::RecentsSort;
+
+    const V0: &str = include_str!("../../testdata/state/launcher-v0.json");
+    const V1: &str = include_str!("../../testdata/state/launcher-v1.json");
+
+    #[test]
+    fn test_load_unversioned_state() -> Result<()> {
+        let state: AppState = persisted::from_str(V0)?;
+
+        assert_eq!(state.selected, 1);
+        assert!(matches!(&state.recents.sort, RecentsSort::Search(q) if q == "mario"));
+        assert_eq!(state.recents.selected, 3);
+
+        assert!(matches!(&state.games.sort, GamesSort::Alphabetical(d) if d.name == "Roms"));
+        let child = state.games.child.as_ref().unwrap();
+        assert!(matches!(
+            &child.sort,
+            GamesSort::LastPlayed(d) if d.path == Path::new("/mnt/SDCARD/Roms/GBA")
+        ));
+        assert_eq!(child.selected, 5);
+
+        assert!(persisted::to_string(&state)?
+            .contains(r#""settings":{"selected":2,"child":{"selected":1}}"#));
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_v1_state() -> Result<()> {
+        let state: AppState = persisted::from_str(V1)?;
+
+        assert_eq!(state.selected, 3);
+        assert!(matches!(state.recents.sort, RecentsSort::MostPlayed));
+        assert!(matches!(state.games.sort, GamesSort::Random(_)));
+        assert_eq!(state.apps.selected, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_optional_fields() -> Result<()> {
+        let state: AppState = persisted::from_str(
+            r#"{"version":1,"state":{"recents":{"sort":"Random"},"games":{"sort":{"Alphabetical":{"name":"Roms","full_name":"Roms","path":"/Roms","image":"NotFound"}}},"apps":{"sort":{"Alphabetical":{"name":"App","full_name":"App","path":"/App","image":"NotFound"}}}}}"#,
+        )?;
+
+        assert_eq!(state.selected, 0);
+        assert_eq!(state.games.selected, 0);
+        assert!(state.games.child.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_folder_positions_round_trip() -> Result<()> {
+        let mut state: AppState = persisted::from_str(V1)?;
+        assert!(state.games.folders.is_empty());
+        assert_eq!(state.games.top, 0);
+
+        state.games.top = 4;
+        state.games.folders.insert(
+            PathBuf::from("/mnt/SDCARD/Roms/GBA"),
+            ListPosition {
+                selected: 37,
+                top: 33,
+            },
+        );
+        let state: AppState = persisted::from_str(&persisted::to_string(&state)?)?;
+        assert_eq!(state.games.top, 4);
+        assert_eq!(
+            state.games.folders[Path::new("/mnt/SDCARD/Roms/GBA")],
+            ListPosition {
+                selected: 37,
+                top: 33
+            }
+        );
+        // Lists without folders remembered don't write any
+        assert!(!persisted::to_string(&state)?.contains(r#""folders":{}"#));
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_round_trip() -> Result<()> {
+        let state: AppState = persisted::from_str(V0)?;
+        let json = persisted::to_string(&state)?;
+        assert!(json.starts_with(r#"{"version":1,"#));
+
+        let state: AppState = persisted::from_str(&json)?;
+        assert_eq!(state.games.child.unwrap().selected, 5);
+        Ok(())
+    }
+}
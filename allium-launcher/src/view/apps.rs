@@ -10,7 +10,7 @@ use common::geom::{Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::View;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
@@ -18,6 +18,7 @@ use tokio::sync::mpsc::Sender;
 use crate::consoles::ConsoleMapper;
 use crate::entry::directory::Directory;
 use crate::entry::{Entry, Sort};
+use crate::library_filter::LibraryFilter;
 use crate::view::entry_list::{EntryList, EntryListState};
 
 pub type AppsState = EntryListState<AppsSort>;
@@ -35,10 +36,7 @@ impl Apps {
 
     pub fn load_or_new(rect: Rect, res: Resources, state: Option<AppsState>) -> Result<Self> {
         let list = if let Some(state) = state {
-            let selected = state.selected;
-            let mut list = EntryList::load(rect, res.clone(), state)?;
-            list.select(selected);
-            list
+            EntryList::load(rect, res.clone(), state)?
         } else {
             EntryList::new(
                 rect,
@@ -60,7 +58,7 @@ impl View for Apps {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -94,7 +92,7 @@ impl View for Apps {
         vec![&mut self.list]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
@@ -137,8 +135,14 @@ impl Sort for AppsSort {
         }
     }
 
-    fn entries(&self, database: &Database, console_mapper: &ConsoleMapper) -> Result<Vec<Entry>> {
+    fn entries(
+        &self,
+        database: &Database,
+        console_mapper: &ConsoleMapper,
+        filter: &LibraryFilter,
+    ) -> Result<Vec<Entry>> {
         let mut entries = self.directory().entries(database, console_mapper)?;
+        filter.retain(&mut entries);
         entries.sort_unstable();
         Ok(entries)
     }
@@ -0,0 +1,596 @@
+//! Multi-select in the games list, and the batch actions that run on the selected games.
+
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::database::Database;
+use common::display::font::FontTextStyleBuilder;
+use common::display::Display;
+use common::filename_rules;
+use common::geom::{Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::playlist;
+use common::resources::Resources;
+use common::stylesheet::Styles;
+use common::trash::{self, Trash};
+use common::view::View;
+use common::write_activity;
+use embedded_graphics::prelude::{Dimensions, OriginDimensions, Size};
+use embedded_graphics::primitives::{
+    CornerRadii, Primitive, PrimitiveStyle, Rectangle, RoundedRectangle,
+};
+use embedded_graphics::text::{Alignment, Text};
+use embedded_graphics::Drawable;
+use log::warn;
+use tokio::sync::mpsc::Sender;
+
+use crate::consoles::ConsoleMapper;
+use crate::scraper;
+
+/// Number of errors listed once a batch is done, the rest are only counted.
+const ERRORS_SHOWN: usize = 3;
+
+/// Games selected in multi-select mode. Tracked by path rather than by index so that the selection
+/// survives the list being reloaded or resorted.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    paths: HashSet<PathBuf>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects the game if it wasn't selected, deselects it otherwise. Returns whether the game
+    /// is now selected.
+    pub fn toggle(&mut self, path: &Path) -> bool {
+        if self.paths.remove(path) {
+            false
+        } else {
+            self.paths.insert(path.to_path_buf());
+            true
+        }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.paths.contains(path)
+    }
+
+    pub fn select_all<'a>(&mut self, paths: impl IntoIterator<Item = &'a Path>) {
+        self.paths.extend(paths.into_iter().map(Path::to_path_buf));
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+    }
+
+    /// Forgets games that are no longer listed, e.g. because they were deleted.
+    pub fn retain(&mut self, mut listed: impl FnMut(&Path) -> bool) {
+        self.paths.retain(|path| listed(path));
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// The selected games, sorted so that batch actions run in a stable order.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = self.paths.iter().cloned().collect();
+        paths.sort_unstable_by(|a, b| {
+            filename_rules::compare_names(&a.to_string_lossy(), &b.to_string_lossy())
+        });
+        paths
+    }
+}
+
+/// Combined size of the files, skipping any that can't be read.
+pub fn total_size(paths: &[PathBuf]) -> u64 {
+    paths
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Files deleted along with the game at `path`: the rest of a multi-file game, and its box art.
+pub fn related_files(path: &Path) -> Vec<PathBuf> {
+    let mut files = playlist::files(path);
+    files.extend(scraper::image_path(path).filter(|art| art.is_file()));
+    files
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchAction {
+    Hide,
+    MarkCompleted,
+    ScrapeArt,
+    Delete,
+}
+
+impl BatchAction {
+    pub const ALL: [BatchAction; 4] = [
+        BatchAction::Hide,
+        BatchAction::MarkCompleted,
+        BatchAction::ScrapeArt,
+        BatchAction::Delete,
+    ];
+
+    pub fn text(&self, locale: &Locale) -> String {
+        match self {
+            BatchAction::Hide => locale.t("batch-hide"),
+            BatchAction::MarkCompleted => locale.t("batch-mark-completed"),
+            BatchAction::ScrapeArt => locale.t("batch-scrape-art"),
+            BatchAction::Delete => locale.t("batch-delete"),
+        }
+    }
+
+    fn progress_text(&self, locale: &Locale, done: usize, total: usize) -> String {
+        let key = match self {
+            BatchAction::Hide => "batch-hide-progress",
+            BatchAction::MarkCompleted => "batch-mark-completed-progress",
+            BatchAction::ScrapeArt => "batch-scrape-art-progress",
+            BatchAction::Delete => "batch-delete-progress",
+        };
+        locale.ta(
+            key,
+            &[
+                ("done".to_string(), done.into()),
+                ("total".to_string(), total.into()),
+            ]
+            .into_iter()
+            .collect(),
+        )
+    }
+
+    /// Applies the action to one game. Returns the id to undo it by, if it can be undone.
+    fn apply(
+        &self,
+        database: &Database,
+        console_mapper: &ConsoleMapper,
+        trash: &Trash,
+        path: &Path,
+    ) -> Result<Option<i64>> {
+        match self {
+            BatchAction::Hide => database.set_hidden(path, true)?,
+            BatchAction::MarkCompleted => database.set_completed(path, true)?,
+            BatchAction::ScrapeArt => scraper::enqueue_game(database, console_mapper, path)?,
+            BatchAction::Delete => {
+                return trash::delete_game(database, trash, path, &related_files(path)).map(Some)
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A batch action on a list of games, applied one game at a time. Errors are collected per game
+/// so that one failure doesn't stop the rest of the batch.
+#[derive(Debug)]
+pub struct Batch {
+    action: BatchAction,
+    paths: Vec<PathBuf>,
+    done: usize,
+    errors: Vec<(PathBuf, String)>,
+    /// Ids to undo the action by, for the games it could be undone for.
+    undo: Vec<i64>,
+}
+
+impl Batch {
+    pub fn new(action: BatchAction, paths: Vec<PathBuf>) -> Self {
+        Self {
+            action,
+            paths,
+            done: 0,
+            errors: Vec::new(),
+            undo: Vec::new(),
+        }
+    }
+
+    /// Applies the action to the next game. Returns false if there was nothing left to do.
+    pub fn step(
+        &mut self,
+        database: &Database,
+        console_mapper: &ConsoleMapper,
+        trash: &Trash,
+    ) -> bool {
+        let Some(path) = self.paths.get(self.done) else {
+            return false;
+        };
+
+        let _guard = write_activity::begin("batch operation");
+        match self.action.apply(database, console_mapper, trash, path) {
+            Ok(undo) => self.undo.extend(undo),
+            Err(e) => {
+                warn!("{:?} failed for {}: {}", self.action, path.display(), e);
+                self.errors.push((path.clone(), e.to_string()));
+            }
+        }
+        self.done += 1;
+
+        true
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done >= self.paths.len()
+    }
+
+    pub fn succeeded(&self) -> usize {
+        self.done - self.errors.len()
+    }
+
+    pub fn errors(&self) -> &[(PathBuf, String)] {
+        &self.errors
+    }
+
+    pub fn undo(&self) -> &[i64] {
+        &self.undo
+    }
+}
+
+/// Dialog showing the progress of a batch. Steps through the batch one game per frame so that
+/// progress is drawn as it goes, then shows a summary with any errors until dismissed with A or B.
+#[derive(Debug)]
+pub struct BatchProgress {
+    res: Resources,
+    batch: Batch,
+    text: String,
+    drawn: Option<Rect>,
+    dirty: bool,
+}
+
+impl BatchProgress {
+    pub fn new(res: Resources, batch: Batch) -> Self {
+        let text = batch.action.progress_text(&res.get(), 0, batch.paths.len());
+        Self {
+            res,
+            batch,
+            text,
+            drawn: None,
+            dirty: true,
+        }
+    }
+
+    /// Text and ids for offering to undo the batch once it is done, if it can be undone.
+    pub fn undo_offer(&self) -> Option<(String, Vec<i64>)> {
+        if !self.batch.is_done() || self.batch.undo().is_empty() {
+            return None;
+        }
+        let text = self.res.get::<Locale>().ta(
+            "undo-delete-games",
+            &[("count".to_string(), self.batch.undo().len().into())]
+                .into_iter()
+                .collect(),
+        );
+        Some((text, self.batch.undo().to_vec()))
+    }
+
+    fn summary(&self) -> String {
+        let locale = self.res.get::<Locale>();
+        let mut text = locale.ta(
+            "batch-done",
+            &[
+                ("succeeded".to_string(), self.batch.succeeded().into()),
+                ("total".to_string(), self.batch.paths.len().into()),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        for (path, error) in self.batch.errors().iter().take(ERRORS_SHOWN) {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            text.push('\n');
+            text.push_str(&format!("{}: {}", name, error));
+        }
+        if self.batch.errors().len() > ERRORS_SHOWN {
+            text.push('\n');
+            text.push_str(
+                &locale.ta(
+                    "batch-more-errors",
+                    &[(
+                        "count".to_string(),
+                        (self.batch.errors().len() - ERRORS_SHOWN).into(),
+                    )]
+                    .into_iter()
+                    .collect(),
+                ),
+            );
+        }
+
+        text
+    }
+}
+
+#[async_trait(?Send)]
+impl View for BatchProgress {
+    fn update(&mut self, _dt: Duration) {
+        let done = {
+            let database = self.res.get::<Database>();
+            let console_mapper = self.res.get::<ConsoleMapper>();
+            let trash = self.res.get::<Trash>();
+            if !self.batch.step(&database, &console_mapper, &trash) {
+                return;
+            }
+            self.batch.is_done()
+        };
+
+        if done {
+            if self.batch.action == BatchAction::ScrapeArt && self.batch.succeeded() > 0 {
+                scraper::spawn_worker();
+            }
+            self.text = self.summary();
+        } else {
+            self.text = self.batch.action.progress_text(
+                &self.res.get(),
+                self.batch.done,
+                self.batch.paths.len(),
+            );
+        }
+        self.dirty = true;
+    }
+
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        if let Some(rect) = self.drawn.take() {
+            display.load(rect)?;
+        }
+
+        let w = display.size().width;
+        let h = display.size().height;
+
+        let text_style = FontTextStyleBuilder::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
+            .font_size(styles.ui_font.size)
+            .background_color(styles.highlight_color)
+            .text_color(styles.foreground_color)
+            .build();
+
+        let lines = self.text.lines().count() as u32;
+
+        let text = Text::with_alignment(
+            &self.text,
+            Point::new(w as i32 / 2, (h - styles.ui_font.size * lines) as i32 / 2).into(),
+            text_style,
+            Alignment::Center,
+        );
+
+        let rect = text.bounding_box();
+        let x = rect.top_left.x;
+        let y = rect.top_left.y;
+        let Size { width, height } = rect.size;
+        let rect = Rect::new(x - 12, y - 8, width + 24, height + 16);
+        RoundedRectangle::new(
+            Rectangle::new(Point::new(rect.x, rect.y).into(), Size::new(rect.w, rect.h)),
+            CornerRadii::new(Size::new_equal(12)),
+        )
+        .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+        .draw(display)?;
+
+        text.draw(display)?;
+
+        self.drawn = Some(rect);
+        self.dirty = false;
+        Ok(true)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        _commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self.batch.is_done() && matches!(event, KeyEvent::Pressed(Key::A | Key::B)) {
+            bubble.push_back(Command::CloseView);
+        }
+        // Input is blocked while the batch is running
+        Ok(true)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.drawn.unwrap_or_default()
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::database::NewGame;
+    use common::test_utils::temp_dir;
+
+    use super::*;
+
+    #[test]
+    fn test_selection_survives_reordering() {
+        let mut selection = Selection::new();
+        let a = PathBuf::from("Roms/GB/a.gb");
+        let b = PathBuf::from("Roms/GB/b.gb");
+        let c = PathBuf::from("Roms/GB/c.gb");
+
+        assert!(selection.toggle(&b));
+        assert!(selection.toggle(&a));
+        assert!(!selection.toggle(&a));
+        assert!(selection.contains(&b));
+        assert_eq!(selection.len(), 1);
+
+        selection.select_all([a.as_path(), b.as_path(), c.as_path()]);
+        assert_eq!(selection.paths(), vec![a.clone(), b.clone(), c.clone()]);
+
+        // `b` was deleted, the list no longer has it
+        selection.retain(|path| path != b);
+        assert_eq!(selection.paths(), vec![a, c]);
+
+        selection.clear();
+        assert!(selection.is_empty());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(32 * 1024 * 1024), "32.0 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024 / 2), "1.5 GB");
+    }
+
+    #[test]
+    fn test_batch_collects_errors() -> Result<()> {
+        let dir = temp_dir("batch");
+        let existing = dir.join("existing.gb");
+        fs::write(&existing, [0; 100])?;
+        let missing = dir.join("missing.gb");
+
+        let database = Database::in_memory()?;
+        database.update_games(&[NewGame {
+            name: "existing".to_string(),
+            path: existing.clone(),
+            image: None,
+            core: None,
+        }])?;
+        let console_mapper = ConsoleMapper::new();
+        let trash = Trash::at(dir.join(".trash"));
+
+        let paths = vec![existing.clone(), missing.clone()];
+        assert_eq!(total_size(&paths), 100);
+
+        let mut batch = Batch::new(BatchAction::Delete, paths);
+        while batch.step(&database, &console_mapper, &trash) {}
+
+        assert!(batch.is_done());
+        assert_eq!(batch.succeeded(), 1);
+        assert_eq!(batch.errors().len(), 1);
+        assert_eq!(batch.errors()[0].0, missing);
+        assert!(!existing.exists());
+        assert_eq!(database.select_game(&existing.display().to_string())?, None);
+
+        // Deleted games go to the trash, and can be undone
+        assert_eq!(batch.undo().len(), 1);
+        assert!(trash.dir().join("existing.gb").exists());
+        trash::undo(&database, &trash, batch.undo()[0])?;
+        assert!(existing.exists());
+        assert!(database
+            .select_game(&existing.display().to_string())?
+            .is_some());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_delete_multi_disc_game() -> Result<()> {
+        let dir = temp_dir("batch-discs");
+        let playlist = dir.join("Game.m3u");
+        fs::write(&playlist, "Game (Disc 1).cue\nGame (Disc 2).cue\n")?;
+        for disc in 1..=2 {
+            fs::write(
+                dir.join(format!("Game (Disc {}).cue", disc)),
+                format!(
+                    "FILE \"Game (Disc {}).bin\" BINARY\n  TRACK 01 MODE2/2352\n",
+                    disc
+                ),
+            )?;
+            fs::write(dir.join(format!("Game (Disc {}).bin", disc)), [0; 10])?;
+        }
+        let files: Vec<_> = fs::read_dir(&dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        assert_eq!(files.len(), 5);
+
+        // A disc scanned before it was part of the playlist is in the database too
+        let disc = dir.join("Game (Disc 1).cue");
+        let database = Database::in_memory()?;
+        database.update_games(&[
+            NewGame {
+                name: "Game".to_string(),
+                path: playlist.clone(),
+                image: None,
+                core: None,
+            },
+            NewGame {
+                name: "Game (Disc 1)".to_string(),
+                path: disc.clone(),
+                image: None,
+                core: None,
+            },
+        ])?;
+        let trash = Trash::at(dir.join(".trash"));
+
+        let mut batch = Batch::new(BatchAction::Delete, vec![playlist.clone()]);
+        while batch.step(&database, &ConsoleMapper::new(), &trash) {}
+
+        assert!(batch.errors().is_empty());
+        assert!(files.iter().all(|file| !file.exists()));
+        assert_eq!(database.select_game(&playlist.display().to_string())?, None);
+        assert_eq!(database.select_game(&disc.display().to_string())?, None);
+
+        trash::undo(&database, &trash, batch.undo()[0])?;
+        assert!(files.iter().all(|file| file.exists()));
+        assert!(database.select_game(&disc.display().to_string())?.is_some());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_hide() {
+        let database = Database::in_memory().unwrap();
+        let console_mapper = ConsoleMapper::new();
+        let paths = vec![PathBuf::from("Roms/GB/a.gb"), PathBuf::from("Roms/GB/b.gb")];
+
+        let mut batch = Batch::new(BatchAction::Hide, paths.clone());
+        while batch.step(&database, &console_mapper, &Trash::new()) {}
+
+        assert!(batch.errors().is_empty());
+        assert!(batch.undo().is_empty());
+        assert_eq!(
+            database.hidden_games().unwrap(),
+            paths.into_iter().collect::<HashSet<_>>()
+        );
+    }
+}
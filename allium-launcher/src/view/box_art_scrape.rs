@@ -0,0 +1,267 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::database::{Database, ScrapeStatus};
+use common::display::Display;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{ButtonHint, ButtonIcon, Label, ProgressBar, Row, View};
+use log::{error, info};
+use tokio::sync::mpsc::Sender;
+
+/// How often the scrape queue is checked for progress.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Shows how far downloading box art for a folder has got, while the scraper works through the
+/// queued games in the background. B takes the games that are left out of the queue.
+#[derive(Debug)]
+pub struct BoxArtScrape {
+    rect: Rect,
+    res: Resources,
+    paths: Vec<PathBuf>,
+    since_poll: Duration,
+    finished: bool,
+    title: Label<String>,
+    status: Label<String>,
+    progress: ProgressBar,
+    button_hints: Row<ButtonHint<String>>,
+    dirty: bool,
+}
+
+impl BoxArtScrape {
+    pub fn new(rect: Rect, res: Resources, paths: Vec<PathBuf>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let mut title = Label::new(
+            Point::new(
+                x + w as i32 / 2,
+                y + h as i32 / 2 - styles.ui_font.size as i32 * 2,
+            ),
+            locale.t("box-art-scrape-title"),
+            Alignment::Center,
+            Some(w - 24),
+        );
+        title.color(StylesheetColor::Highlight);
+
+        let status = Label::new(
+            Point::new(
+                x + w as i32 / 2,
+                y + h as i32 / 2 - styles.ui_font.size as i32 / 2,
+            ),
+            String::new(),
+            Alignment::Center,
+            Some(w - 24),
+        );
+
+        let progress = ProgressBar::new(
+            Point::new(
+                x + w as i32 / 2,
+                y + h as i32 / 2 + styles.ui_font.size as i32,
+            ),
+            0.0,
+            Alignment::Center,
+        );
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![ButtonHint::new(
+                Point::zero(),
+                Key::B,
+                locale.t("batch-cancel"),
+                Alignment::Right,
+            )],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        let mut this = Self {
+            rect,
+            res,
+            paths,
+            since_poll: Duration::ZERO,
+            finished: false,
+            title,
+            status,
+            progress,
+            button_hints,
+            dirty: true,
+        };
+        this.poll();
+        this
+    }
+
+    /// Counts how many of the games are done, and how many of those got box art.
+    fn poll(&mut self) {
+        let (mut done, mut found) = (0, 0);
+        {
+            let database = self.res.get::<Database>();
+            for path in &self.paths {
+                match database.scrape_status(path) {
+                    Ok(Some(ScrapeStatus::Pending)) => {}
+                    Ok(Some(ScrapeStatus::Completed)) => {
+                        done += 1;
+                        found += 1;
+                    }
+                    // Taken out of the queue some other way, e.g. by clearing it
+                    Ok(Some(ScrapeStatus::Failed) | None) => done += 1,
+                    Err(e) => {
+                        error!("failed to check scrape status: {}", e);
+                        return;
+                    }
+                }
+            }
+        }
+
+        let total = self.paths.len();
+        self.progress.set_progress(match total {
+            0 => 1.0,
+            total => done as f32 / total as f32,
+        });
+        if done == total {
+            info!("downloaded box art for {} of {} games", found, total);
+            self.finish(found);
+        } else {
+            let text = self.res.get::<Locale>().ta(
+                "box-art-scrape-progress",
+                &[
+                    ("done".to_string(), done.into()),
+                    ("total".to_string(), total.into()),
+                ]
+                .into_iter()
+                .collect(),
+            );
+            self.status.set_text(text);
+        }
+    }
+
+    fn finish(&mut self, found: usize) {
+        self.finished = true;
+        let locale = self.res.get::<Locale>();
+        self.status.set_text(
+            locale.ta(
+                "box-art-scrape-done",
+                &[
+                    ("found".to_string(), found.into()),
+                    ("total".to_string(), self.paths.len().into()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        );
+        self.button_hints
+            .get_mut(0)
+            .unwrap()
+            .set_text(locale.t("button-back"));
+        self.dirty = true;
+    }
+}
+
+#[async_trait(?Send)]
+impl View for BoxArtScrape {
+    fn update(&mut self, dt: Duration) {
+        if self.finished {
+            return;
+        }
+        self.since_poll += dt;
+        if self.since_poll >= POLL_INTERVAL {
+            self.since_poll = Duration::ZERO;
+            self.poll();
+        }
+    }
+
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            for child in self.children_mut() {
+                child.set_should_draw();
+            }
+            self.dirty = false;
+            drawn = true;
+        }
+
+        for child in self.children_mut() {
+            drawn |= child.should_draw() && child.draw(display, styles)?;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty || self.children().iter().any(|c| c.should_draw())
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::B) if !self.finished => {
+                let cancelled = self.res.get::<Database>().cancel_scrape_jobs(&self.paths)?;
+                info!("cancelled downloading box art for {} games", cancelled);
+                bubble.push_back(Command::CloseView);
+                commands.send(Command::Redraw).await?;
+            }
+            KeyEvent::Pressed(Key::A | Key::B) if self.finished => {
+                bubble.push_back(Command::CloseView);
+                commands.send(Command::Redraw).await?;
+            }
+            _ => {}
+        }
+        // The progress is modal
+        Ok(true)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![
+            &self.title,
+            &self.status,
+            &self.progress,
+            &self.button_hints,
+        ]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![
+            &mut self.title,
+            &mut self.status,
+            &mut self.progress,
+            &mut self.button_hints,
+        ]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::IMAGE_WIDTH;
+use common::database::Database;
+use common::display::Display;
+use common::format::format_play_time;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{ArtPlaceholder, ButtonHint, ButtonIcon, Image, ImageMode, Label, Row, View};
+use tokio::sync::mpsc::Sender;
+
+use crate::entry::game::Game;
+use crate::entry::{names, Entry};
+use crate::view::entry_list::EntryList;
+use crate::view::recents::RecentsSort;
+
+/// The game at the top of the recently played list, if there is one and its file is still there.
+pub fn last_played(database: &Database) -> Result<Option<Game>> {
+    let Some(game) = database
+        .select_last_played(1)?
+        .into_iter()
+        .find(|game| game.path.exists())
+    else {
+        return Ok(None);
+    };
+    let mut entries = vec![Entry::Game(game.into())];
+    names::apply_renames(&mut entries, database)?;
+    match entries.pop() {
+        Some(Entry::Game(game)) => Ok(Some(game)),
+        _ => Ok(None),
+    }
+}
+
+/// Shown over the recents tab when the launcher starts, with the box art of the last played game
+/// so that it can be picked up again with a single press of A. Any other button but Left and Right
+/// goes on to the list, by bubbling up `Command::CloseView`.
+#[derive(Debug)]
+pub struct ContinueGame {
+    rect: Rect,
+    res: Resources,
+    game: Game,
+    image: Option<Image>,
+    placeholder: ArtPlaceholder,
+    title: Label<String>,
+    name: Label<String>,
+    play_time: Label<String>,
+    button_hints: Row<ButtonHint<String>>,
+    dirty: bool,
+}
+
+impl ContinueGame {
+    pub fn new(rect: Rect, res: Resources, mut game: Game) -> Self {
+        let Rect { x, y, w, h } = rect;
+        let styles = res.get::<Styles>();
+        let locale = res.get::<Locale>();
+
+        let art_rect = Rect::new(
+            x + 24,
+            y + 8,
+            IMAGE_WIDTH,
+            h - 8 - ButtonIcon::diameter(&styles) - 16,
+        );
+        let image = game.image.image().map(|path| {
+            let mut image = Image::new(art_rect, path.to_path_buf(), ImageMode::Contain);
+            image.set_border_radius(12).decode_in_background();
+            image
+        });
+        let mut placeholder = ArtPlaceholder::new(art_rect);
+        placeholder.set_name(&game.name);
+
+        let text_x = art_rect.x + art_rect.w as i32 + 24;
+        let text_width = Some((x + w as i32 - 24 - text_x).max(0) as u32);
+        let line_height = styles.ui_font.size as i32 + 8;
+        let mut title = Label::new(
+            Point::new(text_x, y + 8),
+            locale.t("continue-title"),
+            Alignment::Left,
+            text_width,
+        );
+        title.color(StylesheetColor::Highlight);
+        let mut name = Label::new(
+            Point::new(text_x, y + 8 + line_height),
+            game.name.clone(),
+            Alignment::Left,
+            text_width,
+        );
+        name.scroll(true);
+        let mut play_time = Label::new(
+            Point::new(text_x, y + 8 + 2 * line_height),
+            game.play_time
+                .map(format_play_time)
+                .filter(|time| !time.is_empty())
+                .map(|time| {
+                    locale.ta(
+                        "continue-play-time",
+                        &[("time".to_string(), time.into())].into_iter().collect(),
+                    )
+                })
+                .unwrap_or_default(),
+            Alignment::Left,
+            text_width,
+        );
+        play_time.color(StylesheetColor::Disabled);
+
+        let mut button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            Vec::with_capacity(2),
+            Alignment::Right,
+            12,
+        );
+        button_hints.push(ButtonHint::new(
+            Point::zero(),
+            Key::A,
+            locale.t("continue-play"),
+            Alignment::Right,
+        ));
+        button_hints.push(ButtonHint::new(
+            Point::zero(),
+            Key::B,
+            locale.t("continue-browse"),
+            Alignment::Right,
+        ));
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            res,
+            game,
+            image,
+            placeholder,
+            title,
+            name,
+            play_time,
+            button_hints,
+            dirty: true,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl View for ContinueGame {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+        if self.dirty {
+            display.load(self.rect)?;
+            self.set_should_draw();
+            self.dirty = false;
+            drawn = true;
+        }
+
+        match self.image.as_mut() {
+            Some(image) => drawn |= image.should_draw() && image.draw(display, styles)?,
+            None => {
+                drawn |= self.placeholder.should_draw() && self.placeholder.draw(display, styles)?
+            }
+        }
+        drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
+        drawn |= self.name.should_draw() && self.name.draw(display, styles)?;
+        drawn |= self.play_time.should_draw() && self.play_time.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn update(&mut self, dt: Duration) {
+        self.name.update(dt);
+        if let Some(image) = self.image.as_mut() {
+            image.update(dt);
+        }
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self
+                .image
+                .as_ref()
+                .map_or(self.placeholder.should_draw(), |image| image.should_draw())
+            || self.title.should_draw()
+            || self.name.should_draw()
+            || self.play_time.should_draw()
+            || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        if let Some(image) = self.image.as_mut() {
+            image.set_should_draw();
+        }
+        self.placeholder.set_should_draw();
+        self.title.set_should_draw();
+        self.name.set_should_draw();
+        self.play_time.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                EntryList::<RecentsSort>::launch_game(&self.res, &mut self.game, commands).await?;
+                Ok(true)
+            }
+            // Switch tabs
+            KeyEvent::Pressed(Key::Left | Key::Right) => Ok(false),
+            KeyEvent::Pressed(_) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        let mut children: Vec<&dyn View> =
+            vec![&self.title, &self.name, &self.play_time, &self.button_hints];
+        match self.image.as_ref() {
+            Some(image) => children.push(image),
+            None => children.push(&self.placeholder),
+        }
+        children
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        let mut children: Vec<&mut dyn View> = vec![
+            &mut self.title,
+            &mut self.name,
+            &mut self.play_time,
+            &mut self.button_hints,
+        ];
+        match self.image.as_mut() {
+            Some(image) => children.push(image),
+            None => children.push(&mut self.placeholder),
+        }
+        children
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+
+    use common::database::NewGame;
+    use common::test_utils::temp_dir;
+
+    use super::*;
+
+    #[test]
+    fn test_last_played() -> Result<()> {
+        let database = Database::in_memory()?;
+        assert_eq!(last_played(&database)?, None);
+
+        let dir = temp_dir("continue");
+        let present = dir.join("Tetris.gb");
+        let missing = dir.join("Pokemon Red.gb");
+        File::create(&present)?;
+        database.update_games(&[NewGame {
+            name: "Tetris".to_string(),
+            path: present.clone(),
+            image: None,
+            core: None,
+        }])?;
+        database.increment_play_count("Tetris", &present, None)?;
+        assert_eq!(last_played(&database)?.unwrap().name, "Tetris");
+
+        // Only the newest game is offered, and not at all once its file is gone
+        database.increment_play_count("Pokemon Red", &missing, None)?;
+        assert_eq!(last_played(&database)?, None);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -1,32 +1,97 @@
-use std::collections::VecDeque;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::iter;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::Local;
+use common::archive::ArchiveError;
 use common::command::Command;
-use common::constants::{IMAGE_WIDTH, SELECTION_MARGIN};
-use common::database::Database;
+use common::constants::{ALLIUM_SD_ROOT, IMAGE_WIDTH};
+use common::database::{Collection, Database};
 use common::display::Display;
+use common::fingerprint;
+use common::format::format_play_time;
 use common::geom::{Alignment, Point, Rect};
+use common::launch_failure::{LaunchError, LaunchFailure};
+use common::list_settings::ListSettings;
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::profile::Profile;
 use common::resources::Resources;
-use common::stylesheet::{Stylesheet, StylesheetColor};
-use common::view::{ButtonHint, ButtonIcon, Image, ImageMode, Row, ScrollList, View};
+use common::stylesheet::{Styles, StylesheetColor};
+use common::trash;
+use common::view::{
+    ButtonHint, ButtonIcon, Counter, Grid, Image, ImageMode, Keyboard, Label, ListNavigation,
+    Notes, Row, ScrollList, Transition, View,
+};
 use embedded_graphics::prelude::{Dimensions, OriginDimensions, Size};
 use embedded_graphics::primitives::{CornerRadii, Primitive, PrimitiveStyle, RoundedRectangle};
 use embedded_graphics::Drawable;
+use log::warn;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
 use crate::consoles::ConsoleMapper;
+use crate::entry::collection::Listing;
+use crate::entry::directory::{Directory, GameCounts};
+use crate::entry::folder_config::{FolderConfig, FolderConfigError};
+use crate::entry::folder_view::{FolderView, FolderViews, ResolvedView, Setting};
+use crate::entry::game::Game;
+use crate::entry::lazy_image::LazyImage;
+use crate::entry::names::QuickFilter;
 use crate::entry::{Entry, Sort};
+use crate::library_filter::LibraryFilter;
+use crate::scraper;
+use crate::view::app::Layout;
+use crate::view::batch::{
+    format_size, related_files, total_size, Batch, BatchAction, BatchProgress, Selection,
+};
+use crate::view::game_options::GameOptions;
+use crate::view::missing_art::MissingArt;
+
+/// Drawn at the start of selected and unselected games in multi-select mode.
+const CHECKED: char = '■';
+const UNCHECKED: char = '□';
+/// Shown before the names of games pinned to the top of the recently played list.
+const PINNED: char = '▲';
+
+/// Narrowest list that play times are shown in while box art takes up the side of the screen.
+const PLAY_TIME_MIN_LIST_WIDTH: u32 = 300;
+
+/// Rows of the box art grid.
+const GRID_ROWS: usize = 2;
+/// Narrowest that cells of the box art grid are made, which decides how many columns fit.
+const GRID_MIN_CELL_WIDTH: u32 = 180;
+
+/// How long the letter jumped to stays on screen.
+const LETTER_DURATION: Duration = Duration::from_millis(750);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EntryListState<S> {
     pub sort: S,
+    #[serde(default)]
     pub selected: usize,
+    #[serde(default)]
+    pub top: usize,
+    #[serde(default = "Option::default")]
     pub child: Option<Box<EntryListState<S>>>,
+    /// Where folders that were closed were left, to open there again. Only the outermost list
+    /// keeps these.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub folders: HashMap<PathBuf, ListPosition>,
+}
+
+/// Where a list was scrolled to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListPosition {
+    pub selected: usize,
+    pub top: usize,
 }
 
 #[derive(Debug)]
@@ -35,6 +100,44 @@ pub struct CoreSelection {
     cores: Vec<String>,
 }
 
+/// What the popup menu is currently showing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuKind {
+    /// Actions for the highlighted entry.
+    Entry,
+    /// Actions for the selected games in multi-select mode.
+    Batch,
+    /// Confirmation before deleting the selected games.
+    ConfirmDelete,
+    /// Confirmation before leaving multi-select mode with games selected.
+    ConfirmExit,
+    /// View settings of the listed folder.
+    FolderView,
+    /// Where to relink a game whose file has moved.
+    ConfirmRelink,
+    /// Whether to play the randomly picked game, or pick another.
+    ConfirmRandom,
+    /// Which collection to add the highlighted game to.
+    AddToCollection,
+    /// Confirmation before deleting the highlighted collection.
+    ConfirmDeleteCollection,
+    /// Confirmation before deleting the highlighted game.
+    ConfirmDeleteGame,
+    /// Which save state to launch the highlighted game from.
+    LaunchState,
+}
+
+/// What a name typed on the keyboard is for.
+#[derive(Debug, Clone)]
+enum Naming {
+    /// A new collection, which the game at the path is added to.
+    NewCollection(PathBuf),
+    /// A new name for the collection with this ID.
+    Collection(i64),
+    /// A new name for the game at the path.
+    Game(PathBuf),
+}
+
 #[derive(Debug)]
 pub struct EntryList<S>
 where
@@ -42,13 +145,66 @@ where
 {
     rect: Rect,
     res: Resources,
+    /// Entries that pass the current quick filter.
     entries: Vec<Entry>,
+    /// All entries, before quick filtering.
+    unfiltered: Vec<Entry>,
+    /// How many entries the listed ones were picked from, if more than are unfiltered.
+    total: Option<usize>,
+    /// How many games the listed folders hold, shown next to them.
+    game_counts: GameCounts,
+    /// Listed entries that the user hid, which are greyed out.
+    hidden: HashSet<PathBuf>,
+    /// Quick filters offered for the entries, if they have titles from a names database.
+    filters: Vec<QuickFilter>,
+    filter: usize,
     sort: S,
+    /// View settings of the listed folder.
+    view: ResolvedView,
     list: ScrollList,
+    /// Box art grid shown instead of the list, if the folder's view settings ask for it. The list
+    /// still keeps track of the highlighted entry.
+    grid: Option<Grid>,
     image: Image,
+    missing_art: MissingArt,
+    /// Shown in place of the list if there is nothing in it, saying why.
+    empty: Label<String>,
+    /// Whether the folder couldn't be read, rather than being empty.
+    unreadable: bool,
     menu: Option<ScrollList>,
+    menu_kind: MenuKind,
+    /// Entries of the open entry menu.
+    menu_entries: Vec<MenuEntry>,
+    /// Where the highlighted game may have moved to, while asking whether to relink it.
+    relink: Vec<PathBuf>,
+    /// Games a random one is picked from, and which was picked.
+    random: Option<RandomPick>,
+    /// Collections offered to add the highlighted game to.
+    collections: Vec<Collection>,
+    /// State slots offered to launch the highlighted game from.
+    state_slots: Vec<i8>,
+    /// Keyboard for naming a collection, while it is open.
+    naming: Option<(Keyboard, Naming)>,
     core: Option<CoreSelection>,
+    /// Selected games, if in multi-select mode.
+    selection: Option<Selection>,
+    batch: Option<BatchProgress>,
+    /// Note of the highlighted game, while it is open.
+    notes: Option<Notes>,
+    /// Options of the highlighted game, while they are open.
+    game_options: Option<GameOptions>,
+    letter: Option<LetterOverlay>,
+    /// Where the letter was drawn before it was hidden.
+    hidden_letter: Option<Rect>,
     button_hints: Row<ButtonHint<String>>,
+    /// Position of the highlighted entry, in the header.
+    counter: Counter,
+    /// What the counter was last set to.
+    count: Option<ListCount>,
+    /// Clears the list's region when a folder is opened or closed.
+    transition: Transition,
+    /// Where closed folders were left, shared by the lists of folders open in this one.
+    folders: Rc<RefCell<HashMap<PathBuf, ListPosition>>>,
     pub child: Option<Box<EntryList<S>>>,
 }
 
@@ -59,9 +215,9 @@ where
     pub fn new(rect: Rect, res: Resources, sort: S) -> Result<Self> {
         let Rect { x, y, w, h } = rect;
 
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
-        let list = ScrollList::new(
+        let mut list = ScrollList::new(
             Rect::new(
                 x + 12,
                 y + 8,
@@ -70,19 +226,19 @@ where
             ),
             Vec::new(),
             Alignment::Left,
-            res.get::<Stylesheet>().ui_font.size + SELECTION_MARGIN,
+            res.get::<Styles>().row_layout(),
         );
+        list.set_list_settings(*res.get::<ListSettings>());
 
-        let mut image = Image::empty(
-            Rect::new(
-                x + w as i32 - IMAGE_WIDTH as i32 - 24,
-                y + 8,
-                IMAGE_WIDTH,
-                h - 8 - ButtonIcon::diameter(&styles) - 8,
-            ),
-            ImageMode::Contain,
+        let image_rect = Rect::new(
+            x + w as i32 - IMAGE_WIDTH as i32 - 24,
+            y + 8,
+            IMAGE_WIDTH,
+            h - 8 - ButtonIcon::diameter(&styles) - 8,
         );
-        image.set_border_radius(12);
+        let mut image = Image::empty(image_rect, ImageMode::Contain);
+        image.set_border_radius(12).decode_in_background();
+        let missing_art = MissingArt::new(image_rect, res.clone());
 
         let mut button_hints = Row::new(
             Point::new(
@@ -112,66 +268,180 @@ where
             }
         }
 
+        let counter = Counter::new(Layout::for_content(rect, &styles).counter);
+
+        let mut empty = Label::new(
+            Point::new(
+                x + w as i32 / 2,
+                y + (h - ButtonIcon::diameter(&styles) - styles.ui_font.size) as i32 / 2,
+            ),
+            String::new(),
+            Alignment::Center,
+            Some(w - 48),
+        );
+        empty.color(StylesheetColor::Disabled);
+
         drop(styles);
 
+        let view = Self::resolve_view(&res, &sort);
+
         let mut this = Self {
             rect,
             res,
             entries: vec![],
+            unfiltered: vec![],
+            total: None,
+            game_counts: GameCounts::default(),
+            hidden: HashSet::new(),
+            filters: vec![],
+            filter: 0,
             sort,
+            view,
             list,
+            grid: None,
             image,
+            missing_art,
+            empty,
+            unreadable: false,
             menu: None,
+            menu_kind: MenuKind::Entry,
+            menu_entries: Vec::new(),
+            relink: Vec::new(),
+            random: None,
+            collections: Vec::new(),
+            state_slots: Vec::new(),
+            naming: None,
             core: None,
+            selection: None,
+            batch: None,
+            notes: None,
+            game_options: None,
+            letter: None,
+            hidden_letter: None,
             button_hints,
+            counter,
+            count: None,
+            transition: Transition::default(),
+            folders: Rc::default(),
             child: None,
         };
 
         this.load_entries()?;
+        this.layout_grid();
 
         Ok(this)
     }
 
     pub fn save(&self) -> EntryListState<S> {
+        let mut state = self.save_open();
+        state.folders = self.folders.borrow().clone();
+        state
+    }
+
+    /// State of this list and of the folders open in it.
+    fn save_open(&self) -> EntryListState<S> {
         EntryListState {
             sort: self.sort.clone(),
             selected: self.list.selected(),
-            child: self.child.as_ref().map(|c| Box::new(c.save())),
+            top: self.list.top(),
+            child: self.child.as_ref().map(|c| Box::new(c.save_open())),
+            folders: HashMap::new(),
         }
     }
 
-    pub fn load(rect: Rect, res: Resources, state: EntryListState<S>) -> Result<Self> {
+    pub fn load(rect: Rect, res: Resources, mut state: EntryListState<S>) -> Result<Self> {
+        let mut folders = mem::take(&mut state.folders);
+        folders.retain(|path, _| path.is_dir());
+        Self::load_open(rect, res, state, Rc::new(RefCell::new(folders)))
+    }
+
+    fn load_open(
+        rect: Rect,
+        res: Resources,
+        state: EntryListState<S>,
+        folders: Rc<RefCell<HashMap<PathBuf, ListPosition>>>,
+    ) -> Result<Self> {
         let mut this = Self::new(rect, res.clone(), state.sort)?;
-        this.select(state.selected);
+        this.list.select_at(state.selected, state.top);
         if let Some(child) = state.child {
-            this.child = Some(Box::new(Self::load(rect, res, *child)?));
+            this.child = Some(Box::new(Self::load_open(
+                rect,
+                res,
+                *child,
+                Rc::clone(&folders),
+            )?));
         }
+        this.folders = folders;
         Ok(this)
     }
 
-    pub fn select(&mut self, index: usize) {
-        self.list.select(index);
+    /// Goes back to where the listed folder was left when it was last closed.
+    fn restore_position(&mut self) {
+        let position = self
+            .sort
+            .folder()
+            .and_then(|folder| self.folders.borrow().get(folder).copied());
+        if let Some(position) = position {
+            self.list.select_at(position.selected, position.top);
+        }
+    }
+
+    /// Remembers where the listed folder is left as it is closed.
+    fn remember_position(&self) {
+        if let Some(folder) = self.sort.folder() {
+            self.folders.borrow_mut().insert(
+                folder.to_path_buf(),
+                ListPosition {
+                    selected: self.list.selected(),
+                    top: self.list.top(),
+                },
+            );
+        }
+    }
+
+    /// View settings of the listed folder, or the global defaults if the list isn't a folder.
+    fn resolve_view(res: &Resources, sort: &S) -> ResolvedView {
+        let styles = res.get::<Styles>();
+        match sort.folder() {
+            Some(dir) => res.get::<FolderViews>().resolve(dir, &res.get(), &styles),
+            None => ResolvedView::global(&styles),
+        }
     }
 
     async fn select_entry(&mut self, commands: Sender<Command>) -> Result<()> {
+        if let Some(Entry::Game(game)) = self.entries.get(self.list.selected()) {
+            if !game.path.exists() {
+                let candidates = fingerprint::find_moved(&self.res.get(), &game.path)?;
+                if !candidates.is_empty() {
+                    self.confirm_relink(candidates);
+                    commands.send(Command::Redraw).await?;
+                    return Ok(());
+                }
+            }
+        }
+
         if let Some(entry) = self.entries.get_mut(self.list.selected()) {
             match entry {
                 Entry::Directory(dir) => {
-                    let child = EntryList::new(
+                    let mut child = EntryList::new(
                         self.rect,
                         self.res.clone(),
                         self.sort.with_directory(dir.clone()),
                     )?;
+                    self.counter.hand_over(&mut child.counter);
+                    child.folders = Rc::clone(&self.folders);
+                    child.restore_position();
+                    // Rather than launching its games with the wrong core later
+                    if dir.listing.is_none() {
+                        if let Err(e) = FolderConfig::for_dir(&dir.path) {
+                            Self::toast_folder_config(&self.res, &e, commands.clone()).await?;
+                        }
+                    }
                     self.child = Some(Box::new(child));
+                    self.transition.start(self.rect);
                 }
                 Entry::Game(game) => {
-                    let command = self
-                        .res
-                        .get::<ConsoleMapper>()
-                        .launch_game(&self.res.get(), game)?;
-                    if let Some(cmd) = command {
-                        commands.send(cmd).await?;
-                    }
+                    Self::launch_game(&self.res, game, commands).await?;
                 }
                 Entry::App(app) => {
                     commands.send(app.command()).await?;
@@ -181,54 +451,1065 @@ where
         Ok(())
     }
 
+    pub async fn launch_game(
+        res: &Resources,
+        game: &mut Game,
+        commands: Sender<Command>,
+    ) -> Result<()> {
+        let result = res.get::<ConsoleMapper>().launch_game(&res.get(), game);
+        let command = match result {
+            Ok(command) => Some(command),
+            Err(e) if e.is::<FolderConfigError>() => {
+                Self::toast_folder_config(res, &e, commands.clone()).await?;
+                None
+            }
+            Err(e) if e.is::<LaunchError>() => {
+                warn!("can't launch {}: {}", game.path.display(), e);
+                let file_name = game
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| game.name.clone());
+                let error = e.downcast::<LaunchError>()?;
+                commands
+                    .send(Command::ShowLaunchFailure(LaunchFailure::not_started(
+                        file_name, error,
+                    )))
+                    .await?;
+                None
+            }
+            Err(e) => {
+                let Some(error) = e.downcast_ref::<ArchiveError>() else {
+                    return Err(e);
+                };
+                warn!("failed to open {}: {:#}", game.path.display(), e);
+                let key = match error {
+                    ArchiveError::PasswordProtected => "archive-password-protected",
+                    ArchiveError::Corrupt => "archive-corrupt",
+                };
+                let toast = res.get::<Locale>().ta(
+                    key,
+                    &[("name".to_string(), game.name.clone().into())]
+                        .into_iter()
+                        .collect(),
+                );
+                commands
+                    .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+                    .await?;
+                None
+            }
+        };
+        if let Some(cmd) = command {
+            commands.send(cmd).await?;
+        }
+        Ok(())
+    }
+
+    /// Says that a folder's `.allium.toml` couldn't be read, if that's what `e` is.
+    async fn toast_folder_config(
+        res: &Resources,
+        e: &anyhow::Error,
+        commands: Sender<Command>,
+    ) -> Result<()> {
+        let Some(error) = e.downcast_ref::<FolderConfigError>() else {
+            return Ok(());
+        };
+        warn!("invalid folder config: {}", error);
+        let path = error
+            .path
+            .strip_prefix(ALLIUM_SD_ROOT.as_path())
+            .unwrap_or(&error.path);
+        let toast = res.get::<Locale>().ta(
+            "folder-config-invalid",
+            &[
+                ("path".to_string(), path.display().to_string().into()),
+                ("error".to_string(), error.message.clone().into()),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(5))))
+            .await?;
+        Ok(())
+    }
+
+    /// Picks a random game from the listed folder and the folders in it, and asks whether to play
+    /// it. Hidden games are left out, even if hidden games are shown.
+    async fn pick_random_game(&mut self, commands: Sender<Command>) -> Result<()> {
+        let Some(folder) = self.sort.folder() else {
+            return Ok(());
+        };
+        let filter = LibraryFilter::new(&self.res.get(), &self.res.get())?;
+        let games = Directory::new(folder.to_path_buf()).games(
+            &self.res.get(),
+            &self.res.get(),
+            &filter,
+        )?;
+        let Some(random) = RandomPick::new(games, &mut rand::thread_rng()) else {
+            let toast = self.res.get::<Locale>().t("random-game-none");
+            commands
+                .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+                .await?;
+            return Ok(());
+        };
+        self.random = Some(random);
+        self.confirm_random_game();
+        commands.send(Command::Redraw).await?;
+        Ok(())
+    }
+
+    fn confirm_random_game(&mut self) {
+        let Some(random) = self.random.as_ref() else {
+            return;
+        };
+        let locale = self.res.get::<Locale>();
+        let items = vec![
+            locale.ta(
+                "random-game-play",
+                &[("name".to_string(), random.game().name.clone().into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            locale.t("random-game-reroll"),
+            locale.t("random-game-cancel"),
+        ];
+        drop(locale);
+        self.open_popup(items, MenuKind::ConfirmRandom);
+    }
+
+    /// Picks another random game, keeping the popup where it is.
+    fn reroll_random_game(&mut self) {
+        let Some(random) = self.random.as_mut() else {
+            return;
+        };
+        random.reroll(&mut rand::thread_rng());
+        let selected = self.menu.as_ref().map_or(0, ScrollList::selected);
+        self.confirm_random_game();
+        if let Some(menu) = self.menu.as_mut() {
+            menu.select(selected);
+        }
+    }
+
+    /// Asks which collection to add the highlighted game to, or whether to start a new one.
+    fn offer_collections(&mut self) -> Result<()> {
+        self.collections = self.res.get::<Database>().collections()?;
+        let mut items: Vec<_> = self.collections.iter().map(|c| c.name.clone()).collect();
+        items.push(self.res.get::<Locale>().t("collection-new"));
+        self.open_popup(items, MenuKind::AddToCollection);
+        Ok(())
+    }
+
+    /// Offers the save states of the highlighted game to launch it from, newest first.
+    fn offer_state_slots(&mut self) -> Result<()> {
+        let Some(Entry::Game(game)) = self.entries.get(self.list.selected()) else {
+            return Ok(());
+        };
+        let slots = self
+            .res
+            .get::<ConsoleMapper>()
+            .save_states(&self.res.get(), game)?
+            .saved_slots();
+        let locale = self.res.get::<Locale>();
+        let items = slots
+            .iter()
+            .map(|(slot, timestamp)| {
+                locale.ta(
+                    "menu-launch-state-slot",
+                    &[
+                        ("slot".to_string(), (*slot).into()),
+                        (
+                            "time".to_string(),
+                            timestamp
+                                .with_timezone(&Local)
+                                .format("%Y-%m-%d %H:%M")
+                                .to_string()
+                                .into(),
+                        ),
+                    ]
+                    .into_iter()
+                    .collect(),
+                )
+            })
+            .collect();
+        drop(locale);
+        self.state_slots = slots.into_iter().map(|(slot, _)| slot).collect();
+        self.open_popup(items, MenuKind::LaunchState);
+        Ok(())
+    }
+
+    /// The ID of the highlighted collection, if a collection is highlighted.
+    fn selected_collection(&self) -> Option<i64> {
+        match self.entries.get(self.list.selected()) {
+            Some(Entry::Directory(Directory {
+                listing: Some(Listing::Collection(id)),
+                ..
+            })) => Some(*id),
+            _ => None,
+        }
+    }
+
+    fn confirm_delete_collection(&mut self) {
+        let Some(Entry::Directory(dir)) = self.entries.get(self.list.selected()) else {
+            return;
+        };
+        let locale = self.res.get::<Locale>();
+        let items = vec![
+            locale.ta(
+                "collection-confirm-delete",
+                &[("name".to_string(), dir.name.clone().into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            locale.t("collection-cancel"),
+        ];
+        drop(locale);
+        self.open_popup(items, MenuKind::ConfirmDeleteCollection);
+        // Default to keeping it
+        if let Some(menu) = self.menu.as_mut() {
+            menu.select(1);
+        }
+    }
+
+    async fn toast_added(&self, name: &str, commands: Sender<Command>) -> Result<()> {
+        let toast = self.res.get::<Locale>().ta(
+            "collection-added",
+            &[("name".to_string(), name.to_string().into())]
+                .into_iter()
+                .collect(),
+        );
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(2))))
+            .await?;
+        Ok(())
+    }
+
+    /// Renames a game or collection, or creates a collection, with the name typed on the keyboard.
+    async fn finish_naming(&mut self, name: String, commands: Sender<Command>) -> Result<()> {
+        let Some((_, naming)) = self.naming.take() else {
+            return Ok(());
+        };
+        let name = name.trim();
+        match naming {
+            // An empty name goes back to the one from the file
+            Naming::Game(path) => self.res.get::<Database>().set_game_name(&path, name)?,
+            Naming::NewCollection(_) | Naming::Collection(_) => {
+                if name.is_empty() || !self.name_collection(naming, name, commands).await? {
+                    return Ok(());
+                }
+            }
+        }
+
+        let index = self.list.selected();
+        self.load_entries()?;
+        self.list.select(index);
+        Ok(())
+    }
+
+    /// Creates or renames a collection, unless another one has the name. Returns whether it did.
+    async fn name_collection(
+        &self,
+        naming: Naming,
+        name: &str,
+        commands: Sender<Command>,
+    ) -> Result<bool> {
+        let collections = self.res.get::<Database>().collections()?;
+        let renamed = match naming {
+            Naming::Collection(id) => Some(id),
+            Naming::NewCollection(_) | Naming::Game(_) => None,
+        };
+        if collections
+            .iter()
+            .any(|c| c.name == name && Some(c.id) != renamed)
+        {
+            let toast = self.res.get::<Locale>().ta(
+                "collection-exists",
+                &[("name".to_string(), name.to_string().into())]
+                    .into_iter()
+                    .collect(),
+            );
+            commands
+                .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+                .await?;
+            return Ok(false);
+        }
+        let added = {
+            let database = self.res.get::<Database>();
+            match naming {
+                Naming::NewCollection(path) => {
+                    let collection = database.create_collection(name)?;
+                    database.add_to_collection(collection.id, &path)?;
+                    true
+                }
+                Naming::Collection(id) => {
+                    database.rename_collection(id, name)?;
+                    false
+                }
+                Naming::Game(_) => unreachable!(),
+            }
+        };
+
+        if added {
+            self.toast_added(name, commands).await?;
+        }
+        Ok(true)
+    }
+
     pub fn sort(&mut self, sort: S) -> Result<()> {
+        let selected = self
+            .entries
+            .get(self.list.selected())
+            .map(|e| e.path().to_path_buf());
         self.sort = sort;
         self.load_entries()?;
-        if S::HAS_BUTTON_HINTS {
-            self.button_hints
-                .get_mut(1)
-                .unwrap()
-                .set_text(self.sort.button_hint(&self.res.get::<Locale>()));
+        // Keep the highlighted entry wherever it moved to
+        if let Some(index) =
+            selected.and_then(|path| self.entries.iter().position(|e| e.path() == path))
+        {
+            self.list.select(index);
         }
+        self.update_button_hints();
         Ok(())
     }
 
+    pub fn current_sort(&self) -> &S {
+        &self.sort
+    }
+
+    /// The highlighted game in the innermost open list, unless a menu or another mode has focus.
+    pub fn selected_game(&self) -> Option<&Game> {
+        if let Some(child) = self.child.as_ref() {
+            return child.selected_game();
+        }
+        if self.menu.is_some()
+            || self.selection.is_some()
+            || self.batch.is_some()
+            || self.notes.is_some()
+            || self.game_options.is_some()
+        {
+            return None;
+        }
+        match self.entries.get(self.list.selected()) {
+            Some(Entry::Game(game)) => Some(game),
+            _ => None,
+        }
+    }
+
     fn load_entries(&mut self) -> Result<()> {
-        self.entries = self.sort.entries(&self.res.get(), &self.res.get())?;
+        let mut filter = LibraryFilter::new(&self.res.get(), &self.res.get())?;
+        filter.include_hidden = self.res.get::<ListSettings>().show_hidden;
+        let was_empty = self.unfiltered.is_empty();
+        // A folder that can't be read is shown as such, rather than breaking the whole tab
+        let listed = read_entries(&self.sort, &self.res.get(), &self.res.get(), &filter);
+        self.unreadable = listed.is_none();
+        (self.unfiltered, self.total) = listed.unwrap_or_default();
+        let key = if self.unreadable {
+            "entries-unreadable"
+        } else {
+            "entries-empty"
+        };
+        self.empty.set_text(self.res.get::<Locale>().t(key));
+        if was_empty != self.unfiltered.is_empty() {
+            self.transition.start(self.rect);
+        }
+        if self.sort.folder().is_some() {
+            let dirs = self.unfiltered.iter().filter_map(|e| match e {
+                Entry::Directory(dir) if dir.listing.is_none() => Some(dir.path.as_path()),
+                Entry::Directory(_) | Entry::Game(_) | Entry::App(_) => None,
+            });
+            self.game_counts
+                .start(dirs, &self.res.get(), &self.res.get::<ConsoleMapper>());
+        }
+        self.hidden = self
+            .unfiltered
+            .iter()
+            .map(Entry::path)
+            .filter(|path| filter.is_hidden(path))
+            .map(Path::to_path_buf)
+            .collect();
+
+        // Keep the current filter if it still applies
+        let filters = QuickFilter::for_games(self.unfiltered.iter().filter_map(|e| match e {
+            Entry::Game(game) => Some(game),
+            Entry::App(_) | Entry::Directory(_) => None,
+        }));
+        self.filter = self
+            .filters
+            .get(self.filter)
+            .and_then(|filter| filters.iter().position(|f| f == filter))
+            .unwrap_or_default();
+        self.filters = filters;
+
+        self.filter_entries();
+        Ok(())
+    }
+
+    /// How far down the list the highlighted entry is, or how many entries are listed if only some
+    /// are.
+    fn count(&self) -> ListCount {
+        ListCount::new(
+            self.list.selected(),
+            self.entries.len(),
+            self.total.unwrap_or(self.unfiltered.len()),
+        )
+    }
+
+    /// Lists the entries that pass the current quick filter.
+    fn filter_entries(&mut self) {
+        self.entries = match self.filters.get(self.filter) {
+            Some(filter) => self
+                .unfiltered
+                .iter()
+                .filter(|e| filter.matches(e))
+                .cloned()
+                .collect(),
+            None => self.unfiltered.clone(),
+        };
+        if let Some(selection) = self.selection.as_mut() {
+            let listed: HashSet<_> = self.entries.iter().map(Entry::path).collect();
+            selection.retain(|path| listed.contains(path));
+        }
         self.list.set_items(
-            self.entries.iter().map(|e| e.name().to_string()).collect(),
+            self.entries.iter().map(|e| self.entry_text(e)).collect(),
             false,
         );
-        Ok(())
+        self.grey_out_unavailable();
+        self.show_details();
+        self.set_grid_items(false);
+        self.update_filter_hint();
     }
 
-    fn open_menu(&mut self) -> Result<()> {
+    /// Moves to the next or previous quick filter.
+    fn cycle_filter(&mut self, forward: bool) {
+        let len = self.filters.len();
+        self.filter = if forward {
+            (self.filter + 1) % len
+        } else {
+            (self.filter + len - 1) % len
+        };
+        self.filter_entries();
+        self.list.select(0);
+        self.update_button_hints();
+    }
+
+    /// Shows the current quick filter after the other button hints, if there are filters.
+    fn update_filter_hint(&mut self) {
+        let index = if S::HAS_BUTTON_HINTS { 2 } else { 1 };
+        let text = self
+            .filters
+            .get(self.filter)
+            .map(|filter| filter.text(&self.res.get()));
+        match text {
+            Some(text) if self.button_hints.len() > index => {
+                self.button_hints.get_mut(index).unwrap().set_text(text);
+            }
+            Some(text) => self.button_hints.push(ButtonHint::new(
+                Point::zero(),
+                Key::R2,
+                text,
+                Alignment::Right,
+            )),
+            None if self.button_hints.len() > index => {
+                self.button_hints.pop();
+            }
+            None => {}
+        }
+    }
+
+    /// Text of an entry in the list, with a checkbox in front of games in multi-select mode, and a
+    /// mark in front of pinned games.
+    fn entry_text(&self, entry: &Entry) -> String {
+        let name = if self.view.full_names.value {
+            entry.full_name()
+        } else {
+            entry.name()
+        };
+        match (self.selection.as_ref(), entry) {
+            (Some(selection), Entry::Game(game)) => {
+                let checkbox = if selection.contains(&game.path) {
+                    CHECKED
+                } else {
+                    UNCHECKED
+                };
+                format!("{} {}", checkbox, name)
+            }
+            (None, Entry::Game(game)) if game.pinned => format!("{} {}", PINNED, name),
+            _ => name.to_string(),
+        }
+    }
+
+    fn update_button_hints(&mut self) {
+        let locale = self.res.get::<Locale>();
+        let (select, sort) = if let Some(selection) = self.selection.as_ref() {
+            let all_selected = selection.len() == self.games().count();
+            (
+                locale.t("batch-toggle"),
+                if all_selected {
+                    locale.t("batch-select-none")
+                } else {
+                    locale.t("batch-select-all")
+                },
+            )
+        } else {
+            (locale.t("button-select"), self.sort.button_hint(&locale))
+        };
+
+        self.button_hints.get_mut(0).unwrap().set_text(select);
+        if S::HAS_BUTTON_HINTS {
+            self.button_hints.get_mut(1).unwrap().set_text(sort);
+        }
+    }
+
+    fn games(&self) -> impl Iterator<Item = &Path> {
+        self.entries.iter().filter_map(|e| match e {
+            Entry::Game(game) => Some(game.path.as_path()),
+            Entry::App(_) | Entry::Directory(_) => None,
+        })
+    }
+
+    fn enter_multi_select(&mut self) {
+        self.selection = Some(Selection::new());
+        self.refresh_rows();
+        self.update_button_hints();
+    }
+
+    fn exit_multi_select(&mut self) {
+        self.selection = None;
+        self.refresh_rows();
+        self.update_button_hints();
+    }
+
+    /// Leaves multi-select mode, asking first if any games are selected. Returns true if the
+    /// mode was left.
+    fn try_exit_multi_select(&mut self) -> bool {
+        match self.selection.as_ref() {
+            Some(selection) if !selection.is_empty() => {
+                let locale = self.res.get::<Locale>();
+                let items = vec![
+                    locale.ta(
+                        "batch-discard-selection",
+                        &[("count".to_string(), selection.len().into())]
+                            .into_iter()
+                            .collect(),
+                    ),
+                    locale.t("batch-keep-selecting"),
+                ];
+                drop(locale);
+                self.open_popup(items, MenuKind::ConfirmExit);
+                false
+            }
+            Some(_) => {
+                self.exit_multi_select();
+                true
+            }
+            None => true,
+        }
+    }
+
+    fn toggle_selected(&mut self) {
+        let i = self.list.selected();
+        if let (Some(selection), Some(Entry::Game(game))) =
+            (self.selection.as_mut(), self.entries.get(i))
+        {
+            selection.toggle(&game.path);
+            let text = self.entry_text(&self.entries[i]);
+            self.list.set_item(i, text);
+            self.update_button_hints();
+        }
+    }
+
+    /// Selects every game in the list, or none if they are all selected already.
+    fn toggle_all(&mut self) {
+        let paths: Vec<_> = self.games().map(Path::to_path_buf).collect();
+        if let Some(selection) = self.selection.as_mut() {
+            if selection.len() == paths.len() {
+                selection.clear();
+            } else {
+                selection.select_all(paths.iter().map(|p| p.as_path()));
+            }
+        }
+        self.refresh_rows();
+        self.update_button_hints();
+    }
+
+    fn refresh_rows(&mut self) {
+        let items = self.entries.iter().map(|e| self.entry_text(e)).collect();
+        self.list.set_items(items, true);
+        self.grey_out_unavailable();
+        self.show_details();
+        self.set_grid_items(true);
+    }
+
+    /// Shows the entries as a grid of box art instead of a list, if the folder's view settings ask
+    /// for it.
+    fn layout_grid(&mut self) {
+        if self.sort.folder().is_none() || !self.view.grid.value {
+            self.grid = None;
+            return;
+        }
+        let styles = self.res.get::<Styles>();
         let Rect { x, y, w, h } = self.rect;
-        let styles = self.res.get::<Stylesheet>();
+        let rect = Rect::new(
+            x + 12,
+            y + 8,
+            w - 24,
+            h - 8 - ButtonIcon::diameter(&styles) - 8,
+        );
+        let columns = (rect.w / GRID_MIN_CELL_WIDTH).clamp(3, 4) as usize;
+        self.grid = Some(Grid::new(rect, columns, GRID_ROWS, &styles));
+        drop(styles);
+        self.set_grid_items(false);
+    }
+
+    /// Gives the grid the listed entries, if it is shown.
+    fn set_grid_items(&mut self, preserve_selection: bool) {
+        if self.grid.is_none() {
+            return;
+        }
+        let items = self.entries.iter().map(|e| self.entry_text(e)).collect();
+        if let Some(grid) = self.grid.as_mut() {
+            grid.set_items(items, preserve_selection);
+            grid.select(self.list.selected());
+        }
+    }
+
+    /// Shows how many games are in each listed folder that has been counted, and how long each
+    /// game was played in lists that show it and have room for it next to the box art.
+    fn show_details(&mut self) {
+        let locale = self.res.get::<Locale>();
+        let play_times = S::HAS_PLAY_TIME
+            && (!self.view.box_art.value
+                || self.list.bounding_box(&self.res.get()).w >= PLAY_TIME_MIN_LIST_WIDTH);
+        for (i, entry) in self.entries.iter().enumerate() {
+            let detail = match entry {
+                Entry::Directory(dir) => self.game_counts.get(&dir.path).map(|count| {
+                    locale.ta(
+                        "directory-game-count",
+                        &[("count".to_string(), count.into())].into_iter().collect(),
+                    )
+                }),
+                Entry::Game(game) if play_times => game
+                    .play_time
+                    .map(format_play_time)
+                    .filter(|time| !time.is_empty()),
+                Entry::Game(_) | Entry::App(_) => continue,
+            };
+            self.list.set_detail(i, detail);
+        }
+    }
+
+    /// Greys out hidden entries, and games whose files are gone from lists that aren't of a folder.
+    fn grey_out_unavailable(&mut self) {
+        let check_missing = self.sort.folder().is_none();
+        for (i, entry) in self.entries.iter().enumerate() {
+            let missing =
+                check_missing && matches!(entry, Entry::Game(game) if !game.path.exists());
+            if missing || self.hidden.contains(entry.path()) {
+                self.list.set_disabled(i, true);
+            }
+        }
+    }
+
+    fn open_batch_menu(&mut self) {
+        let locale = self.res.get::<Locale>();
+        let items = BatchAction::ALL.iter().map(|a| a.text(&locale)).collect();
+        drop(locale);
+        self.open_popup(items, MenuKind::Batch);
+    }
+
+    fn confirm_delete(&mut self) {
+        let Some(selection) = self.selection.as_ref() else {
+            return;
+        };
+        let paths = selection.paths();
+        let files: Vec<_> = paths
+            .iter()
+            .flat_map(|path| iter::once(path.clone()).chain(related_files(path)))
+            .collect();
         let locale = self.res.get::<Locale>();
+        let items = vec![
+            locale.ta(
+                "batch-confirm-delete",
+                &[
+                    ("count".to_string(), paths.len().into()),
+                    ("size".to_string(), format_size(total_size(&files)).into()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            locale.t("batch-cancel"),
+        ];
+        drop(locale);
+        self.open_popup(items, MenuKind::ConfirmDelete);
+        if let Some(menu) = self.menu.as_mut() {
+            // Default to cancelling
+            menu.select(1);
+        }
+    }
+
+    fn confirm_delete_game(&mut self) {
+        let Some(Entry::Game(game)) = self.entries.get(self.list.selected()) else {
+            return;
+        };
+        let mut files = vec![game.path.clone()];
+        files.extend(related_files(&game.path));
+        let locale = self.res.get::<Locale>();
+        let items = vec![
+            locale.ta(
+                "confirm-delete-game",
+                &[
+                    ("name".to_string(), game.name.clone().into()),
+                    ("size".to_string(), format_size(total_size(&files)).into()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            locale.t("batch-cancel"),
+        ];
+        drop(locale);
+        self.open_popup(items, MenuKind::ConfirmDeleteGame);
+        if let Some(menu) = self.menu.as_mut() {
+            // Default to cancelling
+            menu.select(1);
+        }
+    }
+
+    /// Moves the highlighted game and the files that go with it to the trash, offering to undo it.
+    async fn delete_game(&mut self, commands: Sender<Command>) -> Result<()> {
+        let Some(Entry::Game(game)) = self.entries.get(self.list.selected()) else {
+            return Ok(());
+        };
+        let id = trash::delete_game(
+            &self.res.get(),
+            &self.res.get(),
+            &game.path,
+            &related_files(&game.path),
+        )?;
+        let text = self.res.get::<Locale>().ta(
+            "undo-delete-game",
+            &[("name".to_string(), game.name.clone().into())]
+                .into_iter()
+                .collect(),
+        );
+        let index = self.list.selected();
+        self.load_entries()?;
+        self.list.select(index);
+        commands.send(Command::OfferUndo(text, vec![id])).await?;
+        Ok(())
+    }
 
-        let mut entries = vec![
-            MenuEntry::Launch(None),
-            MenuEntry::RemoveFromRecents,
-            MenuEntry::RepopulateDatabase,
+    /// Shows the view settings of the listed folder, and where each comes from.
+    fn open_folder_view_menu(&mut self, selected: usize) {
+        let locale = self.res.get::<Locale>();
+        let text = |name: &str, setting: Setting| {
+            let value = if setting.value {
+                "folder-view-on"
+            } else {
+                "folder-view-off"
+            };
+            locale.ta(
+                "folder-view-setting",
+                &[
+                    ("name".to_string(), locale.t(name).into()),
+                    ("value".to_string(), locale.t(value).into()),
+                    ("source".to_string(), setting.source.text(&locale).into()),
+                ]
+                .into_iter()
+                .collect(),
+            )
+        };
+        let items = vec![
+            text("folder-view-box-art", self.view.box_art),
+            text("folder-view-full-names", self.view.full_names),
+            text("folder-view-grid", self.view.grid),
+            locale.t("folder-view-reset"),
         ];
+        drop(locale);
+        self.open_popup(items, MenuKind::FolderView);
+        if let Some(menu) = self.menu.as_mut() {
+            menu.select(selected);
+        }
+    }
+
+    /// Toggles a view setting of the listed folder, or resets them to the defaults.
+    fn change_folder_view(&mut self, selected: usize) -> Result<()> {
+        let Some(dir) = self.sort.folder() else {
+            return Ok(());
+        };
+        let mut folder_views = self.res.get::<FolderViews>().clone();
+        let mut view = folder_views.get(dir).copied().unwrap_or_default();
+        match selected {
+            0 => view.box_art = Some(!self.view.box_art.value),
+            1 => view.full_names = Some(!self.view.full_names.value),
+            2 => view.grid = Some(!self.view.grid.value),
+            _ => view = FolderView::default(),
+        }
+        folder_views.set(dir, view);
+        folder_views.save()?;
+        self.res.insert(folder_views);
+
+        self.view = Self::resolve_view(&self.res, &self.sort);
+        self.refresh_rows();
+        self.image.set_should_draw();
+        if self.view.grid.value != self.grid.is_some() {
+            self.layout_grid();
+            self.transition.start(self.rect);
+        }
+        Ok(())
+    }
+
+    /// Asks whether to point the highlighted game, whose file is gone, to where it may have moved.
+    fn confirm_relink(&mut self, candidates: Vec<PathBuf>) {
+        let locale = self.res.get::<Locale>();
+        let mut items: Vec<_> = candidates
+            .iter()
+            .map(|path| {
+                let path = path.strip_prefix(ALLIUM_SD_ROOT.as_path()).unwrap_or(path);
+                locale.ta(
+                    "relink-found",
+                    &[("path".to_string(), path.display().to_string().into())]
+                        .into_iter()
+                        .collect(),
+                )
+            })
+            .collect();
+        items.push(locale.t("relink-cancel"));
+        drop(locale);
+        self.relink = candidates;
+        self.open_popup(items, MenuKind::ConfirmRelink);
+    }
+
+    fn start_batch(&mut self, action: BatchAction) {
+        let Some(selection) = self.selection.as_ref() else {
+            return;
+        };
+        let batch = Batch::new(action, selection.paths());
+        self.batch = Some(BatchProgress::new(self.res.clone(), batch));
+    }
+
+    /// How many entries L and R move by.
+    fn page_size(&self) -> usize {
+        self.grid
+            .as_ref()
+            .map_or(self.list.visible_count(), Grid::page_size)
+    }
+
+    /// Navigation keys, shared by the normal and multi-select modes.
+    async fn handle_list_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::L2 | Key::R2) if self.filters.len() > 1 => {
+                self.cycle_filter(event == KeyEvent::Pressed(Key::R2));
+                commands.send(Command::Redraw).await?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::L) | KeyEvent::Autorepeat(Key::L)
+                if self.sort.is_alphabetical() =>
+            {
+                self.jump_letter(false);
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::R) | KeyEvent::Autorepeat(Key::R)
+                if self.sort.is_alphabetical() =>
+            {
+                self.jump_letter(true);
+                Ok(true)
+            }
+            KeyEvent::Pressed(key @ (Key::L | Key::R))
+            | KeyEvent::Autorepeat(key @ (Key::L | Key::R)) => {
+                let mut navigation = ListNavigation {
+                    top: self.list.top(),
+                    selected: self.list.selected(),
+                    len: self.entries.len(),
+                    visible: self.page_size(),
+                };
+                navigation.page(key == Key::R, self.res.get::<ListSettings>().wrap_pages);
+                self.list.select(navigation.selected);
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::L2) => {
+                let selected = self.list.selected();
+                let len = self.entries.len();
+                let mut entries = self
+                    .entries
+                    .iter()
+                    .rev()
+                    .skip(len - selected)
+                    .map(|e| e.name().chars().next());
+                let Some(char) = entries.next() else {
+                    self.list.select(0);
+                    return Ok(true);
+                };
+
+                if let Some(i) = entries.position(|c| c != char) {
+                    self.list.select(selected - i - 1);
+                } else {
+                    self.list.select(0);
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::R2) => {
+                let selected = self.list.selected();
+                let mut entries = self
+                    .entries
+                    .iter()
+                    .skip(selected)
+                    .map(|e| e.name().chars().next());
+                let Some(char) = entries.next() else {
+                    self.list.select(self.entries.len() - 1);
+                    return Ok(true);
+                };
+
+                if let Some(i) = entries.position(|c| c != char) {
+                    self.list.select(selected + 1 + i);
+                } else {
+                    self.list.select(self.entries.len() - 1);
+                }
+                Ok(true)
+            }
+            _ => match self.grid.as_mut() {
+                Some(grid) => {
+                    let handled = grid.handle_key_event(event, commands, bubble).await?;
+                    self.list.select(grid.selected());
+                    Ok(handled)
+                }
+                None => self.list.handle_key_event(event, commands, bubble).await,
+            },
+        }
+    }
+
+    /// Selects the first entry of the next or previous letter, and shows the letter.
+    fn jump_letter(&mut self, forward: bool) {
+        let groups: Vec<_> = self.entries.iter().map(JumpGroup::of).collect();
+        let selected = jump_target(&groups, self.list.selected(), forward);
+        self.list.select(selected);
+
+        let Some(group) = groups.get(selected) else {
+            return;
+        };
+        let text = match group {
+            JumpGroup::Folders => self.res.get::<Locale>().t("letter-jump-folders"),
+            JumpGroup::Letter(c) => c.to_string(),
+        };
+        match self.letter.as_mut() {
+            Some(letter) => {
+                letter.label.set_text(text);
+                letter.remaining = LETTER_DURATION;
+            }
+            None => self.letter = Some(LetterOverlay::new(text)),
+        }
+    }
+
+    /// Takes the highlighted game off the recently played list, offering to undo it.
+    pub async fn remove_from_recents(&mut self, commands: Sender<Command>) -> Result<()> {
+        let Some(Entry::Game(game)) = self.entries.get(self.list.selected()) else {
+            return Ok(());
+        };
+        let id = trash::remove_from_recents(&self.res.get::<Database>(), &game.path)?;
+        let text = self.res.get::<Locale>().ta(
+            "undo-remove-from-recents",
+            &[("name".to_string(), game.name.clone().into())]
+                .into_iter()
+                .collect(),
+        );
+        self.load_entries()?;
+        commands.send(Command::Redraw).await?;
+        commands.send(Command::OfferUndo(text, vec![id])).await?;
+        Ok(())
+    }
+
+    fn open_menu(&mut self) -> Result<()> {
+        let restricted = self.res.get::<Profile>().restricted;
+        let mut entries = if restricted {
+            vec![MenuEntry::Launch(None)]
+        } else if S::HAS_MULTI_SELECT {
+            vec![
+                MenuEntry::Launch(None),
+                MenuEntry::RemoveFromRecents,
+                MenuEntry::RepopulateDatabase,
+                MenuEntry::SelectMultiple,
+            ]
+        } else {
+            vec![
+                MenuEntry::Launch(None),
+                MenuEntry::RemoveFromRecents,
+                MenuEntry::RepopulateDatabase,
+            ]
+        };
+
+        if !restricted && S::HAS_CLEAR_RECENTS {
+            entries.push(MenuEntry::ClearRecents);
+        }
+        if self.sort.folder().is_some() {
+            entries.push(MenuEntry::RandomGame);
+        }
+        if !restricted && self.sort.folder().is_some() {
+            entries.push(MenuEntry::FolderSettings);
+            entries.push(MenuEntry::ScrapeBoxArt);
+            entries.push(MenuEntry::RedownloadBoxArt);
+        }
 
-        let entry = self.entries.get(self.list.selected()).unwrap();
+        // The menu is about the highlighted entry, so there is none for an empty list
+        let Some(entry) = self.entries.get(self.list.selected()) else {
+            return Ok(());
+        };
+        if !restricted {
+            match entry {
+                Entry::Game(_) | Entry::Directory(_) if self.hidden.contains(entry.path()) => {
+                    entries.push(MenuEntry::Unhide)
+                }
+                Entry::Game(_) | Entry::Directory(_) => entries.push(MenuEntry::Hide),
+                Entry::App(_) => {}
+            }
+            match entry {
+                Entry::Game(game) => {
+                    entries.push(MenuEntry::AddToCollection);
+                    if self.sort.collection().is_some() {
+                        entries.push(MenuEntry::RemoveFromCollection);
+                    }
+                    entries.push(MenuEntry::Rename);
+                    if game.path.exists() {
+                        entries.push(MenuEntry::Delete);
+                    }
+                }
+                Entry::Directory(dir) if matches!(dir.listing, Some(Listing::Collection(_))) => {
+                    entries.push(MenuEntry::RenameCollection);
+                    entries.push(MenuEntry::DeleteCollection);
+                }
+                Entry::Directory(_) | Entry::App(_) => {}
+            }
+        }
         match entry {
             Entry::Game(game) => {
-                let cores = self
-                    .res
-                    .get::<ConsoleMapper>()
+                entries.insert(1, MenuEntry::Notes);
+
+                if !restricted {
+                    entries.insert(2, MenuEntry::GameOptions);
+                }
+
+                let console_mapper = self.res.get::<ConsoleMapper>();
+                let console = console_mapper
                     .get_console(&game.path)
-                    .map(|c| c.cores.clone())
-                    .unwrap_or_default();
+                    .filter(|c| !c.cores.is_empty());
 
-                if !cores.is_empty() {
-                    let core = game.core.to_owned().unwrap_or_else(|| cores[0].clone());
+                if let Some(console) = console {
+                    // Only RetroArch can start a game from a save state
+                    let states = console_mapper.save_states(&self.res.get(), game)?;
+                    if !states.saved_slots().is_empty() {
+                        entries.insert(1, MenuEntry::LaunchWithState);
+                    }
+
+                    let cores = console.cores.clone();
+                    let core = console_mapper
+                        .core_for_game(&self.res.get(), game, console)?
+                        .unwrap_or_else(|| cores[0].clone());
                     let i = cores.iter().position(|c| c == &core).unwrap_or_default();
 
                     if let MenuEntry::Launch(ref mut launch_core) = entries[0] {
-                        let console_mapper = self.res.get::<ConsoleMapper>();
                         *launch_core = Some(console_mapper.get_core_name(&core));
                     }
 
@@ -240,7 +1521,23 @@ where
             Entry::App(_) | Entry::Directory(_) => {}
         }
 
-        let height = entries.len() as u32 * (styles.ui_font.size + SELECTION_MARGIN);
+        let items = {
+            let locale = self.res.get::<Locale>();
+            entries.iter().map(|e| e.text(&locale)).collect()
+        };
+        self.menu_entries = entries;
+        self.open_popup(items, MenuKind::Entry);
+
+        Ok(())
+    }
+
+    fn open_popup(&mut self, items: Vec<String>, kind: MenuKind) {
+        let Rect { x, y, w, h } = self.rect;
+        let styles = self.res.get::<Styles>();
+
+        // Long lists scroll rather than run off the screen
+        let row_height = styles.row_layout().height;
+        let height = items.len().min(((h - 48) / row_height) as usize) as u32 * row_height;
 
         let mut menu = ScrollList::new(
             Rect::new(
@@ -249,13 +1546,114 @@ where
                 (w - 24) * 2 / 3,
                 height,
             ),
-            entries.iter().map(|e| e.text(&locale)).collect(),
+            items,
             Alignment::Left,
-            styles.ui_font.size + SELECTION_MARGIN,
+            styles.row_layout(),
         );
         menu.set_background_color(Some(StylesheetColor::BackgroundHighlightBlend));
+        menu.set_list_settings(*self.res.get::<ListSettings>());
         self.menu = Some(menu);
+        self.menu_kind = kind;
+    }
 
+    async fn handle_menu_select(
+        &mut self,
+        selected: usize,
+        commands: Sender<Command>,
+    ) -> Result<()> {
+        match self.menu_kind {
+            MenuKind::Entry => {}
+            MenuKind::Batch => {
+                self.menu = None;
+                match BatchAction::ALL[selected] {
+                    BatchAction::Delete => self.confirm_delete(),
+                    action => self.start_batch(action),
+                }
+                commands.send(Command::Redraw).await?;
+                return Ok(());
+            }
+            MenuKind::ConfirmDelete => {
+                if selected == 0 {
+                    self.start_batch(BatchAction::Delete);
+                }
+            }
+            MenuKind::ConfirmExit => {
+                if selected == 0 {
+                    self.exit_multi_select();
+                }
+            }
+            MenuKind::FolderView => {
+                self.change_folder_view(selected)?;
+                self.open_folder_view_menu(selected);
+                commands.send(Command::Redraw).await?;
+                return Ok(());
+            }
+            MenuKind::ConfirmRandom => match selected {
+                0 => {
+                    if let Some(mut random) = self.random.take() {
+                        Self::launch_game(&self.res, random.game_mut(), commands.clone()).await?;
+                    }
+                }
+                1 => {
+                    self.reroll_random_game();
+                    commands.send(Command::Redraw).await?;
+                    return Ok(());
+                }
+                _ => self.random = None,
+            },
+            MenuKind::AddToCollection => {
+                let collections = mem::take(&mut self.collections);
+                if let Some(Entry::Game(game)) = self.entries.get(self.list.selected()) {
+                    if let Some(collection) = collections.get(selected) {
+                        self.res
+                            .get::<Database>()
+                            .add_to_collection(collection.id, &game.path)?;
+                        self.toast_added(&collection.name, commands.clone()).await?;
+                    } else {
+                        self.naming = Some((
+                            Keyboard::new(self.res.clone(), String::new(), false),
+                            Naming::NewCollection(game.path.clone()),
+                        ));
+                    }
+                }
+            }
+            MenuKind::ConfirmDeleteGame => {
+                if selected == 0 {
+                    self.delete_game(commands.clone()).await?;
+                }
+            }
+            MenuKind::ConfirmDeleteCollection => {
+                if let (0, Some(id)) = (selected, self.selected_collection()) {
+                    self.res.get::<Database>().delete_collection(id)?;
+                    self.load_entries()?;
+                }
+            }
+            MenuKind::LaunchState => {
+                let slots = mem::take(&mut self.state_slots);
+                if let (Some(slot), Some(Entry::Game(game))) = (
+                    slots.get(selected),
+                    self.entries.get_mut(self.list.selected()),
+                ) {
+                    game.state_slot = Some(*slot);
+                    let result = Self::launch_game(&self.res, game, commands.clone()).await;
+                    game.state_slot = None;
+                    result?;
+                }
+            }
+            MenuKind::ConfirmRelink => {
+                let candidates = std::mem::take(&mut self.relink);
+                if let (Some(path), Some(Entry::Game(game))) = (
+                    candidates.get(selected),
+                    self.entries.get_mut(self.list.selected()),
+                ) {
+                    self.res.get::<Database>().relink_game(&game.path, path)?;
+                    game.path = path.clone();
+                    self.select_entry(commands.clone()).await?;
+                }
+            }
+        }
+        self.menu = None;
+        commands.send(Command::Redraw).await?;
         Ok(())
     }
 }
@@ -268,14 +1666,36 @@ where
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
+        if self.transition.clear(display)? {
+            self.set_should_draw();
+        }
         if let Some(child) = &mut self.child {
             return child.draw(display, styles);
         }
 
         let mut drawn = false;
 
+        let count = self.count();
+        if self.count != Some(count) {
+            self.count = Some(count);
+            self.counter.set_text(count.text(&self.res.get()));
+        }
+        drawn |= self.counter.should_draw() && self.counter.draw(display, styles)?;
+
+        if let Some(batch) = &mut self.batch {
+            return Ok(batch.should_draw() && batch.draw(display, styles)?);
+        }
+
+        if let Some(notes) = &mut self.notes {
+            return Ok(notes.should_draw() && notes.draw(display, styles)?);
+        }
+
+        if let Some(game_options) = &mut self.game_options {
+            return Ok(game_options.should_draw() && game_options.draw(display, styles)?);
+        }
+
         if let Some(menu) = &mut self.menu {
             if menu.should_draw() {
                 let mut rect = menu.bounding_box(styles);
@@ -299,21 +1719,79 @@ where
             return Ok(drawn);
         }
 
-        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        if let Some(rect) = self.hidden_letter.take() {
+            display.load(rect)?;
+            self.list.set_should_draw();
+            if let Some(grid) = self.grid.as_mut() {
+                grid.set_should_draw();
+            }
+        }
+        if let Some(letter) = self.letter.as_mut() {
+            // A shorter label doesn't cover all of the last one
+            if let Some(rect) = letter.rect.filter(|_| letter.label.should_draw()) {
+                display.load(rect)?;
+                self.list.set_should_draw();
+                if let Some(grid) = self.grid.as_mut() {
+                    grid.set_should_draw();
+                }
+            }
+        }
+
+        if let Some(grid) = self.grid.as_mut() {
+            grid.select(self.list.selected());
+            // Only the visible cells look for their box art
+            for i in grid.visible() {
+                let image = self.entries[i].image().map(Path::to_path_buf);
+                grid.set_image(i, image);
+            }
+            drawn |= grid.should_draw() && grid.draw(display, styles)?;
+        } else {
+            drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        }
+        if self.entries.is_empty() && self.empty.should_draw() {
+            drawn |= self.empty.draw(display, styles)?;
+        }
 
-        if styles.enable_box_art {
+        if self.view.box_art.value && self.grid.is_none() {
             // TODO: relayout list if box art is enabled/disabled
-            if let Some(entry) = self.entries.get_mut(self.list.selected()) {
-                if let Some(path) = entry.image() {
-                    self.image.set_path(Some(path.to_path_buf()));
-                } else {
-                    self.image.set_path(None);
+            if let Some(found) = self.missing_art.take_found() {
+                for entry in &mut self.entries {
+                    if let Entry::Game(game) = entry {
+                        if game.path == found {
+                            game.image = LazyImage::Unknown(found.clone());
+                        }
+                    }
                 }
+            }
+            if let Some(entry) = self.entries.get_mut(self.list.selected()) {
+                let image = entry.image().map(Path::to_path_buf);
+                let has_image = image.is_some();
+                self.image.set_path(image);
                 if self.image.should_draw() && self.image.draw(display, styles)? {
+                    self.missing_art.set_should_draw();
                     drawn = true;
                 }
+                match entry {
+                    Entry::Game(game) if !has_image => {
+                        self.missing_art.set_game(game);
+                        if self.missing_art.should_draw()
+                            && self.missing_art.draw(display, styles)?
+                        {
+                            drawn = true;
+                        }
+                    }
+                    _ => self.missing_art.clear(),
+                }
             } else {
                 self.image.set_path(None);
+                self.missing_art.clear();
+            }
+        }
+
+        if let Some(letter) = self.letter.as_mut() {
+            if drawn || letter.label.should_draw() {
+                letter.draw(display, styles, self.list.bounding_box(styles))?;
+                drawn = true;
             }
         }
 
@@ -330,19 +1808,46 @@ where
             }
         }
 
+        if let Some((keyboard, _)) = self.naming.as_mut() {
+            // Anything drawn beneath may have covered it
+            if drawn {
+                keyboard.set_should_draw();
+            }
+            drawn |= keyboard.should_draw() && keyboard.draw(display, styles)?;
+        }
+
         Ok(drawn)
     }
 
     fn should_draw(&self) -> bool {
-        if let Some(child) = self.child.as_ref() {
+        if self.transition.is_pending() {
+            true
+        } else if let Some(child) = self.child.as_ref() {
             child.should_draw()
+        } else if let Some(batch) = self.batch.as_ref() {
+            batch.should_draw()
+        } else if let Some(notes) = self.notes.as_ref() {
+            notes.should_draw()
+        } else if let Some(game_options) = self.game_options.as_ref() {
+            game_options.should_draw()
         } else {
             self.menu
                 .as_ref()
                 .map_or(false, common::view::View::should_draw)
-                || self.list.should_draw()
-                || self.image.should_draw()
+                || match self.grid.as_ref() {
+                    Some(grid) => grid.should_draw(),
+                    None => {
+                        self.list.should_draw()
+                            || self.image.should_draw()
+                            || self.missing_art.should_draw()
+                    }
+                }
+                || (self.entries.is_empty() && self.empty.should_draw())
+                || self.letter.as_ref().is_some_and(|l| l.label.should_draw())
+                || self.hidden_letter.is_some()
                 || self.button_hints.should_draw()
+                || self.counter.should_draw()
+                || self.naming.as_ref().is_some_and(|(k, _)| k.should_draw())
         }
     }
 
@@ -350,12 +1855,50 @@ where
         if let Some(child) = self.child.as_mut() {
             child.set_should_draw();
         } else {
+            if let Some(batch) = self.batch.as_mut() {
+                batch.set_should_draw();
+            }
+            if let Some(notes) = self.notes.as_mut() {
+                notes.set_should_draw();
+            }
+            if let Some(game_options) = self.game_options.as_mut() {
+                game_options.set_should_draw();
+            }
             if let Some(menu) = self.menu.as_mut() {
                 menu.set_should_draw();
             }
             self.list.set_should_draw();
+            if let Some(grid) = self.grid.as_mut() {
+                grid.set_should_draw();
+            }
             self.image.set_should_draw();
+            self.missing_art.set_should_draw();
+            self.empty.set_should_draw();
+            if let Some(letter) = self.letter.as_mut() {
+                letter.label.set_should_draw();
+            }
             self.button_hints.set_should_draw();
+            self.counter.set_should_draw();
+            if let Some((keyboard, _)) = self.naming.as_mut() {
+                keyboard.set_should_draw();
+            }
+        }
+    }
+
+    fn update(&mut self, dt: Duration) {
+        self.children_mut().iter_mut().for_each(|c| c.update(dt));
+        if let (None, Some(grid)) = (self.child.as_ref(), self.grid.as_mut()) {
+            grid.update(dt);
+        }
+        if self.game_counts.poll(&self.res.get()) {
+            self.show_details();
+        }
+        if let Some(letter) = self.letter.as_mut() {
+            letter.remaining = letter.remaining.saturating_sub(dt);
+            if letter.remaining.is_zero() {
+                self.hidden_letter = letter.rect;
+                self.letter = None;
+            }
         }
     }
 
@@ -370,8 +1913,11 @@ where
                 true => {
                     bubble.retain_mut(|c| match c {
                         Command::CloseView => {
-                            self.child = None;
-                            self.set_should_draw();
+                            if let Some(mut child) = self.child.take() {
+                                child.counter.hand_over(&mut self.counter);
+                                child.remember_position();
+                            }
+                            self.transition.start(self.rect);
                             true
                         }
                         _ => true,
@@ -380,63 +1926,178 @@ where
                 }
                 false => Ok(false),
             }
+        } else if let Some(batch) = self.batch.as_mut() {
+            batch
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?;
+            let mut closed = false;
+            bubble.retain(|c| match c {
+                Command::CloseView => {
+                    closed = true;
+                    false
+                }
+                _ => true,
+            });
+            if closed {
+                if let Some((text, ids)) = self.batch.take().and_then(|b| b.undo_offer()) {
+                    commands.send(Command::OfferUndo(text, ids)).await?;
+                }
+                self.selection = None;
+                self.load_entries()?;
+                self.update_button_hints();
+                commands.send(Command::Redraw).await?;
+            }
+            Ok(true)
+        } else if let Some((keyboard, _)) = self.naming.as_mut() {
+            keyboard
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?;
+            let mut name = None;
+            let mut closed = false;
+            bubble.retain_mut(|c| match c {
+                Command::ValueChanged(_, value) => {
+                    name = mem::take(value).as_string();
+                    false
+                }
+                Command::CloseView => {
+                    closed = true;
+                    false
+                }
+                _ => true,
+            });
+            if let Some(name) = name {
+                self.finish_naming(name, commands.clone()).await?;
+            }
+            if closed {
+                self.naming = None;
+                commands.send(Command::Redraw).await?;
+            }
+            Ok(true)
+        } else if let Some(notes) = self.notes.as_mut() {
+            notes
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?;
+            let mut closed = false;
+            bubble.retain(|c| match c {
+                Command::CloseView => {
+                    closed = true;
+                    false
+                }
+                _ => true,
+            });
+            if closed {
+                self.notes = None;
+                commands.send(Command::Redraw).await?;
+            }
+            Ok(true)
+        } else if let Some(game_options) = self.game_options.as_mut() {
+            game_options
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?;
+            let mut closed = false;
+            bubble.retain(|c| match c {
+                Command::CloseView => {
+                    closed = true;
+                    false
+                }
+                _ => true,
+            });
+            if closed {
+                self.game_options = None;
+                commands.send(Command::Redraw).await?;
+            }
+            Ok(true)
         } else if let Some(menu) = self.menu.as_mut() {
             match event {
                 KeyEvent::Pressed(Key::Left) => {
                     if let Some(core) = self.core.as_mut() {
-                        let mut selected = MenuEntry::from_repr(menu.selected());
-                        if let MenuEntry::Launch(ref mut launch_core) = selected {
+                        let selected = self.menu_entries.get_mut(menu.selected());
+                        if let Some(MenuEntry::Launch(launch_core)) = selected {
                             core.core = core.core.saturating_sub(1);
                             let console_mapper = self.res.get::<ConsoleMapper>();
                             *launch_core =
                                 Some(console_mapper.get_core_name(&core.cores[core.core]));
-                            menu.set_item(menu.selected(), selected.text(&self.res.get()));
+                            let text = self.menu_entries[menu.selected()].text(&self.res.get());
+                            menu.set_item(menu.selected(), text);
                         }
                     }
                     Ok(true) // trap tab focus
                 }
                 KeyEvent::Pressed(Key::Right) => {
                     if let Some(core) = self.core.as_mut() {
-                        let mut selected = MenuEntry::from_repr(menu.selected());
-                        if let MenuEntry::Launch(ref mut launch_core) = selected {
+                        let selected = self.menu_entries.get_mut(menu.selected());
+                        if let Some(MenuEntry::Launch(launch_core)) = selected {
                             core.core = (core.core + 1).min(core.cores.len() - 1);
                             let console_mapper = self.res.get::<ConsoleMapper>();
                             *launch_core =
                                 Some(console_mapper.get_core_name(&core.cores[core.core]));
-                            menu.set_item(menu.selected(), selected.text(&self.res.get()));
+                            let text = self.menu_entries[menu.selected()].text(&self.res.get());
+                            menu.set_item(menu.selected(), text);
                         }
                     }
                     Ok(true) // trap tab focus
                 }
+                KeyEvent::Pressed(Key::B) if self.menu_kind == MenuKind::ConfirmRandom => {
+                    self.reroll_random_game();
+                    commands.send(Command::Redraw).await?;
+                    Ok(true)
+                }
                 KeyEvent::Pressed(Key::Select | Key::B) => {
                     self.menu = None;
+                    self.random = None;
                     commands.send(Command::Redraw).await?;
                     Ok(true)
                 }
+                KeyEvent::Pressed(Key::A) if self.menu_kind != MenuKind::Entry => {
+                    let selected = menu.selected();
+                    self.handle_menu_select(selected, commands).await?;
+                    Ok(true)
+                }
                 KeyEvent::Pressed(Key::A) => {
-                    let selected = MenuEntry::from_repr(menu.selected());
+                    let selected = self.menu_entries[menu.selected()].clone();
                     match selected {
                         MenuEntry::Launch(_) => {
                             let entry = self.entries.get_mut(self.list.selected()).unwrap();
                             if let (Some(core), Entry::Game(game)) = (self.core.as_ref(), entry) {
                                 let db = self.res.get::<Database>();
                                 let core = &core.cores[core.core];
-                                db.set_core(&game.path, core)?;
-                                game.core = Some(core.to_string());
+                                let console_mapper = self.res.get::<ConsoleMapper>();
+                                let current = console_mapper
+                                    .get_console(&game.path)
+                                    .map(|console| console_mapper.core_for_game(&db, game, console))
+                                    .transpose()
+                                    .unwrap_or_default()
+                                    .flatten();
+                                // Only a different core is remembered, so that the folder's
+                                // and console's defaults keep applying otherwise
+                                if current.as_ref() != Some(core) {
+                                    // The core picked here replaces the one chosen in the options
+                                    if db.core_override(&game.path)?.is_some() {
+                                        db.set_core_override(&game.path, Some(core))?;
+                                    }
+                                    db.set_core(&game.path, core)?;
+                                    game.core = Some(core.to_string());
+                                }
                             }
                             self.core = None;
                             self.select_entry(commands).await?;
                         }
+                        MenuEntry::LaunchWithState => {
+                            self.core = None;
+                            self.offer_state_slots()?;
+                            commands.send(Command::Redraw).await?;
+                            return Ok(true);
+                        }
                         MenuEntry::RemoveFromRecents => {
-                            if let Some(Entry::Game(game)) = self.entries.get(self.list.selected())
-                            {
-                                if game.path.exists() {
-                                    self.res.get::<Database>().reset_game(&game.path)?;
-                                } else {
-                                    self.res.get::<Database>().delete_game(&game.path)?;
-                                }
-                                self.load_entries()?;
-                                commands.send(Command::Redraw).await?;
+                            self.remove_from_recents(commands).await?;
+                        }
+                        MenuEntry::ClearRecents => {
+                            let id = trash::clear_recents(&self.res.get::<Database>())?;
+                            self.load_entries()?;
+                            commands.send(Command::Redraw).await?;
+                            if let Some(id) = id {
+                                let text = self.res.get::<Locale>().t("undo-clear-recents");
+                                commands.send(Command::OfferUndo(text, vec![id])).await?;
                             }
                         }
                         MenuEntry::RepopulateDatabase => {
@@ -449,56 +2110,167 @@ where
                                 .await?;
                             commands.send(Command::Redraw).await?;
                         }
+                        MenuEntry::Notes => {
+                            if let Some(Entry::Game(game)) = self.entries.get(self.list.selected())
+                            {
+                                let editable = !self.res.get::<Profile>().restricted;
+                                self.notes = Some(Notes::new(
+                                    self.rect,
+                                    self.res.clone(),
+                                    game.path.clone(),
+                                    game.name.clone(),
+                                    editable,
+                                ));
+                            }
+                        }
+                        MenuEntry::GameOptions => {
+                            if let Some(Entry::Game(game)) = self.entries.get(self.list.selected())
+                            {
+                                self.game_options = Some(GameOptions::new(
+                                    self.rect,
+                                    self.res.clone(),
+                                    game.clone(),
+                                )?);
+                            }
+                        }
+                        MenuEntry::SelectMultiple => {
+                            self.enter_multi_select();
+                            commands.send(Command::Redraw).await?;
+                        }
+                        MenuEntry::Hide | MenuEntry::Unhide => {
+                            if let Some(entry) = self.entries.get(self.list.selected()) {
+                                self.res.get::<Database>().set_hidden(
+                                    entry.path(),
+                                    matches!(selected, MenuEntry::Hide),
+                                )?;
+                                let index = self.list.selected();
+                                self.load_entries()?;
+                                self.list.select(index);
+                                commands.send(Command::Redraw).await?;
+                            }
+                        }
+                        MenuEntry::RandomGame => {
+                            self.menu = None;
+                            self.pick_random_game(commands).await?;
+                            return Ok(true);
+                        }
+                        MenuEntry::FolderSettings => {
+                            self.open_folder_view_menu(0);
+                            commands.send(Command::Redraw).await?;
+                            return Ok(true);
+                        }
+                        MenuEntry::ScrapeBoxArt | MenuEntry::RedownloadBoxArt => {
+                            if let Some(folder) = self.sort.folder() {
+                                commands
+                                    .send(Command::ScrapeBoxArt(
+                                        folder.to_path_buf(),
+                                        matches!(selected, MenuEntry::RedownloadBoxArt),
+                                    ))
+                                    .await?;
+                            }
+                            commands.send(Command::Redraw).await?;
+                        }
+                        MenuEntry::AddToCollection => {
+                            self.offer_collections()?;
+                            commands.send(Command::Redraw).await?;
+                            return Ok(true);
+                        }
+                        MenuEntry::RemoveFromCollection => {
+                            if let (Some(id), Some(Entry::Game(game))) = (
+                                self.sort.collection(),
+                                self.entries.get(self.list.selected()),
+                            ) {
+                                self.res
+                                    .get::<Database>()
+                                    .remove_from_collection(id, &game.path)?;
+                                let index = self.list.selected();
+                                self.load_entries()?;
+                                self.list.select(index);
+                            }
+                            commands.send(Command::Redraw).await?;
+                        }
+                        MenuEntry::RenameCollection => {
+                            if let (Some(id), Some(entry)) = (
+                                self.selected_collection(),
+                                self.entries.get(self.list.selected()),
+                            ) {
+                                self.naming = Some((
+                                    Keyboard::new(
+                                        self.res.clone(),
+                                        entry.name().to_string(),
+                                        false,
+                                    ),
+                                    Naming::Collection(id),
+                                ));
+                            }
+                            commands.send(Command::Redraw).await?;
+                        }
+                        MenuEntry::DeleteCollection => {
+                            self.confirm_delete_collection();
+                            commands.send(Command::Redraw).await?;
+                            return Ok(true);
+                        }
+                        MenuEntry::Rename => {
+                            if let Some(Entry::Game(game)) = self.entries.get(self.list.selected())
+                            {
+                                self.naming = Some((
+                                    Keyboard::new(self.res.clone(), game.name.clone(), false),
+                                    Naming::Game(game.path.clone()),
+                                ));
+                            }
+                            commands.send(Command::Redraw).await?;
+                        }
+                        MenuEntry::Delete => {
+                            self.confirm_delete_game();
+                            commands.send(Command::Redraw).await?;
+                            return Ok(true);
+                        }
                     }
                     self.menu = None;
                     Ok(true)
                 }
                 _ => menu.handle_key_event(event, commands, bubble).await,
             }
-        } else {
+        } else if self.selection.is_some() {
             match event {
-                KeyEvent::Pressed(Key::L2) => {
-                    let selected = self.list.selected();
-                    let len = self.entries.len();
-                    let mut entries = self
-                        .entries
-                        .iter()
-                        .rev()
-                        .skip(len - selected)
-                        .map(|e| e.name().chars().next());
-                    println!("{:?}", entries.clone().collect::<Vec<_>>());
-                    let Some(char) = entries.next() else {
-                        self.list.select(0);
-                        return Ok(true);
-                    };
-
-                    println!("entries: {:?}, char: {:?}", entries, char);
-                    if let Some(i) = entries.position(|c| c != char) {
-                        self.list.select(selected - i - 1);
+                KeyEvent::Pressed(Key::A) => {
+                    if let Some(Entry::Directory(_)) = self.entries.get(self.list.selected()) {
+                        if self.try_exit_multi_select() {
+                            self.select_entry(commands).await?;
+                        }
                     } else {
-                        self.list.select(0);
+                        self.toggle_selected();
                     }
                     Ok(true)
                 }
-                KeyEvent::Pressed(Key::R2) => {
-                    let selected = self.list.selected();
-                    let mut entries = self
-                        .entries
-                        .iter()
-                        .skip(selected)
-                        .map(|e| e.name().chars().next());
-                    let Some(char) = entries.next() else {
-                        self.list.select(self.entries.len() - 1);
-                        return Ok(true);
-                    };
-
-                    if let Some(i) = entries.position(|c| c != char) {
-                        self.list.select(selected + 1 + i);
-                    } else {
-                        self.list.select(self.entries.len() - 1);
+                KeyEvent::Pressed(Key::Y) => {
+                    self.toggle_all();
+                    Ok(true)
+                }
+                KeyEvent::Pressed(Key::Select) => {
+                    if self.selection.as_ref().is_some_and(|s| !s.is_empty()) {
+                        self.open_batch_menu();
                     }
                     Ok(true)
                 }
+                KeyEvent::Pressed(Key::B) => {
+                    self.try_exit_multi_select();
+                    commands.send(Command::Redraw).await?;
+                    Ok(true)
+                }
+                KeyEvent::Pressed(Key::Left | Key::Right) => {
+                    // Switching tabs leaves the list, so ask before dropping the selection
+                    if self.try_exit_multi_select() {
+                        commands.send(Command::Redraw).await?;
+                        Ok(false)
+                    } else {
+                        Ok(true)
+                    }
+                }
+                _ => self.handle_list_key_event(event, commands, bubble).await,
+            }
+        } else {
+            match event {
                 KeyEvent::Pressed(Key::B) => {
                     bubble.push_back(Command::CloseView);
                     Ok(true)
@@ -515,7 +2287,16 @@ where
                     self.open_menu()?;
                     Ok(true)
                 }
-                _ => self.list.handle_key_event(event, commands, bubble).await,
+                KeyEvent::Pressed(Key::Start)
+                    if self.grid.is_none() && self.missing_art.offers_search() =>
+                {
+                    if self.missing_art.search()? {
+                        scraper::spawn_worker();
+                        commands.send(Command::Redraw).await?;
+                    }
+                    Ok(true)
+                }
+                _ => self.handle_list_key_event(event, commands, bubble).await,
             }
         }
     }
@@ -523,20 +2304,78 @@ where
     fn children(&self) -> Vec<&dyn View> {
         if let Some(child) = self.child.as_ref() {
             vec![child.as_ref() as &dyn View]
+        } else if let Some(batch) = self.batch.as_ref() {
+            vec![
+                &self.list,
+                &self.image,
+                &self.missing_art,
+                &self.button_hints,
+                batch,
+            ]
+        } else if let Some(notes) = self.notes.as_ref() {
+            vec![
+                &self.list,
+                &self.image,
+                &self.missing_art,
+                &self.button_hints,
+                notes,
+            ]
+        } else if let Some(game_options) = self.game_options.as_ref() {
+            vec![
+                &self.list,
+                &self.image,
+                &self.missing_art,
+                &self.button_hints,
+                game_options,
+            ]
         } else {
-            vec![&self.list, &self.image, &self.button_hints]
+            vec![
+                &self.list,
+                &self.image,
+                &self.missing_art,
+                &self.button_hints,
+            ]
         }
     }
 
     fn children_mut(&mut self) -> Vec<&mut dyn View> {
         if let Some(child) = self.child.as_mut() {
             vec![child.as_mut() as &mut dyn View]
+        } else if let Some(batch) = self.batch.as_mut() {
+            vec![
+                &mut self.list,
+                &mut self.image,
+                &mut self.missing_art,
+                &mut self.button_hints,
+                batch,
+            ]
+        } else if let Some(notes) = self.notes.as_mut() {
+            vec![
+                &mut self.list,
+                &mut self.image,
+                &mut self.missing_art,
+                &mut self.button_hints,
+                notes,
+            ]
+        } else if let Some(game_options) = self.game_options.as_mut() {
+            vec![
+                &mut self.list,
+                &mut self.image,
+                &mut self.missing_art,
+                &mut self.button_hints,
+                game_options,
+            ]
         } else {
-            vec![&mut self.list, &mut self.image, &mut self.button_hints]
+            vec![
+                &mut self.list,
+                &mut self.image,
+                &mut self.missing_art,
+                &mut self.button_hints,
+            ]
         }
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
@@ -545,22 +2384,142 @@ where
     }
 }
 
+/// Runs of entries that L and R jump between when the list is sorted by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JumpGroup {
+    /// Folders are listed first, and are jumped over together.
+    Folders,
+    /// Names starting with the letter, or `#` for names that don't start with a letter.
+    Letter(char),
+}
+
+impl JumpGroup {
+    fn of(entry: &Entry) -> Self {
+        match entry {
+            Entry::Directory(_) => JumpGroup::Folders,
+            entry => match entry.name().chars().next() {
+                Some(c) if c.is_alphabetic() => JumpGroup::Letter(c.to_uppercase().next().unwrap()),
+                _ => JumpGroup::Letter('#'),
+            },
+        }
+    }
+}
+
+/// Entries `sort` lists, and how many there are to pick from if it only lists some. `None` if they
+/// can't be read, such as for a folder that was removed or can't be opened.
+fn read_entries<S: Sort>(
+    sort: &S,
+    database: &Database,
+    console_mapper: &ConsoleMapper,
+    filter: &LibraryFilter,
+) -> Option<(Vec<Entry>, Option<usize>)> {
+    let listed = sort
+        .entries(database, console_mapper, filter)
+        .and_then(|entries| Ok((entries, sort.total(database)?)));
+    match listed {
+        Ok(listed) => Some(listed),
+        Err(e) => {
+            warn!("failed to list entries: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Index of the first entry of the group after or before the one at `selected`. Stays put if
+/// there is no such group, except that going back from within the first group goes to its start.
+fn jump_target(groups: &[JumpGroup], selected: usize, forward: bool) -> usize {
+    let start = |mut i: usize| {
+        while i > 0 && groups[i - 1] == groups[i] {
+            i -= 1;
+        }
+        i
+    };
+    if selected >= groups.len() {
+        return selected;
+    }
+    if forward {
+        (selected + 1..groups.len())
+            .find(|&i| groups[i] != groups[i - 1])
+            .unwrap_or(selected)
+    } else {
+        match start(selected) {
+            0 => 0,
+            current => start(current - 1),
+        }
+    }
+}
+
+/// The letter that was jumped to, shown over the middle of the list.
+#[derive(Debug)]
+struct LetterOverlay {
+    label: Label<String>,
+    remaining: Duration,
+    /// Where it was last drawn.
+    rect: Option<Rect>,
+}
+
+impl LetterOverlay {
+    fn new(text: String) -> Self {
+        let mut label = Label::new(Point::zero(), text, Alignment::Center, None);
+        label.background_color(StylesheetColor::BackgroundHighlightBlend);
+        Self {
+            label,
+            remaining: LETTER_DURATION,
+            rect: None,
+        }
+    }
+
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+        list: Rect,
+    ) -> Result<()> {
+        self.label.set_position(Point::new(
+            list.x + list.w as i32 / 2,
+            list.y + (list.h as i32 - styles.ui_font.size as i32) / 2,
+        ));
+        let mut rect = self.label.bounding_box(styles);
+        rect.x -= 24;
+        rect.w += 48;
+        rect.y -= 12;
+        rect.h += 24;
+        RoundedRectangle::new(rect.into(), CornerRadii::new(Size::new_equal(12)))
+            .into_styled(PrimitiveStyle::with_fill(
+                StylesheetColor::BackgroundHighlightBlend.to_color(styles),
+            ))
+            .draw(display)?;
+        self.label.draw(display, styles)?;
+        self.rect = Some(rect);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
 enum MenuEntry {
     Launch(Option<String>),
+    LaunchWithState,
     RemoveFromRecents,
+    Notes,
+    GameOptions,
     RepopulateDatabase,
+    SelectMultiple,
+    ClearRecents,
+    Hide,
+    Unhide,
+    RandomGame,
+    FolderSettings,
+    ScrapeBoxArt,
+    RedownloadBoxArt,
+    AddToCollection,
+    RemoveFromCollection,
+    RenameCollection,
+    DeleteCollection,
+    Rename,
+    Delete,
 }
 
 impl MenuEntry {
-    fn from_repr(i: usize) -> Self {
-        match i {
-            0 => MenuEntry::Launch(None),
-            1 => MenuEntry::RemoveFromRecents,
-            2 => MenuEntry::RepopulateDatabase,
-            _ => unreachable!("invalid menu entry"),
-        }
-    }
-
     fn text(&self, locale: &Locale) -> String {
         match self {
             MenuEntry::Launch(core) => {
@@ -573,8 +2532,277 @@ impl MenuEntry {
                     locale.t("menu-launch")
                 }
             }
+            MenuEntry::LaunchWithState => locale.t("menu-launch-with-state"),
             MenuEntry::RemoveFromRecents => locale.t("menu-remove-from-recents"),
+            MenuEntry::Notes => locale.t("menu-notes"),
+            MenuEntry::GameOptions => locale.t("menu-game-options"),
             MenuEntry::RepopulateDatabase => locale.t("menu-repopulate-database"),
+            MenuEntry::SelectMultiple => locale.t("menu-select-multiple"),
+            MenuEntry::ClearRecents => locale.t("menu-clear-recents"),
+            MenuEntry::Hide => locale.t("menu-hide"),
+            MenuEntry::Unhide => locale.t("menu-unhide"),
+            MenuEntry::RandomGame => locale.t("menu-random-game"),
+            MenuEntry::FolderSettings => locale.t("menu-folder-settings"),
+            MenuEntry::ScrapeBoxArt => locale.t("menu-scrape-box-art"),
+            MenuEntry::RedownloadBoxArt => locale.t("menu-redownload-box-art"),
+            MenuEntry::AddToCollection => locale.t("menu-add-to-collection"),
+            MenuEntry::RemoveFromCollection => locale.t("menu-remove-from-collection"),
+            MenuEntry::RenameCollection => locale.t("menu-rename-collection"),
+            MenuEntry::DeleteCollection => locale.t("menu-delete-collection"),
+            MenuEntry::Rename => locale.t("menu-rename"),
+            MenuEntry::Delete => locale.t("menu-delete"),
+        }
+    }
+}
+
+/// A game picked at random from a folder, see [`EntryList::pick_random_game`].
+#[derive(Debug)]
+struct RandomPick {
+    games: Vec<Game>,
+    index: usize,
+}
+
+impl RandomPick {
+    fn new(games: Vec<Game>, rng: &mut impl Rng) -> Option<Self> {
+        if games.is_empty() {
+            return None;
+        }
+        let index = rng.gen_range(0..games.len());
+        Some(Self { games, index })
+    }
+
+    /// Picks a game, other than the current one if there is another.
+    fn reroll(&mut self, rng: &mut impl Rng) {
+        let len = self.games.len();
+        self.index = if len > 1 {
+            (self.index + rng.gen_range(1..len)) % len
+        } else {
+            0
+        };
+    }
+
+    fn game(&self) -> &Game {
+        &self.games[self.index]
+    }
+
+    fn game_mut(&mut self) -> &mut Game {
+        &mut self.games[self.index]
+    }
+}
+
+/// What the counter in the header shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListCount {
+    /// The highlighted entry is the `position`th of `total`, counting from 1.
+    Position {
+        position: usize,
+        total: usize,
+    },
+    /// Only `shown` of `total` entries are listed, e.g. because of a filter or search.
+    Filtered {
+        shown: usize,
+        total: usize,
+    },
+    Empty,
+}
+
+impl ListCount {
+    /// The count for `shown` listed entries out of `total`, with the `selected`th highlighted.
+    fn new(selected: usize, shown: usize, total: usize) -> Self {
+        if shown == 0 && total == 0 {
+            ListCount::Empty
+        } else if shown < total {
+            ListCount::Filtered { shown, total }
+        } else {
+            ListCount::Position {
+                position: selected.min(shown - 1) + 1,
+                total: shown,
+            }
+        }
+    }
+
+    fn text(&self, locale: &Locale) -> String {
+        match *self {
+            ListCount::Position { position, total } => locale.ta(
+                "list-position",
+                &[
+                    ("position".to_string(), position.into()),
+                    ("total".to_string(), total.into()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ListCount::Filtered { shown, total } => locale.ta(
+                "list-filtered",
+                &[
+                    ("shown".to_string(), shown.into()),
+                    ("total".to_string(), total.into()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ListCount::Empty => locale.t("list-empty"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::test_utils::temp_dir;
+    use rand::SeedableRng;
+    use type_map::TypeMap;
+
+    use super::*;
+    use crate::view::games::GamesSort;
+
+    #[test]
+    fn test_jump_target() {
+        let groups: Vec<_> = [None, None, Some('#'), Some('A'), Some('A'), Some('C')]
+            .into_iter()
+            .map(|c| c.map_or(JumpGroup::Folders, JumpGroup::Letter))
+            .collect();
+
+        // Letters without entries are skipped
+        assert_eq!(jump_target(&groups, 3, true), 5);
+        assert_eq!(jump_target(&groups, 5, false), 3);
+        // From within a letter, back goes to the previous letter rather than the current one
+        assert_eq!(jump_target(&groups, 4, false), 2);
+        // Folders are one group
+        assert_eq!(jump_target(&groups, 0, true), 2);
+        assert_eq!(jump_target(&groups, 2, false), 0);
+        assert_eq!(jump_target(&groups, 1, false), 0);
+        // Nothing after the last letter
+        assert_eq!(jump_target(&groups, 5, true), 5);
+        assert_eq!(jump_target(&[], 0, true), 0);
+    }
+
+    #[test]
+    fn test_random_pick_rerolls_to_another_game() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        assert!(RandomPick::new(Vec::new(), &mut rng).is_none());
+
+        let games: Vec<_> = (0..3)
+            .map(|i| Game::new(PathBuf::from(format!("/Roms/GBA/{i}.gba"))))
+            .collect();
+        let mut random = RandomPick::new(games, &mut rng).unwrap();
+        for _ in 0..20 {
+            let before = random.game().path.clone();
+            random.reroll(&mut rng);
+            assert_ne!(random.game().path, before);
         }
+
+        let games = vec![Game::new(PathBuf::from("/Roms/GBA/Only.gba"))];
+        let mut random = RandomPick::new(games, &mut rng).unwrap();
+        random.reroll(&mut rng);
+        assert_eq!(random.game().name, "Only");
+    }
+
+    #[test]
+    fn test_list_count() {
+        assert_eq!(
+            ListCount::new(36, 214, 214),
+            ListCount::Position {
+                position: 37,
+                total: 214
+            }
+        );
+        // Wrapping around to either end
+        assert_eq!(
+            ListCount::new(0, 214, 214),
+            ListCount::Position {
+                position: 1,
+                total: 214
+            }
+        );
+        assert_eq!(
+            ListCount::new(213, 214, 214),
+            ListCount::Position {
+                position: 214,
+                total: 214
+            }
+        );
+        // A selection left over from before a refresh removed entries
+        assert_eq!(
+            ListCount::new(20, 9, 9),
+            ListCount::Position {
+                position: 9,
+                total: 9
+            }
+        );
+
+        assert_eq!(
+            ListCount::new(3, 12, 214),
+            ListCount::Filtered {
+                shown: 12,
+                total: 214
+            }
+        );
+        assert_eq!(
+            ListCount::new(0, 0, 214),
+            ListCount::Filtered {
+                shown: 0,
+                total: 214
+            }
+        );
+        assert_eq!(ListCount::new(0, 0, 0), ListCount::Empty);
+    }
+
+    #[test]
+    fn test_read_entries_of_empty_and_unreadable_folders() -> Result<()> {
+        let dir = temp_dir("entry-list-read");
+        std::fs::create_dir(dir.join("Empty"))?;
+        std::fs::write(dir.join("Tetris.gb"), b"rom")?;
+
+        let database = Database::in_memory()?;
+        let profile = Profile {
+            name: "test".to_string(),
+            games_dir: Some(dir.clone()),
+            restricted: false,
+        };
+        let filter = LibraryFilter::new(&database, &profile)?;
+        let read = |path: &Path| {
+            let sort = GamesSort::Alphabetical(Directory::new(path.to_path_buf()));
+            read_entries(&sort, &database, &ConsoleMapper::new(), &filter)
+        };
+
+        let (entries, total) = read(&dir.join("Empty")).expect("empty folder is readable");
+        assert!(entries.is_empty());
+        assert_eq!(total, None);
+
+        // Folders that are gone, or aren't folders, can't be read rather than being empty
+        assert!(read(&dir.join("Removed")).is_none());
+        assert!(read(&dir.join("Tetris.gb")).is_none());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_launch_error_shows_launch_failure() -> Result<()> {
+        let dir = temp_dir("entry-list-launch");
+        let path = dir.join("Game.xyz");
+        std::fs::write(&path, "")?;
+
+        let mut res = TypeMap::new();
+        res.insert(Database::in_memory()?);
+        res.insert(ConsoleMapper::new());
+        let res = Resources::new(res);
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let mut game = Game::new(path);
+        EntryList::<GamesSort>::launch_game(&res, &mut game, tx).await?;
+
+        let Some(Command::ShowLaunchFailure(failure)) = rx.recv().await else {
+            panic!("expected the launch failure to be shown");
+        };
+        assert_eq!(failure.name, "Game.xyz");
+        assert!(matches!(
+            failure.error,
+            Some(LaunchError::NoConsole { ref extension, .. }) if extension == "xyz"
+        ));
+        assert!(rx.try_recv().is_err());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
     }
 }
@@ -0,0 +1,55 @@
+use anyhow::Result;
+use common::database::Database;
+use common::geom::{Alignment, Point};
+use common::locale::Locale;
+use common::platform::Key;
+use common::resources::Resources;
+use common::view::{ButtonHint, Row};
+
+use crate::entry::game::Game;
+
+/// Where the favorite hint goes in the button hints of the games and recents tabs.
+const HINT_INDEX: usize = 1;
+
+/// Marks the game as a favorite, or unmarks it if it already is one. Returns whether it is a
+/// favorite now.
+pub fn toggle(res: &Resources, game: &Game) -> Result<bool> {
+    let database = res.get::<Database>();
+    let favorite = !database.is_favorite(&game.path)?;
+    database.set_favorite(&game.path, favorite)?;
+    Ok(favorite)
+}
+
+/// Shows whether the highlighted game is a favorite, or nothing if no game is highlighted.
+/// Returns true if the hint changed, as the row doesn't clear what it no longer covers.
+pub fn update_hint(
+    res: &Resources,
+    button_hints: &mut Row<ButtonHint<String>>,
+    game: Option<&Game>,
+) -> Result<bool> {
+    let Some(game) = game else {
+        return Ok(button_hints.remove(HINT_INDEX).is_some());
+    };
+
+    let text = if res.get::<Database>().is_favorite(&game.path)? {
+        res.get::<Locale>().t("button-unfavorite")
+    } else {
+        res.get::<Locale>().t("button-favorite")
+    };
+    match button_hints.get_mut(HINT_INDEX) {
+        Some(hint) if hint.text() == text => Ok(false),
+        Some(hint) => {
+            hint.set_text(text);
+            Ok(true)
+        }
+        None => {
+            button_hints.push(ButtonHint::new(
+                Point::zero(),
+                Key::X,
+                text,
+                Alignment::Left,
+            ));
+            Ok(true)
+        }
+    }
+}
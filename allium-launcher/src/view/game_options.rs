@@ -0,0 +1,254 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::database::Database;
+use common::display::Display;
+use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::Styles;
+use common::view::{ButtonHint, ButtonIcon, Label, Row, Select, SettingsList, View};
+use log::error;
+use tokio::sync::mpsc::Sender;
+
+use crate::consoles::ConsoleMapper;
+use crate::entry::game::Game;
+
+/// Options of a single game, opened from its menu: which core it launches with.
+#[derive(Debug)]
+pub struct GameOptions {
+    rect: Rect,
+    res: Resources,
+    game: Game,
+    /// Cores offered after "Default".
+    cores: Vec<String>,
+    title: Label<String>,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+    dirty: bool,
+}
+
+impl GameOptions {
+    pub fn new(rect: Rect, res: Resources, game: Game) -> Result<Self> {
+        let Rect { x, y, w, h } = rect;
+
+        let console_mapper = res.get::<ConsoleMapper>();
+        let database = res.get::<Database>();
+        let cores = console_mapper.cores_for_game(&database, &game.path)?;
+        let core = database.core_override(&game.path)?;
+        drop(database);
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let title = Label::new(
+            Point::new(x + 12, y + 8),
+            game.name.clone(),
+            Alignment::Left,
+            Some(w - 24),
+        );
+
+        let selected = core
+            .and_then(|core| cores.iter().position(|c| *c == core))
+            .map_or(0, |i| i + 1);
+        let values = std::iter::once(locale.t("game-options-core-default"))
+            .chain(cores.iter().map(|c| console_mapper.get_core_name(c)))
+            .collect();
+        let top = 8 + styles.ui_font.size as i32 + 8;
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + top,
+                w - 24,
+                h - top as u32 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            vec![
+                locale.t("game-options-core"),
+                locale.t("game-options-effective-core"),
+            ],
+            vec![
+                Box::new(Select::new(
+                    Point::zero(),
+                    selected,
+                    values,
+                    Alignment::Right,
+                )),
+                Box::new(Label::new(
+                    Point::zero(),
+                    String::new(),
+                    Alignment::Right,
+                    None,
+                )),
+            ],
+            styles.row_layout(),
+        );
+        list.set_list_settings(*res.get::<ListSettings>());
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-edit"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(console_mapper);
+        drop(locale);
+        drop(styles);
+
+        let mut this = Self {
+            rect,
+            res,
+            game,
+            cores,
+            title,
+            list,
+            button_hints,
+            dirty: true,
+        };
+        this.show_effective_core()?;
+        Ok(this)
+    }
+
+    /// Shows the core the game launches with now, which is the console's unless one was chosen.
+    fn show_effective_core(&mut self) -> Result<()> {
+        let console_mapper = self.res.get::<ConsoleMapper>();
+        let database = self.res.get::<Database>();
+        let core = match console_mapper.console_for_game(&database, &self.game.path)? {
+            Some(console) => match &console.path {
+                Some(path) => path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string()),
+                None => console_mapper
+                    .core_for_game(&database, &self.game, console)?
+                    .map(|core| console_mapper.get_core_name(&core)),
+            },
+            None => None,
+        };
+        let core = core.unwrap_or_else(|| self.res.get::<Locale>().t("game-options-no-core"));
+        drop(console_mapper);
+        drop(database);
+
+        self.list.set_right(
+            1,
+            Box::new(Label::new(Point::zero(), core, Alignment::Right, None)),
+        );
+        Ok(())
+    }
+
+    /// Saves the core picked in the list, or removes the override for "Default".
+    fn set_core(&mut self, selected: usize) -> Result<()> {
+        let core = selected
+            .checked_sub(1)
+            .and_then(|i| self.cores.get(i))
+            .map(String::as_str);
+        self.res
+            .get::<Database>()
+            .set_core_override(&self.game.path, core)?;
+        self.show_effective_core()
+    }
+}
+
+#[async_trait(?Send)]
+impl View for GameOptions {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.title.set_should_draw();
+            self.list.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.dirty = false;
+            drawn = true;
+        }
+
+        drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self.title.should_draw()
+            || self.list.should_draw()
+            || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self
+            .list
+            .handle_key_event(event, commands.clone(), bubble)
+            .await?
+        {
+            while let Some(command) = bubble.pop_front() {
+                if let Command::ValueChanged(0, value) = command {
+                    let selected = value.as_int().unwrap_or_default() as usize;
+                    if let Err(e) = self.set_core(selected) {
+                        error!("failed to save core override: {}", e);
+                    }
+                    commands.send(Command::Redraw).await?;
+                }
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.title, &self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.title, &mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
@@ -1,31 +1,42 @@
+use std::cmp::Reverse;
 use std::collections::VecDeque;
+use std::path::Path;
+use std::time::SystemTime;
+use std::{fs, mem};
 
 use anyhow::Result;
 use async_trait::async_trait;
-use chrono::Duration;
 use common::command::Command;
-use common::constants::ALLIUM_GAMES_DIR;
-use common::database::Database;
+use common::database::{self, Database};
+use common::filename_rules::FilenameRules;
 use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::profile::Profile;
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::sort_order::{self, SortKey, SortOrder};
+use common::stylesheet::Styles;
 use common::view::{ButtonHint, ButtonIcon, Row, View};
 use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
 use crate::consoles::ConsoleMapper;
+use crate::entry::collection::{self, Listing};
 use crate::entry::directory::Directory;
-use crate::entry::{Entry, Sort};
+use crate::entry::game::Game;
+use crate::entry::{retroarch_playlist, Entry, Sort};
+use crate::launcher_config;
+use crate::library_filter::LibraryFilter;
 use crate::view::entry_list::{EntryList, EntryListState};
+use crate::view::favorites;
 
 pub type GamesState = EntryListState<GamesSort>;
 
 #[derive(Debug)]
 pub struct Games {
     rect: Rect,
+    res: Resources,
     list: EntryList<GamesSort>,
     button_hints: Row<ButtonHint<String>>,
 }
@@ -34,9 +45,9 @@ impl Games {
     pub fn new(rect: Rect, res: Resources, list: EntryList<GamesSort>) -> Result<Self> {
         let Rect { x, y, w: _, h } = rect;
 
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
-        let button_hints = Row::new(
+        let mut button_hints = Row::new(
             Point::new(
                 x + 12,
                 y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
@@ -45,7 +56,7 @@ impl Games {
                 let locale = res.get::<Locale>();
                 vec![ButtonHint::new(
                     Point::zero(),
-                    Key::X,
+                    Key::Start,
                     locale.t("sort-search"),
                     Alignment::Left,
                 )]
@@ -53,9 +64,12 @@ impl Games {
             Alignment::Left,
             12,
         );
+        drop(styles);
+        favorites::update_hint(&res, &mut button_hints, list.selected_game())?;
 
         Ok(Self {
             rect,
+            res,
             list,
             button_hints,
         })
@@ -63,15 +77,13 @@ impl Games {
 
     pub fn load_or_new(rect: Rect, res: Resources, state: Option<GamesState>) -> Result<Self> {
         let list = if let Some(state) = state {
-            let selected = state.selected;
-            let mut list = EntryList::load(rect, res.clone(), state)?;
-            list.select(selected);
-            list
+            EntryList::load(rect, res.clone(), state)?
         } else {
+            let games_dir = res.get::<Profile>().games_dir();
             EntryList::new(
                 rect,
                 res.clone(),
-                GamesSort::Alphabetical(Directory::new(ALLIUM_GAMES_DIR.clone())),
+                GamesSort::Alphabetical(Directory::new(games_dir)),
             )?
         };
 
@@ -88,7 +100,7 @@ impl View for Games {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -116,13 +128,33 @@ impl View for Games {
         commands: Sender<Command>,
         bubble: &mut VecDeque<Command>,
     ) -> Result<bool> {
-        match event {
-            KeyEvent::Pressed(Key::X) => {
-                commands.send(Command::StartSearch).await?;
-                return Ok(true);
+        let handled = match (event, self.list.selected_game()) {
+            (KeyEvent::Pressed(Key::X), Some(game)) => {
+                favorites::toggle(&self.res, game)?;
+                true
             }
-            _ => self.list.handle_key_event(event, commands, bubble).await,
+            (KeyEvent::Pressed(Key::Start), _) => {
+                // The highlighted game may offer to search for its art instead
+                if !self
+                    .list
+                    .handle_key_event(event, commands.clone(), bubble)
+                    .await?
+                {
+                    commands.send(Command::StartSearch).await?;
+                }
+                true
+            }
+            (_, _) => {
+                self.list
+                    .handle_key_event(event, commands.clone(), bubble)
+                    .await?
+            }
+        };
+
+        if favorites::update_hint(&self.res, &mut self.button_hints, self.list.selected_game())? {
+            commands.send(Command::Redraw).await?;
         }
+        Ok(handled)
     }
 
     fn children(&self) -> Vec<&dyn View> {
@@ -133,7 +165,7 @@ impl View for Games {
         vec![&mut self.list]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
@@ -147,6 +179,7 @@ pub enum GamesSort {
     Alphabetical(Directory),
     LastPlayed(Directory),
     MostPlayed(Directory),
+    RecentlyAdded(Directory),
     Random(Directory),
 }
 
@@ -156,17 +189,21 @@ impl GamesSort {
             GamesSort::Alphabetical(d) => d,
             GamesSort::LastPlayed(d) => d,
             GamesSort::MostPlayed(d) => d,
+            GamesSort::RecentlyAdded(d) => d,
             GamesSort::Random(d) => d,
         }
     }
 }
 
 impl Sort for GamesSort {
+    const HAS_MULTI_SELECT: bool = true;
+
     fn button_hint(&self, locale: &Locale) -> String {
         match self {
             GamesSort::Alphabetical(_) => locale.t("sort-alphabetical"),
             GamesSort::LastPlayed(_) => locale.t("sort-last-played"),
             GamesSort::MostPlayed(_) => locale.t("sort-most-played"),
+            GamesSort::RecentlyAdded(_) => locale.t("sort-recently-added"),
             GamesSort::Random(_) => locale.t("sort-random"),
         }
     }
@@ -175,7 +212,8 @@ impl Sort for GamesSort {
         match self {
             GamesSort::Alphabetical(d) => GamesSort::LastPlayed(d.clone()),
             GamesSort::LastPlayed(d) => GamesSort::MostPlayed(d.clone()),
-            GamesSort::MostPlayed(d) => GamesSort::Random(d.clone()),
+            GamesSort::MostPlayed(d) => GamesSort::RecentlyAdded(d.clone()),
+            GamesSort::RecentlyAdded(d) => GamesSort::Random(d.clone()),
             GamesSort::Random(d) => GamesSort::Alphabetical(d.clone()),
         }
     }
@@ -185,72 +223,62 @@ impl Sort for GamesSort {
             GamesSort::Alphabetical(_) => GamesSort::Alphabetical(directory),
             GamesSort::LastPlayed(_) => GamesSort::LastPlayed(directory),
             GamesSort::MostPlayed(_) => GamesSort::MostPlayed(directory),
+            GamesSort::RecentlyAdded(_) => GamesSort::RecentlyAdded(directory),
             GamesSort::Random(_) => GamesSort::Random(directory),
         }
     }
 
-    fn entries(&self, database: &Database, console_mapper: &ConsoleMapper) -> Result<Vec<Entry>> {
-        let mut entries = self.directory().entries(database, console_mapper)?;
+    fn folder(&self) -> Option<&Path> {
+        let directory = self.directory();
+        // Collections and playlists aren't folders on the SD card
+        directory
+            .listing
+            .is_none()
+            .then_some(directory.path.as_path())
+    }
+
+    fn collection(&self) -> Option<i64> {
+        match self.directory().listing {
+            Some(Listing::Collection(id)) => Some(id),
+            Some(Listing::Collections) | Some(Listing::RetroArchPlaylist) | None => None,
+        }
+    }
+
+    fn is_alphabetical(&self) -> bool {
+        matches!(self, GamesSort::Alphabetical(_))
+    }
+
+    fn entries(
+        &self,
+        database: &Database,
+        console_mapper: &ConsoleMapper,
+        filter: &LibraryFilter,
+    ) -> Result<Vec<Entry>> {
+        let directory = self.directory();
+        let mut entries = directory.entries(database, console_mapper)?;
+        if directory.listing.is_none() && directory.path == filter.games_dir() {
+            if let Some(collections) = collection::folder(&directory.path, database)? {
+                entries.push(Entry::Directory(collections));
+            }
+            entries.extend(
+                retroarch_playlist::folders(&directory.path, &launcher_config::playlists_dir())
+                    .into_iter()
+                    .map(Entry::Directory),
+            );
+        }
+        filter.retain(&mut entries);
 
         match self {
             GamesSort::Alphabetical(_) => {
                 entries.sort_unstable();
             }
             GamesSort::LastPlayed(_) => {
-                // With this current implementation, apps will appear before games.
-                // TOOD: think about whether this is OK?
-                let mut games = Vec::with_capacity(entries.len());
-                let mut i = 0;
-                while i < entries.len() {
-                    if matches!(entries[i], Entry::Game(_)) {
-                        match entries.remove(i) {
-                            Entry::Game(game) => games.push(game),
-                            _ => unreachable!(),
-                        }
-                    } else {
-                        i += 1;
-                    }
-                }
-
-                let db_games = database
-                    .select_games(&games.iter().map(|g| g.path.as_path()).collect::<Vec<_>>())?;
-
-                let mut games = games.into_iter().zip(db_games).collect::<Vec<_>>();
-                games.sort_unstable_by_key(|(_, db_game)| {
-                    db_game.as_ref().map(|g| -g.last_played).unwrap_or_default()
-                });
-                entries.retain(|e| matches!(e, Entry::Directory(_) | Entry::App(_)));
-                entries.sort_unstable();
-                entries.extend(games.into_iter().map(|(game, _)| Entry::Game(game)));
+                sort_by_stats(&mut entries, database, SortOrder::LastPlayed)?
             }
             GamesSort::MostPlayed(_) => {
-                let mut games = Vec::with_capacity(entries.len());
-                let mut i = 0;
-                while i < entries.len() {
-                    if matches!(entries[i], Entry::Game(_)) {
-                        match entries.remove(i) {
-                            Entry::Game(game) => games.push(game),
-                            _ => unreachable!(),
-                        }
-                    } else {
-                        i += 1;
-                    }
-                }
-
-                let db_games = database
-                    .select_games(&games.iter().map(|g| g.path.as_path()).collect::<Vec<_>>())?;
-
-                let mut games = games.into_iter().zip(db_games).collect::<Vec<_>>();
-                games.sort_unstable_by_key(|(_, db_game)| {
-                    db_game
-                        .as_ref()
-                        .map(|g| -g.play_time)
-                        .unwrap_or(Duration::zero())
-                });
-                entries.retain(|e| matches!(e, Entry::Directory(_) | Entry::App(_)));
-                entries.sort_unstable();
-                entries.extend(games.into_iter().map(|(game, _)| Entry::Game(game)));
+                sort_by_stats(&mut entries, database, SortOrder::MostPlayed)?
             }
+            GamesSort::RecentlyAdded(_) => sort_by_modified(&mut entries),
             GamesSort::Random(_) => {
                 entries.shuffle(&mut rand::thread_rng());
             }
@@ -259,3 +287,90 @@ impl Sort for GamesSort {
         Ok(entries)
     }
 }
+
+/// Sorts games by play statistics after the folders and apps, which are sorted by name.
+fn sort_by_stats(entries: &mut Vec<Entry>, database: &Database, order: SortOrder) -> Result<()> {
+    let mut games = Vec::with_capacity(entries.len());
+    for entry in mem::take(entries) {
+        match entry {
+            Entry::Game(game) => games.push(game),
+            entry => entries.push(entry),
+        }
+    }
+
+    let db_games =
+        database.select_games(&games.iter().map(|g| g.path.as_path()).collect::<Vec<_>>())?;
+    let mut games = games.into_iter().zip(db_games).collect::<Vec<_>>();
+    let rules = FilenameRules::active();
+    games.sort_unstable_by(|(a, db_a), (b, db_b)| {
+        sort_order::compare(order, &rules, &sort_key(a, db_a), &sort_key(b, db_b))
+    });
+
+    entries.sort_unstable();
+    entries.extend(games.into_iter().map(|(game, _)| Entry::Game(game)));
+    Ok(())
+}
+
+/// Sorts games by when their files were last modified, newest first, after the folders and apps,
+/// which are sorted by name. Games whose time can't be read sort last.
+fn sort_by_modified(entries: &mut Vec<Entry>) {
+    let mut games = Vec::with_capacity(entries.len());
+    for entry in mem::take(entries) {
+        match entry {
+            Entry::Game(game) => {
+                let modified = fs::metadata(&game.path)
+                    .and_then(|m| m.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                games.push((Reverse(modified), Entry::Game(game)));
+            }
+            entry => entries.push(entry),
+        }
+    }
+    games.sort_unstable();
+
+    entries.sort_unstable();
+    entries.extend(games.into_iter().map(|(_, entry)| entry));
+}
+
+fn sort_key<'a>(game: &'a Game, db_game: &'a Option<database::Game>) -> SortKey<'a> {
+    db_game
+        .as_ref()
+        .map_or_else(|| SortKey::new(&game.name, &game.path), SortKey::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::time::Duration;
+
+    use common::test_utils::temp_dir;
+
+    use super::*;
+
+    #[test]
+    fn test_sort_by_modified() {
+        let dir = temp_dir("games-modified");
+        let touch = |name: &str, secs: u64| {
+            let path = dir.join(name);
+            File::create(&path)
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+                .unwrap();
+            Entry::Game(Game::new(path))
+        };
+
+        let mut entries = vec![
+            touch("Old.gba", 1_000),
+            Entry::Game(Game::new(dir.join("Missing.gba"))),
+            Entry::Directory(Directory::new(dir.join("Folder"))),
+            touch("New.gba", 3_000),
+            touch("Middle.gba", 2_000),
+        ];
+        sort_by_modified(&mut entries);
+
+        let names: Vec<_> = entries.iter().map(Entry::name).collect();
+        assert_eq!(names, ["Folder", "New", "Middle", "Old", "Missing"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::display::Display;
+use common::geom::{Alignment, Point, Rect};
+use common::launch_failure::{LaunchError, LaunchFailure};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{ButtonHint, ButtonIcon, Label, Row, View};
+use tokio::sync::mpsc::Sender;
+
+/// Explains why the last game returned to the launcher right after it was launched, with the end
+/// of its output, or why a game couldn't be launched at all.
+#[derive(Debug)]
+pub struct LaunchFailureDialog {
+    rect: Rect,
+    title: Label<String>,
+    status: Label<String>,
+    hint: Label<String>,
+    output: Vec<Label<String>>,
+    button_hints: Row<ButtonHint<String>>,
+    dirty: bool,
+}
+
+impl LaunchFailureDialog {
+    pub fn new(rect: Rect, res: Resources, failure: LaunchFailure) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let font_size = styles.ui_font.size as i32;
+        let line_height = font_size + 8;
+
+        let mut title = Label::new(
+            Point::new(x + 12, y + 8),
+            locale.ta(
+                "launch-failure-title",
+                &[("name".to_string(), failure.name.into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            Alignment::Left,
+            Some(w - 24),
+        );
+        title.color(StylesheetColor::Highlight);
+
+        let (status, hint) = match &failure.error {
+            Some(LaunchError::NoConsole { extension, folder }) => (
+                locale.t("launch-error-no-console"),
+                locale.ta(
+                    "launch-error-tried",
+                    &[
+                        ("extension".to_string(), extension.clone().into()),
+                        ("folder".to_string(), folder.clone().into()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+            ),
+            Some(LaunchError::NoCore { console }) => (
+                locale.ta(
+                    "launch-error-no-core",
+                    &[("console".to_string(), console.clone().into())]
+                        .into_iter()
+                        .collect(),
+                ),
+                locale.t("launch-error-no-core-hint"),
+            ),
+            Some(LaunchError::CoreMissing { console, path }) => (
+                locale.ta(
+                    "launch-error-core-missing",
+                    &[("console".to_string(), console.clone().into())]
+                        .into_iter()
+                        .collect(),
+                ),
+                locale.ta(
+                    "launch-error-core-missing-hint",
+                    &[("path".to_string(), path.display().to_string().into())]
+                        .into_iter()
+                        .collect(),
+                ),
+            ),
+            None => (
+                match (failure.code, failure.signal) {
+                    (Some(0), _) => locale.t("launch-failure-exited-early"),
+                    (Some(code), _) => locale.ta(
+                        "launch-failure-exit-code",
+                        &[("code".to_string(), code.into())].into_iter().collect(),
+                    ),
+                    (None, Some(signal)) => locale.ta(
+                        "launch-failure-signal",
+                        &[("signal".to_string(), signal.into())]
+                            .into_iter()
+                            .collect(),
+                    ),
+                    (None, None) => locale.t("launch-failure-exited-early"),
+                },
+                locale.t("launch-failure-hint"),
+            ),
+        };
+        let status = Label::new(
+            Point::new(x + 12, y + 8 + line_height + 8),
+            status,
+            Alignment::Left,
+            Some(w - 24),
+        );
+
+        let hint = Label::new(
+            Point::new(x + 12, y + 8 + 2 * line_height + 8),
+            hint,
+            Alignment::Left,
+            Some(w - 24),
+        );
+
+        // Show as much of the end of the output as fits above the button hints
+        let output_y = y + 8 + 3 * line_height + 16;
+        let output_bottom = y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 16;
+        let fits = ((output_bottom - output_y) / line_height).max(0) as usize;
+        let lines: Vec<&str> = failure.stderr.lines().collect();
+        let output = lines[lines.len().saturating_sub(fits)..]
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let mut label = Label::new(
+                    Point::new(x + 12, output_y + i as i32 * line_height),
+                    line.to_string(),
+                    Alignment::Left,
+                    Some(w - 24),
+                );
+                label.color(StylesheetColor::Disabled);
+                label
+            })
+            .collect();
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![ButtonHint::new(
+                Point::zero(),
+                Key::B,
+                locale.t("button-back"),
+                Alignment::Right,
+            )],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            title,
+            status,
+            hint,
+            output,
+            button_hints,
+            dirty: true,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl View for LaunchFailureDialog {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            for child in self.children_mut() {
+                child.set_should_draw();
+            }
+            self.dirty = false;
+            drawn = true;
+        }
+
+        for child in self.children_mut() {
+            drawn |= child.should_draw() && child.draw(display, styles)?;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty || self.children().iter().any(|c| c.should_draw())
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if let KeyEvent::Pressed(Key::A | Key::B) = event {
+            bubble.push_back(Command::CloseView);
+            commands.send(Command::Redraw).await?;
+        }
+        // The dialog is modal
+        Ok(true)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        let mut children: Vec<&dyn View> = vec![&self.title, &self.status, &self.hint];
+        children.extend(self.output.iter().map(|v| v as &dyn View));
+        children.push(&self.button_hints);
+        children
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        let mut children: Vec<&mut dyn View> =
+            vec![&mut self.title, &mut self.status, &mut self.hint];
+        children.extend(self.output.iter_mut().map(|v| v as &mut dyn View));
+        children.push(&mut self.button_hints);
+        children
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
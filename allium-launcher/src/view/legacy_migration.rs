@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::display::Display;
+use common::geom::{Alignment, Point, Rect};
+use common::legacy_layout::{LegacyFolder, MigrationMode};
+use common::list_settings::ListSettings;
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{ButtonHint, ButtonIcon, Label, Row, ScrollList, View};
+use tokio::sync::mpsc::Sender;
+
+/// Lists ROM folders laid out for other firmware and asks the user what to do with them.
+#[derive(Debug)]
+pub struct LegacyMigration {
+    rect: Rect,
+    folders: Vec<LegacyFolder>,
+    title: Label<String>,
+    list: ScrollList,
+    button_hints: Row<ButtonHint<String>>,
+    dirty: bool,
+}
+
+impl LegacyMigration {
+    pub fn new(rect: Rect, res: Resources, folders: Vec<LegacyFolder>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let mut title = Label::new(
+            Point::new(x + w as i32 / 2, y + 8),
+            locale.t("legacy-migration-title"),
+            Alignment::Center,
+            Some(w - 24),
+        );
+        title.color(StylesheetColor::Highlight);
+
+        let list_y = y + 8 + styles.ui_font.size as i32 + 16;
+        let mut list = ScrollList::new(
+            Rect::new(
+                x + 12,
+                list_y,
+                w - 24,
+                h - (list_y - y) as u32 - ButtonIcon::diameter(&styles) - 16,
+            ),
+            folders
+                .iter()
+                .map(|folder| {
+                    locale.ta(
+                        "legacy-migration-folder",
+                        &[
+                            ("legacy".to_string(), folder.name.clone().into()),
+                            ("target".to_string(), folder.target.clone().into()),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    )
+                })
+                .collect(),
+            Alignment::Left,
+            styles.row_layout(),
+        );
+        list.set_list_settings(*res.get::<ListSettings>());
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("legacy-migration-move"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::X,
+                    locale.t("legacy-migration-link"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::Y,
+                    locale.t("legacy-migration-keep"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("legacy-migration-skip"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            folders,
+            title,
+            list,
+            button_hints,
+            dirty: true,
+        }
+    }
+
+    pub fn folders(&self) -> &[LegacyFolder] {
+        &self.folders
+    }
+}
+
+#[async_trait(?Send)]
+impl View for LegacyMigration {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        if self.dirty {
+            display.load(self.rect)?;
+            self.title.set_should_draw();
+            self.list.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.dirty = false;
+        }
+
+        let mut drawn = false;
+        drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self.title.should_draw()
+            || self.list.should_draw()
+            || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        let mode = match event {
+            KeyEvent::Pressed(Key::A) => MigrationMode::Move,
+            KeyEvent::Pressed(Key::X) => MigrationMode::Symlink,
+            KeyEvent::Pressed(Key::Y) => MigrationMode::Map,
+            KeyEvent::Pressed(Key::B) => MigrationMode::Skip,
+            _ => return self.list.handle_key_event(event, commands, bubble).await,
+        };
+        commands.send(Command::MigrateLegacyFolders(mode)).await?;
+        Ok(true)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.title, &self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.title, &mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::ALLIUM_SD_ROOT;
+use common::database::{Database, ScrapeStatus};
+use common::geom::{Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{ArtPlaceholder, View};
+use log::error;
+use tokio::sync::mpsc::Sender;
+
+use crate::consoles::ConsoleMapper;
+use crate::entry::game::Game;
+use crate::scraper;
+
+/// How often a running search is checked on.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Search {
+    /// Art can't be searched for right now, so the user is told where to put it instead.
+    Unavailable,
+    Available,
+    Searching,
+    NotFound,
+    /// Art was saved, and the game should look for it again.
+    Found,
+}
+
+/// Shown in the box art panel for a game without art. Offers to search for art for just that
+/// game, and shows how the search is going.
+#[derive(Debug)]
+pub struct MissingArt {
+    res: Resources,
+    game: Option<PathBuf>,
+    search: Search,
+    since_poll: Duration,
+    /// Whether the hint no longer matches the search.
+    stale_hint: bool,
+    placeholder: ArtPlaceholder,
+}
+
+impl MissingArt {
+    pub fn new(rect: Rect, res: Resources) -> Self {
+        Self {
+            res,
+            game: None,
+            search: Search::Unavailable,
+            since_poll: Duration::ZERO,
+            stale_hint: true,
+            placeholder: ArtPlaceholder::new(rect),
+        }
+    }
+
+    /// Shows the placeholder for `game`. Does nothing if it's already shown.
+    pub fn set_game(&mut self, game: &Game) {
+        if self.game.as_ref() == Some(&game.path) {
+            return;
+        }
+        self.search = self.initial_search(&game.path);
+        self.game = Some(game.path.clone());
+        self.stale_hint = true;
+        self.placeholder.set_name(&game.name);
+        self.placeholder.set_should_draw();
+    }
+
+    /// Hides the placeholder, such as when the selected game has art.
+    pub fn clear(&mut self) {
+        self.game = None;
+    }
+
+    fn initial_search(&self, game: &Path) -> Search {
+        match self.res.get::<Database>().scrape_status(game) {
+            Ok(Some(ScrapeStatus::Pending)) => Search::Searching,
+            Ok(Some(ScrapeStatus::Failed)) => Search::NotFound,
+            Ok(_) if DefaultPlatform::has_wifi() && self.can_scrape(game) => Search::Available,
+            Ok(_) => Search::Unavailable,
+            Err(e) => {
+                error!("failed to load scrape status: {}", e);
+                Search::Unavailable
+            }
+        }
+    }
+
+    fn can_scrape(&self, game: &Path) -> bool {
+        scraper::can_scrape(&self.res.get::<ConsoleMapper>(), game)
+    }
+
+    /// Whether the placeholder offers to search for art.
+    pub fn offers_search(&self) -> bool {
+        self.search == Search::Available
+    }
+
+    /// Queues a search for art for the shown game alone. Returns false without queueing anything
+    /// if it's already being searched for, or can't be. The caller starts the scraper.
+    pub fn search(&mut self) -> Result<bool> {
+        let Some(game) = self.game.as_deref() else {
+            return Ok(false);
+        };
+        if !matches!(self.search, Search::Available | Search::Unavailable) || !self.can_scrape(game)
+        {
+            return Ok(false);
+        }
+        scraper::enqueue_game(
+            &self.res.get::<Database>(),
+            &self.res.get::<ConsoleMapper>(),
+            game,
+        )?;
+        self.search = Search::Searching;
+        self.since_poll = Duration::ZERO;
+        self.stale_hint = true;
+        Ok(true)
+    }
+
+    /// Returns the shown game once art was saved for it, so that it can look for its art again.
+    pub fn take_found(&mut self) -> Option<PathBuf> {
+        if self.search == Search::Found {
+            self.search = Search::Unavailable;
+            return self.game.take();
+        }
+        None
+    }
+
+    fn poll(&mut self) {
+        let Some(game) = self.game.as_deref() else {
+            return;
+        };
+        let search = match self.res.get::<Database>().scrape_status(game) {
+            Ok(Some(ScrapeStatus::Completed)) => Search::Found,
+            Ok(Some(ScrapeStatus::Failed) | None) => Search::NotFound,
+            Ok(Some(ScrapeStatus::Pending)) => return,
+            Err(e) => {
+                error!("failed to load scrape status: {}", e);
+                return;
+            }
+        };
+        self.search = search;
+        self.stale_hint = true;
+        self.placeholder.set_should_draw();
+    }
+
+    fn update_hint(&mut self) {
+        let locale = self.res.get::<Locale>();
+        let path = || {
+            let path = self
+                .game
+                .as_deref()
+                .and_then(scraper::image_path)
+                .unwrap_or_default();
+            let path = path.strip_prefix(ALLIUM_SD_ROOT.as_path()).unwrap_or(&path);
+            [("path".to_string(), path.display().to_string().into())]
+                .into_iter()
+                .collect()
+        };
+        let (hint, color) = match self.search {
+            Search::Unavailable => (
+                locale.ta("art-placeholder-path", &path()),
+                StylesheetColor::Disabled,
+            ),
+            Search::Available => (
+                locale.t("art-placeholder-search"),
+                StylesheetColor::Disabled,
+            ),
+            Search::Searching => (
+                locale.t("art-placeholder-searching"),
+                StylesheetColor::Highlight,
+            ),
+            Search::NotFound => (
+                locale.ta("art-placeholder-not-found", &path()),
+                StylesheetColor::Disabled,
+            ),
+            Search::Found => (String::new(), StylesheetColor::Disabled),
+        };
+        drop(locale);
+        self.placeholder.set_hint(hint, color);
+        self.stale_hint = false;
+    }
+}
+
+#[async_trait(?Send)]
+impl View for MissingArt {
+    fn update(&mut self, dt: Duration) {
+        if self.game.is_none() || self.search != Search::Searching {
+            return;
+        }
+        self.since_poll += dt;
+        if self.since_poll >= POLL_INTERVAL {
+            self.since_poll = Duration::ZERO;
+            self.poll();
+        }
+    }
+
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        if self.stale_hint {
+            self.update_hint();
+        }
+        self.placeholder.draw(display, styles)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.game.is_some() && (self.stale_hint || self.placeholder.should_draw())
+    }
+
+    fn set_should_draw(&mut self) {
+        self.placeholder.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        _event: KeyEvent,
+        _commands: Sender<Command>,
+        _bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.placeholder]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.placeholder]
+    }
+
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
+        self.placeholder.bounding_box(styles)
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.placeholder.set_position(point);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::database::NewGame;
+    use type_map::TypeMap;
+
+    use super::*;
+
+    fn missing_art() -> (MissingArt, Database) {
+        let database = Database::in_memory().unwrap();
+        let mut console_mapper = ConsoleMapper::new();
+        console_mapper
+            .parse_config(include_str!(
+                "../../../assets/root/.allium/config/consoles.toml"
+            ))
+            .unwrap();
+
+        let mut res = TypeMap::new();
+        res.insert(database.clone());
+        res.insert(console_mapper);
+        let art = MissingArt::new(Rect::new(0, 0, 250, 360), Resources::new(res));
+        (art, database)
+    }
+
+    #[test]
+    fn test_search_queues_one_game() -> Result<()> {
+        let (mut art, database) = missing_art();
+        // Other games without art aren't queued along with it
+        let games = ["Roms/GBA/Game.gba", "Roms/GBA/Other.gba"].map(PathBuf::from);
+        database.update_games(
+            &games
+                .iter()
+                .map(|path| NewGame {
+                    name: path.file_stem().unwrap().to_string_lossy().to_string(),
+                    path: path.clone(),
+                    image: None,
+                    core: None,
+                })
+                .collect::<Vec<_>>(),
+        )?;
+
+        art.set_game(&Game::new(games[0].clone()));
+        assert!(art.search()?);
+        assert_eq!(database.scrape_progress()?.pending, 1);
+        assert_eq!(database.next_scrape_job()?.unwrap().path, games[0]);
+
+        // Asking again while it's searching doesn't queue it twice
+        assert!(!art.search()?);
+        assert_eq!(database.scrape_progress()?.total(), 1);
+
+        // Art is looked for again once it's saved
+        art.update(POLL_INTERVAL);
+        assert_eq!(art.take_found(), None);
+        database.complete_scrape_job(&games[0])?;
+        art.update(POLL_INTERVAL);
+        assert_eq!(art.take_found(), Some(games[0].clone()));
+        assert_eq!(art.take_found(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_needs_thumbnails() -> Result<()> {
+        let (mut art, database) = missing_art();
+        art.set_game(&Game::new(PathBuf::from("Roms/Unknown/Game.xyz")));
+        assert!(!art.offers_search());
+        assert!(!art.search()?);
+        assert_eq!(database.scrape_progress()?.total(), 0);
+        Ok(())
+    }
+}
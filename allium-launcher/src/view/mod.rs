@@ -1,14 +1,31 @@
 mod app;
 mod apps;
+mod batch;
+mod box_art_scrape;
+mod continue_game;
 mod entry_list;
-mod games;
-mod recents;
+mod favorites;
+mod game_options;
+pub mod games;
+mod launch_failure;
+mod legacy_migration;
+mod missing_art;
+mod profile_chooser;
+pub mod recents;
 mod settings;
-mod toast;
+mod setup;
+mod suspended_game;
+mod theme_confirm;
 
 pub use app::App;
 pub use apps::Apps;
+pub use box_art_scrape::BoxArtScrape;
 pub use games::Games;
+pub use launch_failure::LaunchFailureDialog;
+pub use legacy_migration::LegacyMigration;
+pub use profile_chooser::ProfileChooser;
 pub use recents::Recents;
 pub use settings::Settings;
-pub use toast::Toast;
+pub use setup::SetupWizard;
+pub use suspended_game::SuspendedGame;
+pub use theme_confirm::ThemeConfirm;
@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::{Command, Value};
+use common::display::Display;
+use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::profile::Profiles;
+use common::resources::Resources;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{Keyboard, Label, ScrollList, View};
+use tokio::sync::mpsc::Sender;
+
+/// Lets the user pick which profile to use before the launcher starts. Leaving a restricted
+/// profile for an unrestricted one asks for the PIN.
+#[derive(Debug)]
+pub struct ProfileChooser {
+    rect: Rect,
+    res: Resources,
+    profiles: Profiles,
+    /// Profile that was used last.
+    last: Option<String>,
+    title: Label<String>,
+    list: ScrollList,
+    /// PIN entry for the selected profile.
+    keyboard: Option<Keyboard>,
+    dirty: bool,
+}
+
+impl ProfileChooser {
+    pub fn new(rect: Rect, res: Resources, profiles: Profiles, last: Option<String>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let styles = res.get::<Styles>();
+
+        let mut title = Label::new(
+            Point::new(x + w as i32 / 2, y + 8),
+            res.get::<Locale>().t("profile-chooser-title"),
+            Alignment::Center,
+            None,
+        );
+        title.color(StylesheetColor::Highlight);
+
+        let list_y = y + 8 + styles.ui_font.size as i32 + 16;
+        let mut list = ScrollList::new(
+            Rect::new(
+                x + 12 + (w as i32 - 24) / 6,
+                list_y,
+                (w - 24) * 2 / 3,
+                h - list_y as u32,
+            ),
+            profiles.profiles.iter().map(|p| p.name.clone()).collect(),
+            Alignment::Center,
+            styles.row_layout(),
+        );
+        list.set_list_settings(*res.get::<ListSettings>());
+
+        drop(styles);
+
+        Self {
+            rect,
+            res,
+            profiles,
+            last,
+            title,
+            list,
+            keyboard: None,
+            dirty: true,
+        }
+    }
+
+    async fn select(&mut self, commands: Sender<Command>) -> Result<()> {
+        let Some(profile) = self.profiles.profiles.get(self.list.selected()) else {
+            return Ok(());
+        };
+        if self.profiles.needs_pin(self.last.as_deref(), profile) {
+            self.keyboard = Some(Keyboard::new(self.res.clone(), String::new(), true));
+        } else {
+            commands
+                .send(Command::SelectProfile(profile.name.clone()))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn enter_pin(&mut self, pin: &str, commands: Sender<Command>) -> Result<()> {
+        let Some(profile) = self.profiles.profiles.get(self.list.selected()) else {
+            return Ok(());
+        };
+        if self.profiles.check_pin(pin) {
+            commands
+                .send(Command::SelectProfile(profile.name.clone()))
+                .await?;
+        } else {
+            commands
+                .send(Command::Toast(
+                    self.res.get::<Locale>().t("profile-chooser-wrong-pin"),
+                    Some(Duration::from_secs(3)),
+                ))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl View for ProfileChooser {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        if self.dirty {
+            display.load(self.rect)?;
+            self.title.set_should_draw();
+            self.list.set_should_draw();
+            self.dirty = false;
+        }
+
+        let mut drawn = false;
+        drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            if drawn {
+                keyboard.set_should_draw();
+            }
+            drawn |= keyboard.should_draw() && keyboard.draw(display, styles)?;
+        }
+
+        Ok(drawn)
+    }
+
+    fn update(&mut self, dt: Duration) {
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            keyboard.update(dt);
+        }
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self.title.should_draw()
+            || self.list.should_draw()
+            || self.keyboard.as_ref().map_or(false, |k| k.should_draw())
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            keyboard.set_should_draw();
+        }
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            if !keyboard
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?
+            {
+                return Ok(true);
+            }
+            let mut pin = None;
+            bubble.retain_mut(|c| match c {
+                Command::ValueChanged(_, Value::String(val)) => {
+                    pin = Some(val.clone());
+                    false
+                }
+                Command::CloseView => {
+                    self.keyboard = None;
+                    self.dirty = true;
+                    false
+                }
+                _ => true,
+            });
+            if let Some(pin) = pin {
+                self.enter_pin(&pin, commands).await?;
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                self.select(commands).await?;
+                Ok(true)
+            }
+            _ => self.list.handle_key_event(event, commands, bubble).await,
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.title, &self.list]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.title, &mut self.list]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
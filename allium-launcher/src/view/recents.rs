@@ -1,4 +1,6 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+use std::rc::Rc;
 use std::time::Duration;
 
 use anyhow::Result;
@@ -6,24 +8,33 @@ use async_trait::async_trait;
 use common::command::{Command, Value};
 use common::constants::RECENT_GAMES_LIMIT;
 use common::database::Database;
+use common::display::Display;
 use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::profile::Profile;
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
-use common::view::{ButtonHint, ButtonIcon, Keyboard, Row, View};
+use common::stylesheet::Styles;
+use common::view::{ButtonHint, ButtonIcon, Keyboard, Row, SuggestionProvider, View};
+use log::warn;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
 use crate::consoles::ConsoleMapper;
 use crate::entry::directory::Directory;
 use crate::entry::game::Game;
-use crate::entry::lazy_image::LazyImage;
-use crate::entry::{Entry, Sort};
+use crate::entry::{names, Entry, Sort};
+use crate::library_filter::LibraryFilter;
+use crate::view::continue_game::{self, ContinueGame};
 use crate::view::entry_list::{EntryList, EntryListState};
+use crate::view::favorites;
 
 pub type RecentsState = EntryListState<RecentsSort>;
 
+/// Where the hint for removing the highlighted game goes, after the favorite hint. The hint for
+/// pinning it follows.
+const REMOVE_HINT_INDEX: usize = 2;
+
 #[derive(Debug)]
 pub struct Recents {
     res: Resources,
@@ -31,13 +42,19 @@ pub struct Recents {
     list: EntryList<RecentsSort>,
     button_hints: Row<ButtonHint<String>>,
     keyboard: Option<Keyboard>,
+    /// Offers the last played game when the launcher starts, until another button is pressed.
+    continue_game: Option<ContinueGame>,
+    /// Whether the continue card was closed, and what it drew needs clearing.
+    clear: bool,
+    /// The list shown before searching, which leaving the search results goes back to.
+    sort_before_search: Option<RecentsSort>,
 }
 
 impl Recents {
     pub fn new(rect: Rect, res: Resources, list: EntryList<RecentsSort>) -> Result<Self> {
         let Rect { x, y, w: _w, h } = rect;
 
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let button_hints = Row::new(
             Point::new(
@@ -48,7 +65,7 @@ impl Recents {
                 let locale = res.get::<Locale>();
                 vec![ButtonHint::new(
                     Point::zero(),
-                    Key::X,
+                    Key::Start,
                     locale.t("sort-search"),
                     Alignment::Left,
                 )]
@@ -59,21 +76,32 @@ impl Recents {
 
         drop(styles);
 
-        Ok(Self {
+        let continue_game = match list.current_sort() {
+            RecentsSort::LastPlayed => continue_game::last_played(&res.get())
+                .map_err(|e| warn!("failed to find last played game: {}", e))
+                .ok()
+                .flatten()
+                .map(|game| ContinueGame::new(rect, res.clone(), game)),
+            _ => None,
+        };
+
+        let mut this = Self {
             res,
             rect,
             list,
             button_hints,
             keyboard: None,
-        })
+            continue_game,
+            clear: false,
+            sort_before_search: None,
+        };
+        this.update_hints()?;
+        Ok(this)
     }
 
     pub fn load_or_new(rect: Rect, res: Resources, state: Option<RecentsState>) -> Result<Self> {
         let list = if let Some(state) = state {
-            let selected = state.selected;
-            let mut list = EntryList::load(rect, res.clone(), state)?;
-            list.select(selected);
-            list
+            EntryList::load(rect, res.clone(), state)?
         } else {
             EntryList::new(rect, res.clone(), RecentsSort::LastPlayed)?
         };
@@ -86,7 +114,16 @@ impl Recents {
     }
 
     pub fn start_search(&mut self) {
-        self.keyboard = Some(Keyboard::new(self.res.clone(), String::new(), false));
+        if self.continue_game.take().is_some() {
+            self.clear = true;
+        }
+        let suggestions = SearchSuggestions {
+            database: self.res.get::<Database>().clone(),
+        };
+        self.keyboard = Some(
+            Keyboard::new(self.res.clone(), String::new(), false)
+                .with_suggestions(Rc::new(suggestions)),
+        );
     }
 
     pub async fn try_search(&mut self, commands: Sender<Command>, query: String) -> Result<()> {
@@ -99,15 +136,112 @@ impl Recents {
                 .await?;
         }
 
+        let trimmed = query.trim();
+        if !trimmed.is_empty() {
+            if let Err(e) = self.res.get::<Database>().add_search_query(trimmed) {
+                warn!("failed to save search query: {}", e);
+            }
+        }
+
         commands.send(Command::Search(query)).await?;
 
         Ok(())
     }
 
     pub fn search(&mut self, query: String) -> Result<()> {
+        if !self.is_searching() {
+            self.sort_before_search = Some(self.list.current_sort().clone());
+        }
         self.list.sort(RecentsSort::Search(query))?;
         Ok(())
     }
+
+    /// Goes back to the list that was shown before searching.
+    fn end_search(&mut self) -> Result<()> {
+        let sort = self
+            .sort_before_search
+            .take()
+            .unwrap_or(RecentsSort::LastPlayed);
+        self.list.sort(sort)
+    }
+
+    fn is_searching(&self) -> bool {
+        matches!(self.list.current_sort(), RecentsSort::Search(_))
+    }
+
+    /// Whether the highlighted game can be taken off the list, which only the recently played lists
+    /// allow.
+    fn can_remove(&self) -> bool {
+        matches!(
+            self.list.current_sort(),
+            RecentsSort::LastPlayed | RecentsSort::MostPlayed
+        ) && self.list.selected_game().is_some()
+            && !self.res.get::<Profile>().restricted
+    }
+
+    /// Whether the highlighted game can be pinned or unpinned, which only the last played list
+    /// shows.
+    fn can_pin(&self) -> bool {
+        matches!(self.list.current_sort(), RecentsSort::LastPlayed) && self.can_remove()
+    }
+
+    /// Shows the favorite, remove and pin hints for the highlighted game. Returns true if they
+    /// changed.
+    fn update_hints(&mut self) -> Result<bool> {
+        // Taken out first, as the favorite hint before them may be taken out too
+        let mut removed = Vec::new();
+        while let Some(hint) = self.button_hints.remove(REMOVE_HINT_INDEX) {
+            removed.push(hint.text().to_string());
+        }
+        let changed =
+            favorites::update_hint(&self.res, &mut self.button_hints, self.list.selected_game())?;
+
+        let mut shown = Vec::new();
+        if self.can_remove() {
+            shown.push((Key::L2, "button-remove-from-recents"));
+        }
+        if self.can_pin() {
+            let pinned = self.list.selected_game().is_some_and(|game| game.pinned);
+            shown.push((Key::R2, if pinned { "button-unpin" } else { "button-pin" }));
+        }
+        let locale = self.res.get::<Locale>();
+        let shown: Vec<_> = shown
+            .into_iter()
+            .map(|(key, text)| (key, locale.t(text)))
+            .collect();
+        drop(locale);
+        let hints_changed = removed.len() != shown.len()
+            || removed.iter().zip(&shown).any(|(old, (_, new))| old != new);
+        for (key, text) in shown {
+            self.button_hints
+                .push(ButtonHint::new(Point::zero(), key, text, Alignment::Left));
+        }
+        Ok(changed || hints_changed)
+    }
+
+    /// Pins the highlighted game to the top of the list, or unpins it.
+    fn toggle_pinned(&mut self) -> Result<()> {
+        let Some(game) = self.list.selected_game() else {
+            return Ok(());
+        };
+        self.res
+            .get::<Database>()
+            .set_pinned(&game.path, !game.pinned)?;
+        self.list.sort(RecentsSort::LastPlayed)
+    }
+
+    /// Marks or unmarks the highlighted game as a favorite. An unmarked game leaves the list if it
+    /// is showing favorites.
+    fn toggle_favorite(&mut self) -> Result<()> {
+        let Some(game) = self.list.selected_game() else {
+            return Ok(());
+        };
+        favorites::toggle(&self.res, game)?;
+        if let RecentsSort::Favorites = self.list.current_sort() {
+            self.list.sort(RecentsSort::Favorites)?;
+        }
+        Ok(())
+    }
 }
 
 #[async_trait(?Send)]
@@ -115,9 +249,19 @@ impl View for Recents {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
+        if let Some(continue_game) = self.continue_game.as_mut() {
+            return Ok(continue_game.should_draw() && continue_game.draw(display, styles)?);
+        }
+
         let mut drawn = false;
+        if self.clear {
+            display.load(self.rect)?;
+            self.clear = false;
+            self.list.set_should_draw();
+            drawn = true;
+        }
 
         if self.list.should_draw() {
             drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
@@ -135,13 +279,30 @@ impl View for Recents {
         Ok(drawn)
     }
 
+    fn update(&mut self, dt: Duration) {
+        if let Some(continue_game) = self.continue_game.as_mut() {
+            continue_game.update(dt);
+        }
+        self.list.update(dt);
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            keyboard.update(dt);
+        }
+    }
+
     fn should_draw(&self) -> bool {
-        self.list.should_draw()
+        if let Some(continue_game) = self.continue_game.as_ref() {
+            return continue_game.should_draw();
+        }
+        self.clear
+            || self.list.should_draw()
             || self.button_hints.should_draw()
             || self.keyboard.as_ref().map_or(false, |k| k.should_draw())
     }
 
     fn set_should_draw(&mut self) {
+        if let Some(continue_game) = self.continue_game.as_mut() {
+            continue_game.set_should_draw();
+        }
         self.list.set_should_draw();
         self.button_hints.set_should_draw();
         if let Some(keyboard) = self.keyboard.as_mut() {
@@ -155,12 +316,29 @@ impl View for Recents {
         commands: Sender<Command>,
         bubble: &mut VecDeque<Command>,
     ) -> Result<bool> {
+        if let Some(continue_game) = self.continue_game.as_mut() {
+            if !continue_game
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?
+            {
+                return Ok(false);
+            }
+            if let Some(i) = bubble.iter().position(|c| matches!(c, Command::CloseView)) {
+                bubble.remove(i);
+                self.continue_game = None;
+                self.clear = true;
+                commands.send(Command::Redraw).await?;
+            }
+            return Ok(true);
+        }
+
         if let Some(keyboard) = self.keyboard.as_mut() {
             if keyboard
                 .handle_key_event(event, commands.clone(), bubble)
                 .await?
             {
                 let mut query = None;
+                let mut closed = false;
                 bubble.retain_mut(|c| match c {
                     Command::ValueChanged(_, val) => {
                         if let Value::String(val) = val {
@@ -170,30 +348,73 @@ impl View for Recents {
                     }
                     Command::CloseView => {
                         self.keyboard = None;
+                        closed = true;
                         false
                     }
                     _ => true,
                 });
                 if let Some(query) = query {
                     self.try_search(commands, query).await?;
+                } else if closed && !self.is_searching() {
+                    // Cancelled before searching, so go back to where the search was started
+                    bubble.push_back(Command::CloseView);
                 }
                 return Ok(true);
             }
         }
 
-        match event {
-            KeyEvent::Pressed(Key::X) => {
-                if self.keyboard.is_none() {
+        if let KeyEvent::Pressed(Key::B) = event {
+            if self.is_searching() {
+                self.end_search()?;
+                // Goes back to where the search was started
+                bubble.push_back(Command::CloseView);
+                return Ok(true);
+            }
+        }
+
+        let handled = match event {
+            KeyEvent::Pressed(Key::X) if self.list.selected_game().is_some() => {
+                self.toggle_favorite()?;
+                true
+            }
+            // Quick filters can still be cycled through in the other lists
+            KeyEvent::Pressed(Key::L2) if self.can_remove() => {
+                self.list.remove_from_recents(commands.clone()).await?;
+                true
+            }
+            KeyEvent::Pressed(Key::R2) if self.can_pin() => {
+                self.toggle_pinned()?;
+                commands.send(Command::Redraw).await?;
+                true
+            }
+            KeyEvent::Pressed(Key::Start) if self.keyboard.is_some() => {
+                self.keyboard = None;
+                self.end_search()?;
+                commands.send(Command::Redraw).await?;
+                true
+            }
+            KeyEvent::Pressed(Key::Start) => {
+                // The highlighted game may offer to search for its art instead
+                if !self
+                    .list
+                    .handle_key_event(event, commands.clone(), bubble)
+                    .await?
+                {
                     self.start_search();
-                } else {
-                    self.keyboard = None;
-                    self.list.sort(RecentsSort::LastPlayed)?;
-                    commands.send(Command::Redraw).await?;
                 }
-                return Ok(true);
+                true
+            }
+            _ => {
+                self.list
+                    .handle_key_event(event, commands.clone(), bubble)
+                    .await?
             }
-            _ => self.list.handle_key_event(event, commands, bubble).await,
+        };
+
+        if self.update_hints()? {
+            commands.send(Command::Redraw).await?;
         }
+        Ok(handled)
     }
 
     fn children(&self) -> Vec<&dyn View> {
@@ -204,7 +425,7 @@ impl View for Recents {
         vec![&mut self.list]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
@@ -213,20 +434,46 @@ impl View for Recents {
     }
 }
 
+/// Suggests recent searches while the query is empty, and names of games once typing starts.
+#[derive(Debug)]
+struct SearchSuggestions {
+    database: Database,
+}
+
+impl SuggestionProvider for SearchSuggestions {
+    fn suggestions(&self, value: &str, limit: usize) -> Vec<String> {
+        let value = value.trim_start();
+        let suggestions = if value.is_empty() {
+            self.database.search_history()
+        } else {
+            self.database.suggest_names(value, limit as i64)
+        };
+        suggestions.unwrap_or_else(|e| {
+            warn!("failed to get search suggestions: {}", e);
+            Vec::new()
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RecentsSort {
     LastPlayed,
     MostPlayed,
     Random,
+    Favorites,
     Search(String),
 }
 
 impl Sort for RecentsSort {
+    const HAS_CLEAR_RECENTS: bool = true;
+    const HAS_PLAY_TIME: bool = true;
+
     fn button_hint(&self, locale: &Locale) -> String {
         match self {
             RecentsSort::LastPlayed => locale.t("sort-last-played"),
             RecentsSort::MostPlayed => locale.t("sort-most-played"),
             RecentsSort::Random => locale.t("sort-random"),
+            RecentsSort::Favorites => locale.t("sort-favorites"),
             RecentsSort::Search(_) => locale.t("sort-search"),
         }
     }
@@ -235,7 +482,8 @@ impl Sort for RecentsSort {
         match self {
             RecentsSort::LastPlayed => RecentsSort::MostPlayed,
             RecentsSort::MostPlayed => RecentsSort::Random,
-            RecentsSort::Random => RecentsSort::LastPlayed,
+            RecentsSort::Random => RecentsSort::Favorites,
+            RecentsSort::Favorites => RecentsSort::LastPlayed,
             RecentsSort::Search(_) => RecentsSort::LastPlayed,
         }
     }
@@ -244,13 +492,30 @@ impl Sort for RecentsSort {
         unimplemented!();
     }
 
-    fn entries(&self, database: &Database, _console_mapper: &ConsoleMapper) -> Result<Vec<Entry>> {
-        let games = match self {
-            RecentsSort::LastPlayed => database.select_last_played(RECENT_GAMES_LIMIT),
-            RecentsSort::MostPlayed => database.select_most_played(RECENT_GAMES_LIMIT),
-            RecentsSort::Random => database.select_random(RECENT_GAMES_LIMIT),
-            RecentsSort::Search(query) => database.search(query, RECENT_GAMES_LIMIT),
-        };
+    fn total(&self, database: &Database) -> Result<Option<usize>> {
+        match self {
+            RecentsSort::Search(_) => database.count_games().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn entries(
+        &self,
+        database: &Database,
+        console_mapper: &ConsoleMapper,
+        filter: &LibraryFilter,
+    ) -> Result<Vec<Entry>> {
+        if let RecentsSort::Favorites = self {
+            return favorite_entries(database, console_mapper, filter);
+        }
+
+        let games = filter.select_games(RECENT_GAMES_LIMIT, |limit| match self {
+            RecentsSort::LastPlayed => database.select_last_played(limit),
+            RecentsSort::MostPlayed => database.select_most_played(limit),
+            RecentsSort::Random => database.select_random(limit),
+            RecentsSort::Favorites => unreachable!(),
+            RecentsSort::Search(query) => database.search(query, limit),
+        });
 
         let games = match games {
             Ok(games) => games,
@@ -260,29 +525,66 @@ impl Sort for RecentsSort {
             }
         };
 
-        Ok(games
+        // Games whose files are gone would only fail to launch
+        let (games, missing): (Vec<_>, Vec<_>) =
+            games.into_iter().partition(|game| game.path.exists());
+        if let RecentsSort::LastPlayed | RecentsSort::MostPlayed = self {
+            for game in missing {
+                if let Err(e) = database.remove_from_recents(&game.path) {
+                    warn!("failed to prune {}: {}", game.path.display(), e);
+                }
+            }
+        }
+
+        let pinned: HashSet<_> = match self {
+            RecentsSort::LastPlayed => database.pinned_games()?.into_iter().collect(),
+            _ => HashSet::new(),
+        };
+        let mut entries: Vec<Entry> = games
             .into_iter()
             .map(|game| {
-                let extension = game
-                    .path
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or_default()
-                    .to_owned();
-
-                let full_name = game.name.clone();
-
-                let image = LazyImage::from_path(&game.path, game.image);
-
-                Entry::Game(Game {
-                    name: game.name,
-                    full_name,
-                    path: game.path,
-                    image,
-                    extension,
-                    core: game.core,
-                })
+                let mut game: Game = game.into();
+                game.pinned = pinned.contains(&game.path);
+                Entry::Game(game)
             })
-            .collect())
+            .collect();
+        names::apply_renames(&mut entries, database)?;
+
+        // Search results come in no particular order
+        if let RecentsSort::Search(_) = self {
+            entries.sort_unstable();
+        }
+
+        Ok(entries)
     }
 }
+
+/// Favorite games, by name. Games whose files are gone are left out, but stay favorites in case
+/// they come back, e.g. on another SD card.
+fn favorite_entries(
+    database: &Database,
+    console_mapper: &ConsoleMapper,
+    filter: &LibraryFilter,
+) -> Result<Vec<Entry>> {
+    let paths: Vec<_> = database
+        .favorites()?
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect();
+    let games = database.select_games(&paths.iter().map(PathBuf::as_path).collect::<Vec<_>>())?;
+
+    let mut entries = Vec::with_capacity(paths.len());
+    for (path, game) in paths.into_iter().zip(games) {
+        match game {
+            Some(game) => entries.push(Entry::Game(game.into())),
+            // Favorited before the games were indexed
+            None => entries
+                .extend(Entry::new(path, console_mapper)?.filter(|e| matches!(e, Entry::Game(_)))),
+        }
+    }
+    filter.retain(&mut entries);
+    names::apply_renames(&mut entries, database)?;
+    entries.sort_unstable();
+
+    Ok(entries)
+}
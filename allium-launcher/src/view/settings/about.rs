@@ -3,18 +3,26 @@ use std::collections::VecDeque;
 use anyhow::Result;
 use async_trait::async_trait;
 use common::command::Command;
-use common::constants::{ALLIUM_VERSION, SELECTION_MARGIN};
+use common::constants::ALLIUM_VERSION;
 use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
 use common::locale::Locale;
+use common::maintenance::MaintenanceReport;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, View};
+use log::warn;
 use sysinfo::{DiskExt, SystemExt};
 use tokio::sync::mpsc::Sender;
 
 use crate::view::settings::{ChildState, SettingsChild};
 
+/// Row that creates a diagnostics bundle when selected.
+const DIAGNOSTICS_ROW: usize = 7;
+/// Row that shows the setup wizard again when selected.
+const SETUP_ROW: usize = 8;
+
 pub struct About {
     rect: Rect,
     list: SettingsList,
@@ -32,7 +40,7 @@ impl About {
         let disk = &sys.disks()[1];
 
         let locale = res.get::<Locale>();
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let mut list = SettingsList::new(
             Rect::new(
@@ -48,6 +56,9 @@ impl About {
                 locale.t("settings-about-operating-system-version"),
                 locale.t("settings-about-kernel-version"),
                 locale.t("settings-about-storage-used"),
+                locale.t("settings-about-last-maintenance"),
+                locale.t("settings-about-diagnostics"),
+                locale.t("settings-about-setup"),
             ],
             vec![
                 Box::new(Label::new(
@@ -89,9 +100,28 @@ impl About {
                     Alignment::Right,
                     None,
                 )),
+                Box::new(Label::new(
+                    Point::zero(),
+                    maintenance_summary(&locale),
+                    Alignment::Right,
+                    None,
+                )),
+                Box::new(Label::new(
+                    Point::zero(),
+                    String::new(),
+                    Alignment::Right,
+                    None,
+                )),
+                Box::new(Label::new(
+                    Point::zero(),
+                    String::new(),
+                    Alignment::Right,
+                    None,
+                )),
             ],
-            styles.ui_font.size + SELECTION_MARGIN,
+            styles.row_layout(),
         );
+        list.set_list_settings(*res.get::<ListSettings>());
         if let Some(state) = state {
             list.select(state.selected);
         }
@@ -119,12 +149,42 @@ impl About {
     }
 }
 
+fn maintenance_summary(locale: &Locale) -> String {
+    let report = match MaintenanceReport::load() {
+        Ok(Some(report)) => report,
+        Ok(None) => return locale.t("settings-about-maintenance-never"),
+        Err(e) => {
+            warn!("failed to load maintenance report: {}", e);
+            return locale.t("settings-about-unknown-value");
+        }
+    };
+
+    let key = if report.aborted.is_some() {
+        "settings-about-maintenance-aborted"
+    } else {
+        "settings-about-maintenance-completed"
+    };
+    locale.ta(
+        key,
+        &[
+            (
+                "date".to_string(),
+                report.started.format("%Y-%m-%d %H:%M").to_string().into(),
+            ),
+            ("completed".to_string(), report.completed().into()),
+            ("total".to_string(), report.tasks.len().into()),
+        ]
+        .into_iter()
+        .collect(),
+    )
+}
+
 #[async_trait(?Send)]
 impl View for About {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -155,6 +215,14 @@ impl View for About {
         bubble: &mut VecDeque<Command>,
     ) -> Result<bool> {
         match event {
+            KeyEvent::Pressed(Key::A) if self.list.selected() == DIAGNOSTICS_ROW => {
+                commands.send(Command::RunDiagnostics).await?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::A) if self.list.selected() == SETUP_ROW => {
+                commands.send(Command::StartSetup).await?;
+                Ok(true)
+            }
             KeyEvent::Pressed(Key::B) => {
                 bubble.push_back(Command::CloseView);
                 Ok(true)
@@ -171,7 +239,7 @@ impl View for About {
         vec![&mut self.list, &mut self.button_hints]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
@@ -0,0 +1,338 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Utc};
+use common::command::Command;
+use common::database::{Database, Game};
+use common::display::Display as DisplayTrait;
+use common::format::format_play_time;
+use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::Styles;
+use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, View};
+use log::error;
+use tokio::sync::mpsc::Sender;
+
+use crate::view::settings::{ChildState, SettingsChild};
+
+/// How many games are listed below the totals.
+const TOP_GAMES_LIMIT: i64 = 10;
+/// How far back recent play time goes.
+const RECENT_DAYS: i64 = 7;
+
+/// What the games listed below the totals are ranked by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ranking {
+    PlayTime,
+    Launches,
+}
+
+impl Ranking {
+    fn button_hint(self, locale: &Locale) -> String {
+        match self {
+            Ranking::PlayTime => locale.t("settings-activity-by-play-time"),
+            Ranking::Launches => locale.t("settings-activity-by-launches"),
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Ranking::PlayTime => Ranking::Launches,
+            Ranking::Launches => Ranking::PlayTime,
+        }
+    }
+}
+
+/// Play statistics of the current profile.
+#[derive(Debug, Clone, Default)]
+struct Stats {
+    total: chrono::Duration,
+    recent: chrono::Duration,
+    first_played: Option<DateTime<Utc>>,
+    most_played: Vec<Game>,
+    most_launched: Vec<Game>,
+}
+
+impl Stats {
+    fn load(database: &Database) -> Result<Self> {
+        Ok(Self {
+            total: database.total_play_time()?,
+            recent: database.play_time_since(Utc::now() - chrono::Duration::days(RECENT_DAYS))?,
+            first_played: database.first_played()?,
+            most_played: database.select_most_played(TOP_GAMES_LIMIT)?,
+            most_launched: database.select_most_launched(TOP_GAMES_LIMIT)?,
+        })
+    }
+}
+
+/// How much has been played overall, lately, and which games the most. The statistics are read
+/// on a blocking task when the screen is opened, so that a large library doesn't hold up drawing.
+pub struct Activity {
+    rect: Rect,
+    res: Resources,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+    ranking: Ranking,
+    stats: Option<Stats>,
+    pending: Option<Receiver<Result<Stats>>>,
+    /// Row to select once the games are listed, when coming back to the screen.
+    restore: Option<usize>,
+    dirty: bool,
+}
+
+impl Activity {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            Vec::new(),
+            Vec::new(),
+            styles.row_layout(),
+        );
+        list.set_list_settings(*res.get::<ListSettings>());
+
+        let ranking = Ranking::PlayTime;
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::Y,
+                    ranking.button_hint(&locale),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        let mut this = Self {
+            rect,
+            res,
+            list,
+            button_hints,
+            ranking,
+            stats: None,
+            pending: None,
+            restore: state.map(|state| state.selected),
+            dirty: true,
+        };
+        this.enter();
+        this.set_items();
+        this
+    }
+
+    /// Starts reading the statistics on a blocking task, with a connection of its own.
+    fn enter(&mut self) {
+        let profile = self.res.get::<Database>().profile().to_string();
+        let (tx, rx) = mpsc::channel();
+        tokio::task::spawn_blocking(move || {
+            let stats = Database::new().and_then(|db| Stats::load(&db.with_profile(&profile)));
+            // The screen may have been closed already
+            let _ = tx.send(stats);
+        });
+        self.pending = Some(rx);
+    }
+
+    /// Takes the statistics once they are read. Returns whether they were.
+    fn poll(&mut self) -> bool {
+        let Some(rx) = self.pending.as_ref() else {
+            return false;
+        };
+        let stats = match rx.try_recv() {
+            Ok(Ok(stats)) => stats,
+            Ok(Err(e)) => {
+                error!("failed to load play activity: {}", e);
+                Stats::default()
+            }
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => Stats::default(),
+        };
+        self.pending = None;
+        self.stats = Some(stats);
+        true
+    }
+
+    fn set_items(&mut self) {
+        let locale = self.res.get::<Locale>();
+        let label = |text: String| {
+            Box::new(Label::new(Point::zero(), text, Alignment::Right, None)) as Box<dyn View>
+        };
+        let play_time = |time: chrono::Duration| {
+            let time = format_play_time(time);
+            if time.is_empty() {
+                locale.t("settings-activity-none")
+            } else {
+                time
+            }
+        };
+
+        let mut left = vec![
+            locale.t("settings-activity-total"),
+            locale.ta(
+                "settings-activity-recent",
+                &[("days".to_string(), RECENT_DAYS.into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            locale.t("settings-activity-since"),
+        ];
+        let mut right = Vec::new();
+        match self.stats.as_ref() {
+            None => {
+                for _ in 0..left.len() {
+                    right.push(label(locale.t("settings-activity-loading")));
+                }
+            }
+            Some(stats) => {
+                right.push(label(play_time(stats.total)));
+                right.push(label(play_time(stats.recent)));
+                right.push(label(stats.first_played.map_or_else(
+                    || locale.t("settings-activity-none"),
+                    |date| date.with_timezone(&Local).format("%Y-%m-%d").to_string(),
+                )));
+                let games = match self.ranking {
+                    Ranking::PlayTime => &stats.most_played,
+                    Ranking::Launches => &stats.most_launched,
+                };
+                for game in games {
+                    left.push(game.name.clone());
+                    right.push(label(match self.ranking {
+                        Ranking::PlayTime => play_time(game.play_time),
+                        Ranking::Launches => locale.ta(
+                            "settings-activity-launches",
+                            &[("count".to_string(), game.play_count.into())]
+                                .into_iter()
+                                .collect(),
+                        ),
+                    }));
+                }
+            }
+        }
+        drop(locale);
+
+        let selected = match self.stats {
+            Some(_) => self.restore.take().unwrap_or(self.list.selected()),
+            None => 0,
+        };
+        self.list.set_items(left, right);
+        self.list.select(selected);
+        self.dirty = true;
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Activity {
+    fn update(&mut self, dt: Duration) {
+        if self.poll() {
+            self.set_items();
+        }
+        self.list.update(dt);
+    }
+
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.list.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.dirty = false;
+        }
+
+        if self.list.should_draw() && self.list.draw(display, styles)? {
+            drawn = true;
+        }
+
+        if self.button_hints.should_draw() && self.button_hints.draw(display, styles)? {
+            drawn = true;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty || self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::Y) => {
+                self.ranking = self.ranking.next();
+                let hint = self.ranking.button_hint(&self.res.get::<Locale>());
+                self.button_hints.get_mut(0).unwrap().set_text(hint);
+                self.set_items();
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => self.list.handle_key_event(event, commands, bubble).await,
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for Activity {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
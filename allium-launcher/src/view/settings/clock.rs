@@ -5,14 +5,15 @@ use anyhow::Result;
 use async_trait::async_trait;
 use chrono::Local;
 use common::command::Command;
-use common::constants::{ALLIUM_TIMEZONE, SELECTION_MARGIN};
+use common::constants::ALLIUM_TIMEZONE;
 
 use common::display::Display as DisplayTrait;
 use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::{ButtonHint, ButtonIcon, DateTime, Row, Select, SettingsList, View};
 
 use tokio::fs::File;
@@ -122,7 +123,7 @@ impl Clock {
             .map(|tz| TIMEZONE_VALUES.iter().position(|&s| s == tz).unwrap_or(0))
             .unwrap_or(0);
         let locale = res.get::<Locale>();
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let mut list = SettingsList::new(
             Rect::new(
@@ -148,8 +149,9 @@ impl Clock {
                     Alignment::Right,
                 )),
             ],
-            styles.ui_font.size + SELECTION_MARGIN,
+            styles.row_layout(),
         );
+        list.set_list_settings(*res.get::<ListSettings>());
         if let Some(state) = state {
             list.select(state.selected);
         }
@@ -183,7 +185,7 @@ impl View for Clock {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -275,7 +277,7 @@ impl View for Clock {
         vec![&mut self.list, &mut self.button_hints]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
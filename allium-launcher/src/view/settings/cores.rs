@@ -0,0 +1,228 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::{ALLIUM_CONFIG_CORES, RETROARCH_CORES_DIR};
+use common::display::Display as DisplayTrait;
+use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{ButtonHint, ButtonIcon, Row, ScrollList, View};
+use tokio::sync::mpsc::Sender;
+
+use crate::consoles::ConsoleMapper;
+use crate::view::settings::{ChildState, SettingsChild};
+
+/// Whether the user's `cores.toml` applied and what was wrong with it, then every console with
+/// the folders and extensions it takes and the files it launches with, to find out why a game
+/// launches with the wrong emulator.
+pub struct Cores {
+    rect: Rect,
+    list: ScrollList,
+    button_hints: Row<ButtonHint<String>>,
+    dirty: bool,
+}
+
+impl Cores {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+        let console_mapper = res.get::<ConsoleMapper>();
+
+        let warnings = console_mapper.warnings();
+        let status = if !ALLIUM_CONFIG_CORES.exists() {
+            locale.t("settings-cores-config-missing")
+        } else if warnings.is_empty() {
+            locale.t("settings-cores-config-ok")
+        } else {
+            locale.ta(
+                "settings-cores-config-warnings",
+                &[("count".to_string(), warnings.len().into())]
+                    .into_iter()
+                    .collect(),
+            )
+        };
+
+        let mut items = vec![locale.t("settings-cores-config")];
+        let mut details = vec![Some(status)];
+        let mut errors = vec![false];
+        for warning in warnings {
+            items.push(warning.text(&locale));
+            details.push(None);
+            errors.push(true);
+        }
+
+        for console in console_mapper.consoles() {
+            items.push(console.name.clone());
+            details.push(
+                console_mapper
+                    .is_overridden(console)
+                    .then(|| locale.t("settings-cores-overridden")),
+            );
+            errors.push(false);
+
+            if !console.patterns.is_empty() {
+                items.push(
+                    locale.ta(
+                        "settings-cores-folders",
+                        &[("folders".to_string(), console.patterns.join(", ").into())]
+                            .into_iter()
+                            .collect(),
+                    ),
+                );
+                details.push(None);
+                errors.push(false);
+            }
+            if !console.extensions.is_empty() {
+                items.push(
+                    locale.ta(
+                        "settings-cores-extensions",
+                        &[(
+                            "extensions".to_string(),
+                            console.extensions.join(", ").into(),
+                        )]
+                        .into_iter()
+                        .collect(),
+                    ),
+                );
+                details.push(None);
+                errors.push(false);
+            }
+            for launcher in console.launchers(&RETROARCH_CORES_DIR) {
+                let missing = if console.path.is_some() {
+                    !launcher.exists()
+                } else {
+                    !launcher.is_file()
+                };
+                items.push(launcher.display().to_string());
+                details.push(missing.then(|| locale.t("settings-cores-missing")));
+                errors.push(missing);
+            }
+        }
+
+        let mut list = ScrollList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            items,
+            Alignment::Left,
+            styles.row_layout(),
+        );
+        list.set_list_settings(*res.get::<ListSettings>());
+        for (i, (detail, error)) in details.into_iter().zip(errors).enumerate() {
+            list.set_detail(i, detail);
+            if error {
+                list.set_color(i, Some(StylesheetColor::Error));
+            }
+        }
+        if let Some(state) = state {
+            list.select(state.selected);
+        }
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![ButtonHint::new(
+                Point::zero(),
+                Key::B,
+                locale.t("button-back"),
+                Alignment::Right,
+            )],
+            Alignment::Right,
+            12,
+        );
+
+        Self {
+            rect,
+            list,
+            button_hints,
+            dirty: true,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Cores {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.list.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.dirty = false;
+        }
+
+        if self.list.should_draw() && self.list.draw(display, styles)? {
+            drawn = true;
+        }
+
+        if self.button_hints.should_draw() && self.button_hints.draw(display, styles)? {
+            drawn = true;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty || self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => self.list.handle_key_event(event, commands, bubble).await,
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for Cores {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
@@ -3,16 +3,19 @@ use std::collections::VecDeque;
 use anyhow::Result;
 use async_trait::async_trait;
 use common::command::Command;
-use common::constants::SELECTION_MARGIN;
 
 use common::display::settings::DisplaySettings;
 use common::display::Display as DisplayTrait;
 use common::geom::{Alignment, Point, Rect, Size};
+use common::list_settings::ListSettings;
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
-use common::view::{ButtonHint, ButtonIcon, Label, Percentage, Row, SettingsList, View};
+use common::stylesheet::{Density, StyleConfig, Styles};
+use common::view::{
+    ButtonHint, ButtonIcon, Label, Percentage, Row, Select, SettingsList, Toggle, View,
+};
+use strum::IntoEnumIterator;
 
 use tokio::sync::mpsc::Sender;
 
@@ -33,7 +36,7 @@ impl Display {
         let settings = DisplaySettings::load().unwrap();
 
         let locale = res.get::<Locale>();
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let mut list = SettingsList::new(
             Rect::new(
@@ -51,6 +54,8 @@ impl Display {
                 locale.t("settings-display-red"),
                 locale.t("settings-display-green"),
                 locale.t("settings-display-blue"),
+                locale.t("settings-display-boot-splash"),
+                locale.t("settings-display-density"),
             ],
             vec![
                 Box::new(Label::new(
@@ -97,9 +102,23 @@ impl Display {
                     i32::from(settings.b),
                     Alignment::Right,
                 )),
+                Box::new(Toggle::new(
+                    Point::zero(),
+                    settings.boot_splash,
+                    Alignment::Right,
+                )),
+                Box::new(Select::new(
+                    Point::zero(),
+                    Density::iter()
+                        .position(|d| d == styles.density().unwrap_or(Density::Normal))
+                        .unwrap_or_default(),
+                    Density::iter().map(|d| d.as_str(&locale)).collect(),
+                    Alignment::Right,
+                )),
             ],
-            styles.ui_font.size + SELECTION_MARGIN,
+            styles.row_layout(),
         );
+        list.set_list_settings(*res.get::<ListSettings>());
         if let Some(state) = state {
             list.select(state.selected);
         }
@@ -140,7 +159,7 @@ impl View for Display {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -188,6 +207,16 @@ impl View for Display {
             }
             while let Some(command) = bubble.pop_front() {
                 if let Command::ValueChanged(i, val) = command {
+                    if i == 9 {
+                        // Changing the density lays out every view again
+                        let density = Density::iter().nth(val.as_int().unwrap() as usize).unwrap();
+                        let mut stylesheet = StyleConfig::load()?;
+                        stylesheet.set_density(density);
+                        commands
+                            .send(Command::SaveStylesheet(Box::new(stylesheet)))
+                            .await?;
+                        continue;
+                    }
                     match i {
                         0 => {}
                         1 => self.settings.luminance = val.as_int().unwrap() as u8,
@@ -197,6 +226,7 @@ impl View for Display {
                         5 => self.settings.r = val.as_int().unwrap() as u8,
                         6 => self.settings.g = val.as_int().unwrap() as u8,
                         7 => self.settings.b = val.as_int().unwrap() as u8,
+                        8 => self.settings.boot_splash = val.as_bool().unwrap(),
                         _ => unreachable!("Invalid index"),
                     }
 
@@ -227,7 +257,7 @@ impl View for Display {
         vec![&mut self.list, &mut self.button_hints]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
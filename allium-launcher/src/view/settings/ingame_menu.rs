@@ -0,0 +1,307 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::geom::{Alignment, Point, Rect};
+use common::ingame_menu::{IngameMenuSettings, MenuEntry};
+use common::list_settings::ListSettings;
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::Styles;
+use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, Toggle, View};
+use tokio::sync::mpsc::Sender;
+
+use crate::view::settings::{ChildState, SettingsChild};
+
+pub struct IngameMenu {
+    rect: Rect,
+    res: Resources,
+    entries: Vec<(MenuEntry, bool)>,
+    backup_states: bool,
+    save_on_switch: bool,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+    is_moving: bool,
+}
+
+impl IngameMenu {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let settings = IngameMenuSettings::load().unwrap_or_default();
+        let entries = settings.entries();
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            Vec::new(),
+            Vec::new(),
+            styles.row_layout(),
+        );
+        list.set_list_settings(*res.get::<ListSettings>());
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-edit"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::Y,
+                    locale.t("button-move"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        let mut this = Self {
+            rect,
+            res,
+            entries,
+            backup_states: settings.backup_states,
+            save_on_switch: settings.save_on_switch,
+            list,
+            button_hints,
+            is_moving: false,
+        };
+        this.update_list();
+        if let Some(state) = state {
+            this.list.select(state.selected);
+        }
+        this
+    }
+
+    fn update_list(&mut self) {
+        let locale = self.res.get::<Locale>();
+        let selected = self.list.selected();
+        self.list.set_items(
+            self.entries
+                .iter()
+                .enumerate()
+                .map(|(i, (entry, _))| {
+                    let text = entry.as_str(&locale);
+                    if self.is_moving && i == selected {
+                        format!("[ {} ]", text)
+                    } else {
+                        text
+                    }
+                })
+                .chain([
+                    locale.t("settings-ingame-menu-backup-states"),
+                    locale.t("settings-ingame-menu-save-on-switch"),
+                ])
+                .collect(),
+            self.entries
+                .iter()
+                .map(|(entry, visible)| {
+                    if entry.is_required() {
+                        Box::new(Label::new(
+                            Point::zero(),
+                            String::new(),
+                            Alignment::Right,
+                            None,
+                        )) as Box<dyn View>
+                    } else {
+                        Box::new(Toggle::new(Point::zero(), *visible, Alignment::Right))
+                    }
+                })
+                .chain([
+                    Box::new(Toggle::new(
+                        Point::zero(),
+                        self.backup_states,
+                        Alignment::Right,
+                    )) as Box<dyn View>,
+                    Box::new(Toggle::new(
+                        Point::zero(),
+                        self.save_on_switch,
+                        Alignment::Right,
+                    )),
+                ])
+                .collect(),
+        );
+        self.list.select(selected);
+    }
+
+    /// Moves the selected entry by `offset`, keeping required entries pinned.
+    fn move_selected(&mut self, offset: isize) {
+        let selected = self.list.selected();
+        let target = selected as isize + offset;
+        if target < 0 || target as usize >= self.entries.len() {
+            return;
+        }
+        let target = target as usize;
+        if self.entries[selected].0.is_required() || self.entries[target].0.is_required() {
+            return;
+        }
+        self.entries.swap(selected, target);
+        self.list.select(target);
+        self.update_list();
+    }
+
+    fn set_moving(&mut self, is_moving: bool) {
+        self.is_moving = is_moving;
+        let text = self.res.get::<Locale>().t(if is_moving {
+            "button-confirm"
+        } else {
+            "button-move"
+        });
+        self.button_hints.get_mut(1).unwrap().set_text(text);
+        self.update_list();
+    }
+
+    /// The selected menu entry, or `None` if another setting is selected.
+    fn selected_entry(&self) -> Option<MenuEntry> {
+        self.entries
+            .get(self.list.selected())
+            .map(|(entry, _)| *entry)
+    }
+
+    fn save_settings(&self) -> Result<()> {
+        IngameMenuSettings {
+            backup_states: self.backup_states,
+            save_on_switch: self.save_on_switch,
+            ..IngameMenuSettings::from_entries(&self.entries)
+        }
+        .save()
+    }
+}
+
+#[async_trait(?Send)]
+impl View for IngameMenu {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.list.should_draw() && self.list.draw(display, styles)? {
+            drawn = true;
+        }
+
+        if self.button_hints.should_draw() && self.button_hints.draw(display, styles)? {
+            drawn = true;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self.is_moving {
+            match event {
+                KeyEvent::Pressed(Key::Up) | KeyEvent::Autorepeat(Key::Up) => {
+                    self.move_selected(-1);
+                }
+                KeyEvent::Pressed(Key::Down) | KeyEvent::Autorepeat(Key::Down) => {
+                    self.move_selected(1);
+                }
+                KeyEvent::Pressed(Key::A | Key::B | Key::Y) => {
+                    self.set_moving(false);
+                    self.save_settings()?;
+                }
+                _ => {}
+            }
+            return Ok(true);
+        }
+
+        if self
+            .list
+            .handle_key_event(event, commands.clone(), bubble)
+            .await?
+        {
+            while let Some(command) = bubble.pop_front() {
+                if let Command::ValueChanged(i, val) = command {
+                    if let Some(value) = val.as_bool() {
+                        let count = self.entries.len();
+                        match self.entries.get_mut(i) {
+                            Some((_, visible)) => *visible = value,
+                            None if i == count => self.backup_states = value,
+                            None => self.save_on_switch = value,
+                        }
+                        self.save_settings()?;
+                    }
+                }
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::Y) => {
+                if self.selected_entry().is_some_and(|e| !e.is_required()) {
+                    self.set_moving(true);
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for IngameMenu {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
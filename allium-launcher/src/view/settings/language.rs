@@ -3,13 +3,13 @@ use std::collections::VecDeque;
 use anyhow::Result;
 use async_trait::async_trait;
 use common::command::Command;
-use common::constants::SELECTION_MARGIN;
 
 use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
 use common::locale::{Locale, LocaleSettings};
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::{ButtonHint, ButtonIcon, Row, Select, SettingsList, View};
 
 use tokio::sync::mpsc::Sender;
@@ -35,7 +35,7 @@ impl Language {
         let langs = locale.languages();
         let lang = langs.iter().position(|l| l == &settings.lang).unwrap();
 
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let mut list = SettingsList::new(
             Rect::new(
@@ -54,8 +54,9 @@ impl Language {
                     .collect(),
                 Alignment::Right,
             ))],
-            styles.ui_font.size + SELECTION_MARGIN,
+            styles.row_layout(),
         );
+        list.set_list_settings(*res.get::<ListSettings>());
         if let Some(state) = state {
             list.select(state.selected);
         }
@@ -99,7 +100,7 @@ impl View for Language {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -170,7 +171,7 @@ impl View for Language {
         vec![&mut self.list, &mut self.button_hints]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
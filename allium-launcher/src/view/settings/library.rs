@@ -0,0 +1,802 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::{ALLIUM_LIBRARY_REPORT, ALLIUM_SD_ROOT};
+use common::database::{Database, ScrapeProgress};
+use common::display::Display as DisplayTrait;
+use common::filename_rules::FilenameRules;
+use common::geom::{Alignment, Point, Rect};
+use common::library_export::export_library_to_file;
+use common::list_settings::ListSettings;
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::play_time_import::{self, ImportPlan};
+use common::profile::Profile;
+use common::resources::Resources;
+use common::sort_order::TieBreak;
+use common::stylesheet::Styles;
+use common::view::{
+    ButtonHint, ButtonIcon, ConfirmDialog, Label, ProgressBar, Row, Select, SettingsList, Toggle,
+    View,
+};
+use log::{error, info};
+use tokio::sync::mpsc::Sender;
+
+use crate::consoles::ConsoleMapper;
+use crate::entry::art_index;
+use crate::entry::game_index::{self, IndexProgress};
+use crate::library_filter::LibraryFilter;
+use crate::library_report::{LibraryReport, ReportText};
+use crate::scraper;
+use crate::view::batch::format_size;
+use crate::view::settings::play_time_import::PlayTimeImport;
+use crate::view::settings::{ChildState, SettingsChild};
+
+/// How often the scrape progress is refreshed while the page is open.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of failed items listed at once.
+const FAILURES_SHOWN: usize = 5;
+
+const TIE_BREAKS: [TieBreak; 2] = [TieBreak::Name, TieBreak::RecentlyAdded];
+
+pub struct Library {
+    rect: Rect,
+    res: Resources,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+    progress: ScrapeProgress,
+    since_progress: Duration,
+    rules: FilenameRules,
+    lists: ListSettings,
+    /// Library report being written, one system per frame, and where to say when it's done.
+    report: Option<(LibraryReport<BufWriter<File>>, Sender<Command>)>,
+    /// Where to say when the library has been rescanned, while it's being rescanned.
+    rescan: Option<Sender<Command>>,
+    /// Preview of importing play time from another firmware, shown over the list.
+    import: Option<PlayTimeImport>,
+    /// Confirmation before clearing the recently played list, shown over the list.
+    confirm_clear: Option<ConfirmDialog>,
+    dirty: bool,
+}
+
+impl Library {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let rules = FilenameRules::load().unwrap_or_else(|e| {
+            error!("failed to load filename rules: {}", e);
+            FilenameRules::default()
+        });
+        let lists = *res.get::<ListSettings>();
+
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            vec![
+                locale.t("settings-library-export"),
+                locale.t("settings-library-scrape"),
+                locale.t("settings-library-scrape-failures"),
+                locale.t("settings-library-scrape-retry"),
+                locale.t("settings-library-scrape-clear"),
+                locale.t("settings-library-orphaned-art"),
+                locale.t("settings-library-natural-order"),
+                locale.t("settings-library-tie-break"),
+                locale.t("settings-library-report"),
+                locale.t("settings-library-show-hidden"),
+                locale.t("settings-library-import-play-time"),
+                locale.t("settings-library-wrap-pages"),
+                locale.t("settings-library-rescan"),
+                locale.t("settings-library-clear-recents"),
+            ],
+            (0..6)
+                .map(|_| {
+                    Box::new(Label::new(
+                        Point::zero(),
+                        String::new(),
+                        Alignment::Right,
+                        None,
+                    )) as Box<dyn View>
+                })
+                .chain([
+                    Box::new(Toggle::new(
+                        Point::zero(),
+                        rules.natural_order,
+                        Alignment::Right,
+                    )) as Box<dyn View>,
+                    Box::new(Select::new(
+                        Point::zero(),
+                        TIE_BREAKS
+                            .iter()
+                            .position(|t| *t == rules.tie_break)
+                            .unwrap_or_default(),
+                        vec![
+                            locale.t("settings-library-tie-break-name"),
+                            locale.t("settings-library-tie-break-recently-added"),
+                        ],
+                        Alignment::Right,
+                    )),
+                    Box::new(Label::new(
+                        Point::zero(),
+                        String::new(),
+                        Alignment::Right,
+                        None,
+                    )),
+                    Box::new(Toggle::new(
+                        Point::zero(),
+                        lists.show_hidden,
+                        Alignment::Right,
+                    )),
+                    Box::new(Label::new(
+                        Point::zero(),
+                        String::new(),
+                        Alignment::Right,
+                        None,
+                    )),
+                    Box::new(Toggle::new(
+                        Point::zero(),
+                        lists.wrap_pages,
+                        Alignment::Right,
+                    )),
+                    Box::new(Label::new(
+                        Point::zero(),
+                        String::new(),
+                        Alignment::Right,
+                        None,
+                    )),
+                    Box::new(Label::new(
+                        Point::zero(),
+                        String::new(),
+                        Alignment::Right,
+                        None,
+                    )),
+                ])
+                .collect(),
+            styles.row_layout(),
+        );
+        if let Some(state) = state {
+            list.select(state.selected);
+        }
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-select"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        list.set_list_settings(lists);
+
+        drop(locale);
+        drop(styles);
+
+        let mut this = Self {
+            rect,
+            res,
+            list,
+            button_hints,
+            progress: ScrapeProgress::default(),
+            since_progress: Duration::ZERO,
+            rules,
+            lists,
+            report: None,
+            rescan: None,
+            import: None,
+            confirm_clear: None,
+            dirty: false,
+        };
+        this.update_progress();
+        this
+    }
+
+    fn update_progress(&mut self) {
+        let progress = match self.res.get::<Database>().scrape_progress() {
+            Ok(progress) => progress,
+            Err(e) => {
+                error!("failed to load scrape progress: {}", e);
+                return;
+            }
+        };
+        self.progress = progress;
+
+        let scraped = if progress.total() > 0 {
+            format!(
+                "{}/{}",
+                progress.completed + progress.failed,
+                progress.total()
+            )
+        } else {
+            String::new()
+        };
+        self.list.set_right(
+            1,
+            Box::new(Label::new(Point::zero(), scraped, Alignment::Right, None)),
+        );
+        self.list.set_right(
+            2,
+            Box::new(Label::new(
+                Point::zero(),
+                progress.failed.to_string(),
+                Alignment::Right,
+                None,
+            )),
+        );
+    }
+
+    async fn scrape(&mut self, commands: Sender<Command>) -> Result<()> {
+        let count = {
+            let database = self.res.get::<Database>();
+            scraper::enqueue_missing_art(
+                &database,
+                &self.res.get::<ConsoleMapper>(),
+                &LibraryFilter::new(&database, &self.res.get::<Profile>())?,
+            )?
+        };
+        scraper::spawn_worker();
+        self.update_progress();
+
+        let toast = self.res.get::<Locale>().ta(
+            "settings-library-scrape-queued",
+            &[("count".to_string(), count.into())].into_iter().collect(),
+        );
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn show_failures(&self, commands: Sender<Command>) -> Result<()> {
+        let failed = self.res.get::<Database>().failed_scrape_jobs()?;
+
+        let toast = {
+            let locale = self.res.get::<Locale>();
+            if failed.is_empty() {
+                locale.t("settings-library-scrape-no-failures")
+            } else {
+                let mut lines: Vec<String> = failed
+                    .iter()
+                    .take(FAILURES_SHOWN)
+                    .map(|job| {
+                        format!(
+                            "{}: {}",
+                            job.path.file_stem().unwrap_or_default().to_string_lossy(),
+                            job.error.as_deref().unwrap_or_default()
+                        )
+                    })
+                    .collect();
+                if failed.len() > FAILURES_SHOWN {
+                    lines.push(
+                        locale.ta(
+                            "settings-library-scrape-more-failures",
+                            &[("count".to_string(), (failed.len() - FAILURES_SHOWN).into())]
+                                .into_iter()
+                                .collect(),
+                        ),
+                    );
+                }
+                lines.join("\n")
+            }
+        };
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(5))))
+            .await?;
+
+        Ok(())
+    }
+
+    fn retry_failed(&mut self) -> Result<()> {
+        self.res.get::<Database>().retry_failed_scrape_jobs()?;
+        scraper::spawn_worker();
+        self.update_progress();
+        Ok(())
+    }
+
+    async fn clear_queue(&mut self, commands: Sender<Command>) -> Result<()> {
+        self.res.get::<Database>().clear_scrape_queue()?;
+        self.update_progress();
+
+        let toast = self
+            .res
+            .get::<Locale>()
+            .t("settings-library-scrape-cleared");
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Reports box art that no game in the library uses.
+    async fn count_orphaned_art(&mut self, commands: Sender<Command>) -> Result<()> {
+        let toast = {
+            let index = art_index::read();
+            let locale = self.res.get::<Locale>();
+            if index.covers(&self.res.get::<Profile>().games_dir()) {
+                let games = self.res.get::<Database>().select_all_games()?;
+                let count = index
+                    .orphans(games.iter().map(|game| game.path.as_path()))
+                    .len();
+                self.list.set_right(
+                    5,
+                    Box::new(Label::new(
+                        Point::zero(),
+                        count.to_string(),
+                        Alignment::Right,
+                        None,
+                    )),
+                );
+                locale.ta(
+                    "settings-library-orphaned-art-count",
+                    &[("count".to_string(), count.into())].into_iter().collect(),
+                )
+            } else {
+                locale.t("settings-library-orphaned-art-indexing")
+            }
+        };
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn export(&mut self, commands: Sender<Command>) -> Result<()> {
+        let result = export_library_to_file(
+            &self.res.get::<Database>(),
+            &self.res.get::<Profile>().games_dir(),
+        );
+
+        let toast = {
+            let locale = self.res.get::<Locale>();
+            match result {
+                Ok(path) => locale.ta(
+                    "settings-library-export-done",
+                    &[("path".to_string(), path.display().to_string().into())]
+                        .into_iter()
+                        .collect(),
+                ),
+                Err(e) => {
+                    error!("failed to export library: {}", e);
+                    locale.t("settings-library-export-failed")
+                }
+            }
+        };
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Looks for play time recorded by other firmware, and previews importing it.
+    async fn preview_import(&mut self, commands: Sender<Command>) -> Result<()> {
+        let Some(source) = play_time_import::detect(&ALLIUM_SD_ROOT).into_iter().next() else {
+            let toast = self.res.get::<Locale>().t("settings-library-import-none");
+            commands
+                .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+                .await?;
+            return Ok(());
+        };
+
+        let plan = ImportPlan::new(&self.res.get::<Database>(), source.as_ref());
+        match plan {
+            Ok(plan) => {
+                self.import = Some(PlayTimeImport::new(
+                    self.rect,
+                    self.res.clone(),
+                    source.name(),
+                    plan,
+                ));
+            }
+            Err(e) => {
+                error!("failed to read play time from {}: {}", source.name(), e);
+                let toast = self.res.get::<Locale>().t("settings-library-import-failed");
+                commands
+                    .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn start_report(&mut self, commands: Sender<Command>) -> Result<()> {
+        if self.report.is_some() {
+            return Ok(());
+        }
+        let result = {
+            let database = self.res.get::<Database>();
+            let profile = self.res.get::<Profile>();
+            let locale = self.res.get::<Locale>();
+            let text = ReportText {
+                title: locale.t("library-report-title"),
+                completed: locale.t("library-report-completed"),
+                favorite: locale.t("library-report-favorite"),
+                other: locale.t("library-report-other"),
+            };
+            LibraryFilter::new(&database, &profile).and_then(|filter| {
+                LibraryReport::to_file(&database, filter, &profile.games_dir(), text)
+            })
+        };
+        let report = match result {
+            Ok(report) => report,
+            Err(e) => {
+                error!("failed to start library report: {}", e);
+                let toast = self.res.get::<Locale>().t("settings-library-report-failed");
+                commands
+                    .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        info!("writing library report of {} games", report.total());
+        let toast = self.res.get::<Locale>().ta(
+            "settings-library-report-started",
+            &[(
+                "size".to_string(),
+                format_size(report.estimated_size()).into(),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+            .await?;
+        self.list.set_right(
+            8,
+            Box::new(ProgressBar::new(Point::zero(), 0.0, Alignment::Right)),
+        );
+        self.report = Some((report, commands));
+        Ok(())
+    }
+
+    /// Reads every folder in the games folder into the game index again, in the background.
+    async fn rescan(&mut self, commands: Sender<Command>) -> Result<()> {
+        if self.rescan.is_some() {
+            return Ok(());
+        }
+        info!("rescanning library");
+        game_index::spawn_indexer(true);
+        let toast = self
+            .res
+            .get::<Locale>()
+            .t("settings-library-rescan-started");
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+            .await?;
+        self.list.set_right(
+            12,
+            Box::new(ProgressBar::new(Point::zero(), 0.0, Alignment::Right)),
+        );
+        self.rescan = Some(commands);
+        Ok(())
+    }
+
+    /// Shows how far the rescan has got, and says when it's done.
+    fn poll_rescan(&mut self) {
+        if self.rescan.is_none() {
+            return;
+        }
+        let IndexProgress {
+            running,
+            done,
+            found,
+        } = game_index::progress();
+        if running {
+            let progress = done as f32 / found.max(1) as f32;
+            self.list.set_right(
+                12,
+                Box::new(ProgressBar::new(Point::zero(), progress, Alignment::Right)),
+            );
+            return;
+        }
+
+        let commands = self.rescan.take().unwrap();
+        self.list.set_right(
+            12,
+            Box::new(Label::new(
+                Point::zero(),
+                String::new(),
+                Alignment::Right,
+                None,
+            )),
+        );
+        let toast = self.res.get::<Locale>().ta(
+            "settings-library-rescan-done",
+            &[("count".to_string(), done.into())].into_iter().collect(),
+        );
+        if let Err(e) = commands.try_send(Command::Toast(toast, Some(Duration::from_secs(3)))) {
+            error!("failed to show rescan toast: {}", e);
+        }
+    }
+
+    /// Writes the next system of the library report, and says when it's done.
+    fn step_report(&mut self) {
+        let Some((report, _)) = self.report.as_mut() else {
+            return;
+        };
+        let result = report.step(&self.res.get::<Database>());
+        let progress = match report.total() {
+            0 => 1.0,
+            total => report.written() as f32 / total as f32,
+        };
+
+        let toast = match result {
+            Ok(false) => {
+                self.list.set_right(
+                    8,
+                    Box::new(ProgressBar::new(Point::zero(), progress, Alignment::Right)),
+                );
+                return;
+            }
+            Ok(true) => self.res.get::<Locale>().ta(
+                "settings-library-report-done",
+                &[(
+                    "path".to_string(),
+                    ALLIUM_LIBRARY_REPORT.display().to_string().into(),
+                )]
+                .into_iter()
+                .collect(),
+            ),
+            Err(e) => {
+                error!("failed to write library report: {}", e);
+                self.res.get::<Locale>().t("settings-library-report-failed")
+            }
+        };
+        let (_, commands) = self.report.take().unwrap();
+        self.list.set_right(
+            8,
+            Box::new(Label::new(
+                Point::zero(),
+                String::new(),
+                Alignment::Right,
+                None,
+            )),
+        );
+        if let Err(e) = commands.try_send(Command::Toast(toast, Some(Duration::from_secs(5)))) {
+            error!("failed to show library report toast: {}", e);
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Library {
+    fn update(&mut self, dt: Duration) {
+        self.step_report();
+        self.poll_rescan();
+
+        self.since_progress += dt;
+        if self.since_progress >= PROGRESS_INTERVAL {
+            self.since_progress = Duration::ZERO;
+            if self.progress.pending > 0 {
+                self.update_progress();
+            }
+        }
+    }
+
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        if let Some(import) = self.import.as_mut() {
+            return import.draw(display, styles);
+        }
+        if let Some(confirm) = self.confirm_clear.as_mut() {
+            return confirm.draw(display, styles);
+        }
+
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.list.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.dirty = false;
+        }
+
+        if self.list.should_draw() && self.list.draw(display, styles)? {
+            drawn = true;
+        }
+
+        if self.button_hints.should_draw() && self.button_hints.draw(display, styles)? {
+            drawn = true;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        if let Some(import) = self.import.as_ref() {
+            return import.should_draw();
+        }
+        if let Some(confirm) = self.confirm_clear.as_ref() {
+            return confirm.should_draw();
+        }
+        self.dirty || self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        if let Some(import) = self.import.as_mut() {
+            import.set_should_draw();
+            return;
+        }
+        if let Some(confirm) = self.confirm_clear.as_mut() {
+            confirm.set_should_draw();
+            return;
+        }
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if let Some(import) = self.import.as_mut() {
+            let consumed = import.handle_key_event(event, commands, bubble).await?;
+            bubble.retain(|command| match command {
+                Command::CloseView => {
+                    self.import = None;
+                    self.dirty = true;
+                    false
+                }
+                _ => true,
+            });
+            return Ok(consumed);
+        }
+
+        if let Some(confirm) = self.confirm_clear.as_mut() {
+            let consumed = confirm
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?;
+            let mut confirmed = false;
+            bubble.retain(|command| match command {
+                Command::ValueChanged(_, _) => {
+                    confirmed = true;
+                    false
+                }
+                Command::CloseView => {
+                    self.confirm_clear = None;
+                    self.dirty = true;
+                    false
+                }
+                _ => true,
+            });
+            if confirmed {
+                commands.send(Command::ClearRecents).await?;
+            }
+            return Ok(consumed);
+        }
+
+        if self
+            .list
+            .handle_key_event(event, commands.clone(), bubble)
+            .await?
+        {
+            while let Some(command) = bubble.pop_front() {
+                if let Command::ValueChanged(i, val) = command {
+                    match i {
+                        6 => self.rules.natural_order = val.as_bool().unwrap(),
+                        7 => self.rules.tie_break = TIE_BREAKS[val.as_int().unwrap() as usize],
+                        9 => self.lists.show_hidden = val.as_bool().unwrap(),
+                        11 => self.lists.wrap_pages = val.as_bool().unwrap(),
+                        _ => continue,
+                    }
+                    let command = if matches!(i, 6 | 7) {
+                        Command::SaveFilenameRules(self.rules)
+                    } else {
+                        Command::SaveListSettings(self.lists)
+                    };
+                    commands.send(command).await?;
+                }
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                match self.list.selected() {
+                    0 => self.export(commands).await?,
+                    1 => self.scrape(commands).await?,
+                    2 => self.show_failures(commands).await?,
+                    3 => self.retry_failed()?,
+                    4 => self.clear_queue(commands).await?,
+                    5 => self.count_orphaned_art(commands).await?,
+                    6 => {}
+                    8 => self.start_report(commands).await?,
+                    9 => {}
+                    10 => self.preview_import(commands).await?,
+                    11 => {}
+                    12 => self.rescan(commands).await?,
+                    13 => {
+                        let title = self
+                            .res
+                            .get::<Locale>()
+                            .t("settings-library-clear-recents-confirm");
+                        self.confirm_clear = Some(ConfirmDialog::new(
+                            self.rect,
+                            self.res.clone(),
+                            title,
+                            vec![],
+                        ));
+                    }
+                    _ => unreachable!("Invalid index"),
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        if let Some(import) = self.import.as_ref() {
+            return vec![import];
+        }
+        if let Some(confirm) = self.confirm_clear.as_ref() {
+            return vec![confirm];
+        }
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        if let Some(import) = self.import.as_mut() {
+            return vec![import];
+        }
+        if let Some(confirm) = self.confirm_clear.as_mut() {
+            return vec![confirm];
+        }
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for Library {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
@@ -1,17 +1,28 @@
 mod about;
+mod activity;
 mod clock;
+mod cores;
 mod display;
+mod ingame_menu;
 mod language;
+mod library;
+mod play_time_import;
 mod theme;
+mod trash;
 mod wifi;
 
-use crate::view::settings::clock::Clock;
+pub use self::clock::Clock;
+pub use self::language::Language;
+pub use self::theme::Theme;
+pub use self::wifi::Wifi;
 
 use self::about::About;
+use self::activity::Activity;
+use self::cores::Cores;
 use self::display::Display;
-use self::language::Language;
-use self::theme::Theme;
-use self::wifi::Wifi;
+use self::ingame_menu::IngameMenu;
+use self::library::Library;
+use self::trash::Trash;
 
 use std::collections::VecDeque;
 use std::fmt::Debug;
@@ -19,29 +30,32 @@ use std::fmt::Debug;
 use anyhow::Result;
 use async_trait::async_trait;
 use common::command::Command;
-use common::constants::SELECTION_MARGIN;
 use common::display::Display as DisplayTrait;
 use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::{ButtonHint, ButtonIcon, Row, ScrollList, View};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct SettingsState {
+    #[serde(default)]
     selected: usize,
+    #[serde(default)]
     child: Option<ChildState>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ChildState {
-    selected: usize,
+    #[serde(default)]
+    pub selected: usize,
 }
 
-trait SettingsChild: View {
+pub trait SettingsChild: View {
     fn save(&self) -> ChildState;
 }
 
@@ -67,10 +81,10 @@ impl Settings {
         let Rect { x, y, w, h } = rect;
 
         let locale = res.get::<Locale>();
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let has_wifi = DefaultPlatform::has_wifi();
-        let mut labels = Vec::with_capacity(7);
+        let mut labels = Vec::with_capacity(11);
         if has_wifi {
             labels.push(locale.t("settings-wifi"));
         }
@@ -78,14 +92,20 @@ impl Settings {
         labels.push(locale.t("settings-display"));
         labels.push(locale.t("settings-theme"));
         labels.push(locale.t("settings-language"));
+        labels.push(locale.t("settings-ingame-menu"));
+        labels.push(locale.t("settings-library"));
+        labels.push(locale.t("settings-cores"));
+        labels.push(locale.t("settings-activity"));
+        labels.push(locale.t("settings-trash"));
         labels.push(locale.t("settings-about"));
 
         let mut list = ScrollList::new(
             Rect::new(x + 12, y + 8, w - 24, h - 8 - styles.ui_font.size - 8),
             labels,
             Alignment::Left,
-            styles.ui_font.size + SELECTION_MARGIN,
+            styles.row_layout(),
         );
+        list.set_list_settings(*res.get::<ListSettings>());
         list.select(state.selected);
 
         let child: Option<Box<dyn SettingsChild>> = if let Some(child) = state.child {
@@ -99,7 +119,12 @@ impl Settings {
                 2 => Some(Box::new(Display::new(rect, res.clone(), Some(child)))),
                 3 => Some(Box::new(Theme::new(rect, res.clone(), Some(child)))),
                 4 => Some(Box::new(Language::new(rect, res.clone(), Some(child)))),
-                5 => Some(Box::new(About::new(rect, res.clone(), Some(child)))),
+                5 => Some(Box::new(IngameMenu::new(rect, res.clone(), Some(child)))),
+                6 => Some(Box::new(Library::new(rect, res.clone(), Some(child)))),
+                7 => Some(Box::new(Cores::new(rect, res.clone(), Some(child)))),
+                8 => Some(Box::new(Activity::new(rect, res.clone(), Some(child)))),
+                9 => Some(Box::new(Trash::new(rect, res.clone(), Some(child)))),
+                10 => Some(Box::new(About::new(rect, res.clone(), Some(child)))),
                 _ => None,
             }
         } else {
@@ -160,7 +185,12 @@ impl Settings {
             2 => self.child = Some(Box::new(Display::new(self.rect, self.res.clone(), None))),
             3 => self.child = Some(Box::new(Theme::new(self.rect, self.res.clone(), None))),
             4 => self.child = Some(Box::new(Language::new(self.rect, self.res.clone(), None))),
-            5 => self.child = Some(Box::new(About::new(self.rect, self.res.clone(), None))),
+            5 => self.child = Some(Box::new(IngameMenu::new(self.rect, self.res.clone(), None))),
+            6 => self.child = Some(Box::new(Library::new(self.rect, self.res.clone(), None))),
+            7 => self.child = Some(Box::new(Cores::new(self.rect, self.res.clone(), None))),
+            8 => self.child = Some(Box::new(Activity::new(self.rect, self.res.clone(), None))),
+            9 => self.child = Some(Box::new(Trash::new(self.rect, self.res.clone(), None))),
+            10 => self.child = Some(Box::new(About::new(self.rect, self.res.clone(), None))),
             _ => unreachable!("Invalid index"),
         }
         self.dirty = true;
@@ -173,7 +203,7 @@ impl View for Settings {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -256,7 +286,7 @@ impl View for Settings {
         }
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::database::Database;
+use common::display::Display as DisplayTrait;
+use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::play_time_import::ImportPlan;
+use common::resources::Resources;
+use common::stylesheet::Styles;
+use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, View};
+use log::{error, info};
+use tokio::sync::mpsc::Sender;
+
+/// Previews importing play time from another firmware, and imports it once confirmed.
+pub struct PlayTimeImport {
+    rect: Rect,
+    res: Resources,
+    plan: ImportPlan,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+    dirty: bool,
+}
+
+impl PlayTimeImport {
+    pub fn new(rect: Rect, res: Resources, name: &str, plan: ImportPlan) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let label = |text: String| {
+            Box::new(Label::new(Point::zero(), text, Alignment::Right, None)) as Box<dyn View>
+        };
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            vec![
+                locale.t("settings-library-import-source"),
+                locale.t("settings-library-import-matched"),
+                locale.t("settings-library-import-ambiguous"),
+                locale.t("settings-library-import-unmatched"),
+                locale.t("settings-library-import-total"),
+            ],
+            vec![
+                label(name.to_string()),
+                label(plan.matched().to_string()),
+                label(plan.ambiguous.to_string()),
+                label(plan.unmatched.to_string()),
+                label(format_hours(&locale, &plan)),
+            ],
+            styles.row_layout(),
+        );
+        list.set_list_settings(*res.get::<ListSettings>());
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("settings-library-import"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            res,
+            plan,
+            list,
+            button_hints,
+            dirty: true,
+        }
+    }
+
+    async fn import(&self, commands: Sender<Command>) -> Result<()> {
+        let toast = {
+            let locale = self.res.get::<Locale>();
+            if self.plan.matched() == 0 {
+                locale.t("settings-library-import-nothing")
+            } else {
+                match self.plan.import(&self.res.get::<Database>()) {
+                    Ok(()) => {
+                        info!(
+                            "imported {} of play time for {} games",
+                            self.plan.play_time(),
+                            self.plan.matched()
+                        );
+                        locale.ta(
+                            "settings-library-import-done",
+                            &[
+                                ("count".to_string(), self.plan.matched().into()),
+                                (
+                                    "hours".to_string(),
+                                    format_hours(&locale, &self.plan).into(),
+                                ),
+                            ]
+                            .into_iter()
+                            .collect(),
+                        )
+                    }
+                    Err(e) => {
+                        error!("failed to import play time: {}", e);
+                        locale.t("settings-library-import-failed")
+                    }
+                }
+            }
+        };
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+            .await?;
+        Ok(())
+    }
+}
+
+/// Play time to import in hours, to a tenth of an hour.
+fn format_hours(locale: &Locale, plan: &ImportPlan) -> String {
+    let hours = plan.play_time().num_minutes() as f64 / 60.0;
+    locale.ta(
+        "settings-library-import-hours",
+        &[("hours".to_string(), format!("{:.1}", hours).into())]
+            .into_iter()
+            .collect(),
+    )
+}
+
+#[async_trait(?Send)]
+impl View for PlayTimeImport {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.list.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.dirty = false;
+        }
+
+        if self.list.should_draw() && self.list.draw(display, styles)? {
+            drawn = true;
+        }
+
+        if self.button_hints.should_draw() && self.button_hints.draw(display, styles)? {
+            drawn = true;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty || self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                self.import(commands).await?;
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => self.list.handle_key_event(event, commands, bubble).await,
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
@@ -3,25 +3,38 @@ use std::path::PathBuf;
 
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{NaiveTime, Timelike};
 use common::command::Command;
-use common::constants::SELECTION_MARGIN;
+use common::display::greyscale::Dithering;
 use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::{Stylesheet, StylesheetFont};
+use common::stylesheet::{StyleConfig, Styles, StylesheetColor, StylesheetFont};
+use common::theme_schedule::{ScheduleMode, ThemePeriod, ThemeSchedule};
 use common::view::{
-    ButtonHint, ButtonIcon, ColorPicker, Number, Row, Select, SettingsList, Toggle, View,
+    ButtonHint, ButtonIcon, ColorPicker, Label, Number, Row, Select, SettingsList, Toggle, View,
 };
 use tokio::sync::mpsc::Sender;
 
 use crate::view::settings::{ChildState, SettingsChild};
 
+const DITHERING: [Dithering; 2] = [Dithering::None, Dithering::Ordered];
+const SCHEDULE_MODES: [ScheduleMode; 3] =
+    [ScheduleMode::Off, ScheduleMode::Times, ScheduleMode::Sunset];
+/// Schedule times can be picked in steps of this many minutes.
+const SCHEDULE_STEP: u32 = 30;
+
 pub struct Theme {
     rect: Rect,
-    stylesheet: Stylesheet,
+    res: Resources,
+    stylesheet: StyleConfig,
+    schedule: ThemeSchedule,
     fonts: Vec<PathBuf>,
     list: SettingsList,
+    /// Says which of the day and night themes is being edited while the schedule is on.
+    scheduled: Label<String>,
     button_hints: Row<ButtonHint<String>>,
 }
 
@@ -29,10 +42,11 @@ impl Theme {
     pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
         let Rect { x, y, w, h } = rect;
 
-        let stylesheet = Stylesheet::load().unwrap();
+        let stylesheet = res.get::<Styles>().config().clone();
+        let schedule = ThemeSchedule::load().unwrap_or_default();
 
         let locale = res.get::<Locale>();
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let fonts = StylesheetFont::available_fonts().unwrap_or_default();
         let font_names: Vec<String> = fonts
@@ -45,6 +59,10 @@ impl Theme {
             })
             .collect();
 
+        let times: Vec<String> = (0..24 * 60 / SCHEDULE_STEP)
+            .map(|i| schedule_time(i as usize).format("%H:%M").to_string())
+            .collect();
+
         let mut list = SettingsList::new(
             Rect::new(
                 x + 12,
@@ -53,7 +71,7 @@ impl Theme {
                 h - 8 - ButtonIcon::diameter(&styles) - 8,
             ),
             vec![
-                locale.t("settings-theme-dark-mode"),
+                dark_mode_label(&locale, &schedule),
                 locale.t("settings-theme-ui-font"),
                 locale.t("settings-theme-ui-font-size"),
                 locale.t("settings-theme-guide-font"),
@@ -66,6 +84,13 @@ impl Theme {
                 locale.t("settings-theme-button-b-color"),
                 locale.t("settings-theme-button-x-color"),
                 locale.t("settings-theme-button-y-color"),
+                locale.t("settings-theme-greyscale"),
+                locale.t("settings-theme-dithering"),
+                locale.t("settings-theme-schedule"),
+                locale.t("settings-theme-light-from"),
+                locale.t("settings-theme-dark-from"),
+                locale.t("settings-theme-latitude"),
+                locale.t("settings-theme-longitude"),
             ],
             vec![
                 Box::new(Toggle::new(
@@ -145,9 +170,66 @@ impl Theme {
                     stylesheet.button_y_color,
                     Alignment::Right,
                 )),
+                Box::new(Toggle::new(
+                    Point::zero(),
+                    stylesheet.greyscale,
+                    Alignment::Right,
+                )),
+                Box::new(Select::new(
+                    Point::zero(),
+                    DITHERING
+                        .iter()
+                        .position(|d| *d == stylesheet.dithering)
+                        .unwrap_or_default(),
+                    vec![
+                        locale.t("settings-theme-dithering-none"),
+                        locale.t("settings-theme-dithering-ordered"),
+                    ],
+                    Alignment::Right,
+                )),
+                Box::new(Select::new(
+                    Point::zero(),
+                    SCHEDULE_MODES
+                        .iter()
+                        .position(|m| *m == schedule.mode)
+                        .unwrap_or_default(),
+                    vec![
+                        locale.t("settings-theme-schedule-off"),
+                        locale.t("settings-theme-schedule-times"),
+                        locale.t("settings-theme-schedule-sunset"),
+                    ],
+                    Alignment::Right,
+                )),
+                Box::new(Select::new(
+                    Point::zero(),
+                    schedule_index(schedule.light_from),
+                    times.clone(),
+                    Alignment::Right,
+                )),
+                Box::new(Select::new(
+                    Point::zero(),
+                    schedule_index(schedule.dark_from),
+                    times,
+                    Alignment::Right,
+                )),
+                Box::new(Number::new(
+                    Point::zero(),
+                    schedule.latitude,
+                    -90,
+                    90,
+                    Alignment::Right,
+                )),
+                Box::new(Number::new(
+                    Point::zero(),
+                    schedule.longitude,
+                    -180,
+                    180,
+                    Alignment::Right,
+                )),
             ],
-            res.get::<Stylesheet>().ui_font.size + SELECTION_MARGIN,
+            res.get::<Styles>().row_layout(),
         );
+        list.set_list_settings(*res.get::<ListSettings>());
         if let Some(state) = state {
             list.select(state.selected);
         }
@@ -175,22 +257,85 @@ impl Theme {
             12,
         );
 
+        let mut scheduled = Label::new(
+            Point::new(
+                rect.x + 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            scheduled_label(&locale, &schedule),
+            Alignment::Left,
+            None,
+        );
+        scheduled.color(StylesheetColor::Highlight);
+
+        drop(locale);
+        drop(styles);
         Self {
             rect,
+            res,
             stylesheet,
+            schedule,
             fonts,
             list,
+            scheduled,
             button_hints,
         }
     }
 }
 
+impl Theme {
+    fn set_schedule(&mut self, i: usize, val: i32) {
+        match i {
+            15 => {
+                self.schedule.mode = SCHEDULE_MODES[val as usize];
+                let label = dark_mode_label(&self.res.get::<Locale>(), &self.schedule);
+                self.list.left_mut(0).set_text(label);
+            }
+            16 => self.schedule.light_from = schedule_time(val as usize),
+            17 => self.schedule.dark_from = schedule_time(val as usize),
+            18 => self.schedule.latitude = val,
+            19 => self.schedule.longitude = val,
+            _ => unreachable!("Invalid index"),
+        }
+        let label = scheduled_label(&self.res.get::<Locale>(), &self.schedule);
+        self.scheduled.set_text(label);
+    }
+}
+
+/// While the schedule is on, the theme being edited is the one of the current period.
+fn scheduled_label(locale: &Locale, schedule: &ThemeSchedule) -> String {
+    match schedule.current() {
+        Some(ThemePeriod::Day) => locale.t("settings-theme-editing-day"),
+        Some(ThemePeriod::Night) => locale.t("settings-theme-editing-night"),
+        None => String::new(),
+    }
+}
+
+/// The dark mode toggle says when the schedule overrides it.
+fn dark_mode_label(locale: &Locale, schedule: &ThemeSchedule) -> String {
+    if schedule.is_enabled() {
+        locale.t("settings-theme-dark-mode-scheduled")
+    } else {
+        locale.t("settings-theme-dark-mode")
+    }
+}
+
+fn schedule_time(i: usize) -> NaiveTime {
+    let minutes = i as u32 * SCHEDULE_STEP;
+    NaiveTime::from_hms_opt(minutes / 60, minutes % 60, 0).unwrap()
+}
+
+/// The schedule time option nearest to `time`, rounding down.
+fn schedule_index(time: NaiveTime) -> usize {
+    ((time.hour() * 60 + time.minute()) / SCHEDULE_STEP) as usize
+}
+
 #[async_trait(?Send)]
 impl View for Theme {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -198,6 +343,10 @@ impl View for Theme {
             drawn = true;
         }
 
+        if self.scheduled.should_draw() && self.scheduled.draw(display, styles)? {
+            drawn = true;
+        }
+
         if self.button_hints.should_draw() && self.button_hints.draw(display, styles)? {
             drawn = true;
         }
@@ -206,11 +355,12 @@ impl View for Theme {
     }
 
     fn should_draw(&self) -> bool {
-        self.list.should_draw() || self.button_hints.should_draw()
+        self.list.should_draw() || self.scheduled.should_draw() || self.button_hints.should_draw()
     }
 
     fn set_should_draw(&mut self) {
         self.list.set_should_draw();
+        self.scheduled.set_should_draw();
         self.button_hints.set_should_draw();
     }
 
@@ -305,6 +455,15 @@ impl View for Theme {
                         10 => self.stylesheet.button_b_color = val.as_color().unwrap(),
                         11 => self.stylesheet.button_x_color = val.as_color().unwrap(),
                         12 => self.stylesheet.button_y_color = val.as_color().unwrap(),
+                        13 => self.stylesheet.greyscale = val.as_bool().unwrap(),
+                        14 => self.stylesheet.dithering = DITHERING[val.as_int().unwrap() as usize],
+                        15..=19 => {
+                            self.set_schedule(i, val.as_int().unwrap());
+                            commands
+                                .send(Command::SaveThemeSchedule(Box::new(self.schedule.clone())))
+                                .await?;
+                            continue;
+                        }
                         _ => unreachable!("Invalid index"),
                     }
 
@@ -326,14 +485,14 @@ impl View for Theme {
     }
 
     fn children(&self) -> Vec<&dyn View> {
-        vec![&self.list, &self.button_hints]
+        vec![&self.list, &self.scheduled, &self.button_hints]
     }
 
     fn children_mut(&mut self) -> Vec<&mut dyn View> {
-        vec![&mut self.list, &mut self.button_hints]
+        vec![&mut self.list, &mut self.scheduled, &mut self.button_hints]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
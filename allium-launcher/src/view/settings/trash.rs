@@ -0,0 +1,290 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use common::command::Command;
+use common::database::Database;
+use common::display::Display as DisplayTrait;
+use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::Styles;
+use common::trash::{self, Trash as TrashDir, UndoEntry};
+use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, View};
+use log::error;
+use tokio::sync::mpsc::Sender;
+
+use crate::view::batch::format_size;
+use crate::view::settings::{ChildState, SettingsChild};
+
+/// Games in the trash, which can be restored or deleted for good.
+pub struct Trash {
+    rect: Rect,
+    res: Resources,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+    entries: Vec<UndoEntry>,
+    dirty: bool,
+}
+
+impl Trash {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            Vec::new(),
+            Vec::new(),
+            styles.row_layout(),
+        );
+        list.set_list_settings(*res.get::<ListSettings>());
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("settings-trash-restore"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::X,
+                    locale.t("settings-trash-purge"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        let mut this = Self {
+            rect,
+            res,
+            list,
+            button_hints,
+            entries: Vec::new(),
+            dirty: true,
+        };
+        this.load_entries();
+        if let Some(state) = state {
+            this.list.select(state.selected);
+        }
+        this
+    }
+
+    /// Lists the current profile's actions that trashed files, newest first.
+    fn load_entries(&mut self) {
+        self.entries = match self.res.get::<Database>().undo_entries() {
+            Ok(entries) => entries
+                .into_iter()
+                .filter(|entry| !entry.files.is_empty())
+                .collect(),
+            Err(e) => {
+                error!("failed to load trash: {}", e);
+                Vec::new()
+            }
+        };
+
+        let locale = self.res.get::<Locale>();
+        let now = Utc::now().timestamp();
+        let (left, right) = if self.entries.is_empty() {
+            (
+                vec![locale.t("settings-trash-empty")],
+                vec![Box::new(Label::new(
+                    Point::zero(),
+                    String::new(),
+                    Alignment::Right,
+                    None,
+                )) as Box<dyn View>],
+            )
+        } else {
+            self.entries
+                .iter()
+                .map(|entry| {
+                    let name = entry
+                        .files
+                        .iter()
+                        .map(|file| {
+                            file.original
+                                .file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy()
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let days = (now - entry.created).max(0) / (24 * 60 * 60);
+                    let age = locale.ta(
+                        "settings-trash-age",
+                        &[
+                            ("days".to_string(), days.into()),
+                            ("size".to_string(), format_size(entry.size()).into()),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    );
+                    (
+                        name,
+                        Box::new(Label::new(Point::zero(), age, Alignment::Right, None))
+                            as Box<dyn View>,
+                    )
+                })
+                .unzip()
+        };
+        drop(locale);
+
+        let selected = self.list.selected();
+        self.list.set_items(left, right);
+        self.list.select(selected);
+        self.dirty = true;
+    }
+
+    async fn restore(&mut self, commands: Sender<Command>) -> Result<()> {
+        let Some(entry) = self.entries.get(self.list.selected()) else {
+            return Ok(());
+        };
+        // The launcher reloads the views, so that restored games are listed again
+        commands.send(Command::Undo(vec![entry.id])).await?;
+        Ok(())
+    }
+
+    async fn purge(&mut self, commands: Sender<Command>) -> Result<()> {
+        let Some(entry) = self.entries.get(self.list.selected()) else {
+            return Ok(());
+        };
+        let result = trash::purge(
+            &self.res.get::<Database>(),
+            &self.res.get::<TrashDir>(),
+            entry,
+        );
+        let toast = match result {
+            Ok(()) => self.res.get::<Locale>().t("settings-trash-purged"),
+            Err(e) => {
+                error!("failed to purge trash: {}", e);
+                self.res.get::<Locale>().t("settings-trash-purge-failed")
+            }
+        };
+        self.load_entries();
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Trash {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.list.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.dirty = false;
+        }
+
+        if self.list.should_draw() && self.list.draw(display, styles)? {
+            drawn = true;
+        }
+
+        if self.button_hints.should_draw() && self.button_hints.draw(display, styles)? {
+            drawn = true;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty || self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self
+            .list
+            .handle_key_event(event, commands.clone(), bubble)
+            .await?
+        {
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                self.restore(commands).await?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::X) => {
+                self.purge(commands).await?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for Trash {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
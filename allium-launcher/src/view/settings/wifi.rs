@@ -4,14 +4,16 @@ use std::time::Duration;
 use anyhow::Result;
 use async_trait::async_trait;
 use common::command::Command;
-use common::constants::SELECTION_MARGIN;
 use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::remote_token;
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, TextBox, Toggle, View};
 use common::wifi::{self, WiFiSettings};
+use log::warn;
 use tokio::sync::mpsc::Sender;
 
 use crate::view::settings::{ChildState, SettingsChild};
@@ -31,9 +33,13 @@ impl Wifi {
         let Rect { x, y, w, h } = rect;
 
         let settings = WiFiSettings::load().unwrap();
+        let token = remote_token::token().unwrap_or_else(|e| {
+            warn!("failed to load remote token: {}", e);
+            String::new()
+        });
 
         let locale = res.get::<Locale>();
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let mut list = SettingsList::new(
             Rect::new(
@@ -50,6 +56,8 @@ impl Wifi {
                 locale.t("settings-wifi-ntp-enabled"),
                 locale.t("settings-wifi-telnet-enabled"),
                 locale.t("settings-wifi-ftp-enabled"),
+                locale.t("settings-wifi-remote-enabled"),
+                locale.t("settings-wifi-remote-token"),
             ],
             vec![
                 Box::new(Toggle::new(Point::zero(), settings.wifi, Alignment::Right)),
@@ -80,9 +88,16 @@ impl Wifi {
                     Alignment::Right,
                 )),
                 Box::new(Toggle::new(Point::zero(), settings.ftp, Alignment::Right)),
+                Box::new(Toggle::new(
+                    Point::zero(),
+                    settings.remote,
+                    Alignment::Right,
+                )),
+                Box::new(Label::new(Point::zero(), token, Alignment::Right, None)),
             ],
-            res.get::<Stylesheet>().ui_font.size + SELECTION_MARGIN,
+            res.get::<Styles>().row_layout(),
         );
+        list.set_list_settings(*res.get::<ListSettings>());
         if let Some(state) = state {
             list.select(state.selected);
         }
@@ -177,7 +192,7 @@ impl View for Wifi {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -229,6 +244,8 @@ impl View for Wifi {
                         4 => self.settings.toggle_ntp(val.as_bool().unwrap())?,
                         5 => self.settings.toggle_telnet(val.as_bool().unwrap())?,
                         6 => self.settings.toggle_ftp(val.as_bool().unwrap())?,
+                        7 => self.settings.toggle_remote(val.as_bool().unwrap()),
+                        8 => {} // remote token
                         _ => unreachable!("Invalid index"),
                     }
                 }
@@ -238,6 +255,15 @@ impl View for Wifi {
         }
 
         match event {
+            KeyEvent::Pressed(Key::A) if self.list.selected() == 8 => {
+                // Replacing the token signs out every device that had it
+                let token = remote_token::rotate()?;
+                self.list.set_right(
+                    8,
+                    Box::new(Label::new(Point::zero(), token, Alignment::Right, None)),
+                );
+                Ok(true)
+            }
             KeyEvent::Pressed(Key::B) => {
                 bubble.push_back(Command::CloseView);
                 Ok(true)
@@ -254,7 +280,7 @@ impl View for Wifi {
         vec![&mut self.list, &mut self.button_hints]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
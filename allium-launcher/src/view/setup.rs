@@ -0,0 +1,415 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::display::Display;
+use common::geom::{Alignment, Point, Rect};
+use common::list_settings::ListSettings;
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::profile::Profile;
+use common::resources::Resources;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, View};
+use log::{error, info};
+use tokio::sync::mpsc::Sender;
+
+use crate::consoles::ConsoleMapper;
+use crate::setup::{self, ScreenStack, Step};
+use crate::view::settings::{
+    ChildState, Clock, Language, SettingsChild, Theme as ThemeSettings, Wifi,
+};
+
+/// Walks the user through setting up the device on first boot, one settings page at a time.
+/// Start goes to the next step, B back to the previous one, and Select skips the rest.
+#[derive(Debug)]
+pub struct SetupWizard {
+    rect: Rect,
+    res: Resources,
+    stack: ScreenStack,
+    title: Label<String>,
+    button_hints: Row<ButtonHint<String>>,
+    page: Box<dyn SettingsChild>,
+    dirty: bool,
+}
+
+impl SetupWizard {
+    pub fn new(rect: Rect, res: Resources) -> Self {
+        let stack = ScreenStack::new(Step::all(DefaultPlatform::has_wifi()));
+        let (title, button_hints) = header(rect, &res, &stack);
+        let page = page(stack.current(), page_rect(rect, &res), res.clone(), None);
+        Self {
+            rect,
+            res,
+            stack,
+            title,
+            button_hints,
+            page,
+            dirty: true,
+        }
+    }
+
+    /// Lays out the current step again, e.g. after the language or theme was changed.
+    pub fn reload(&mut self) {
+        let state = self.page.save();
+        self.load_step(Some(state));
+    }
+
+    fn load_step(&mut self, state: Option<ChildState>) {
+        (self.title, self.button_hints) = header(self.rect, &self.res, &self.stack);
+        self.page = page(
+            self.stack.current(),
+            page_rect(self.rect, &self.res),
+            self.res.clone(),
+            state,
+        );
+        self.dirty = true;
+    }
+}
+
+/// The title with the step's position, and the hints to go on or skip.
+fn header(
+    rect: Rect,
+    res: &Resources,
+    stack: &ScreenStack,
+) -> (Label<String>, Row<ButtonHint<String>>) {
+    let Rect { x, y, w, .. } = rect;
+    let locale = res.get::<Locale>();
+
+    let name = match stack.current() {
+        Step::Language => locale.t("settings-language"),
+        Step::Clock => locale.t("settings-clock"),
+        Step::Wifi => locale.t("settings-wifi"),
+        Step::Theme => locale.t("settings-theme"),
+        Step::Folders => locale.t("setup-folders"),
+    };
+    let mut title = Label::new(
+        Point::new(x + 12, y + 8),
+        locale.ta(
+            "setup-title",
+            &[
+                ("step".to_string(), (stack.index() + 1).into()),
+                ("total".to_string(), stack.len().into()),
+                ("name".to_string(), name.into()),
+            ]
+            .into_iter()
+            .collect(),
+        ),
+        Alignment::Left,
+        None,
+    );
+    title.color(StylesheetColor::Highlight);
+
+    let next = if stack.index() + 1 == stack.len() {
+        locale.t("setup-finish")
+    } else {
+        locale.t("setup-next")
+    };
+    let button_hints = Row::new(
+        Point::new(x + w as i32 - 12, y + 8),
+        vec![
+            ButtonHint::new(Point::zero(), Key::Start, next, Alignment::Right),
+            ButtonHint::new(
+                Point::zero(),
+                Key::Select,
+                locale.t("setup-skip"),
+                Alignment::Right,
+            ),
+        ],
+        Alignment::Right,
+        12,
+    );
+
+    (title, button_hints)
+}
+
+/// Where the step's page goes, below the header.
+fn page_rect(rect: Rect, res: &Resources) -> Rect {
+    let styles = res.get::<Styles>();
+    let header = 8 + styles.ui_font.size.max(ButtonIcon::diameter(&styles));
+    Rect::new(rect.x, rect.y + header as i32, rect.w, rect.h - header)
+}
+
+fn page(
+    step: Step,
+    rect: Rect,
+    res: Resources,
+    state: Option<ChildState>,
+) -> Box<dyn SettingsChild> {
+    match step {
+        Step::Language => Box::new(Language::new(rect, res, state)),
+        Step::Clock => Box::new(Clock::new(rect, res, state)),
+        Step::Wifi => Box::new(Wifi::new(rect, res, state)),
+        Step::Theme => Box::new(ThemeSettings::new(rect, res, state)),
+        Step::Folders => Box::new(Folders::new(rect, res, state)),
+    }
+}
+
+#[async_trait(?Send)]
+impl View for SetupWizard {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        if self.dirty {
+            display.load(self.rect)?;
+            self.title.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.page.set_should_draw();
+            self.dirty = false;
+        }
+
+        let mut drawn = false;
+        drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+        drawn |= self.page.should_draw() && self.page.draw(display, styles)?;
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self.title.should_draw()
+            || self.button_hints.should_draw()
+            || self.page.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self.page.handle_key_event(event, commands, bubble).await? {
+            // Pages close themselves on B, which goes back a step instead
+            let mut back = false;
+            bubble.retain(|command| match command {
+                Command::CloseView => {
+                    back = true;
+                    false
+                }
+                _ => true,
+            });
+            if back && self.stack.back() {
+                self.load_step(None);
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::Start) => {
+                if self.stack.next() {
+                    self.load_step(None);
+                } else {
+                    info!("setup finished");
+                    bubble.push_back(Command::CloseView);
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::Select) => {
+                info!("setup skipped");
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![
+            &self.title,
+            &self.button_hints,
+            self.page.as_ref() as &dyn View,
+        ]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![
+            &mut self.title,
+            &mut self.button_hints,
+            self.page.as_mut() as &mut dyn View,
+        ]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+/// Shows which folder each console's games go in, and offers to create the missing ones.
+struct Folders {
+    rect: Rect,
+    res: Resources,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+}
+
+impl Folders {
+    fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let (left, right) = res
+            .get::<ConsoleMapper>()
+            .console_folders()
+            .map(|(name, folder)| {
+                (
+                    name.to_string(),
+                    Box::new(Label::new(
+                        Point::zero(),
+                        format!("{folder}/"),
+                        Alignment::Right,
+                        None,
+                    )) as Box<dyn View>,
+                )
+            })
+            .unzip();
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            left,
+            right,
+            styles.row_layout(),
+        );
+        list.set_list_settings(*res.get::<ListSettings>());
+        if let Some(state) = state {
+            list.select(state.selected);
+        }
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("setup-folders-create"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            res,
+            list,
+            button_hints,
+        }
+    }
+
+    async fn create_folders(&self, commands: Sender<Command>) -> Result<()> {
+        let result = setup::create_folder_skeleton(
+            &self.res.get::<Profile>().games_dir(),
+            &self.res.get::<ConsoleMapper>(),
+        );
+        let toast = match result {
+            Ok(count) => self.res.get::<Locale>().ta(
+                "setup-folders-created",
+                &[("count".to_string(), count.into())].into_iter().collect(),
+            ),
+            Err(e) => {
+                error!("failed to create game folders: {}", e);
+                self.res.get::<Locale>().t("setup-folders-failed")
+            }
+        };
+        commands
+            .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Folders {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                self.create_folders(commands).await?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => self.list.handle_key_event(event, commands, bubble).await,
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for Folders {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
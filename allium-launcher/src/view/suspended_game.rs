@@ -1,14 +1,15 @@
 use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 use anyhow::Result;
 use async_trait::async_trait;
 
 use common::command::Command;
+use common::constants::LONG_PRESS_DURATION;
 use common::display::font::FontTextStyleBuilder;
 use common::geom::{Point, Rect};
-use common::platform::{DefaultPlatform, KeyEvent, Platform};
-use common::stylesheet::Stylesheet;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::stylesheet::Styles;
 use common::view::View;
 use embedded_graphics::prelude::{Dimensions, OriginDimensions, Size};
 use embedded_graphics::primitives::{
@@ -18,51 +19,44 @@ use embedded_graphics::text::{Alignment, Text};
 use embedded_graphics::Drawable;
 use tokio::sync::mpsc::Sender;
 
+/// Banner at the top of the screen while a game is suspended in the background. Pressing Start
+/// resumes the game, holding it quits the game.
 #[derive(Debug, Clone)]
-pub struct Toast {
+pub struct SuspendedGame {
     text: String,
-    expires: Option<Instant>,
+    pressed_start: Option<Instant>,
+    dirty: bool,
 }
 
-impl Toast {
-    pub fn new(text: String, duration: Option<Duration>) -> Self {
+impl SuspendedGame {
+    pub fn new(text: String) -> Self {
         Self {
             text,
-            expires: duration.map(|duration| Instant::now() + duration),
-        }
-    }
-
-    pub fn has_expired(&self) -> bool {
-        if let Some(expires) = self.expires {
-            Instant::now() > expires
-        } else {
-            false
+            pressed_start: None,
+            dirty: true,
         }
     }
 }
 
 #[async_trait(?Send)]
-impl View for Toast {
+impl View for SuspendedGame {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let w = display.size().width;
-        let h = display.size().height;
 
-        let text_style = FontTextStyleBuilder::new(styles.ui_font.font())
-            .font_fallback(styles.cjk_font.font())
+        let text_style = FontTextStyleBuilder::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
             .font_size(styles.ui_font.size)
             .background_color(styles.highlight_color)
             .text_color(styles.foreground_color)
             .build();
 
-        let lines = self.text.lines().count() as u32;
-
         let text = Text::with_alignment(
             &self.text,
-            Point::new(w as i32 / 2, (h - styles.ui_font.size * lines) as i32 / 2).into(),
+            Point::new(w as i32 / 2, 8).into(),
             text_style,
             Alignment::Center,
         );
@@ -72,10 +66,7 @@ impl View for Toast {
         let y = rect.top_left.y;
         let Size { width, height } = rect.size;
         RoundedRectangle::new(
-            Rectangle::new(
-                Point::new(x - 12, y - 8).into(),
-                Size::new(width + 24, height + 16),
-            ),
+            Rectangle::new(Point::new(x - 12, y).into(), Size::new(width + 24, height)),
             CornerRadii::new(Size::new_equal(12)),
         )
         .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
@@ -83,22 +74,47 @@ impl View for Toast {
 
         text.draw(display)?;
 
+        self.dirty = false;
         Ok(true)
     }
 
     fn should_draw(&self) -> bool {
-        true
+        self.dirty
     }
 
-    fn set_should_draw(&mut self) {}
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
 
     async fn handle_key_event(
         &mut self,
-        _event: KeyEvent,
-        _commands: Sender<Command>,
+        event: KeyEvent,
+        commands: Sender<Command>,
         _bubble: &mut VecDeque<Command>,
     ) -> Result<bool> {
-        Ok(false)
+        match event {
+            KeyEvent::Pressed(Key::Start) => {
+                self.pressed_start = Some(Instant::now());
+                Ok(true)
+            }
+            KeyEvent::Autorepeat(Key::Start) => {
+                if self
+                    .pressed_start
+                    .is_some_and(|pressed| pressed.elapsed() >= LONG_PRESS_DURATION)
+                {
+                    self.pressed_start = None;
+                    commands.send(Command::QuitSuspendedGame).await?;
+                }
+                Ok(true)
+            }
+            KeyEvent::Released(Key::Start) => {
+                if self.pressed_start.take().is_some() {
+                    commands.send(Command::ResumeGame).await?;
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
     }
 
     fn children(&self) -> Vec<&dyn View> {
@@ -109,7 +125,7 @@ impl View for Toast {
         vec![]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         Rect::zero()
     }
 
@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::geom::{Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::Styles;
+use common::view::{ConfirmDialog, View};
+use tokio::sync::mpsc::Sender;
+
+/// How long a new theme is shown before it's reverted, unless it's kept.
+pub const REVERT_AFTER: Duration = Duration::from_secs(10);
+
+/// Time left until a deadline, in whole seconds as shown to the user.
+#[derive(Debug, Clone, Copy)]
+pub struct Countdown {
+    deadline: Instant,
+}
+
+impl Countdown {
+    pub fn new(duration: Duration, now: Instant) -> Self {
+        Self {
+            deadline: now + duration,
+        }
+    }
+
+    /// Seconds left, rounded up so that the countdown shows 0 only once it has expired.
+    pub fn seconds_left(&self, now: Instant) -> u64 {
+        let left = self.deadline.saturating_duration_since(now);
+        left.as_secs() + u64::from(left.subsec_nanos() > 0)
+    }
+
+    pub fn has_expired(&self, now: Instant) -> bool {
+        now >= self.deadline
+    }
+}
+
+/// Asks whether to keep a theme that was just applied, reverting to the previous one if it isn't
+/// kept in time. A theme that makes the screen unreadable thus undoes itself.
+///
+/// Keeping the theme bubbles `ValueChanged(0, Value::Bool(true))`, then `CloseView`. Reverting
+/// only bubbles `CloseView`.
+#[derive(Debug)]
+pub struct ThemeConfirm {
+    res: Resources,
+    previous: Styles,
+    countdown: Countdown,
+    seconds_left: u64,
+    dialog: ConfirmDialog,
+}
+
+impl ThemeConfirm {
+    pub fn new(rect: Rect, res: Resources, previous: Styles) -> Self {
+        let countdown = Countdown::new(REVERT_AFTER, Instant::now());
+        let seconds_left = REVERT_AFTER.as_secs();
+
+        let height = {
+            let styles = res.get::<Styles>();
+            styles.ui_font.size * 3 + 48
+        };
+        let width = rect.w * 2 / 3;
+        let dialog = ConfirmDialog::new(
+            Rect::new(
+                rect.x + (rect.w - width) as i32 / 2,
+                rect.y + (rect.h.saturating_sub(height)) as i32 / 2,
+                width,
+                height,
+            ),
+            res.clone(),
+            title(&res, seconds_left),
+            Vec::new(),
+        );
+
+        Self {
+            res,
+            previous,
+            countdown,
+            seconds_left,
+            dialog,
+        }
+    }
+
+    /// Updates the countdown. Returns whether the theme should be reverted now.
+    pub fn tick(&mut self) -> bool {
+        let now = Instant::now();
+        let seconds_left = self.countdown.seconds_left(now);
+        if seconds_left != self.seconds_left {
+            self.seconds_left = seconds_left;
+            self.dialog.set_title(title(&self.res, seconds_left));
+        }
+        self.countdown.has_expired(now)
+    }
+
+    /// The theme to revert to.
+    pub fn into_previous(self) -> Styles {
+        self.previous
+    }
+}
+
+fn title(res: &Resources, seconds: u64) -> String {
+    res.get::<Locale>().ta(
+        "settings-theme-confirm",
+        &[("seconds".to_string(), seconds.into())]
+            .into_iter()
+            .collect(),
+    )
+}
+
+#[async_trait(?Send)]
+impl View for ThemeConfirm {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        self.dialog.draw(display, styles)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dialog.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dialog.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        self.dialog.handle_key_event(event, commands, bubble).await
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.dialog]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.dialog]
+    }
+
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
+        self.dialog.bounding_box(styles)
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.dialog.set_position(point);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_countdown() {
+        let start = Instant::now();
+        let countdown = Countdown::new(REVERT_AFTER, start);
+        assert_eq!(countdown.seconds_left(start), 10);
+        assert_eq!(countdown.seconds_left(start + Duration::from_millis(1)), 10);
+        assert_eq!(
+            countdown.seconds_left(start + Duration::from_millis(9001)),
+            1
+        );
+        assert!(!countdown.has_expired(start + Duration::from_millis(9999)));
+
+        // Reverts once the time is up, and stays expired
+        let end = start + REVERT_AFTER;
+        assert_eq!(countdown.seconds_left(end), 0);
+        assert!(countdown.has_expired(end));
+        assert!(countdown.has_expired(end + Duration::from_secs(5)));
+        assert_eq!(countdown.seconds_left(end + Duration::from_secs(5)), 0);
+    }
+}
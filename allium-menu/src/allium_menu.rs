@@ -1,20 +1,26 @@
 use std::collections::VecDeque;
-use std::process;
+use std::time::Instant;
+use std::{env, process};
 
 use anyhow::Result;
 use common::command::Command;
+use common::constants::WRITE_ACTIVITY_UPDATE_INTERVAL;
 use common::database::Database;
 use common::display::color::Color;
 use common::display::Display;
-use common::game_info::GameInfo;
+use common::game_info::{
+    GameInfo, GameStatus, ALLIUM_MAIN_PID_ENV, MENU_EXIT_QUIT_GAME, MENU_EXIT_SUSPEND_GAME,
+    MENU_EXIT_SWITCH_GAME, MENU_EXIT_TERMINATE_MAIN,
+};
 use common::geom;
 use common::locale::{Locale, LocaleSettings};
-use common::platform::{DefaultPlatform, Platform};
+use common::platform::{self, DefaultPlatform, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
-use common::view::View;
+use common::stylesheet::Styles;
+use common::theme_schedule::{ThemeChanged, ThemeSchedule};
+use common::view::{Toast, View};
 use embedded_graphics::prelude::*;
-use log::warn;
+use log::{info, warn};
 use type_map::TypeMap;
 
 use crate::retroarch_info::RetroArchInfo;
@@ -31,18 +37,24 @@ where
     display: P::Display,
     res: Resources,
     view: IngameMenu<P::Battery>,
+    toast: Option<Toast>,
 }
 
 impl AlliumMenu<DefaultPlatform> {
     pub async fn new(mut platform: DefaultPlatform, info: Option<RetroArchInfo>) -> Result<Self> {
-        let display = platform.display()?;
+        let display = platform::open_display(&mut platform)?;
         let battery = platform.battery()?;
         let rect = display.bounding_box().into();
 
         let mut res = TypeMap::new();
         res.insert(Database::new()?);
-        res.insert(GameInfo::load()?.unwrap_or_default());
-        res.insert(Stylesheet::load()?);
+        let game_info = GameInfo::load()?;
+        let main_pid = env::var(ALLIUM_MAIN_PID_ENV)
+            .ok()
+            .and_then(|pid| pid.parse().ok());
+        res.insert(GameStatus::detect(game_info.as_ref(), main_pid));
+        res.insert(game_info.unwrap_or_default());
+        res.insert(Styles::load_fitted(display.size().height)?);
         res.insert(Locale::new(&LocaleSettings::load()?.lang));
         res.insert(Into::<geom::Size>::into(display.size()));
         let res = Resources::new(res);
@@ -52,32 +64,64 @@ impl AlliumMenu<DefaultPlatform> {
             display,
             res: res.clone(),
             view: IngameMenu::load_or_new(rect, res, battery, info).await?,
+            toast: None,
         })
     }
 
     pub async fn run_event_loop(&mut self) -> Result<()> {
         {
-            let styles = self.res.get::<Stylesheet>();
+            // Taken before the screen is darkened, to compare with an existing save state
+            match self.display.capture() {
+                Ok(screenshot) => self.view.set_screenshot(screenshot),
+                Err(e) => warn!("failed to capture screen: {}", e),
+            }
+
+            let styles = self.res.get::<Styles>();
             self.display
-                .map_pixels(|pixel| pixel.blend(styles.background_color.overlay(pixel), 192))?;
+                .map_pixels(|pixel| styles.dim_behind_menu(pixel))?;
             self.display.save()?;
         }
 
         #[cfg(unix)]
         let mut sigterm = tokio::signal::unix::signal(SignalKind::terminate())?;
+        // Sent by alliumd when the theme schedule switches between day and night
+        #[cfg(unix)]
+        let mut sigusr1 = tokio::signal::unix::signal(SignalKind::user_defined1())?;
 
         let (tx, mut rx) = tokio::sync::mpsc::channel(100);
 
+        // Keeps the status bar, such as the write activity indicator, up to date between key presses
+        let mut update_interval = tokio::time::interval(WRITE_ACTIVITY_UPDATE_INTERVAL);
+
+        let mut last_update = Instant::now();
         loop {
-            if self.view.should_draw() && self.view.draw(&mut self.display, &self.res.get())? {
+            self.view.update(last_update.elapsed());
+            last_update = Instant::now();
+
+            if self.toast.as_ref().is_some_and(Toast::has_expired) {
+                self.toast = None;
+                self.display.load(self.display.bounding_box().into())?;
+                self.view.set_should_draw();
+            }
+
+            let mut drawn =
+                self.view.should_draw() && self.view.draw(&mut self.display, &self.res.get())?;
+            if let Some(toast) = self.toast.as_mut() {
+                drawn |= toast.draw(&mut self.display, &self.res.get())?;
+            }
+            if drawn {
                 self.display.flush()?;
             }
 
             #[cfg(unix)]
             tokio::select! {
+                _ = update_interval.tick() => {}
                 _ = sigterm.recv() => {
                     self.handle_command(Command::Exit)?;
                 }
+                _ = sigusr1.recv() => {
+                    self.apply_theme_change()?;
+                }
                 Some(command) = rx.recv() => {
                     self.handle_command(command)?;
                 }
@@ -90,6 +134,7 @@ impl AlliumMenu<DefaultPlatform> {
 
             #[cfg(not(unix))]
             tokio::select! {
+                _ = update_interval.tick() => {}
                 Some(command) = rx.recv() => {
                     self.handle_command(command)?;
                 }
@@ -104,20 +149,58 @@ impl AlliumMenu<DefaultPlatform> {
 
     fn handle_command(&mut self, command: Command) -> Result<()> {
         match command {
-            Command::Exit => {
-                self.view.save()?;
-                self.display.clear(Color::new(0, 0, 0))?;
-                self.display.flush()?;
-                process::exit(0);
+            Command::Exit => self.exit(0)?,
+            Command::TerminateMain => self.exit(MENU_EXIT_TERMINATE_MAIN)?,
+            Command::QuitGame => self.exit(MENU_EXIT_QUIT_GAME)?,
+            Command::SuspendGame => self.exit(MENU_EXIT_SUSPEND_GAME)?,
+            Command::SwitchGame(request) => {
+                request.save()?;
+                self.exit(MENU_EXIT_SWITCH_GAME)?;
             }
             Command::Redraw => {
                 self.display.load(self.display.bounding_box().into())?;
                 self.view.set_should_draw();
             }
+            Command::Toast(text, duration) => {
+                self.toast = Some(Toast::new(text, duration));
+            }
             command => {
                 warn!("unhandled command: {:?}", command);
             }
         }
         Ok(())
     }
+
+    /// Switches to the day or night theme when alliumd says the theme schedule switched. The
+    /// current theme is kept if the new one can't be loaded.
+    #[cfg(unix)]
+    fn apply_theme_change(&mut self) -> Result<()> {
+        let Some(ThemeChanged { period }) = ThemeChanged::receive()? else {
+            return Ok(());
+        };
+        let current = self.res.get::<Styles>().clone();
+        let mut config = ThemeSchedule::load()?.theme_for(period, current.config().clone());
+        config.clamp_metrics(self.display.size().height);
+        if config == *current.config() {
+            return Ok(());
+        }
+        info!("switching to {:?} theme", period);
+        match Styles::from_config(config) {
+            Ok(styles) => {
+                styles.apply_render_mode();
+                self.res.insert(styles);
+                self.display.load(self.display.bounding_box().into())?;
+                self.view.set_should_draw();
+            }
+            Err(e) => warn!("failed to load {:?} theme: {}", period, e),
+        }
+        Ok(())
+    }
+
+    fn exit(&mut self, code: i32) -> Result<()> {
+        self.view.save()?;
+        self.display.clear(Color::new(0, 0, 0))?;
+        self.display.flush()?;
+        process::exit(code);
+    }
 }
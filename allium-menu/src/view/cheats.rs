@@ -0,0 +1,221 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::cheats::CheatFile;
+use common::command::Command;
+use common::display::Display;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, Toggle, View};
+use log::warn;
+use tokio::sync::mpsc::Sender;
+
+/// Turns the cheats in the running game's cheat file on and off. The file is written when the
+/// list is closed, and RetroArch applies it the next time the game is launched or resumed.
+pub struct Cheats {
+    rect: Rect,
+    res: Resources,
+    file: CheatFile,
+    title: Label<String>,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+    changed: bool,
+    dirty: bool,
+}
+
+impl Cheats {
+    pub fn new(rect: Rect, res: Resources, file: CheatFile) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let mut title = Label::new(
+            Point::new(x + 12, y + 8),
+            locale.t("ingame-menu-cheats"),
+            Alignment::Left,
+            None,
+        );
+        title.color(StylesheetColor::Highlight);
+
+        // Only the visible rows are drawn, so long cheat lists scroll as quickly as short ones
+        let list = SettingsList::new(
+            Rect::new(
+                x + 24,
+                y + 8 + styles.ui_font.size as i32 + 8,
+                w - 48,
+                h - 8 - styles.ui_font.size - 8 - ButtonIcon::diameter(&styles) - 8 - 8,
+            ),
+            file.cheats()
+                .iter()
+                .map(|cheat| cheat.description.clone())
+                .collect(),
+            file.cheats()
+                .iter()
+                .map(|cheat| {
+                    Box::new(Toggle::new(Point::zero(), cheat.enabled, Alignment::Right))
+                        as Box<dyn View>
+                })
+                .collect(),
+            styles.row_layout(),
+        );
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-edit"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            res,
+            file,
+            title,
+            list,
+            button_hints,
+            changed: false,
+            dirty: true,
+        }
+    }
+
+    /// Writes the cheat file if any cheat was turned on or off.
+    pub fn save(&self) -> Result<()> {
+        if self.changed {
+            self.file.save()?;
+        }
+        Ok(())
+    }
+
+    /// Saves the cheats before closing, showing why if they couldn't be.
+    async fn close(
+        &mut self,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<()> {
+        if let Err(e) = self.save() {
+            warn!("failed to save cheats: {}", e);
+            let mut map = HashMap::new();
+            map.insert("error".to_string(), e.to_string().into());
+            let toast = self
+                .res
+                .get::<Locale>()
+                .ta("ingame-menu-cheats-save-failed", &map);
+            commands
+                .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+                .await?;
+        }
+        self.changed = false;
+        bubble.push_back(Command::CloseView);
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Cheats {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.title.set_should_draw();
+            self.list.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.dirty = false;
+            drawn = true;
+        }
+
+        drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self.title.should_draw()
+            || self.list.should_draw()
+            || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self
+            .list
+            .handle_key_event(event, commands.clone(), bubble)
+            .await?
+        {
+            while let Some(command) = bubble.pop_front() {
+                if let Command::ValueChanged(i, value) = command {
+                    if let Some(enabled) = value.as_bool() {
+                        self.file.set_enabled(i, enabled);
+                        self.changed = true;
+                    }
+                }
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::B) => {
+                self.close(commands, bubble).await?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.title, &self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.title, &mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.rect.x = point.x;
+        self.rect.y = point.y;
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::display::Display;
+use common::game_info::GameInfo;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::retroarch::RetroArchCommand;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{ButtonHint, ButtonIcon, Label, Row, ScrollList, View};
+use log::warn;
+use tokio::sync::mpsc::Sender;
+
+/// Picks a disc of the running game's playlist to switch to. The disc is switched through
+/// RetroArch's network commands and the game resumed right away.
+pub struct DiscPicker {
+    rect: Rect,
+    /// Disc that is inserted.
+    current: usize,
+    title: Label<String>,
+    list: ScrollList,
+    button_hints: Row<ButtonHint<String>>,
+    dirty: bool,
+}
+
+impl DiscPicker {
+    pub fn new(rect: Rect, res: Resources, discs: &[PathBuf], current: usize) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let mut title = Label::new(
+            Point::new(x + 12, y + 8),
+            locale.t("ingame-menu-change-disc"),
+            Alignment::Left,
+            None,
+        );
+        title.color(StylesheetColor::Highlight);
+
+        let mut list = ScrollList::new(
+            Rect::new(
+                x + 24,
+                y + 8 + styles.ui_font.size as i32 + 8,
+                w - 48,
+                h - 8 - styles.ui_font.size - 8 - ButtonIcon::diameter(&styles) - 8 - 8,
+            ),
+            discs
+                .iter()
+                .map(|disc| {
+                    disc.file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect(),
+            Alignment::Left,
+            styles.row_layout(),
+        );
+        if current < discs.len() {
+            list.set_detail(current, Some(locale.t("ingame-menu-disc-current")));
+            list.select(current);
+        }
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-select"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            current,
+            title,
+            list,
+            button_hints,
+            dirty: true,
+        }
+    }
+
+    /// Switches to the selected disc and resumes the game.
+    async fn select(
+        &mut self,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<()> {
+        let disc = self.list.selected();
+        if disc == self.current {
+            bubble.push_back(Command::CloseView);
+            return Ok(());
+        }
+
+        RetroArchCommand::SetDiskSlot(disc as u8).send().await?;
+        if let Err(e) = record_disc(disc) {
+            warn!("failed to record disc in game info: {}", e);
+        }
+        commands.send(Command::Exit).await?;
+        Ok(())
+    }
+}
+
+/// Records the inserted disc in the game info. The game info is read again rather than taken from
+/// the menu's resources, since alliumd updates it while the menu is open.
+fn record_disc(disc: usize) -> Result<()> {
+    if let Some(mut game_info) = GameInfo::load()? {
+        game_info.disc = disc;
+        game_info.save()?;
+    }
+    Ok(())
+}
+
+#[async_trait(?Send)]
+impl View for DiscPicker {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.title.set_should_draw();
+            self.list.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.dirty = false;
+            drawn = true;
+        }
+
+        drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self.title.should_draw()
+            || self.list.should_draw()
+            || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                self.select(commands, bubble).await?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            event => self.list.handle_key_event(event, commands, bubble).await,
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.title, &self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.title, &mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.rect.x = point.x;
+        self.rect.y = point.y;
+    }
+}
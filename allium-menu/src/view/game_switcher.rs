@@ -0,0 +1,293 @@
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::IMAGE_WIDTH;
+use common::database::{Database, Game};
+use common::display::Display;
+use common::game_info::{GameInfo, SwitchRequest};
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{
+    ArtPlaceholder, ButtonHint, ButtonIcon, Image, ImageMode, Label, Row, ScrollList, View,
+};
+use log::warn;
+use tokio::sync::mpsc::Sender;
+
+/// Number of recently played games offered to switch to.
+const RECENT_GAMES: usize = 8;
+
+/// Picks a recently played game to switch to from the one that is running.
+pub struct GameSwitcher {
+    rect: Rect,
+    res: Resources,
+    games: Vec<Game>,
+    /// Save the current game's state before switching.
+    save_state: bool,
+    title: Label<String>,
+    list: ScrollList,
+    image: Image,
+    placeholder: ArtPlaceholder,
+    /// Why the selected game can't be switched to.
+    error: Label<String>,
+    button_hints: Row<ButtonHint<String>>,
+    art_rect: Rect,
+    art_dirty: bool,
+    dirty: bool,
+}
+
+impl GameSwitcher {
+    pub fn new(rect: Rect, res: Resources, save_state: bool) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let current = res.get::<GameInfo>().path.clone();
+        let games: Vec<Game> = res
+            .get::<Database>()
+            .select_last_played(RECENT_GAMES as i64 + 1)
+            .map_err(|e| warn!("failed to load recent games: {}", e))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|game| game.path != current)
+            .take(RECENT_GAMES)
+            .collect();
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let mut title = Label::new(
+            Point::new(x + 12, y + 8),
+            if games.is_empty() {
+                locale.t("ingame-menu-switch-game-empty")
+            } else {
+                locale.t("ingame-menu-switch-game")
+            },
+            Alignment::Left,
+            None,
+        );
+        title.color(StylesheetColor::Highlight);
+
+        let top = y + 8 + styles.ui_font.size as i32 + 8;
+        let height = h - 8 - styles.ui_font.size - 8 - ButtonIcon::diameter(&styles) - 8 - 8;
+        let list = ScrollList::new(
+            Rect::new(x + 24, top, w - IMAGE_WIDTH - 24 - 24 - 24, height),
+            games.iter().map(|game| game.name.clone()).collect(),
+            Alignment::Left,
+            styles.row_layout(),
+        );
+
+        let art_rect = Rect::new(
+            x + w as i32 - IMAGE_WIDTH as i32 - 24,
+            top,
+            IMAGE_WIDTH,
+            height,
+        );
+        let mut image = Image::empty(art_rect, ImageMode::Contain);
+        image.set_border_radius(12);
+
+        let mut error = Label::new(
+            Point::new(
+                x + 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            String::new(),
+            Alignment::Left,
+            Some(w / 2),
+        );
+        error.color(StylesheetColor::Highlight);
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-select"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            res,
+            games,
+            save_state,
+            title,
+            list,
+            image,
+            placeholder: ArtPlaceholder::new(art_rect),
+            error,
+            button_hints,
+            art_rect,
+            art_dirty: true,
+            dirty: true,
+        }
+    }
+
+    /// Asks to switch to the selected game, or shows why it can't be.
+    async fn select(&mut self, commands: Sender<Command>) -> Result<()> {
+        let Some(game) = self.games.get(self.list.selected()) else {
+            return Ok(());
+        };
+
+        if !game.path.exists() {
+            warn!("can't switch to {}, it is missing", game.path.display());
+            let mut map = HashMap::new();
+            map.insert("name".to_string(), game.name.clone().into());
+            let text = self
+                .res
+                .get::<Locale>()
+                .ta("ingame-menu-switch-game-missing", &map);
+            self.error.set_text(text);
+            self.dirty = true;
+            return Ok(());
+        }
+
+        commands
+            .send(Command::SwitchGame(SwitchRequest {
+                path: game.path.clone(),
+                save_state: self.save_state,
+            }))
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl View for GameSwitcher {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.title.set_should_draw();
+            self.list.set_should_draw();
+            self.error.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.art_dirty = true;
+            self.dirty = false;
+            drawn = true;
+        }
+
+        drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.error.should_draw() && self.error.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        if self.art_dirty {
+            display.load(self.art_rect)?;
+            if let Some(game) = self.games.get(self.list.selected()) {
+                match game.image.as_ref() {
+                    Some(image) => {
+                        self.image.set_path(Some(image.clone()));
+                        self.image.draw(display, styles)?;
+                    }
+                    None => {
+                        self.placeholder.set_name(&game.name);
+                        self.placeholder.draw(display, styles)?;
+                    }
+                }
+            }
+            self.art_dirty = false;
+            drawn = true;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self.art_dirty
+            || self.title.should_draw()
+            || self.list.should_draw()
+            || self.error.should_draw()
+            || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                self.select(commands).await?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            event => {
+                let prev = self.list.selected();
+                let consumed = self.list.handle_key_event(event, commands, bubble).await?;
+                if self.list.selected() != prev {
+                    self.art_dirty = true;
+                    if !self.error.text().is_empty() {
+                        self.error.set_text(String::new());
+                        self.dirty = true;
+                    }
+                }
+                Ok(consumed)
+            }
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![
+            &self.title,
+            &self.list,
+            &self.image,
+            &self.placeholder,
+            &self.error,
+            &self.button_hints,
+        ]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![
+            &mut self.title,
+            &mut self.list,
+            &mut self.image,
+            &mut self.placeholder,
+            &mut self.error,
+            &mut self.button_hints,
+        ]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.rect.x = point.x;
+        self.rect.y = point.y;
+    }
+}
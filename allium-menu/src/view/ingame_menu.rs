@@ -1,35 +1,55 @@
 use std::collections::{HashMap, VecDeque};
 use std::fs;
-use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use chrono::{DateTime, Local};
 use common::battery::Battery;
-use common::command::Command;
-use common::constants::{ALLIUM_MENU_STATE, SELECTION_MARGIN};
+use common::cheats::CheatFile;
+use common::command::{Command, Value};
+use common::constants::{ALLIUM_MENU_STATE, ALLIUM_SCREENSHOTS_DIR, ALLIUM_STATE_PREVIEW};
+use common::database::Database;
 use common::display::Display;
-use common::game_info::GameInfo;
+use common::game_info::{GameInfo, GameStatus};
 use common::geom::{Alignment, Point, Rect};
+use common::ingame_menu::{IngameMenuSettings, MenuEntry};
 use common::locale::Locale;
+use common::persisted::{self, Versioned};
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::playlist;
 use common::resources::Resources;
 use common::retroarch::RetroArchCommand;
-use common::stylesheet::Stylesheet;
+use common::save_state::{SaveStates, SlotInfo, AUTO_SLOT};
+use common::stylesheet::Styles;
 use common::view::{
-    BatteryIndicator, ButtonHint, ButtonIcon, Label, NullView, Row, SettingsList, View,
+    BatteryIndicator, ButtonHint, ButtonIcon, ConfirmDialog, Label, Notes, NullView, Row,
+    SettingsList, View, WriteIndicator,
 };
+use common::write_activity;
+use image::RgbImage;
 use log::warn;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
 use crate::retroarch_info::RetroArchInfo;
+use crate::view::cheats::Cheats;
+use crate::view::disc_picker::DiscPicker;
+use crate::view::game_switcher::GameSwitcher;
+use crate::view::save_states::SaveStateManager;
 use crate::view::text_reader::TextReader;
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct IngameMenuState {
+    #[serde(default)]
     is_text_reader_open: bool,
 }
 
+impl Versioned for IngameMenuState {
+    const VERSION: u32 = 1;
+}
+
 pub struct IngameMenu<B>
 where
     B: Battery + 'static,
@@ -38,14 +58,44 @@ where
     res: Resources,
     name: Label<String>,
     battery_indicator: BatteryIndicator<B>,
+    write_indicator: WriteIndicator,
     menu: SettingsList,
     child: Option<TextReader>,
+    /// The game's note, while it is open.
+    notes: Option<Notes>,
+    /// The game's cheats, while they are open.
+    cheats: Option<Cheats>,
+    /// The game's cheat file, if it has one.
+    cheat_file: Option<PathBuf>,
+    /// Recent games to switch to, while picking one.
+    switcher: Option<GameSwitcher>,
+    /// The game's state slots, while they are open.
+    save_states: Option<SaveStateManager>,
+    /// Discs of the game's playlist, while picking one.
+    disc_picker: Option<DiscPicker>,
+    /// Discs of the game's playlist, if it has more than one.
+    discs: Vec<PathBuf>,
+    /// Asks before overwriting a state or clearing stale game info.
+    confirm: Option<(Confirm, ConfirmDialog)>,
     button_hints: Row<ButtonHint<String>>,
     entries: Vec<MenuEntry>,
     info: Option<RetroArchInfo>,
+    backup_states: bool,
+    save_on_switch: bool,
+    /// The game screen from before the menu was opened.
+    screenshot: Option<RgbImage>,
     dirty: bool,
 }
 
+/// What the confirmation dialog is asking about.
+#[derive(Debug, Clone, Copy)]
+enum Confirm {
+    /// Overwriting the state in a slot.
+    Overwrite(i8),
+    /// Clearing game info left over from a game that is no longer running.
+    ClearStale,
+}
+
 impl<B> IngameMenu<B>
 where
     B: Battery + 'static,
@@ -55,25 +105,72 @@ where
         state: IngameMenuState,
         res: Resources,
         battery: B,
-        info: Option<RetroArchInfo>,
+        mut info: Option<RetroArchInfo>,
     ) -> Self {
         let Rect { x, y, w, h } = rect;
 
+        let status = *res.get::<GameStatus>();
         let game_info = res.get::<GameInfo>();
         let locale = res.get::<Locale>();
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
+
+        // Disks and states are looked up by the game's path, which is only known from its game info
+        if status != GameStatus::Running {
+            if let Some(info) = info.as_mut() {
+                info.max_disk_slots = 0;
+                info.state_slot = None;
+            }
+        }
 
         let mut name = Label::new(
             Point::new(x + 12, y + 8),
-            game_info.name.clone(),
+            if status == GameStatus::Unknown {
+                locale.t("ingame-menu-unknown-game")
+            } else {
+                game_info.name.clone()
+            },
             Alignment::Left,
             None,
         );
         name.color(common::stylesheet::StylesheetColor::Highlight);
 
         let battery_indicator = BatteryIndicator::new(Point::new(w as i32 - 12, y + 8), battery);
+        let write_indicator = WriteIndicator::new(Point::new(
+            w as i32 - 12 - styles.ui_font.size as i32 * 2 - 8,
+            y + 8,
+        ));
 
-        let entries = MenuEntry::entries(&info);
+        let has_note = status == GameStatus::Running
+            && res
+                .get::<Database>()
+                .note(&game_info.path)
+                .map_err(|e| warn!("failed to load note from database: {}", e))
+                .is_ok_and(|note| note.is_some());
+
+        // Cheats are applied by RetroArch, so they are only offered for games running in it
+        let cheat_file = if status == GameStatus::Running && info.is_some() {
+            CheatFile::find(&game_info.path)
+        } else {
+            None
+        };
+
+        // Discs are switched through RetroArch too
+        let discs = match game_info.playlist.as_ref() {
+            Some(path) if status == GameStatus::Running && info.is_some() => playlist::discs(path)
+                .map_err(|e| warn!("failed to read discs: {}", e))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+        let settings = IngameMenuSettings::load().unwrap_or_default();
+        let entries = menu_entries(
+            &settings,
+            &info,
+            has_note,
+            cheat_file.is_some(),
+            discs.len() > 1,
+            status == GameStatus::Running,
+        );
         let mut menu = SettingsList::new(
             Rect::new(
                 x + 24,
@@ -86,14 +183,17 @@ where
                 .iter()
                 .map(|_| Box::new(NullView) as Box<dyn View>)
                 .collect(),
-            styles.ui_font.size + SELECTION_MARGIN,
+            styles.row_layout(),
         );
         if let Some(info) = info.as_ref() {
             if info.max_disk_slots > 1 && !state.is_text_reader_open {
                 let mut map = HashMap::new();
                 map.insert("disk".to_string(), (info.disk_slot + 1).into());
                 menu.set_right(
-                    MenuEntry::Continue as usize,
+                    entries
+                        .iter()
+                        .position(|e| *e == MenuEntry::Continue)
+                        .unwrap_or_default(),
                     Box::new(Label::new(
                         Point::zero(),
                         locale.ta("ingame-menu-disk", &map),
@@ -130,11 +230,27 @@ where
         let mut child = None;
         if state.is_text_reader_open {
             if let Some(guide) = game_info.guide.as_ref() {
-                menu.select(MenuEntry::Guide as usize);
+                if let Some(i) = entries.iter().position(|e| *e == MenuEntry::Guide) {
+                    menu.select(i);
+                }
                 child = Some(TextReader::new(rect, res.clone(), guide.clone()));
             }
         }
 
+        let confirm = (status == GameStatus::Stale).then(|| {
+            let mut map = HashMap::new();
+            map.insert("name".to_string(), game_info.name.clone().into());
+            (
+                Confirm::ClearStale,
+                ConfirmDialog::new(
+                    rect,
+                    res.clone(),
+                    locale.ta("ingame-menu-stale-game", &map),
+                    Vec::new(),
+                ),
+            )
+        });
+
         drop(game_info);
         drop(locale);
         drop(styles);
@@ -144,43 +260,55 @@ where
             res,
             name,
             battery_indicator,
+            write_indicator,
             menu,
             child,
+            notes: None,
+            cheats: None,
+            cheat_file,
+            switcher: None,
+            save_states: None,
+            disc_picker: None,
+            discs,
+            confirm,
             button_hints,
             entries,
             info,
+            backup_states: settings.backup_states,
+            save_on_switch: settings.save_on_switch,
+            screenshot: None,
             dirty: false,
         }
     }
 
+    /// Sets the game screen, shown when asking to overwrite a state.
+    pub fn set_screenshot(&mut self, screenshot: RgbImage) {
+        self.screenshot = Some(screenshot);
+    }
+
     pub async fn load_or_new(
         rect: Rect,
         res: Resources,
         battery: B,
         info: Option<RetroArchInfo>,
     ) -> Result<Self> {
-        if ALLIUM_MENU_STATE.exists() {
-            let file = File::open(ALLIUM_MENU_STATE.as_path())?;
-            if let Ok(state) = serde_json::from_reader::<_, IngameMenuState>(file) {
-                return Ok(Self::new(rect, state, res, battery, info));
-            }
-            warn!("failed to deserialize state file, deleting");
-            fs::remove_file(ALLIUM_MENU_STATE.as_path())?;
-        }
-
-        Ok(Self::new(rect, Default::default(), res, battery, info))
+        let state = persisted::load(&ALLIUM_MENU_STATE)?.unwrap_or_default();
+        Ok(Self::new(rect, state, res, battery, info))
     }
 
     pub fn save(&self) -> Result<()> {
-        let file = File::create(ALLIUM_MENU_STATE.as_path())?;
+        if let Some(cheats) = self.cheats.as_ref() {
+            if let Err(e) = cheats.save() {
+                warn!("failed to save cheats: {}", e);
+            }
+        }
         let state = IngameMenuState {
             is_text_reader_open: self.child.is_some(),
         };
         if let Some(child) = self.child.as_ref() {
             child.save_cursor();
         }
-        serde_json::to_writer(file, &state)?;
-        Ok(())
+        persisted::save(&ALLIUM_MENU_STATE, &state)
     }
 
     async fn select_entry(&mut self, commands: Sender<Command>) -> Result<bool> {
@@ -190,10 +318,8 @@ where
                 commands.send(Command::Exit).await?;
             }
             MenuEntry::Save => {
-                RetroArchCommand::SaveStateSlot(self.info.as_ref().unwrap().state_slot.unwrap())
-                    .send()
-                    .await?;
-                commands.send(Command::Exit).await?;
+                let slot = self.info.as_ref().unwrap().state_slot.unwrap();
+                self.request_save(slot, commands).await?;
             }
             MenuEntry::Load => {
                 RetroArchCommand::LoadStateSlot(self.info.as_ref().unwrap().state_slot.unwrap())
@@ -201,6 +327,23 @@ where
                     .await?;
                 commands.send(Command::Exit).await?;
             }
+            MenuEntry::SaveStates => {
+                let slot = self.info.as_ref().and_then(|info| info.state_slot);
+                self.save_states = Some(SaveStateManager::new(self.rect, self.res.clone(), slot));
+            }
+            MenuEntry::ChangeDisc => {
+                // RetroArch knows which disc is inserted, unless it didn't say
+                let current = match self.info.as_ref() {
+                    Some(info) if info.max_disk_slots > 1 => info.disk_slot as usize,
+                    _ => self.res.get::<GameInfo>().disc,
+                };
+                self.disc_picker = Some(DiscPicker::new(
+                    self.rect,
+                    self.res.clone(),
+                    &self.discs,
+                    current,
+                ));
+            }
             MenuEntry::Reset => {
                 RetroArchCommand::Reset.send().await?;
                 commands.send(Command::Exit).await?;
@@ -210,27 +353,225 @@ where
                     self.child = Some(TextReader::new(self.rect, self.res.clone(), guide.clone()));
                 }
             }
+            MenuEntry::Notes => {
+                let game_info = self.res.get::<GameInfo>();
+                self.notes = Some(Notes::new(
+                    self.rect,
+                    self.res.clone(),
+                    game_info.path.clone(),
+                    game_info.name.clone(),
+                    false,
+                ));
+            }
+            MenuEntry::Cheats => {
+                if let Some(path) = self.cheat_file.as_ref() {
+                    let locale = self.res.get::<Locale>();
+                    let toast = match CheatFile::load(path) {
+                        Ok(file) if file.cheats().is_empty() => {
+                            warn!("no cheats could be read from {}", path.display());
+                            Some(locale.t("ingame-menu-cheats-empty"))
+                        }
+                        Ok(file) => {
+                            self.cheats = Some(Cheats::new(self.rect, self.res.clone(), file));
+                            None
+                        }
+                        Err(e) => {
+                            warn!("failed to read cheats from {}: {}", path.display(), e);
+                            let mut map = HashMap::new();
+                            map.insert("error".to_string(), e.to_string().into());
+                            Some(locale.ta("ingame-menu-cheats-failed", &map))
+                        }
+                    };
+                    drop(locale);
+                    if let Some(toast) = toast {
+                        commands
+                            .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+                            .await?;
+                    }
+                }
+            }
+            MenuEntry::Screenshot => {
+                let result = self.save_screenshot();
+                let locale = self.res.get::<Locale>();
+                let mut map = HashMap::new();
+                let toast = match result {
+                    Ok(path) => {
+                        map.insert("path".to_string(), path.display().to_string().into());
+                        locale.ta("ingame-menu-screenshot-saved", &map)
+                    }
+                    Err(e) => {
+                        warn!("failed to save screenshot: {}", e);
+                        map.insert("error".to_string(), e.to_string().into());
+                        locale.ta("ingame-menu-screenshot-failed", &map)
+                    }
+                };
+                drop(locale);
+                commands
+                    .send(Command::Toast(toast, Some(Duration::from_secs(3))))
+                    .await?;
+            }
+            MenuEntry::Launcher => {
+                commands.send(Command::SuspendGame).await?;
+            }
+            MenuEntry::SwitchGame => {
+                let save_state = self.save_on_switch
+                    && self
+                        .info
+                        .as_ref()
+                        .is_some_and(|info| info.state_slot.is_some());
+                self.switcher = Some(GameSwitcher::new(self.rect, self.res.clone(), save_state));
+            }
             MenuEntry::Settings => {
                 RetroArchCommand::Unpause.send().await?;
                 RetroArchCommand::MenuToggle.send().await?;
                 commands.send(Command::Exit).await?;
             }
             MenuEntry::Quit => {
+                let status = *self.res.get::<GameStatus>();
                 if self.info.is_some() {
                     RetroArchCommand::Quit.send().await?;
-                } else {
+                } else if status == GameStatus::Running {
                     tokio::process::Command::new("pkill")
                         .arg("retroarch")
                         .spawn()?
                         .wait()
                         .await?;
+                } else {
+                    // Without game info, there's no telling what is running, so leave it to alliumd
+                    self.quit_to_launcher(status, commands).await?;
+                    return Ok(true);
                 }
-                commands.send(Command::Exit).await?;
+                commands.send(Command::QuitGame).await?;
             }
         }
         Ok(true)
     }
 
+    /// Asks alliumd to terminate the main process and return to the launcher, clearing stale game
+    /// info so that the launcher starts instead of the game.
+    async fn quit_to_launcher(&self, status: GameStatus, commands: Sender<Command>) -> Result<()> {
+        if status == GameStatus::Stale {
+            GameInfo::delete()?;
+        }
+        commands.send(Command::TerminateMain).await?;
+        Ok(())
+    }
+
+    /// Writes the game screen from before the menu was opened to the screenshots folder. This is
+    /// the frame captured before the screen was dimmed, so the menu isn't in it.
+    fn save_screenshot(&self) -> Result<PathBuf> {
+        let screenshot = self
+            .screenshot
+            .as_ref()
+            .ok_or_else(|| anyhow!("the game screen wasn't captured"))?;
+
+        let _guard = write_activity::begin("screenshot");
+        fs::create_dir_all(ALLIUM_SCREENSHOTS_DIR.as_path())?;
+        let path = screenshot_path(
+            &ALLIUM_SCREENSHOTS_DIR,
+            &self.res.get::<GameInfo>().name,
+            Local::now(),
+        );
+        if let Err(e) = screenshot.save(&path) {
+            // Don't leave a truncated file behind, e.g. when the SD card is full
+            let _ = fs::remove_file(&path);
+            return Err(e.into());
+        }
+        Ok(path)
+    }
+
+    /// Saves the state to `slot`, asking first if that would overwrite a state.
+    async fn request_save(&mut self, slot: i8, commands: Sender<Command>) -> Result<()> {
+        let states = SaveStates::for_game_info(&self.res.get::<GameInfo>());
+        if states.is_occupied(slot) {
+            self.confirm = Some((
+                Confirm::Overwrite(slot),
+                self.overwrite_dialog(&states, slot),
+            ));
+        } else {
+            self.save_state(&states, slot, commands).await?;
+        }
+        Ok(())
+    }
+
+    /// Compares the state in `slot` with the current game screen.
+    fn overwrite_dialog(&self, states: &SaveStates, slot: i8) -> ConfirmDialog {
+        let locale = self.res.get::<Locale>();
+
+        let title = if slot == AUTO_SLOT {
+            locale.t("ingame-menu-overwrite-slot-auto")
+        } else {
+            let mut map = HashMap::new();
+            map.insert("slot".to_string(), slot.into());
+            locale.ta("ingame-menu-overwrite-slot", &map)
+        };
+
+        let existing = match states.slot_info(slot) {
+            Ok(Some(info)) => {
+                let mut map = HashMap::new();
+                map.insert(
+                    "time".to_string(),
+                    info.timestamp
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                        .into(),
+                );
+                locale.ta("ingame-menu-slot-saved", &map)
+            }
+            Ok(None) => locale.t("ingame-menu-slot-existing"),
+            Err(e) => {
+                warn!("failed to read slot metadata: {}", e);
+                locale.t("ingame-menu-slot-existing")
+            }
+        };
+        let thumbnail = states.thumbnail_path(slot);
+
+        let preview = self.screenshot.as_ref().and_then(|screenshot| {
+            screenshot
+                .save(ALLIUM_STATE_PREVIEW.as_path())
+                .map_err(|e| warn!("failed to write state preview: {}", e))
+                .ok()
+                .map(|_| ALLIUM_STATE_PREVIEW.clone())
+        });
+
+        ConfirmDialog::new(
+            self.rect,
+            self.res.clone(),
+            title,
+            vec![
+                (thumbnail.exists().then_some(thumbnail), existing),
+                (preview, locale.t("ingame-menu-slot-new")),
+            ],
+        )
+    }
+
+    /// Saves the state to `slot`, backing up the state it replaces if enabled, then closes the
+    /// menu.
+    async fn save_state(
+        &self,
+        states: &SaveStates,
+        slot: i8,
+        commands: Sender<Command>,
+    ) -> Result<()> {
+        let _guard = write_activity::begin("save state");
+        if self.backup_states {
+            if let Err(e) = states.backup(slot) {
+                warn!("failed to back up state: {}", e);
+            }
+        }
+
+        RetroArchCommand::SaveStateSlot(slot).send().await?;
+
+        let info = SlotInfo::now(&self.res.get::<Database>(), &self.res.get::<GameInfo>());
+        if let Err(e) = states.set_slot_info(slot, info) {
+            warn!("failed to write slot metadata: {}", e);
+        }
+
+        commands.send(Command::Exit).await?;
+        Ok(())
+    }
+
     fn update_state_slot_label(&mut self, state_slot: i8) {
         if state_slot == -1 {
             self.menu.set_right(
@@ -266,7 +607,7 @@ where
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -275,12 +616,26 @@ where
             self.dirty = false;
         }
 
-        if let Some(child) = self.child.as_mut() {
+        if let Some((_, confirm)) = self.confirm.as_mut() {
+            drawn |= confirm.should_draw() && confirm.draw(display, styles)?;
+        } else if let Some(notes) = self.notes.as_mut() {
+            drawn |= notes.should_draw() && notes.draw(display, styles)?;
+        } else if let Some(cheats) = self.cheats.as_mut() {
+            drawn |= cheats.should_draw() && cheats.draw(display, styles)?;
+        } else if let Some(switcher) = self.switcher.as_mut() {
+            drawn |= switcher.should_draw() && switcher.draw(display, styles)?;
+        } else if let Some(save_states) = self.save_states.as_mut() {
+            drawn |= save_states.should_draw() && save_states.draw(display, styles)?;
+        } else if let Some(disc_picker) = self.disc_picker.as_mut() {
+            drawn |= disc_picker.should_draw() && disc_picker.draw(display, styles)?;
+        } else if let Some(child) = self.child.as_mut() {
             drawn |= child.should_draw() && child.draw(display, styles)?;
         } else {
             drawn |= self.name.should_draw() && self.name.draw(display, styles)?;
             drawn |= self.battery_indicator.should_draw()
                 && self.battery_indicator.draw(display, styles)?;
+            drawn |=
+                self.write_indicator.should_draw() && self.write_indicator.draw(display, styles)?;
             drawn |= self.menu.should_draw() && self.menu.draw(display, styles)?;
             drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
         }
@@ -289,12 +644,25 @@ where
     }
 
     fn should_draw(&self) -> bool {
-        if let Some(child) = self.child.as_ref() {
+        if let Some((_, confirm)) = self.confirm.as_ref() {
+            self.dirty || confirm.should_draw()
+        } else if let Some(notes) = self.notes.as_ref() {
+            self.dirty || notes.should_draw()
+        } else if let Some(cheats) = self.cheats.as_ref() {
+            self.dirty || cheats.should_draw()
+        } else if let Some(switcher) = self.switcher.as_ref() {
+            self.dirty || switcher.should_draw()
+        } else if let Some(save_states) = self.save_states.as_ref() {
+            self.dirty || save_states.should_draw()
+        } else if let Some(disc_picker) = self.disc_picker.as_ref() {
+            self.dirty || disc_picker.should_draw()
+        } else if let Some(child) = self.child.as_ref() {
             self.dirty || child.should_draw()
         } else {
             self.dirty
                 || self.name.should_draw()
                 || self.battery_indicator.should_draw()
+                || self.write_indicator.should_draw()
                 || self.menu.should_draw()
                 || self.button_hints.should_draw()
         }
@@ -302,11 +670,24 @@ where
 
     fn set_should_draw(&mut self) {
         self.dirty = true;
-        if let Some(child) = self.child.as_mut() {
+        if let Some((_, confirm)) = self.confirm.as_mut() {
+            confirm.set_should_draw();
+        } else if let Some(notes) = self.notes.as_mut() {
+            notes.set_should_draw();
+        } else if let Some(cheats) = self.cheats.as_mut() {
+            cheats.set_should_draw();
+        } else if let Some(switcher) = self.switcher.as_mut() {
+            switcher.set_should_draw();
+        } else if let Some(save_states) = self.save_states.as_mut() {
+            save_states.set_should_draw();
+        } else if let Some(disc_picker) = self.disc_picker.as_mut() {
+            disc_picker.set_should_draw();
+        } else if let Some(child) = self.child.as_mut() {
             child.set_should_draw();
         } else {
             self.name.set_should_draw();
             self.battery_indicator.set_should_draw();
+            self.write_indicator.set_should_draw();
             self.menu.set_should_draw();
             self.button_hints.set_should_draw();
         }
@@ -318,6 +699,115 @@ where
         commands: Sender<Command>,
         bubble: &mut VecDeque<Command>,
     ) -> Result<bool> {
+        if let Some((reason, confirm)) = self.confirm.as_mut() {
+            let reason = *reason;
+            confirm
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?;
+            let mut confirmed = false;
+            let mut closed = false;
+            bubble.retain(|cmd| match cmd {
+                Command::ValueChanged(_, Value::Bool(true)) => {
+                    confirmed = true;
+                    false
+                }
+                Command::CloseView => {
+                    closed = true;
+                    false
+                }
+                _ => true,
+            });
+            if closed {
+                self.confirm = None;
+                self.set_should_draw();
+            }
+            if confirmed {
+                match reason {
+                    Confirm::Overwrite(slot) => {
+                        let states = SaveStates::for_game_info(&self.res.get::<GameInfo>());
+                        self.save_state(&states, slot, commands).await?;
+                    }
+                    Confirm::ClearStale => {
+                        self.quit_to_launcher(GameStatus::Stale, commands).await?;
+                    }
+                }
+            }
+            return Ok(true);
+        }
+
+        if let Some(notes) = self.notes.as_mut() {
+            notes.handle_key_event(event, commands, bubble).await?;
+            bubble.retain(|cmd| match cmd {
+                Command::CloseView => {
+                    self.notes = None;
+                    self.set_should_draw();
+                    false
+                }
+                _ => true,
+            });
+            return Ok(true);
+        }
+
+        if let Some(cheats) = self.cheats.as_mut() {
+            cheats.handle_key_event(event, commands, bubble).await?;
+            bubble.retain(|cmd| match cmd {
+                Command::CloseView => {
+                    self.cheats = None;
+                    self.set_should_draw();
+                    false
+                }
+                _ => true,
+            });
+            return Ok(true);
+        }
+
+        if let Some(switcher) = self.switcher.as_mut() {
+            switcher.handle_key_event(event, commands, bubble).await?;
+            bubble.retain(|cmd| match cmd {
+                Command::CloseView => {
+                    self.switcher = None;
+                    self.set_should_draw();
+                    false
+                }
+                _ => true,
+            });
+            return Ok(true);
+        }
+
+        if let Some(save_states) = self.save_states.as_mut() {
+            save_states
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?;
+            let save = save_states.take_save();
+            bubble.retain(|cmd| match cmd {
+                Command::CloseView => {
+                    self.save_states = None;
+                    self.set_should_draw();
+                    false
+                }
+                _ => true,
+            });
+            if let Some(slot) = save {
+                self.request_save(slot, commands).await?;
+            }
+            return Ok(true);
+        }
+
+        if let Some(disc_picker) = self.disc_picker.as_mut() {
+            disc_picker
+                .handle_key_event(event, commands, bubble)
+                .await?;
+            bubble.retain(|cmd| match cmd {
+                Command::CloseView => {
+                    self.disc_picker = None;
+                    self.set_should_draw();
+                    false
+                }
+                _ => true,
+            });
+            return Ok(true);
+        }
+
         if let Some(child) = self.child.as_mut() {
             if child
                 .handle_key_event(event, commands.clone(), bubble)
@@ -335,11 +825,11 @@ where
             }
         }
 
-        let selected = self.menu.selected();
+        let selected = self.entries[self.menu.selected()];
 
         // Handle disk slot selection
         if let Some(info) = self.info.as_mut() {
-            if info.max_disk_slots > 1 && selected == MenuEntry::Continue as usize {
+            if info.max_disk_slots > 1 && selected == MenuEntry::Continue {
                 match event {
                     KeyEvent::Pressed(Key::Left) | KeyEvent::Autorepeat(Key::Left) => {
                         info.disk_slot = info.disk_slot.saturating_sub(1);
@@ -381,7 +871,7 @@ where
 
             // Handle state slot selection
             if let Some(state_slot) = info.state_slot.as_mut() {
-                if selected == MenuEntry::Save as usize || selected == MenuEntry::Load as usize {
+                if matches!(selected, MenuEntry::Save | MenuEntry::Load) {
                     match event {
                         KeyEvent::Pressed(Key::Left) | KeyEvent::Autorepeat(Key::Left) => {
                             *state_slot = (*state_slot - 1).max(-1);
@@ -417,10 +907,10 @@ where
                 if consumed && prev != curr {
                     if let Some(info) = self.info.as_ref() {
                         if info.max_disk_slots > 1 {
-                            if prev == MenuEntry::Continue as usize {
+                            if self.entries[prev] == MenuEntry::Continue {
                                 self.menu.set_right(prev, Box::new(NullView));
                             }
-                            if curr == MenuEntry::Continue as usize {
+                            if self.entries[curr] == MenuEntry::Continue {
                                 let mut map = HashMap::new();
                                 map.insert("disk".to_string(), (info.disk_slot + 1).into());
                                 self.menu.set_right(
@@ -436,12 +926,10 @@ where
                         }
 
                         if let Some(state_slot) = info.state_slot {
-                            if prev == MenuEntry::Save as usize || prev == MenuEntry::Load as usize
-                            {
+                            if matches!(self.entries[prev], MenuEntry::Save | MenuEntry::Load) {
                                 self.menu.set_right(prev, Box::new(NullView));
                             }
-                            if curr == MenuEntry::Save as usize || curr == MenuEntry::Load as usize
-                            {
+                            if matches!(self.entries[curr], MenuEntry::Save | MenuEntry::Load) {
                                 self.update_state_slot_label(state_slot);
                             }
                         }
@@ -456,6 +944,7 @@ where
         vec![
             &self.name,
             &self.battery_indicator,
+            &self.write_indicator,
             &self.menu,
             &self.button_hints,
         ]
@@ -465,12 +954,13 @@ where
         vec![
             &mut self.name,
             &mut self.battery_indicator,
+            &mut self.write_indicator,
             &mut self.menu,
             &mut self.button_hints,
         ]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
@@ -480,52 +970,131 @@ where
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub enum MenuEntry {
-    Continue,
-    Save,
-    Load,
-    Reset,
-    Guide,
-    Settings,
-    Quit,
+/// Visible menu entries that are supported by the running core. Notes are only shown if the game
+/// has one, since they can't be edited from the menu, cheats if it has a cheat file, and discs if
+/// its playlist has more than one. Screenshots are named after the current game and switching
+/// games needs its info to quit it cleanly, so both need a running game.
+fn menu_entries(
+    settings: &IngameMenuSettings,
+    info: &Option<RetroArchInfo>,
+    has_note: bool,
+    has_cheats: bool,
+    has_discs: bool,
+    is_running: bool,
+) -> Vec<MenuEntry> {
+    settings
+        .visible_entries()
+        .into_iter()
+        .filter(|entry| match entry {
+            MenuEntry::Continue | MenuEntry::Guide | MenuEntry::Quit => true,
+            MenuEntry::Save | MenuEntry::Load | MenuEntry::SaveStates => {
+                matches!(
+                    info,
+                    Some(RetroArchInfo {
+                        state_slot: Some(_),
+                        ..
+                    })
+                )
+            }
+            MenuEntry::Notes => has_note,
+            MenuEntry::Cheats => has_cheats,
+            MenuEntry::ChangeDisc => has_discs,
+            MenuEntry::Screenshot | MenuEntry::Launcher | MenuEntry::SwitchGame => is_running,
+            MenuEntry::Reset | MenuEntry::Settings => info.is_some(),
+        })
+        .collect()
+}
+
+/// Where to save a screenshot of the game `name` taken at `time`.
+fn screenshot_path(dir: &Path, name: &str, time: DateTime<Local>) -> PathBuf {
+    // Names from the database may contain characters that can't be in a file name
+    let name = name.replace(['/', '\\'], "_");
+    dir.join(format!("{}-{}.png", name, time.format("%Y-%m-%d_%H-%M-%S")))
 }
 
-impl MenuEntry {
-    fn as_str(&self, locale: &Locale) -> String {
-        match self {
-            MenuEntry::Continue => locale.t("ingame-menu-continue"),
-            MenuEntry::Save => locale.t("ingame-menu-save"),
-            MenuEntry::Load => locale.t("ingame-menu-load"),
-            MenuEntry::Reset => locale.t("ingame-menu-reset"),
-            MenuEntry::Guide => locale.t("ingame-menu-guide"),
-            MenuEntry::Settings => locale.t("ingame-menu-settings"),
-            MenuEntry::Quit => locale.t("ingame-menu-quit"),
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const V0: &str = include_str!("../../testdata/state/menu-v0.json");
+    const V1: &str = include_str!("../../testdata/state/menu-v1.json");
+
+    #[test]
+    fn test_load_old_state() -> Result<()> {
+        for json in [V0, V1] {
+            let state: IngameMenuState = persisted::from_str(json)?;
+            assert!(state.is_text_reader_open);
         }
+        Ok(())
     }
 
-    fn entries(info: &Option<RetroArchInfo>) -> Vec<Self> {
-        match info {
-            Some(RetroArchInfo {
-                state_slot: Some(_),
-                ..
-            }) => vec![
+    #[test]
+    fn test_notes_only_with_note() {
+        let settings = IngameMenuSettings::new();
+        assert_eq!(
+            menu_entries(&settings, &None, false, false, false, false),
+            vec![MenuEntry::Continue, MenuEntry::Guide, MenuEntry::Quit]
+        );
+        assert_eq!(
+            menu_entries(&settings, &None, true, false, false, false),
+            vec![
                 MenuEntry::Continue,
-                MenuEntry::Save,
-                MenuEntry::Load,
-                MenuEntry::Reset,
                 MenuEntry::Guide,
-                MenuEntry::Settings,
-                MenuEntry::Quit,
-            ],
-            Some(_) => vec![
+                MenuEntry::Notes,
+                MenuEntry::Quit
+            ]
+        );
+    }
+
+    #[test]
+    fn test_switch_game_only_while_running() {
+        let settings = IngameMenuSettings::new();
+        assert_eq!(
+            menu_entries(&settings, &None, false, false, false, true),
+            vec![
                 MenuEntry::Continue,
-                MenuEntry::Reset,
                 MenuEntry::Guide,
-                MenuEntry::Settings,
-                MenuEntry::Quit,
-            ],
-            None => vec![MenuEntry::Continue, MenuEntry::Guide, MenuEntry::Quit],
-        }
+                MenuEntry::Screenshot,
+                MenuEntry::Launcher,
+                MenuEntry::SwitchGame,
+                MenuEntry::Quit
+            ]
+        );
+    }
+
+    #[test]
+    fn test_change_disc_only_with_discs() {
+        let settings = IngameMenuSettings::new();
+        assert_eq!(
+            menu_entries(&settings, &None, false, false, true, false),
+            vec![
+                MenuEntry::Continue,
+                MenuEntry::ChangeDisc,
+                MenuEntry::Guide,
+                MenuEntry::Quit
+            ]
+        );
+    }
+
+    #[test]
+    fn test_screenshot_path() {
+        let time = Local.with_ymd_and_hms(2024, 3, 1, 9, 5, 7).unwrap();
+        assert_eq!(
+            screenshot_path(Path::new("/mnt/SDCARD/Screenshots"), "Metroid", time),
+            Path::new("/mnt/SDCARD/Screenshots/Metroid-2024-03-01_09-05-07.png")
+        );
+        assert_eq!(
+            screenshot_path(Path::new("/mnt/SDCARD/Screenshots"), "AC/DC", time),
+            Path::new("/mnt/SDCARD/Screenshots/AC_DC-2024-03-01_09-05-07.png")
+        );
+    }
+
+    #[test]
+    fn test_missing_fields() -> Result<()> {
+        let state: IngameMenuState = persisted::from_str(r#"{"version":1,"state":{}}"#)?;
+        assert!(!state.is_text_reader_open);
+        Ok(())
     }
 }
@@ -1,2 +1,6 @@
+mod cheats;
+mod disc_picker;
+mod game_switcher;
 pub mod ingame_menu;
+mod save_states;
 mod text_reader;
@@ -0,0 +1,392 @@
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Local;
+use common::command::Command;
+use common::constants::IMAGE_WIDTH;
+use common::display::Display;
+use common::game_info::GameInfo;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::retroarch::RetroArchCommand;
+use common::save_state::SaveStates;
+use common::stylesheet::{Styles, StylesheetColor};
+use common::view::{ButtonHint, ButtonIcon, Image, ImageMode, Label, Row, ScrollList, View};
+use tokio::sync::mpsc::Sender;
+
+/// Number of slots listed, from slot 0.
+const SLOTS: i8 = 10;
+
+/// The state slots of the running game, with when each was saved and the screenshot RetroArch
+/// took of it. States are loaded and saved through RetroArch's network commands while the game is
+/// paused behind the menu. Loading happens here, while saving is left to the menu so that it can
+/// ask before overwriting a state.
+pub struct SaveStateManager {
+    rect: Rect,
+    states: SaveStates,
+    title: Label<String>,
+    list: ScrollList,
+    image: Image,
+    button_hints: Row<ButtonHint<String>>,
+    /// Hint for loading, taken out of the button hints while the highlighted slot is empty.
+    load_hint: Option<ButtonHint<String>>,
+    /// Slot picked to save to, until the menu takes it.
+    save: Option<i8>,
+    art_rect: Rect,
+    art_dirty: bool,
+    dirty: bool,
+}
+
+impl SaveStateManager {
+    /// Lists the slots with `selected`, the slot RetroArch is on, highlighted.
+    pub fn new(rect: Rect, res: Resources, selected: Option<i8>) -> Self {
+        let states = SaveStates::for_game_info(&res.get::<GameInfo>());
+        Self::with_states(rect, res, states, selected)
+    }
+
+    fn with_states(rect: Rect, res: Resources, states: SaveStates, selected: Option<i8>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let saved: HashMap<_, _> = states.saved_slots().into_iter().collect();
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let mut title = Label::new(
+            Point::new(x + 12, y + 8),
+            locale.t("ingame-menu-save-states"),
+            Alignment::Left,
+            None,
+        );
+        title.color(StylesheetColor::Highlight);
+
+        let top = y + 8 + styles.ui_font.size as i32 + 8;
+        let height = h - 8 - styles.ui_font.size - 8 - ButtonIcon::diameter(&styles) - 8 - 8;
+        let mut list = ScrollList::new(
+            Rect::new(x + 24, top, w - IMAGE_WIDTH - 24 - 24 - 24, height),
+            (0..SLOTS)
+                .map(|slot| {
+                    let mut map = HashMap::new();
+                    map.insert("slot".to_string(), slot.into());
+                    locale.ta("ingame-menu-slot", &map)
+                })
+                .collect(),
+            Alignment::Left,
+            styles.row_layout(),
+        );
+        for slot in 0..SLOTS {
+            let detail = match saved.get(&slot) {
+                Some(timestamp) => timestamp
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                None => {
+                    list.set_disabled(slot as usize, true);
+                    locale.t("ingame-menu-slot-empty")
+                }
+            };
+            list.set_detail(slot as usize, Some(detail));
+        }
+        if let Some(slot) = selected.filter(|slot| (0..SLOTS).contains(slot)) {
+            list.select(slot as usize);
+        }
+
+        let art_rect = Rect::new(
+            x + w as i32 - IMAGE_WIDTH as i32 - 24,
+            top,
+            IMAGE_WIDTH,
+            height,
+        );
+        let mut image = Image::empty(art_rect, ImageMode::Contain);
+        image.set_border_radius(12);
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("ingame-menu-load"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::X,
+                    locale.t("ingame-menu-save"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        let mut this = Self {
+            rect,
+            states,
+            title,
+            list,
+            image,
+            button_hints,
+            load_hint: None,
+            save: None,
+            art_rect,
+            art_dirty: true,
+            dirty: true,
+        };
+        this.update_hints();
+        this
+    }
+
+    fn selected_slot(&self) -> i8 {
+        self.list.selected() as i8
+    }
+
+    /// Whether the highlighted slot has a state to load.
+    fn can_load(&self) -> bool {
+        self.states.state_path(self.selected_slot()).exists()
+    }
+
+    /// Shows the hint for loading only if the highlighted slot has a state to load.
+    fn update_hints(&mut self) {
+        if self.can_load() {
+            if let Some(hint) = self.load_hint.take() {
+                self.button_hints.insert(0, hint);
+                self.dirty = true;
+            }
+        } else if self.load_hint.is_none() {
+            self.load_hint = self.button_hints.remove(0);
+            self.dirty = true;
+        }
+    }
+
+    /// The slot picked to save to, if one was picked since this was last called.
+    pub fn take_save(&mut self) -> Option<i8> {
+        self.save.take()
+    }
+}
+
+#[async_trait(?Send)]
+impl View for SaveStateManager {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.title.set_should_draw();
+            self.list.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.art_dirty = true;
+            self.dirty = false;
+            drawn = true;
+        }
+
+        drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        if self.art_dirty {
+            display.load(self.art_rect)?;
+            let thumbnail = self.states.thumbnail_path(self.selected_slot());
+            if thumbnail.exists() {
+                self.image.set_path(Some(thumbnail));
+                self.image.draw(display, styles)?;
+            }
+            self.art_dirty = false;
+            drawn = true;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self.art_dirty
+            || self.title.should_draw()
+            || self.list.should_draw()
+            || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                // Empty slots don't show the hint for loading
+                if self.can_load() {
+                    RetroArchCommand::LoadStateSlot(self.selected_slot())
+                        .send()
+                        .await?;
+                    commands.send(Command::Exit).await?;
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::X) => {
+                self.save = Some(self.selected_slot());
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            event => {
+                let prev = self.list.selected();
+                let consumed = self.list.handle_key_event(event, commands, bubble).await?;
+                if self.list.selected() != prev {
+                    self.art_dirty = true;
+                    self.update_hints();
+                }
+                Ok(consumed)
+            }
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.title, &self.list, &self.image, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![
+            &mut self.title,
+            &mut self.list,
+            &mut self.image,
+            &mut self.button_hints,
+        ]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.rect.x = point.x;
+        self.rect.y = point.y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    use common::stylesheet::StyleConfig;
+    use common::test_utils::temp_dir;
+    use type_map::TypeMap;
+
+    use super::*;
+
+    fn manager(dir: &Path, selected: Option<i8>) -> Result<SaveStateManager> {
+        env::set_var("ALLIUM_BASE_DIR", "../assets/root/.allium");
+        let mut res = TypeMap::new();
+        res.insert(Locale::new("en-US"));
+        res.insert(Styles::from_config(StyleConfig::default())?);
+        let states = SaveStates::new(dir, Path::new("Roms/GBA/Game.gba"));
+        Ok(SaveStateManager::with_states(
+            Rect::new(0, 0, 640, 480),
+            Resources::new(res),
+            states,
+            selected,
+        ))
+    }
+
+    fn hints(manager: &SaveStateManager) -> Vec<String> {
+        (0..manager.button_hints.len())
+            .filter_map(|i| manager.button_hints.get(i))
+            .map(|hint| hint.text().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_slots() -> Result<()> {
+        let dir = temp_dir("save-states-slots");
+        fs::write(dir.join("Game.state"), "")?;
+        fs::write(dir.join("Game.state3"), "")?;
+
+        let manager = manager(&dir, Some(3))?;
+        assert_eq!(manager.selected_slot(), 3);
+        for slot in 0..SLOTS as usize {
+            let saved = slot == 0 || slot == 3;
+            assert_eq!(manager.list.is_disabled(slot), !saved, "slot {}", slot);
+            assert_eq!(
+                manager.list.detail(slot) == Some("Empty"),
+                !saved,
+                "slot {}",
+                slot
+            );
+        }
+
+        // Slots RetroArch has that aren't listed aren't highlighted
+        let manager = self::manager(&dir, Some(SLOTS))?;
+        assert_eq!(manager.selected_slot(), 0);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keys() -> Result<()> {
+        let dir = temp_dir("save-states-keys");
+        fs::write(dir.join("Game.state1"), "")?;
+
+        let mut manager = manager(&dir, Some(0))?;
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let mut bubble = VecDeque::new();
+
+        // Empty slots can't be loaded, so they don't offer to
+        assert_eq!(hints(&manager), ["Save", "Back"]);
+        assert!(
+            manager
+                .handle_key_event(KeyEvent::Pressed(Key::A), tx.clone(), &mut bubble)
+                .await?
+        );
+        assert!(rx.try_recv().is_err());
+
+        // Any slot can be saved to, leaving the saving to the menu
+        assert!(
+            manager
+                .handle_key_event(KeyEvent::Pressed(Key::X), tx.clone(), &mut bubble)
+                .await?
+        );
+        assert_eq!(manager.take_save(), Some(0));
+        assert_eq!(manager.take_save(), None);
+
+        manager
+            .handle_key_event(KeyEvent::Pressed(Key::Down), tx.clone(), &mut bubble)
+            .await?;
+        assert_eq!(manager.selected_slot(), 1);
+        assert_eq!(hints(&manager), ["Load", "Save", "Back"]);
+        manager
+            .handle_key_event(KeyEvent::Pressed(Key::A), tx.clone(), &mut bubble)
+            .await?;
+        assert!(matches!(rx.try_recv(), Ok(Command::Exit)));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -11,7 +11,7 @@ use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::{ButtonHint, Row, View};
 use common::view::{ButtonIcon, Keyboard};
 use embedded_graphics::prelude::{Dimensions, Size};
@@ -54,7 +54,7 @@ impl TextReader {
         let Rect { x, y, w, h } = rect;
 
         let locale = res.get::<Locale>();
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let button_hints = Row::new(
             Point::new(
@@ -104,7 +104,7 @@ impl TextReader {
             .ok();
     }
 
-    fn visible_text(&self, styles: &Stylesheet) -> Vec<&str> {
+    fn visible_text(&self, styles: &Styles) -> Vec<&str> {
         let line_count =
             (self.rect.h - 12 - 8 - ButtonIcon::diameter(styles) - 8) / styles.guide_font.size;
         let mut lines = Vec::with_capacity(line_count as usize);
@@ -127,10 +127,10 @@ impl TextReader {
         lines
     }
 
-    fn get_line(&self, styles: &Stylesheet, cursor: usize) -> &str {
+    fn get_line(&self, styles: &Styles, cursor: usize) -> &str {
         let line_width = self.rect.w - 24 - 24;
-        let text_style = FontTextStyleBuilder::new(styles.guide_font.font())
-            .font_fallback(styles.cjk_font.font())
+        let text_style = FontTextStyleBuilder::new(styles.guide_font())
+            .font_fallback(styles.cjk_font())
             .font_size(styles.guide_font.size)
             .background_color(styles.background_color)
             .text_color(styles.foreground_color)
@@ -265,7 +265,7 @@ impl TextReader {
     }
 
     fn move_back_lines(&mut self, lines: usize) {
-        let styles = self.res.get::<Stylesheet>();
+        let styles = self.res.get::<Styles>();
 
         // Keep moving back until we've moved back the requested number of lines
         let mut cursor;
@@ -311,7 +311,7 @@ impl TextReader {
     }
 
     fn move_forward_lines(&mut self, lines: usize) {
-        let styles = &self.res.get::<Stylesheet>();
+        let styles = &self.res.get::<Styles>();
         for _ in 0..lines {
             if self.cursor > self.text.len() {
                 self.cursor = self.text.rfind('\n').map(|i| i + 1).unwrap_or_default();
@@ -347,7 +347,7 @@ impl View for TextReader {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -364,8 +364,8 @@ impl View for TextReader {
             .into_styled(PrimitiveStyle::with_fill(styles.background_color))
             .draw(display)?;
 
-            let text_style = FontTextStyleBuilder::new(styles.guide_font.font())
-                .font_fallback(styles.cjk_font.font())
+            let text_style = FontTextStyleBuilder::new(styles.guide_font())
+                .font_fallback(styles.cjk_font())
                 .font_size(styles.guide_font.size)
                 .background_color(styles.background_color)
                 .text_color(styles.foreground_color)
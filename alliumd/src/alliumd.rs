@@ -1,27 +1,65 @@
+use std::cell::Cell;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
-use std::time::Instant;
+use std::mem;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
 use common::battery::Battery;
 use common::constants::{
-    ALLIUMD_STATE, ALLIUM_GAME_INFO, ALLIUM_MENU, ALLIUM_SD_ROOT, ALLIUM_VERSION,
-    BATTERY_SHUTDOWN_THRESHOLD, BATTERY_UPDATE_INTERVAL, LONG_PRESS_DURATION,
+    ALLIUMD_STATE, ALLIUM_GAME_INFO, ALLIUM_LAUNCH_ENV, ALLIUM_MAIN_STDERR, ALLIUM_MENU,
+    ALLIUM_REMOTE_PORT, ALLIUM_SCREENSHOTS_DIR, ALLIUM_TOAST_ENV, ALLIUM_VERSION,
+    AUDIO_OUTPUT_CHECK_INTERVAL, BATTERY_SHUTDOWN_THRESHOLD, BATTERY_UPDATE_INTERVAL,
+    DATABASE_BUSY_TIMEOUT, LONG_PRESS_DURATION, MAINTENANCE_CHECK_INTERVAL,
+    POWER_OFF_WRITE_TIMEOUT, REMOTE_CHECK_INTERVAL, SPLASH_TIMEOUT, SWITCH_SAVE_TIMEOUT,
+    TERMINATE_GRACE_PERIOD, THEME_SCHEDULE_CHECK_INTERVAL, VOLUME_RAMP_INTERVAL,
 };
+use common::diagnostics::{self, Budget, SelfTest};
 use common::display::settings::DisplaySettings;
+use common::emergency_exit::EmergencyExitSettings;
+use common::ingame_menu::IngameMenuSettings;
+use common::launch_failure::{LaunchFailure, ALLIUM_LAUNCH_FAILURE_ENV};
+use common::led::LedPattern;
+use common::library_export::export_library;
 use common::locale::{Locale, LocaleSettings};
+use common::maintenance::{self, MaintenanceReport, MaintenanceSettings, TaskOutcome};
+use common::notification::{Notification, NotificationQueue, Severity, ALLIUM_NOTIFICATIONS_ENV};
+use common::persisted::{self, Versioned};
+use common::profile::{Profile, Profiles};
+use common::quit_prompt::{clear_quit_prompt, draw_quit_prompt};
+use common::remote_token;
 use common::retroarch::RetroArchCommand;
-use common::wifi::WiFiSettings;
+use common::save_state::{SaveStates, SlotInfo, AUTO_SLOT};
+use common::splash::{draw_splash, ALLIUMD_PID_ENV};
+use common::stylesheet::Styles;
+use common::theme_schedule::{ThemeChanged, ThemePeriod, ThemeSchedule};
+use common::view::WriteIndicator;
+use common::volume::{volume_to_raw, AudioOutput, VolumeRamp, VolumeSettings, VolumeSource};
+use common::wifi::{self, WiFiSettings};
+use common::write_activity;
 use enum_map::EnumMap;
 use log::{debug, error, info, trace, warn};
 use serde::{Deserialize, Serialize};
 use tokio::process::{Child, Command};
 
 use common::database::Database;
-use common::game_info::GameInfo;
-use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::game_info::{
+    GameInfo, GameStatus, SwitchRequest, ALLIUM_MAIN_PID_ENV, MENU_EXIT_QUIT_GAME,
+    MENU_EXIT_SUSPEND_GAME, MENU_EXIT_SWITCH_GAME, MENU_EXIT_TERMINATE_MAIN,
+};
+use common::platform::{self, DefaultPlatform, Key, KeyEvent, Platform};
+
+use crate::headless::{self, Headless, Input};
+use crate::led::{Led, LedSettings};
+use crate::lid::{Lid, LidTarget};
+use crate::maintenance::{charging_stopped, Interrupt, LocalClock, Maintenance};
+use crate::quick_quit::{QuickQuit, QuickQuitAction, QuickQuitSettings};
+use crate::remote::{self, LaunchOutcome, RemoteServer, RemoteTarget, Status, StatusGame};
+use crate::switch::{self, SwitchTarget};
 
 #[cfg(unix)]
 use {
@@ -30,11 +68,25 @@ use {
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AlliumDState {
-    #[serde(default = "Utc::now")]
     time: DateTime<Utc>,
     volume: i32,
     brightness: u8,
+    led: LedSettings,
+    /// Notifications for the launcher to show when it next starts.
+    notifications: NotificationQueue,
+    quick_quit: QuickQuitSettings,
+}
+
+impl Default for AlliumDState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Versioned for AlliumDState {
+    const VERSION: u32 = 1;
 }
 
 #[derive(Debug)]
@@ -42,12 +94,43 @@ pub struct AlliumD<P: Platform> {
     platform: P,
     main: Child,
     menu: Option<Child>,
+    /// Game suspended in the background while the launcher is in the foreground.
+    background: Option<Child>,
+    ingame: IngameCache,
     keys: EnumMap<Key, bool>,
     is_menu_pressed_alone: bool,
     pressed_menu: Instant,
     is_terminating: bool,
+    /// Whether the game was asked to quit, so that it exiting right after launching isn't taken
+    /// for a failed launch.
+    quit_requested: bool,
     state: AlliumDState,
     locale: Locale,
+    splash_deadline: Option<tokio::time::Instant>,
+    /// Whether the main process has drawn since boot. Until then, diagnostics can be run with a
+    /// key chord.
+    is_main_ready: bool,
+    emergency_exit_deadline: Option<tokio::time::Instant>,
+    quick_quit: QuickQuit,
+    /// Display the quick quit confirmation was drawn on, holding what was on screen under it.
+    quick_quit_display: Option<P::Display>,
+    maintenance: Maintenance<LocalClock>,
+    volume_settings: VolumeSettings,
+    volume_ramp: VolumeRamp,
+    volume_ramp_deadline: Option<tokio::time::Instant>,
+    audio_output: AudioOutput,
+    led: Led,
+    /// Pattern the LED was last set to.
+    led_pattern: Option<LedPattern>,
+    led_deadline: Option<tokio::time::Instant>,
+    remote: RemoteServer,
+    lid: Lid,
+    /// Whether the theme schedule last said to use the light or dark colors.
+    theme_period: Option<ThemePeriod>,
+    /// Set when running without a device, see `headless`.
+    headless: Option<Headless>,
+    /// Input injected while running headless.
+    input: Option<tokio::sync::mpsc::Receiver<Input>>,
 }
 
 impl AlliumDState {
@@ -56,6 +139,9 @@ impl AlliumDState {
             time: Utc::now(),
             volume: 0,
             brightness: 50,
+            led: LedSettings::default(),
+            notifications: NotificationQueue::new(),
+            quick_quit: QuickQuitSettings::default(),
         }
     }
 
@@ -63,7 +149,7 @@ impl AlliumDState {
         if ALLIUMD_STATE.exists() {
             debug!("found state, loading from file");
             if let Ok(json) = fs::read_to_string(ALLIUMD_STATE.as_path()) {
-                if let Ok(this) = serde_json::from_str::<AlliumDState>(&json) {
+                if let Ok(this) = persisted::from_str::<AlliumDState>(&json) {
                     if Utc::now() < this.time {
                         info!(
                             "RTC is not working, advancing time to {}",
@@ -91,53 +177,185 @@ impl AlliumDState {
     }
 
     fn save(&self) -> Result<()> {
-        let json = serde_json::to_string(self).unwrap();
+        let json = persisted::to_string(self)?;
         File::create(ALLIUMD_STATE.as_path())?.write_all(json.as_bytes())?;
         Ok(())
     }
 }
 
-fn spawn_main() -> Result<Child> {
-    #[cfg(feature = "miyoo")]
-    return Ok(match GameInfo::load()? {
+fn spawn_main(state: &mut AlliumDState, headless: Option<&Headless>) -> Result<Child> {
+    let child = main_command(state, headless)?.spawn()?;
+    if let Some(mut game_info) = GameInfo::load()? {
+        game_info.pid = child.id();
+        game_info.save()?;
+    }
+    Ok(child)
+}
+
+/// Command to resume the current game, or to start the launcher if there is none.
+fn main_command(state: &mut AlliumDState, headless: Option<&Headless>) -> Result<Command> {
+    // The simulator always starts the launcher
+    let game_info = if cfg!(feature = "miyoo") || headless.is_some() {
+        GameInfo::load()?
+    } else {
+        None
+    };
+    let mut command = match game_info {
         Some(mut game_info) => {
             debug!("found game info, resuming game");
-            game_info.start_time = Utc::now();
+            game_info.reset_session();
+            // The game continues from its auto state, not the state it was launched from
+            game_info.state_slot = None;
             game_info.save()?;
             game_info.command().into()
         }
         None => {
             debug!("no game info found, launching launcher");
-            use common::constants::ALLIUM_LAUNCHER;
-            Command::new(ALLIUM_LAUNCHER.as_path())
+            launcher_command(headless)
         }
+    };
+
+    command.env(ALLIUMD_PID_ENV, std::process::id().to_string());
+
+    // Shown by the launcher and ignored by games. They are only acknowledged by the launcher
+    // once it's ready, so a game started instead gets them again the next time.
+    let now = Utc::now();
+    if let Err(e) = state.notifications.collect(now) {
+        warn!("failed to collect notifications: {}", e);
+    }
+    let notifications = state.notifications.replay(now);
+    if !notifications.is_empty() {
+        debug!("passing on {} notifications", notifications.len());
+        command.env(
+            ALLIUM_NOTIFICATIONS_ENV,
+            serde_json::to_string(&notifications)?,
+        );
+        if let Err(e) = state.save() {
+            warn!("failed to save notifications: {}", e);
+        }
+    }
+
+    // Kept to explain why a game failed to launch
+    match File::create(ALLIUM_MAIN_STDERR.as_path()) {
+        Ok(file) => {
+            command.stderr(file);
+        }
+        Err(e) => warn!("failed to create main process output file: {}", e),
+    }
+
+    Ok(command)
+}
+
+fn launcher_command(headless: Option<&Headless>) -> Command {
+    if let Some(headless) = headless {
+        return headless.launcher_command();
+    }
+
+    #[cfg(feature = "miyoo")]
+    {
+        use common::constants::ALLIUM_LAUNCHER;
+        Command::new(ALLIUM_LAUNCHER.as_path())
     }
-    .spawn()?);
 
     #[cfg(not(feature = "miyoo"))]
-    return Ok(Command::new("/bin/sh")
-        .arg("-c")
-        .arg("make simulator-launcher")
-        .spawn()?);
+    {
+        let mut command = Command::new("/bin/sh");
+        command.arg("-c").arg("make simulator-launcher");
+        command
+    }
+}
+
+/// Draws the boot splash, returning whether it is now on screen.
+fn show_splash(mut display: <DefaultPlatform as Platform>::Display) -> bool {
+    if !DisplaySettings::load().map_or(true, |s| s.boot_splash) {
+        debug!("boot splash is disabled");
+        return false;
+    }
+
+    let result = Styles::load().and_then(|styles| draw_splash(&mut display, &styles));
+    if let Err(e) = result {
+        error!("failed to draw boot splash: {}", e);
+        return false;
+    }
+    true
 }
 
 impl AlliumD<DefaultPlatform> {
     pub fn new() -> Result<AlliumD<DefaultPlatform>> {
-        let platform = DefaultPlatform::new()?;
-        let state = AlliumDState::load()?;
-        let main = spawn_main()?;
+        let headless = Headless::from_env()?;
+        if headless.is_some() {
+            info!("running headless");
+        }
+        let input = match &headless {
+            Some(headless) => headless.read_input()?,
+            None => None,
+        };
+
+        // Input is initialized along with the platform, so drawing the splash doesn't delay it
+        let mut platform = DefaultPlatform::new()?;
+        // The launcher isn't started until the display is available, so that it doesn't race the
+        // boot process for it
+        let display = platform::open_display(&mut platform)?;
+        let splash_deadline =
+            show_splash(display).then(|| tokio::time::Instant::now() + SPLASH_TIMEOUT);
+        let mut state = AlliumDState::load()?;
+        let led = Led::new(state.led.clone());
+        let quick_quit = QuickQuit::new(state.quick_quit.clone());
+
+        // Ask for a profile again on every boot, unless we're resuming a game or the profile is
+        // restricted with no PIN to leave it
+        if !ALLIUM_GAME_INFO.exists() {
+            let profiles = Profiles::load()?;
+            if profiles.profiles.len() > 1 && !profiles.keeps_active() {
+                Profile::clear_active()?;
+            }
+        }
+
+        let main = spawn_main(&mut state, headless.as_ref())?;
         let locale = Locale::new(&LocaleSettings::load()?.lang);
+        let volume_settings = VolumeSettings::load()?;
+        let volume_ramp = VolumeRamp::new(
+            platform.get_volume()?.unwrap_or(0),
+            volume_settings.ramp_step,
+        );
+        let audio_output = platform.audio_output();
+        let maintenance = Maintenance::new(
+            LocalClock,
+            MaintenanceSettings::load()?,
+            maintenance::tasks(),
+        );
 
         Ok(AlliumD {
             platform,
             main,
             menu: None,
+            background: None,
+            ingame: IngameCache::default(),
             keys: EnumMap::default(),
             is_menu_pressed_alone: false,
             pressed_menu: Instant::now(),
             is_terminating: false,
+            quit_requested: false,
             state,
             locale,
+            splash_deadline,
+            is_main_ready: false,
+            emergency_exit_deadline: None,
+            quick_quit,
+            quick_quit_display: None,
+            maintenance,
+            volume_settings,
+            volume_ramp,
+            volume_ramp_deadline: None,
+            audio_output,
+            led,
+            led_pattern: None,
+            led_deadline: None,
+            remote: RemoteServer::new(),
+            lid: Lid::default(),
+            theme_period: current_theme_period(),
+            headless,
+            input,
         })
     }
 
@@ -145,7 +363,7 @@ impl AlliumD<DefaultPlatform> {
         info!("hello from Allium {}", ALLIUM_VERSION);
 
         info!("setting volume: {}", self.state.volume);
-        self.platform.set_volume(self.state.volume)?;
+        self.apply_volume(VolumeSource::Automatic)?;
 
         info!("setting brightness: {}", self.state.brightness);
         self.platform.set_brightness(self.state.brightness)?;
@@ -163,43 +381,194 @@ impl AlliumD<DefaultPlatform> {
         {
             let mut sigint = tokio::signal::unix::signal(SignalKind::interrupt())?;
             let mut sigterm = tokio::signal::unix::signal(SignalKind::terminate())?;
+            let mut sigusr1 = tokio::signal::unix::signal(SignalKind::user_defined1())?;
+            let mut sigusr2 = tokio::signal::unix::signal(SignalKind::user_defined2())?;
+            let mut sighup = tokio::signal::unix::signal(SignalKind::hangup())?;
 
             let mut battery_interval = tokio::time::interval(BATTERY_UPDATE_INTERVAL);
             let mut battery = self.platform.battery()?;
+            let mut maintenance_interval = tokio::time::interval(MAINTENANCE_CHECK_INTERVAL);
+            let mut audio_output_interval = tokio::time::interval(AUDIO_OUTPUT_CHECK_INTERVAL);
+            let mut remote_interval = tokio::time::interval(REMOTE_CHECK_INTERVAL);
+            let mut theme_interval = tokio::time::interval(THEME_SCHEDULE_CHECK_INTERVAL);
 
             loop {
+                if self.is_terminating && self.headless.is_some() {
+                    info!("stopped headless");
+                    return Ok(());
+                }
+
                 if let Some(menu) = self.menu.as_mut() {
-                    if menu.try_wait()?.is_some() {
-                        info!("menu process terminated, resuming game");
+                    if let Some(status) = menu.try_wait()? {
                         self.menu = None;
-                        RetroArchCommand::Unpause.send().await?;
+                        match status.code() {
+                            Some(MENU_EXIT_TERMINATE_MAIN) => {
+                                info!("menu asked to quit the game, terminating main process");
+                                self.quit_requested = true;
+                                terminate(&mut self.main, TERMINATE_GRACE_PERIOD).await?;
+                            }
+                            Some(MENU_EXIT_QUIT_GAME) => {
+                                info!("menu asked the game to quit, resuming it to let it exit");
+                                self.quit_requested = true;
+                                set_paused(false)?;
+                                RetroArchCommand::Unpause.send().await?;
+                            }
+                            Some(MENU_EXIT_SWITCH_GAME) => self.switch_game().await?,
+                            Some(MENU_EXIT_SUSPEND_GAME) => self.suspend_game()?,
+                            _ => {
+                                info!("menu process terminated, resuming game");
+                                set_paused(false)?;
+                                RetroArchCommand::Unpause.send().await?;
+                            }
+                        }
                     }
                 }
 
+                let splash_deadline = self
+                    .splash_deadline
+                    .unwrap_or_else(tokio::time::Instant::now);
+                let emergency_exit_deadline = self
+                    .emergency_exit_deadline
+                    .unwrap_or_else(tokio::time::Instant::now);
+                let quick_quit_deadline = self
+                    .quick_quit
+                    .deadline()
+                    .map(tokio::time::Instant::from_std)
+                    .unwrap_or_else(tokio::time::Instant::now);
+                let volume_ramp_deadline = self
+                    .volume_ramp_deadline
+                    .unwrap_or_else(tokio::time::Instant::now);
+                let led_deadline = self.led_deadline.unwrap_or_else(tokio::time::Instant::now);
+
                 tokio::select! {
                     key_event = self.platform.poll() => {
                         self.handle_key_event(key_event).await?;
                     }
-                    _ = self.main.wait() => {
-                        if !self.is_terminating {
-                            info!("main process terminated, recording play time");
-                            self.update_play_time()?;
+                    input = headless::recv_input(&mut self.input) => match input {
+                        Input::Key(key_event) => self.handle_key_event(key_event).await?,
+                        Input::Launch(path) => {
+                            let outcome = self.launch(&path).await?;
+                            info!("injected launch of {}: {:?}", path.display(), outcome);
+                        }
+                    },
+                    status = self.main.wait() => {
+                        let action = self.quick_quit.reset();
+                        self.apply_quick_quit(action).await?;
+                        if !self.is_terminating && self.background.is_some() {
+                            info!("main process terminated, resuming background game");
+                            self.resume_background().await?;
+                        } else if !self.is_terminating {
+                            self.reassert_volume()?;
+                            // A game that exits while the menu is open was quit from it, even if
+                            // the menu's exit hasn't been seen yet
+                            let quit_requested = self.quit_requested || self.menu.is_some();
+                            let failure = launch_failure(status?, quit_requested)?;
+                            self.quit_requested = false;
+                            if failure.is_none() {
+                                info!("main process terminated, recording play time");
+                                self.update_play_time()?;
+                            }
                             GameInfo::delete()?;
-                            self.main = spawn_main()?;
+                            self.main = match failure {
+                                Some(failure) => {
+                                    warn!("game failed to launch: {:?}", failure);
+                                    main_command(&mut self.state, self.headless.as_ref())?
+                                        .env(ALLIUM_LAUNCH_FAILURE_ENV, failure.to_json())
+                                        .spawn()?
+                                }
+                                None => spawn_main(&mut self.state, self.headless.as_ref())?,
+                            };
                         }
                     }
                     _ = sigint.recv() => self.handle_quit().await?,
                     _ = sigterm.recv() => self.handle_quit().await?,
+                    _ = sigusr2.recv() => self.resume_background().await?,
+                    _ = sighup.recv() => {
+                        info!("background task completed");
+                        self.led.notify(std::time::Instant::now());
+                        self.update_led();
+                    }
+                    _ = wait_background(&mut self.background) => {
+                        info!("background game terminated, recording play time");
+                        self.background = None;
+                        self.update_play_time()?;
+                        GameInfo::delete()?;
+                    }
+                    _ = sigusr1.recv() => {
+                        self.is_main_ready = true;
+                        if self.splash_deadline.take().is_some() {
+                            info!("main process is ready, boot splash is gone");
+                        }
+                        if self.state.notifications.acknowledge() {
+                            debug!("launcher received notifications");
+                            if let Err(e) = self.state.save() {
+                                warn!("failed to save notifications: {}", e);
+                            }
+                        }
+                    }
+                    _ = tokio::time::sleep_until(splash_deadline), if self.splash_deadline.is_some() => {
+                        warn!("main process did not draw in time, no longer waiting on boot splash");
+                        self.splash_deadline = None;
+                    }
+                    _ = tokio::time::sleep_until(emergency_exit_deadline), if self.emergency_exit_deadline.is_some() => {
+                        self.emergency_exit_deadline = None;
+                        self.handle_emergency_exit().await?;
+                    }
+                    _ = tokio::time::sleep_until(quick_quit_deadline), if self.quick_quit.deadline().is_some() => {
+                        let action = self.quick_quit.expire(Instant::now());
+                        self.apply_quick_quit(action).await?;
+                    }
+                    _ = tokio::time::sleep_until(volume_ramp_deadline), if self.volume_ramp_deadline.is_some() => {
+                        self.step_volume_ramp()?;
+                    }
+                    _ = tokio::time::sleep_until(led_deadline), if self.led_deadline.is_some() => {
+                        self.led_deadline = None;
+                        self.update_led();
+                    }
+                    _ = audio_output_interval.tick() => {
+                        let audio_output = self.platform.audio_output();
+                        if audio_output != self.audio_output {
+                            info!("audio output changed to {:?}", audio_output);
+                            self.audio_output = audio_output;
+                            self.apply_volume(VolumeSource::Automatic)?;
+                        }
+                    }
                     _ = battery_interval.tick() => {
                         trace!("updating battery");
                         if let Err(e) = battery.update() {
                             error!("failed to update battery: {}", e);
                         }
+                        self.led.set_battery(battery.percentage(), battery.charging());
+                        self.update_led();
                         if battery.percentage() <= BATTERY_SHUTDOWN_THRESHOLD && !battery.charging() {
                             warn!("battery is low, shutting down");
                             self.handle_quit().await?;
                         }
                     }
+                    _ = remote_interval.tick() => {
+                        self.sync_remote().await;
+                    }
+                    _ = theme_interval.tick() => {
+                        self.check_theme_schedule()?;
+                    }
+                    (command, reply) = self.remote.recv() => {
+                        let response = remote::dispatch(
+                            &mut RemoteControl { daemon: self, battery: &battery },
+                            command,
+                        )
+                        .await;
+                        // The client may have given up waiting
+                        let _ = reply.send(response);
+                    }
+                    _ = maintenance_interval.tick() => {
+                        if !self.is_ingame()
+                            && self.menu.is_none()
+                            && !self.lid.is_closed()
+                            && self.maintenance.is_due(&battery)
+                        {
+                            self.run_maintenance(&mut battery).await?;
+                        }
+                    }
                 }
             }
         }
@@ -222,6 +591,24 @@ impl AlliumD<DefaultPlatform> {
             self.is_ingame()
         );
 
+        if let KeyEvent::Lid(closed) = key_event {
+            // The lid stops and resumes the game itself
+            let action = self.quick_quit.reset();
+            self.apply_quick_quit(action).await?;
+            let mut lid = self.lid;
+            let result = lid
+                .set_closed(&mut LidControl { daemon: self }, closed)
+                .await;
+            self.lid = lid;
+            return result;
+        }
+        // Keys pressed by the closed lid itself shouldn't do anything
+        if self.lid.is_closed() {
+            return Ok(());
+        }
+
+        self.maintenance.input();
+
         // Handle menu key
         match key_event {
             KeyEvent::Pressed(Key::Menu) => {
@@ -231,7 +618,7 @@ impl AlliumD<DefaultPlatform> {
             KeyEvent::Pressed(_) => {
                 self.is_menu_pressed_alone = false;
             }
-            KeyEvent::Released(_) | KeyEvent::Autorepeat(_) => {}
+            KeyEvent::Released(_) | KeyEvent::Autorepeat(_) | KeyEvent::Lid(_) => {}
         }
 
         // Update self.keys
@@ -242,7 +629,41 @@ impl AlliumD<DefaultPlatform> {
             KeyEvent::Released(key) => {
                 self.keys[key] = false;
             }
-            KeyEvent::Autorepeat(_) => {}
+            KeyEvent::Autorepeat(_) | KeyEvent::Lid(_) => {}
+        }
+
+        self.update_emergency_exit();
+
+        // While the quick quit confirmation is shown, it takes every key but Power, which hides it
+        // so that powering off works as usual
+        let is_power = matches!(key_event, KeyEvent::Pressed(Key::Power));
+        let was_quick_quit_visible = self.quick_quit.is_visible();
+        let action = if was_quick_quit_visible && is_power {
+            self.quick_quit.reset()
+        } else {
+            let (main, has_menu, has_background, is_terminating) = (
+                self.game_pid(),
+                self.menu.is_some(),
+                self.background.is_some(),
+                self.is_terminating,
+            );
+            let ingame = &self.ingame;
+            self.quick_quit.handle_key_event(
+                key_event,
+                &self.keys,
+                || !has_menu && !has_background && !is_terminating && ingame.is_ingame(main),
+                Instant::now(),
+            )
+        };
+        self.apply_quick_quit(action).await?;
+        if (was_quick_quit_visible && !is_power) || action == QuickQuitAction::Quit {
+            return Ok(());
+        }
+
+        if !self.is_main_ready && is_diagnostics_chord(&self.keys) {
+            // The chord is released while diagnostics run
+            self.keys = EnumMap::default();
+            return self.run_diagnostics().await;
         }
 
         if self.keys[Key::Menu] {
@@ -261,7 +682,12 @@ impl AlliumD<DefaultPlatform> {
                                 signal(menu, Signal::SIGSTOP)?;
                             }
                         }
+                        set_paused(true)?;
                         Command::new("show-hotkeys").spawn()?.wait().await?;
+                        // The game stays paused if the menu is still open
+                        if self.menu.is_none() {
+                            set_paused(false)?;
+                        }
                         #[cfg(unix)]
                         {
                             signal(&self.main, Signal::SIGCONT)?;
@@ -286,21 +712,7 @@ impl AlliumD<DefaultPlatform> {
                     self.add_volume(1)?;
                 }
                 KeyEvent::Released(Key::Power) => {
-                    let game_info = GameInfo::load()?;
-                    let name = match game_info.as_ref() {
-                        Some(game_info) => game_info.name.as_str(),
-                        None => "Allium",
-                    };
-                    let file_name = format!(
-                        "{}-{}.png",
-                        chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"),
-                        name,
-                    );
-                    Command::new("screenshot")
-                        .arg(ALLIUM_SD_ROOT.join("Screenshots").join(file_name))
-                        .spawn()?
-                        .wait()
-                        .await?;
+                    take_screenshot().await?;
                 }
                 _ => {}
             }
@@ -318,19 +730,32 @@ impl AlliumD<DefaultPlatform> {
                     }
                 }
                 KeyEvent::Released(Key::Menu) => {
-                    if self.is_menu_pressed_alone {
-                        if self.is_ingame()
+                    // The ingame menu isn't opened over the launcher for a suspended game
+                    if self.is_menu_pressed_alone && self.background.is_none() {
+                        let game_info = GameInfo::load()?;
+                        let status = GameStatus::detect(game_info.as_ref(), self.game_pid());
+                        if status != GameStatus::NotRunning
                             && self
                                 .keys
                                 .iter()
                                 .all(|(k, pressed)| k == Key::Menu || !pressed)
                         {
-                            if let Some(game_info) = GameInfo::load()? {
-                                if let Some(menu) = &mut self.menu {
-                                    terminate(menu).await?;
-                                } else if game_info.has_menu {
-                                    self.menu = Some(Command::new(ALLIUM_MENU.as_path()).spawn()?);
+                            if let Some(menu) = &mut self.menu {
+                                terminate(menu, TERMINATE_GRACE_PERIOD).await?;
+                            } else if status != GameStatus::Running
+                                || game_info.is_some_and(|g| g.has_menu)
+                            {
+                                // Without up to date game info, the menu is still needed to quit
+                                set_paused(true)?;
+                                let mut menu = match &self.headless {
+                                    Some(headless) => headless.menu_command(),
+                                    None => Command::new(ALLIUM_MENU.as_path()),
+                                };
+                                menu.env(ALLIUMD_PID_ENV, std::process::id().to_string());
+                                if let Some(pid) = self.main.id() {
+                                    menu.env(ALLIUM_MAIN_PID_ENV, pid.to_string());
                                 }
+                                self.menu = Some(menu.spawn()?);
                             }
                         }
                         self.is_menu_pressed_alone = false;
@@ -343,6 +768,136 @@ impl AlliumD<DefaultPlatform> {
         Ok(())
     }
 
+    /// Starts or stops the companion API to match the Wi-Fi settings, and follows changes to the
+    /// IP address and token.
+    async fn sync_remote(&mut self) {
+        let addr = remote_addr().unwrap_or_else(|e| {
+            warn!("failed to load remote settings: {}", e);
+            None
+        });
+        let token = match addr {
+            Some(_) => match remote_token::token() {
+                Ok(token) => token,
+                Err(e) => {
+                    warn!("failed to load remote token: {}", e);
+                    return;
+                }
+            },
+            None => String::new(),
+        };
+        if let Err(e) = self.remote.sync(addr, &token).await {
+            warn!("failed to start remote server: {}", e);
+        }
+    }
+
+    /// Runs maintenance until it is done, a key is pressed, or the device is unplugged. The key
+    /// press that stopped maintenance is handled as usual afterwards.
+    #[cfg(unix)]
+    async fn run_maintenance(
+        &mut self,
+        battery: &mut <DefaultPlatform as Platform>::Battery,
+    ) -> Result<()> {
+        let platform = &mut self.platform;
+        let interrupt = async {
+            tokio::select! {
+                key_event = platform.poll() => Interrupt::Input(key_event),
+                _ = charging_stopped(battery, BATTERY_UPDATE_INTERVAL) => Interrupt::ChargingStopped,
+            }
+        };
+        let (report, interrupt) = self.maintenance.run(interrupt).await;
+
+        if let Err(e) = report.save() {
+            error!("failed to save maintenance report: {}", e);
+        }
+        self.notify_maintenance_failures(&report);
+        if report.completed() > 0 {
+            self.led.notify(std::time::Instant::now());
+            self.update_led();
+        }
+        if let Some(Interrupt::Input(key_event)) = interrupt {
+            self.handle_key_event(key_event).await?;
+        }
+        Ok(())
+    }
+
+    /// Creates a diagnostics bundle, for when the launcher doesn't start. B cancels it.
+    async fn run_diagnostics(&mut self) -> Result<()> {
+        info!("diagnostics chord held, running diagnostics");
+        let mut self_test = SelfTest::new(Budget::new(diagnostics::TIME_LIMIT));
+        let mut battery = self.platform.battery()?;
+        match self.platform.display() {
+            Ok(mut display) => self_test.run_device_checks(&mut display, &mut battery),
+            Err(e) => {
+                self_test.run("display", |_| Err(e));
+                self_test.run("battery", |_| diagnostics::check_battery(&mut battery));
+            }
+        }
+
+        Command::new("say")
+            .arg(self.locale.t("diagnostics-running"))
+            .spawn()?
+            .wait()
+            .await?;
+        let self_test = self_test.spawn();
+        while !self_test.is_finished() {
+            tokio::select! {
+                key_event = self.platform.poll() => {
+                    if key_event == KeyEvent::Pressed(Key::B) {
+                        info!("cancelling diagnostics");
+                        self_test.cancel();
+                    }
+                }
+                _ = tokio::time::sleep(std::time::Duration::from_millis(100)) => {}
+            }
+        }
+
+        let is_cancelled = self_test.is_cancelled();
+        let text = match self_test.join() {
+            Ok(path) => self.locale.ta(
+                "diagnostics-done",
+                &[("path".to_string(), path.display().to_string().into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            Err(_) if is_cancelled => self.locale.t("diagnostics-cancelled"),
+            Err(e) => {
+                error!("failed to create diagnostics: {}", e);
+                self.locale.t("diagnostics-failed")
+            }
+        };
+        Command::new("say").arg(text).spawn()?.wait().await?;
+        Ok(())
+    }
+
+    /// Queues a notification for the launcher to show when it next starts.
+    fn notify(&mut self, notification: Notification) {
+        self.state.notifications.push(notification, Utc::now());
+        if let Err(e) = self.state.save() {
+            warn!("failed to save notifications: {}", e);
+        }
+    }
+
+    fn notify_maintenance_failures(&mut self, report: &MaintenanceReport) {
+        for task in &report.tasks {
+            if let TaskOutcome::Failed(_) = task.outcome {
+                let text = self.locale.ta(
+                    "maintenance-task-failed",
+                    &[("name".to_string(), task.name.clone().into())]
+                        .into_iter()
+                        .collect(),
+                );
+                self.notify(
+                    Notification::new(
+                        format!("maintenance-{}", task.name),
+                        Severity::Warning,
+                        text,
+                    )
+                    .expires_in(Duration::days(1)),
+                );
+            }
+        }
+    }
+
     #[cfg(unix)]
     async fn handle_quit(&mut self) -> Result<()> {
         if self.is_terminating {
@@ -354,18 +909,31 @@ impl AlliumD<DefaultPlatform> {
         self.state.time = Utc::now();
         self.state.save()?;
 
-        if self.is_ingame() {
+        self.wait_for_writes().await;
+
+        if self.is_ingame() || self.background.is_some() {
             self.update_play_time()?;
 
             if let Some(menu) = self.menu.as_mut() {
-                terminate(menu).await?;
+                terminate(menu, TERMINATE_GRACE_PERIOD).await?;
             }
 
-            terminate(&mut self.main).await?;
+            terminate(&mut self.main, TERMINATE_GRACE_PERIOD).await?;
+
+            if let Some(mut game) = self.background.take() {
+                signal(&game, Signal::SIGCONT)?;
+                terminate(&mut game, TERMINATE_GRACE_PERIOD).await?;
+            }
         }
 
         self.is_terminating = true;
 
+        if self.headless.is_some() {
+            // There is no device to power off, only the launcher left to stop
+            terminate(&mut self.main, TERMINATE_GRACE_PERIOD).await?;
+            return Ok(());
+        }
+
         Command::new("show").arg("--darken").spawn()?.wait().await?;
         Command::new("say")
             .arg(self.locale.t("powering-off"))
@@ -379,9 +947,233 @@ impl AlliumD<DefaultPlatform> {
         Ok(())
     }
 
+    /// Waits a bounded time for writes to the SD card to finish, so that powering off doesn't
+    /// corrupt them. The launcher and menu show that writes are in progress, but in game nothing
+    /// does, so the indicator is drawn over the game.
+    async fn wait_for_writes(&mut self) {
+        if !write_activity::is_writing() {
+            return;
+        }
+
+        info!("waiting for writes to finish before powering off");
+        if self.is_ingame() && self.menu.is_none() {
+            let result = self.platform.display().and_then(|mut display| {
+                let styles = Styles::load()?;
+                WriteIndicator::draw_over(&mut display, &styles)
+            });
+            if let Err(e) = result {
+                warn!("failed to draw write indicator: {}", e);
+            }
+        }
+
+        if !write_activity::wait_idle(POWER_OFF_WRITE_TIMEOUT).await {
+            warn!("powering off with writes still in progress");
+        }
+    }
+
+    /// Stops the game and keeps it in the background, with the launcher started in its place. The
+    /// game stays paused, so the time spent in the launcher doesn't count as play time.
+    #[cfg(unix)]
+    fn suspend_game(&mut self) -> Result<()> {
+        if self.background.is_some() {
+            warn!("asked to suspend a game, but one is already in the background");
+            return Ok(());
+        }
+        info!("suspending game in the background, starting launcher");
+
+        signal(&self.main, Signal::SIGSTOP)?;
+        // Not `main_command`, which would resume the game from its game info
+        let launcher = launcher_command(self.headless.as_ref())
+            .env(ALLIUMD_PID_ENV, std::process::id().to_string())
+            .spawn()?;
+        self.background = Some(mem::replace(&mut self.main, launcher));
+
+        Ok(())
+    }
+
+    /// Brings the game suspended in the background back to the foreground, closing the launcher.
+    #[cfg(unix)]
+    async fn resume_background(&mut self) -> Result<()> {
+        let Some(game) = self.background.take() else {
+            warn!("asked to resume a game, but none is in the background");
+            return Ok(());
+        };
+        info!("resuming background game");
+
+        terminate(&mut self.main, TERMINATE_GRACE_PERIOD).await?;
+        self.main = game;
+        signal(&self.main, Signal::SIGCONT)?;
+        set_paused(false)?;
+        RetroArchCommand::Unpause.send().await?;
+
+        Ok(())
+    }
+
+    /// Quits the current game and launches the one picked in the menu in its place.
+    async fn switch_game(&mut self) -> Result<()> {
+        let Some(request) = SwitchRequest::take()? else {
+            warn!("menu asked to switch games without saying which, resuming game");
+            set_paused(false)?;
+            RetroArchCommand::Unpause.send().await?;
+            return Ok(());
+        };
+        switch::switch_game(
+            &mut SwitchControl { daemon: self },
+            &request,
+            TERMINATE_GRACE_PERIOD,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Starts the emergency exit countdown when Menu and Power are the only keys held, and cancels
+    /// it as soon as that is no longer the case.
+    fn update_emergency_exit(&mut self) {
+        if !is_emergency_exit_chord(&self.keys) {
+            self.emergency_exit_deadline = None;
+            return;
+        }
+        if self.emergency_exit_deadline.is_some() {
+            return;
+        }
+
+        let settings = EmergencyExitSettings::load().unwrap_or_else(|e| {
+            warn!("failed to load emergency exit settings: {}", e);
+            EmergencyExitSettings::default()
+        });
+        if let Some(hold) = settings.hold_duration() {
+            debug!("emergency exit chord held, exiting in {:?}", hold);
+            self.emergency_exit_deadline = Some(tokio::time::Instant::now() + hold);
+        }
+    }
+
+    /// Acts on the quick quit chord: stops the game under the confirmation, takes the input from
+    /// it once the chord is released, and quits or resumes it once answered.
+    async fn apply_quick_quit(&mut self, action: QuickQuitAction) -> Result<()> {
+        match action {
+            QuickQuitAction::None => {}
+            QuickQuitAction::Show => {
+                info!("quick quit chord held, asking to quit the game");
+                signal(&self.main, Signal::SIGSTOP)?;
+                set_paused(true)?;
+                if let Err(e) = self.draw_quick_quit() {
+                    warn!("failed to draw quick quit confirmation: {}", e);
+                    self.quick_quit.reset();
+                    self.dismiss_quick_quit()?;
+                }
+            }
+            QuickQuitAction::Grab => self.platform.grab_input(true)?,
+            QuickQuitAction::Dismiss => {
+                info!("quick quit dismissed, resuming game");
+                self.dismiss_quick_quit()?;
+            }
+            QuickQuitAction::Quit => {
+                info!("quick quitting game, terminating main process");
+                self.hide_quick_quit()?;
+                let game_info = GameInfo::load()?;
+                // Recorded while the game is still paused, so that the confirmation isn't counted
+                self.update_play_time()?;
+                // A stopped process can't act on SIGTERM
+                signal(&self.main, Signal::SIGCONT)?;
+                if terminate(&mut self.main, TERMINATE_GRACE_PERIOD).await? {
+                    warn!("main process did not exit in time, killed");
+                    if let Some(game_info) = game_info.as_ref() {
+                        add_unclean_exit(&game_info.path);
+                    }
+                }
+                GameInfo::delete()?;
+                // Replaced before the event loop sees the game exit, so it isn't taken for a
+                // launch failure
+                self.main = spawn_main(&mut self.state, self.headless.as_ref())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_quick_quit(&mut self) -> Result<()> {
+        let name = GameInfo::load()?.map_or_else(String::new, |game_info| game_info.name);
+        let title = self.locale.ta(
+            "quick-quit-confirm",
+            &[("name".to_string(), name.into())].into_iter().collect(),
+        );
+        let mut display = self.platform.display()?;
+        let styles = Styles::load()?;
+        draw_quit_prompt(
+            &mut display,
+            &styles,
+            title,
+            self.locale.t("quick-quit-yes"),
+            self.locale.t("quick-quit-no"),
+        )?;
+        self.quick_quit_display = Some(display);
+        Ok(())
+    }
+
+    /// Restores what was on screen under the confirmation, and gives the input back.
+    fn hide_quick_quit(&mut self) -> Result<()> {
+        if let Some(mut display) = self.quick_quit_display.take() {
+            clear_quit_prompt(&mut display)?;
+        }
+        self.platform.grab_input(false)
+    }
+
+    fn dismiss_quick_quit(&mut self) -> Result<()> {
+        self.hide_quick_quit()?;
+        signal(&self.main, Signal::SIGCONT)?;
+        set_paused(false)
+    }
+
+    /// Force quits the main process, e.g. a frozen core that grabbed the input device, and
+    /// returns to the launcher.
+    #[cfg(unix)]
+    async fn handle_emergency_exit(&mut self) -> Result<()> {
+        if self.is_terminating {
+            return Ok(());
+        }
+
+        let game_info = GameInfo::load()?;
+        warn!(
+            "emergency exit, force quitting {}",
+            game_info
+                .as_ref()
+                .map_or("main process", |g| g.name.as_str())
+        );
+
+        if let Some(game_info) = game_info.as_ref() {
+            self.update_play_time()?;
+            add_unclean_exit(&game_info.path);
+        }
+
+        if let Some(mut menu) = self.menu.take() {
+            terminate(&mut menu, TERMINATE_GRACE_PERIOD).await?;
+        }
+        if terminate(&mut self.main, TERMINATE_GRACE_PERIOD).await? {
+            warn!("main process did not exit in time, killed");
+        }
+        GameInfo::delete()?;
+        // The chord is still held, so don't treat its release as a screenshot
+        self.keys = EnumMap::default();
+
+        let toast = match game_info {
+            Some(game_info) => self.locale.ta(
+                "emergency-exit-game",
+                &[("name".to_string(), game_info.name.into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            None => self.locale.t("emergency-exit"),
+        };
+        self.main = main_command(&mut self.state, self.headless.as_ref())?
+            .env(ALLIUM_TOAST_ENV, toast)
+            .spawn()?;
+
+        Ok(())
+    }
+
     #[allow(unused)]
     fn update_play_time(&self) -> Result<()> {
-        if !self.is_ingame() {
+        // The game has usually exited by now, so only check that there is something to record
+        if !ALLIUM_GAME_INFO.exists() {
             return Ok(());
         }
 
@@ -401,13 +1193,125 @@ impl AlliumD<DefaultPlatform> {
     }
 
     fn is_ingame(&self) -> bool {
-        Path::new(&*ALLIUM_GAME_INFO).exists()
+        // A game suspended in the background has the launcher in front of it
+        self.background.is_none() && self.ingame.is_ingame(self.game_pid())
+    }
+
+    /// The main process, to tell whether it is a game when there is no game info for it. The
+    /// launcher's headless stand-in can't be told apart from a game, so only game info counts.
+    fn game_pid(&self) -> Option<u32> {
+        match self.headless {
+            Some(_) => None,
+            None => self.main.id(),
+        }
+    }
+
+    /// Restarts the launcher to launch the game at `path`, unless a game is already running.
+    async fn launch(&mut self, path: &Path) -> Result<LaunchOutcome> {
+        if self.is_terminating
+            || self.is_ingame()
+            || self.menu.is_some()
+            || self.background.is_some()
+        {
+            return Ok(LaunchOutcome::Busy);
+        }
+
+        info!("launch of {}, restarting launcher", path.display());
+        terminate(&mut self.main, TERMINATE_GRACE_PERIOD).await?;
+        self.main = main_command(&mut self.state, self.headless.as_ref())?
+            .env(ALLIUM_LAUNCH_ENV, path)
+            .spawn()?;
+        Ok(LaunchOutcome::Launching)
     }
 
     fn add_volume(&mut self, add: i32) -> Result<()> {
         info!("adding volume: {}", add);
-        self.state.volume = (self.state.volume + add).clamp(0, 20);
-        self.platform.set_volume(self.state.volume)?;
+        self.state.volume = self
+            .volume_settings
+            .limit(self.state.volume + add, self.audio_output);
+        self.apply_volume(VolumeSource::User)
+    }
+
+    /// Moves the output towards the volume level, limited for the current audio output.
+    /// Automatic increases are ramped up over the following ticks.
+    fn apply_volume(&mut self, source: VolumeSource) -> Result<()> {
+        let target = self
+            .volume_settings
+            .limit(self.state.volume, self.audio_output);
+        let volume = self.volume_ramp.set_target(target, source);
+        self.platform.set_volume(volume)?;
+        self.volume_ramp_deadline = self
+            .volume_ramp
+            .is_ramping()
+            .then(|| tokio::time::Instant::now() + VOLUME_RAMP_INTERVAL);
+        Ok(())
+    }
+
+    /// Tells the launcher and menu to switch between the day and night themes when the theme
+    /// schedule crosses a boundary. A running game isn't told; the menu and launcher pick the
+    /// change up when they're next started.
+    #[cfg(unix)]
+    fn check_theme_schedule(&mut self) -> Result<()> {
+        let period = current_theme_period();
+        if period == self.theme_period {
+            return Ok(());
+        }
+        self.theme_period = period;
+        let Some(period) = period else {
+            return Ok(());
+        };
+        info!("theme schedule switched to {:?}", period);
+        ThemeChanged { period }.post()?;
+        if !self.is_ingame() {
+            signal(&self.main, Signal::SIGUSR1)?;
+        }
+        if let Some(menu) = self.menu.as_ref() {
+            signal(menu, Signal::SIGUSR1)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the LED to the pattern it should be showing now, if that changed.
+    fn update_led(&mut self) {
+        let now = std::time::Instant::now();
+        let pattern = self.led.pattern(now);
+        if self.led_pattern != Some(pattern) {
+            debug!("setting LED: {:?}", pattern);
+            // A missing LED shouldn't take down the daemon, so only log failures
+            if let Err(e) = self.platform.set_led(pattern) {
+                warn!("failed to set LED: {}", e);
+            }
+            self.led_pattern = Some(pattern);
+        }
+        self.led_deadline = self.led.deadline(now).map(tokio::time::Instant::from_std);
+    }
+
+    fn step_volume_ramp(&mut self) -> Result<()> {
+        if let Some(volume) = self.volume_ramp.tick() {
+            trace!("ramping volume: {}", volume);
+            self.platform.set_volume(volume)?;
+        }
+        self.volume_ramp_deadline = self
+            .volume_ramp
+            .is_ramping()
+            .then(|| tokio::time::Instant::now() + VOLUME_RAMP_INTERVAL);
+        Ok(())
+    }
+
+    /// Games may change the mixer level themselves, so set it back to our volume once they exit.
+    fn reassert_volume(&mut self) -> Result<()> {
+        let Some(volume) = self.platform.get_volume()? else {
+            return Ok(());
+        };
+        if volume_to_raw(volume) != volume_to_raw(self.volume_ramp.current()) {
+            warn!(
+                "mixer volume was changed from {} to {}, restoring",
+                self.volume_ramp.current(),
+                volume
+            );
+            self.volume_ramp.set_current(volume);
+            self.apply_volume(VolumeSource::Automatic)?;
+        }
         Ok(())
     }
 
@@ -419,19 +1323,392 @@ impl AlliumD<DefaultPlatform> {
     }
 }
 
+/// Address the companion API should listen on, if it's enabled and Wi-Fi is connected.
+fn remote_addr() -> Result<Option<SocketAddr>> {
+    if !DefaultPlatform::has_wifi() {
+        return Ok(None);
+    }
+    let settings = WiFiSettings::load()?;
+    if !settings.wifi || !settings.remote {
+        return Ok(None);
+    }
+    Ok(wifi::ip_address()
+        .and_then(|ip| ip.parse::<IpAddr>().ok())
+        .map(|ip| SocketAddr::new(ip, ALLIUM_REMOTE_PORT)))
+}
+
+/// Saves a screenshot to the SD card, named after the current game, and returns its path.
+async fn take_screenshot() -> Result<PathBuf> {
+    let game_info = GameInfo::load()?;
+    let name = match game_info.as_ref() {
+        Some(game_info) => game_info.name.as_str(),
+        None => "Allium",
+    };
+    let file_name = format!(
+        "{}-{}.png",
+        chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"),
+        name,
+    );
+    let path = ALLIUM_SCREENSHOTS_DIR.join(file_name);
+    Command::new("screenshot")
+        .arg(&path)
+        .spawn()?
+        .wait()
+        .await?;
+    Ok(path)
+}
+
+/// alliumd, as controlled through the companion API.
+struct RemoteControl<'a> {
+    daemon: &'a mut AlliumD<DefaultPlatform>,
+    battery: &'a <DefaultPlatform as Platform>::Battery,
+}
+
+#[async_trait(?Send)]
+impl RemoteTarget for RemoteControl<'_> {
+    fn games_dir(&self) -> Result<PathBuf> {
+        Ok(Profiles::load()?.active().games_dir())
+    }
+
+    fn library(&self) -> Result<Vec<u8>> {
+        let database = Database::new()?;
+        database.set_busy_timeout(DATABASE_BUSY_TIMEOUT)?;
+        let mut library = Vec::new();
+        export_library(&database, &self.games_dir()?, &mut library)?;
+        Ok(library)
+    }
+
+    fn status(&self) -> Result<Status> {
+        let game = if self.daemon.is_ingame() {
+            GameInfo::load()?.map(|game_info| StatusGame {
+                name: game_info.name,
+                path: game_info.path,
+            })
+        } else {
+            None
+        };
+        Ok(Status {
+            game,
+            battery: self.battery.percentage(),
+            charging: self.battery.charging(),
+            volume: self.daemon.state.volume,
+            brightness: self.daemon.state.brightness,
+        })
+    }
+
+    async fn launch(&mut self, path: &Path) -> Result<LaunchOutcome> {
+        self.daemon.launch(path).await
+    }
+
+    fn set_volume(&mut self, volume: i32) -> Result<()> {
+        info!("remote volume: {}", volume);
+        let daemon = &mut *self.daemon;
+        daemon.state.volume = daemon.volume_settings.limit(volume, daemon.audio_output);
+        daemon.apply_volume(VolumeSource::User)
+    }
+
+    fn set_brightness(&mut self, brightness: u8) -> Result<()> {
+        info!("remote brightness: {}", brightness);
+        self.daemon.state.brightness = brightness.min(100);
+        self.daemon
+            .platform
+            .set_brightness(self.daemon.state.brightness)
+    }
+
+    async fn screenshot(&mut self) -> Result<Vec<u8>> {
+        let path = take_screenshot().await?;
+        match fs::read(&path) {
+            Ok(png) => Ok(png),
+            Err(e) => bail!("screenshot was not saved: {}", e),
+        }
+    }
+}
+
+/// alliumd, as suspended by closing the lid.
+struct LidControl<'a> {
+    daemon: &'a mut AlliumD<DefaultPlatform>,
+}
+
+#[async_trait(?Send)]
+impl LidTarget for LidControl<'_> {
+    async fn dismiss_menu(&mut self) -> Result<bool> {
+        let Some(mut menu) = self.daemon.menu.take() else {
+            return Ok(false);
+        };
+        info!("closing menu for the lid");
+        terminate(&mut menu, TERMINATE_GRACE_PERIOD).await?;
+        Ok(true)
+    }
+
+    fn set_stopped(&mut self, stopped: bool) -> Result<()> {
+        signal(
+            &self.daemon.main,
+            if stopped {
+                Signal::SIGSTOP
+            } else {
+                Signal::SIGCONT
+            },
+        )
+    }
+
+    fn set_paused(&mut self, paused: bool) -> Result<()> {
+        // A game suspended in the background stays paused under the launcher
+        if self.daemon.is_ingame() {
+            set_paused(paused)?;
+        }
+        Ok(())
+    }
+
+    fn set_backlight(&mut self, on: bool) -> Result<()> {
+        self.daemon.platform.set_backlight(on)
+    }
+
+    async fn unpause(&mut self) -> Result<()> {
+        RetroArchCommand::Unpause.send().await?;
+        Ok(())
+    }
+}
+
+/// alliumd, as it switches games for the in-game menu.
+struct SwitchControl<'a> {
+    daemon: &'a mut AlliumD<DefaultPlatform>,
+}
+
+#[async_trait(?Send)]
+impl SwitchTarget for SwitchControl<'_> {
+    fn main(&mut self) -> &mut Child {
+        &mut self.daemon.main
+    }
+
+    async fn save_state(&mut self) -> Result<()> {
+        let Some(game_info) = GameInfo::load()? else {
+            return Ok(());
+        };
+        let _guard = write_activity::begin("save state");
+        let states = SaveStates::for_game_info(&game_info);
+        if IngameMenuSettings::load()?.backup_states {
+            if let Err(e) = states.backup(AUTO_SLOT) {
+                warn!("failed to back up state: {}", e);
+            }
+        }
+
+        let state = states.state_path(AUTO_SLOT);
+        let before = file_stamp(&state);
+        RetroArchCommand::SaveStateSlot(AUTO_SLOT).send().await?;
+
+        // RetroArch saves in the background, and quitting it mid-write would lose the state, so
+        // wait until the file has changed and then stopped changing
+        let written = tokio::time::timeout(SWITCH_SAVE_TIMEOUT, async {
+            let mut last = before;
+            loop {
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                let stamp = file_stamp(&state);
+                if stamp != before && stamp == last {
+                    break;
+                }
+                last = stamp;
+            }
+        })
+        .await;
+        if written.is_err() {
+            bail!("state was not written in time");
+        }
+
+        let database = Database::new()?;
+        database.set_busy_timeout(DATABASE_BUSY_TIMEOUT)?;
+        states.set_slot_info(AUTO_SLOT, SlotInfo::now(&database, &game_info))?;
+        Ok(())
+    }
+
+    fn finish_game(&mut self, killed: bool) -> Result<()> {
+        if killed {
+            if let Some(game_info) = GameInfo::load()? {
+                add_unclean_exit(&game_info.path);
+            }
+        }
+        self.daemon.update_play_time()?;
+        GameInfo::delete()
+    }
+
+    fn launch(&mut self, path: &Path) -> Result<Child> {
+        Ok(
+            main_command(&mut self.daemon.state, self.daemon.headless.as_ref())?
+                .env(ALLIUM_LAUNCH_ENV, path)
+                .spawn()?,
+        )
+    }
+
+    async fn resume(&mut self) -> Result<()> {
+        set_paused(false)?;
+        RetroArchCommand::Unpause.send().await?;
+        Ok(())
+    }
+}
+
+/// When the file was last modified and how big it is, or `None` if it doesn't exist.
+fn file_stamp(path: &Path) -> Option<(std::time::SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Waits for the background game to exit, or forever if there is none.
+async fn wait_background(background: &mut Option<Child>) {
+    match background {
+        Some(child) => {
+            let _ = child.wait().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Whether Menu and Power are held together, and nothing else.
+fn is_emergency_exit_chord(keys: &EnumMap<Key, bool>) -> bool {
+    keys.iter()
+        .all(|(key, &pressed)| pressed == matches!(key, Key::Menu | Key::Power))
+}
+
+/// Whether Select and Start are held together, and nothing else.
+fn is_diagnostics_chord(keys: &EnumMap<Key, bool>) -> bool {
+    keys.iter()
+        .all(|(key, &pressed)| pressed == matches!(key, Key::Select | Key::Start))
+}
+
+/// Asks `child` to exit, and kills it if it is still running after `grace`. Returns whether it
+/// had to be killed.
 #[allow(clippy::needless_pass_by_ref_mut)]
-async fn terminate(child: &mut Child) -> Result<()> {
+pub(crate) async fn terminate(child: &mut Child, grace: std::time::Duration) -> Result<bool> {
     #[cfg(unix)]
     signal(child, Signal::SIGTERM)?;
     #[cfg(not(unix))]
     child.kill().await?;
 
-    if let Err(_e) = tokio::time::timeout(std::time::Duration::from_secs(1), child.wait()).await {
-        signal(child, Signal::SIGKILL)?;
+    if tokio::time::timeout(grace, child.wait()).await.is_ok() {
+        return Ok(false);
+    }
+
+    signal(child, Signal::SIGKILL)?;
+    // A process stuck in the kernel can't be killed, so don't wait on it forever
+    if tokio::time::timeout(grace, child.wait()).await.is_err() {
+        warn!("process {:?} did not exit after SIGKILL", child.id());
+    }
+    Ok(true)
+}
+
+/// Checks whether the game that just exited failed to launch, given whether it was asked to quit.
+/// Nothing is checked if there was no game, e.g. when the launcher itself exited.
+fn launch_failure(
+    status: std::process::ExitStatus,
+    quit_requested: bool,
+) -> Result<Option<LaunchFailure>> {
+    let Some(game_info) = GameInfo::load()? else {
+        return Ok(None);
+    };
+    Ok(LaunchFailure::detect(
+        &game_info,
+        status,
+        game_info.play_time(),
+        quit_requested,
+        &ALLIUM_MAIN_STDERR,
+    ))
+}
+
+/// Records when the current game is paused or resumed, so that paused time isn't counted as play time.
+/// Resuming a game also moves it to the top of the recently played list.
+fn set_paused(paused: bool) -> Result<()> {
+    if let Some(mut game_info) = GameInfo::load()? {
+        if paused {
+            game_info.pause();
+        } else {
+            game_info.resume();
+            touch_last_played(&game_info.path);
+        }
+        game_info.save()?;
     }
     Ok(())
 }
 
+/// Bumps the game in the recents list. The launcher or menu may be holding the database, in which
+/// case this is skipped rather than blocking the event loop.
+fn touch_last_played(path: &Path) {
+    let result = Database::new().and_then(|database| {
+        database.set_busy_timeout(DATABASE_BUSY_TIMEOUT)?;
+        database.touch_last_played(path)
+    });
+    if let Err(e) = result {
+        warn!("failed to update last played: {}", e);
+    }
+}
+
+/// Whether a game is running in the main process with the given PID.
+fn is_ingame(main: Option<u32>) -> bool {
+    let game_info = GameInfo::load().unwrap_or_else(|e| {
+        warn!("failed to load game info: {}", e);
+        None
+    });
+    GameStatus::detect(game_info.as_ref(), main).is_ingame()
+}
+
+/// Whether a game is running, as of when the game info was last read. Game info is only written as
+/// games are launched and loaded, so it is read again once it has been modified or the main
+/// process has changed, rather than on every key event.
+#[derive(Debug, Default)]
+struct IngameCache(Cell<Option<(Option<u32>, SystemTime, bool)>>);
+
+impl IngameCache {
+    /// Whether a game is running in the main process with the given PID.
+    fn is_ingame(&self, main: Option<u32>) -> bool {
+        // Without game info there is nothing to read, only the main process to look at
+        let Ok(modified) = fs::metadata(ALLIUM_GAME_INFO.as_path()).and_then(|m| m.modified())
+        else {
+            self.0.set(None);
+            return is_ingame(main);
+        };
+        self.get_or_update(main, modified, || is_ingame(main))
+    }
+
+    fn get_or_update(
+        &self,
+        main: Option<u32>,
+        modified: SystemTime,
+        is_ingame: impl FnOnce() -> bool,
+    ) -> bool {
+        match self.0.get() {
+            Some((cached_main, cached_modified, ingame))
+                if cached_main == main && cached_modified == modified =>
+            {
+                ingame
+            }
+            _ => {
+                let ingame = is_ingame();
+                self.0.set(Some((main, modified, ingame)));
+                ingame
+            }
+        }
+    }
+}
+
+/// Records that the game had to be force quit.
+fn add_unclean_exit(path: &Path) {
+    let result = Database::new().and_then(|database| {
+        database.set_busy_timeout(DATABASE_BUSY_TIMEOUT)?;
+        database.add_unclean_exit(path)
+    });
+    if let Err(e) = result {
+        warn!("failed to record unclean exit: {}", e);
+    }
+}
+
+/// Returns whether the theme schedule says to use the light or dark colors now, if it's enabled.
+fn current_theme_period() -> Option<ThemePeriod> {
+    match ThemeSchedule::load() {
+        Ok(schedule) => schedule.current(),
+        Err(e) => {
+            warn!("failed to load theme schedule: {}", e);
+            None
+        }
+    }
+}
+
 #[cfg(unix)]
 fn signal(child: &Child, signal: Signal) -> Result<()> {
     if let Some(pid) = child.id() {
@@ -440,3 +1717,150 @@ fn signal(child: &Child, signal: Signal) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::io;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Stdio;
+    use std::time::Duration;
+
+    use common::retry::RetryPolicy;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_terminate_exits_on_sigterm() -> Result<()> {
+        let mut child = Command::new("sleep").arg("10").spawn()?;
+
+        let killed = terminate(&mut child, Duration::from_secs(5)).await?;
+
+        assert!(!killed);
+        let status = child.try_wait()?.expect("child has exited");
+        assert_eq!(status.signal(), Some(Signal::SIGTERM as i32));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_terminate_kills_after_grace_period() -> Result<()> {
+        // Ignores SIGTERM like a frozen core that doesn't respond to it
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg("trap '' TERM; echo ready; exec sleep 10")
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut ready = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut ready)
+            .await?;
+
+        let start = std::time::Instant::now();
+        let killed = terminate(&mut child, Duration::from_millis(200)).await?;
+
+        assert!(killed);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        let status = child.try_wait()?.expect("child has exited");
+        assert_eq!(status.signal(), Some(Signal::SIGKILL as i32));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_terminate_exited_child() -> Result<()> {
+        let mut child = Command::new("true").spawn()?;
+        child.wait().await?;
+
+        assert!(!terminate(&mut child, Duration::from_millis(200)).await?);
+        Ok(())
+    }
+
+    const V0: &str = include_str!("../testdata/state/alliumd-v0.json");
+    const V1: &str = include_str!("../testdata/state/alliumd-v1.json");
+
+    #[test]
+    fn test_load_old_state() -> Result<()> {
+        for json in [V0, V1] {
+            let state: AlliumDState = persisted::from_str(json)?;
+            assert_eq!(state.time.to_rfc3339(), "2024-03-01T12:30:00+00:00");
+            assert_eq!(state.volume, 7);
+            assert_eq!(state.brightness, 80);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() -> Result<()> {
+        let state: AlliumDState = persisted::from_str(r#"{"version":1,"state":{"volume":3}}"#)?;
+        assert_eq!(state.volume, 3);
+        assert_eq!(state.brightness, 50);
+        Ok(())
+    }
+
+    #[test]
+    fn test_emergency_exit_chord() {
+        let mut keys: EnumMap<Key, bool> = EnumMap::default();
+        assert!(!is_emergency_exit_chord(&keys));
+
+        keys[Key::Menu] = true;
+        assert!(!is_emergency_exit_chord(&keys));
+
+        keys[Key::Power] = true;
+        assert!(is_emergency_exit_chord(&keys));
+
+        // Any other key held means it's some other hotkey
+        keys[Key::A] = true;
+        assert!(!is_emergency_exit_chord(&keys));
+    }
+
+    #[test]
+    fn test_waits_for_busy_display() -> Result<()> {
+        let policy = RetryPolicy {
+            attempts: 3,
+            delay_ms: 0,
+            max_delay_ms: 0,
+        };
+        let mut platform = DefaultPlatform::new()?;
+
+        DefaultPlatform::fail_display(2, io::ErrorKind::ResourceBusy);
+        assert!(platform::open_display_with(&mut platform, &policy).is_ok());
+
+        // Still busy after the last attempt
+        DefaultPlatform::fail_display(3, io::ErrorKind::ResourceBusy);
+        assert!(platform::open_display_with(&mut platform, &policy).is_err());
+        assert!(platform.display().is_ok());
+
+        // A missing display isn't waited for
+        DefaultPlatform::fail_display(2, io::ErrorKind::NotFound);
+        assert!(platform::open_display_with(&mut platform, &policy).is_err());
+        assert!(platform.display().is_err());
+        assert!(platform.display().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnostics_chord() {
+        let mut keys: EnumMap<Key, bool> = EnumMap::default();
+        keys[Key::Select] = true;
+        assert!(!is_diagnostics_chord(&keys));
+
+        keys[Key::Start] = true;
+        assert!(is_diagnostics_chord(&keys));
+        assert!(!is_emergency_exit_chord(&keys));
+
+        keys[Key::Menu] = true;
+        assert!(!is_diagnostics_chord(&keys));
+    }
+
+    #[test]
+    fn test_ingame_cache() {
+        let cache = IngameCache::default();
+        let launched = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+        assert!(cache.get_or_update(Some(1), launched, || true));
+
+        // Not read again until the game info or the main process changes
+        assert!(cache.get_or_update(Some(1), launched, || unreachable!()));
+        assert!(!cache.get_or_update(Some(2), launched, || false));
+        let loaded = launched + Duration::from_secs(1);
+        assert!(cache.get_or_update(Some(2), loaded, || true));
+    }
+}
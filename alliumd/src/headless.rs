@@ -0,0 +1,189 @@
+//! Running alliumd without a device, such as in integration tests. The platform is the stubbed one
+//! that builds without a platform feature use, the launcher and menu are shell commands given in
+//! the environment, and keys are injected through a FIFO.
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use common::platform::{Key, KeyEvent};
+use log::{info, warn};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+/// Set to run headless, as is passing `--headless`.
+pub const ALLIUMD_HEADLESS_ENV: &str = "ALLIUMD_HEADLESS";
+/// Shell command run in place of the launcher.
+pub const ALLIUMD_LAUNCHER_COMMAND_ENV: &str = "ALLIUMD_LAUNCHER_COMMAND";
+/// Shell command run in place of the ingame menu.
+pub const ALLIUMD_MENU_COMMAND_ENV: &str = "ALLIUMD_MENU_COMMAND";
+/// FIFO to read injected input from, see [`Input`].
+pub const ALLIUMD_INPUT_ENV: &str = "ALLIUMD_INPUT";
+
+/// How alliumd runs without a device.
+#[derive(Debug, Clone)]
+pub struct Headless {
+    launcher: String,
+    menu: String,
+    input: Option<PathBuf>,
+}
+
+impl Headless {
+    /// The headless settings from the environment, if alliumd was asked to run headless.
+    pub fn from_env() -> Result<Option<Self>> {
+        if env::var_os(ALLIUMD_HEADLESS_ENV).is_none()
+            && !env::args().skip(1).any(|arg| arg == "--headless")
+        {
+            return Ok(None);
+        }
+        if cfg!(any(feature = "miyoo", feature = "simulator")) {
+            bail!("headless mode needs a build without the miyoo or simulator feature");
+        }
+
+        let command = |name: &str| {
+            env::var(name).with_context(|| format!("{} must be set in headless mode", name))
+        };
+        Ok(Some(Self {
+            launcher: command(ALLIUMD_LAUNCHER_COMMAND_ENV)?,
+            menu: command(ALLIUMD_MENU_COMMAND_ENV)?,
+            input: env::var_os(ALLIUMD_INPUT_ENV).map(PathBuf::from),
+        }))
+    }
+
+    pub fn launcher_command(&self) -> Command {
+        shell(&self.launcher)
+    }
+
+    pub fn menu_command(&self) -> Command {
+        shell(&self.menu)
+    }
+
+    /// Starts reading injected input, if there is a FIFO to read it from.
+    pub fn read_input(&self) -> Result<Option<mpsc::Receiver<Input>>> {
+        self.input.as_deref().map(read_input).transpose()
+    }
+}
+
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("/bin/sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+/// A line written to the input FIFO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    /// `press <key>`, `release <key>`, `repeat <key>` or `lid open|closed`, handled as if it came
+    /// from the platform.
+    Key(KeyEvent),
+    /// `launch <path>`, handled like a launch from the companion API.
+    Launch(PathBuf),
+}
+
+impl FromStr for Input {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let (verb, arg) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let key = || -> Result<Key> {
+            serde_json::from_value(serde_json::Value::String(arg.to_string()))
+                .map_err(|_| anyhow!("unknown key: {:?}", arg))
+        };
+        Ok(match verb {
+            "press" => Input::Key(KeyEvent::Pressed(key()?)),
+            "release" => Input::Key(KeyEvent::Released(key()?)),
+            "repeat" => Input::Key(KeyEvent::Autorepeat(key()?)),
+            "lid" => match arg {
+                "open" => Input::Key(KeyEvent::Lid(false)),
+                "closed" => Input::Key(KeyEvent::Lid(true)),
+                _ => bail!("lid is either open or closed: {:?}", arg),
+            },
+            "launch" if !arg.is_empty() => Input::Launch(PathBuf::from(arg)),
+            _ => bail!("unknown input: {:?}", line),
+        })
+    }
+}
+
+/// Reads input from the FIFO at `path` on a thread of its own. The FIFO is opened for writing too,
+/// so that it doesn't close when whoever is injecting input does.
+fn read_input(path: &Path) -> Result<mpsc::Receiver<Input>> {
+    let fifo = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("failed to open input FIFO {}", path.display()))?;
+    info!("reading input from {}", path.display());
+
+    let (tx, rx) = mpsc::channel(16);
+    std::thread::spawn(move || {
+        for line in BufReader::new(fifo).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    warn!("failed to read input: {}", e);
+                    return;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match line.parse() {
+                Ok(input) => {
+                    if tx.blocking_send(input).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => warn!("ignoring input: {}", e),
+            }
+        }
+    });
+    Ok(rx)
+}
+
+/// Waits for the next injected input. Never finishes if there is no input to read.
+pub async fn recv_input(input: &mut Option<mpsc::Receiver<Input>>) -> Input {
+    match input.as_mut() {
+        Some(rx) => match rx.recv().await {
+            Some(input) => input,
+            None => std::future::pending().await,
+        },
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_input() -> Result<()> {
+        assert_eq!(
+            "press Menu".parse::<Input>()?,
+            Input::Key(KeyEvent::Pressed(Key::Menu))
+        );
+        assert_eq!(
+            "release A\n".parse::<Input>()?,
+            Input::Key(KeyEvent::Released(Key::A))
+        );
+        assert_eq!(
+            "repeat VolUp".parse::<Input>()?,
+            Input::Key(KeyEvent::Autorepeat(Key::VolUp))
+        );
+        assert_eq!(
+            "lid closed".parse::<Input>()?,
+            Input::Key(KeyEvent::Lid(true))
+        );
+        assert_eq!(
+            "launch /Roms/GBA/My Game.gba".parse::<Input>()?,
+            Input::Launch(PathBuf::from("/Roms/GBA/My Game.gba"))
+        );
+
+        for line in ["press", "press Turbo", "lid ajar", "launch", "jump"] {
+            assert!(line.parse::<Input>().is_err(), "{:?} parsed", line);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+//! Drives the power LED, so that the battery state and finished background work are visible
+//! while the screen is off. Background work is maintenance, and the launcher's box art scraping,
+//! which it reports with `common::led::notify_task_completed`.
+//!
+//! When several patterns are wanted at once, the highest priority one wins:
+//!
+//! 1. Off, if the LED is disabled.
+//! 2. The low battery warning, while discharging below `BATTERY_LOW_THRESHOLD`.
+//! 3. A notification that a background task completed, for `NOTIFICATION_DURATION`.
+//! 4. Charging, until `BATTERY_CHARGED_THRESHOLD` is reached, then charged.
+//! 5. Off.
+
+use std::time::{Duration, Instant};
+
+use common::constants::{BATTERY_CHARGED_THRESHOLD, BATTERY_LOW_THRESHOLD};
+use common::led::LedPattern;
+use serde::{Deserialize, Serialize};
+
+/// How long a notification is shown for.
+pub const NOTIFICATION_DURATION: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LedSettings {
+    pub enabled: bool,
+    pub charging: LedPattern,
+    pub charged: LedPattern,
+    pub low_battery: LedPattern,
+    pub notification: LedPattern,
+}
+
+impl Default for LedSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            charging: LedPattern::SlowBlink,
+            charged: LedPattern::Solid,
+            low_battery: LedPattern::FastBlink,
+            notification: LedPattern::FastBlink,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Led {
+    settings: LedSettings,
+    percentage: i32,
+    charging: bool,
+    notification_until: Option<Instant>,
+}
+
+impl Led {
+    pub fn new(settings: LedSettings) -> Self {
+        Self {
+            settings,
+            percentage: 100,
+            charging: false,
+            notification_until: None,
+        }
+    }
+
+    pub fn set_battery(&mut self, percentage: i32, charging: bool) {
+        self.percentage = percentage;
+        self.charging = charging;
+    }
+
+    /// Shows that a background task completed.
+    pub fn notify(&mut self, now: Instant) {
+        self.notification_until = Some(now + NOTIFICATION_DURATION);
+    }
+
+    /// The pattern that should be showing at `now`.
+    pub fn pattern(&self, now: Instant) -> LedPattern {
+        let settings = &self.settings;
+        if !settings.enabled {
+            LedPattern::Off
+        } else if !self.charging && self.percentage < BATTERY_LOW_THRESHOLD {
+            settings.low_battery
+        } else if self.notification_until.is_some_and(|until| now < until) {
+            settings.notification
+        } else if self.charging && self.percentage < BATTERY_CHARGED_THRESHOLD {
+            settings.charging
+        } else if self.charging {
+            settings.charged
+        } else {
+            LedPattern::Off
+        }
+    }
+
+    /// When the pattern next changes without any new input, i.e. when the notification ends.
+    pub fn deadline(&self, now: Instant) -> Option<Instant> {
+        self.notification_until.filter(|until| now < *until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_battery_patterns() {
+        let now = Instant::now();
+        let mut led = Led::new(LedSettings::default());
+
+        led.set_battery(50, false);
+        assert_eq!(led.pattern(now), LedPattern::Off);
+
+        led.set_battery(50, true);
+        assert_eq!(led.pattern(now), LedPattern::SlowBlink);
+
+        led.set_battery(BATTERY_CHARGED_THRESHOLD, true);
+        assert_eq!(led.pattern(now), LedPattern::Solid);
+
+        led.set_battery(BATTERY_LOW_THRESHOLD - 1, false);
+        assert_eq!(led.pattern(now), LedPattern::FastBlink);
+
+        // Plugging in silences the warning
+        led.set_battery(BATTERY_LOW_THRESHOLD - 1, true);
+        assert_eq!(led.pattern(now), LedPattern::SlowBlink);
+    }
+
+    #[test]
+    fn test_notification_priority() {
+        let now = Instant::now();
+        let settings = LedSettings {
+            notification: LedPattern::Solid,
+            ..Default::default()
+        };
+        let mut led = Led::new(settings);
+
+        // Notifications override the charging pattern, until they end
+        led.set_battery(50, true);
+        led.notify(now);
+        assert_eq!(led.pattern(now), LedPattern::Solid);
+        assert_eq!(led.deadline(now), Some(now + NOTIFICATION_DURATION));
+        let later = now + NOTIFICATION_DURATION;
+        assert_eq!(led.pattern(later), LedPattern::SlowBlink);
+        assert_eq!(led.deadline(later), None);
+
+        // The low battery warning overrides notifications
+        led.set_battery(BATTERY_LOW_THRESHOLD - 1, false);
+        led.notify(now);
+        assert_eq!(led.pattern(now), LedPattern::FastBlink);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let now = Instant::now();
+        let mut led = Led::new(LedSettings {
+            enabled: false,
+            ..Default::default()
+        });
+
+        led.set_battery(BATTERY_LOW_THRESHOLD - 1, false);
+        led.notify(now);
+        assert_eq!(led.pattern(now), LedPattern::Off);
+    }
+}
@@ -0,0 +1,206 @@
+//! Suspends clamshell devices while the lid is closed, the same way opening the in-game menu
+//! does for play time: the main process is stopped, the backlight turned off, and the game's play
+//! time paused until the lid is opened again.
+//!
+//! Devices without a lid switch never send lid events, so nothing here runs on them.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use log::info;
+
+/// What closing the lid acts on.
+#[async_trait(?Send)]
+pub trait LidTarget {
+    /// Closes the in-game menu without resuming the game. Returns whether it was open.
+    async fn dismiss_menu(&mut self) -> Result<bool>;
+    /// Stops or continues the main process.
+    fn set_stopped(&mut self, stopped: bool) -> Result<()>;
+    /// Pauses or resumes play time of the current game, if there is one.
+    fn set_paused(&mut self, paused: bool) -> Result<()>;
+    fn set_backlight(&mut self, on: bool) -> Result<()>;
+    /// Unpauses RetroArch, which pauses itself while the in-game menu is open.
+    async fn unpause(&mut self) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lid {
+    closed: bool,
+    /// Whether the in-game menu was dismissed when the lid was closed, leaving RetroArch paused.
+    dismissed_menu: bool,
+}
+
+impl Lid {
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Suspends the target when the lid closes and resumes it when the lid opens. Repeated events
+    /// for the same position are ignored.
+    pub async fn set_closed(&mut self, target: &mut impl LidTarget, closed: bool) -> Result<()> {
+        if closed == self.closed {
+            return Ok(());
+        }
+        self.closed = closed;
+
+        if closed {
+            info!("lid closed, suspending");
+            // The menu would be stopped half drawn and come back on opening, so close it first
+            self.dismissed_menu = target.dismiss_menu().await?;
+            target.set_stopped(true)?;
+            target.set_paused(true)?;
+            target.set_backlight(false)?;
+        } else {
+            info!("lid opened, resuming");
+            target.set_backlight(true)?;
+            target.set_stopped(false)?;
+            target.set_paused(false)?;
+            if std::mem::take(&mut self.dismissed_menu) {
+                target.unpause().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::platform::{DefaultPlatform, Platform};
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Call {
+        DismissMenu,
+        Stop,
+        Continue,
+        Pause,
+        Resume,
+        Backlight(bool),
+        Unpause,
+    }
+
+    /// alliumd, on the stubbed platform.
+    struct StubTarget {
+        platform: DefaultPlatform,
+        is_menu_open: bool,
+        calls: Vec<Call>,
+    }
+
+    impl StubTarget {
+        fn new(is_menu_open: bool) -> Self {
+            Self {
+                platform: DefaultPlatform::new().unwrap(),
+                is_menu_open,
+                calls: Vec::new(),
+            }
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl LidTarget for StubTarget {
+        async fn dismiss_menu(&mut self) -> Result<bool> {
+            if !self.is_menu_open {
+                return Ok(false);
+            }
+            self.is_menu_open = false;
+            self.calls.push(Call::DismissMenu);
+            Ok(true)
+        }
+
+        fn set_stopped(&mut self, stopped: bool) -> Result<()> {
+            self.calls
+                .push(if stopped { Call::Stop } else { Call::Continue });
+            Ok(())
+        }
+
+        fn set_paused(&mut self, paused: bool) -> Result<()> {
+            self.calls
+                .push(if paused { Call::Pause } else { Call::Resume });
+            Ok(())
+        }
+
+        fn set_backlight(&mut self, on: bool) -> Result<()> {
+            self.platform.set_backlight(on)?;
+            self.calls.push(Call::Backlight(on));
+            Ok(())
+        }
+
+        async fn unpause(&mut self) -> Result<()> {
+            self.calls.push(Call::Unpause);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_close_and_open() -> Result<()> {
+        let mut lid = Lid::default();
+        let mut target = StubTarget::new(false);
+
+        lid.set_closed(&mut target, true).await?;
+        assert!(lid.is_closed());
+        assert_eq!(
+            target.calls.drain(..).collect::<Vec<_>>(),
+            [Call::Stop, Call::Pause, Call::Backlight(false)]
+        );
+
+        lid.set_closed(&mut target, false).await?;
+        assert!(!lid.is_closed());
+        assert_eq!(
+            target.calls,
+            [Call::Backlight(true), Call::Continue, Call::Resume]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_close_with_menu_open() -> Result<()> {
+        let mut lid = Lid::default();
+        let mut target = StubTarget::new(true);
+
+        lid.set_closed(&mut target, true).await?;
+        assert!(!target.is_menu_open);
+        assert_eq!(
+            target.calls.drain(..).collect::<Vec<_>>(),
+            [
+                Call::DismissMenu,
+                Call::Stop,
+                Call::Pause,
+                Call::Backlight(false)
+            ]
+        );
+
+        // The menu paused RetroArch, and is no longer there to unpause it
+        lid.set_closed(&mut target, false).await?;
+        assert_eq!(
+            target.calls.drain(..).collect::<Vec<_>>(),
+            [
+                Call::Backlight(true),
+                Call::Continue,
+                Call::Resume,
+                Call::Unpause
+            ]
+        );
+
+        // Only once
+        lid.set_closed(&mut target, true).await?;
+        lid.set_closed(&mut target, false).await?;
+        assert!(!target.calls.contains(&Call::Unpause));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_repeated_events_are_ignored() -> Result<()> {
+        let mut lid = Lid::default();
+        let mut target = StubTarget::new(false);
+
+        lid.set_closed(&mut target, false).await?;
+        assert!(target.calls.is_empty());
+
+        lid.set_closed(&mut target, true).await?;
+        target.calls.clear();
+        lid.set_closed(&mut target, true).await?;
+        assert!(target.calls.is_empty());
+        assert!(lid.is_closed());
+        Ok(())
+    }
+}
@@ -2,6 +2,13 @@
 #![warn(rust_2018_idioms)]
 
 mod alliumd;
+mod headless;
+mod led;
+mod lid;
+mod maintenance;
+mod quick_quit;
+mod remote;
+mod switch;
 
 use anyhow::Result;
 use simple_logger::SimpleLogger;
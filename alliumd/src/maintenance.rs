@@ -0,0 +1,445 @@
+use std::future::Future;
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime};
+use common::battery::Battery;
+use common::constants::MAINTENANCE_IDLE_TIMEOUT;
+use common::maintenance::{
+    AbortReason, MaintenanceReport, MaintenanceSettings, MaintenanceTask, TaskOutcome, TaskReport,
+};
+use common::platform::KeyEvent;
+use log::{error, info, warn};
+
+pub trait Clock {
+    fn now(&self) -> NaiveDateTime;
+}
+
+#[derive(Debug)]
+pub struct LocalClock;
+
+impl Clock for LocalClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+/// Why maintenance was stopped early.
+#[derive(Debug)]
+pub enum Interrupt {
+    /// A key was pressed. The event is passed on once maintenance has stopped.
+    Input(KeyEvent),
+    ChargingStopped,
+}
+
+impl Interrupt {
+    fn reason(&self) -> AbortReason {
+        match self {
+            Interrupt::Input(_) => AbortReason::Input,
+            Interrupt::ChargingStopped => AbortReason::ChargingStopped,
+        }
+    }
+}
+
+/// Runs the maintenance tasks once per window, when the device has been idle for a while and is
+/// charging.
+#[derive(Debug)]
+pub struct Maintenance<C: Clock> {
+    clock: C,
+    settings: MaintenanceSettings,
+    tasks: Vec<Box<dyn MaintenanceTask>>,
+    last_input: NaiveDateTime,
+    last_run: Option<NaiveDateTime>,
+}
+
+impl<C: Clock> Maintenance<C> {
+    pub fn new(
+        clock: C,
+        settings: MaintenanceSettings,
+        tasks: Vec<Box<dyn MaintenanceTask>>,
+    ) -> Self {
+        let last_input = clock.now();
+        Self {
+            clock,
+            settings,
+            tasks,
+            last_input,
+            last_run: None,
+        }
+    }
+
+    /// Restarts the idle timer.
+    pub fn input(&mut self) {
+        self.last_input = self.clock.now();
+    }
+
+    /// Returns true if maintenance should start now.
+    pub fn is_due(&self, battery: &impl Battery) -> bool {
+        if !self.settings.enabled || self.tasks.is_empty() || !battery.charging() {
+            return false;
+        }
+
+        let now = self.clock.now();
+        let idle = (now - self.last_input).to_std().unwrap_or_default();
+        if idle < MAINTENANCE_IDLE_TIMEOUT {
+            return false;
+        }
+
+        match self.settings.window_started(now) {
+            Some(started) => self.last_run.is_none_or(|last_run| last_run < started),
+            None => false,
+        }
+    }
+
+    /// Runs the tasks in order, each with its own timeout, until they are all done or `interrupt`
+    /// completes. Tasks that haven't finished by then are abandoned.
+    pub async fn run(
+        &mut self,
+        interrupt: impl Future<Output = Interrupt>,
+    ) -> (MaintenanceReport, Option<Interrupt>) {
+        let started = self.clock.now();
+        self.last_run = Some(started);
+        info!("starting maintenance");
+
+        tokio::pin!(interrupt);
+        let mut interrupted = None;
+        let mut tasks = Vec::with_capacity(self.tasks.len());
+        for task in &mut self.tasks {
+            let outcome = if interrupted.is_some() {
+                TaskOutcome::Skipped
+            } else {
+                info!("running maintenance task {}", task.name());
+                let timeout = task.timeout();
+                tokio::select! {
+                    biased;
+                    i = &mut interrupt => {
+                        warn!("maintenance interrupted: {:?}", i);
+                        interrupted = Some(i);
+                        TaskOutcome::Aborted
+                    }
+                    result = tokio::time::timeout(timeout, task.run()) => match result {
+                        Ok(Ok(())) => TaskOutcome::Completed,
+                        Ok(Err(e)) => {
+                            error!("maintenance task {} failed: {}", task.name(), e);
+                            TaskOutcome::Failed(e.to_string())
+                        }
+                        Err(_) => {
+                            warn!("maintenance task {} timed out after {:?}", task.name(), timeout);
+                            TaskOutcome::TimedOut
+                        }
+                    },
+                }
+            };
+            tasks.push(TaskReport {
+                name: task.name().to_owned(),
+                outcome,
+            });
+        }
+
+        let report = MaintenanceReport {
+            started,
+            tasks,
+            aborted: interrupted.as_ref().map(Interrupt::reason),
+        };
+        info!(
+            "maintenance finished, {}/{} tasks completed",
+            report.completed(),
+            report.tasks.len()
+        );
+        (report, interrupted)
+    }
+}
+
+/// Completes once `battery` is no longer charging, checking every `interval`.
+pub async fn charging_stopped(battery: &mut impl Battery, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        if let Err(e) = battery.update() {
+            error!("failed to update battery: {}", e);
+        }
+        if !battery.charging() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use anyhow::{bail, Result};
+    use async_trait::async_trait;
+    use chrono::NaiveDate;
+    use common::platform::Key;
+
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct MockClock(Rc<Cell<NaiveDateTime>>);
+
+    impl MockClock {
+        fn at(hour: u32, min: u32) -> Self {
+            let clock = Self(Rc::new(Cell::new(NaiveDateTime::default())));
+            clock.set(hour, min);
+            clock
+        }
+
+        fn set(&self, hour: u32, min: u32) {
+            self.0.set(
+                NaiveDate::from_ymd_opt(2023, 6, 1)
+                    .unwrap()
+                    .and_hms_opt(hour, min, 0)
+                    .unwrap(),
+            );
+        }
+
+        fn advance(&self, minutes: i64) {
+            self.0
+                .set(self.0.get() + chrono::Duration::minutes(minutes));
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> NaiveDateTime {
+            self.0.get()
+        }
+    }
+
+    /// Charging for the given number of updates, then unplugged.
+    struct MockBattery {
+        charging_updates: Option<usize>,
+    }
+
+    impl MockBattery {
+        fn charging() -> Self {
+            Self {
+                charging_updates: None,
+            }
+        }
+
+        fn unplugged_after(updates: usize) -> Self {
+            Self {
+                charging_updates: Some(updates),
+            }
+        }
+    }
+
+    impl Battery for MockBattery {
+        fn update(&mut self) -> Result<()> {
+            if let Some(updates) = self.charging_updates.as_mut() {
+                *updates = updates.saturating_sub(1);
+            }
+            Ok(())
+        }
+
+        fn percentage(&self) -> i32 {
+            50
+        }
+
+        fn charging(&self) -> bool {
+            self.charging_updates != Some(0)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Sleep {
+        name: &'static str,
+        duration: Duration,
+        fail: bool,
+    }
+
+    #[async_trait(?Send)]
+    impl MaintenanceTask for Sleep {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn estimated_cost(&self) -> Duration {
+            Duration::from_millis(50)
+        }
+
+        async fn run(&mut self) -> Result<()> {
+            tokio::time::sleep(self.duration).await;
+            if self.fail {
+                bail!("failed");
+            }
+            Ok(())
+        }
+    }
+
+    fn task(name: &'static str, millis: u64) -> Box<dyn MaintenanceTask> {
+        Box::new(Sleep {
+            name,
+            duration: Duration::from_millis(millis),
+            fail: false,
+        })
+    }
+
+    fn outcomes(report: &MaintenanceReport) -> Vec<TaskOutcome> {
+        report.tasks.iter().map(|t| t.outcome.clone()).collect()
+    }
+
+    #[test]
+    fn test_is_due() {
+        let clock = MockClock::at(1, 0);
+        let mut maintenance = Maintenance::new(
+            clock.clone(),
+            MaintenanceSettings::new(),
+            vec![task("a", 0)],
+        );
+        let charging = MockBattery::charging();
+
+        // Before the window
+        clock.set(1, 59);
+        assert!(!maintenance.is_due(&charging));
+
+        // In the window and idle since 1:00
+        clock.set(2, 0);
+        assert!(maintenance.is_due(&charging));
+        assert!(!maintenance.is_due(&MockBattery::unplugged_after(0)));
+
+        // Input restarts the idle timer
+        maintenance.input();
+        clock.advance(29);
+        assert!(!maintenance.is_due(&charging));
+        clock.advance(1);
+        assert!(maintenance.is_due(&charging));
+
+        // After the window
+        clock.set(5, 0);
+        assert!(!maintenance.is_due(&charging));
+    }
+
+    #[test]
+    fn test_is_due_when_disabled() {
+        let clock = MockClock::at(3, 0);
+        let maintenance = Maintenance::new(
+            clock.clone(),
+            MaintenanceSettings {
+                enabled: false,
+                ..MaintenanceSettings::new()
+            },
+            vec![task("a", 0)],
+        );
+        clock.advance(60);
+        assert!(!maintenance.is_due(&MockBattery::charging()));
+    }
+
+    #[tokio::test]
+    async fn test_runs_once_per_window() {
+        let clock = MockClock::at(1, 0);
+        let mut maintenance = Maintenance::new(
+            clock.clone(),
+            MaintenanceSettings::new(),
+            vec![task("a", 0)],
+        );
+        let charging = MockBattery::charging();
+
+        clock.set(2, 30);
+        assert!(maintenance.is_due(&charging));
+        let (report, interrupt) = maintenance.run(std::future::pending()).await;
+        assert!(interrupt.is_none());
+        assert_eq!(outcomes(&report), vec![TaskOutcome::Completed]);
+
+        clock.set(4, 0);
+        assert!(!maintenance.is_due(&charging));
+
+        // The next night
+        clock.advance(22 * 60);
+        assert!(maintenance.is_due(&charging));
+    }
+
+    #[tokio::test]
+    async fn test_task_outcomes() {
+        let clock = MockClock::at(3, 0);
+        let mut maintenance = Maintenance::new(
+            clock,
+            MaintenanceSettings::new(),
+            vec![
+                task("fast", 0),
+                Box::new(Sleep {
+                    name: "broken",
+                    duration: Duration::ZERO,
+                    fail: true,
+                }),
+                task("slow", 10_000),
+                task("after", 0),
+            ],
+        );
+
+        let (report, interrupt) = maintenance.run(std::future::pending()).await;
+        assert!(interrupt.is_none());
+        assert_eq!(report.aborted, None);
+        assert_eq!(
+            outcomes(&report),
+            vec![
+                TaskOutcome::Completed,
+                TaskOutcome::Failed("failed".to_string()),
+                TaskOutcome::TimedOut,
+                TaskOutcome::Completed,
+            ]
+        );
+        assert_eq!(report.completed(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_input_aborts() {
+        let clock = MockClock::at(3, 0);
+        let mut maintenance = Maintenance::new(
+            clock,
+            MaintenanceSettings::new(),
+            vec![task("first", 0), task("second", 150), task("third", 0)],
+        );
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            tx.send(KeyEvent::Pressed(Key::A)).unwrap();
+        });
+        let (report, interrupt) = maintenance
+            .run(async { Interrupt::Input(rx.await.unwrap()) })
+            .await;
+
+        assert!(matches!(
+            interrupt,
+            Some(Interrupt::Input(KeyEvent::Pressed(Key::A)))
+        ));
+        assert_eq!(report.aborted, Some(AbortReason::Input));
+        assert_eq!(
+            outcomes(&report),
+            vec![
+                TaskOutcome::Completed,
+                TaskOutcome::Aborted,
+                TaskOutcome::Skipped,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unplugging_aborts() {
+        let clock = MockClock::at(3, 0);
+        let mut maintenance = Maintenance::new(
+            clock,
+            MaintenanceSettings::new(),
+            vec![task("first", 150), task("second", 0)],
+        );
+
+        let mut battery = MockBattery::unplugged_after(2);
+        let started = std::time::Instant::now();
+        let (report, interrupt) = maintenance
+            .run(async {
+                charging_stopped(&mut battery, Duration::from_millis(10)).await;
+                Interrupt::ChargingStopped
+            })
+            .await;
+
+        assert!(started.elapsed() < Duration::from_millis(150));
+        assert!(matches!(interrupt, Some(Interrupt::ChargingStopped)));
+        assert_eq!(report.aborted, Some(AbortReason::ChargingStopped));
+        assert_eq!(
+            outcomes(&report),
+            vec![TaskOutcome::Aborted, TaskOutcome::Skipped]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Quits the game when a key chord, Select+Start by default, is held in game, as on other
+//! firmware. The game is stopped while alliumd asks for confirmation over it.
+//!
+//! The confirmation takes the input from the game only once the chord is released, so that the
+//! game sees the chord released when it is resumed, and doesn't see the answer at all.
+
+use std::time::{Duration, Instant};
+
+use common::platform::{Key, KeyEvent};
+use enum_map::EnumMap;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuickQuitSettings {
+    pub enabled: bool,
+    /// The two keys to hold together, and nothing else.
+    pub chord: [Key; 2],
+    /// How long the chord is held before it quits, in milliseconds.
+    pub hold_millis: u64,
+    /// Ask before quitting.
+    pub confirm: bool,
+}
+
+impl Default for QuickQuitSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            chord: [Key::Select, Key::Start],
+            hold_millis: 1000,
+            confirm: true,
+        }
+    }
+}
+
+/// What alliumd should do as the chord is held and the confirmation answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuickQuitAction {
+    None,
+    /// Stop the game and show the confirmation over it.
+    Show,
+    /// The chord was released, take the input from the game while the confirmation is shown.
+    Grab,
+    /// Quit the game, hiding the confirmation if it's shown.
+    Quit,
+    /// Hide the confirmation and resume the game.
+    Dismiss,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    /// The chord is held, and quits at the deadline.
+    Holding(Instant),
+    /// The confirmation is shown. The input is grabbed once every key is released.
+    Confirming {
+        grabbed: bool,
+    },
+}
+
+#[derive(Debug)]
+pub struct QuickQuit {
+    settings: QuickQuitSettings,
+    state: State,
+}
+
+impl QuickQuit {
+    pub fn new(settings: QuickQuitSettings) -> Self {
+        Self {
+            settings,
+            state: State::Idle,
+        }
+    }
+
+    /// Whether the confirmation is shown. Keys go to it rather than anything else meanwhile.
+    pub fn is_visible(&self) -> bool {
+        matches!(self.state, State::Confirming { .. })
+    }
+
+    /// When the chord will have been held long enough, if it is held.
+    pub fn deadline(&self) -> Option<Instant> {
+        match self.state {
+            State::Holding(deadline) => Some(deadline),
+            _ => None,
+        }
+    }
+
+    /// Follows the chord and answers the confirmation. `keys` are the keys held after `event`, and
+    /// `ingame` whether there is a game to quit, which is only needed while the chord is held.
+    pub fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        keys: &EnumMap<Key, bool>,
+        ingame: impl FnOnce() -> bool,
+        now: Instant,
+    ) -> QuickQuitAction {
+        match self.state {
+            State::Idle | State::Holding(_) => {
+                if !self.is_chord(keys) {
+                    self.state = State::Idle;
+                } else if self.state == State::Idle && ingame() {
+                    self.state =
+                        State::Holding(now + Duration::from_millis(self.settings.hold_millis));
+                }
+                QuickQuitAction::None
+            }
+            State::Confirming { grabbed: false } => {
+                if keys.values().any(|&pressed| pressed) {
+                    QuickQuitAction::None
+                } else {
+                    self.state = State::Confirming { grabbed: true };
+                    QuickQuitAction::Grab
+                }
+            }
+            State::Confirming { grabbed: true } => match event {
+                KeyEvent::Pressed(Key::A) => {
+                    self.state = State::Idle;
+                    QuickQuitAction::Quit
+                }
+                KeyEvent::Pressed(Key::B) => {
+                    self.state = State::Idle;
+                    QuickQuitAction::Dismiss
+                }
+                _ => QuickQuitAction::None,
+            },
+        }
+    }
+
+    /// Acts on the chord once it has been held until the deadline.
+    pub fn expire(&mut self, now: Instant) -> QuickQuitAction {
+        match self.state {
+            State::Holding(deadline) if now >= deadline => {
+                if self.settings.confirm {
+                    self.state = State::Confirming { grabbed: false };
+                    QuickQuitAction::Show
+                } else {
+                    self.state = State::Idle;
+                    QuickQuitAction::Quit
+                }
+            }
+            _ => QuickQuitAction::None,
+        }
+    }
+
+    /// Forgets the chord and hides the confirmation, e.g. when the game exits by itself.
+    pub fn reset(&mut self) -> QuickQuitAction {
+        let was_visible = self.is_visible();
+        self.state = State::Idle;
+        if was_visible {
+            QuickQuitAction::Dismiss
+        } else {
+            QuickQuitAction::None
+        }
+    }
+
+    fn is_chord(&self, keys: &EnumMap<Key, bool>) -> bool {
+        self.settings.enabled
+            && keys
+                .iter()
+                .all(|(key, &pressed)| pressed == self.settings.chord.contains(&key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays key events, keeping track of the keys held, and returns the actions they led to.
+    struct Script {
+        quick_quit: QuickQuit,
+        keys: EnumMap<Key, bool>,
+        now: Instant,
+        ingame: bool,
+    }
+
+    impl Script {
+        fn new(settings: QuickQuitSettings) -> Self {
+            Self {
+                quick_quit: QuickQuit::new(settings),
+                keys: EnumMap::default(),
+                now: Instant::now(),
+                ingame: true,
+            }
+        }
+
+        fn key(&mut self, event: KeyEvent) -> QuickQuitAction {
+            match event {
+                KeyEvent::Pressed(key) => self.keys[key] = true,
+                KeyEvent::Released(key) => self.keys[key] = false,
+                _ => {}
+            }
+            let ingame = self.ingame;
+            self.quick_quit
+                .handle_key_event(event, &self.keys, || ingame, self.now)
+        }
+
+        fn keys(&mut self, events: &[KeyEvent]) -> Vec<QuickQuitAction> {
+            events.iter().map(|&event| self.key(event)).collect()
+        }
+
+        fn wait(&mut self, millis: u64) -> QuickQuitAction {
+            self.now += Duration::from_millis(millis);
+            match self.quick_quit.deadline() {
+                Some(deadline) if deadline <= self.now => self.quick_quit.expire(self.now),
+                _ => QuickQuitAction::None,
+            }
+        }
+    }
+
+    use KeyEvent::{Pressed, Released};
+    use QuickQuitAction::{Dismiss, Grab, Quit, Show};
+
+    const NONE: QuickQuitAction = QuickQuitAction::None;
+
+    #[test]
+    fn test_confirm_quit() {
+        let mut script = Script::new(QuickQuitSettings::default());
+
+        assert_eq!(
+            script.keys(&[Pressed(Key::Select), Pressed(Key::Start)]),
+            [NONE, NONE]
+        );
+        assert_eq!(script.wait(999), NONE);
+        assert_eq!(script.wait(1), Show);
+        assert!(script.quick_quit.is_visible());
+
+        // Nothing is answered until the chord is released and the input grabbed
+        assert_eq!(
+            script.keys(&[
+                Pressed(Key::A),
+                Released(Key::A),
+                Released(Key::Select),
+                Released(Key::Start)
+            ]),
+            [NONE, NONE, NONE, Grab]
+        );
+        // Other keys are taken from the game, but don't answer
+        assert_eq!(
+            script.keys(&[Pressed(Key::X), Released(Key::X)]),
+            [NONE, NONE]
+        );
+        assert_eq!(script.key(Pressed(Key::A)), Quit);
+        assert!(!script.quick_quit.is_visible());
+    }
+
+    #[test]
+    fn test_dismiss() {
+        let mut script = Script::new(QuickQuitSettings::default());
+
+        script.keys(&[Pressed(Key::Start), Pressed(Key::Select)]);
+        assert_eq!(script.wait(1000), Show);
+        assert_eq!(
+            script.keys(&[Released(Key::Start), Released(Key::Select)]),
+            [NONE, Grab]
+        );
+        assert_eq!(script.key(Pressed(Key::B)), Dismiss);
+        assert!(!script.quick_quit.is_visible());
+
+        // The B release goes to the game, and doesn't start anything
+        assert_eq!(script.key(Released(Key::B)), NONE);
+        assert_eq!(script.wait(2000), NONE);
+    }
+
+    #[test]
+    fn test_chord_must_be_held() {
+        let mut script = Script::new(QuickQuitSettings::default());
+
+        // Released too early
+        script.keys(&[Pressed(Key::Select), Pressed(Key::Start)]);
+        assert_eq!(script.wait(500), NONE);
+        script.key(Released(Key::Start));
+        assert_eq!(script.wait(1000), NONE);
+
+        // Another key joins in
+        script.key(Pressed(Key::Start));
+        assert_eq!(script.wait(500), NONE);
+        script.key(Pressed(Key::A));
+        assert_eq!(script.wait(1000), NONE);
+
+        // Back to just the chord, which starts over
+        script.key(Released(Key::A));
+        assert_eq!(script.wait(999), NONE);
+        assert_eq!(script.wait(1), Show);
+    }
+
+    #[test]
+    fn test_only_in_game() {
+        let mut script = Script::new(QuickQuitSettings::default());
+        script.ingame = false;
+
+        script.keys(&[Pressed(Key::Select), Pressed(Key::Start)]);
+        assert_eq!(script.quick_quit.deadline(), None);
+        assert_eq!(script.wait(2000), NONE);
+    }
+
+    #[test]
+    fn test_settings() {
+        let mut script = Script::new(QuickQuitSettings {
+            chord: [Key::L, Key::R],
+            hold_millis: 2000,
+            confirm: false,
+            ..Default::default()
+        });
+
+        script.keys(&[Pressed(Key::Select), Pressed(Key::Start)]);
+        assert_eq!(script.wait(3000), NONE);
+        script.keys(&[Released(Key::Select), Released(Key::Start)]);
+
+        script.keys(&[Pressed(Key::R), Pressed(Key::L)]);
+        assert_eq!(script.wait(1000), NONE);
+        assert_eq!(script.wait(1000), Quit);
+        assert!(!script.quick_quit.is_visible());
+
+        let mut script = Script::new(QuickQuitSettings {
+            enabled: false,
+            ..Default::default()
+        });
+        script.keys(&[Pressed(Key::Select), Pressed(Key::Start)]);
+        assert_eq!(script.wait(2000), NONE);
+    }
+
+    #[test]
+    fn test_reset_hides_confirmation() {
+        let mut script = Script::new(QuickQuitSettings::default());
+
+        script.keys(&[Pressed(Key::Select), Pressed(Key::Start)]);
+        assert_eq!(script.wait(1000), Show);
+        assert_eq!(script.quick_quit.reset(), Dismiss);
+        assert_eq!(script.quick_quit.reset(), NONE);
+    }
+}
@@ -0,0 +1,907 @@
+//! Companion API, for controlling the device from a phone's browser on the same network.
+//!
+//! alliumd serves a small JSON API over HTTP while Wi-Fi is connected and it's enabled in the
+//! Wi-Fi settings, listening only on the Wi-Fi address. Requests must carry the token from the
+//! settings as `Authorization: Bearer <token>`, except for the page at `/`, which asks for it.
+//!
+//! | Request            | Body                      | Response                          |
+//! |--------------------|---------------------------|-----------------------------------|
+//! | `GET /library`     |                           | the library export                |
+//! | `GET /status`      |                           | current game, battery and volume  |
+//! | `POST /launch`     | `{"path": "..."}`         | 202 once the game is starting     |
+//! | `POST /volume`     | `{"volume": 0..=20}`      | 204                               |
+//! | `POST /brightness` | `{"brightness": 0..=100}` | 204                               |
+//! | `POST /screenshot` |                           | the screenshot as a PNG           |
+//!
+//! Paths to launch may be absolute, or relative to the games folder as in the library export.
+//!
+//! Connections are handled a few at a time, each in its own task, which passes requests on to
+//! alliumd's event loop as [`RemoteCommand`]s, where [`dispatch`] carries them out on a
+//! [`RemoteTarget`].
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use common::remote_token;
+use common::volume::MAX_VOLUME;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot, Semaphore};
+use tokio::task::JoinHandle;
+
+/// The page served at `/`.
+const INDEX: &str = include_str!("remote.html");
+
+/// How long a client has to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait before answering a request with the wrong token, to slow down guessing.
+const AUTH_FAILURE_DELAY: Duration = Duration::from_secs(1);
+
+/// How many connections are handled at once. Further connections wait to be accepted, so that a
+/// client opening many of them can't use up the device's memory.
+const MAX_CONNECTIONS: usize = 4;
+
+const MAX_HEADER_LINES: usize = 64;
+const MAX_LINE_LENGTH: usize = 8 * 1024;
+const MAX_BODY_LENGTH: usize = 64 * 1024;
+
+/// What a request asks alliumd to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteCommand {
+    Library,
+    Status,
+    Launch(PathBuf),
+    Volume(i32),
+    Brightness(u8),
+    Screenshot,
+}
+
+/// A command from a connection, and where to send the response.
+pub type RemoteRequest = (RemoteCommand, oneshot::Sender<Response>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Status {
+    pub game: Option<StatusGame>,
+    pub battery: i32,
+    pub charging: bool,
+    pub volume: i32,
+    pub brightness: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusGame {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchOutcome {
+    Launching,
+    /// A game, or the menu, is in the foreground, and isn't replaced from afar.
+    Busy,
+}
+
+/// What the companion API controls. alliumd implements it, and tests stub it.
+#[async_trait(?Send)]
+pub trait RemoteTarget {
+    /// Games folder of the active profile. Only games in it can be launched.
+    fn games_dir(&self) -> Result<PathBuf>;
+    fn library(&self) -> Result<Vec<u8>>;
+    fn status(&self) -> Result<Status>;
+    async fn launch(&mut self, path: &Path) -> Result<LaunchOutcome>;
+    fn set_volume(&mut self, volume: i32) -> Result<()>;
+    fn set_brightness(&mut self, brightness: u8) -> Result<()>;
+    /// Takes a screenshot, returning it as a PNG.
+    async fn screenshot(&mut self) -> Result<Vec<u8>>;
+}
+
+/// Carries out a command on the target.
+pub async fn dispatch(target: &mut impl RemoteTarget, command: RemoteCommand) -> Response {
+    let result = match command {
+        RemoteCommand::Library => target.library().map(Response::json_bytes),
+        RemoteCommand::Status => target.status().and_then(|status| Response::json(&status)),
+        RemoteCommand::Launch(path) => match target.games_dir() {
+            Ok(games_dir) => {
+                let path = games_dir.join(path);
+                if !path.starts_with(&games_dir)
+                    || path.components().any(|c| c == Component::ParentDir)
+                {
+                    return Response::error(403, "not in the library");
+                }
+                if !path.is_file() {
+                    return Response::error(404, "no such game");
+                }
+                // Links in the games folder may lead out of it
+                if !resolves_within(&path, &games_dir) {
+                    return Response::error(403, "not in the library");
+                }
+                target.launch(&path).await.map(|outcome| match outcome {
+                    LaunchOutcome::Launching => Response::empty(202),
+                    LaunchOutcome::Busy => Response::error(409, "a game is running"),
+                })
+            }
+            Err(e) => Err(e),
+        },
+        RemoteCommand::Volume(volume) => target.set_volume(volume).map(|_| Response::empty(204)),
+        RemoteCommand::Brightness(brightness) => target
+            .set_brightness(brightness)
+            .map(|_| Response::empty(204)),
+        RemoteCommand::Screenshot => target.screenshot().await.map(Response::png),
+    };
+    result.unwrap_or_else(|e| {
+        warn!("remote request failed: {}", e);
+        Response::error(500, &e.to_string())
+    })
+}
+
+/// Whether `path` is in `root` once links in either are followed.
+fn resolves_within(path: &Path, root: &Path) -> bool {
+    match (path.canonicalize(), root.canonicalize()) {
+        (Ok(path), Ok(root)) => path.starts_with(root),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// Path without the query string.
+    pub path: String,
+    /// Headers by lowercase name.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    fn token(&self) -> Option<&str> {
+        self.headers
+            .get("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+    }
+}
+
+/// Reads an HTTP/1.1 request, refusing ones that are too large.
+pub async fn read_request<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> Result<Request> {
+    let request_line = read_line(reader).await?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        bail!("invalid request line: {:?}", request_line);
+    };
+    if !version.starts_with("HTTP/1.") {
+        bail!("unsupported version: {}", version);
+    }
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+
+    let mut headers = HashMap::new();
+    loop {
+        let line = read_line(reader).await?;
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() >= MAX_HEADER_LINES {
+            bail!("too many headers");
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            bail!("invalid header: {:?}", line);
+        };
+        headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+    }
+
+    let length = match headers.get("content-length") {
+        Some(length) => length.parse::<usize>()?,
+        None => 0,
+    };
+    if length > MAX_BODY_LENGTH {
+        bail!("body is too large: {} bytes", length);
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers,
+        body,
+    })
+}
+
+async fn read_line<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> Result<String> {
+    let mut line = Vec::new();
+    reader
+        .take(MAX_LINE_LENGTH as u64)
+        .read_until(b'\n', &mut line)
+        .await?;
+    if line.last() != Some(&b'\n') {
+        bail!("line is too long, or the connection was closed");
+    }
+    let line = String::from_utf8(line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Where a request goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    Index,
+    Command(RemoteCommand),
+}
+
+#[derive(Deserialize)]
+struct LaunchBody {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct VolumeBody {
+    volume: i32,
+}
+
+#[derive(Deserialize)]
+struct BrightnessBody {
+    brightness: u8,
+}
+
+/// Works out what a request asks for, or the error response to send if it can't be done.
+pub fn route(request: &Request, token: &str) -> Result<Route, Response> {
+    let method = request.method.as_str();
+    let path = request.path.as_str();
+
+    let is_get = match path {
+        "/" | "/library" | "/status" => true,
+        "/launch" | "/volume" | "/brightness" | "/screenshot" => false,
+        _ => return Err(Response::error(404, "not found")),
+    };
+    if method != if is_get { "GET" } else { "POST" } {
+        return Err(Response::error(405, "method not allowed"));
+    }
+
+    if path == "/" {
+        return Ok(Route::Index);
+    }
+    if !request
+        .token()
+        .is_some_and(|sent| remote_token::matches(sent, token))
+    {
+        return Err(Response::error(401, "missing or wrong token"));
+    }
+
+    let command = match path {
+        "/library" => RemoteCommand::Library,
+        "/status" => RemoteCommand::Status,
+        "/launch" => RemoteCommand::Launch(parse_body::<LaunchBody>(request)?.path),
+        "/volume" => {
+            let volume = parse_body::<VolumeBody>(request)?.volume;
+            if !(0..=MAX_VOLUME).contains(&volume) {
+                return Err(Response::error(400, "volume is out of range"));
+            }
+            RemoteCommand::Volume(volume)
+        }
+        "/brightness" => {
+            let brightness = parse_body::<BrightnessBody>(request)?.brightness;
+            if brightness > 100 {
+                return Err(Response::error(400, "brightness is out of range"));
+            }
+            RemoteCommand::Brightness(brightness)
+        }
+        "/screenshot" => RemoteCommand::Screenshot,
+        _ => unreachable!(),
+    };
+    Ok(Route::Command(command))
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(request: &Request) -> Result<T, Response> {
+    serde_json::from_slice(&request.body).map_err(|e| Response::error(400, &e.to_string()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn empty(status: u16) -> Self {
+        Self {
+            status,
+            content_type: "text/plain",
+            body: Vec::new(),
+        }
+    }
+
+    fn json<T: Serialize>(value: &T) -> Result<Self> {
+        Ok(Self::json_bytes(serde_json::to_vec(value)?))
+    }
+
+    fn json_bytes(body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn png(body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            content_type: "image/png",
+            body,
+        }
+    }
+
+    fn html(body: &str) -> Self {
+        Self {
+            status: 200,
+            content_type: "text/html; charset=utf-8",
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::json!({ "error": message })
+                .to_string()
+                .into_bytes(),
+        }
+    }
+
+    async fn write_to<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            202 => "Accepted",
+            204 => "No Content",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        };
+        let head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len()
+        );
+        writer.write_all(head.as_bytes()).await?;
+        writer.write_all(&self.body).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+/// Accepts connections until the task is aborted, passing commands on to `requests`.
+pub async fn serve(listener: TcpListener, token: String, requests: mpsc::Sender<RemoteRequest>) {
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    loop {
+        // The semaphore is never closed
+        let Ok(permit) = connections.clone().acquire_owned().await else {
+            return;
+        };
+        let (stream, addr) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("failed to accept remote connection: {}", e);
+                continue;
+            }
+        };
+        let token = token.clone();
+        let requests = requests.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &token, requests).await {
+                debug!("remote connection from {} failed: {}", addr, e);
+            }
+            drop(permit);
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    token: &str,
+    requests: mpsc::Sender<RemoteRequest>,
+) -> Result<()> {
+    let mut stream = BufReader::new(stream);
+    let request = match tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => request,
+        Ok(Err(e)) => {
+            Response::error(400, &e.to_string())
+                .write_to(&mut stream)
+                .await?;
+            return Ok(());
+        }
+        Err(_) => bail!("timed out reading request"),
+    };
+    debug!("remote request: {} {}", request.method, request.path);
+
+    let response = match route(&request, token) {
+        Ok(Route::Index) => Response::html(INDEX),
+        Ok(Route::Command(command)) => {
+            let (tx, rx) = oneshot::channel();
+            requests
+                .send((command, tx))
+                .await
+                .map_err(|_| anyhow!("alliumd stopped handling requests"))?;
+            rx.await
+                .unwrap_or_else(|_| Response::error(503, "alliumd is busy"))
+        }
+        Err(response) => {
+            if response.status == 401 {
+                tokio::time::sleep(AUTH_FAILURE_DELAY).await;
+            }
+            response
+        }
+    };
+    response.write_to(&mut stream).await
+}
+
+/// Runs the server on the address it should be listening on, and hands its requests to alliumd.
+#[derive(Debug)]
+pub struct RemoteServer {
+    sender: mpsc::Sender<RemoteRequest>,
+    receiver: mpsc::Receiver<RemoteRequest>,
+    running: Option<Running>,
+}
+
+#[derive(Debug)]
+struct Running {
+    addr: SocketAddr,
+    token: String,
+    task: JoinHandle<()>,
+}
+
+impl RemoteServer {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel(8);
+        Self {
+            sender,
+            receiver,
+            running: None,
+        }
+    }
+
+    /// Starts, restarts or stops the server, so that it listens on `addr` and accepts `token`,
+    /// or doesn't listen at all if `addr` is `None`.
+    pub async fn sync(&mut self, addr: Option<SocketAddr>, token: &str) -> Result<()> {
+        if let Some(running) = &self.running {
+            if Some(running.addr) == addr && running.token == token {
+                return Ok(());
+            }
+        }
+        if let Some(running) = self.running.take() {
+            info!("stopping remote server on {}", running.addr);
+            running.task.abort();
+            // Wait for the listener to be dropped, so that the address can be bound again
+            let _ = running.task.await;
+        }
+
+        let Some(addr) = addr else {
+            return Ok(());
+        };
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        info!("serving remote API on {}", addr);
+        self.running = Some(Running {
+            addr,
+            token: token.to_string(),
+            task: tokio::spawn(serve(listener, token.to_string(), self.sender.clone())),
+        });
+        Ok(())
+    }
+
+    /// Address the server is listening on, if it is.
+    #[allow(unused)]
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.running.as_ref().map(|running| running.addr)
+    }
+
+    /// Waits for the next request.
+    pub async fn recv(&mut self) -> RemoteRequest {
+        // The server holds a sender, so the channel is never closed
+        self.receiver.recv().await.unwrap()
+    }
+}
+
+impl Default for RemoteServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        if let Some(running) = self.running.take() {
+            running.task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use common::battery::Battery;
+    use common::platform::{DefaultPlatform, Platform};
+    use common::test_utils::temp_dir;
+
+    use super::*;
+
+    const TOKEN: &str = "abcdefghjk";
+
+    /// alliumd, on the stubbed platform.
+    struct StubTarget {
+        platform: DefaultPlatform,
+        volume: i32,
+        brightness: u8,
+        launched: Vec<PathBuf>,
+        busy: bool,
+    }
+
+    impl StubTarget {
+        fn new() -> Self {
+            Self {
+                platform: DefaultPlatform::new().unwrap(),
+                volume: 10,
+                brightness: 50,
+                launched: Vec::new(),
+                busy: false,
+            }
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl RemoteTarget for StubTarget {
+        fn games_dir(&self) -> Result<PathBuf> {
+            Ok(std::env::temp_dir())
+        }
+
+        fn library(&self) -> Result<Vec<u8>> {
+            Ok(br#"{"version":1,"games":[]}"#.to_vec())
+        }
+
+        fn status(&self) -> Result<Status> {
+            let battery = self.platform.battery()?;
+            Ok(Status {
+                game: None,
+                battery: battery.percentage(),
+                charging: battery.charging(),
+                volume: self.volume,
+                brightness: self.brightness,
+            })
+        }
+
+        async fn launch(&mut self, path: &Path) -> Result<LaunchOutcome> {
+            if self.busy {
+                return Ok(LaunchOutcome::Busy);
+            }
+            self.launched.push(path.to_path_buf());
+            Ok(LaunchOutcome::Launching)
+        }
+
+        fn set_volume(&mut self, volume: i32) -> Result<()> {
+            self.platform.set_volume(volume)?;
+            self.volume = volume;
+            Ok(())
+        }
+
+        fn set_brightness(&mut self, brightness: u8) -> Result<()> {
+            self.platform.set_brightness(brightness)?;
+            self.brightness = brightness;
+            Ok(())
+        }
+
+        async fn screenshot(&mut self) -> Result<Vec<u8>> {
+            Ok(b"\x89PNG".to_vec())
+        }
+    }
+
+    fn request(method: &str, path: &str, token: Option<&str>, body: &str) -> Request {
+        let mut headers = HashMap::new();
+        if let Some(token) = token {
+            headers.insert("authorization".to_string(), format!("Bearer {token}"));
+        }
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers,
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn status_of(result: Result<Route, Response>) -> u16 {
+        result.err().map_or(200, |response| response.status)
+    }
+
+    #[test]
+    fn test_route() {
+        // The page asks for the token itself
+        assert_eq!(
+            route(&request("GET", "/", None, ""), TOKEN),
+            Ok(Route::Index)
+        );
+
+        assert_eq!(
+            status_of(route(&request("GET", "/status", None, ""), TOKEN)),
+            401
+        );
+        assert_eq!(
+            status_of(route(
+                &request("GET", "/status", Some("abcdefghjm"), ""),
+                TOKEN
+            )),
+            401
+        );
+        assert_eq!(
+            route(&request("GET", "/status", Some(TOKEN), ""), TOKEN),
+            Ok(Route::Command(RemoteCommand::Status))
+        );
+        assert_eq!(
+            status_of(route(&request("GET", "/nope", Some(TOKEN), ""), TOKEN)),
+            404
+        );
+        assert_eq!(
+            status_of(route(&request("GET", "/volume", Some(TOKEN), ""), TOKEN)),
+            405
+        );
+        assert_eq!(
+            status_of(route(&request("POST", "/library", Some(TOKEN), ""), TOKEN)),
+            405
+        );
+
+        assert_eq!(
+            route(
+                &request(
+                    "POST",
+                    "/launch",
+                    Some(TOKEN),
+                    r#"{"path":"/Roms/GBA/a.gba"}"#
+                ),
+                TOKEN
+            ),
+            Ok(Route::Command(RemoteCommand::Launch(PathBuf::from(
+                "/Roms/GBA/a.gba"
+            ))))
+        );
+        assert_eq!(
+            route(
+                &request("POST", "/volume", Some(TOKEN), r#"{"volume":20}"#),
+                TOKEN
+            ),
+            Ok(Route::Command(RemoteCommand::Volume(20)))
+        );
+        assert_eq!(
+            status_of(route(
+                &request("POST", "/volume", Some(TOKEN), r#"{"volume":21}"#),
+                TOKEN
+            )),
+            400
+        );
+        assert_eq!(
+            status_of(route(
+                &request("POST", "/brightness", Some(TOKEN), r#"{"brightness":101}"#),
+                TOKEN
+            )),
+            400
+        );
+        assert_eq!(
+            status_of(route(
+                &request("POST", "/brightness", Some(TOKEN), "not json"),
+                TOKEN
+            )),
+            400
+        );
+        // The token is checked before the body
+        assert_eq!(
+            status_of(route(&request("POST", "/launch", None, "not json"), TOKEN)),
+            401
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let raw = "POST /volume?x=1 HTTP/1.1\r\nHost: allium\r\nAuthorization: Bearer abc\r\nContent-Length: 13\r\n\r\n{\"volume\":3}\n";
+        let request = read_request(&mut BufReader::new(raw.as_bytes()))
+            .await
+            .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/volume");
+        assert_eq!(request.token(), Some("abc"));
+        assert_eq!(request.body, b"{\"volume\":3}\n");
+
+        let too_large = format!(
+            "POST /launch HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_LENGTH + 1
+        );
+        assert!(read_request(&mut BufReader::new(too_large.as_bytes()))
+            .await
+            .is_err());
+        assert!(
+            read_request(&mut BufReader::new(&b"GET /status\r\n\r\n"[..]))
+                .await
+                .is_err()
+        );
+        let long_line = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE_LENGTH));
+        assert!(read_request(&mut BufReader::new(long_line.as_bytes()))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch() {
+        let mut target = StubTarget::new();
+
+        let response = dispatch(&mut target, RemoteCommand::Volume(4)).await;
+        assert_eq!(response.status, 204);
+        assert_eq!(target.volume, 4);
+        let response = dispatch(&mut target, RemoteCommand::Brightness(80)).await;
+        assert_eq!(response.status, 204);
+        assert_eq!(target.brightness, 80);
+
+        let response = dispatch(&mut target, RemoteCommand::Status).await;
+        assert_eq!(response.content_type, "application/json");
+        let status: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(status["volume"], 4);
+        assert_eq!(status["brightness"], 80);
+        assert_eq!(status["battery"], 50);
+        assert!(status["game"].is_null());
+
+        let game = std::env::temp_dir().join(format!("allium-remote-{}.gba", std::process::id()));
+        std::fs::write(&game, b"rom").unwrap();
+        let missing = game.with_extension("gbc");
+        let response = dispatch(&mut target, RemoteCommand::Launch(missing)).await;
+        assert_eq!(response.status, 404);
+        let response = dispatch(&mut target, RemoteCommand::Launch(game.clone())).await;
+        assert_eq!(response.status, 202);
+        // As listed in the library export
+        let relative = PathBuf::from(game.file_name().unwrap());
+        let response = dispatch(&mut target, RemoteCommand::Launch(relative)).await;
+        assert_eq!(response.status, 202);
+        assert_eq!(target.launched, vec![game.clone(), game.clone()]);
+        let outside = PathBuf::from("/etc/passwd");
+        let response = dispatch(&mut target, RemoteCommand::Launch(outside)).await;
+        assert_eq!(response.status, 403);
+        let escape = PathBuf::from("../../etc/passwd");
+        let response = dispatch(&mut target, RemoteCommand::Launch(escape)).await;
+        assert_eq!(response.status, 403);
+        let dir = temp_dir("remote-link");
+        let link = dir.join("Passwd.gba");
+        std::os::unix::fs::symlink("/etc/passwd", &link).unwrap();
+        let response = dispatch(&mut target, RemoteCommand::Launch(link)).await;
+        assert_eq!(response.status, 403);
+        std::fs::remove_dir_all(&dir).unwrap();
+        target.busy = true;
+        let response = dispatch(&mut target, RemoteCommand::Launch(game.clone())).await;
+        assert_eq!(response.status, 409);
+        std::fs::remove_file(&game).unwrap();
+
+        let response = dispatch(&mut target, RemoteCommand::Screenshot).await;
+        assert_eq!(response.content_type, "image/png");
+    }
+
+    /// Sends a request with a real HTTP client, returning the status and body.
+    async fn send(
+        addr: SocketAddr,
+        method: &'static str,
+        path: &'static str,
+        token: Option<&'static str>,
+        body: Option<&'static str>,
+    ) -> (u16, String) {
+        tokio::task::spawn_blocking(move || {
+            let mut request = ureq::request(method, &format!("http://{addr}{path}"));
+            if let Some(token) = token {
+                request = request.set("Authorization", &format!("Bearer {token}"));
+            }
+            let result = match body {
+                Some(body) => request
+                    .set("Content-Type", "application/json")
+                    .send_string(body),
+                None => request.call(),
+            };
+            let response = match result {
+                Ok(response) => response,
+                Err(ureq::Error::Status(_, response)) => response,
+                Err(e) => panic!("request failed: {e}"),
+            };
+            (response.status(), response.into_string().unwrap())
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_server() {
+        let mut server = RemoteServer::new();
+        let localhost = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        server.sync(Some(localhost), TOKEN).await.unwrap();
+        let addr = server.addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let (status, body) = send(addr, "GET", "/", None, None).await;
+            assert_eq!(status, 200);
+            assert!(body.contains("<html"));
+
+            let (status, _) = send(addr, "GET", "/status", Some("wrongtoken"), None).await;
+            assert_eq!(status, 401);
+
+            let (status, body) = send(addr, "GET", "/library", Some(TOKEN), None).await;
+            assert_eq!(status, 200);
+            assert!(body.contains("games"));
+
+            let (status, _) = send(addr, "POST", "/brightness", Some(TOKEN), Some("{}")).await;
+            assert_eq!(status, 400);
+            let body = Some(r#"{"brightness":30}"#);
+            let (status, _) = send(addr, "POST", "/brightness", Some(TOKEN), body).await;
+            assert_eq!(status, 204);
+        });
+
+        // alliumd's side of the channel, until the client is done
+        let mut target = StubTarget::new();
+        tokio::pin!(client);
+        loop {
+            tokio::select! {
+                (command, reply) = server.recv() => {
+                    let _ = reply.send(dispatch(&mut target, command).await);
+                }
+                result = &mut client => {
+                    result.unwrap();
+                    break;
+                }
+            }
+        }
+        assert_eq!(target.brightness, 30);
+
+        // A new token replaces the old one
+        server.sync(Some(addr), "mnpqrstuvw").await.unwrap();
+        let addr = server.addr().unwrap();
+        let (status, _) = send(addr, "GET", "/status", Some(TOKEN), None).await;
+        assert_eq!(status, 401);
+
+        // Stopping the server closes the port
+        server.sync(None, TOKEN).await.unwrap();
+        assert!(server.addr().is_none());
+        tokio::task::yield_now().await;
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connection_cap() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (requests, _rx) = mpsc::channel(1);
+        let server = tokio::spawn(serve(listener, TOKEN.to_string(), requests));
+
+        let mut idle = Vec::new();
+        for _ in 0..MAX_CONNECTIONS {
+            idle.push(TcpStream::connect(addr).await.unwrap());
+        }
+        let mut extra = TcpStream::connect(addr).await.unwrap();
+        extra.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+
+        // Waits until one of the others is closed
+        let mut status = [0; 12];
+        let read = tokio::time::timeout(Duration::from_millis(200), extra.read_exact(&mut status));
+        assert!(read.await.is_err());
+        idle.pop();
+        tokio::time::timeout(REQUEST_TIMEOUT, extra.read_exact(&mut status))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&status, b"HTTP/1.1 200");
+
+        server.abort();
+    }
+}
@@ -0,0 +1,263 @@
+//! Switches from the running game straight to another one picked in the in-game menu, without
+//! going back to the launcher. The old game is quit with the same care as when powering off: it
+//! gets the grace period to exit before it is killed, and its play time is recorded once it is
+//! gone.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::game_info::SwitchRequest;
+use log::{info, warn};
+use tokio::process::Child;
+
+use crate::alliumd::terminate;
+
+/// What switching games acts on.
+#[async_trait(?Send)]
+pub trait SwitchTarget {
+    /// The main process, running the current game.
+    fn main(&mut self) -> &mut Child;
+    /// Saves the current game's state to the auto slot, waiting for it to be written.
+    async fn save_state(&mut self) -> Result<()>;
+    /// Records the play time of the game that just exited, and whether it had to be killed, then
+    /// clears its game info so that it isn't resumed.
+    fn finish_game(&mut self, killed: bool) -> Result<()>;
+    /// Starts a main process that launches the game at `path` directly.
+    fn launch(&mut self, path: &Path) -> Result<Child>;
+    /// Lets the current game carry on, when it isn't quit after all.
+    async fn resume(&mut self) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchOutcome {
+    /// The old game was quit and the new one is launching.
+    Switched,
+    /// The game to switch to is missing, so the current game was resumed.
+    Missing,
+    /// The old game was quit, but the new one couldn't be started.
+    LaunchFailed,
+}
+
+/// Quits the current game and launches the requested one in its place. If the new game can't be
+/// started, the main process is left exited so that the launcher takes over.
+pub async fn switch_game(
+    target: &mut impl SwitchTarget,
+    request: &SwitchRequest,
+    grace: Duration,
+) -> Result<SwitchOutcome> {
+    // The menu checked too, but the card may have been swapped since
+    if !request.path.exists() {
+        warn!("can't switch to {}, it is missing", request.path.display());
+        target.resume().await?;
+        return Ok(SwitchOutcome::Missing);
+    }
+
+    info!("switching to {}", request.path.display());
+    if request.save_state {
+        if let Err(e) = target.save_state().await {
+            warn!("failed to save state before switching: {}", e);
+        }
+    }
+
+    let killed = terminate(target.main(), grace).await?;
+    if killed {
+        warn!("game did not exit in time, killed");
+    }
+    target.finish_game(killed)?;
+
+    match target.launch(&request.path) {
+        Ok(main) => {
+            *target.main() = main;
+            Ok(SwitchOutcome::Switched)
+        }
+        Err(e) => {
+            warn!("failed to launch {}: {}", request.path.display(), e);
+            Ok(SwitchOutcome::LaunchFailed)
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+    use std::path::PathBuf;
+    use std::process::Stdio;
+
+    use anyhow::bail;
+    use nix::sys::signal::Signal;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::process::Command;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Call {
+        SaveState,
+        /// With the signal that ended the old game, if it had exited by then.
+        FinishGame {
+            killed: bool,
+            signal: Option<i32>,
+        },
+        Launch(PathBuf),
+        Resume,
+    }
+
+    /// alliumd, with stub processes in place of the game and the launcher.
+    struct StubTarget {
+        main: Child,
+        can_launch: bool,
+        calls: Vec<Call>,
+    }
+
+    impl StubTarget {
+        fn new(main: Child) -> Self {
+            Self {
+                main,
+                can_launch: true,
+                calls: Vec::new(),
+            }
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl SwitchTarget for StubTarget {
+        fn main(&mut self) -> &mut Child {
+            &mut self.main
+        }
+
+        async fn save_state(&mut self) -> Result<()> {
+            self.calls.push(Call::SaveState);
+            Ok(())
+        }
+
+        fn finish_game(&mut self, killed: bool) -> Result<()> {
+            let signal = self.main.try_wait()?.and_then(|status| status.signal());
+            self.calls.push(Call::FinishGame { killed, signal });
+            Ok(())
+        }
+
+        fn launch(&mut self, path: &Path) -> Result<Child> {
+            self.calls.push(Call::Launch(path.to_path_buf()));
+            if !self.can_launch {
+                bail!("launcher is missing");
+            }
+            Ok(sleep()?)
+        }
+
+        async fn resume(&mut self) -> Result<()> {
+            self.calls.push(Call::Resume);
+            Ok(())
+        }
+    }
+
+    fn sleep() -> std::io::Result<Child> {
+        Command::new("sleep").arg("10").kill_on_drop(true).spawn()
+    }
+
+    /// A file that exists, to switch to.
+    fn game() -> PathBuf {
+        std::env::current_exe().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_switches_after_game_exits() -> Result<()> {
+        let mut target = StubTarget::new(sleep()?);
+        let old = target.main.id();
+        let request = SwitchRequest {
+            path: game(),
+            save_state: true,
+        };
+
+        let outcome = switch_game(&mut target, &request, Duration::from_secs(5)).await?;
+
+        assert_eq!(outcome, SwitchOutcome::Switched);
+        assert_eq!(
+            target.calls,
+            vec![
+                Call::SaveState,
+                Call::FinishGame {
+                    killed: false,
+                    signal: Some(Signal::SIGTERM as i32),
+                },
+                Call::Launch(game()),
+            ]
+        );
+        assert_ne!(target.main.id(), old);
+        assert!(target.main.try_wait()?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_kills_game_that_refuses_to_exit() -> Result<()> {
+        // Ignores SIGTERM like a frozen core that doesn't respond to it
+        let mut main = Command::new("sh")
+            .arg("-c")
+            .arg("trap '' TERM; echo ready; exec sleep 10")
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let mut ready = String::new();
+        BufReader::new(main.stdout.take().unwrap())
+            .read_line(&mut ready)
+            .await?;
+        let mut target = StubTarget::new(main);
+        let request = SwitchRequest {
+            path: game(),
+            save_state: false,
+        };
+
+        let start = std::time::Instant::now();
+        let outcome = switch_game(&mut target, &request, Duration::from_millis(200)).await?;
+
+        assert_eq!(outcome, SwitchOutcome::Switched);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert_eq!(
+            target.calls,
+            vec![
+                Call::FinishGame {
+                    killed: true,
+                    signal: Some(Signal::SIGKILL as i32),
+                },
+                Call::Launch(game()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_launch_failure_leaves_main_exited() -> Result<()> {
+        let mut target = StubTarget::new(sleep()?);
+        target.can_launch = false;
+        let request = SwitchRequest {
+            path: game(),
+            save_state: false,
+        };
+
+        let outcome = switch_game(&mut target, &request, Duration::from_secs(5)).await?;
+
+        // The exited main process is noticed by the event loop, which starts the launcher
+        assert_eq!(outcome, SwitchOutcome::LaunchFailed);
+        assert!(target.main.try_wait()?.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_game_resumes_current_one() -> Result<()> {
+        let mut target = StubTarget::new(sleep()?);
+        let old = target.main.id();
+        let request = SwitchRequest {
+            path: PathBuf::from("/nonexistent/Game.gba"),
+            save_state: true,
+        };
+
+        let outcome = switch_game(&mut target, &request, Duration::from_secs(5)).await?;
+
+        assert_eq!(outcome, SwitchOutcome::Missing);
+        assert_eq!(target.calls, vec![Call::Resume]);
+        assert_eq!(target.main.id(), old);
+        assert!(target.main.try_wait()?.is_none());
+        Ok(())
+    }
+}
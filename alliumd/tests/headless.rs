@@ -0,0 +1,247 @@
+//! Runs alliumd headless through a whole game session: booting into the launcher, launching a
+//! game, opening and closing the ingame menu, suspending the game under the launcher and resuming
+//! it, and quitting the game from the menu.
+#![cfg(unix)]
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use common::database::{Database, NewGame};
+use common::game_info::{GameInfo, MENU_EXIT_SUSPEND_GAME, MENU_EXIT_TERMINATE_MAIN};
+use common::test_utils::temp_dir;
+use nix::fcntl::OFlag;
+use nix::sys::signal::{kill, Signal};
+use nix::sys::stat::Mode;
+use nix::unistd::{mkfifo, Pid};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long ago the game claims to have started, so that it isn't taken for a failed launch.
+const PLAYED_SECS: i64 = 60;
+
+/// alliumd running headless in a directory of its own, killed if the test fails.
+struct Daemon {
+    dir: PathBuf,
+    child: Child,
+    input: Option<fs::File>,
+}
+
+impl Daemon {
+    fn spawn(dir: &Path) -> Self {
+        let input = dir.join("input");
+        mkfifo(input.as_path(), Mode::S_IRUSR | Mode::S_IWUSR).unwrap();
+
+        // Like the launcher, launching execs the game, which records itself in the game info
+        let start_time = (Utc::now() - chrono::Duration::seconds(PLAYED_SECS)).to_rfc3339();
+        let launcher = format!(
+            r#"echo launcher >> "{dir}/launcher.log"
+            if [ -n "$ALLIUM_LAUNCH" ]; then
+                printf '{{"name":"Game","path":"%s","command":"","args":[],"has_menu":true,"image":null,"guide":null,"start_time":"{start_time}","pid":%d}}' "$ALLIUM_LAUNCH" $$ > "{dir}/game.json"
+                mv "{dir}/game.json" "{dir}/.allium/state/current_game"
+                trap 'exit 0' TERM
+                while :; do sleep 0.1; done
+            fi
+            exec sleep 60"#,
+            dir = dir.display(),
+        );
+        // Quits or suspends the game once asked to
+        let menu = format!(
+            r#"echo menu >> "{dir}/menu.log"
+            [ -e "{dir}/quit" ] && exit {MENU_EXIT_TERMINATE_MAIN}
+            [ -e "{dir}/suspend" ] && rm "{dir}/suspend" && exit {MENU_EXIT_SUSPEND_GAME}
+            exec sleep 60"#,
+            dir = dir.display(),
+        );
+
+        let child = Command::new(env!("CARGO_BIN_EXE_alliumd"))
+            .arg("--headless")
+            .current_dir(dir)
+            .env("ALLIUM_SD_ROOT", dir)
+            .env("ALLIUM_BASE_DIR", dir.join(".allium"))
+            .env("ALLIUM_DATABASE", dir.join("allium.db"))
+            .env("ALLIUMD_LAUNCHER_COMMAND", launcher)
+            .env("ALLIUMD_MENU_COMMAND", menu)
+            .env("ALLIUMD_INPUT", &input)
+            .stdout(Stdio::null())
+            .stderr(fs::File::create(dir.join("alliumd.log")).unwrap())
+            .spawn()
+            .unwrap();
+
+        let mut daemon = Self {
+            dir: dir.to_path_buf(),
+            child,
+            input: None,
+        };
+        // Opening the FIFO fails until alliumd has opened it for reading
+        daemon.wait_for("the input FIFO", |_| {
+            OpenOptions::new()
+                .write(true)
+                .custom_flags(OFlag::O_NONBLOCK.bits())
+                .open(&input)
+                .is_ok()
+        });
+        daemon.input = OpenOptions::new().write(true).open(&input).ok();
+        daemon
+    }
+
+    fn inject(&mut self, lines: &[&str]) {
+        let input = self.input.as_mut().unwrap();
+        for line in lines {
+            writeln!(input, "{}", line).unwrap();
+        }
+    }
+
+    fn press(&mut self, key: &str) {
+        self.inject(&[&format!("press {}", key), &format!("release {}", key)]);
+    }
+
+    /// The current game info. Read here rather than with `GameInfo::load`, which removes a file it
+    /// can't parse, such as one alliumd is halfway through writing.
+    fn game_info(&self) -> Option<GameInfo> {
+        let json = fs::read_to_string(self.dir.join(".allium/state/current_game")).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    fn has_game_info(&self) -> bool {
+        self.dir.join(".allium/state/current_game").exists()
+    }
+
+    /// How many times the dummy command logging to `log` has been started.
+    fn started(&self, log: &str) -> usize {
+        fs::read_to_string(self.dir.join(log)).map_or(0, |log| log.lines().count())
+    }
+
+    fn wait_for(&mut self, what: &str, mut done: impl FnMut(&mut Self) -> bool) {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            // Checked first, in case alliumd exiting is what's being waited for
+            let exited = self.child.try_wait().unwrap().is_some();
+            if done(self) {
+                return;
+            }
+            if exited || Instant::now() > deadline {
+                panic!(
+                    "gave up waiting for {}, alliumd logged:\n{}",
+                    what,
+                    fs::read_to_string(self.dir.join("alliumd.log")).unwrap_or_default()
+                );
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+/// The state of process `pid`, e.g. `T` while it is stopped.
+fn process_state(pid: u32) -> Option<char> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    stat.rsplit_once(')')?.1.trim_start().chars().next()
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn test_game_session() {
+    let dir = temp_dir("headless");
+    fs::create_dir_all(dir.join(".allium/state")).unwrap();
+    // Read by the constants common uses here too, so set before anything is loaded
+    std::env::set_var("ALLIUM_SD_ROOT", &dir);
+    std::env::set_var("ALLIUM_BASE_DIR", dir.join(".allium"));
+    std::env::set_var("ALLIUM_DATABASE", dir.join("allium.db"));
+
+    let rom = dir.join("Roms/GBA/Game.gba");
+    let database = Database::new().unwrap();
+    database
+        .update_games(&[NewGame {
+            name: "Game".to_string(),
+            path: rom.clone(),
+            image: None,
+            core: None,
+        }])
+        .unwrap();
+    drop(database);
+
+    let mut daemon = Daemon::spawn(&dir);
+    daemon.wait_for("the launcher", |d| d.started("launcher.log") == 1);
+
+    daemon.inject(&[&format!("launch {}", rom.display())]);
+    daemon.wait_for("the game", |d| d.game_info().is_some());
+    assert_eq!(daemon.started("launcher.log"), 2);
+    let game_info = daemon.game_info().unwrap();
+    assert_eq!(game_info.path, rom);
+    assert!(game_info.is_running());
+
+    // Opening the menu pauses the game, and closing it resumes it
+    daemon.press("Menu");
+    daemon.wait_for("the menu", |d| d.started("menu.log") == 1);
+    daemon.wait_for("the game to pause", |d| {
+        d.game_info().is_some_and(|g| g.paused_at.is_some())
+    });
+    daemon.press("Menu");
+    daemon.wait_for("the game to resume", |d| {
+        d.game_info().is_some_and(|g| g.paused_at.is_none())
+    });
+
+    // Going to the launcher stops the game and keeps it paused in the background
+    let pid = game_info.pid.unwrap();
+    fs::write(dir.join("suspend"), "").unwrap();
+    daemon.press("Menu");
+    daemon.wait_for("the launcher over the game", |d| {
+        d.started("launcher.log") == 3
+    });
+    daemon.wait_for("the game to stop", |_| process_state(pid) == Some('T'));
+    assert_eq!(daemon.started("menu.log"), 2);
+    let suspended = daemon.game_info().unwrap();
+    assert!(suspended.is_running());
+    assert!(suspended.paused_at.is_some());
+
+    // The menu isn't opened over the launcher
+    daemon.press("Menu");
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(daemon.started("menu.log"), 2);
+
+    // Resuming, as the launcher asks to, closes the launcher and continues the game
+    kill(Pid::from_raw(daemon.child.id() as i32), Signal::SIGUSR2).unwrap();
+    daemon.wait_for("the game to resume", |d| {
+        process_state(pid).is_some_and(|state| state != 'T')
+            && d.game_info().is_some_and(|g| g.paused_at.is_none())
+    });
+    assert_eq!(daemon.started("launcher.log"), 3);
+
+    // Quitting from the menu records the play time and goes back to the launcher
+    fs::write(dir.join("quit"), "").unwrap();
+    daemon.press("Menu");
+    daemon.wait_for("the launcher to return", |d| {
+        d.started("launcher.log") == 4 && !d.has_game_info()
+    });
+    assert_eq!(daemon.started("menu.log"), 3);
+    assert!(!game_info.is_running());
+
+    let game = Database::new()
+        .unwrap()
+        .select_game(&rom.display().to_string())
+        .unwrap()
+        .unwrap();
+    assert!(
+        game.play_time >= chrono::Duration::seconds(PLAYED_SECS),
+        "recorded {} of play time",
+        game.play_time
+    );
+
+    kill(Pid::from_raw(daemon.child.id() as i32), Signal::SIGTERM).unwrap();
+    daemon.wait_for("alliumd to stop", |d| d.child.try_wait().unwrap().is_some());
+    assert!(daemon.child.wait().unwrap().success());
+
+    drop(daemon);
+    fs::remove_dir_all(&dir).unwrap();
+}
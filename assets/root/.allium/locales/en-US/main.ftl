@@ -7,17 +7,136 @@ tab-settings = Settings
 sort-alphabetical = Sort: A-Z
 sort-last-played = Sort: Recent
 sort-most-played = Sort: Playtime
+sort-recently-added = Sort: New
 sort-random = Sort: Random
+sort-favorites = Sort: Favorites
 sort-search = Search
 
+list-position = { $position } / { $total }
+list-filtered = { $shown } of { $total } shown
+list-empty = 0 items
+entries-empty = No games found
+entries-unreadable = Couldn't read this folder
+directory-game-count = ({ $count })
+
+continue-title = Continue
+continue-play-time = Played { $time }
+continue-play = Play
+continue-browse = Recent games
+
+button-favorite = Favorite
+button-remove-from-recents = Remove
+button-pin = Pin
+button-unpin = Unpin
+button-unfavorite = Unfavorite
+letter-jump-folders = Folders
+
+quick-filter-all = All games
+quick-filter-decade = { $decade }s
+
 populating-database = Populating database...
     This may take several minutes.
     Go grab a coffee!
 
 menu-launch = Launch
 menu-launch-with-core = Launch with { $core }
+menu-launch-with-state = Launch from Save State
+menu-launch-state-slot = Slot { $slot }: { $time }
 menu-remove-from-recents = Remove from Recents
+menu-notes = Notes
+menu-game-options = Game Options
 menu-repopulate-database = Repopulate Database
+menu-folder-settings = Folder Settings
+menu-select-multiple = Select Multiple
+menu-clear-recents = Clear Recents
+menu-hide = Hide
+menu-unhide = Unhide
+menu-scrape-box-art = Download Box Art
+menu-redownload-box-art = Download All Box Art Again
+menu-random-game = Random Game
+menu-add-to-collection = Add to Collection
+menu-remove-from-collection = Remove from Collection
+menu-rename-collection = Rename Collection
+menu-delete-collection = Delete Collection
+menu-rename = Rename
+menu-delete = Delete
+
+undo-offer = { $action } — press Y to undo
+undo-delete-games = Deleted { $count } games
+undo-delete-game = Deleted { $name }
+undo-remove-from-recents = Removed { $name } from Recents
+undo-clear-recents = Cleared Recents
+undo-done = Undone
+undo-failed = Couldn't undo: { $reason }
+
+confirm-delete-game = Delete { $name } ({ $size })?
+
+folder-config-invalid = Couldn't read { $path }: { $error }
+
+relink-found = Found at { $path } — update entry?
+relink-cancel = Don't Update
+
+random-game-play = Play { $name }
+random-game-reroll = Pick Another
+random-game-cancel = Cancel
+random-game-none = No games to pick from
+
+collection-new = New Collection…
+collection-added = Added to { $name }
+collection-exists = There's already a collection named { $name }
+collection-confirm-delete = Delete { $name }?
+collection-cancel = Keep It
+
+folder-view-box-art = Box Art
+folder-view-full-names = Full Names
+folder-view-grid = Grid
+folder-view-on = On
+folder-view-off = Off
+folder-view-setting = { $name }: { $value } ({ $source })
+folder-view-reset = Reset to Defaults
+folder-view-source-global = default
+folder-view-source-console = console default
+folder-view-source-folder = this folder
+
+batch-toggle = Toggle
+batch-select-all = Select All
+batch-select-none = Select None
+batch-hide = Hide
+batch-mark-completed = Mark as Completed
+batch-scrape-art = Scrape Box Art
+batch-delete = Delete
+batch-cancel = Cancel
+box-art-scrape-title = Downloading box art
+box-art-scrape-progress = { $done }/{ $total } games
+box-art-scrape-done = Found box art for { $found } of { $total } games
+box-art-scrape-nothing = All games in this folder have box art
+box-art-scrape-no-wifi = Connect to WiFi to download box art
+batch-confirm-delete = Delete { $count } games ({ $size })
+batch-discard-selection = Discard { $count } selected
+batch-keep-selecting = Keep Selecting
+batch-hide-progress = Hiding games... { $done }/{ $total }
+batch-mark-completed-progress = Marking games as completed... { $done }/{ $total }
+batch-scrape-art-progress = Queueing box art... { $done }/{ $total }
+batch-delete-progress = Deleting games... { $done }/{ $total }
+batch-done = Done: { $succeeded } of { $total } games
+batch-more-errors = ...and { $count } more errors
+
+art-placeholder-search = Press Start to search for art
+art-placeholder-searching = Searching for art...
+art-placeholder-path = Put a PNG at { $path }
+art-placeholder-not-found = No art was found. Put a PNG at { $path }
+
+profile-chooser-title = Who's playing?
+profile-chooser-wrong-pin = Wrong PIN
+
+legacy-migration-title = Found games from another firmware
+legacy-migration-folder = { $legacy } > { $target }
+legacy-migration-move = Move
+legacy-migration-link = Link
+legacy-migration-keep = Keep
+legacy-migration-skip = Skip
+legacy-migration-done = Your game folders are ready.
+legacy-migration-failed = Failed to migrate game folders.
 
 settings-wifi = Wi-Fi
 settings-wifi-wifi-enabled = Wi-Fi Enabled
@@ -27,6 +146,8 @@ settings-wifi-wifi-password = Wi-Fi Password
 settings-wifi-ntp-enabled = NTP Enabled
 settings-wifi-telnet-enabled = Telnet Enabled
 settings-wifi-ftp-enabled = FTP Enabled
+settings-wifi-remote-enabled = Remote Control
+settings-wifi-remote-token = Remote Token
 settings-wifi-connecting= Connecting...
 
 settings-clock = Date & Time
@@ -42,6 +163,11 @@ settings-display-red = Red
 settings-display-green = Green
 settings-display-blue = Blue
 settings-display-screen-resolution = Screen Resolution
+settings-display-boot-splash = Boot Splash
+settings-display-density = Density
+settings-display-density-compact = Compact
+settings-display-density-normal = Normal
+settings-display-density-comfortable = Comfortable
 
 settings-theme = Theme
 settings-theme-dark-mode = Dark Mode
@@ -57,12 +183,120 @@ settings-theme-button-a-color = Button A Color
 settings-theme-button-b-color = Button B Color
 settings-theme-button-x-color = Button X Color
 settings-theme-button-y-color = Button Y Color
+settings-theme-greyscale = Greyscale
+settings-theme-dithering = Greyscale Dithering
+settings-theme-dithering-none = None
+settings-theme-dithering-ordered = Ordered
+settings-theme-dark-mode-scheduled = Dark Mode (Scheduled)
+settings-theme-schedule = Theme Schedule
+settings-theme-schedule-off = Off
+settings-theme-schedule-times = Fixed Times
+settings-theme-schedule-sunset = Sunset
+settings-theme-light-from = Light From
+settings-theme-dark-from = Dark From
+settings-theme-latitude = Latitude
+settings-theme-longitude = Longitude
+settings-theme-editing-day = Editing the day theme
+settings-theme-editing-night = Editing the night theme
+settings-theme-confirm = Keep these settings? Reverting in { $seconds }s
+settings-theme-invalid = Theme not applied: { $reason }
+settings-theme-failed = Theme couldn't be applied, reverted.
 
 settings-language = Language
 settings-language-language = Language
 
 settings-files = Files
 
+settings-ingame-menu = Ingame Menu
+settings-ingame-menu-backup-states = Back Up Overwritten States
+settings-ingame-menu-save-on-switch = Save State When Switching Games
+
+settings-library = Library
+settings-library-export = Export Library JSON
+settings-library-export-done = Exported library to { $path }
+settings-library-export-failed = Failed to export library
+settings-library-report = Export Library Report
+settings-library-report-started = Writing library report (about { $size })
+settings-library-report-done = Wrote library report to { $path }
+settings-library-report-failed = Failed to write library report
+settings-library-show-hidden = Show Hidden Games
+settings-library-wrap-pages = Wrap Around When Paging
+settings-library-rescan = Rescan Library
+settings-library-rescan-started = Rescanning the games folder
+settings-library-rescan-done = Rescanned { $count } folders
+settings-library-clear-recents = Clear Recent Games
+settings-library-clear-recents-confirm = Clear the list of recently played games?
+settings-library-clear-recents-empty = No recent games to clear
+settings-library-import-play-time = Import Play Time
+settings-library-import-none = No play time from other firmware found
+settings-library-import-source = From
+settings-library-import-matched = Games Matched
+settings-library-import-ambiguous = Ambiguous Games
+settings-library-import-unmatched = Games Not Found
+settings-library-import-total = Play Time
+settings-library-import-hours = { $hours } h
+settings-library-import = Import
+settings-library-import-nothing = Nothing new to import
+settings-library-import-done = Imported { $hours } for { $count } games
+settings-library-import-failed = Failed to import play time
+library-report-title = Game Library
+library-report-completed = Completed
+library-report-favorite = Favorite
+library-report-other = Other
+settings-library-scrape = Scrape Missing Art
+settings-library-scrape-queued = Queued { $count } games for scraping
+settings-library-scrape-failures = Failed Items
+settings-library-scrape-no-failures = No failed items
+settings-library-scrape-more-failures = ...and { $count } more
+settings-library-scrape-retry = Retry Failed
+settings-library-scrape-clear = Clear Scrape Queue
+settings-library-scrape-cleared = Scrape queue cleared
+settings-library-orphaned-art = Unused Box Art
+settings-library-orphaned-art-count = { $count } box art images don't match any game
+settings-library-orphaned-art-indexing = Still indexing box art, try again shortly
+settings-library-natural-order = Sort Numbers by Value
+settings-library-tie-break = Then Sort Played Games By
+settings-library-tie-break-name = Name
+settings-library-tie-break-recently-added = Recently Added
+
+settings-cores = Cores
+settings-cores-config = cores.toml
+settings-cores-config-missing = Not Used
+settings-cores-config-ok = Applied
+settings-cores-config-warnings = { $count ->
+    [one] { $count } problem
+   *[other] { $count } problems
+}
+settings-cores-overridden = Changed
+settings-cores-folders = Folders: { $folders }
+settings-cores-extensions = Extensions: { $extensions }
+settings-cores-missing = Missing
+core-config-invalid = cores.toml couldn't be read: { $error }
+core-config-incomplete = { $console } needs a core or program, and extensions or patterns
+core-config-missing-core = { $console }: core { $core } isn't installed
+core-config-missing-program = { $console }: { $path } doesn't exist
+
+settings-activity = Activity
+settings-activity-total = Total Play Time
+settings-activity-recent = Last { $days } Days
+settings-activity-since = Playing Since
+settings-activity-loading = Loading...
+settings-activity-none = None
+settings-activity-launches = { $count ->
+    [one] { $count } launch
+   *[other] { $count } launches
+}
+settings-activity-by-play-time = By Play Time
+settings-activity-by-launches = By Launches
+
+settings-trash = Trash
+settings-trash-empty = Trash is empty
+settings-trash-age = { $size }, { $days }d ago
+settings-trash-restore = Restore
+settings-trash-purge = Delete Forever
+settings-trash-purged = Deleted for good
+settings-trash-purge-failed = Failed to empty trash
+
 settings-about = About
 settings-about-allium-version = Allium Version
 settings-about-model-name = Model Name
@@ -71,23 +305,79 @@ settings-about-operating-system-version = OS Version
 settings-about-kernel-version = Kernel Version
 settings-about-storage-used = Storage Used
 settings-about-unknown-value = Unknown
+settings-about-last-maintenance = Last Maintenance
+settings-about-maintenance-never = Never
+settings-about-maintenance-completed = { $date } ({ $completed }/{ $total } done)
+settings-about-maintenance-aborted = { $date } (stopped, { $completed }/{ $total } done)
+settings-about-diagnostics = Create Diagnostics Bundle
+settings-about-setup = Run Setup Again
+diagnostics-running = Running diagnostics... Press B to cancel.
+diagnostics-done = Diagnostics saved to { $path }
+diagnostics-cancelled = Diagnostics cancelled
+diagnostics-failed = Failed to create diagnostics
+
+setup-title = Setup { $step }/{ $total }: { $name }
+setup-next = Next
+setup-finish = Finish
+setup-skip = Skip
+setup-folders = Game Folders
+setup-folders-create = Create Folders
+setup-folders-created = Created { $count } game folders
+setup-folders-failed = Failed to create game folders
+
+suspended-game-banner = Paused: { $name } — press Start to resume, hold to quit
+suspended-game-quitting = Quitting { $name }...
 
 # Menu
 ingame-menu-continue = Continue
 ingame-menu-save = Save
 ingame-menu-load = Load
+ingame-menu-save-states = Save States
+ingame-menu-change-disc = Change Disc
+ingame-menu-disc-current = Inserted
 ingame-menu-reset = Reset
 ingame-menu-settings = Settings
 ingame-menu-guide = Guide
+ingame-menu-notes = Notes
+ingame-menu-cheats = Cheats
+ingame-menu-cheats-empty = No cheats could be read
+ingame-menu-cheats-failed = Couldn't read cheats: { $error }
+ingame-menu-cheats-save-failed = Couldn't save cheats: { $error }
+ingame-menu-screenshot = Screenshot
+ingame-menu-screenshot-saved = Saved screenshot to { $path }
+ingame-menu-screenshot-failed = Couldn't save screenshot: { $error }
+ingame-menu-launcher = Go to Launcher
+ingame-menu-switch-game = Switch Game
+ingame-menu-switch-game-empty = No other recent games
+ingame-menu-switch-game-missing = { $name } is missing
 ingame-menu-quit = Quit
 ingame-menu-slot = Slot { $slot }
 ingame-menu-slot-auto = Auto
 ingame-menu-disk = Disk { $disk }
+ingame-menu-overwrite-slot = Overwrite slot { $slot }?
+ingame-menu-overwrite-slot-auto = Overwrite auto save?
+ingame-menu-slot-saved = Saved { $time }
+ingame-menu-slot-existing = Existing save
+ingame-menu-slot-new = New save
+ingame-menu-slot-empty = Empty
+ingame-menu-unknown-game = Unknown game
+ingame-menu-stale-game = { $name } has stopped. Return to launcher?
 
 guide-button-search = Search
 guide-button-next = Next
 guide-button-prev = Prev
 
+notes-empty = No notes yet.
+notes-button-type = Type
+notes-button-delete = Delete
+notes-button-new-line = New Line
+notes-button-done = Done
+
+game-options-core = Core
+game-options-core-default = Default
+game-options-effective-core = Launches With
+game-options-no-core = None
+
 # Hotkeys
 hotkeys-global = Global Hotkeys:
 hotkeys-screenshot = Screenshot
@@ -105,8 +395,30 @@ button-back = Back
 button-confirm = Confirm
 button-edit = Edit
 button-select = Select
+button-move = Move
 
 keyboard-button-backspace = Backspace
 keyboard-button-shift = Shift
 
-powering-off = Powering off...
\ No newline at end of file
+powering-off = Powering off...
+
+emergency-exit = Allium stopped responding and was restarted.
+emergency-exit-game = { $name } stopped responding and was closed.
+quick-quit-confirm = Quit { $name }?
+quick-quit-yes = Yes
+quick-quit-no = No
+remote-launch-failed = Couldn't launch { $name }.
+archive-password-protected = Couldn't open { $name }, the archive is password protected.
+archive-corrupt = Couldn't open { $name }, the archive is damaged.
+maintenance-task-failed = Maintenance failed: { $name }
+launch-failure-title = { $name } couldn't start
+launch-failure-exit-code = Exited with code { $code }
+launch-failure-signal = Stopped by signal { $signal }
+launch-failure-exited-early = Exited right after starting
+launch-failure-hint = Check the core's BIOS files and the ROM.
+launch-error-no-console = No console launches this file
+launch-error-tried = Tried .{ $extension } files and the { $folder } folder
+launch-error-no-core = { $console } has no core set up
+launch-error-no-core-hint = Add cores for it in cores.toml.
+launch-error-core-missing = The core for { $console } isn't installed
+launch-error-core-missing-hint = Missing { $path }
@@ -0,0 +1,43 @@
+#![feature(test)]
+
+extern crate test;
+
+use common::display::color::{BlendMode, Color};
+use test::{black_box, Bencher};
+
+/// Roughly one 640x480 screen of pixels, as when dimming the screen behind the menu.
+const PIXELS: u32 = 640 * 480;
+
+fn pixels() -> impl Iterator<Item = Color> {
+    (0..PIXELS).map(|i| Color::new(i as u8, (i >> 8) as u8, (i >> 16) as u8))
+}
+
+#[bench]
+fn blend_naive(b: &mut Bencher) {
+    let tint = Color::new(30, 30, 46);
+    b.iter(|| {
+        for pixel in pixels() {
+            black_box(pixel.blend_with(tint, 192, BlendMode::Naive));
+        }
+    });
+}
+
+#[bench]
+fn blend_gamma_correct(b: &mut Bencher) {
+    let tint = Color::new(30, 30, 46);
+    b.iter(|| {
+        for pixel in pixels() {
+            black_box(pixel.blend_with(tint, 192, BlendMode::GammaCorrect));
+        }
+    });
+}
+
+#[bench]
+fn over(b: &mut Bencher) {
+    let background = Color::new(30, 30, 46);
+    b.iter(|| {
+        for pixel in pixels() {
+            black_box(pixel.with_a(128).over(background));
+        }
+    });
+}
@@ -0,0 +1,46 @@
+#![feature(test)]
+
+extern crate test;
+
+use common::display::greyscale::{Dithering, Greyscale};
+use test::{black_box, Bencher};
+
+/// One 640x480 frame, as flushed to the screen.
+const WIDTH: usize = 640;
+const HEIGHT: usize = 480;
+
+fn frame(bytes_per_pixel: usize) -> Vec<u8> {
+    (0..WIDTH * HEIGHT * bytes_per_pixel)
+        .map(|i| (i * 7 + i / 3) as u8)
+        .collect()
+}
+
+fn bench(b: &mut Bencher, dithering: Dithering, bytes_per_pixel: usize) {
+    let greyscale = Greyscale::new(dithering);
+    let src = frame(bytes_per_pixel);
+    let mut dst = vec![0; src.len()];
+    b.iter(|| {
+        greyscale.transform(black_box(&src), &mut dst, WIDTH, bytes_per_pixel);
+        black_box(&dst);
+    });
+}
+
+#[bench]
+fn bgra_none(b: &mut Bencher) {
+    bench(b, Dithering::None, 4);
+}
+
+#[bench]
+fn bgra_ordered(b: &mut Bencher) {
+    bench(b, Dithering::Ordered, 4);
+}
+
+#[bench]
+fn rgb565_none(b: &mut Bencher) {
+    bench(b, Dithering::None, 2);
+}
+
+#[bench]
+fn rgb565_ordered(b: &mut Bencher) {
+    bench(b, Dithering::Ordered, 2);
+}
@@ -0,0 +1,53 @@
+#![feature(test)]
+
+extern crate test;
+
+use common::natural_order::natural_cmp;
+use test::{black_box, Bencher};
+
+/// A large games folder.
+const ENTRIES: usize = 5000;
+
+/// Names like the ones in a games folder, with disc and track numbers, regions and revisions, in
+/// a fixed shuffled order.
+fn names() -> Vec<String> {
+    let mut seed: u32 = 0x2545_f491;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed
+    };
+    (0..ENTRIES)
+        .map(|i| match next() % 4 {
+            0 => format!("Game {} (Disc {})", next() % 500, i % 12 + 1),
+            1 => format!("Track {:02} - Song {}", next() % 100, i),
+            2 => format!("romhack v1.{} [{}] (USA)", next() % 30, i),
+            _ => format!(
+                "Some Longer Game Title Number {} (Europe) (Rev {})",
+                i,
+                next() % 3
+            ),
+        })
+        .collect()
+}
+
+#[bench]
+fn sort_lexicographic(b: &mut Bencher) {
+    let names = names();
+    b.iter(|| {
+        let mut names = names.clone();
+        names.sort_unstable();
+        black_box(names);
+    });
+}
+
+#[bench]
+fn sort_natural(b: &mut Bencher) {
+    let names = names();
+    b.iter(|| {
+        let mut names = names.clone();
+        names.sort_unstable_by(|a, b| natural_cmp(a, b));
+        black_box(names);
+    });
+}
@@ -0,0 +1,187 @@
+//! Looking into zip archives, to tell which console a zipped game is for when neither its name nor
+//! its folder says.
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::Result;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// Extensions of files that come with games in archives, but aren't the game itself.
+const NON_ROM_EXTENSIONS: [&str; 16] = [
+    "txt", "nfo", "diz", "md", "pdf", "htm", "html", "xml", "dat", "url", "sfv", "md5", "jpg",
+    "jpeg", "png", "gif",
+];
+
+/// Why an archive couldn't be looked into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveError {
+    PasswordProtected,
+    Corrupt,
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::PasswordProtected => write!(f, "archive is password protected"),
+            ArchiveError::Corrupt => write!(f, "archive is corrupt"),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+pub fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Lowercase extension of the first file in the zip archive at `path` that looks like a game,
+/// skipping folders, hidden files and the likes of readmes. `None` if there is no such file.
+/// Fails with an [`ArchiveError`] if the archive can't be read.
+pub fn rom_extension(path: &Path) -> Result<Option<String>> {
+    let file = BufReader::new(File::open(path)?);
+    let mut archive = ZipArchive::new(file).map_err(archive_error)?;
+
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i).map_err(archive_error)?;
+        let name = Path::new(entry.name());
+        if entry.is_dir()
+            || name.components().any(|component| {
+                component
+                    .as_os_str()
+                    .to_str()
+                    .is_none_or(|s| s.starts_with('.') || s == "__MACOSX")
+            })
+        {
+            continue;
+        }
+        let Some(ext) = name
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase)
+        else {
+            continue;
+        };
+        if NON_ROM_EXTENSIONS.contains(&ext.as_str()) {
+            continue;
+        }
+        drop(entry);
+
+        // Reading the entry's header is enough to tell whether it's encrypted
+        archive.by_index(i).map_err(archive_error)?;
+        return Ok(Some(ext));
+    }
+
+    Ok(None)
+}
+
+fn archive_error(e: ZipError) -> anyhow::Error {
+    let error = match e {
+        ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => {
+            ArchiveError::PasswordProtected
+        }
+        _ => ArchiveError::Corrupt,
+    };
+    anyhow::Error::new(error).context(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    fn write_zip(path: &Path, files: &[&str]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for name in files {
+            if let Some(dir) = name.strip_suffix('/') {
+                zip.add_directory(dir, FileOptions::default()).unwrap();
+            } else {
+                zip.start_file(*name, FileOptions::default()).unwrap();
+                zip.write_all(b"contents").unwrap();
+            }
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_is_zip() {
+        assert!(is_zip(Path::new("/Roms/Game.zip")));
+        assert!(is_zip(Path::new("/Roms/Game.ZIP")));
+        assert!(!is_zip(Path::new("/Roms/Game.gba")));
+        assert!(!is_zip(Path::new("/Roms/zip")));
+    }
+
+    #[test]
+    fn test_rom_extension() -> Result<()> {
+        let dir = temp_dir("archive-rom-extension");
+
+        let path = dir.join("Game.zip");
+        write_zip(
+            &path,
+            &[
+                "__MACOSX/._Game.GBA",
+                "Game/",
+                "Game/readme.txt",
+                "Game/.hidden.nes",
+                "Game/Game.GBA",
+                "Game/Game.sav",
+            ],
+        );
+        assert_eq!(rom_extension(&path)?, Some("gba".to_string()));
+
+        let path = dir.join("Docs.zip");
+        write_zip(&path, &["manual.pdf", "README"]);
+        assert_eq!(rom_extension(&path)?, None);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_unreadable_archives() -> Result<()> {
+        let dir = temp_dir("archive-unreadable");
+
+        let path = dir.join("Corrupt.zip");
+        fs::write(&path, b"PK\x03\x04 not really a zip")?;
+        let e = rom_extension(&path).unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<ArchiveError>(),
+            Some(&ArchiveError::Corrupt)
+        );
+
+        // A stored file with the encryption flag set in both of its headers
+        let path = dir.join("Encrypted.zip");
+        write_zip(&path, &["Game.gba"]);
+        let mut bytes = fs::read(&path)?;
+        let mut i = 0;
+        while i + 4 <= bytes.len() {
+            match &bytes[i..i + 4] {
+                b"PK\x03\x04" => bytes[i + 6] |= 1,
+                b"PK\x01\x02" => bytes[i + 8] |= 1,
+                _ => {}
+            }
+            i += 1;
+        }
+        fs::write(&path, bytes)?;
+        let e = rom_extension(&path).unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<ArchiveError>(),
+            Some(&ArchiveError::PasswordProtected)
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+//! RetroArch cheat files, which list a game's cheats under numbered keys:
+//!
+//! ```text
+//! cheats = 2
+//! cheat0_desc = "Infinite Lives"
+//! cheat0_code = "00A1-B2C3"
+//! cheat0_enable = false
+//! ```
+//!
+//! RetroArch looks for a game's cheats in `<game>.cht`, in a folder of the cheats folder named
+//! after the core or system. Only whether each cheat is enabled is changed here, so the rest of
+//! the file, including keys Allium doesn't know about, is written back as it was read.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use log::warn;
+
+use crate::constants::RETROARCH_CHEATS_DIR;
+use crate::write_activity;
+
+/// Extension of RetroArch cheat files.
+const CHEAT_EXTENSION: &str = "cht";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    /// What the cheat does, or its code if it has no description.
+    pub description: String,
+    pub enabled: bool,
+    /// Number of the cheat in the file, the `N` in its keys.
+    number: usize,
+    /// Line of `cheatN_code`, after which `cheatN_enable` is added if the file has none.
+    code_line: usize,
+    /// Line of `cheatN_enable`, if the file has one.
+    enable_line: Option<usize>,
+}
+
+impl Cheat {
+    fn enable_line(&self, quoted: bool) -> String {
+        if quoted {
+            format!("cheat{}_enable = \"{}\"", self.number, self.enabled)
+        } else {
+            format!("cheat{}_enable = {}", self.number, self.enabled)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CheatFile {
+    path: PathBuf,
+    lines: Vec<String>,
+    cheats: Vec<Cheat>,
+}
+
+impl CheatFile {
+    /// The cheat file of `game`, next to it or in RetroArch's cheats folder.
+    pub fn find(game: &Path) -> Option<PathBuf> {
+        find(game, &RETROARCH_CHEATS_DIR)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let (lines, cheats) = parse(&fs::read_to_string(path)?)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            lines,
+            cheats,
+        })
+    }
+
+    /// The cheats that could be read, in the order they are listed.
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    pub fn set_enabled(&mut self, i: usize, enabled: bool) {
+        if let Some(cheat) = self.cheats.get_mut(i) {
+            cheat.enabled = enabled;
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let _guard = write_activity::begin("cheats");
+        fs::write(&self.path, self.contents())?;
+        Ok(())
+    }
+
+    /// The file as read, with each cheat's enabled flag updated.
+    fn contents(&self) -> String {
+        let mut enable_lines = HashMap::new();
+        let mut code_lines = HashMap::new();
+        for cheat in &self.cheats {
+            match cheat.enable_line {
+                Some(line) => enable_lines.insert(line, cheat),
+                None => code_lines.insert(cheat.code_line, cheat),
+            };
+        }
+
+        let mut contents = String::new();
+        for (line, text) in self.lines.iter().enumerate() {
+            match enable_lines.get(&line) {
+                Some(cheat) => {
+                    // Keep the quoting the file was written with
+                    let quoted = text
+                        .split_once('=')
+                        .map_or(true, |(_, value)| value.trim_start().starts_with('"'));
+                    contents.push_str(&cheat.enable_line(quoted));
+                }
+                None => contents.push_str(text),
+            }
+            contents.push('\n');
+            if let Some(cheat) = code_lines.get(&line) {
+                contents.push_str(&cheat.enable_line(true));
+                contents.push('\n');
+            }
+        }
+        contents
+    }
+}
+
+fn find(game: &Path, cheats_dir: &Path) -> Option<PathBuf> {
+    let file_name = format!(
+        "{}.{}",
+        game.file_stem()?.to_string_lossy(),
+        CHEAT_EXTENSION
+    );
+
+    let beside = game.with_extension(CHEAT_EXTENSION);
+    if beside.exists() {
+        return Some(beside);
+    }
+
+    let path = cheats_dir.join(&file_name);
+    if path.exists() {
+        return Some(path);
+    }
+
+    fs::read_dir(cheats_dir)
+        .ok()?
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path().join(&file_name))
+        .find(|path| path.exists())
+}
+
+/// The lines of a cheat file and the cheats in it. Cheats that are missing their code or have an
+/// invalid enabled flag are left out.
+fn parse(text: &str) -> Result<(Vec<String>, Vec<Cheat>)> {
+    let lines: Vec<String> = text.lines().map(str::to_string).collect();
+
+    let mut values = HashMap::new();
+    for (line, text) in lines.iter().enumerate() {
+        if let Some((key, value)) = text.split_once('=') {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            values.insert(key.trim(), (line, value));
+        }
+    }
+
+    let count: usize = values
+        .get("cheats")
+        .and_then(|(_, count)| count.parse().ok())
+        .ok_or_else(|| anyhow!("missing cheat count"))?;
+
+    let mut cheats = Vec::with_capacity(count);
+    for i in 0..count {
+        let code = values.get(format!("cheat{}_code", i).as_str());
+        let Some(&(code_line, code)) = code.filter(|(_, code)| !code.is_empty()) else {
+            warn!("skipping cheat {}: missing code", i);
+            continue;
+        };
+
+        let enable = values.get(format!("cheat{}_enable", i).as_str());
+        let enabled = match enable.map(|(_, enabled)| *enabled) {
+            None | Some("false" | "0") => false,
+            Some("true" | "1") => true,
+            Some(enabled) => {
+                warn!("skipping cheat {}: invalid enabled flag {:?}", i, enabled);
+                continue;
+            }
+        };
+
+        let description = values
+            .get(format!("cheat{}_desc", i).as_str())
+            .map(|(_, desc)| *desc)
+            .filter(|desc| !desc.is_empty())
+            .unwrap_or(code);
+
+        cheats.push(Cheat {
+            description: description.to_string(),
+            enabled,
+            number: i,
+            code_line,
+            enable_line: enable.map(|(line, _)| *line),
+        });
+    }
+
+    Ok((lines, cheats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    const CHEATS: &str = r#"cheats = 4
+
+cheat0_desc = "Infinite Lives"
+cheat0_code = "00A1-B2C3"
+cheat0_enable = false
+
+cheat1_desc = "Broken"
+cheat1_enable = true
+
+cheat2_code = "1234-5678"
+cheat2_enable = maybe
+
+cheat3_desc = "Max Money"
+cheat3_code = "FFFF-0000"
+cheat3_handler = 1
+"#;
+
+    #[test]
+    fn test_parse_skips_malformed_cheats() -> Result<()> {
+        let (_, cheats) = parse(CHEATS)?;
+        let cheats: Vec<_> = cheats
+            .iter()
+            .map(|cheat| (cheat.description.as_str(), cheat.enabled))
+            .collect();
+        assert_eq!(cheats, [("Infinite Lives", false), ("Max Money", false)]);
+
+        assert!(parse("cheat0_code = 1234").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_keeps_rest_of_file() -> Result<()> {
+        let dir = temp_dir("cheats");
+        let path = dir.join("Game.cht");
+        fs::write(&path, CHEATS)?;
+
+        let mut file = CheatFile::load(&path)?;
+        file.set_enabled(0, true);
+        file.set_enabled(1, true);
+        file.save()?;
+
+        let expected = CHEATS
+            .replace("cheat0_enable = false", "cheat0_enable = true")
+            .replace(
+                "cheat3_code = \"FFFF-0000\"\n",
+                "cheat3_code = \"FFFF-0000\"\ncheat3_enable = \"true\"\n",
+            );
+        assert_eq!(fs::read_to_string(&path)?, expected);
+
+        let file = CheatFile::load(&path)?;
+        assert!(file.cheats().iter().all(|cheat| cheat.enabled));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_find() -> Result<()> {
+        let dir = temp_dir("cheats-find");
+        let cheats_dir = dir.join("cheats");
+        fs::create_dir_all(cheats_dir.join("gpSP"))?;
+        fs::write(cheats_dir.join("gpSP/Metroid Fusion.cht"), "cheats = 0")?;
+
+        let game = dir.join("Metroid Fusion.gba");
+        assert_eq!(
+            find(&game, &cheats_dir),
+            Some(cheats_dir.join("gpSP/Metroid Fusion.cht"))
+        );
+
+        fs::write(dir.join("Metroid Fusion.cht"), "cheats = 0")?;
+        assert_eq!(
+            find(&game, &cheats_dir),
+            Some(dir.join("Metroid Fusion.cht"))
+        );
+
+        assert_eq!(find(&dir.join("Zero Mission.gba"), &cheats_dir), None);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
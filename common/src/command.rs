@@ -1,16 +1,29 @@
+use std::path::PathBuf;
 use std::time::Duration;
 
 use crate::display::color::Color;
+use crate::filename_rules::FilenameRules;
+use crate::game_info::SwitchRequest;
+use crate::launch_failure::LaunchFailure;
+use crate::legacy_layout::MigrationMode;
+use crate::list_settings::ListSettings;
 use crate::locale::LocaleSettings;
-use crate::{display::settings::DisplaySettings, stylesheet::Stylesheet};
+use crate::theme_schedule::{ThemePeriod, ThemeSchedule};
+use crate::{display::settings::DisplaySettings, stylesheet::StyleConfig};
 
 #[derive(Debug)]
 pub enum Command {
     Exit,
     Exec(std::process::Command),
-    SaveStylesheet(Box<Stylesheet>),
+    SaveStylesheet(Box<StyleConfig>),
+    /// Saves when to switch between the day and night themes, and switches if it's time to.
+    SaveThemeSchedule(Box<ThemeSchedule>),
+    /// Switches to the theme of the period the theme schedule switched to.
+    ThemeChanged(ThemePeriod),
     SaveDisplaySettings(Box<DisplaySettings>),
     SaveLocaleSettings(LocaleSettings),
+    SaveFilenameRules(FilenameRules),
+    SaveListSettings(ListSettings),
     CloseView,
     ValueChanged(usize, Value),
     TrapFocus,
@@ -19,7 +32,32 @@ pub enum Command {
     StartSearch,
     Search(String),
     Toast(String, Option<Duration>),
+    /// Shows a toast offering to undo the actions with these ids for a short while.
+    OfferUndo(String, Vec<i64>),
+    /// Undoes the actions with these ids, newest last.
+    Undo(Vec<i64>),
     PopulateDb,
+    /// Clears the recently played list, offering to undo it.
+    ClearRecents,
+    /// Downloads box art for the games in a folder and its subfolders. Games that already have
+    /// box art are only included if set.
+    ScrapeBoxArt(PathBuf, bool),
+    RunDiagnostics,
+    /// Shows the first-boot setup wizard again.
+    StartSetup,
+    SelectProfile(String),
+    MigrateLegacyFolders(MigrationMode),
+    ResumeGame,
+    QuitSuspendedGame,
+    TerminateMain,
+    /// Closes the menu after asking the game to quit.
+    QuitGame,
+    /// Suspends the game in the background and brings up the launcher.
+    SuspendGame,
+    /// Quits the game and starts another one without going through the launcher.
+    SwitchGame(SwitchRequest),
+    /// Explains why a game couldn't be launched.
+    ShowLaunchFailure(LaunchFailure),
 }
 
 #[derive(Debug, Clone)]
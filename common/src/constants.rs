@@ -29,9 +29,18 @@ lazy_static! {
     pub static ref ALLIUM_FONTS_DIR: PathBuf = ALLIUM_BASE_DIR.join("fonts");
     pub static ref ALLIUM_LOCALES_DIR: PathBuf = ALLIUM_BASE_DIR.join("locales");
     pub static ref ALLIUM_IMAGES_DIR: PathBuf = ALLIUM_BASE_DIR.join("images");
+    pub static ref ALLIUM_TRASH_DIR: PathBuf = ALLIUM_SD_ROOT.join(".trash");
+    pub static ref ALLIUM_IMAGE_CACHE_DIR: PathBuf = ALLIUM_SD_ROOT.join(".allium-cache/images");
+    pub static ref ALLIUM_SPLASH_IMAGE: PathBuf = ALLIUM_SD_ROOT.join("splash.png");
+    pub static ref ALLIUM_SCREENSHOTS_DIR: PathBuf = ALLIUM_SD_ROOT.join("Screenshots");
 
     // Config
     pub static ref ALLIUM_CONFIG_CONSOLES: PathBuf = ALLIUM_BASE_DIR.join("config/consoles.toml");
+    pub static ref ALLIUM_CONFIG_CORES: PathBuf = ALLIUM_BASE_DIR.join("config/cores.toml");
+    pub static ref ALLIUM_CONFIG_PROFILES: PathBuf = ALLIUM_BASE_DIR.join("config/profiles.toml");
+    pub static ref ALLIUM_CONFIG_LEGACY_LAYOUTS: PathBuf =
+        ALLIUM_BASE_DIR.join("config/legacy_layouts.toml");
+    pub static ref ALLIUM_CONFIG_LAUNCHER: PathBuf = ALLIUM_BASE_DIR.join("config/launcher.toml");
 
     // State
     pub static ref ALLIUMD_STATE: PathBuf = ALLIUM_BASE_DIR.join("state/alliumd.json");
@@ -45,31 +54,91 @@ lazy_static! {
     pub static ref ALLIUM_LOCALE_SETTINGS: PathBuf = ALLIUM_BASE_DIR.join("state/locale.json");
     pub static ref ALLIUM_WIFI_SETTINGS: PathBuf = ALLIUM_BASE_DIR.join("state/wifi.json");
     pub static ref ALLIUM_TIMEZONE: PathBuf = ALLIUM_BASE_DIR.join("state/timezone");
+    pub static ref ALLIUM_INGAME_MENU_SETTINGS: PathBuf =
+        ALLIUM_BASE_DIR.join("state/ingame-menu.json");
+    pub static ref ALLIUM_PROFILE: PathBuf = ALLIUM_BASE_DIR.join("state/profile");
+    pub static ref ALLIUM_LAST_PROFILE: PathBuf = ALLIUM_BASE_DIR.join("state/last-profile");
+    pub static ref ALLIUM_VOLUME_SETTINGS: PathBuf = ALLIUM_BASE_DIR.join("state/volume.json");
+    pub static ref ALLIUM_EMERGENCY_EXIT_SETTINGS: PathBuf =
+        ALLIUM_BASE_DIR.join("state/emergency-exit.json");
+    pub static ref ALLIUM_MAINTENANCE_SETTINGS: PathBuf =
+        ALLIUM_BASE_DIR.join("state/maintenance.json");
+    pub static ref ALLIUM_MAINTENANCE_REPORT: PathBuf =
+        ALLIUM_BASE_DIR.join("state/maintenance-report.json");
+    pub static ref ALLIUM_LEGACY_STATE: PathBuf = ALLIUM_BASE_DIR.join("state/legacy-layout.json");
+    pub static ref ALLIUM_NOTIFICATIONS: PathBuf = ALLIUM_BASE_DIR.join("state/notifications");
+    pub static ref ALLIUM_FOLDER_VIEWS: PathBuf = ALLIUM_BASE_DIR.join("state/folder-views.json");
+    pub static ref ALLIUM_SETUP_STATE: PathBuf = ALLIUM_BASE_DIR.join("state/setup.json");
+    pub static ref ALLIUM_FILENAME_RULES: PathBuf =
+        ALLIUM_BASE_DIR.join("state/filename-rules.json");
+    pub static ref ALLIUM_LIST_SETTINGS: PathBuf =
+        ALLIUM_BASE_DIR.join("state/list-settings.json");
+    pub static ref ALLIUM_THEME_SCHEDULE: PathBuf =
+        ALLIUM_BASE_DIR.join("state/theme-schedule.json");
+    pub static ref ALLIUM_THEME_CHANGED: PathBuf =
+        ALLIUM_BASE_DIR.join("state/theme-changed.json");
+    pub static ref ALLIUM_REMOTE_TOKEN: PathBuf = ALLIUM_BASE_DIR.join("state/remote-token");
+
+    // Exports
+    pub static ref ALLIUM_LIBRARY_EXPORT: PathBuf = ALLIUM_SD_ROOT.join("allium-library.json");
+    pub static ref ALLIUM_LIBRARY_REPORT: PathBuf = ALLIUM_SD_ROOT.join("allium-library.html");
+    pub static ref ALLIUM_DIAGNOSTICS: PathBuf = ALLIUM_SD_ROOT.join("allium-diagnostics.zip");
 
     // Database
     pub static ref ALLIUM_DATABASE: PathBuf = env::var("ALLIUM_DATABASE")
         .map(PathBuf::from)
         .unwrap_or_else(|_| ALLIUM_SD_ROOT.join("Saves/CurrentProfile/allium.db"));
 
+    // Save states
+    pub static ref ALLIUM_SAVE_STATES_DIR: PathBuf = ALLIUM_SD_ROOT.join("Saves/CurrentProfile/states");
+    pub static ref ALLIUM_STATE_PREVIEW: PathBuf = PathBuf::from("/tmp/allium-state-preview.png");
+
+    // Game to switch to, from the ingame menu to alliumd
+    pub static ref ALLIUM_SWITCH_REQUEST: PathBuf = PathBuf::from("/tmp/allium-switch-game.json");
+
+    // Markers of writes in progress, see `write_activity`
+    pub static ref ALLIUM_WRITE_ACTIVITY: PathBuf = PathBuf::from("/tmp/allium-writes");
+
+    // Output of the launcher and the games it runs, kept for the current session
+    pub static ref ALLIUM_MAIN_STDERR: PathBuf = PathBuf::from("/tmp/allium-main-stderr.log");
+
     // Binaries & Scripts
     pub static ref ALLIUM_LAUNCHER: PathBuf = ALLIUM_BASE_DIR.join("bin/allium-launcher");
     pub static ref ALLIUM_MENU: PathBuf = ALLIUM_BASE_DIR.join("bin/allium-menu");
     pub static ref ALLIUM_RETROARCH: PathBuf = ALLIUM_BASE_DIR.join("cores/retroarch/launch.sh");
+    pub static ref RETROARCH_DIR: PathBuf = ALLIUM_SD_ROOT.join("RetroArch/.retroarch");
+    pub static ref RETROARCH_CORES_DIR: PathBuf = ALLIUM_SD_ROOT.join("RetroArch/.retroarch/cores");
+    pub static ref RETROARCH_CHEATS_DIR: PathBuf = ALLIUM_SD_ROOT.join("RetroArch/.retroarch/cheats");
+    pub static ref RETROARCH_PLAYLISTS_DIR: PathBuf = ALLIUM_SD_ROOT.join("RetroArch/.retroarch/playlists");
 }
 
 // Styles
 pub const IMAGE_WIDTH: u32 = 250;
-pub const SELECTION_MARGIN: u32 = 8;
 
 /// After the battery level drops below this threshold, the device will shut down.
 pub const BATTERY_SHUTDOWN_THRESHOLD: i32 = 5;
 
+/// Below this battery level, the power LED warns that the battery is low.
+pub const BATTERY_LOW_THRESHOLD: i32 = 15;
+
+/// From this battery level on, the power LED shows the battery as charged.
+pub const BATTERY_CHARGED_THRESHOLD: i32 = 90;
+
 /// The interval at which the battery level is updated.
 pub const BATTERY_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
 
+/// How often an automatic volume increase is stepped up.
+pub const VOLUME_RAMP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often alliumd checks whether headphones were plugged in or out.
+pub const AUDIO_OUTPUT_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
 /// The interval at which the clock is updated.
 pub const CLOCK_UPDATE_INTERVAL: Duration = Duration::from_secs(60);
 
+/// How often the write activity indicator checks whether writes are in progress.
+pub const WRITE_ACTIVITY_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
 /// The number of items to jump when pressing left/right in a listing.
 pub const LISTING_JUMP_SIZE: i32 = 5;
 
@@ -79,8 +148,52 @@ pub const MAXIMUM_FRAME_TIME: Duration = Duration::from_millis(100);
 /// Maximum number of recent games to retrieve from the database.
 pub const RECENT_GAMES_LIMIT: i64 = 100;
 
+/// How long the device has to go without input before maintenance may run.
+pub const MAINTENANCE_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// How often alliumd checks whether maintenance is due.
+pub const MAINTENANCE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Longest alliumd waits before checking the theme schedule again, so that changes to it are
+/// picked up.
+pub const THEME_SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Maximum number of search queries to remember.
+pub const SEARCH_HISTORY_LIMIT: i64 = 20;
+
+/// Maximum length of a game's note, in characters.
+pub const NOTE_LENGTH_LIMIT: usize = 4000;
+
 /// RetroArch network command interface.
 pub const RETROARCH_UDP_SOCKET: &str = "127.0.0.1:55355";
 
+/// Port of the companion HTTP API that alliumd serves on the local network.
+pub const ALLIUM_REMOTE_PORT: u16 = 8080;
+
+/// How often alliumd checks whether the companion API should be served, e.g. after Wi-Fi connected.
+pub const REMOTE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Long press duration for the menu button.
 pub const LONG_PRESS_DURATION: Duration = Duration::from_millis(1000);
+
+/// How long alliumd waits for the main process to draw its first frame before giving up on the boot splash.
+pub const SPLASH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long alliumd waits for a process to exit after asking it to, before killing it.
+pub const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// How long alliumd waits for RetroArch to write the state before switching games anyway.
+pub const SWITCH_SAVE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long alliumd waits for writes to the SD card to finish before powering off anyway.
+pub const POWER_OFF_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long alliumd waits for the database while a game is running before giving up.
+pub const DATABASE_BUSY_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Environment variable alliumd uses to pass a message for the launcher to show on startup.
+pub const ALLIUM_TOAST_ENV: &str = "ALLIUM_TOAST";
+
+/// Environment variable alliumd uses to pass a game for the launcher to launch on startup, when
+/// one was asked for through the companion API.
+pub const ALLIUM_LAUNCH_ENV: &str = "ALLIUM_LAUNCH";
@@ -1,19 +1,28 @@
 use std::{
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     rc::Rc,
 };
 
-use anyhow::{Context, Result};
-use chrono::Duration;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use log::info;
-use rusqlite::{params, Connection, OptionalExtension, Row};
+use rusqlite::{params, Connection, InterruptHandle, OptionalExtension, Row};
 use rusqlite_migration::{Migrations, M};
+use serde::{Deserialize, Serialize};
 
-use crate::constants::{ALLIUM_BASE_DIR, ALLIUM_DATABASE};
+use crate::constants::{ALLIUM_BASE_DIR, ALLIUM_DATABASE, SEARCH_HISTORY_LIMIT};
+use crate::filename_rules::FilenameRules;
+use crate::fingerprint::Fingerprint;
+use crate::profile::{Profile, DEFAULT_PROFILE};
+use crate::sort_order::{self, SortOrder};
+use crate::trash::{TrashedFile, UndoEntry, UndoKind};
+use crate::write_activity;
 
 #[derive(Debug, Clone, Default)]
 pub struct Database {
     conn: Option<Rc<Connection>>,
+    profile: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -25,6 +34,8 @@ pub struct Game {
     pub play_time: Duration,
     pub last_played: i64,
     pub core: Option<String>,
+    /// Row id, which increases as games are added to the library.
+    pub id: i64,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -35,6 +46,123 @@ pub struct NewGame {
     pub core: Option<String>,
 }
 
+/// Everything recorded about a game in the games table, kept so that removing it can be undone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub name: String,
+    pub path: PathBuf,
+    pub image: Option<PathBuf>,
+    pub play_count: i64,
+    /// Play time in seconds.
+    pub play_time: i64,
+    pub last_played: i64,
+    pub core: Option<String>,
+    pub unclean_exits: i64,
+    pub file_size: Option<i64>,
+    pub crc: Option<i64>,
+    /// Where the game is pinned on the recently played list, if it is.
+    #[serde(default)]
+    pub pinned: Option<i64>,
+    /// When the game was first launched, in seconds since the epoch.
+    #[serde(default)]
+    pub first_played: Option<i64>,
+    /// What else was recorded about the game, if it was deleted rather than reset.
+    #[serde(default)]
+    pub related: Option<RelatedRows>,
+}
+
+/// What is recorded about a game outside of the games table, kept so that deleting it can be
+/// undone.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelatedRows {
+    /// Id of the game's favorites row, which orders the favorites.
+    pub favorite: Option<i64>,
+    pub note: Option<String>,
+    pub name: Option<String>,
+    pub core: Option<String>,
+    pub hidden: bool,
+    pub completed: bool,
+    /// The collections the game is in, with its position in each.
+    pub collections: Vec<(i64, i64)>,
+    /// When each play session ended, and how long it was in seconds.
+    pub play_sessions: Vec<(i64, i64)>,
+}
+
+/// Descriptive title of a game with a cryptic file name, e.g. an arcade set, taken from a names
+/// database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameTitle {
+    pub name: String,
+    pub manufacturer: Option<String>,
+    pub year: Option<u16>,
+}
+
+/// A list of games put together by the user, which may span consoles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+}
+
+/// Play time recorded for a game by another firmware, one entry per row of its records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedGame {
+    pub path: PathBuf,
+    /// Row ids in the source, each with the play time it recorded.
+    pub rows: Vec<(i64, Duration)>,
+}
+
+/// A file or folder in the games folder, as it was when the folder it's in was last indexed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedFile {
+    pub path: PathBuf,
+    /// File name, including the extension.
+    pub name: String,
+    pub directory: PathBuf,
+    /// Extension without the dot, or empty if there is none.
+    pub extension: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// When it was last modified, in nanoseconds since the epoch.
+    pub modified: i64,
+}
+
+/// A game waiting for its box art to be downloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrapeJob {
+    pub path: PathBuf,
+    pub url: String,
+    /// URLs to try in order if there is nothing at `url`.
+    pub fallback_urls: Vec<String>,
+    /// Number of failed attempts so far.
+    pub retries: i64,
+    /// Unix timestamp before which the job shouldn't be attempted again.
+    pub next_attempt: i64,
+    /// Error of the last failed attempt.
+    pub error: Option<String>,
+}
+
+/// Where a game is in the scrape queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrapeStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScrapeProgress {
+    pub pending: i64,
+    pub completed: i64,
+    pub failed: i64,
+}
+
+impl ScrapeProgress {
+    pub fn total(&self) -> i64 {
+        self.pending + self.completed + self.failed
+    }
+}
+
 impl Database {
     pub fn new() -> Result<Self> {
         if !ALLIUM_DATABASE.exists() {
@@ -47,20 +175,36 @@ impl Database {
 
         let mut conn = Connection::open(ALLIUM_DATABASE.as_path())
             .with_context(|| format!("{}", ALLIUM_DATABASE.display()))?;
+        sort_order::create_collations(&conn)?;
         Self::migrations().to_latest(&mut conn)?;
         Ok(Self {
             conn: Some(Rc::new(conn)),
+            profile: Profile::active_name().unwrap_or_else(|| DEFAULT_PROFILE.to_string()),
         })
     }
 
     pub fn in_memory() -> Result<Self> {
         let mut conn = Connection::open_in_memory()?;
+        sort_order::create_collations(&conn)?;
         Self::migrations().to_latest(&mut conn)?;
         Ok(Self {
             conn: Some(Rc::new(conn)),
+            profile: DEFAULT_PROFILE.to_string(),
         })
     }
 
+    /// Returns a handle to the same database that reads and records games for another profile.
+    pub fn with_profile(&self, profile: &str) -> Self {
+        Self {
+            conn: self.conn.clone(),
+            profile: profile.to_string(),
+        }
+    }
+
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
     pub fn migrations<'a>() -> Migrations<'a> {
         Migrations::new(vec![
 M::up("
@@ -100,13 +244,189 @@ CREATE TABLE IF NOT EXISTS key_value (
 M::up("
 ALTER TABLE games ADD COLUMN core TEXT;
 "),
+M::up("
+CREATE TABLE games_new (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL DEFAULT 'default',
+    name TEXT NOT NULL,
+    path TEXT NOT NULL,
+    image TEXT,
+    play_count INTEGER NOT NULL,
+    play_time INTEGER NOT NULL,
+    last_played INTEGER NOT NULL,
+    core TEXT,
+    UNIQUE(profile, path)
+);
+INSERT INTO games_new (id, profile, name, path, image, play_count, play_time, last_played, core)
+SELECT id, 'default', name, path, image, play_count, play_time, last_played, core FROM games;
+DROP TABLE games;
+ALTER TABLE games_new RENAME TO games;
+
+CREATE TRIGGER games_fts_ai AFTER INSERT ON games BEGIN
+    INSERT INTO games_fts(rowid, name, path) VALUES (new.id, new.name, new.path);
+END;
+CREATE TRIGGER games_fts_ad AFTER DELETE ON games BEGIN
+    INSERT INTO games_fts(games_fts, rowid, name, path) VALUES ('delete', old.id, old.name, old.path);
+END;
+CREATE TRIGGER games_fts_au AFTER UPDATE ON games BEGIN
+    INSERT INTO games_fts(games_fts, rowid, name, path) VALUES ('delete', old.id, old.name, old.path);
+    INSERT INTO games_fts(rowid, name, path) VALUES (new.id, new.name, new.path);
+END;"),
+M::up("
+CREATE TABLE IF NOT EXISTS scrape_queue (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    url TEXT NOT NULL,
+    status TEXT NOT NULL,
+    retries INTEGER NOT NULL DEFAULT 0,
+    next_attempt INTEGER NOT NULL DEFAULT 0,
+    error TEXT
+);"),
+M::up("
+ALTER TABLE games ADD COLUMN unclean_exits INTEGER NOT NULL DEFAULT 0;
+"),
+M::up("
+CREATE TABLE IF NOT EXISTS search_history (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    query TEXT NOT NULL,
+    UNIQUE(profile, query)
+);"),
+M::up("
+CREATE TABLE IF NOT EXISTS game_flags (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    path TEXT NOT NULL,
+    hidden INTEGER NOT NULL DEFAULT 0,
+    completed INTEGER NOT NULL DEFAULT 0,
+    UNIQUE(profile, path)
+);"),
+M::up("
+CREATE TABLE IF NOT EXISTS game_titles (
+    path TEXT PRIMARY KEY,
+    name TEXT,
+    manufacturer TEXT,
+    year INTEGER
+);"),
+M::up("
+ALTER TABLE games ADD COLUMN file_size INTEGER;
+ALTER TABLE games ADD COLUMN crc INTEGER;
+"),
+M::up("
+CREATE TABLE IF NOT EXISTS undo (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    created INTEGER NOT NULL,
+    files TEXT NOT NULL,
+    games TEXT NOT NULL
+);"),
+M::up("
+CREATE TABLE IF NOT EXISTS game_notes (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    path TEXT NOT NULL,
+    note TEXT NOT NULL,
+    UNIQUE(profile, path)
+);"),
+M::up("
+CREATE TABLE IF NOT EXISTS favorites (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    path TEXT NOT NULL,
+    UNIQUE(profile, path)
+);"),
+M::up("
+CREATE TABLE IF NOT EXISTS imported_play_time (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    source TEXT NOT NULL,
+    row_id INTEGER NOT NULL,
+    UNIQUE(profile, source, row_id)
+);"),
+M::up("
+CREATE TABLE IF NOT EXISTS archive_entries (
+    path TEXT PRIMARY KEY,
+    size INTEGER NOT NULL,
+    extension TEXT
+);"),
+M::up("
+ALTER TABLE scrape_queue ADD COLUMN fallback_urls TEXT NOT NULL DEFAULT '';
+"),
+M::up("
+CREATE TABLE IF NOT EXISTS directory_counts (
+    path TEXT PRIMARY KEY,
+    modified INTEGER NOT NULL,
+    count INTEGER NOT NULL
+);"),
+M::up("
+CREATE TABLE IF NOT EXISTS collections (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    name TEXT NOT NULL,
+    UNIQUE(profile, name)
+);
+CREATE TABLE IF NOT EXISTS collection_games (
+    collection INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    UNIQUE(collection, path)
+);"),
+M::up("
+CREATE TABLE IF NOT EXISTS game_names (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    path TEXT NOT NULL,
+    name TEXT NOT NULL,
+    UNIQUE(profile, path)
+);"),
+M::up("
+CREATE TABLE IF NOT EXISTS indexed_files (
+    path TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    directory TEXT NOT NULL,
+    extension TEXT NOT NULL,
+    is_dir INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    modified INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS indexed_files_directory ON indexed_files (directory);
+CREATE TABLE IF NOT EXISTS indexed_directories (
+    path TEXT PRIMARY KEY,
+    modified INTEGER NOT NULL
+);"),
+M::up("ALTER TABLE games ADD COLUMN pinned INTEGER;"),
+M::up("
+ALTER TABLE games ADD COLUMN first_played INTEGER;
+CREATE TABLE IF NOT EXISTS play_sessions (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    path TEXT NOT NULL,
+    ended INTEGER NOT NULL,
+    play_time INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS play_sessions_ended ON play_sessions (profile, ended);"),
+M::up("
+CREATE TABLE IF NOT EXISTS game_settings (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    path TEXT NOT NULL,
+    core TEXT,
+    UNIQUE(profile, path)
+);"),
+M::up("
+CREATE TABLE IF NOT EXISTS header_consoles (
+    path TEXT PRIMARY KEY,
+    modified INTEGER NOT NULL,
+    pattern TEXT
+);"),
         ])
     }
 
     pub fn reset_game(&self, path: &Path) -> Result<()> {
         self.conn.as_ref().unwrap().execute(
-            "UPDATE games SET play_count = 0, play_time = 0, last_played = 0 WHERE path = ?",
-            params![path.display().to_string()],
+            "UPDATE games SET play_count = 0, play_time = 0, last_played = 0, pinned = NULL WHERE profile = ? AND path = ?",
+            params![self.profile, path.display().to_string()],
         )?;
         Ok(())
     }
@@ -116,27 +436,308 @@ ALTER TABLE games ADD COLUMN core TEXT;
             .conn
             .as_ref()
             .unwrap()
-            .prepare("UPDATE games SET path = ? WHERE path = ?")?;
+            .prepare("UPDATE games SET path = ? WHERE profile = ? AND path = ?")?;
         stmt.execute(params![
             new.display().to_string(),
+            self.profile,
             old.display().to_string()
         ])?;
         Ok(())
     }
 
+    /// Points everything recorded about the game at `old` to `new` instead, for all profiles. A
+    /// game already indexed at `new` is replaced.
+    pub fn relink_game(&self, old: &Path, new: &Path) -> Result<()> {
+        let (old, new) = (old.display().to_string(), new.display().to_string());
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?;
+        // Replacing through a conflict wouldn't update the search index
+        tx.execute(
+            "DELETE FROM games WHERE path = ? AND profile IN (SELECT profile FROM games WHERE path = ?)",
+            params![new, old],
+        )?;
+        tx.execute(
+            "UPDATE games SET path = ? WHERE path = ?",
+            params![new, old],
+        )?;
+        for table in [
+            "game_flags",
+            "game_notes",
+            "game_titles",
+            "scrape_queue",
+            "favorites",
+            "archive_entries",
+            "header_consoles",
+            "collection_games",
+            "game_names",
+            "game_settings",
+            "play_sessions",
+        ] {
+            tx.execute(
+                &format!("UPDATE OR REPLACE {table} SET path = ? WHERE path = ?"),
+                params![new, old],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn fingerprint(&self, path: &Path) -> Result<Option<Fingerprint>> {
+        let fingerprint = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT file_size, crc FROM games WHERE profile = ? AND path = ? AND file_size IS NOT NULL",
+                params![self.profile, path.display().to_string()],
+                |row| {
+                    Ok(Fingerprint {
+                        size: row.get(0)?,
+                        crc: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(fingerprint)
+    }
+
+    /// Games that have been fingerprinted, with their fingerprints.
+    pub fn fingerprints(&self) -> Result<Vec<(PathBuf, Fingerprint)>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT path, file_size, crc FROM games WHERE profile = ? AND file_size IS NOT NULL",
+        )?;
+
+        let results = stmt
+            .query_map([&self.profile], |row| {
+                Ok((
+                    PathBuf::from(row.get::<_, String>(0)?),
+                    Fingerprint {
+                        size: row.get(1)?,
+                        crc: row.get(2)?,
+                    },
+                ))
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Stores the fingerprint of the game at `path`, for all profiles.
+    pub fn set_fingerprint(&self, path: &Path, fingerprint: Fingerprint) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE games SET file_size = ?, crc = ? WHERE path = ?",
+            params![
+                fingerprint.size,
+                fingerprint.crc,
+                path.display().to_string()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The extension of the game inside the archive at `path`, if it was looked into while it was
+    /// `size` bytes long. `Some(None)` if it had no game inside.
+    pub fn archive_extension(&self, path: &Path, size: u64) -> Result<Option<Option<String>>> {
+        let extension = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT extension FROM archive_entries WHERE path = ? AND size = ?",
+                params![path.display().to_string(), size],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(extension)
+    }
+
+    pub fn set_archive_extension(
+        &self,
+        path: &Path,
+        size: u64,
+        extension: Option<&str>,
+    ) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "INSERT OR REPLACE INTO archive_entries (path, size, extension) VALUES (?, ?, ?)",
+            params![path.display().to_string(), size, extension],
+        )?;
+        Ok(())
+    }
+
+    /// The folder pattern of the console that the header of the file at `path` is for, if it was
+    /// read while the file was last `modified` then. `Some(None)` if no console was recognized.
+    pub fn header_console(&self, path: &Path, modified: i64) -> Result<Option<Option<String>>> {
+        let pattern = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT pattern FROM header_consoles WHERE path = ? AND modified = ?",
+                params![path.display().to_string(), modified],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(pattern)
+    }
+
+    pub fn set_header_console(
+        &self,
+        path: &Path,
+        modified: i64,
+        pattern: Option<&str>,
+    ) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "INSERT OR REPLACE INTO header_consoles (path, modified, pattern) VALUES (?, ?, ?)",
+            params![path.display().to_string(), modified, pattern],
+        )?;
+        Ok(())
+    }
+
+    /// How many games the directory at `path` holds, if they were counted while it was last
+    /// `modified` then.
+    pub fn directory_count(&self, path: &Path, modified: i64) -> Result<Option<usize>> {
+        let count = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT count FROM directory_counts WHERE path = ? AND modified = ?",
+                params![path.display().to_string(), modified],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(count)
+    }
+
+    pub fn set_directory_count(&self, path: &Path, modified: i64, count: usize) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "INSERT OR REPLACE INTO directory_counts (path, modified, count) VALUES (?, ?, ?)",
+            params![path.display().to_string(), modified, count],
+        )?;
+        Ok(())
+    }
+
+    /// The files and folders in the directory at `path`, if it was indexed while it was last
+    /// `modified` then.
+    pub fn indexed_files(&self, path: &Path, modified: i64) -> Result<Option<Vec<IndexedFile>>> {
+        let conn = self.conn.as_ref().unwrap();
+        let path = path.display().to_string();
+        let indexed: Option<i64> = conn
+            .query_row(
+                "SELECT modified FROM indexed_directories WHERE path = ?",
+                [&path],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if indexed != Some(modified) {
+            return Ok(None);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT path, name, directory, extension, is_dir, size, modified FROM indexed_files
+WHERE directory = ? ORDER BY path",
+        )?;
+        let files = stmt
+            .query_map([&path], |row| {
+                Ok(IndexedFile {
+                    path: PathBuf::from(row.get::<_, String>(0)?),
+                    name: row.get(1)?,
+                    directory: PathBuf::from(row.get::<_, String>(2)?),
+                    extension: row.get(3)?,
+                    is_dir: row.get(4)?,
+                    size: row.get::<_, i64>(5)? as u64,
+                    modified: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(Some(files))
+    }
+
+    /// Records `files` as what the directory at `path` holds while it was last `modified` then,
+    /// replacing what was indexed before.
+    pub fn index_directory(&self, path: &Path, modified: i64, files: &[IndexedFile]) -> Result<()> {
+        let path = path.display().to_string();
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?;
+        tx.execute("DELETE FROM indexed_files WHERE directory = ?", [&path])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO indexed_files
+(path, name, directory, extension, is_dir, size, modified) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )?;
+            for file in files {
+                stmt.execute(params![
+                    file.path.display().to_string(),
+                    file.name,
+                    path,
+                    file.extension,
+                    file.is_dir,
+                    file.size as i64,
+                    file.modified,
+                ])?;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO indexed_directories (path, modified) VALUES (?, ?)",
+            params![path, modified],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Directories that have been indexed, with when they were last modified then.
+    pub fn indexed_directories(&self) -> Result<Vec<(PathBuf, i64)>> {
+        let mut stmt = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .prepare("SELECT path, modified FROM indexed_directories ORDER BY path")?;
+        let dirs = stmt
+            .query_map([], |row| {
+                Ok((PathBuf::from(row.get::<_, String>(0)?), row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(dirs)
+    }
+
+    /// Forgets what the directory at `path` held, e.g. once it's gone.
+    pub fn remove_indexed_directory(&self, path: &Path) -> Result<()> {
+        let path = path.display().to_string();
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?;
+        tx.execute("DELETE FROM indexed_files WHERE directory = ?", [&path])?;
+        tx.execute("DELETE FROM indexed_directories WHERE path = ?", [&path])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Forgets every indexed directory, so that the next indexing pass reads them all again.
+    pub fn clear_file_index(&self) -> Result<()> {
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?;
+        tx.execute("DELETE FROM indexed_files", [])?;
+        tx.execute("DELETE FROM indexed_directories", [])?;
+        tx.commit()?;
+        Ok(())
+    }
+
     pub fn update_games(&self, games: &[NewGame]) -> Result<()> {
         let mut stmt = self.conn.as_ref().unwrap().prepare(
             "
-INSERT INTO games (name, path, image, play_count, play_time, last_played, core)
-VALUES (?, ?, ?, 0, 0, 0, ?)
-ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?",
+INSERT INTO games (profile, name, path, image, play_count, play_time, last_played, core)
+VALUES (?, ?, ?, ?, 0, 0, 0, ?)
+ON CONFLICT(profile, path) DO UPDATE SET name = ?, image = ?, core = ?",
         )?;
 
         for game in games {
             let path = game.path.display().to_string();
             let image = game.image.as_ref().map(|p| p.display().to_string());
             stmt.execute(params![
-                game.name, path, image, game.core, game.name, image, game.core
+                self.profile,
+                game.name,
+                path,
+                image,
+                game.core,
+                game.name,
+                image,
+                game.core
             ])?;
         }
 
@@ -149,26 +750,42 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?",
             .conn
             .as_ref()
             .unwrap()
-            .prepare("SELECT name, path, image, play_count, play_time, last_played, core FROM games WHERE last_played > 0 ORDER BY play_time DESC LIMIT ?")?;
+            .prepare(&format!("SELECT name, path, image, play_count, play_time, last_played, core, id FROM games WHERE profile = ? AND last_played > 0 ORDER BY {} LIMIT ?", SortOrder::MostPlayed.order_by(&FilenameRules::active())))?;
 
         let results = stmt
-            .query_map([limit], map_game)?
+            .query_map(params![self.profile, limit], map_game)?
             .filter_map(|r| r.ok())
             .collect();
 
         Ok(results)
     }
 
-    /// Selects played games sorted by last played first.
-    pub fn select_last_played(&self, limit: i64) -> Result<Vec<Game>> {
+    /// Selects launched games sorted by most launches first.
+    pub fn select_most_launched(&self, limit: i64) -> Result<Vec<Game>> {
         let mut stmt = self
             .conn
             .as_ref()
             .unwrap()
-            .prepare("SELECT name, path, image, play_count, play_time, last_played, core FROM games WHERE last_played > 0 ORDER BY last_played DESC LIMIT ?")?;
+            .prepare("SELECT name, path, image, play_count, play_time, last_played, core, id FROM games WHERE profile = ? AND play_count > 0 ORDER BY play_count DESC, play_time DESC LIMIT ?")?;
 
         let results = stmt
-            .query_map([limit], map_game)?
+            .query_map(params![self.profile, limit], map_game)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Selects played games sorted by last played first. Pinned games come before the others in
+    /// the order they were pinned, and don't count towards `limit`.
+    pub fn select_last_played(&self, limit: i64) -> Result<Vec<Game>> {
+        let conn = self.conn.as_ref().unwrap();
+        let mut pinned = conn.prepare("SELECT name, path, image, play_count, play_time, last_played, core, id FROM games WHERE profile = ? AND pinned IS NOT NULL ORDER BY pinned")?;
+        let mut stmt = conn.prepare(&format!("SELECT name, path, image, play_count, play_time, last_played, core, id FROM games WHERE profile = ? AND pinned IS NULL AND last_played > 0 ORDER BY {} LIMIT ?", SortOrder::LastPlayed.order_by(&FilenameRules::active())))?;
+
+        let results = pinned
+            .query_map([&self.profile], map_game)?
+            .chain(stmt.query_map(params![self.profile, limit], map_game)?)
             .filter_map(|r| r.ok())
             .collect();
 
@@ -181,17 +798,18 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?",
             .conn
             .as_ref()
             .unwrap()
-            .prepare("SELECT name, path, image, play_count, play_time, last_played, core FROM games WHERE id IN (SELECT id FROM games ORDER BY RANDOM() LIMIT ?)")?;
+            .prepare("SELECT name, path, image, play_count, play_time, last_played, core, id FROM games WHERE id IN (SELECT id FROM games WHERE profile = ? ORDER BY RANDOM() LIMIT ?)")?;
 
         let results = stmt
-            .query_map([limit], map_game)?
+            .query_map(params![self.profile, limit], map_game)?
             .filter_map(|r| r.ok())
             .collect();
 
         Ok(results)
     }
 
-    /// Search for games by name. The query is a prefix search on words, so "Fi" will match both "Fire Emblem" and "Pokemon Fire Red".
+    /// Games whose names have words starting with `query`, followed by games whose names
+    /// contain it anywhere, ignoring case.
     pub fn search(&self, query: &str, limit: i64) -> Result<Vec<Game>> {
         if query.is_empty() {
             return Ok(Vec::new());
@@ -199,38 +817,110 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?",
 
         let conn = self.conn.as_ref().unwrap();
 
-        let mut stmt = conn.prepare("SELECT games.name, games.path, image, play_count, play_time, last_played, core FROM games JOIN games_fts ON games.id = games_fts.rowid WHERE games_fts.name MATCH ? LIMIT ?")?;
+        let mut stmt = conn.prepare("SELECT games.name, games.path, image, play_count, play_time, last_played, core, games.id FROM games JOIN games_fts ON games.id = games_fts.rowid WHERE games.profile = ? AND games_fts.name MATCH ? LIMIT ?")?;
 
-        let results = stmt
-            .query_map(params![format!("{}*", query), limit], map_game)?
+        let mut results: Vec<Game> = stmt
+            .query_map(
+                params![self.profile, format!("{}*", query), limit],
+                map_game,
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        // Full text search only matches the start of words, so "ario" wouldn't find "Mario"
+        let mut stmt = conn.prepare("SELECT name, path, image, play_count, play_time, last_played, core, id FROM games WHERE profile = ? AND name LIKE ? ESCAPE '\\' ORDER BY name LIMIT ?")?;
+        let substring_results: Vec<Game> = stmt
+            .query_map(
+                params![self.profile, format!("%{}%", escape_like(query)), limit],
+                map_game,
+            )?
             .filter_map(|r| r.ok())
             .collect();
+        for game in substring_results {
+            if results.len() as i64 >= limit {
+                break;
+            }
+            if !results.iter().any(|r| r.id == game.id) {
+                results.push(game);
+            }
+        }
 
         Ok(results)
     }
 
-    pub fn select_game(&self, path: &str) -> Result<Option<Game>> {
-        let game = self
-            .conn
-            .as_ref()
-            .unwrap()
-            .query_row("SELECT name, path, image, play_count, play_time, last_played, core FROM games WHERE path = ? LIMIT 1", [path], map_game)
-            .optional()?;
+    /// Names of games starting with `prefix`, most played first.
+    pub fn suggest_names(&self, prefix: &str, limit: i64) -> Result<Vec<String>> {
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
 
-        Ok(game)
-    }
+        let pattern = format!("{}%", escape_like(prefix));
+        let conn = self.conn.as_ref().unwrap();
+        let mut stmt = conn.prepare("SELECT name FROM games WHERE profile = ? AND name LIKE ? ESCAPE '\\' GROUP BY name ORDER BY MAX(play_count) DESC, name ASC LIMIT ?")?;
 
-    pub fn select_games(&self, paths: &[&Path]) -> Result<Vec<Option<Game>>> {
-        let mut stmt = self
-            .conn
-            .as_ref()
-            .unwrap()
-            .prepare("SELECT name, path, image, play_count, play_time, last_played, core FROM games WHERE path = ?")?;
+        let names = stmt
+            .query_map(params![self.profile, pattern, limit], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
 
-        let mut results = vec![None; paths.len()];
+        Ok(names)
+    }
+
+    /// Remembers a search query, keeping only the most recent ones.
+    pub fn add_search_query(&self, query: &str) -> Result<()> {
+        let conn = self.conn.as_ref().unwrap();
+        // Searching again moves the query to the front
+        conn.execute(
+            "DELETE FROM search_history WHERE profile = ? AND query = ?",
+            params![self.profile, query],
+        )?;
+        conn.execute(
+            "INSERT INTO search_history (profile, query) VALUES (?, ?)",
+            params![self.profile, query],
+        )?;
+        conn.execute(
+            "DELETE FROM search_history WHERE profile = ? AND id NOT IN (SELECT id FROM search_history WHERE profile = ? ORDER BY id DESC LIMIT ?)",
+            params![self.profile, self.profile, SEARCH_HISTORY_LIMIT],
+        )?;
+        Ok(())
+    }
+
+    /// Recent search queries, most recent first.
+    pub fn search_history(&self) -> Result<Vec<String>> {
+        let conn = self.conn.as_ref().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT query FROM search_history WHERE profile = ? ORDER BY id DESC")?;
+
+        let queries = stmt
+            .query_map([&self.profile], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(queries)
+    }
+
+    pub fn select_game(&self, path: &str) -> Result<Option<Game>> {
+        let game = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row("SELECT name, path, image, play_count, play_time, last_played, core, id FROM games WHERE profile = ? AND path = ? LIMIT 1", params![self.profile, path], map_game)
+            .optional()?;
+
+        Ok(game)
+    }
+
+    pub fn select_games(&self, paths: &[&Path]) -> Result<Vec<Option<Game>>> {
+        let mut stmt = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .prepare("SELECT name, path, image, play_count, play_time, last_played, core, id FROM games WHERE profile = ? AND path = ?")?;
+
+        let mut results = vec![None; paths.len()];
         for (i, path) in paths.iter().enumerate() {
             let game = stmt
-                .query_row(params![path.display().to_string()], map_game)
+                .query_row(params![self.profile, path.display().to_string()], map_game)
                 .optional()?;
 
             results[i] = game;
@@ -241,17 +931,60 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?",
 
     pub fn select_all_games(&self) -> Result<Vec<Game>> {
         let mut stmt = self.conn.as_ref().unwrap().prepare(
-            "SELECT name, path, image, play_count, play_time, last_played, core FROM games",
+            "SELECT name, path, image, play_count, play_time, last_played, core, id FROM games WHERE profile = ?",
         )?;
 
         let results = stmt
-            .query_map([], map_game)?
+            .query_map([&self.profile], map_game)?
             .filter_map(|r| r.ok())
             .collect();
 
         Ok(results)
     }
 
+    /// How many games are in the library.
+    pub fn count_games(&self) -> Result<usize> {
+        let count = self.conn.as_ref().unwrap().query_row(
+            "SELECT COUNT(*) FROM games WHERE profile = ?",
+            [&self.profile],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Calls `f` with every game in path order without loading them all into memory.
+    pub fn for_each_game(&self, mut f: impl FnMut(Game) -> Result<()>) -> Result<()> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT name, path, image, play_count, play_time, last_played, core, id FROM games WHERE profile = ? ORDER BY path",
+        )?;
+
+        let mut rows = stmt.query([&self.profile])?;
+        while let Some(row) = rows.next()? {
+            f(map_game(row)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Like `for_each_game`, for the games in `dir` and its subfolders.
+    pub fn for_each_game_in(
+        &self,
+        dir: &Path,
+        mut f: impl FnMut(Game) -> Result<()>,
+    ) -> Result<()> {
+        let pattern = format!("{}/%", escape_like(&dir.display().to_string()));
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT name, path, image, play_count, play_time, last_played, core, id FROM games WHERE profile = ? AND path LIKE ? ESCAPE '\\' ORDER BY path",
+        )?;
+
+        let mut rows = stmt.query(params![self.profile, pattern])?;
+        while let Some(row) = rows.next()? {
+            f(map_game(row)?)?;
+        }
+
+        Ok(())
+    }
+
     /// Increment the play count of a game, inserting a new row if it doesn't exist.
     pub fn increment_play_count(
         &self,
@@ -261,10 +994,11 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?",
     ) -> Result<()> {
         self.conn.as_ref().unwrap().execute(
             "
-INSERT INTO games (name, path, image, play_count, play_time, last_played, core)
-VALUES (?, ?, ?, ?, ?, ?, ?)
-ON CONFLICT(path) DO UPDATE SET play_count = play_count + 1;",
+INSERT INTO games (profile, name, path, image, play_count, play_time, last_played, core)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT(profile, path) DO UPDATE SET play_count = play_count + 1;",
             params![
+                self.profile,
                 name,
                 path.display().to_string(),
                 image.map(|p| p.display().to_string()),
@@ -276,19 +1010,197 @@ ON CONFLICT(path) DO UPDATE SET play_count = play_count + 1;",
         )?;
 
         self.conn.as_ref().unwrap().execute(
-            "UPDATE games SET last_played = (SELECT MAX(last_played) FROM games) + 1 WHERE path = ?",
-        [path.display().to_string()])?;
+            "UPDATE games SET last_played = (SELECT MAX(last_played) FROM games) + 1, first_played = COALESCE(first_played, ?) WHERE profile = ? AND path = ?",
+        params![Utc::now().timestamp(), self.profile, path.display().to_string()])?;
 
         Ok(())
     }
 
-    /// Increases the play time of a game. Does nothing if the game doesn't exist.
-    pub fn add_play_time(&self, path: &Path, play_time: Duration) -> Result<()> {
+    /// Moves a game to the top of the recently played list, e.g. when it is resumed after being
+    /// paused. Does nothing if the game doesn't exist or is already the most recently played.
+    pub fn touch_last_played(&self, path: &Path) -> Result<()> {
         self.conn.as_ref().unwrap().execute(
-            "UPDATE games SET play_time = play_time + ? WHERE path = ?",
-            params![play_time.num_seconds(), path.display().to_string()],
+            "UPDATE games SET last_played = (SELECT MAX(last_played) FROM games) + 1 WHERE profile = ? AND path = ? AND last_played < (SELECT MAX(last_played) FROM games)",
+            params![self.profile, path.display().to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    /// Records that a game had to be force quit. Does nothing if the game doesn't exist.
+    pub fn add_unclean_exit(&self, path: &Path) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE games SET unclean_exits = unclean_exits + 1 WHERE profile = ? AND path = ?",
+            params![self.profile, path.display().to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    /// Number of times a game had to be force quit.
+    pub fn unclean_exits(&self, path: &Path) -> Result<i64> {
+        let count = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT unclean_exits FROM games WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(count.unwrap_or(0))
+    }
+
+    /// Sets how long to wait for other processes to release the database before giving up.
+    /// Rebuilds the database file to reclaim space left by deleted rows.
+    pub fn vacuum(&self) -> Result<()> {
+        let _guard = write_activity::begin("database vacuum");
+        self.conn
+            .as_ref()
+            .unwrap()
+            .execute_batch("VACUUM; PRAGMA optimize;")?;
+        Ok(())
+    }
+
+    /// Returns a handle that can stop a long running query, such as [`Database::vacuum`], from
+    /// another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.conn.as_ref().unwrap().get_interrupt_handle()
+    }
+
+    pub fn set_busy_timeout(&self, timeout: std::time::Duration) -> Result<()> {
+        self.conn.as_ref().unwrap().busy_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Increases the play time of a game, and records the session for play time over a period.
+    /// Does nothing if the game doesn't exist.
+    pub fn add_play_time(&self, path: &Path, play_time: Duration) -> Result<()> {
+        let conn = self.conn.as_ref().unwrap();
+        let updated = conn.execute(
+            "UPDATE games SET play_time = play_time + ? WHERE profile = ? AND path = ?",
+            params![
+                play_time.num_seconds(),
+                self.profile,
+                path.display().to_string()
+            ],
+        )?;
+        if updated > 0 {
+            conn.execute(
+                "INSERT INTO play_sessions (profile, path, ended, play_time) VALUES (?, ?, ?, ?)",
+                params![
+                    self.profile,
+                    path.display().to_string(),
+                    Utc::now().timestamp(),
+                    play_time.num_seconds(),
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Play time of all games together.
+    pub fn total_play_time(&self) -> Result<Duration> {
+        let seconds: i64 = self.conn.as_ref().unwrap().query_row(
+            "SELECT COALESCE(SUM(play_time), 0) FROM games WHERE profile = ?",
+            [&self.profile],
+            |row| row.get(0),
         )?;
+        Ok(Duration::seconds(seconds))
+    }
+
+    /// Play time of the sessions that ended since `since`.
+    pub fn play_time_since(&self, since: DateTime<Utc>) -> Result<Duration> {
+        let seconds: i64 = self.conn.as_ref().unwrap().query_row(
+            "SELECT COALESCE(SUM(play_time), 0) FROM play_sessions WHERE profile = ? AND ended >= ?",
+            params![self.profile, since.timestamp()],
+            |row| row.get(0),
+        )?;
+        Ok(Duration::seconds(seconds))
+    }
+
+    /// When the first game was first launched, if any has been since this was recorded.
+    pub fn first_played(&self) -> Result<Option<DateTime<Utc>>> {
+        let timestamp: Option<i64> = self.conn.as_ref().unwrap().query_row(
+            "SELECT MIN(first_played) FROM games WHERE profile = ?",
+            [&self.profile],
+            |row| row.get(0),
+        )?;
+        Ok(timestamp.and_then(|t| Utc.timestamp_opt(t, 0).single()))
+    }
+
+    /// Ids of the rows from `source` whose play time has already been imported.
+    pub fn imported_rows(&self, source: &str) -> Result<HashSet<i64>> {
+        let mut stmt =
+            self.conn.as_ref().unwrap().prepare(
+                "SELECT row_id FROM imported_play_time WHERE profile = ? AND source = ?",
+            )?;
 
+        let results = stmt
+            .query_map(params![self.profile, source], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Adds play time imported from `source` to the games, counting each source row once however
+    /// many times it is imported. `games` are ordered oldest played first, and those that were
+    /// never played before are listed in that order below the games that were in the recently
+    /// played list, which only records the order games were played in.
+    pub fn import_play_time(&self, source: &str, games: &[ImportedGame]) -> Result<()> {
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?;
+
+        let mut unplayed = Vec::new();
+        for game in games {
+            let path = game.path.display().to_string();
+            let mut play_time = 0;
+            for (row_id, duration) in &game.rows {
+                let inserted = tx.execute(
+                    "INSERT OR IGNORE INTO imported_play_time (profile, source, row_id) VALUES (?, ?, ?)",
+                    params![self.profile, source, row_id],
+                )?;
+                if inserted > 0 {
+                    play_time += duration.num_seconds();
+                }
+            }
+            if play_time == 0 {
+                continue;
+            }
+
+            tx.execute(
+                "UPDATE games SET play_time = play_time + ? WHERE profile = ? AND path = ?",
+                params![play_time, self.profile, path],
+            )?;
+            let last_played: Option<i64> = tx
+                .query_row(
+                    "SELECT last_played FROM games WHERE profile = ? AND path = ?",
+                    params![self.profile, path],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if last_played == Some(0) {
+                unplayed.push(path);
+            }
+        }
+
+        if !unplayed.is_empty() {
+            tx.execute(
+                "UPDATE games SET last_played = last_played + ? WHERE last_played > 0",
+                [unplayed.len() as i64],
+            )?;
+            for (i, path) in unplayed.iter().enumerate() {
+                tx.execute(
+                    "UPDATE games SET last_played = ? WHERE profile = ? AND path = ?",
+                    params![i as i64 + 1, self.profile, path],
+                )?;
+            }
+        }
+
+        tx.commit()?;
         Ok(())
     }
 
@@ -320,70 +1232,898 @@ ON CONFLICT(path) DO UPDATE SET play_count = play_count + 1;",
     /// Deletes a game from the database.
     pub fn delete_game(&self, path: &Path) -> Result<()> {
         self.conn.as_ref().unwrap().execute(
-            "DELETE FROM games WHERE path = ?",
-            [path.display().to_string()],
+            "DELETE FROM games WHERE profile = ? AND path = ?",
+            params![self.profile, path.display().to_string()],
         )?;
 
         Ok(())
     }
 
+    /// Deletes a game from the database along with everything else recorded about it, such as
+    /// whether it's a favorite, its note, and its play sessions.
+    pub fn purge_game(&self, path: &Path) -> Result<()> {
+        let path = path.display().to_string();
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?;
+        for table in [
+            "games",
+            "favorites",
+            "game_notes",
+            "game_names",
+            "game_settings",
+            "game_flags",
+            "play_sessions",
+        ] {
+            tx.execute(
+                &format!("DELETE FROM {table} WHERE profile = ? AND path = ?"),
+                params![self.profile, path],
+            )?;
+        }
+        tx.execute(
+            "DELETE FROM collection_games WHERE path = ? AND collection IN (SELECT id FROM collections WHERE profile = ?)",
+            params![path, self.profile],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Removes a game from the recently played list by forgetting that it was played. A game whose
+    /// file is gone is removed from the database instead.
+    pub fn remove_from_recents(&self, path: &Path) -> Result<()> {
+        if path.exists() {
+            self.reset_game(path)
+        } else {
+            self.delete_game(path)
+        }
+    }
+
     /// Deletes all games that have no play time, play count.
     pub fn delete_all_unplayed_games(&self) -> Result<()> {
         self.conn.as_ref().unwrap().execute(
-            "DELETE FROM games WHERE last_played = 0 AND play_time = 0",
+            "DELETE FROM games WHERE profile = ? AND last_played = 0 AND play_time = 0",
+            [&self.profile],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn set_has_indexed(&self, has_indexed: bool) -> Result<()> {
+        self
+            .conn
+            .as_ref()
+            .unwrap()
+            .execute("INSERT INTO key_value (key, value) VALUES ('has_indexed', 1) ON CONFLICT(key) DO UPDATE SET value = ?", [if has_indexed { "1" } else {"0"}])?;
+
+        Ok(())
+    }
+
+    pub fn has_indexed(&self) -> Result<bool> {
+        let value = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT value FROM key_value WHERE key = 'has_indexed'",
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+
+        Ok(matches!(value.as_deref(), Some("1")))
+    }
+
+    pub fn get_core(&self, path: &Path) -> Result<Option<String>> {
+        let core = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT core FROM games WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?
+            .flatten();
+
+        Ok(core)
+    }
+
+    pub fn set_core(&self, path: &Path, core: &str) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE games SET core = ? WHERE profile = ? AND path = ?",
+            params![core, self.profile, path.display().to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    /// The core chosen for a game in its options, which takes priority over any other.
+    pub fn core_override(&self, path: &Path) -> Result<Option<String>> {
+        let core = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT core FROM game_settings WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?
+            .flatten();
+
+        Ok(core)
+    }
+
+    /// Chooses the core a game launches with, or goes back to the default with `None`.
+    pub fn set_core_override(&self, path: &Path, core: Option<&str>) -> Result<()> {
+        let conn = self.conn.as_ref().unwrap();
+        match core {
+            Some(core) => conn.execute(
+                "INSERT INTO game_settings (profile, path, core) VALUES (?, ?, ?) ON CONFLICT(profile, path) DO UPDATE SET core = excluded.core",
+                params![self.profile, path.display().to_string(), core],
+            )?,
+            None => conn.execute(
+                "DELETE FROM game_settings WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+            )?,
+        };
+
+        Ok(())
+    }
+
+    /// Hides a game from the games list.
+    pub fn set_hidden(&self, path: &Path, hidden: bool) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "INSERT INTO game_flags (profile, path, hidden) VALUES (?, ?, ?) ON CONFLICT(profile, path) DO UPDATE SET hidden = excluded.hidden",
+            params![self.profile, path.display().to_string(), hidden],
+        )?;
+
+        Ok(())
+    }
+
+    /// Paths of all hidden games.
+    pub fn hidden_games(&self) -> Result<HashSet<PathBuf>> {
+        let conn = self.conn.as_ref().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT path FROM game_flags WHERE profile = ? AND hidden = 1")?;
+        let paths = stmt
+            .query_map([&self.profile], |row| {
+                Ok(PathBuf::from(row.get::<_, String>(0)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(paths)
+    }
+
+    pub fn completed_games(&self) -> Result<HashSet<PathBuf>> {
+        let conn = self.conn.as_ref().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT path FROM game_flags WHERE profile = ? AND completed = 1")?;
+        let paths = stmt
+            .query_map([&self.profile], |row| {
+                Ok(PathBuf::from(row.get::<_, String>(0)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(paths)
+    }
+
+    /// Titles resolved from a names database for games in `dir`, including its subfolders. Games
+    /// that the names database doesn't know are stored as `None`, so that the database doesn't
+    /// have to be read again for them.
+    pub fn game_titles(&self, dir: &Path) -> Result<HashMap<PathBuf, Option<GameTitle>>> {
+        let pattern = format!(
+            "{}/%",
+            dir.display()
+                .to_string()
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let conn = self.conn.as_ref().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT path, name, manufacturer, year FROM game_titles WHERE path LIKE ? ESCAPE '\\'",
+        )?;
+        let titles = stmt
+            .query_map([pattern], |row| {
+                let path = PathBuf::from(row.get::<_, String>(0)?);
+                let manufacturer = row.get(2)?;
+                let year = row.get(3)?;
+                let title = row.get::<_, Option<String>>(1)?.map(|name| GameTitle {
+                    name,
+                    manufacturer,
+                    year,
+                });
+                Ok((path, title))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(titles)
+    }
+
+    pub fn set_game_titles(&self, titles: &[(PathBuf, Option<GameTitle>)]) -> Result<()> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "INSERT INTO game_titles (path, name, manufacturer, year) VALUES (?, ?, ?, ?) ON CONFLICT(path) DO UPDATE SET name = excluded.name, manufacturer = excluded.manufacturer, year = excluded.year",
+        )?;
+
+        for (path, title) in titles {
+            stmt.execute(params![
+                path.display().to_string(),
+                title.as_ref().map(|t| &t.name),
+                title.as_ref().and_then(|t| t.manufacturer.as_ref()),
+                title.as_ref().and_then(|t| t.year),
+            ])?;
+        }
+
+        Ok(())
+    }
+
+    /// Forgets all resolved titles, e.g. after a names database changed.
+    pub fn clear_game_titles(&self) -> Result<()> {
+        self.conn
+            .as_ref()
+            .unwrap()
+            .execute("DELETE FROM game_titles", [])?;
+
+        Ok(())
+    }
+
+    pub fn set_completed(&self, path: &Path, completed: bool) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "INSERT INTO game_flags (profile, path, completed) VALUES (?, ?, ?) ON CONFLICT(profile, path) DO UPDATE SET completed = excluded.completed",
+            params![self.profile, path.display().to_string(), completed],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn is_completed(&self, path: &Path) -> Result<bool> {
+        let completed = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT completed FROM game_flags WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+                |row| row.get::<_, bool>(0),
+            )
+            .optional()?
+            .unwrap_or_default();
+
+        Ok(completed)
+    }
+
+    /// The note written for a game, if any.
+    pub fn note(&self, path: &Path) -> Result<Option<String>> {
+        let note = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT note FROM game_notes WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(note)
+    }
+
+    /// Replaces the note for a game. An empty note is removed.
+    pub fn set_note(&self, path: &Path, note: &str) -> Result<()> {
+        let conn = self.conn.as_ref().unwrap();
+        if note.is_empty() {
+            conn.execute(
+                "DELETE FROM game_notes WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+            )?;
+        } else {
+            conn.execute(
+                "INSERT INTO game_notes (profile, path, note) VALUES (?, ?, ?) ON CONFLICT(profile, path) DO UPDATE SET note = excluded.note",
+                params![self.profile, path.display().to_string(), note],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Notes for all games, by path.
+    pub fn notes(&self) -> Result<HashMap<PathBuf, String>> {
+        let conn = self.conn.as_ref().unwrap();
+        let mut stmt = conn.prepare("SELECT path, note FROM game_notes WHERE profile = ?")?;
+        let notes = stmt
+            .query_map([&self.profile], |row| {
+                Ok((PathBuf::from(row.get::<_, String>(0)?), row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(notes)
+    }
+
+    /// Renames a game, showing it under `name` instead of the name from its file. An empty name
+    /// removes the override.
+    pub fn set_game_name(&self, path: &Path, name: &str) -> Result<()> {
+        let conn = self.conn.as_ref().unwrap();
+        if name.is_empty() {
+            conn.execute(
+                "DELETE FROM game_names WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+            )?;
+        } else {
+            conn.execute(
+                "INSERT INTO game_names (profile, path, name) VALUES (?, ?, ?) ON CONFLICT(profile, path) DO UPDATE SET name = excluded.name",
+                params![self.profile, path.display().to_string(), name],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Names that games were renamed to, by path.
+    pub fn game_names(&self) -> Result<HashMap<PathBuf, String>> {
+        let conn = self.conn.as_ref().unwrap();
+        let mut stmt = conn.prepare("SELECT path, name FROM game_names WHERE profile = ?")?;
+        let names = stmt
+            .query_map([&self.profile], |row| {
+                Ok((PathBuf::from(row.get::<_, String>(0)?), row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(names)
+    }
+
+    /// Marks or unmarks a game as a favorite.
+    pub fn set_favorite(&self, path: &Path, favorite: bool) -> Result<()> {
+        let conn = self.conn.as_ref().unwrap();
+        if favorite {
+            conn.execute(
+                "INSERT OR IGNORE INTO favorites (profile, path) VALUES (?, ?)",
+                params![self.profile, path.display().to_string()],
+            )?;
+        } else {
+            conn.execute(
+                "DELETE FROM favorites WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Pins a game to the top of the recently played list, after the games already pinned, or
+    /// unpins it.
+    pub fn set_pinned(&self, path: &Path, pinned: bool) -> Result<()> {
+        let conn = self.conn.as_ref().unwrap();
+        if pinned {
+            conn.execute(
+                "UPDATE games SET pinned = (SELECT COALESCE(MAX(pinned), 0) + 1 FROM games WHERE profile = ?1) WHERE profile = ?1 AND path = ?2 AND pinned IS NULL",
+                params![self.profile, path.display().to_string()],
+            )?;
+        } else {
+            conn.execute(
+                "UPDATE games SET pinned = NULL WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Paths of the games pinned to the recently played list, in the order they were pinned.
+    pub fn pinned_games(&self) -> Result<Vec<PathBuf>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT path FROM games WHERE profile = ? AND pinned IS NOT NULL ORDER BY pinned",
+        )?;
+
+        let results = stmt
+            .query_map([&self.profile], |row| {
+                Ok(PathBuf::from(row.get::<_, String>(0)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(results)
+    }
+
+    pub fn is_favorite(&self, path: &Path) -> Result<bool> {
+        let favorite = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT 1 FROM favorites WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+
+        Ok(favorite)
+    }
+
+    /// Paths of all favorite games, in the order they were added. Games whose files are gone are
+    /// still listed.
+    pub fn favorites(&self) -> Result<Vec<PathBuf>> {
+        let conn = self.conn.as_ref().unwrap();
+        let mut stmt = conn.prepare("SELECT path FROM favorites WHERE profile = ? ORDER BY id")?;
+        let paths = stmt
+            .query_map([&self.profile], |row| {
+                Ok(PathBuf::from(row.get::<_, String>(0)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(paths)
+    }
+
+    /// The collections of the profile, by name.
+    pub fn collections(&self) -> Result<Vec<Collection>> {
+        let conn = self.conn.as_ref().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT id, name FROM collections WHERE profile = ? ORDER BY name")?;
+        let collections = stmt
+            .query_map([&self.profile], |row| {
+                Ok(Collection {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(collections)
+    }
+
+    /// Creates an empty collection, failing if the profile already has one named `name`.
+    pub fn create_collection(&self, name: &str) -> Result<Collection> {
+        let conn = self.conn.as_ref().unwrap();
+        conn.execute(
+            "INSERT INTO collections (profile, name) VALUES (?, ?)",
+            params![self.profile, name],
+        )?;
+        Ok(Collection {
+            id: conn.last_insert_rowid(),
+            name: name.to_string(),
+        })
+    }
+
+    /// Renames a collection, failing if the profile already has one named `name`.
+    pub fn rename_collection(&self, id: i64, name: &str) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE collections SET name = ? WHERE profile = ? AND id = ?",
+            params![name, self.profile, id],
+        )?;
+        Ok(())
+    }
+
+    /// Deletes a collection. The games in it are left alone.
+    pub fn delete_collection(&self, id: i64) -> Result<()> {
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?;
+        let deleted = tx.execute(
+            "DELETE FROM collections WHERE profile = ? AND id = ?",
+            params![self.profile, id],
+        )?;
+        if deleted > 0 {
+            tx.execute(
+                "DELETE FROM collection_games WHERE collection = ?",
+                params![id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Paths of the games in a collection, in the order they were added. Games whose files are
+    /// gone are still listed.
+    pub fn collection_games(&self, id: i64) -> Result<Vec<PathBuf>> {
+        let conn = self.conn.as_ref().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT collection_games.path FROM collection_games
+            JOIN collections ON collections.id = collection_games.collection
+            WHERE collections.profile = ? AND collections.id = ?
+            ORDER BY collection_games.position",
+        )?;
+        let paths = stmt
+            .query_map(params![self.profile, id], |row| {
+                Ok(PathBuf::from(row.get::<_, String>(0)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(paths)
+    }
+
+    /// Adds a game to the end of a collection, unless it is in it already.
+    pub fn add_to_collection(&self, id: i64, path: &Path) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "INSERT OR IGNORE INTO collection_games (collection, position, path)
+            SELECT id, (SELECT COALESCE(MAX(position), -1) + 1 FROM collection_games WHERE collection = ?), ?
+            FROM collections WHERE profile = ? AND id = ?",
+            params![id, path.display().to_string(), self.profile, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_from_collection(&self, id: i64, path: &Path) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "DELETE FROM collection_games WHERE path = ? AND collection IN (SELECT id FROM collections WHERE profile = ? AND id = ?)",
+            params![path.display().to_string(), self.profile, id],
+        )?;
+        Ok(())
+    }
+
+    /// Adds a game to the scrape queue, to be looked for at the first of `urls` that has it.
+    /// Games that were already scraped are queued again, but pending and failed games are left as
+    /// they are.
+    pub fn enqueue_scrape(&self, path: &Path, urls: &[String]) -> Result<()> {
+        self.queue_scrape(path, urls, "status = 'completed'")
+    }
+
+    /// Like `enqueue_scrape`, but games that failed are queued again too.
+    pub fn requeue_scrape(&self, path: &Path, urls: &[String]) -> Result<()> {
+        self.queue_scrape(path, urls, "status != 'pending'")
+    }
+
+    fn queue_scrape(&self, path: &Path, urls: &[String], requeue_if: &str) -> Result<()> {
+        let Some((url, fallback_urls)) = urls.split_first() else {
+            bail!("no URL to scrape {} from", path.display());
+        };
+        self.conn.as_ref().unwrap().execute(
+            &format!(
+                "
+INSERT INTO scrape_queue (path, url, fallback_urls, status) VALUES (?, ?, ?, 'pending')
+ON CONFLICT(path) DO UPDATE SET url = excluded.url, fallback_urls = excluded.fallback_urls, status = 'pending', retries = 0, next_attempt = 0, error = NULL
+WHERE {requeue_if}"
+            ),
+            params![path.display().to_string(), url, fallback_urls.join("\n")],
+        )?;
+
+        Ok(())
+    }
+
+    /// Takes the games that are still pending out of the scrape queue. Returns how many were.
+    pub fn cancel_scrape_jobs(&self, paths: &[PathBuf]) -> Result<usize> {
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?;
+        let mut cancelled = 0;
+        for path in paths {
+            cancelled += tx.execute(
+                "DELETE FROM scrape_queue WHERE path = ? AND status = 'pending'",
+                [path.display().to_string()],
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(cancelled)
+    }
+
+    /// Selects the pending job that is due the soonest.
+    pub fn next_scrape_job(&self) -> Result<Option<ScrapeJob>> {
+        let job = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT path, url, retries, next_attempt, error, fallback_urls FROM scrape_queue WHERE status = 'pending' ORDER BY next_attempt, id LIMIT 1",
+                [],
+                map_scrape_job,
+            )
+            .optional()?;
+
+        Ok(job)
+    }
+
+    pub fn complete_scrape_job(&self, path: &Path) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE scrape_queue SET status = 'completed', error = NULL WHERE path = ?",
+            [path.display().to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt, keeping the job pending until `next_attempt`.
+    pub fn retry_scrape_job(&self, path: &Path, error: &str, next_attempt: i64) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE scrape_queue SET retries = retries + 1, next_attempt = ?, error = ? WHERE path = ?",
+            params![next_attempt, error, path.display().to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt and gives up on the job.
+    pub fn fail_scrape_job(&self, path: &Path, error: &str) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE scrape_queue SET status = 'failed', retries = retries + 1, error = ? WHERE path = ?",
+            params![error, path.display().to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    /// Where the game at `path` is in the scrape queue, if it was ever queued.
+    pub fn scrape_status(&self, path: &Path) -> Result<Option<ScrapeStatus>> {
+        let status = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT status FROM scrape_queue WHERE path = ?",
+                [path.display().to_string()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+
+        Ok(status.map(|status| match status.as_str() {
+            "pending" => ScrapeStatus::Pending,
+            "completed" => ScrapeStatus::Completed,
+            _ => ScrapeStatus::Failed,
+        }))
+    }
+
+    pub fn scrape_progress(&self) -> Result<ScrapeProgress> {
+        let progress = self.conn.as_ref().unwrap().query_row(
+            "
+SELECT
+    COUNT(*) FILTER (WHERE status = 'pending'),
+    COUNT(*) FILTER (WHERE status = 'completed'),
+    COUNT(*) FILTER (WHERE status = 'failed')
+FROM scrape_queue",
+            [],
+            |row| {
+                Ok(ScrapeProgress {
+                    pending: row.get(0)?,
+                    completed: row.get(1)?,
+                    failed: row.get(2)?,
+                })
+            },
+        )?;
+
+        Ok(progress)
+    }
+
+    /// Selects jobs that were given up on, along with their last error.
+    pub fn failed_scrape_jobs(&self) -> Result<Vec<ScrapeJob>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT path, url, retries, next_attempt, error, fallback_urls FROM scrape_queue WHERE status = 'failed' ORDER BY id",
+        )?;
+
+        let results = stmt
+            .query_map([], map_scrape_job)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Moves all failed jobs back into the queue with their retries reset.
+    pub fn retry_failed_scrape_jobs(&self) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE scrape_queue SET status = 'pending', retries = 0, next_attempt = 0 WHERE status = 'failed'",
             [],
         )?;
 
         Ok(())
     }
 
-    pub fn set_has_indexed(&self, has_indexed: bool) -> Result<()> {
-        self
-            .conn
+    pub fn clear_scrape_queue(&self) -> Result<()> {
+        self.conn
             .as_ref()
             .unwrap()
-            .execute("INSERT INTO key_value (key, value) VALUES ('has_indexed', 1) ON CONFLICT(key) DO UPDATE SET value = ?", [if has_indexed { "1" } else {"0"}])?;
+            .execute("DELETE FROM scrape_queue", [])?;
 
         Ok(())
     }
 
-    pub fn has_indexed(&self) -> Result<bool> {
-        let value = self
+    /// The game at `path` as it is recorded now, if it is in the database.
+    pub fn snapshot_game(&self, path: &Path) -> Result<Option<GameSnapshot>> {
+        let snapshot = self
             .conn
             .as_ref()
             .unwrap()
             .query_row(
-                "SELECT value FROM key_value WHERE key = 'has_indexed'",
-                [],
-                |row| row.get::<_, String>(0),
+                "SELECT name, path, image, play_count, play_time, last_played, core, unclean_exits, file_size, crc, pinned, first_played FROM games WHERE profile = ? AND path = ?",
+                params![self.profile, path.display().to_string()],
+                map_snapshot,
             )
             .optional()?;
+        Ok(snapshot)
+    }
 
-        Ok(matches!(value.as_deref(), Some("1")))
+    /// Everything recorded about a game outside of the games table.
+    pub fn snapshot_related(&self, path: &Path) -> Result<RelatedRows> {
+        let conn = self.conn.as_ref().unwrap();
+        let path = path.display().to_string();
+        let params = params![self.profile, path];
+
+        let (hidden, completed) = conn
+            .query_row(
+                "SELECT hidden, completed FROM game_flags WHERE profile = ? AND path = ?",
+                params,
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .unwrap_or_default();
+
+        let mut stmt = conn.prepare(
+            "SELECT collection, position FROM collection_games WHERE path = ? AND collection IN (SELECT id FROM collections WHERE profile = ?)",
+        )?;
+        let collections = stmt
+            .query_map(params![path, self.profile], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT ended, play_time FROM play_sessions WHERE profile = ? AND path = ? ORDER BY id",
+        )?;
+        let play_sessions = stmt
+            .query_map(params, |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(RelatedRows {
+            favorite: conn
+                .query_row(
+                    "SELECT id FROM favorites WHERE profile = ? AND path = ?",
+                    params,
+                    |row| row.get(0),
+                )
+                .optional()?,
+            note: conn
+                .query_row(
+                    "SELECT note FROM game_notes WHERE profile = ? AND path = ?",
+                    params,
+                    |row| row.get(0),
+                )
+                .optional()?,
+            name: conn
+                .query_row(
+                    "SELECT name FROM game_names WHERE profile = ? AND path = ?",
+                    params,
+                    |row| row.get(0),
+                )
+                .optional()?,
+            core: conn
+                .query_row(
+                    "SELECT core FROM game_settings WHERE profile = ? AND path = ?",
+                    params,
+                    |row| row.get::<_, Option<String>>(0),
+                )
+                .optional()?
+                .flatten(),
+            hidden,
+            completed,
+            collections,
+            play_sessions,
+        })
     }
 
-    pub fn get_core(&self, path: &Path) -> Result<Option<String>> {
-        let core = self
+    /// The games on the recently played list as they are recorded now.
+    pub fn snapshot_recents(&self) -> Result<Vec<GameSnapshot>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT name, path, image, play_count, play_time, last_played, core, unclean_exits, file_size, crc, pinned, first_played FROM games WHERE profile = ? AND (last_played > 0 OR pinned IS NOT NULL) ORDER BY last_played DESC",
+        )?;
+
+        let results = stmt
+            .query_map([&self.profile], map_snapshot)?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(results)
+    }
+
+    /// Puts back games as they were snapshot, whether they were reset or deleted since.
+    pub fn restore_games(&self, games: &[GameSnapshot]) -> Result<()> {
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "
+INSERT INTO games (profile, name, path, image, play_count, play_time, last_played, core, unclean_exits, file_size, crc, pinned, first_played)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT(profile, path) DO UPDATE SET
+    name = excluded.name,
+    image = excluded.image,
+    play_count = excluded.play_count,
+    play_time = excluded.play_time,
+    last_played = excluded.last_played,
+    core = excluded.core,
+    unclean_exits = excluded.unclean_exits,
+    file_size = excluded.file_size,
+    crc = excluded.crc,
+    pinned = excluded.pinned,
+    first_played = excluded.first_played",
+            )?;
+            for game in games {
+                stmt.execute(params![
+                    self.profile,
+                    game.name,
+                    game.path.display().to_string(),
+                    game.image.as_ref().map(|p| p.display().to_string()),
+                    game.play_count,
+                    game.play_time,
+                    game.last_played,
+                    game.core,
+                    game.unclean_exits,
+                    game.file_size,
+                    game.crc,
+                    game.pinned,
+                    game.first_played,
+                ])?;
+            }
+        }
+        for game in games {
+            if let Some(related) = &game.related {
+                restore_related(&tx, &self.profile, &game.path, related)?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Records how to undo an action, returning the id to undo it by.
+    pub fn add_undo(
+        &self,
+        kind: UndoKind,
+        files: &[TrashedFile],
+        games: &[GameSnapshot],
+    ) -> Result<i64> {
+        let conn = self.conn.as_ref().unwrap();
+        conn.execute(
+            "INSERT INTO undo (profile, kind, created, files, games) VALUES (?, ?, ?, ?, ?)",
+            params![
+                self.profile,
+                <&str>::from(kind),
+                Utc::now().timestamp(),
+                serde_json::to_string(files)?,
+                serde_json::to_string(games)?,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn undo_entry(&self, id: i64) -> Result<Option<UndoEntry>> {
+        let mut stmt = self
             .conn
             .as_ref()
             .unwrap()
-            .query_row(
-                "SELECT core FROM games WHERE path = ?",
-                [path.display().to_string()],
-                |row| row.get::<_, Option<String>>(0),
-            )
-            .optional()?
-            .flatten();
+            .prepare("SELECT id, kind, created, files, games FROM undo WHERE id = ?")?;
 
-        Ok(core)
+        let mut rows = stmt.query([id])?;
+        rows.next()?.map(map_undo_entry).transpose()
     }
 
-    pub fn set_core(&self, path: &Path, core: &str) -> Result<()> {
-        self.conn.as_ref().unwrap().execute(
-            "UPDATE games SET core = ? WHERE path = ?",
-            params![core, path.display().to_string()],
+    /// Undoable actions of the current profile, newest first.
+    pub fn undo_entries(&self) -> Result<Vec<UndoEntry>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT id, kind, created, files, games FROM undo WHERE profile = ? ORDER BY id DESC",
         )?;
 
+        let mut rows = stmt.query([&self.profile])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(map_undo_entry(row)?);
+        }
+
+        Ok(results)
+    }
+
+    /// Undoable actions of all profiles, oldest first.
+    pub fn all_undo_entries(&self) -> Result<Vec<UndoEntry>> {
+        let mut stmt = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .prepare("SELECT id, kind, created, files, games FROM undo ORDER BY id")?;
+
+        let mut rows = stmt.query([])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(map_undo_entry(row)?);
+        }
+
+        Ok(results)
+    }
+
+    pub fn remove_undo(&self, id: i64) -> Result<()> {
+        self.conn
+            .as_ref()
+            .unwrap()
+            .execute("DELETE FROM undo WHERE id = ?", [id])?;
+
         Ok(())
     }
 }
@@ -495,6 +2235,132 @@ mod tests {
         assert_eq!(last_played[1].path, games[1].path);
     }
 
+    #[test]
+    fn test_touch_last_played() -> Result<()> {
+        let database = Database::in_memory()?;
+
+        let games: Vec<NewGame> = ["Paused", "Quick"]
+            .into_iter()
+            .map(|name| NewGame {
+                name: name.to_string(),
+                path: PathBuf::from(format!("{}.rom", name)),
+                image: None,
+                core: None,
+            })
+            .collect();
+        database.update_games(&games)?;
+
+        // Launch a game and keep it paused in the background, then play another one
+        database.increment_play_count(&games[0].name, &games[0].path, None)?;
+        database.increment_play_count(&games[1].name, &games[1].path, None)?;
+        let last_played = database.select_last_played(2)?;
+        assert_eq!(last_played[0].path, games[1].path);
+
+        // Resuming the paused game moves it back to the top
+        database.touch_last_played(&games[0].path)?;
+        let last_played = database.select_last_played(2)?;
+        assert_eq!(last_played[0].path, games[0].path);
+        assert_eq!(last_played[1].path, games[1].path);
+
+        // Resuming it again doesn't change anything
+        let before = last_played[0].last_played;
+        database.touch_last_played(&games[0].path)?;
+        assert_eq!(database.select_last_played(2)?[0].last_played, before);
+
+        // Games that were never launched stay out of the recents list
+        database.touch_last_played(Path::new("Missing.rom"))?;
+        assert_eq!(database.select_last_played(10)?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unclean_exits() -> Result<()> {
+        let database = Database::in_memory()?;
+        let game = NewGame {
+            name: "Frozen".to_string(),
+            path: PathBuf::from("Frozen.rom"),
+            image: None,
+            core: None,
+        };
+        database.update_games(std::slice::from_ref(&game))?;
+
+        assert_eq!(database.unclean_exits(&game.path)?, 0);
+        database.add_unclean_exit(&game.path)?;
+        database.add_unclean_exit(&game.path)?;
+        assert_eq!(database.unclean_exits(&game.path)?, 2);
+
+        // Other profiles keep their own count
+        let kids = database.with_profile("kids");
+        kids.update_games(std::slice::from_ref(&game))?;
+        assert_eq!(kids.unclean_exits(&game.path)?, 0);
+
+        database.add_unclean_exit(Path::new("Missing.rom"))?;
+        assert_eq!(database.unclean_exits(Path::new("Missing.rom"))?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_suggest_names() -> Result<()> {
+        let database = Database::in_memory()?;
+        let games: Vec<NewGame> = [
+            "Mario Kart",
+            "Mario Party",
+            "Metroid",
+            "Mario_Bros",
+            "Zelda",
+        ]
+        .into_iter()
+        .map(|name| NewGame {
+            name: name.to_string(),
+            path: PathBuf::from(format!("{}.rom", name)),
+            image: None,
+            core: None,
+        })
+        .collect();
+        database.update_games(&games)?;
+        database.increment_play_count(&games[1].name, &games[1].path, None)?;
+
+        // Most played first, then alphabetically
+        assert_eq!(
+            database.suggest_names("mario", 5)?,
+            vec!["Mario Party", "Mario Kart", "Mario_Bros"]
+        );
+        assert_eq!(
+            database.suggest_names("M", 2)?,
+            vec!["Mario Party", "Mario Kart"]
+        );
+        // Wildcards are matched literally
+        assert_eq!(database.suggest_names("Mario_", 5)?, vec!["Mario_Bros"]);
+        assert_eq!(database.suggest_names("%", 5)?, Vec::<String>::new());
+        assert_eq!(database.suggest_names("", 5)?, Vec::<String>::new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_history() -> Result<()> {
+        let database = Database::in_memory()?;
+        for i in 0..25 {
+            database.add_search_query(&format!("query {}", i))?;
+        }
+        database.add_search_query("query 10")?;
+        database.with_profile("kids").add_search_query("kids")?;
+
+        let history = database.search_history()?;
+        assert_eq!(history.len(), SEARCH_HISTORY_LIMIT as usize);
+        assert_eq!(history[0], "query 10");
+        assert_eq!(history[1], "query 24");
+        assert!(!history.contains(&"query 4".to_string()));
+        assert_eq!(
+            database.with_profile("kids").search_history()?,
+            vec!["kids"]
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_search() {
         let database = Database::in_memory().unwrap();
@@ -515,6 +2381,7 @@ mod tests {
         ];
 
         database.update_games(&games).unwrap();
+        assert_eq!(database.count_games().unwrap(), 2);
 
         let results = database.search("Game", 100).unwrap();
         assert_eq!(results.len(), 2);
@@ -527,6 +2394,38 @@ mod tests {
 
         let results = database.search("Ga", 100).unwrap();
         assert_eq!(results[0].path, games[0].path);
+
+        // Matches inside words too, ignoring case
+        let results = database.search("WO", 100).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, games[1].path);
+
+        // Games matched both ways are only listed once
+        let results = database.search("game", 100).unwrap();
+        assert_eq!(results.len(), 2);
+
+        let results = database.search("ame", 1).unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_search_ranks_word_prefix_first() -> Result<()> {
+        let database = Database::in_memory()?;
+
+        let games = ["Ario Race", "Rio Grande"].map(|name| NewGame {
+            name: name.to_string(),
+            path: PathBuf::from(format!("test_directory/{}.rom", name)),
+            image: None,
+            core: None,
+        });
+        database.update_games(&games)?;
+
+        // "Ario Race" sorts first by name, but only contains the query inside a word
+        let results = database.search("rio", 100)?;
+        let names: Vec<_> = results.iter().map(|game| game.name.as_str()).collect();
+        assert_eq!(names, ["Rio Grande", "Ario Race"]);
+
+        Ok(())
     }
 
     #[test]
@@ -601,6 +2500,638 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_core_override() -> Result<()> {
+        let db = Database::in_memory()?;
+        let other = db.with_profile("other");
+
+        let game = PathBuf::from("test_directory/Game One.gba");
+        assert_eq!(db.core_override(&game)?, None);
+
+        db.set_core_override(&game, Some("gpsp"))?;
+        db.set_core_override(&game, Some("mgba"))?;
+        assert_eq!(db.core_override(&game)?.as_deref(), Some("mgba"));
+        assert_eq!(other.core_override(&game)?, None);
+
+        // Overrides follow a game that moved
+        let moved = PathBuf::from("test_directory/Game One (USA).gba");
+        db.relink_game(&game, &moved)?;
+        assert_eq!(db.core_override(&game)?, None);
+        assert_eq!(db.core_override(&moved)?.as_deref(), Some("mgba"));
+
+        // Going back to the default removes it
+        db.set_core_override(&moved, None)?;
+        assert_eq!(db.core_override(&moved)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_game_flags() -> Result<()> {
+        let db = Database::in_memory()?;
+        let other = db.with_profile("other");
+
+        let one = PathBuf::from("test_directory/Game One.rom");
+        let two = PathBuf::from("test_directory/Game Two.rom");
+
+        db.set_hidden(&one, true)?;
+        db.set_completed(&two, true)?;
+        assert_eq!(db.hidden_games()?, HashSet::from([one.clone()]));
+        assert!(!db.is_completed(&one)?);
+        assert!(db.is_completed(&two)?);
+
+        // Setting one flag leaves the other alone
+        db.set_completed(&one, true)?;
+        assert_eq!(db.hidden_games()?, HashSet::from([one.clone()]));
+        db.set_hidden(&one, false)?;
+        assert!(db.hidden_games()?.is_empty());
+        assert!(db.is_completed(&one)?);
+
+        assert!(other.hidden_games()?.is_empty());
+        assert!(!other.is_completed(&two)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_favorites() -> Result<()> {
+        let db = Database::in_memory()?;
+        let other = db.with_profile("other");
+
+        let one = PathBuf::from("test_directory/Game One.rom");
+        let two = PathBuf::from("test_directory/Game Two.rom");
+
+        db.set_favorite(&two, true)?;
+        db.set_favorite(&one, true)?;
+        db.set_favorite(&two, true)?;
+        assert!(db.is_favorite(&one)?);
+        assert_eq!(db.favorites()?, vec![two.clone(), one.clone()]);
+        assert!(other.favorites()?.is_empty());
+
+        db.set_favorite(&two, false)?;
+        assert!(!db.is_favorite(&two)?);
+        assert_eq!(db.favorites()?, vec![one.clone()]);
+
+        // Favorites follow a game that moved
+        let moved = PathBuf::from("test_directory/Game One (USA).rom");
+        db.relink_game(&one, &moved)?;
+        assert_eq!(db.favorites()?, vec![moved]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_play_activity() -> Result<()> {
+        let db = Database::in_memory()?;
+        let other = db.with_profile("other");
+        assert_eq!(db.total_play_time()?, Duration::zero());
+        assert_eq!(db.first_played()?, None);
+
+        let tetris = PathBuf::from("test_directory/Tetris.gb");
+        let zelda = PathBuf::from("test_directory/Zelda.gb");
+        let before = Utc::now() - Duration::seconds(1);
+        for _ in 0..3 {
+            db.increment_play_count("Tetris", &tetris, None)?;
+        }
+        db.increment_play_count("Zelda", &zelda, None)?;
+        db.add_play_time(&tetris, Duration::minutes(5))?;
+        db.add_play_time(&zelda, Duration::minutes(90))?;
+        // Not a game, so not played
+        db.add_play_time(Path::new("test_directory/Missing.gb"), Duration::hours(1))?;
+
+        let first_played = db.first_played()?.unwrap();
+        assert!(first_played >= before && first_played <= Utc::now());
+        assert_eq!(db.total_play_time()?, Duration::minutes(95));
+        assert_eq!(db.play_time_since(before)?, Duration::minutes(95));
+        assert_eq!(
+            db.play_time_since(Utc::now() + Duration::minutes(1))?,
+            Duration::zero()
+        );
+        let names = |games: Vec<Game>| games.into_iter().map(|g| g.name).collect::<Vec<_>>();
+        assert_eq!(names(db.select_most_launched(10)?), ["Tetris", "Zelda"]);
+        assert_eq!(names(db.select_most_played(10)?), ["Zelda", "Tetris"]);
+
+        assert_eq!(other.total_play_time()?, Duration::zero());
+        assert_eq!(other.play_time_since(before)?, Duration::zero());
+        assert!(other.select_most_launched(10)?.is_empty());
+
+        // Sessions follow a game that moved
+        let moved = PathBuf::from("test_directory/Tetris (World).gb");
+        db.relink_game(&tetris, &moved)?;
+        assert!(db.snapshot_related(&tetris)?.play_sessions.is_empty());
+        assert_eq!(db.snapshot_related(&moved)?.play_sessions.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pinned_recents() -> Result<()> {
+        let db = Database::in_memory()?;
+        let other = db.with_profile("other");
+
+        let paths: Vec<_> = ["One", "Two", "Three", "Four"]
+            .iter()
+            .map(|name| PathBuf::from(format!("test_directory/Game {name}.rom")))
+            .collect();
+        for path in &paths {
+            let name = path.file_stem().unwrap().to_str().unwrap();
+            db.increment_play_count(name, path, None)?;
+            other.increment_play_count(name, path, None)?;
+        }
+        let last_played = |db: &Database, limit| -> Result<Vec<PathBuf>> {
+            Ok(db
+                .select_last_played(limit)?
+                .into_iter()
+                .map(|game| game.path)
+                .collect())
+        };
+        assert_eq!(
+            last_played(&db, 2)?,
+            vec![paths[3].clone(), paths[2].clone()]
+        );
+
+        // Pinned games come first in the order they were pinned, and don't count towards the limit
+        db.set_pinned(&paths[1], true)?;
+        db.set_pinned(&paths[0], true)?;
+        db.set_pinned(&paths[1], true)?;
+        assert_eq!(db.pinned_games()?, vec![paths[1].clone(), paths[0].clone()]);
+        assert_eq!(
+            last_played(&db, 1)?,
+            vec![paths[1].clone(), paths[0].clone(), paths[3].clone()]
+        );
+        assert!(other.pinned_games()?.is_empty());
+
+        db.set_pinned(&paths[1], false)?;
+        assert_eq!(
+            last_played(&db, 4)?,
+            vec![
+                paths[0].clone(),
+                paths[3].clone(),
+                paths[2].clone(),
+                paths[1].clone()
+            ]
+        );
+
+        // Taking a game off the list unpins it, and undoing puts the pin back
+        let snapshot = db.snapshot_game(&paths[0])?.unwrap();
+        db.reset_game(&paths[0])?;
+        assert!(db.pinned_games()?.is_empty());
+        db.restore_games(&[snapshot])?;
+        assert_eq!(db.pinned_games()?, vec![paths[0].clone()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_collections() -> Result<()> {
+        let db = Database::in_memory()?;
+        let other = db.with_profile("other");
+
+        let snes = PathBuf::from("/Roms/SFC/Bomberman.sfc");
+        let ps = PathBuf::from("/Roms/PS/Crash Team Racing.chd");
+        let gba = PathBuf::from("/Roms/GBA/Mario Kart.gba");
+
+        let couch = db.create_collection("Couch co-op")?;
+        let rpgs = db.create_collection("RPGs")?;
+        assert!(db.create_collection("Couch co-op").is_err());
+        assert_eq!(db.collections()?, vec![couch.clone(), rpgs.clone()]);
+        assert!(other.collections()?.is_empty());
+
+        for path in [&snes, &ps, &gba, &snes] {
+            db.add_to_collection(couch.id, path)?;
+        }
+        assert_eq!(
+            db.collection_games(couch.id)?,
+            vec![snes.clone(), ps.clone(), gba.clone()]
+        );
+        assert!(db.collection_games(rpgs.id)?.is_empty());
+        // Other profiles can't see or change the collection
+        other.add_to_collection(couch.id, &snes)?;
+        assert!(other.collection_games(couch.id)?.is_empty());
+
+        db.remove_from_collection(couch.id, &ps)?;
+        db.add_to_collection(couch.id, &ps)?;
+        assert_eq!(
+            db.collection_games(couch.id)?,
+            vec![snes.clone(), gba.clone(), ps.clone()]
+        );
+
+        // Games follow a game that moved
+        let moved = PathBuf::from("/Roms/GBA/Mario Kart (USA).gba");
+        db.relink_game(&gba, &moved)?;
+        assert_eq!(
+            db.collection_games(couch.id)?,
+            vec![snes.clone(), moved, ps.clone()]
+        );
+
+        db.rename_collection(couch.id, "Party")?;
+        assert!(db.rename_collection(rpgs.id, "Party").is_err());
+        assert_eq!(db.collections()?[0].name, "Party");
+
+        other.delete_collection(couch.id)?;
+        assert_eq!(db.collection_games(couch.id)?.len(), 3);
+        db.delete_collection(couch.id)?;
+        assert_eq!(db.collections()?, vec![rpgs]);
+        assert!(db.collection_games(couch.id)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_game_names() -> Result<()> {
+        let db = Database::in_memory()?;
+        let other = db.with_profile("other");
+
+        let game = PathBuf::from("test_directory/1942.zip");
+        db.set_game_name(&game, "first")?;
+        db.set_game_name(&game, "1942")?;
+        assert_eq!(
+            db.game_names()?,
+            HashMap::from([(game.clone(), "1942".to_string())])
+        );
+        assert!(other.game_names()?.is_empty());
+
+        // Names follow a game that moved
+        let moved = PathBuf::from("test_directory/1942 (Japan).zip");
+        db.relink_game(&game, &moved)?;
+        assert_eq!(
+            db.game_names()?,
+            HashMap::from([(moved.clone(), "1942".to_string())])
+        );
+
+        // Clearing a name removes it
+        db.set_game_name(&moved, "")?;
+        assert!(db.game_names()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_notes() -> Result<()> {
+        let db = Database::in_memory()?;
+        let other = db.with_profile("other");
+
+        let one = PathBuf::from("test_directory/Game One.rom");
+        let two = PathBuf::from("test_directory/Game Two.rom");
+
+        assert_eq!(db.note(&one)?, None);
+        db.set_note(&one, "door code is 4512")?;
+        db.set_note(&two, "first")?;
+        db.set_note(&two, "second\nline")?;
+        assert_eq!(db.note(&one)?.as_deref(), Some("door code is 4512"));
+        assert_eq!(
+            db.notes()?,
+            HashMap::from([
+                (one.clone(), "door code is 4512".to_string()),
+                (two.clone(), "second\nline".to_string()),
+            ])
+        );
+        assert!(other.notes()?.is_empty());
+
+        // Clearing a note removes it
+        db.set_note(&one, "")?;
+        assert_eq!(db.note(&one)?, None);
+        assert_eq!(db.notes()?.len(), 1);
+
+        // Notes follow a game that moved
+        let moved = PathBuf::from("test_directory/Game Two (USA).rom");
+        db.relink_game(&two, &moved)?;
+        assert_eq!(db.note(&two)?, None);
+        assert_eq!(db.note(&moved)?.as_deref(), Some("second\nline"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profiles_do_not_share_recents() -> Result<()> {
+        let db = Database::in_memory()?;
+        let kids = db.with_profile("kids");
+
+        let games = vec![NewGame {
+            name: "Game One".to_string(),
+            path: PathBuf::from("test_directory/Game One.rom"),
+            image: None,
+            core: None,
+        }];
+        let game = &games[0];
+
+        db.update_games(&games)?;
+        kids.update_games(&games)?;
+
+        db.increment_play_count(&game.name, &game.path, None)?;
+        db.add_play_time(&game.path, Duration::seconds(10))?;
+
+        assert_eq!(db.select_last_played(10)?.len(), 1);
+        assert_eq!(db.select_most_played(10)?.len(), 1);
+        assert!(kids.select_last_played(10)?.is_empty());
+        assert!(kids.select_most_played(10)?.is_empty());
+        assert_eq!(db.with_profile("nobody").count_games()?, 0);
+
+        let kids_game = kids.select_game(&game.path.display().to_string())?.unwrap();
+        assert_eq!(kids_game.play_count, 0);
+        assert_eq!(kids_game.play_time, Duration::zero());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_existing_games_belong_to_default_profile() -> Result<()> {
+        let mut conn = Connection::open_in_memory()?;
+        sort_order::create_collations(&conn)?;
+
+        // The schema from before profiles, where games had no profile column
+        Database::migrations().to_version(&mut conn, 5)?;
+        conn.execute(
+            "INSERT INTO games (name, path, image, play_count, play_time, last_played, core) VALUES ('Game', 'Game.rom', NULL, 1, 0, 1, NULL)",
+            [],
+        )?;
+
+        Database::migrations().to_latest(&mut conn)?;
+        let db = Database {
+            conn: Some(Rc::new(conn)),
+            profile: DEFAULT_PROFILE.to_string(),
+        };
+
+        let games = db.select_last_played(10)?;
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].path, Path::new("Game.rom"));
+        assert!(db.with_profile("kids").select_last_played(10)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_extension() -> Result<()> {
+        let db = Database::in_memory()?;
+        let path = Path::new("/Roms/Game.zip");
+
+        assert_eq!(db.archive_extension(path, 100)?, None);
+        db.set_archive_extension(path, 100, Some("gba"))?;
+        assert_eq!(
+            db.archive_extension(path, 100)?,
+            Some(Some("gba".to_string()))
+        );
+        // The archive changed since it was looked into
+        assert_eq!(db.archive_extension(path, 200)?, None);
+
+        db.set_archive_extension(path, 200, None)?;
+        assert_eq!(db.archive_extension(path, 200)?, Some(None));
+        assert_eq!(db.archive_extension(path, 100)?, None);
+
+        db.relink_game(path, Path::new("/Roms/GBA/Game.zip"))?;
+        assert_eq!(db.archive_extension(path, 200)?, None);
+        assert_eq!(
+            db.archive_extension(Path::new("/Roms/GBA/Game.zip"), 200)?,
+            Some(None)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_console() -> Result<()> {
+        let db = Database::in_memory()?;
+        let path = Path::new("/Roms/Game.bin");
+
+        assert_eq!(db.header_console(path, 100)?, None);
+        db.set_header_console(path, 100, Some("PS"))?;
+        assert_eq!(db.header_console(path, 100)?, Some(Some("PS".to_string())));
+        // The file changed since its header was read
+        assert_eq!(db.header_console(path, 200)?, None);
+
+        db.set_header_console(path, 200, None)?;
+        assert_eq!(db.header_console(path, 200)?, Some(None));
+        Ok(())
+    }
+
+    #[test]
+    fn test_directory_count() -> Result<()> {
+        let db = Database::in_memory()?;
+        let path = Path::new("/Roms/GBA");
+
+        assert_eq!(db.directory_count(path, 100)?, None);
+        db.set_directory_count(path, 100, 124)?;
+        assert_eq!(db.directory_count(path, 100)?, Some(124));
+        // Games were added or removed since they were counted
+        assert_eq!(db.directory_count(path, 200)?, None);
+
+        db.set_directory_count(path, 200, 125)?;
+        assert_eq!(db.directory_count(path, 200)?, Some(125));
+        assert_eq!(db.directory_count(path, 100)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_index() -> Result<()> {
+        let db = Database::in_memory()?;
+        let dir = Path::new("/Roms/GBA");
+        let file = |name: &str, is_dir: bool| IndexedFile {
+            path: dir.join(name),
+            name: name.to_string(),
+            directory: dir.to_path_buf(),
+            extension: Path::new(name)
+                .extension()
+                .map(|e| e.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            is_dir,
+            size: 1024,
+            modified: 5,
+        };
+
+        assert_eq!(db.indexed_files(dir, 100)?, None);
+        db.index_directory(dir, 100, &[file("Tetris.gba", false), file("Hacks", true)])?;
+        assert_eq!(
+            db.indexed_files(dir, 100)?,
+            Some(vec![file("Hacks", true), file("Tetris.gba", false)])
+        );
+        // Files were added or removed since it was indexed
+        assert_eq!(db.indexed_files(dir, 200)?, None);
+
+        db.index_directory(dir, 200, &[file("Tetris.gba", false)])?;
+        assert_eq!(
+            db.indexed_files(dir, 200)?,
+            Some(vec![file("Tetris.gba", false)])
+        );
+        db.index_directory(Path::new("/Roms"), 300, &[])?;
+        assert_eq!(
+            db.indexed_directories()?,
+            vec![(PathBuf::from("/Roms"), 300), (dir.to_path_buf(), 200)]
+        );
+
+        db.remove_indexed_directory(dir)?;
+        assert_eq!(db.indexed_files(dir, 200)?, None);
+        assert_eq!(db.indexed_files(Path::new("/Roms"), 300)?, Some(vec![]));
+        db.clear_file_index()?;
+        assert_eq!(db.indexed_directories()?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_requeue_and_cancel_scrape() -> Result<()> {
+        let db = Database::in_memory()?;
+        let (a, b) = (
+            PathBuf::from("/Roms/GB/A.gb"),
+            PathBuf::from("/Roms/GB/B.gb"),
+        );
+        let urls = |name: &str| {
+            vec![
+                format!("http://test/{name}"),
+                format!("http://test/{name} (USA)"),
+            ]
+        };
+
+        db.enqueue_scrape(&a, &urls("A"))?;
+        db.enqueue_scrape(&b, &urls("B"))?;
+        let job = db.next_scrape_job()?.unwrap();
+        assert_eq!(job.url, "http://test/A");
+        assert_eq!(job.fallback_urls, vec!["http://test/A (USA)"]);
+
+        // Failed games are only queued again when asked for
+        db.fail_scrape_job(&a, "not found")?;
+        db.enqueue_scrape(&a, &urls("A"))?;
+        assert_eq!(db.scrape_status(&a)?, Some(ScrapeStatus::Failed));
+        db.requeue_scrape(&a, &urls("A"))?;
+        assert_eq!(db.scrape_status(&a)?, Some(ScrapeStatus::Pending));
+        assert_eq!(db.next_scrape_job()?.unwrap().retries, 0);
+
+        // Only pending games are cancelled
+        db.complete_scrape_job(&b)?;
+        assert_eq!(db.cancel_scrape_jobs(&[a.clone(), b.clone()])?, 1);
+        assert_eq!(db.scrape_status(&a)?, None);
+        assert_eq!(db.scrape_status(&b)?, Some(ScrapeStatus::Completed));
+
+        assert!(db.enqueue_scrape(&a, &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_game_titles() -> Result<()> {
+        let db = Database::in_memory()?;
+        let title = GameTitle {
+            name: "Street Fighter II".to_string(),
+            manufacturer: Some("Capcom".to_string()),
+            year: Some(1991),
+        };
+        db.set_game_titles(&[
+            (PathBuf::from("/Roms/ARCADE/sf2.zip"), Some(title.clone())),
+            (PathBuf::from("/Roms/ARCADE/unknown.zip"), None),
+            (PathBuf::from("/Roms/ARCADE_2/sf2.zip"), Some(title.clone())),
+        ])?;
+
+        let titles = db.game_titles(Path::new("/Roms/ARCADE"))?;
+        assert_eq!(titles.len(), 2);
+        assert_eq!(titles[Path::new("/Roms/ARCADE/sf2.zip")], Some(title));
+        assert_eq!(titles[Path::new("/Roms/ARCADE/unknown.zip")], None);
+
+        db.clear_game_titles()?;
+        assert!(db.game_titles(Path::new("/Roms/ARCADE"))?.is_empty());
+
+        Ok(())
+    }
+}
+
+fn map_scrape_job(row: &Row<'_>) -> rusqlite::Result<ScrapeJob> {
+    Ok(ScrapeJob {
+        path: PathBuf::from(row.get::<_, String>(0)?),
+        url: row.get(1)?,
+        retries: row.get(2)?,
+        next_attempt: row.get(3)?,
+        error: row.get(4)?,
+        fallback_urls: row
+            .get::<_, String>(5)?
+            .lines()
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+fn map_snapshot(row: &Row<'_>) -> rusqlite::Result<GameSnapshot> {
+    Ok(GameSnapshot {
+        name: row.get(0)?,
+        path: PathBuf::from(row.get::<_, String>(1)?),
+        image: row.get::<_, Option<String>>(2)?.map(PathBuf::from),
+        play_count: row.get(3)?,
+        play_time: row.get(4)?,
+        last_played: row.get(5)?,
+        core: row.get(6)?,
+        unclean_exits: row.get(7)?,
+        file_size: row.get(8)?,
+        crc: row.get(9)?,
+        pinned: row.get(10)?,
+        first_played: row.get(11)?,
+        related: None,
+    })
+}
+
+/// Puts back what was recorded about the game at `path` outside of the games table. A favorite
+/// keeps its place unless another took it, and collections deleted since are skipped.
+fn restore_related(
+    conn: &Connection,
+    profile: &str,
+    path: &Path,
+    related: &RelatedRows,
+) -> Result<()> {
+    let path = path.display().to_string();
+    if let Some(id) = related.favorite {
+        conn.execute(
+            "INSERT OR IGNORE INTO favorites (id, profile, path) VALUES ((SELECT CASE WHEN EXISTS (SELECT 1 FROM favorites WHERE id = ?1) THEN NULL ELSE ?1 END), ?2, ?3)",
+            params![id, profile, path],
+        )?;
+    }
+    if let Some(note) = &related.note {
+        conn.execute(
+            "INSERT OR REPLACE INTO game_notes (profile, path, note) VALUES (?, ?, ?)",
+            params![profile, path, note],
+        )?;
+    }
+    if let Some(name) = &related.name {
+        conn.execute(
+            "INSERT OR REPLACE INTO game_names (profile, path, name) VALUES (?, ?, ?)",
+            params![profile, path, name],
+        )?;
+    }
+    if let Some(core) = &related.core {
+        conn.execute(
+            "INSERT OR REPLACE INTO game_settings (profile, path, core) VALUES (?, ?, ?)",
+            params![profile, path, core],
+        )?;
+    }
+    if related.hidden || related.completed {
+        conn.execute(
+            "INSERT OR REPLACE INTO game_flags (profile, path, hidden, completed) VALUES (?, ?, ?, ?)",
+            params![profile, path, related.hidden, related.completed],
+        )?;
+    }
+    for (collection, position) in &related.collections {
+        conn.execute(
+            "INSERT OR IGNORE INTO collection_games (collection, position, path) SELECT id, ?, ? FROM collections WHERE id = ? AND profile = ?",
+            params![position, path, collection, profile],
+        )?;
+    }
+    for (ended, play_time) in &related.play_sessions {
+        conn.execute(
+            "INSERT INTO play_sessions (profile, path, ended, play_time) VALUES (?, ?, ?, ?)",
+            params![profile, path, ended, play_time],
+        )?;
+    }
+    Ok(())
+}
+
+fn map_undo_entry(row: &Row<'_>) -> Result<UndoEntry> {
+    Ok(UndoEntry {
+        id: row.get(0)?,
+        kind: row.get::<_, String>(1)?.parse()?,
+        created: row.get(2)?,
+        files: serde_json::from_str(&row.get::<_, String>(3)?)?,
+        games: serde_json::from_str(&row.get::<_, String>(4)?)?,
+    })
+}
+
+/// Escapes the wildcards of a `LIKE` pattern, for use with `ESCAPE '\\'`.
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
 
 fn map_game(row: &Row<'_>) -> rusqlite::Result<Game> {
@@ -612,5 +3143,6 @@ fn map_game(row: &Row<'_>) -> rusqlite::Result<Game> {
         play_time: Duration::seconds(row.get(4)?),
         last_played: row.get(5)?,
         core: row.get(6)?,
+        id: row.get(7)?,
     })
 }
@@ -0,0 +1,806 @@
+//! Self-test that collects everything needed to triage a problem report into a single bundle.
+//!
+//! Each check is a small function that returns its findings as JSON. [`SelfTest`] runs them one
+//! after another within a time budget, and writes a zip to the root of the SD card with:
+//!
+//! - `diagnostics.json`: the device, and the outcome of every check, e.g.
+//!   `{"name":"battery","status":"pass","elapsed_ms":3,"details":{"percentage":80,"charging":false}}`.
+//!   A check that fails has `{"error": "..."}` as its details. Checks that didn't run because the
+//!   self-test was cancelled or ran out of time are `skipped`.
+//! - `state/`: the persisted state files, with passwords and other secrets redacted.
+//! - `logs/`: the output of the main process, redacted likewise.
+//!
+//! Checks that need the display or battery run on the caller's thread, as those aren't shared
+//! between threads. The rest run in the background with [`SelfTest::spawn`], and can be cancelled.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use serde_json::{json, Value};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::battery::Battery;
+use crate::constants::{
+    ALLIUM_BASE_DIR, ALLIUM_DATABASE, ALLIUM_DIAGNOSTICS, ALLIUM_MAIN_STDERR, ALLIUM_SD_ROOT,
+    ALLIUM_VERSION, ALLIUM_WIFI_SETTINGS,
+};
+use crate::display::{assets, Display};
+use crate::platform::{DefaultPlatform, Platform};
+use crate::view::IMAGE_CACHE;
+use crate::wifi::WiFiSettings;
+
+/// How long a whole self-test may take. Checks that haven't started by then are skipped.
+pub const TIME_LIMIT: Duration = Duration::from_secs(30);
+
+/// Version of the `diagnostics.json` format.
+pub const DIAGNOSTICS_VERSION: u32 = 1;
+
+/// Replaces secrets in the bundle.
+pub const REDACTED: &str = "<redacted>";
+
+/// Keys whose values are secrets, matched case-insensitively anywhere in the key.
+const SENSITIVE_KEYS: &[&str] = &["password", "passwd", "passphrase", "psk", "secret", "token"];
+
+/// Number of frames flushed to time the display.
+const DISPLAY_FLUSHES: u32 = 10;
+
+/// Size of the temporary file that storage speed is measured with.
+const STORAGE_TEST_SIZE: usize = 4 * 1024 * 1024;
+const STORAGE_TEST_CHUNK: usize = 64 * 1024;
+
+/// State files larger than this aren't included in the bundle.
+const MAX_STATE_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Only the end of logs is included in the bundle.
+const MAX_LOG_SIZE: u64 = 256 * 1024;
+
+/// Problems reported by the database integrity check, at most.
+const MAX_INTEGRITY_ERRORS: u32 = 10;
+
+/// How long the self-test may still take, and whether it was cancelled. Clones share
+/// cancellation, so a clone can cancel a self-test running on another thread.
+#[derive(Debug, Clone)]
+pub struct Budget {
+    cancelled: Arc<AtomicBool>,
+    deadline: Instant,
+}
+
+impl Budget {
+    pub fn new(limit: Duration) -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: Instant::now() + limit,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Whether the self-test should stop, because it was cancelled or is out of time.
+    pub fn is_exhausted(&self) -> bool {
+        self.is_cancelled() || Instant::now() >= self.deadline
+    }
+
+    /// Fails if the self-test should stop. Long checks call this between steps.
+    fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            bail!("cancelled");
+        }
+        if Instant::now() >= self.deadline {
+            bail!("out of time");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Fail,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: Status,
+    pub elapsed_ms: u64,
+    pub details: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    version: u32,
+    allium_version: &'a str,
+    model: String,
+    firmware: String,
+    created: DateTime<Utc>,
+    checks: &'a [CheckResult],
+}
+
+#[derive(Debug)]
+pub struct SelfTest {
+    budget: Budget,
+    results: Vec<CheckResult>,
+}
+
+impl SelfTest {
+    pub fn new(budget: Budget) -> Self {
+        Self {
+            budget,
+            results: Vec::new(),
+        }
+    }
+
+    pub fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    pub fn results(&self) -> &[CheckResult] {
+        &self.results
+    }
+
+    /// Runs `check` and records its outcome, or records it as skipped if the self-test should stop.
+    pub fn run(&mut self, name: &'static str, check: impl FnOnce(&Budget) -> Result<Value>) {
+        let (status, elapsed, details) = if self.budget.is_exhausted() {
+            (Status::Skipped, Duration::ZERO, Value::Null)
+        } else {
+            let start = Instant::now();
+            let result = check(&self.budget);
+            let elapsed = start.elapsed();
+            match result {
+                Ok(details) => (Status::Pass, elapsed, details),
+                // A check that was stopped part way says nothing about the device
+                Err(e) if self.budget.is_exhausted() => {
+                    (Status::Skipped, elapsed, json!({ "error": e.to_string() }))
+                }
+                Err(e) => (
+                    Status::Fail,
+                    elapsed,
+                    json!({ "error": format!("{:#}", e) }),
+                ),
+            }
+        };
+        info!("diagnostics: {} {:?} in {:?}", name, status, elapsed);
+        self.results.push(CheckResult {
+            name,
+            status,
+            elapsed_ms: elapsed.as_millis() as u64,
+            details,
+        });
+    }
+
+    /// Runs the checks that need the display and battery. These must be run on the thread that
+    /// owns them.
+    pub fn run_device_checks(&mut self, display: &mut impl Display, battery: &mut dyn Battery) {
+        self.run("display", |budget| check_display(display, budget));
+        self.run("battery", |_| check_battery(battery));
+    }
+
+    /// Runs the remaining checks.
+    pub fn run_system_checks(&mut self) {
+        self.run("input", |_| {
+            check_input_devices(Path::new("/dev/input"), Path::new("/sys/class/input"))
+        });
+        self.run("storage", |budget| check_storage(&ALLIUM_SD_ROOT, budget));
+        self.run("database", |budget| {
+            check_database(&ALLIUM_DATABASE, budget)
+        });
+        self.run("wifi", |_| {
+            check_wifi(DefaultPlatform::has_wifi(), Path::new("/sys/class/net"))
+        });
+        self.run("image-cache", |_| Ok(check_image_cache()));
+    }
+
+    /// Runs the remaining checks and writes the bundle on a background thread.
+    pub fn spawn(mut self) -> RunningSelfTest {
+        let budget = self.budget.clone();
+        let thread = thread::spawn(move || {
+            self.run_system_checks();
+            self.write_bundle()
+        });
+        RunningSelfTest { budget, thread }
+    }
+
+    /// Writes the bundle to the root of the SD card, returning its path. Fails without leaving a
+    /// bundle behind if the self-test was cancelled.
+    pub fn write_bundle(&self) -> Result<PathBuf> {
+        let tmp = ALLIUM_DIAGNOSTICS.with_extension("tmp");
+        let result = File::create(&tmp).map_err(Into::into).and_then(|file| {
+            self.write_bundle_to(
+                file,
+                &ALLIUM_BASE_DIR.join("state"),
+                &[ALLIUM_MAIN_STDERR.as_path()],
+                &Redactor::from_settings(),
+            )
+        });
+        if let Err(e) = result {
+            if let Err(e) = fs::remove_file(&tmp) {
+                warn!("failed to remove partial bundle: {}", e);
+            }
+            return Err(e);
+        }
+        fs::rename(&tmp, ALLIUM_DIAGNOSTICS.as_path())?;
+        info!("wrote diagnostics to {}", ALLIUM_DIAGNOSTICS.display());
+        Ok(ALLIUM_DIAGNOSTICS.clone())
+    }
+
+    fn write_bundle_to<W: Write + Seek>(
+        &self,
+        writer: W,
+        state_dir: &Path,
+        logs: &[&Path],
+        redactor: &Redactor,
+    ) -> Result<()> {
+        if self.budget.is_cancelled() {
+            bail!("cancelled");
+        }
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        let mut zip = ZipWriter::new(writer);
+
+        let report = Report {
+            version: DIAGNOSTICS_VERSION,
+            allium_version: ALLIUM_VERSION,
+            model: DefaultPlatform::device_model(),
+            firmware: DefaultPlatform::firmware(),
+            created: Utc::now(),
+            checks: &self.results,
+        };
+        zip.start_file("diagnostics.json", options)?;
+        serde_json::to_writer_pretty(&mut zip, &report)?;
+
+        let mut state_files = match fs::read_dir(state_dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file())
+                .collect(),
+            Err(e) => {
+                warn!("failed to read {}: {}", state_dir.display(), e);
+                Vec::new()
+            }
+        };
+        state_files.sort();
+        for path in state_files {
+            if self.budget.is_cancelled() {
+                bail!("cancelled");
+            }
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if fs::metadata(&path)?.len() > MAX_STATE_FILE_SIZE {
+                warn!("not including {} in diagnostics, too large", path.display());
+                continue;
+            }
+            // Binary files such as the database are checked, not included
+            let Ok(text) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let text = match serde_json::from_str::<Value>(&text) {
+                Ok(mut value) => {
+                    redactor.redact_json(&mut value);
+                    serde_json::to_string_pretty(&value)?
+                }
+                Err(_) => redactor.redact_text(&text),
+            };
+            zip.start_file(format!("state/{}", name), options)?;
+            zip.write_all(text.as_bytes())?;
+        }
+
+        for path in logs {
+            if self.budget.is_cancelled() {
+                bail!("cancelled");
+            }
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            match read_tail(path, MAX_LOG_SIZE) {
+                Ok(log) => {
+                    zip.start_file(format!("logs/{}", name), options)?;
+                    zip.write_all(
+                        redactor
+                            .redact_text(&String::from_utf8_lossy(&log))
+                            .as_bytes(),
+                    )?;
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => warn!("failed to read {}: {}", path.display(), e),
+            }
+        }
+
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+/// A self-test running in the background.
+#[derive(Debug)]
+pub struct RunningSelfTest {
+    budget: Budget,
+    thread: JoinHandle<Result<PathBuf>>,
+}
+
+impl RunningSelfTest {
+    /// Stops the self-test as soon as possible. No bundle is written.
+    pub fn cancel(&self) {
+        self.budget.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.budget.is_cancelled()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the self-test to finish, returning the path of the bundle.
+    pub fn join(self) -> Result<PathBuf> {
+        self.thread
+            .join()
+            .map_err(|_| anyhow!("diagnostics thread panicked"))?
+    }
+}
+
+/// Times how long it takes to flush a frame to the screen.
+pub fn check_display(display: &mut impl Display, budget: &Budget) -> Result<Value> {
+    let mut total = Duration::ZERO;
+    let mut max = Duration::ZERO;
+    for _ in 0..DISPLAY_FLUSHES {
+        budget.check()?;
+        let start = Instant::now();
+        display.flush()?;
+        let elapsed = start.elapsed();
+        total += elapsed;
+        max = max.max(elapsed);
+    }
+    let size = display.size();
+    Ok(json!({
+        "width": size.width,
+        "height": size.height,
+        "flushes": DISPLAY_FLUSHES,
+        "average_ms": total.as_secs_f64() * 1000.0 / f64::from(DISPLAY_FLUSHES),
+        "max_ms": max.as_secs_f64() * 1000.0,
+    }))
+}
+
+pub fn check_battery(battery: &mut dyn Battery) -> Result<Value> {
+    battery.update()?;
+    let percentage = battery.percentage();
+    if !(0..=100).contains(&percentage) {
+        bail!("implausible battery level: {}%", percentage);
+    }
+    Ok(json!({
+        "percentage": percentage,
+        "charging": battery.charging(),
+    }))
+}
+
+/// Lists the input devices in `dev`, with their names from `sys` where known.
+pub fn check_input_devices(dev: &Path, sys: &Path) -> Result<Value> {
+    let mut devices: Vec<_> = fs::read_dir(dev)
+        .with_context(|| dev.display().to_string())?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with("event"))
+        .collect();
+    if devices.is_empty() {
+        bail!("no input devices in {}", dev.display());
+    }
+    devices.sort();
+
+    let devices: Vec<_> = devices
+        .into_iter()
+        .map(|device| {
+            let name = fs::read_to_string(sys.join(&device).join("device/name"))
+                .ok()
+                .map(|name| name.trim().to_string());
+            json!({ "device": device, "name": name })
+        })
+        .collect();
+    Ok(json!({ "devices": devices }))
+}
+
+/// Measures write and read speed in `dir` with a temporary file, which is removed afterwards.
+pub fn check_storage(dir: &Path, budget: &Budget) -> Result<Value> {
+    let path = dir.join(".allium-diagnostics.tmp");
+    let result = measure_storage(&path, budget);
+    if let Err(e) = fs::remove_file(&path) {
+        if e.kind() != io::ErrorKind::NotFound {
+            warn!("failed to remove {}: {}", path.display(), e);
+        }
+    }
+    result
+}
+
+fn measure_storage(path: &Path, budget: &Budget) -> Result<Value> {
+    let chunk: Vec<u8> = (0..STORAGE_TEST_CHUNK).map(|i| i as u8).collect();
+    let chunks = STORAGE_TEST_SIZE / STORAGE_TEST_CHUNK;
+
+    let start = Instant::now();
+    let mut file = File::create(path)?;
+    for _ in 0..chunks {
+        budget.check()?;
+        file.write_all(&chunk)?;
+    }
+    file.sync_all()?;
+    let write = start.elapsed();
+    drop(file);
+
+    let start = Instant::now();
+    let mut file = File::open(path)?;
+    let mut buf = vec![0; STORAGE_TEST_CHUNK];
+    for _ in 0..chunks {
+        budget.check()?;
+        file.read_exact(&mut buf)?;
+        if buf != chunk {
+            bail!("data read back differs from what was written");
+        }
+    }
+    let read = start.elapsed();
+
+    let speed = |elapsed: Duration| {
+        STORAGE_TEST_SIZE as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64().max(f64::EPSILON)
+    };
+    Ok(json!({
+        "bytes": STORAGE_TEST_SIZE,
+        "write_mib_s": speed(write),
+        "read_mib_s": speed(read),
+    }))
+}
+
+/// Runs SQLite's integrity check on the database at `path` without modifying it.
+pub fn check_database(path: &Path, budget: &Budget) -> Result<Value> {
+    let size = fs::metadata(path)
+        .with_context(|| path.display().to_string())?
+        .len();
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+
+    // Interrupts the check once the self-test should stop
+    let interrupt = conn.get_interrupt_handle();
+    let done = AtomicBool::new(false);
+    let problems = thread::scope(|scope| {
+        scope.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                if budget.is_exhausted() {
+                    interrupt.interrupt();
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+        let problems = conn
+            .prepare(&format!("PRAGMA integrity_check({})", MAX_INTEGRITY_ERRORS))
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            });
+        done.store(true, Ordering::Relaxed);
+        problems
+    })?;
+
+    if problems != ["ok"] {
+        bail!("database is corrupt: {}", problems.join("; "));
+    }
+    Ok(json!({ "bytes": size }))
+}
+
+/// Checks that the wireless interface is present on devices that have WiFi.
+pub fn check_wifi(supported: bool, sys: &Path) -> Result<Value> {
+    if !supported {
+        return Ok(json!({ "supported": false }));
+    }
+    let interface = sys.join("wlan0").exists();
+    // The interface only exists while WiFi is turned on
+    let enabled = wifi_settings().is_some_and(|settings| settings.wifi);
+    if enabled && !interface {
+        bail!("WiFi is turned on, but there is no wireless interface");
+    }
+    Ok(json!({
+        "supported": true,
+        "enabled": enabled,
+        "interface": interface,
+    }))
+}
+
+/// Reports the memory used by decoded images in this process, by kind, and by the theme's fonts
+/// and icons.
+pub fn check_image_cache() -> Value {
+    let cache = IMAGE_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    let kinds: serde_json::Map<_, _> = cache
+        .usages()
+        .map(|(kind, usage)| (format!("{:?}", kind).to_lowercase(), json!(usage)))
+        .collect();
+    json!({
+        "budget": cache.budget(),
+        "used": cache.used(),
+        "kinds": kinds,
+        "theme": assets::usage(),
+    })
+}
+
+/// Reads the WiFi settings file directly, as loading the settings removes the file if it's invalid.
+fn wifi_settings() -> Option<WiFiSettings> {
+    let json = fs::read_to_string(ALLIUM_WIFI_SETTINGS.as_path()).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Reads at most the last `max` bytes of the file at `path`.
+fn read_tail(path: &Path, max: u64) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max)))?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Removes passwords and other secrets from what goes into the bundle.
+#[derive(Debug, Default)]
+pub struct Redactor {
+    /// Secrets that are removed wherever they appear, such as in logs.
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(secrets: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            secrets: secrets
+                .into_iter()
+                .filter(|secret| !secret.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Redacts the secrets in the settings, wherever else they appear.
+    pub fn from_settings() -> Self {
+        Self::new(wifi_settings().map(|wifi| wifi.password))
+    }
+
+    /// Replaces the values of sensitive keys, and known secrets in strings.
+    pub fn redact_json(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if is_sensitive_key(key) && !value.is_null() {
+                        *value = Value::String(REDACTED.to_string());
+                    } else {
+                        self.redact_json(value);
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.redact_json(value);
+                }
+            }
+            Value::String(s) => {
+                if self
+                    .secrets
+                    .iter()
+                    .any(|secret| s.contains(secret.as_str()))
+                {
+                    *s = self.redact_secrets(s);
+                }
+            }
+            Value::Null | Value::Bool(_) | Value::Number(_) => {}
+        }
+    }
+
+    /// Replaces the value of lines such as `psk="hunter2"` or `password: hunter2`, and known
+    /// secrets anywhere.
+    pub fn redact_text(&self, text: &str) -> String {
+        let text = text
+            .split_inclusive('\n')
+            .map(|line| {
+                let lower = line.to_ascii_lowercase();
+                let separator = SENSITIVE_KEYS
+                    .iter()
+                    .filter_map(|key| lower.find(key).map(|i| i + key.len()))
+                    .min()
+                    .and_then(|start| lower[start..].find(['=', ':']).map(|i| start + i + 1));
+                match separator {
+                    Some(i) => {
+                        let newline = if line.ends_with('\n') { "\n" } else { "" };
+                        format!("{}{}{}", &line[..i], REDACTED, newline)
+                    }
+                    None => line.to_string(),
+                }
+            })
+            .collect::<String>();
+        self.redact_secrets(&text)
+    }
+
+    fn redact_secrets(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), REDACTED)
+        })
+    }
+}
+
+fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SENSITIVE_KEYS
+        .iter()
+        .any(|sensitive| key.contains(sensitive))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use zip::ZipArchive;
+
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    #[test]
+    fn test_redact_json() {
+        let redactor = Redactor::new(["hunter2".to_string()]);
+        let mut value = json!({
+            "wifi": true,
+            "ssid": "home",
+            "password": "hunter2",
+            "nested": [{ "API_Token": "abc", "key": "sync-failed" }],
+            "unset_secret": null,
+            "text": "connecting with hunter2",
+        });
+        redactor.redact_json(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "wifi": true,
+                "ssid": "home",
+                "password": REDACTED,
+                "nested": [{ "API_Token": REDACTED, "key": "sync-failed" }],
+                "unset_secret": null,
+                "text": format!("connecting with {}", REDACTED),
+            })
+        );
+    }
+
+    #[test]
+    fn test_redact_text() {
+        let redactor = Redactor::new(["hunter2".to_string(), String::new()]);
+        let text = "network={\n\tssid=\"home\"\n\tpsk=\"s3cret\"\n}\nPassword: s3cret\nudhcpc: lease of 10.0.0.2\njoined with hunter2\n";
+        assert_eq!(
+            redactor.redact_text(text),
+            format!(
+                "network={{\n\tssid=\"home\"\n\tpsk={0}\n}}\nPassword:{0}\nudhcpc: lease of 10.0.0.2\njoined with {0}\n",
+                REDACTED
+            )
+        );
+    }
+
+    #[test]
+    fn test_bundle_has_no_secrets() -> Result<()> {
+        let dir = temp_dir("diagnostics-bundle");
+        let state_dir = dir.join("state");
+        fs::create_dir_all(&state_dir)?;
+        fs::write(
+            state_dir.join("wifi.json"),
+            r#"{"wifi":true,"ssid":"home","password":"hunter2","ntp":true,"telnet":false,"ftp":false}"#,
+        )?;
+        fs::write(state_dir.join("wpa_supplicant.conf"), "psk=\"hunter2\"\n")?;
+        fs::write(state_dir.join("allium.db"), [0xff, 0xfe, 0x00])?;
+        let log = dir.join("main.log");
+        fs::write(&log, "starting\nwpa_cli set_network 0 psk hunter2\n")?;
+
+        let mut self_test = SelfTest::new(Budget::new(TIME_LIMIT));
+        self_test.run("wifi", |_| Ok(json!({ "supported": true })));
+        let mut bundle = Cursor::new(Vec::new());
+        self_test.write_bundle_to(
+            &mut bundle,
+            &state_dir,
+            &[&log, &dir.join("missing.log")],
+            &Redactor::new(["hunter2".to_string()]),
+        )?;
+
+        let mut archive = ZipArchive::new(bundle)?;
+        let mut names: Vec<_> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "diagnostics.json",
+                "logs/main.log",
+                "state/wifi.json",
+                "state/wpa_supplicant.conf"
+            ]
+        );
+        for name in names {
+            let mut text = String::new();
+            archive.by_name(&name)?.read_to_string(&mut text)?;
+            assert!(!text.contains("hunter2"), "{} has a secret: {}", name, text);
+        }
+
+        let mut report = String::new();
+        archive
+            .by_name("diagnostics.json")?
+            .read_to_string(&mut report)?;
+        let report: Value = serde_json::from_str(&report)?;
+        assert_eq!(report["version"], DIAGNOSTICS_VERSION);
+        assert_eq!(report["checks"][0]["name"], "wifi");
+        assert_eq!(report["checks"][0]["status"], "pass");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancelled() -> Result<()> {
+        let mut self_test = SelfTest::new(Budget::new(TIME_LIMIT));
+        self_test.run("fails", |_| bail!("broken"));
+        self_test.budget().cancel();
+        self_test.run("later", |_| Ok(Value::Null));
+        let statuses: Vec<_> = self_test.results().iter().map(|r| r.status).collect();
+        assert_eq!(statuses, [Status::Fail, Status::Skipped]);
+        assert_eq!(self_test.results()[0].details["error"], "broken");
+
+        // A cancelled self-test doesn't write a bundle
+        let dir = temp_dir("diagnostics-cancelled");
+        let result =
+            self_test.write_bundle_to(Cursor::new(Vec::new()), &dir, &[], &Redactor::default());
+        assert!(result.is_err());
+
+        // Long checks stop part way, and clean up after themselves
+        assert!(check_storage(&dir, self_test.budget()).is_err());
+        assert_eq!(fs::read_dir(&dir)?.count(), 0);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_out_of_time() {
+        let mut self_test = SelfTest::new(Budget::new(Duration::ZERO));
+        self_test.run("never", |_| Ok(Value::Null));
+        assert_eq!(self_test.results()[0].status, Status::Skipped);
+    }
+
+    #[test]
+    fn test_storage() -> Result<()> {
+        let dir = temp_dir("diagnostics-storage");
+        let details = check_storage(&dir, &Budget::new(TIME_LIMIT))?;
+        assert_eq!(details["bytes"], STORAGE_TEST_SIZE);
+        assert!(details["write_mib_s"].as_f64().unwrap() > 0.0);
+        assert_eq!(fs::read_dir(&dir)?.count(), 0);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_database() -> Result<()> {
+        let dir = temp_dir("diagnostics-database");
+        let path = dir.join("test.db");
+        Connection::open(&path)?.execute_batch("CREATE TABLE t (x); INSERT INTO t VALUES (1);")?;
+        assert!(check_database(&path, &Budget::new(TIME_LIMIT)).is_ok());
+        assert!(check_database(&dir.join("missing.db"), &Budget::new(TIME_LIMIT)).is_err());
+
+        fs::write(
+            &path,
+            b"not a database, but long enough to look like one at a glance",
+        )?;
+        assert!(check_database(&path, &Budget::new(TIME_LIMIT)).is_err());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
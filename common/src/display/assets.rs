@@ -0,0 +1,134 @@
+//! Fonts and icon atlases loaded by themes.
+//!
+//! Every style handle that names the same file shares one copy of it. The cache only holds weak
+//! references, so an asset is freed as soon as the last handle using it is dropped, such as when
+//! the theme is changed, and loaded again if a later theme uses it.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use rusttype::Font;
+
+use crate::display::atlas::IconAtlas;
+use crate::display::cache::CacheUsage;
+
+lazy_static! {
+    static ref FONTS: Mutex<AssetCache<Font<'static>>> = Mutex::new(AssetCache::default());
+    static ref ATLASES: Mutex<AssetCache<IconAtlas>> = Mutex::new(AssetCache::default());
+}
+
+#[derive(Debug)]
+struct Loaded<T> {
+    asset: Weak<T>,
+    bytes: usize,
+}
+
+#[derive(Debug)]
+struct AssetCache<T> {
+    entries: HashMap<PathBuf, Loaded<T>>,
+}
+
+impl<T> Default for AssetCache<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<T> AssetCache<T> {
+    /// Returns the asset at `path` if it's still in use, or loads it, along with its size in
+    /// bytes.
+    fn get_or_load(
+        &mut self,
+        path: &Path,
+        load: impl FnOnce() -> Result<(T, usize)>,
+    ) -> Result<Arc<T>> {
+        if let Some(asset) = self.entries.get(path).and_then(|e| e.asset.upgrade()) {
+            return Ok(asset);
+        }
+        let (asset, bytes) = load()?;
+        let asset = Arc::new(asset);
+        self.entries.insert(
+            path.to_path_buf(),
+            Loaded {
+                asset: Arc::downgrade(&asset),
+                bytes,
+            },
+        );
+        Ok(asset)
+    }
+
+    /// Forgets assets that have been freed.
+    fn prune(&mut self) {
+        self.entries.retain(|_, e| e.asset.strong_count() > 0);
+    }
+
+    fn usage(&mut self) -> CacheUsage {
+        self.prune();
+        CacheUsage {
+            entries: self.entries.len(),
+            bytes: self.entries.values().map(|e| e.bytes).sum(),
+        }
+    }
+
+    fn is_loaded(&mut self, path: &Path) -> bool {
+        self.prune();
+        self.entries.contains_key(path)
+    }
+}
+
+/// Loads the font at `path`, or shares it if it's already loaded.
+pub fn load_font(path: &Path) -> Result<Arc<Font<'static>>> {
+    FONTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_load(path, || {
+            let bytes = fs::read(path)?;
+            let len = bytes.len();
+            let font = Font::try_from_vec(bytes)
+                .ok_or_else(|| anyhow!("not a font: {}", path.display()))?;
+            Ok((font, len))
+        })
+}
+
+/// Loads the icon atlas at `path`, or shares it if it's already loaded.
+pub fn load_atlas(path: &Path) -> Result<Arc<IconAtlas>> {
+    ATLASES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_load(path, || {
+            let atlas = IconAtlas::load(path)?;
+            let bytes = atlas.bytes();
+            Ok((atlas, bytes))
+        })
+}
+
+/// Memory used by the fonts and icon atlases that are still in use.
+pub fn usage() -> CacheUsage {
+    let fonts = FONTS.lock().unwrap_or_else(PoisonError::into_inner).usage();
+    let atlases = ATLASES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .usage();
+    CacheUsage {
+        entries: fonts.entries + atlases.entries,
+        bytes: fonts.bytes + atlases.bytes,
+    }
+}
+
+/// Whether the font or icon atlas at `path` is still in use.
+pub fn is_loaded(path: &Path) -> bool {
+    FONTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .is_loaded(path)
+        || ATLASES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_loaded(path)
+}
@@ -0,0 +1,202 @@
+//! Sprite sheets that themes can ship to replace the built-in button icons.
+//!
+//! An atlas is a TOML file next to a PNG sprite sheet:
+//!
+//! ```toml
+//! image = "buttons.png"
+//!
+//! [sprites]
+//! A = { x = 0, y = 0, w = 36, h = 36 }
+//! Menu = { x = 36, y = 0, w = 72, h = 36 }
+//! ```
+//!
+//! The image path is relative to the TOML file. Keys without a sprite use the built-in icons.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use embedded_graphics::prelude::*;
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::display::color::Color;
+use crate::geom::Rect;
+use crate::platform::Key;
+
+#[derive(Debug, Deserialize)]
+struct IconAtlasConfig {
+    image: PathBuf,
+    #[serde(default)]
+    sprites: HashMap<Key, Rect>,
+}
+
+/// Decoded sprite sheet and the location of each key's sprite in it.
+pub struct IconAtlas {
+    image: RgbaImage,
+    sprites: HashMap<Key, Rect>,
+}
+
+impl IconAtlas {
+    /// Loads an atlas from its TOML description, decoding the sprite sheet once.
+    pub fn load(path: &Path) -> Result<Self> {
+        let config = fs::read_to_string(path).with_context(|| format!("{}", path.display()))?;
+        let config: IconAtlasConfig =
+            toml::from_str(&config).with_context(|| format!("{}", path.display()))?;
+
+        let image_path = path
+            .parent()
+            .map_or_else(|| config.image.clone(), |dir| dir.join(&config.image));
+        let image = ::image::open(&image_path)
+            .with_context(|| format!("{}", image_path.display()))?
+            .to_rgba8();
+
+        Self::new(image, config.sprites)
+    }
+
+    /// Creates an atlas from an already decoded sprite sheet. Fails if a sprite lies outside of
+    /// the image.
+    pub fn new(image: RgbaImage, sprites: HashMap<Key, Rect>) -> Result<Self> {
+        for (key, rect) in &sprites {
+            if rect.x < 0
+                || rect.y < 0
+                || rect.x as u32 + rect.w > image.width()
+                || rect.y as u32 + rect.h > image.height()
+            {
+                bail!("sprite for {:?} is outside of the atlas: {:?}", key, rect);
+            }
+        }
+        Ok(Self { image, sprites })
+    }
+
+    /// Memory taken by the decoded sprite sheet.
+    pub fn bytes(&self) -> usize {
+        self.image.as_raw().len()
+    }
+
+    /// Size of the sprite for `key`, if the atlas has one.
+    pub fn sprite_size(&self, key: Key) -> Option<Size> {
+        self.sprites.get(&key).map(|rect| Size::new(rect.w, rect.h))
+    }
+
+    /// Draws the sprite for `key` with its top left corner at `point`, blending translucent
+    /// pixels with `background`. Returns false without drawing if the atlas has no such sprite.
+    pub fn draw<D>(
+        &self,
+        display: &mut D,
+        key: Key,
+        point: Point,
+        background: Color,
+    ) -> Result<bool>
+    where
+        D: DrawTarget<Color = Color, Error = anyhow::Error>,
+    {
+        let Some(rect) = self.sprites.get(&key) else {
+            return Ok(false);
+        };
+
+        let pixels = (0..rect.h).flat_map(|y| {
+            (0..rect.w).filter_map(move |x| {
+                let [r, g, b, a] = self.image.get_pixel(rect.x as u32 + x, rect.y as u32 + y).0;
+                if a == 0 {
+                    return None;
+                }
+                Some(Pixel(
+                    Point::new(point.x + x as i32, point.y + y as i32),
+                    Color::rgba(r, g, b, a).over(background),
+                ))
+            })
+        });
+        display.draw_iter(pixels)?;
+
+        Ok(true)
+    }
+}
+
+impl fmt::Debug for IconAtlas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IconAtlas")
+            .field("size", &self.image.dimensions())
+            .field("sprites", &self.sprites)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+    use crate::display::golden::{assert_golden, Framebuffer};
+    use crate::test_utils::temp_dir;
+
+    /// 4x2 sheet with an opaque red sprite for A and a half transparent white sprite for B.
+    fn atlas() -> IconAtlas {
+        let mut image = RgbaImage::new(4, 2);
+        for y in 0..2 {
+            image.put_pixel(0, y, Rgba([255, 0, 0, 255]));
+            image.put_pixel(1, y, Rgba([255, 0, 0, 255]));
+            image.put_pixel(2, y, Rgba([255, 255, 255, 128]));
+        }
+        IconAtlas::new(
+            image,
+            [
+                (Key::A, Rect::new(0, 0, 2, 2)),
+                (Key::B, Rect::new(2, 0, 2, 2)),
+            ]
+            .into_iter()
+            .collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_draw_sprites() {
+        let atlas = atlas();
+        let background = Color::new(0, 0, 0);
+        let mut display = Framebuffer::new(6, 3, background);
+
+        assert!(atlas
+            .draw(&mut display, Key::A, Point::new(0, 0), background)
+            .unwrap());
+        assert!(atlas
+            .draw(&mut display, Key::B, Point::new(3, 1), background)
+            .unwrap());
+        assert!(!atlas
+            .draw(&mut display, Key::X, Point::new(0, 0), background)
+            .unwrap());
+
+        assert_golden("atlas_sprites", &display);
+    }
+
+    #[test]
+    fn test_sprite_size() {
+        let atlas = atlas();
+        assert_eq!(atlas.sprite_size(Key::A), Some(Size::new(2, 2)));
+        assert_eq!(atlas.sprite_size(Key::Menu), None);
+    }
+
+    #[test]
+    fn test_sprite_outside_of_atlas() {
+        let sprites = [(Key::A, Rect::new(3, 0, 2, 2))].into_iter().collect();
+        assert!(IconAtlas::new(RgbaImage::new(4, 2), sprites).is_err());
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = temp_dir("atlas");
+        RgbaImage::new(4, 2).save(dir.join("buttons.png")).unwrap();
+        fs::write(
+            dir.join("buttons.toml"),
+            "image = \"buttons.png\"\n[sprites]\nA = { x = 0, y = 0, w = 2, h = 2 }\n",
+        )
+        .unwrap();
+
+        let atlas = IconAtlas::load(&dir.join("buttons.toml")).unwrap();
+        assert_eq!(atlas.sprite_size(Key::A), Some(Size::new(2, 2)));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,248 @@
+//! Decoded images kept in memory, so that going back to an image doesn't decode it again.
+//!
+//! The device has very little RAM, so the cache holds at most a budget of bytes across all kinds
+//! of images, evicting the least recently used ones to make room. Images are shared with the views
+//! that draw them, and an image that a view still holds is never evicted. Eviction only drops the
+//! cache's reference, so a view never loses the buffer it draws, and an evicted image is simply
+//! decoded again when it is next needed.
+
+use std::collections::HashMap;
+use std::env;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use enum_map::{Enum, EnumMap};
+use image::RgbaImage;
+use log::debug;
+use serde::Serialize;
+
+/// Bytes of decoded images kept in memory, unless overridden by `ALLIUM_IMAGE_CACHE_BUDGET`.
+pub const DEFAULT_IMAGE_CACHE_BUDGET: usize = 4 * 1024 * 1024;
+
+/// Bytes taken by a cached image besides its pixels: the key, the reference count, and the slot
+/// in the map.
+const ENTRY_OVERHEAD: usize = 128;
+
+/// What a cached image is used for, to tell where memory goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Enum)]
+pub enum CacheKind {
+    /// Images drawn at their own size.
+    Images,
+    /// Images scaled down to fit, such as box art.
+    Thumbnails,
+}
+
+/// Memory used by one kind of cached image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheUsage {
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+#[derive(Debug)]
+struct Cached {
+    image: Arc<RgbaImage>,
+    kind: CacheKind,
+    bytes: usize,
+    last_used: u64,
+}
+
+#[derive(Debug)]
+pub struct ImageCache<K> {
+    budget: usize,
+    entries: HashMap<K, Cached>,
+    usage: EnumMap<CacheKind, CacheUsage>,
+    /// Increases with every lookup, to tell which images were used least recently.
+    clock: u64,
+}
+
+impl<K: Hash + Eq + Clone> ImageCache<K> {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            entries: HashMap::new(),
+            usage: EnumMap::default(),
+            clock: 0,
+        }
+    }
+
+    /// A cache with the budget from `ALLIUM_IMAGE_CACHE_BUDGET`, in bytes, or the default one.
+    pub fn from_env() -> Self {
+        let budget = env::var("ALLIUM_IMAGE_CACHE_BUDGET")
+            .ok()
+            .and_then(|budget| budget.parse().ok())
+            .unwrap_or(DEFAULT_IMAGE_CACHE_BUDGET);
+        Self::new(budget)
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Bytes used by all cached images.
+    pub fn used(&self) -> usize {
+        self.usage.values().map(|usage| usage.bytes).sum()
+    }
+
+    pub fn usage(&self, kind: CacheKind) -> CacheUsage {
+        self.usage[kind]
+    }
+
+    /// Memory used by each kind of cached image.
+    pub fn usages(&self) -> impl Iterator<Item = (CacheKind, CacheUsage)> + '_ {
+        self.usage.iter().map(|(kind, usage)| (kind, *usage))
+    }
+
+    /// Returns the image for `key` if it is cached.
+    pub fn get(&mut self, key: &K) -> Option<Arc<RgbaImage>> {
+        self.clock += 1;
+        let cached = self.entries.get_mut(key)?;
+        cached.last_used = self.clock;
+        Some(Arc::clone(&cached.image))
+    }
+
+    /// Returns the image for `key`, decoding it with `decode` if it isn't cached. The decoded image
+    /// is cached if room can be made for it within the budget, and is returned either way.
+    pub fn get_or_insert_with(
+        &mut self,
+        key: K,
+        kind: CacheKind,
+        decode: impl FnOnce() -> Option<RgbaImage>,
+    ) -> Option<Arc<RgbaImage>> {
+        if let Some(image) = self.get(&key) {
+            return Some(image);
+        }
+
+        let image = Arc::new(decode()?);
+        let bytes = cost(&image);
+        if self.make_room(bytes) {
+            self.usage[kind].entries += 1;
+            self.usage[kind].bytes += bytes;
+            self.entries.insert(
+                key,
+                Cached {
+                    image: Arc::clone(&image),
+                    kind,
+                    bytes,
+                    last_used: self.clock,
+                },
+            );
+        } else {
+            debug!("no room to cache image of {} bytes", bytes);
+        }
+        Some(image)
+    }
+
+    /// Evicts the least recently used images that no view holds until `bytes` more fit within the
+    /// budget. Returns whether they do.
+    fn make_room(&mut self, bytes: usize) -> bool {
+        if bytes > self.budget {
+            return false;
+        }
+        let mut evictable: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, cached)| Arc::strong_count(&cached.image) == 1)
+            .map(|(key, cached)| (cached.last_used, cached.bytes, key))
+            .collect();
+        evictable.sort_unstable_by_key(|(last_used, _, _)| *last_used);
+
+        let mut used = self.used();
+        let mut evicted = Vec::new();
+        for (_, size, key) in evictable {
+            if used + bytes <= self.budget {
+                break;
+            }
+            used -= size;
+            evicted.push(key.clone());
+        }
+        if used + bytes > self.budget {
+            return false;
+        }
+
+        if !evicted.is_empty() {
+            debug!(
+                "evicting {} images, {} of {} bytes used",
+                evicted.len(),
+                used,
+                self.budget
+            );
+        }
+        for key in evicted {
+            if let Some(cached) = self.entries.remove(&key) {
+                self.usage[cached.kind].entries -= 1;
+                self.usage[cached.kind].bytes -= cached.bytes;
+            }
+        }
+        true
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+}
+
+/// Bytes taken by a cached image.
+fn cost(image: &RgbaImage) -> usize {
+    image.as_raw().len() + ENTRY_OVERHEAD
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    fn image(size: u32) -> RgbaImage {
+        RgbaImage::from_pixel(size, size, Rgba([1, 2, 3, 255]))
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        // Room for three 8x8 images
+        let mut cache = ImageCache::new(3 * (8 * 8 * 4 + ENTRY_OVERHEAD));
+        for key in ["a", "b", "c"] {
+            cache.get_or_insert_with(key, CacheKind::Thumbnails, || Some(image(8)));
+        }
+        assert_eq!(cache.used(), cache.budget());
+
+        // Using "a" makes "b" the least recently used
+        let mut decoded = false;
+        cache.get_or_insert_with("a", CacheKind::Thumbnails, || {
+            decoded = true;
+            None
+        });
+        assert!(!decoded);
+
+        cache.get_or_insert_with("d", CacheKind::Images, || Some(image(8)));
+        assert_eq!(cache.usage(CacheKind::Thumbnails).entries, 2);
+        assert_eq!(cache.usage(CacheKind::Images).entries, 1);
+        assert!(cache.entries.contains_key("a"));
+        assert!(!cache.entries.contains_key("b"));
+    }
+
+    #[test]
+    fn test_images_in_use_are_kept() {
+        let mut cache = ImageCache::new(2 * (8 * 8 * 4 + ENTRY_OVERHEAD));
+        let drawn = cache
+            .get_or_insert_with("drawn", CacheKind::Images, || Some(image(8)))
+            .unwrap();
+        cache.get_or_insert_with("other", CacheKind::Images, || Some(image(8)));
+
+        // The least recently used image is still held, so the other one goes
+        cache.get_or_insert_with("new", CacheKind::Images, || Some(image(8)));
+        assert!(cache.entries.contains_key("drawn"));
+        assert!(!cache.entries.contains_key("other"));
+
+        // Without room, a new image is returned but not cached
+        let held = cache
+            .get_or_insert_with("held", CacheKind::Images, || Some(image(8)))
+            .unwrap();
+        let big = cache.get_or_insert_with("big", CacheKind::Images, || Some(image(64)));
+        assert!(big.is_some());
+        assert!(!cache.entries.contains_key("big"));
+        assert!(cache.used() <= cache.budget());
+        assert_eq!(drawn.dimensions(), (8, 8));
+        assert_eq!(held.dimensions(), (8, 8));
+    }
+}
@@ -1,10 +1,71 @@
 use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
 
 use embedded_graphics::pixelcolor::{raw::RawU32, Rgb888};
 use embedded_graphics::prelude::{PixelColor, RawData, RgbColor};
 use image::Rgba;
+use lazy_static::lazy_static;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+/// How colors are mixed when blending.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// Mixes the sRGB channel values directly. Cheap, but mixing saturated colors comes out darker
+    /// and muddier than it should.
+    #[default]
+    Naive,
+    /// Mixes in linear light, so that mixes keep their brightness.
+    GammaCorrect,
+}
+
+static BLEND_MODE: AtomicU8 = AtomicU8::new(BlendMode::Naive as u8);
+
+/// The blend mode used by [`Color::blend`] and everything built on it.
+pub fn blend_mode() -> BlendMode {
+    match BLEND_MODE.load(Ordering::Relaxed) {
+        0 => BlendMode::Naive,
+        _ => BlendMode::GammaCorrect,
+    }
+}
+
+/// Sets the blend mode for the whole process. The stylesheet applies its `blend_mode` when loaded.
+pub fn set_blend_mode(mode: BlendMode) {
+    BLEND_MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+/// Precision of linear light values: 12 bits are enough for every sRGB value to survive the round
+/// trip.
+const LINEAR_MAX: u32 = 4095;
+
+lazy_static! {
+    static ref SRGB_TO_LINEAR: [u16; 256] = {
+        let mut table = [0; 256];
+        for (i, v) in table.iter_mut().enumerate() {
+            let c = i as f32 / 255.0;
+            let l = if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            };
+            *v = (l * LINEAR_MAX as f32).round() as u16;
+        }
+        table
+    };
+    static ref LINEAR_TO_SRGB: [u8; LINEAR_MAX as usize + 1] = {
+        let mut table = [0; LINEAR_MAX as usize + 1];
+        for (i, v) in table.iter_mut().enumerate() {
+            let l = i as f32 / LINEAR_MAX as f32;
+            let c = if l <= 0.0031308 {
+                l * 12.92
+            } else {
+                1.055 * l.powf(1.0 / 2.4) - 0.055
+            };
+            *v = (c * 255.0).round() as u8;
+        }
+        table
+    };
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Color(u32);
 
@@ -73,21 +134,60 @@ impl Color {
         self.r() < 128 && self.g() < 128 && self.b() < 128
     }
 
+    /// Relative luminance, from 0 for black to 1 for white.
+    pub fn luminance(&self) -> f32 {
+        let channel = |c: u8| SRGB_TO_LINEAR[c as usize] as f32 / LINEAR_MAX as f32;
+        0.2126 * channel(self.r()) + 0.7152 * channel(self.g()) + 0.0722 * channel(self.b())
+    }
+
+    /// Contrast ratio with `other`, from 1 for the same color to 21 for black and white.
+    pub fn contrast(&self, other: Self) -> f32 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
     pub fn invert(&self) -> Self {
         Self::new(255 - self.r(), 255 - self.g(), 255 - self.b())
     }
 
+    #[inline]
+    pub fn with_a(&self, a: u8) -> Self {
+        Self(a as u32 | self.0 & 0xFFFFFF00)
+    }
+
+    /// Mixes `alpha` of `other` into this color, using the process-wide [`blend_mode`]. The result
+    /// is opaque.
+    #[inline]
     pub fn blend(&self, other: Self, alpha: u8) -> Self {
+        self.blend_with(other, alpha, blend_mode())
+    }
+
+    /// Mixes `alpha` of `other` into this color using `mode`. The result is opaque.
+    pub fn blend_with(&self, other: Self, alpha: u8, mode: BlendMode) -> Self {
+        let mix = match mode {
+            BlendMode::Naive => mix,
+            BlendMode::GammaCorrect => mix_linear,
+        };
         Self::new(
-            ((self.r() as i32 * (255 - alpha as i32) + other.r() as i32 * alpha as i32) / 255)
-                as u8,
-            ((self.g() as i32 * (255 - alpha as i32) + other.g() as i32 * alpha as i32) / 255)
-                as u8,
-            ((self.b() as i32 * (255 - alpha as i32) + other.b() as i32 * alpha as i32) / 255)
-                as u8,
+            mix(self.r(), other.r(), alpha),
+            mix(self.g(), other.g(), alpha),
+            mix(self.b(), other.b(), alpha),
         )
     }
 
+    /// Draws this color over `background`, using its own alpha.
+    #[inline]
+    pub fn over(&self, background: Self) -> Self {
+        background.blend(*self, self.a())
+    }
+
+    /// Tints this color with `color` through an overlay, with `strength` of the tint mixed in.
+    /// Used to dim the screen behind menus drawn over a game.
+    #[inline]
+    pub fn dim(&self, color: Self, strength: u8) -> Self {
+        self.blend(color.overlay(*self), strength)
+    }
+
     pub fn overlay(&self, other: Self) -> Self {
         Self::new(
             overlay(self.r(), other.r()),
@@ -167,6 +267,19 @@ impl From<Color> for Rgba<u8> {
     }
 }
 
+#[inline]
+fn mix(a: u8, b: u8, alpha: u8) -> u8 {
+    ((a as u32 * (255 - alpha as u32) + b as u32 * alpha as u32) / 255) as u8
+}
+
+#[inline]
+fn mix_linear(a: u8, b: u8, alpha: u8) -> u8 {
+    let a = SRGB_TO_LINEAR[a as usize] as u32;
+    let b = SRGB_TO_LINEAR[b as usize] as u32;
+    let l = (a * (255 - alpha as u32) + b * alpha as u32 + 127) / 255;
+    LINEAR_TO_SRGB[l as usize]
+}
+
 fn overlay(a: u8, b: u8) -> u8 {
     if a < 128 {
         (a as i32 * b as i32 / 255) as u8
@@ -174,3 +287,112 @@ fn overlay(a: u8, b: u8) -> u8 {
         255 - ((255 - a as i32) * (255 - b as i32) / 255) as u8
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+
+    use super::*;
+    use crate::display::golden::{assert_golden, Framebuffer};
+
+    const PAIRS: [(Color, Color); 6] = [
+        (Color(0x000000FF), Color(0xFFFFFFFF)),
+        (Color(0xFF0000FF), Color(0x00FF00FF)),
+        (Color(0x0000FFFF), Color(0xFFFF00FF)),
+        (Color(0xFF00FFFF), Color(0x00FFFFFF)),
+        (Color(0x000000FF), Color(0x7287FDFF)),
+        (Color(0xEFF1F5FF), Color(0xEB1A1DFF)),
+    ];
+
+    const ALPHAS: [u8; 5] = [0, 64, 128, 192, 255];
+
+    #[test]
+    fn test_srgb_round_trip() {
+        for i in 0..=255 {
+            assert_eq!(LINEAR_TO_SRGB[SRGB_TO_LINEAR[i] as usize], i as u8);
+        }
+    }
+
+    #[test]
+    fn test_blend_endpoints() {
+        for mode in [BlendMode::Naive, BlendMode::GammaCorrect] {
+            for (a, b) in PAIRS {
+                assert_eq!(a.blend_with(b, 0, mode), a);
+                assert_eq!(a.blend_with(b, 255, mode), b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_gamma_correct_keeps_brightness() {
+        let black = Color::new(0, 0, 0);
+        let white = Color::new(255, 255, 255);
+        assert_eq!(
+            black.blend_with(white, 128, BlendMode::Naive),
+            Color::new(128, 128, 128)
+        );
+        assert_eq!(
+            black.blend_with(white, 128, BlendMode::GammaCorrect),
+            Color::new(188, 188, 188)
+        );
+
+        // Red and green mix to a brighter yellow instead of a muddy olive
+        let red = Color::new(255, 0, 0);
+        let green = Color::new(0, 255, 0);
+        assert_eq!(
+            red.blend_with(green, 128, BlendMode::GammaCorrect),
+            Color::new(187, 188, 0)
+        );
+    }
+
+    #[test]
+    fn test_contrast() {
+        let black = Color::new(0, 0, 0);
+        let white = Color::new(255, 255, 255);
+        assert!((black.contrast(white) - 21.0).abs() < 0.01);
+        assert!((white.contrast(black) - 21.0).abs() < 0.01);
+        assert_eq!(white.contrast(white), 1.0);
+    }
+
+    #[test]
+    fn test_over() {
+        let background = Color::new(0, 0, 0);
+        assert_eq!(Color::rgba(255, 0, 0, 0).over(background), background);
+        assert_eq!(
+            Color::rgba(255, 0, 0, 255).over(background),
+            Color::new(255, 0, 0)
+        );
+    }
+
+    /// Draws a swatch of each pair mixed at each of `ALPHAS`, naive on the left and gamma-correct
+    /// on the right.
+    #[test]
+    fn test_blend_golden() {
+        const SIZE: u32 = 8;
+        let columns = ALPHAS.len() as u32;
+        let mut display = Framebuffer::new(
+            SIZE * (2 * columns + 1),
+            SIZE * PAIRS.len() as u32,
+            Color::new(128, 128, 128),
+        );
+
+        for (row, (a, b)) in PAIRS.iter().enumerate() {
+            for (i, mode) in [BlendMode::Naive, BlendMode::GammaCorrect]
+                .into_iter()
+                .enumerate()
+            {
+                for (column, alpha) in ALPHAS.iter().enumerate() {
+                    let x = (i as u32 * (columns + 1) + column as u32) * SIZE;
+                    let y = row as u32 * SIZE;
+                    Rectangle::new(Point::new(x as i32, y as i32), Size::new_equal(SIZE))
+                        .into_styled(PrimitiveStyle::with_fill(a.blend_with(*b, *alpha, mode)))
+                        .draw(&mut display)
+                        .unwrap();
+                }
+            }
+        }
+
+        assert_golden("color_blend", &display);
+    }
+}
@@ -0,0 +1,171 @@
+//! Golden image testing. Drawing is done into an in-memory [`Framebuffer`] and compared against a
+//! PNG in `testdata/golden`. Run the tests with `ALLIUM_UPDATE_GOLDEN=1` to rewrite the images after
+//! an intended change, and check the new images before committing them.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use embedded_graphics::prelude::*;
+use image::buffer::ConvertBuffer;
+use image::{RgbImage, Rgba, RgbaImage};
+
+use crate::display::atlas::IconAtlas;
+use crate::display::color::Color;
+use crate::display::greyscale::Greyscale;
+use crate::display::Display;
+use crate::geom::Rect;
+use crate::stylesheet::{StyleAssets, StyleConfig, Styles, StylesheetFont};
+
+pub struct Framebuffer {
+    image: RgbaImage,
+    saved: Option<RgbaImage>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32, background: Color) -> Self {
+        Self {
+            image: RgbaImage::from_pixel(width, height, background.into()),
+            saved: None,
+        }
+    }
+
+    /// Passes what is drawn through `greyscale`, as flushing it to a 32-bit screen would.
+    pub fn flush_greyscale(&mut self, greyscale: &Greyscale) {
+        let src: Vec<u8> = self
+            .image
+            .pixels()
+            .flat_map(|Rgba([r, g, b, a])| [*b, *g, *r, *a])
+            .collect();
+        let mut dst = vec![0; src.len()];
+        greyscale.transform(&src, &mut dst, self.image.width() as usize, 4);
+        for (pixel, bgra) in self.image.pixels_mut().zip(dst.chunks_exact(4)) {
+            *pixel = Rgba([bgra[2], bgra[1], bgra[0], bgra[3]]);
+        }
+    }
+}
+
+impl Display for Framebuffer {
+    fn map_pixels<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(Color) -> Color,
+    {
+        for pixel in self.image.pixels_mut() {
+            let Rgba([r, g, b, a]) = *pixel;
+            *pixel = f(Color::rgba(r, g, b, a)).into();
+        }
+        Ok(())
+    }
+
+    fn save(&mut self) -> Result<()> {
+        self.saved = Some(self.image.clone());
+        Ok(())
+    }
+
+    fn capture(&self) -> Result<RgbImage> {
+        Ok(self.image.convert())
+    }
+
+    fn load(&mut self, area: Rect) -> Result<()> {
+        let saved = self
+            .saved
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No saved image"))?;
+        for y in area.y.max(0)..(area.y + area.h as i32).min(self.image.height() as i32) {
+            for x in area.x.max(0)..(area.x + area.w as i32).min(self.image.width() as i32) {
+                self.image
+                    .put_pixel(x as u32, y as u32, *saved.get_pixel(x as u32, y as u32));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DrawTarget for Framebuffer {
+    type Color = Color;
+    type Error = anyhow::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<()>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x >= 0
+                && point.y >= 0
+                && (point.x as u32) < self.image.width()
+                && (point.y as u32) < self.image.height()
+            {
+                self.image
+                    .put_pixel(point.x as u32, point.y as u32, color.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Framebuffer {
+    fn size(&self) -> Size {
+        Size::new(self.image.width(), self.image.height())
+    }
+}
+
+/// Default stylesheet with the bundled UI font, so that tests don't depend on the device fonts.
+pub fn style_config() -> StyleConfig {
+    let font =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../assets/root/.allium/fonts/Nunito.ttf");
+    let mut styles = StyleConfig::default();
+    styles.ui_font = StylesheetFont::new(font.clone(), 36);
+    styles.guide_font = StylesheetFont::new(font.clone(), 28);
+    styles.cjk_font = StylesheetFont::new(font, 32);
+    styles
+}
+
+/// [`style_config`] with its fonts loaded.
+pub fn styles() -> Styles {
+    Styles::from_config(style_config()).unwrap()
+}
+
+/// `styles` with `atlas` as its button icons.
+pub fn with_button_atlas(styles: &Styles, atlas: Arc<IconAtlas>) -> Styles {
+    let assets = StyleAssets {
+        button_atlas: Some(atlas),
+        ..styles.assets().clone()
+    };
+    Styles::new(styles.config().clone(), assets)
+}
+
+/// Asserts that `display` matches the golden image called `name`.
+pub fn assert_golden(name: &str, display: &Framebuffer) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("testdata/golden")
+        .join(format!("{}.png", name));
+
+    if env::var_os("ALLIUM_UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        display.image.save(&path).unwrap();
+        return;
+    }
+
+    let golden = image::open(&path)
+        .unwrap_or_else(|e| panic!("failed to open golden image {}: {}", path.display(), e))
+        .to_rgba8();
+    assert_eq!(
+        golden.dimensions(),
+        display.image.dimensions(),
+        "{} has a different size",
+        name
+    );
+    if let Some((x, y, pixel)) = display
+        .image
+        .enumerate_pixels()
+        .find(|(x, y, pixel)| golden.get_pixel(*x, *y) != *pixel)
+    {
+        let Rgba(expected) = golden.get_pixel(x, y);
+        panic!(
+            "{} differs at ({}, {}): expected {:?}, got {:?}",
+            name, x, y, expected, pixel.0
+        );
+    }
+}
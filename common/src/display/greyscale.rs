@@ -0,0 +1,413 @@
+//! High-contrast greyscale rendering, for panels that can't show the theme's colors, such as
+//! transflective and e-ink mods.
+//!
+//! The transform is applied when a frame is flushed to the screen, so that everything drawn passes
+//! through it. It is built from lookup tables to be cheap enough for full-screen flushes: the luma
+//! of a pixel is looked up per channel for 24 and 32-bit pixels, or for the whole pixel for
+//! RGB565, and the contrast curve, quantization and dithering are folded into one table per cell
+//! of the dither pattern.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::display::color::Color;
+
+/// How colors between two grey levels are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dithering {
+    /// Rounds to the nearest grey level. Keeps text and flat areas clean, but gradients in box art
+    /// turn into bands.
+    #[default]
+    None,
+    /// Mixes the two nearest grey levels in a 4x4 pattern.
+    Ordered,
+}
+
+/// Number of grey levels in the output, as shown by typical e-ink panels.
+pub const GREY_LEVELS: u32 = 16;
+
+/// Luma at or below which the output is black. Together with `WHITE_POINT`, stretches the
+/// contrast of the theme's mid tones.
+const BLACK_POINT: u32 = 24;
+
+/// Luma at or above which the output is white.
+const WHITE_POINT: u32 = 232;
+
+/// Thresholds of the 4x4 Bayer matrix, row by row.
+const BAYER: [u8; 16] = [0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5];
+
+/// Contributions of red, green and blue to luma, out of 256.
+const WEIGHTS: [u32; 3] = [54, 183, 19];
+
+/// Lookup tables for transforming frames to greyscale.
+pub struct Greyscale {
+    /// Luma of each channel value, scaled by 256, for red, green and blue.
+    luma: [[u16; 256]; 3],
+    /// Luma of each RGB565 pixel.
+    luma565: Box<[u8]>,
+    /// Output grey by cell of the dither pattern and luma.
+    levels: [[u8; 256]; 16],
+}
+
+impl Greyscale {
+    pub fn new(dithering: Dithering) -> Self {
+        let mut luma = [[0; 256]; 3];
+        for (table, weight) in luma.iter_mut().zip(WEIGHTS) {
+            for (v, entry) in table.iter_mut().enumerate() {
+                *entry = (v as u32 * weight) as u16;
+            }
+        }
+
+        let luma565 = (0..=u16::MAX)
+            .map(|pixel| {
+                let (r, g, b) = (pixel >> 11, (pixel >> 5) & 0x3F, pixel & 0x1F);
+                let (r, g, b) = (
+                    (r << 3 | r >> 2) as usize,
+                    (g << 2 | g >> 4) as usize,
+                    (b << 3 | b >> 2) as usize,
+                );
+                ((luma[0][r] + luma[1][g] + luma[2][b]) >> 8) as u8
+            })
+            .collect();
+
+        let step = 255.0 / (GREY_LEVELS - 1) as f32;
+        let mut levels = [[0; 256]; 16];
+        for (cell, table) in levels.iter_mut().enumerate() {
+            let threshold = match dithering {
+                Dithering::None => 0.5,
+                Dithering::Ordered => (BAYER[cell] as f32 + 0.5) / 16.0,
+            };
+            for (luma, grey) in table.iter_mut().enumerate() {
+                let stretched =
+                    (luma as f32 - BLACK_POINT as f32) / (WHITE_POINT - BLACK_POINT) as f32 * 255.0;
+                let level = (stretched.clamp(0.0, 255.0) / step + threshold)
+                    .floor()
+                    .min((GREY_LEVELS - 1) as f32);
+                *grey = (level * step).round() as u8;
+            }
+        }
+
+        Self {
+            luma,
+            luma565,
+            levels,
+        }
+    }
+
+    /// Grey that `color` is shown as at `x`, `y`.
+    #[inline]
+    pub fn grey(&self, color: Color, x: usize, y: usize) -> u8 {
+        let luma = (self.luma[0][color.r() as usize]
+            + self.luma[1][color.g() as usize]
+            + self.luma[2][color.b() as usize])
+            >> 8;
+        self.levels[(y & 3) << 2 | (x & 3)][luma as usize]
+    }
+
+    /// Transforms a frame `width` pixels wide from `src` into `dst`, which must be the same size.
+    /// Pixels of 3 and 4 bytes are BGR with any fourth byte kept as is, and pixels of 2 bytes are
+    /// little-endian RGB565. Frames in any other format are copied unchanged.
+    pub fn transform(&self, src: &[u8], dst: &mut [u8], width: usize, bytes_per_pixel: usize) {
+        match bytes_per_pixel {
+            2 => self.transform_rgb565(src, dst, width),
+            3 | 4 => self.transform_bgr888(src, dst, width, bytes_per_pixel),
+            _ => dst.copy_from_slice(src),
+        }
+    }
+
+    fn transform_bgr888(&self, src: &[u8], dst: &mut [u8], width: usize, bytes_per_pixel: usize) {
+        let stride = width * bytes_per_pixel;
+        for (y, (src, dst)) in src
+            .chunks_exact(stride)
+            .zip(dst.chunks_exact_mut(stride))
+            .enumerate()
+        {
+            let levels = &self.levels[(y & 3) << 2..][..4];
+            for (x, (src, dst)) in src
+                .chunks_exact(bytes_per_pixel)
+                .zip(dst.chunks_exact_mut(bytes_per_pixel))
+                .enumerate()
+            {
+                let luma = (self.luma[0][src[2] as usize]
+                    + self.luma[1][src[1] as usize]
+                    + self.luma[2][src[0] as usize])
+                    >> 8;
+                let grey = levels[x & 3][luma as usize];
+                dst[..3].fill(grey);
+                dst[3..].copy_from_slice(&src[3..]);
+            }
+        }
+    }
+
+    fn transform_rgb565(&self, src: &[u8], dst: &mut [u8], width: usize) {
+        let stride = width * 2;
+        for (y, (src, dst)) in src
+            .chunks_exact(stride)
+            .zip(dst.chunks_exact_mut(stride))
+            .enumerate()
+        {
+            let levels = &self.levels[(y & 3) << 2..][..4];
+            for (x, (src, dst)) in src.chunks_exact(2).zip(dst.chunks_exact_mut(2)).enumerate() {
+                let luma = self.luma565[u16::from_le_bytes([src[0], src[1]]) as usize];
+                let grey = levels[x & 3][luma as usize] as u16;
+                let pixel = (grey >> 3) << 11 | (grey >> 2) << 5 | grey >> 3;
+                dst.copy_from_slice(&pixel.to_le_bytes());
+            }
+        }
+    }
+}
+
+lazy_static! {
+    static ref UNDITHERED: Greyscale = Greyscale::new(Dithering::None);
+    static ref ORDERED: Greyscale = Greyscale::new(Dithering::Ordered);
+}
+
+/// 0 for color, otherwise 1 more than the dithering.
+static GREYSCALE: AtomicU8 = AtomicU8::new(0);
+
+/// The greyscale transform that frames are flushed through, if greyscale rendering is on.
+pub fn greyscale() -> Option<&'static Greyscale> {
+    match GREYSCALE.load(Ordering::Relaxed) {
+        0 => None,
+        1 => Some(&UNDITHERED),
+        _ => Some(&ORDERED),
+    }
+}
+
+/// Turns greyscale rendering on with `dithering`, or off, for the whole process. The stylesheet
+/// applies its `greyscale` and `dithering` when loaded.
+pub fn set_greyscale(dithering: Option<Dithering>) {
+    let mode = match dithering {
+        None => 0,
+        Some(Dithering::None) => 1,
+        Some(Dithering::Ordered) => 2,
+    };
+    GREYSCALE.store(mode, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::primitives::{CornerRadii, PrimitiveStyle, Rectangle, RoundedRectangle};
+    use embedded_graphics::text::Text;
+
+    use super::*;
+    use crate::display::font::FontTextStyleBuilder;
+    use crate::display::golden::{assert_golden, styles, Framebuffer};
+    use crate::geom::{Alignment, Point as GeomPoint};
+    use crate::platform::Key;
+    use crate::stylesheet::Styles;
+    use crate::view::ButtonIcon;
+
+    fn bgra(color: Color) -> [u8; 4] {
+        [color.b(), color.g(), color.r(), color.a()]
+    }
+
+    #[test]
+    fn test_contrast_is_stretched() {
+        let greyscale = Greyscale::new(Dithering::None);
+        assert_eq!(greyscale.grey(Color::new(0, 0, 0), 0, 0), 0);
+        assert_eq!(greyscale.grey(Color::new(20, 20, 20), 0, 0), 0);
+        assert_eq!(greyscale.grey(Color::new(255, 255, 255), 0, 0), 255);
+        assert_eq!(greyscale.grey(Color::new(240, 240, 240), 0, 0), 255);
+        // Green is brighter than red, which is brighter than blue
+        let red = greyscale.grey(Color::new(255, 0, 0), 0, 0);
+        let green = greyscale.grey(Color::new(0, 255, 0), 0, 0);
+        let blue = greyscale.grey(Color::new(0, 0, 255), 0, 0);
+        assert!(blue < red && red < green, "{red} {green} {blue}");
+        // Output is quantized to the grey levels
+        for v in 0..=255 {
+            let grey = greyscale.grey(Color::new(v, v, v), 0, 0);
+            assert_eq!(grey as u32 % (255 / (GREY_LEVELS - 1)), 0, "{v}");
+        }
+    }
+
+    #[test]
+    fn test_ordered_dithering_averages() {
+        let greyscale = Greyscale::new(Dithering::Ordered);
+        for v in [40, 100, 128, 200] {
+            let color = Color::new(v, v, v);
+            let sum: u32 = (0..4)
+                .flat_map(|y| (0..4).map(move |x| (x, y)))
+                .map(|(x, y)| greyscale.grey(color, x, y) as u32)
+                .sum();
+            let undithered = Greyscale::new(Dithering::None).levels[0][v as usize];
+            // The pattern averages out close to the stretched luma, rather than to one level
+            assert!((sum as i32 / 16 - undithered as i32).abs() <= 17, "{v}");
+        }
+        // Black and white stay solid
+        for (x, y) in [(0, 0), (1, 2), (3, 3)] {
+            assert_eq!(greyscale.grey(Color::new(0, 0, 0), x, y), 0);
+            assert_eq!(greyscale.grey(Color::new(255, 255, 255), x, y), 255);
+        }
+    }
+
+    #[test]
+    fn test_formats_agree() {
+        let greyscale = Greyscale::new(Dithering::Ordered);
+        // Colors that RGB565 represents exactly
+        let colors = [
+            Color::new(0, 0, 0),
+            Color::new(255, 255, 255),
+            Color::new(132, 130, 132),
+            Color::new(255, 0, 0),
+            Color::new(0, 130, 255),
+        ];
+        let width = colors.len();
+
+        let src: Vec<u8> = colors.iter().flat_map(|c| bgra(c.with_a(7))).collect();
+        let mut dst = vec![0; src.len()];
+        greyscale.transform(&src, &mut dst, width, 4);
+        for (x, (color, pixel)) in colors.iter().zip(dst.chunks_exact(4)).enumerate() {
+            let grey = greyscale.grey(*color, x, 0);
+            assert_eq!(pixel, [grey, grey, grey, 7]);
+        }
+
+        let src: Vec<u8> = colors
+            .iter()
+            .flat_map(|c| {
+                let [b, g, r, _] = bgra(*c);
+                [b, g, r]
+            })
+            .collect();
+        let mut dst = vec![0; src.len()];
+        greyscale.transform(&src, &mut dst, width, 3);
+        for (x, (color, pixel)) in colors.iter().zip(dst.chunks_exact(3)).enumerate() {
+            let grey = greyscale.grey(*color, x, 0);
+            assert_eq!(pixel, [grey, grey, grey]);
+        }
+
+        let src: Vec<u8> = colors
+            .iter()
+            .flat_map(|c| {
+                let pixel =
+                    (c.r() as u16 >> 3) << 11 | (c.g() as u16 >> 2) << 5 | c.b() as u16 >> 3;
+                pixel.to_le_bytes()
+            })
+            .collect();
+        let mut dst = vec![0; src.len()];
+        greyscale.transform(&src, &mut dst, width, 2);
+        for (x, (color, pixel)) in colors.iter().zip(dst.chunks_exact(2)).enumerate() {
+            let grey = greyscale.grey(*color, x, 0) as u16;
+            let pixel = u16::from_le_bytes([pixel[0], pixel[1]]);
+            assert_eq!(pixel >> 11, grey >> 3, "{color}");
+            assert_eq!((pixel >> 5) & 0x3F, grey >> 2, "{color}");
+        }
+    }
+
+    #[test]
+    fn test_set_greyscale() {
+        set_greyscale(Some(Dithering::Ordered));
+        assert!(greyscale().is_some());
+        set_greyscale(None);
+        assert!(greyscale().is_none());
+    }
+
+    /// The games list: tabs, rows with the selection pill, box art, and button hints.
+    fn launcher_frame(styles: &Styles) -> Framebuffer {
+        let mut display = Framebuffer::new(640, 480, styles.background_color);
+
+        let text_style = |color: Color| {
+            FontTextStyleBuilder::new(styles.ui_font())
+                .font_size(styles.ui_font.size)
+                .text_color(color)
+                .build()
+        };
+
+        let mut x = 12;
+        for (i, tab) in ["Recents", "Games", "Apps", "Settings"].iter().enumerate() {
+            let color = if i == 1 {
+                styles.highlight_color
+            } else {
+                styles.disabled_color
+            };
+            let text = Text::new(tab, Point::new(x, 8), text_style(color));
+            x += text.bounding_box().size.width as i32 + 24;
+            text.draw(&mut display).unwrap();
+        }
+
+        let layout = styles.row_layout();
+        let games = [
+            "Advance Wars",
+            "Golden Sun",
+            "Metroid Fusion",
+            "Pokémon Emerald",
+        ];
+        for (i, game) in games.iter().enumerate() {
+            let y = 60 + (i as u32 * layout.height) as i32;
+            if i == 1 {
+                let width = Text::new(game, Point::zero(), text_style(Color::new(0, 0, 0)))
+                    .bounding_box()
+                    .size
+                    .width;
+                RoundedRectangle::new(
+                    Rectangle::new(
+                        Point::new(12, y),
+                        Size::new(width + 2 * layout.padding, layout.height),
+                    ),
+                    CornerRadii::new(Size::new_equal(layout.radius)),
+                )
+                .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+                .draw(&mut display)
+                .unwrap();
+            }
+            Text::new(
+                game,
+                Point::new(12 + layout.padding as i32, y + layout.inset as i32),
+                text_style(styles.foreground_color),
+            )
+            .draw(&mut display)
+            .unwrap();
+        }
+
+        // Box art with smooth gradients, where dithering shows
+        let art = (0..200).flat_map(|y| {
+            (0..200).map(move |x| {
+                Pixel(
+                    Point::new(416 + x, 60 + y),
+                    Color::new(x as u8, 255 - y as u8, ((x + y) / 2) as u8 + 28),
+                )
+            })
+        });
+        display.draw_iter(art).unwrap();
+
+        let mut x = 628;
+        for (key, hint) in [(Key::B, "Back"), (Key::A, "Select")] {
+            let text = Text::new(hint, Point::zero(), text_style(Color::new(0, 0, 0)));
+            x -= text.bounding_box().size.width as i32;
+            Text::new(
+                hint,
+                Point::new(x, 480 - 8 - styles.ui_font.size as i32),
+                text_style(styles.foreground_color),
+            )
+            .draw(&mut display)
+            .unwrap();
+            x -= ButtonIcon::diameter(styles) as i32 + 8;
+            ButtonIcon::new(
+                GeomPoint::new(x, 480 - 8 - ButtonIcon::diameter(styles) as i32),
+                key,
+                Alignment::Left,
+            )
+            .draw_icon(&mut display, styles)
+            .unwrap();
+            x -= 16;
+        }
+
+        display
+    }
+
+    #[test]
+    fn test_launcher_frame_golden() {
+        let styles = styles();
+        for (dithering, name) in [
+            (Dithering::None, "greyscale_none"),
+            (Dithering::Ordered, "greyscale_ordered"),
+        ] {
+            let mut display = launcher_frame(&styles);
+            display.flush_greyscale(&Greyscale::new(dithering));
+            assert_golden(name, &display);
+        }
+    }
+}
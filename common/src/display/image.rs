@@ -1,4 +1,340 @@
-use image::{Rgba, RgbaImage};
+//! Helpers for images drawn on screen, and the cache of scaled images on the SD card.
+
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use image::imageops::FilterType;
+use image::io::Reader;
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageResult, Rgba, RgbaImage};
+use lazy_static::lazy_static;
+use log::{debug, warn};
+
+use crate::constants::{ALLIUM_IMAGE_CACHE_DIR, ALLIUM_SD_ROOT};
+
+/// Bytes of scaled images kept on the SD card, unless overridden by
+/// `ALLIUM_DISK_IMAGE_CACHE_BUDGET`.
+pub const DEFAULT_DISK_IMAGE_CACHE_BUDGET: u64 = 64 * 1024 * 1024;
+
+/// Bytes read from the start of a file to find its EXIF orientation without decoding it. Cameras
+/// and scanners put EXIF data first, well within this.
+const EXIF_SEARCH_LENGTH: u64 = 64 * 1024;
+
+/// Bytes before the pixels of a cached image: its width and height.
+const HEADER_LENGTH: usize = 8;
+
+lazy_static! {
+    /// Scaled images shared by every process, so that scrolling past box art doesn't decode and
+    /// scale it again, even after a restart.
+    pub static ref DISK_IMAGE_CACHE: Mutex<DiskImageCache> = Mutex::new(DiskImageCache::from_env());
+}
+
+/// Images as they are drawn, after decoding, scaling and rounding, stored raw so that loading one
+/// is a single read. Callers key images by everything that changes how they are drawn, including
+/// when the source was last modified, so that changed images aren't served stale.
+///
+/// Files are named by the hash of their key. Once the cache outgrows its budget, the files written
+/// longest ago are removed.
+#[derive(Debug)]
+pub struct DiskImageCache {
+    /// `None` if the cache is disabled.
+    dir: Option<PathBuf>,
+    budget: u64,
+    /// Bytes used by the cache, counted on first write.
+    used: Option<u64>,
+}
+
+impl DiskImageCache {
+    pub fn new(dir: PathBuf, budget: u64) -> Self {
+        Self {
+            dir: Some(dir),
+            budget,
+            used: None,
+        }
+    }
+
+    /// A cache that never has anything cached.
+    pub fn disabled() -> Self {
+        Self {
+            dir: None,
+            budget: 0,
+            used: None,
+        }
+    }
+
+    /// A cache on the SD card with the budget from `ALLIUM_DISK_IMAGE_CACHE_BUDGET`, in bytes, or
+    /// the default one. Disabled without an SD card, such as in tests.
+    pub fn from_env() -> Self {
+        if !ALLIUM_SD_ROOT.exists() {
+            return Self::disabled();
+        }
+        let budget = env::var("ALLIUM_DISK_IMAGE_CACHE_BUDGET")
+            .ok()
+            .and_then(|budget| budget.parse().ok())
+            .unwrap_or(DEFAULT_DISK_IMAGE_CACHE_BUDGET);
+        Self::new(ALLIUM_IMAGE_CACHE_DIR.clone(), budget)
+    }
+
+    /// Returns the image for `key`, making it with `make` and storing it if it isn't cached.
+    /// Failing to read or write the cache only costs making the image again.
+    pub fn get_or_insert_with<K: Hash>(
+        &mut self,
+        key: &K,
+        make: impl FnOnce() -> Option<RgbaImage>,
+    ) -> Option<RgbaImage> {
+        let Some(path) = self.path(key) else {
+            return make();
+        };
+        if let Some(image) = read(&path) {
+            return Some(image);
+        }
+
+        let image = make()?;
+        if let Err(e) = self.write(&path, &image) {
+            warn!("failed to cache image at {}: {}", path.display(), e);
+        }
+        Some(image)
+    }
+
+    /// Removes images left half written by a process that was stopped mid-write, and the oldest
+    /// images if the cache is over its budget, such as after the budget was lowered.
+    pub fn prune(&mut self) -> Result<()> {
+        let Some(dir) = self.dir.clone() else {
+            return Ok(());
+        };
+        if !dir.exists() {
+            return Ok(());
+        }
+
+        let mut used = 0;
+        for (path, _, len) in files(&dir)? {
+            if path.extension().is_some_and(|ext| ext == "rgba") {
+                used += len;
+            } else if let Err(e) = fs::remove_file(&path) {
+                warn!("failed to remove {}: {}", path.display(), e);
+            }
+        }
+        self.used = Some(if used > self.budget {
+            evict(&dir, self.budget)?
+        } else {
+            used
+        });
+        Ok(())
+    }
+
+    fn path<K: Hash>(&self, key: &K) -> Option<PathBuf> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        Some(
+            self.dir
+                .as_ref()?
+                .join(format!("{:016x}.rgba", hasher.finish())),
+        )
+    }
+
+    fn write(&mut self, path: &Path, image: &RgbaImage) -> Result<()> {
+        let Some(dir) = self.dir.clone() else {
+            return Ok(());
+        };
+        fs::create_dir_all(&dir)?;
+        let used = match self.used {
+            Some(used) => used,
+            None => files(&dir)?.iter().map(|(_, _, len)| len).sum(),
+        };
+
+        // Written aside first, so that another process never reads half an image
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        let mut file = File::create(&tmp)?;
+        file.write_all(&image.width().to_le_bytes())?;
+        file.write_all(&image.height().to_le_bytes())?;
+        file.write_all(image.as_raw())?;
+        drop(file);
+        fs::rename(&tmp, path)?;
+
+        let used = used + (HEADER_LENGTH + image.as_raw().len()) as u64;
+        self.used = Some(if used > self.budget {
+            evict(&dir, self.budget)?
+        } else {
+            used
+        });
+        Ok(())
+    }
+}
+
+/// The cached image at `path`, if there is a complete one.
+fn read(path: &Path) -> Option<RgbaImage> {
+    let mut bytes = Vec::new();
+    File::open(path).ok()?.read_to_end(&mut bytes).ok()?;
+    if bytes.len() < HEADER_LENGTH {
+        return None;
+    }
+    let width = u32::from_le_bytes(bytes[0..4].try_into().ok()?);
+    let height = u32::from_le_bytes(bytes[4..8].try_into().ok()?);
+    RgbaImage::from_raw(width, height, bytes.split_off(HEADER_LENGTH))
+}
+
+/// Removes the files written longest ago until the cache is within three quarters of `budget`, so
+/// that it isn't done again on every write. Returns the bytes still used.
+fn evict(dir: &Path, budget: u64) -> Result<u64> {
+    let mut files = files(dir)?;
+    files.sort_unstable_by_key(|(_, modified, _)| *modified);
+
+    let mut used: u64 = files.iter().map(|(_, _, len)| len).sum();
+    let mut removed = 0;
+    for (path, _, len) in files {
+        if used <= budget / 4 * 3 {
+            break;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                used -= len;
+                removed += 1;
+            }
+            Err(e) => warn!("failed to remove cached image {}: {}", path.display(), e),
+        }
+    }
+    debug!(
+        "evicted {} cached images, {} of {} bytes used",
+        removed, used, budget
+    );
+    Ok(used)
+}
+
+/// The files in the cache, with when they were written and their size.
+fn files(dir: &Path) -> Result<Vec<(PathBuf, std::time::SystemTime, u64)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            files.push((entry.path(), metadata.modified()?, metadata.len()));
+        }
+    }
+    Ok(files)
+}
+
+/// Size of an image of `size` scaled to fit within `bounds`, keeping its aspect ratio. Neither side
+/// is ever less than a pixel, however thin the image.
+pub fn fit_within((w, h): (u32, u32), (max_w, max_h): (u32, u32)) -> (u32, u32) {
+    let (w, h) = (u64::from(w.max(1)), u64::from(h.max(1)));
+    let (max_w, max_h) = (u64::from(max_w), u64::from(max_h));
+    let (w, h) = if w * max_h > h * max_w {
+        (max_w, max_w * h / w)
+    } else {
+        (max_h * w / h, max_h)
+    };
+    (w.max(1) as u32, h.max(1) as u32)
+}
+
+/// Decodes the image at `path`, turned the way it is meant to be seen if it has an EXIF
+/// orientation, as photos and scans of box art often do.
+pub fn open(path: &Path) -> ImageResult<DynamicImage> {
+    let bytes = fs::read(path)?;
+    let mut reader = Reader::new(Cursor::new(&bytes));
+    if let Ok(format) = ImageFormat::from_path(path) {
+        reader.set_format(format);
+    }
+    let image = reader.with_guessed_format()?.decode()?;
+    Ok(orient(image, exif_orientation(&bytes)))
+}
+
+/// Size of the image at `path` once it is turned upright, without decoding it.
+pub fn dimensions(path: &Path) -> ImageResult<(u32, u32)> {
+    let (w, h) = image::image_dimensions(path)?;
+    let mut header = Vec::new();
+    File::open(path)?
+        .take(EXIF_SEARCH_LENGTH)
+        .read_to_end(&mut header)?;
+    Ok(match exif_orientation(&header) {
+        // Turned a quarter
+        Some(5..=8) => (h, w),
+        _ => (w, h),
+    })
+}
+
+/// Box art at `path`, scaled to fit within `size` keeping its aspect ratio, however tall or wide
+/// it is. Whatever of `size` it doesn't cover is left to the background.
+pub fn contain(path: &Path, size: (u32, u32)) -> ImageResult<RgbaImage> {
+    let image = open(path)?;
+    let (w, h) = fit_within(image.dimensions(), size);
+    Ok(image.resize_exact(w, h, FilterType::Nearest).to_rgba8())
+}
+
+/// Turns `image` upright by its EXIF `orientation`, from 1 (already upright) to 8.
+fn orient(image: DynamicImage, orientation: Option<u16>) -> DynamicImage {
+    match orientation {
+        Some(2) => image.fliph(),
+        Some(3) => image.rotate180(),
+        Some(4) => image.flipv(),
+        Some(5) => image.rotate90().fliph(),
+        Some(6) => image.rotate90(),
+        Some(7) => image.rotate270().fliph(),
+        Some(8) => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// The EXIF orientation of a JPEG from the start of its `bytes`, if it has one.
+fn exif_orientation(bytes: &[u8]) -> Option<u16> {
+    if bytes.get(..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+    let mut i = 2;
+    loop {
+        let marker = bytes.get(i..i + 2)?;
+        // Metadata comes before the start of the scan
+        if marker[0] != 0xFF || marker[1] == 0xDA {
+            return None;
+        }
+        let len = usize::from(u16::from_be_bytes(
+            bytes.get(i + 2..i + 4)?.try_into().ok()?,
+        ));
+        let segment = bytes.get(i + 4..i + 2 + len)?;
+        if marker[1] == 0xE1 {
+            if let Some(tiff) = segment.strip_prefix(b"Exif\0\0") {
+                return tiff_orientation(tiff);
+            }
+        }
+        i += 2 + len;
+    }
+}
+
+/// The orientation tag in the first IFD of EXIF data.
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    const ORIENTATION: u16 = 0x0112;
+    let big_endian = match tiff.get(..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let u16_at = |i: usize| {
+        let bytes = tiff.get(i..i + 2)?.try_into().ok()?;
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let u32_at = |i: usize| {
+        let bytes = tiff.get(i..i + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+    let ifd = u32_at(4)? as usize;
+    // Each entry is a tag, a type, a count and a value that short values are stored in
+    (0..usize::from(u16_at(ifd)?))
+        .map(|n| ifd + 2 + n * 12)
+        .find(|&entry| u16_at(entry) == Some(ORIENTATION))
+        .and_then(|entry| u16_at(entry + 8))
+}
 
 /// Draw rounded corners on an image.
 pub fn round(image: &mut RgbaImage, radius: u32) {
@@ -85,3 +421,321 @@ pub fn round(image: &mut RgbaImage, radius: u32) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    fn image(i: u8) -> RgbaImage {
+        RgbaImage::from_pixel(16, 8, Rgba([i, 2, 3, 255]))
+    }
+
+    #[test]
+    fn test_cached_images_are_reused() {
+        let dir = temp_dir("disk-image-cache-reuse");
+        let mut cache = DiskImageCache::new(dir.clone(), 1024 * 1024);
+
+        let made = cache.get_or_insert_with(&("Game.png", 1), || Some(image(1)));
+        assert_eq!(made, Some(image(1)));
+
+        // Another process, with nothing in memory, reads what was written
+        let mut cache = DiskImageCache::new(dir.clone(), 1024 * 1024);
+        let cached = cache.get_or_insert_with(&("Game.png", 1), || panic!("made again"));
+        assert_eq!(cached, Some(image(1)));
+
+        // A different key, such as the source having changed since, is made again
+        let changed = cache.get_or_insert_with(&("Game.png", 2), || Some(image(2)));
+        assert_eq!(changed, Some(image(2)));
+
+        // A partly written file is ignored
+        let path = cache.path(&("Game.png", 2)).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        let remade = cache.get_or_insert_with(&("Game.png", 2), || Some(image(3)));
+        assert_eq!(remade, Some(image(3)));
+
+        // Images that can't be made aren't cached
+        assert_eq!(cache.get_or_insert_with(&"Missing.png", || None), None);
+        assert!(!cache.path(&"Missing.png").unwrap().exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = temp_dir("disk-image-cache-prune");
+        let bytes = (HEADER_LENGTH + 16 * 8 * 4) as u64;
+        let mut cache = DiskImageCache::new(dir.clone(), 10 * bytes);
+        for i in 0..4u8 {
+            cache.get_or_insert_with(&i, || Some(image(i)));
+        }
+        fs::write(dir.join("0123456789abcdef.tmp42"), [0; 16]).unwrap();
+
+        // Within the budget, only the half written image goes
+        cache.prune().unwrap();
+        assert_eq!(files(&dir).unwrap().len(), 4);
+
+        // Over a lowered budget, images go too
+        let mut cache = DiskImageCache::new(dir.clone(), 2 * bytes);
+        cache.prune().unwrap();
+        assert_eq!(files(&dir).unwrap().len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_budget() {
+        let dir = temp_dir("disk-image-cache-budget");
+        let bytes = (HEADER_LENGTH + 16 * 8 * 4) as u64;
+        // Room for ten images
+        let mut cache = DiskImageCache::new(dir.clone(), 10 * bytes);
+
+        let start = SystemTime::now() - Duration::from_secs(60);
+        for i in 0..10u8 {
+            cache.get_or_insert_with(&i, || Some(image(i)));
+            // The order files were written in, beyond what the file system's clock can tell
+            File::options()
+                .write(true)
+                .open(cache.path(&i).unwrap())
+                .unwrap()
+                .set_modified(start + Duration::from_secs(i.into()))
+                .unwrap();
+        }
+        assert_eq!(files(&dir).unwrap().len(), 10);
+
+        // Going over the budget removes the oldest images, down to three quarters of it
+        cache.get_or_insert_with(&10u8, || Some(image(10)));
+        let mut kept: Vec<u8> = (0..=10u8)
+            .filter(|i| cache.path(i).unwrap().exists())
+            .collect();
+        kept.sort_unstable();
+        assert_eq!(kept, vec![4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(cache.used, Some(7 * bytes));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Writes a PNG with an 8-bit palette, which decoders expand differently from true colour.
+    fn palettized_png(path: &Path, width: u32, height: u32, palette: &[[u8; 3]]) {
+        fn chunk(png: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+            png.extend((data.len() as u32).to_be_bytes());
+            let start = png.len();
+            png.extend(kind);
+            png.extend(data);
+            let crc = crc32fast::hash(&png[start..]);
+            png.extend(crc.to_be_bytes());
+        }
+
+        // Each row starts with its filter, then one palette index per pixel
+        let mut rows = Vec::new();
+        for y in 0..height {
+            rows.push(0);
+            rows.extend((0..width).map(|x| ((x + y) % palette.len() as u32) as u8));
+        }
+        // Deflate's stored blocks hold the rows uncompressed
+        let mut zlib = vec![0x78, 0x01];
+        let blocks: Vec<_> = rows.chunks(0xFFFF).collect();
+        for (i, block) in blocks.iter().enumerate() {
+            zlib.push((i + 1 == blocks.len()) as u8);
+            zlib.extend((block.len() as u16).to_le_bytes());
+            zlib.extend((!(block.len() as u16)).to_le_bytes());
+            zlib.extend(*block);
+        }
+        let (mut a, mut b) = (1u32, 0u32);
+        for byte in &rows {
+            a = (a + u32::from(*byte)) % 65521;
+            b = (b + a) % 65521;
+        }
+        zlib.extend(((b << 16) | a).to_be_bytes());
+
+        let mut header = Vec::new();
+        header.extend(width.to_be_bytes());
+        header.extend(height.to_be_bytes());
+        // 8 bits per index, palette colour, default compression, filtering and no interlacing
+        header.extend([8, 3, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut png, b"IHDR", &header);
+        chunk(&mut png, b"PLTE", &palette.concat());
+        chunk(&mut png, b"IDAT", &zlib);
+        chunk(&mut png, b"IEND", &[]);
+        fs::write(path, png).unwrap();
+    }
+
+    /// Writes a JPEG of `image` with an EXIF `orientation`, in `byte_order`.
+    fn jpeg_with_orientation(path: &Path, image: &RgbaImage, orientation: u16, byte_order: &[u8]) {
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 100)
+            .encode_image(&DynamicImage::ImageRgba8(image.clone()).to_rgb8())
+            .unwrap();
+
+        let big_endian = byte_order == b"MM";
+        let u16_bytes = |n: u16| {
+            if big_endian {
+                n.to_be_bytes()
+            } else {
+                n.to_le_bytes()
+            }
+        };
+        let u32_bytes = |n: u32| {
+            if big_endian {
+                n.to_be_bytes()
+            } else {
+                n.to_le_bytes()
+            }
+        };
+        let mut tiff = byte_order.to_vec();
+        tiff.extend(u16_bytes(42));
+        tiff.extend(u32_bytes(8));
+        // One entry in the first IFD: the orientation, a single short
+        tiff.extend(u16_bytes(1));
+        tiff.extend(u16_bytes(0x0112));
+        tiff.extend(u16_bytes(3));
+        tiff.extend(u32_bytes(1));
+        tiff.extend(u16_bytes(orientation));
+        tiff.extend([0, 0]);
+        tiff.extend(u32_bytes(0));
+
+        let mut app1 = vec![0xFF, 0xE1];
+        app1.extend(((tiff.len() + 8) as u16).to_be_bytes());
+        app1.extend(b"Exif\0\0");
+        app1.extend(tiff);
+        // Right after the start of image marker
+        jpeg.splice(2..2, app1);
+        fs::write(path, jpeg).unwrap();
+    }
+
+    fn is_close(pixel: Rgba<u8>, color: [u8; 3]) -> bool {
+        pixel.0[..3]
+            .iter()
+            .zip(color)
+            .all(|(a, b)| a.abs_diff(b) < 24)
+    }
+
+    #[test]
+    fn test_fit_within() {
+        // A 200x800 portrait scan is as tall as the box, not as wide
+        assert_eq!(fit_within((200, 800), (250, 360)), (90, 360));
+        assert_eq!(fit_within((800, 50), (250, 360)), (250, 15));
+        assert_eq!(fit_within((250, 360), (250, 360)), (250, 360));
+        // Small images are scaled up
+        assert_eq!(fit_within((25, 36), (250, 360)), (250, 360));
+        // Never less than a pixel
+        assert_eq!(fit_within((10000, 1), (250, 360)), (250, 1));
+        assert_eq!(fit_within((1, 10000), (250, 360)), (1, 360));
+        assert_eq!(fit_within((0, 0), (250, 360)), (250, 250));
+    }
+
+    #[test]
+    fn test_contain_extreme_aspect_ratios() {
+        let dir = temp_dir("disk-image-cache-contain");
+        for (size, expected) in [
+            ((200, 800), (90, 360)),
+            ((800, 50), (250, 15)),
+            ((2, 1000), (1, 360)),
+            ((3000, 4), (250, 1)),
+        ] {
+            let path = dir.join(format!("{}x{}.png", size.0, size.1));
+            RgbaImage::from_pixel(size.0, size.1, Rgba([200, 100, 50, 255]))
+                .save(&path)
+                .unwrap();
+            let image = contain(&path, (250, 360)).unwrap();
+            assert_eq!(image.dimensions(), expected, "{:?}", size);
+            assert_eq!(dimensions(&path).unwrap(), size);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_palettized_png() {
+        let dir = temp_dir("disk-image-cache-palette");
+        let path = dir.join("palette.png");
+        let palette = [[255, 0, 0], [0, 255, 0], [0, 0, 255]];
+        palettized_png(&path, 30, 60, &palette);
+
+        let image = open(&path).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (30, 60));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(1, 1).0, [0, 0, 255, 255]);
+
+        let image = contain(&path, (250, 360)).unwrap();
+        assert_eq!(image.dimensions(), (180, 360));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_exif_orientation() {
+        let dir = temp_dir("disk-image-cache-exif");
+        // Red on the left, blue on the right, as stored
+        let stored = RgbaImage::from_fn(64, 32, |x, _| {
+            if x < 32 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        });
+        const RED: [u8; 3] = [255, 0, 0];
+        const BLUE: [u8; 3] = [0, 0, 255];
+
+        for (orientation, byte_order, size, top_left, bottom_right) in [
+            (1, b"II", (64, 32), RED, BLUE),
+            (2, b"MM", (64, 32), BLUE, RED),
+            (3, b"II", (64, 32), BLUE, RED),
+            // Turned clockwise, so the left side is at the top
+            (6, b"MM", (32, 64), RED, BLUE),
+            (6, b"II", (32, 64), RED, BLUE),
+            (8, b"II", (32, 64), BLUE, RED),
+        ] {
+            let path = dir.join(format!("{}.jpg", orientation));
+            jpeg_with_orientation(&path, &stored, orientation, byte_order);
+            assert_eq!(dimensions(&path).unwrap(), size, "{}", orientation);
+
+            let image = open(&path).unwrap().to_rgba8();
+            assert_eq!(image.dimensions(), size, "{}", orientation);
+            let (w, h) = size;
+            assert!(
+                is_close(*image.get_pixel(4, 4), top_left),
+                "{}",
+                orientation
+            );
+            assert!(
+                is_close(*image.get_pixel(w - 5, h - 5), bottom_right),
+                "{}",
+                orientation
+            );
+
+            // Turned before it's fitted to the box
+            let image = contain(&path, (250, 360)).unwrap();
+            let expected = if size.0 > size.1 {
+                (250, 125)
+            } else {
+                (180, 360)
+            };
+            assert_eq!(image.dimensions(), expected, "{}", orientation);
+        }
+
+        // Without EXIF data, or not a JPEG at all
+        assert_eq!(exif_orientation(&[0xFF, 0xD8, 0xFF, 0xDA, 0, 2]), None);
+        assert_eq!(exif_orientation(b"\x89PNG"), None);
+        assert_eq!(exif_orientation(&[0xFF, 0xD8, 0xFF, 0xE1, 0]), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut cache = DiskImageCache::disabled();
+        let mut made = 0;
+        for _ in 0..2 {
+            cache.get_or_insert_with(&"Game.png", || {
+                made += 1;
+                Some(image(1))
+            });
+        }
+        assert_eq!(made, 2);
+    }
+}
@@ -1,10 +1,17 @@
+pub mod assets;
+pub mod atlas;
+pub mod cache;
 pub mod color;
 pub mod font;
+#[cfg(test)]
+pub(crate) mod golden;
+pub mod greyscale;
 pub mod image;
 pub mod settings;
 
 use anyhow::Result;
 
+use ::image::RgbImage;
 use embedded_graphics::prelude::*;
 
 use crate::display::color::Color;
@@ -24,4 +31,7 @@ pub trait Display:
 
     fn save(&mut self) -> Result<()>;
     fn load(&mut self, area: Rect) -> Result<()>;
+
+    /// Copies what is currently drawn into an image.
+    fn capture(&self) -> Result<RgbImage>;
 }
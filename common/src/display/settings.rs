@@ -8,6 +8,7 @@ use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 
 use crate::constants::ALLIUM_DISPLAY_SETTINGS;
+use crate::retry::RetryPolicy;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DisplaySettings {
@@ -18,6 +19,11 @@ pub struct DisplaySettings {
     pub r: u8,
     pub g: u8,
     pub b: u8,
+    #[serde(default = "DisplaySettings::default_boot_splash")]
+    pub boot_splash: bool,
+    /// How long to keep trying to open the display while it's busy at startup.
+    #[serde(default)]
+    pub open_retry: RetryPolicy,
 }
 
 impl DisplaySettings {
@@ -54,6 +60,10 @@ impl DisplaySettings {
         Ok(())
     }
 
+    fn default_boot_splash() -> bool {
+        true
+    }
+
     pub fn load() -> Result<Self> {
         if ALLIUM_DISPLAY_SETTINGS.exists() {
             debug!("found state, loading from file");
@@ -85,6 +95,8 @@ impl Default for DisplaySettings {
             r: 50,
             g: 50,
             b: 50,
+            boot_splash: Self::default_boot_splash(),
+            open_retry: RetryPolicy::default(),
         }
     }
 }
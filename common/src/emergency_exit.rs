@@ -0,0 +1,104 @@
+use std::fs::{self, File};
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::ALLIUM_EMERGENCY_EXIT_SETTINGS;
+
+/// Shortest hold that is accepted, so that the chord can't be triggered by accident.
+const MIN_HOLD_SECS: u64 = 2;
+/// Longest hold that is accepted.
+const MAX_HOLD_SECS: u64 = 10;
+
+/// Holding Menu and Power together force quits the running program, for when a core has frozen
+/// and no other button works.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmergencyExitSettings {
+    #[serde(default = "EmergencyExitSettings::default_enabled")]
+    pub enabled: bool,
+    /// How long the chord has to be held, in seconds.
+    #[serde(default = "EmergencyExitSettings::default_hold_secs")]
+    pub hold_secs: u64,
+}
+
+impl Default for EmergencyExitSettings {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            hold_secs: Self::default_hold_secs(),
+        }
+    }
+}
+
+impl EmergencyExitSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_hold_secs() -> u64 {
+        3
+    }
+
+    /// How long the chord has to be held, or None if the emergency exit is disabled.
+    pub fn hold_duration(&self) -> Option<Duration> {
+        self.enabled
+            .then(|| Duration::from_secs(self.hold_secs.clamp(MIN_HOLD_SECS, MAX_HOLD_SECS)))
+    }
+
+    pub fn load() -> Result<Self> {
+        if ALLIUM_EMERGENCY_EXIT_SETTINGS.exists() {
+            debug!("found state, loading from file");
+            let file = File::open(ALLIUM_EMERGENCY_EXIT_SETTINGS.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(json);
+            }
+            warn!("failed to read emergency exit settings, removing");
+            fs::remove_file(ALLIUM_EMERGENCY_EXIT_SETTINGS.as_path())?;
+        }
+        Ok(Self::new())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_EMERGENCY_EXIT_SETTINGS.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hold_duration() {
+        let mut settings = EmergencyExitSettings::new();
+        assert_eq!(settings.hold_duration(), Some(Duration::from_secs(3)));
+
+        settings.hold_secs = 0;
+        assert_eq!(settings.hold_duration(), Some(Duration::from_secs(2)));
+
+        settings.hold_secs = 60;
+        assert_eq!(settings.hold_duration(), Some(Duration::from_secs(10)));
+
+        settings.enabled = false;
+        assert_eq!(settings.hold_duration(), None);
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let settings: EmergencyExitSettings = serde_json::from_str(r#"{"enabled":false}"#).unwrap();
+        assert_eq!(
+            settings,
+            EmergencyExitSettings {
+                enabled: false,
+                hold_secs: 3,
+            }
+        );
+    }
+}
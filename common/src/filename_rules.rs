@@ -0,0 +1,94 @@
+//! Rules for how file names are listed, kept in `state/filename-rules.json`.
+//!
+//! Names are sorted in natural order by default, so that "Disc 2" comes before "Disc 10". Users
+//! who relied on the old order can switch back to comparing names character by character.
+//!
+//! Games that tie in a sort by play statistics are ordered by name, or by when they were added.
+
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering as AtomicOrdering};
+
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::ALLIUM_FILENAME_RULES;
+use crate::natural_order::natural_cmp;
+use crate::sort_order::TieBreak;
+
+/// The active rules. Entries are sorted through `Ord`, which can't be passed the rules, so they are
+/// kept here. Everything else is passed the rules it sorts by.
+static NATURAL_ORDER: AtomicBool = AtomicBool::new(true);
+static TIE_BREAK: AtomicU8 = AtomicU8::new(TieBreak::Name as u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilenameRules {
+    /// Whether numbers in names are sorted by their value, ignoring case, instead of strictly
+    /// lexicographically.
+    pub natural_order: bool,
+    /// How games that tie in a sort by last played or most played are ordered.
+    pub tie_break: TieBreak,
+}
+
+impl Default for FilenameRules {
+    fn default() -> Self {
+        Self {
+            natural_order: true,
+            tie_break: TieBreak::default(),
+        }
+    }
+}
+
+impl FilenameRules {
+    pub fn load() -> Result<Self> {
+        if ALLIUM_FILENAME_RULES.exists() {
+            debug!("found filename rules, loading from file");
+            let file = File::open(ALLIUM_FILENAME_RULES.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(json);
+            }
+            warn!("failed to read filename rules, removing");
+            fs::remove_file(ALLIUM_FILENAME_RULES.as_path())?;
+        }
+        Ok(Self::default())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_FILENAME_RULES.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+
+    /// Makes these the rules that entries are sorted by.
+    pub fn apply(&self) {
+        NATURAL_ORDER.store(self.natural_order, AtomicOrdering::Relaxed);
+        TIE_BREAK.store(self.tie_break as u8, AtomicOrdering::Relaxed);
+    }
+
+    /// The rules that entries are sorted by.
+    pub fn active() -> Self {
+        Self {
+            natural_order: NATURAL_ORDER.load(AtomicOrdering::Relaxed),
+            tie_break: match TIE_BREAK.load(AtomicOrdering::Relaxed) {
+                x if x == TieBreak::RecentlyAdded as u8 => TieBreak::RecentlyAdded,
+                _ => TieBreak::Name,
+            },
+        }
+    }
+
+    /// Compares two names by these rules.
+    pub fn compare_names(&self, a: &str, b: &str) -> Ordering {
+        if self.natural_order {
+            natural_cmp(a, b)
+        } else {
+            a.cmp(b)
+        }
+    }
+}
+
+/// Compares two names by the active rules.
+pub fn compare_names(a: &str, b: &str) -> Ordering {
+    FilenameRules::active().compare_names(a, b)
+}
@@ -0,0 +1,210 @@
+//! Recognising a game file after it was moved, so that recently played games that point at the
+//! old location can be relinked.
+//!
+//! A fingerprint is the file's size and the CRC of its first 64 KiB, stored in the database when
+//! the game is first launched. Files beyond `HASH_MAX_SIZE` are only fingerprinted by size, and
+//! must also keep their file name to match.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::{debug, warn};
+
+use crate::database::Database;
+
+/// Bytes from the start of a file that the CRC covers.
+const HASH_LENGTH: u64 = 64 * 1024;
+
+/// Files larger than this, such as disc images, are matched by size and name instead of a CRC.
+pub const HASH_MAX_SIZE: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    pub size: u64,
+    /// CRC of the first `HASH_LENGTH` bytes, or `None` if the file is too large to hash.
+    pub crc: Option<u32>,
+}
+
+impl Fingerprint {
+    pub fn of(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        let crc = if size > HASH_MAX_SIZE {
+            None
+        } else {
+            let mut buf = Vec::with_capacity(HASH_LENGTH.min(size) as usize);
+            file.take(HASH_LENGTH).read_to_end(&mut buf)?;
+            Some(crc32fast::hash(&buf))
+        };
+        Ok(Self { size, crc })
+    }
+
+    /// Whether the file at `candidate` is likely the file that had this fingerprint at `original`.
+    fn matches(&self, original: &Path, candidate: &Path) -> Result<bool> {
+        if candidate.metadata()?.len() != self.size {
+            return Ok(false);
+        }
+        Ok(match self.crc {
+            Some(crc) => Fingerprint::of(candidate)?.crc == Some(crc),
+            None => original.file_name() == candidate.file_name(),
+        })
+    }
+}
+
+/// Finds where the game that was at `path` has moved to, by looking for games in the database
+/// with the same fingerprint. Candidates with the same file name come first. Empty if the game
+/// has no fingerprint, or nothing matches.
+pub fn find_moved(database: &Database, path: &Path) -> Result<Vec<PathBuf>> {
+    let Some(fingerprint) = database.fingerprint(path)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut candidates: Vec<_> = database
+        .select_all_games()?
+        .into_iter()
+        .map(|game| game.path)
+        .filter(|candidate| candidate != path && candidate.is_file())
+        .filter(|candidate| match fingerprint.matches(path, candidate) {
+            Ok(matches) => matches,
+            Err(e) => {
+                warn!("failed to fingerprint {}: {}", candidate.display(), e);
+                false
+            }
+        })
+        .collect();
+    candidates
+        .sort_by_key(|candidate| (candidate.file_name() != path.file_name(), candidate.clone()));
+    debug!(
+        "found {} candidates for moved game {}",
+        candidates.len(),
+        path.display()
+    );
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::database::NewGame;
+    use crate::test_utils::{temp_dir, write_game};
+
+    struct Fixture {
+        dir: PathBuf,
+        database: Database,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let dir = temp_dir(&format!("fingerprint-{}", name));
+            Self {
+                dir,
+                database: Database::in_memory().unwrap(),
+            }
+        }
+
+        /// Writes a game file and indexes it.
+        fn game(&self, path: &str, contents: &[u8]) -> PathBuf {
+            write_game(&self.database, &self.dir, path, contents)
+        }
+
+        /// Launches the game at `path` and moves it to `to`, without indexing the new location.
+        fn launch_and_move(&self, path: &Path, to: &str) -> PathBuf {
+            self.database
+                .increment_play_count("game", path, None)
+                .unwrap();
+            self.database
+                .set_fingerprint(path, Fingerprint::of(path).unwrap())
+                .unwrap();
+            let to = self.dir.join(to);
+            fs::create_dir_all(to.parent().unwrap()).unwrap();
+            fs::rename(path, &to).unwrap();
+            to
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let fixture = Fixture::new("fingerprint");
+        let small = fixture.game("small.gb", b"small");
+        assert_eq!(
+            Fingerprint::of(&small).unwrap(),
+            Fingerprint {
+                size: 5,
+                crc: Some(crc32fast::hash(b"small")),
+            }
+        );
+
+        // Only the start of the file is hashed
+        let mut contents = vec![7; HASH_LENGTH as usize];
+        let a = fixture.game("a.gba", &contents);
+        contents.push(1);
+        let b = fixture.game("b.gba", &contents);
+        let (a, b) = (Fingerprint::of(&a).unwrap(), Fingerprint::of(&b).unwrap());
+        assert_eq!(a.crc, b.crc);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_multiple_candidates() {
+        let fixture = Fixture::new("multiple");
+        let game = fixture.game("Roms/GBA/foo.gba", b"foo rom");
+        fixture.game("Roms/GBA/bar.gba", b"bar rom");
+        let moved = fixture.launch_and_move(&game, "Roms/GBA/sorted/foo.gba");
+        fixture
+            .database
+            .update_games(&[NewGame {
+                name: "foo".to_string(),
+                path: moved.clone(),
+                image: None,
+                core: None,
+            }])
+            .unwrap();
+        // A copy under another name also matches, unlike bar.gba of the same size
+        let copy = fixture.game("Roms/GBA/foo (copy).gba", b"foo rom");
+
+        assert_eq!(
+            find_moved(&fixture.database, &game).unwrap(),
+            vec![moved.clone(), copy]
+        );
+
+        fixture.database.set_completed(&game, true).unwrap();
+        fixture.database.relink_game(&game, &moved).unwrap();
+        assert!(fixture.database.is_completed(&moved).unwrap());
+        let relinked = fixture
+            .database
+            .select_game(&moved.display().to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(relinked.play_count, 1);
+        assert!(fixture
+            .database
+            .select_game(&game.display().to_string())
+            .unwrap()
+            .is_none());
+        assert!(fixture.database.fingerprint(&moved).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_no_match() {
+        let fixture = Fixture::new("no-match");
+        let game = fixture.game("Roms/GB/tetris.gb", b"tetris");
+        fixture.game("Roms/GB/other.gb", b"other!");
+
+        // Games that were never launched have no fingerprint to search with
+        assert!(find_moved(&fixture.database, &game).unwrap().is_empty());
+
+        // The moved file wasn't indexed at its new location
+        fixture.launch_and_move(&game, "Roms/GB/puzzle/tetris.gb");
+        assert!(find_moved(&fixture.database, &game).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,25 @@
+//! Values formatted for display the same way in every language.
+
+use chrono::Duration;
+
+/// Play time in hours and minutes, such as "2h 05m". Empty if it's under a minute.
+pub fn format_play_time(play_time: Duration) -> String {
+    let minutes = play_time.num_minutes();
+    match minutes {
+        0 => String::new(),
+        1..=59 => format!("{}m", minutes),
+        _ => format!("{}h {:02}m", minutes / 60, minutes % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_play_time() {
+        assert_eq!(format_play_time(Duration::seconds(30)), "");
+        assert_eq!(format_play_time(Duration::minutes(5)), "5m");
+        assert_eq!(format_play_time(Duration::minutes(125)), "2h 05m");
+    }
+}
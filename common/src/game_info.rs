@@ -6,9 +6,39 @@ use std::{
 
 use anyhow::Result;
 use chrono::{DateTime, Duration, Utc};
+use log::warn;
 use serde::{Deserialize, Serialize};
 
-use crate::constants::{ALLIUM_GAMES_DIR, ALLIUM_GAME_INFO};
+use crate::constants::{
+    ALLIUM_GAMES_DIR, ALLIUM_GAME_INFO, ALLIUM_LAUNCHER, ALLIUM_SWITCH_REQUEST,
+};
+use crate::playlist;
+use crate::splash::ALLIUMD_PID_ENV;
+
+#[cfg(unix)]
+use {
+    nix::sys::signal::{kill, Signal},
+    nix::unistd::Pid,
+};
+
+/// Environment variable with the process ID of alliumd's main process, set for the ingame menu.
+pub const ALLIUM_MAIN_PID_ENV: &str = "ALLIUM_MAIN_PID";
+
+/// Exit code with which the ingame menu asks alliumd to terminate the main process, for when the
+/// game can't be quit through RetroArch or its game info.
+pub const MENU_EXIT_TERMINATE_MAIN: i32 = 3;
+
+/// Exit code with which the ingame menu asks alliumd to quit the game and start the one in the
+/// saved `SwitchRequest` instead.
+pub const MENU_EXIT_SWITCH_GAME: i32 = 4;
+
+/// Exit code with which the ingame menu asks alliumd to suspend the game in the background and
+/// bring up the launcher.
+pub const MENU_EXIT_SUSPEND_GAME: i32 = 5;
+
+/// Exit code with which the ingame menu tells alliumd that it asked the game to quit, so that the
+/// game exiting isn't taken for a failed launch.
+pub const MENU_EXIT_QUIT_GAME: i32 = 6;
 
 #[derive(Debug, Serialize, Deserialize)]
 /// Information about a game. Used to restore a game after a restart, and to calculate playtime.
@@ -29,6 +59,28 @@ pub struct GameInfo {
     pub guide: Option<PathBuf>,
     /// Start time. Used to measure playtime.
     pub start_time: DateTime<Utc>,
+    /// Time spent paused since the start time, in milliseconds. Excluded from playtime.
+    #[serde(default)]
+    pub paused_millis: i64,
+    /// When the game was paused, if it is currently paused.
+    #[serde(default)]
+    pub paused_at: Option<DateTime<Utc>>,
+    /// Process ID of the running game, if known.
+    #[serde(default)]
+    pub pid: Option<u32>,
+    /// Folder the game is run from, if not the launcher's.
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+    /// RetroArch state slot loaded when the game starts, if one was picked to launch from.
+    #[serde(default)]
+    pub state_slot: Option<i8>,
+    /// Playlist listing the game's discs, if it was launched from an `.m3u`.
+    #[serde(default)]
+    pub playlist: Option<PathBuf>,
+    /// Disc of the playlist that is inserted, from 0. Kept so that the disc switched to from the
+    /// ingame menu is still known after the game is suspended and resumed.
+    #[serde(default)]
+    pub disc: usize,
 }
 
 impl Default for GameInfo {
@@ -42,6 +94,13 @@ impl Default for GameInfo {
             image: None,
             guide: None,
             start_time: Utc::now(),
+            paused_millis: 0,
+            paused_at: None,
+            pid: None,
+            working_dir: None,
+            state_slot: None,
+            playlist: None,
+            disc: 0,
         }
     }
 }
@@ -57,6 +116,7 @@ impl GameInfo {
         has_menu: bool,
     ) -> Self {
         let guide = find_guide(&path);
+        let playlist = playlist::is_playlist(&path).then(|| path.clone());
 
         Self {
             name,
@@ -67,6 +127,13 @@ impl GameInfo {
             image,
             guide,
             start_time: Utc::now(),
+            paused_millis: 0,
+            paused_at: None,
+            pid: None,
+            working_dir: None,
+            state_slot: None,
+            playlist,
+            disc: 0,
         }
     }
 
@@ -103,12 +170,192 @@ impl GameInfo {
     pub fn command(self) -> Command {
         let mut command = Command::new(self.command);
         command.args(self.args);
+        if let Some(slot) = self.state_slot {
+            command.arg(format!("--entryslot={}", slot));
+        }
+        if let Some(dir) = self.working_dir {
+            command.current_dir(dir);
+        }
         command
     }
 
-    /// How long the game has been running.
+    /// Restarts playtime measurement from now, e.g. when the game is resumed or the clock changes.
+    pub fn reset_session(&mut self) {
+        let now = Utc::now();
+        self.start_time = now;
+        self.paused_millis = 0;
+        if self.paused_at.is_some() {
+            self.paused_at = Some(now);
+        }
+    }
+
+    /// Marks the game as paused. Does nothing if it is already paused.
+    pub fn pause(&mut self) {
+        self.pause_at(Utc::now());
+    }
+
+    /// Marks the game as running again, adding the time since it was paused to the paused time.
+    pub fn resume(&mut self) {
+        self.resume_at(Utc::now());
+    }
+
+    fn pause_at(&mut self, now: DateTime<Utc>) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(now);
+        }
+    }
+
+    fn resume_at(&mut self, now: DateTime<Utc>) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_millis += now
+                .signed_duration_since(paused_at)
+                .max(Duration::zero())
+                .num_milliseconds();
+        }
+    }
+
+    /// Whether the game process is still alive, e.g. while it is suspended in the background.
+    pub fn is_running(&self) -> bool {
+        #[cfg(unix)]
+        return self
+            .pid
+            .is_some_and(|pid| kill(Pid::from_raw(pid as i32), None).is_ok());
+
+        #[cfg(not(unix))]
+        return false;
+    }
+
+    /// Asks alliumd to bring the suspended game back to the foreground.
+    pub fn request_resume() {
+        #[cfg(unix)]
+        {
+            let Some(pid) = std::env::var(ALLIUMD_PID_ENV)
+                .ok()
+                .and_then(|pid| pid.parse().ok())
+            else {
+                warn!("alliumd pid is unknown, can't resume game");
+                return;
+            };
+            if let Err(e) = kill(Pid::from_raw(pid), Signal::SIGUSR2) {
+                warn!("failed to ask alliumd to resume game: {}", e);
+            }
+        }
+    }
+
+    /// Asks the suspended game to quit. alliumd records its play time once it has exited.
+    pub fn request_quit(&self) -> Result<()> {
+        #[cfg(unix)]
+        if let Some(pid) = self.pid {
+            let pid = Pid::from_raw(pid as i32);
+            // A stopped process only handles SIGTERM once it is continued
+            kill(pid, Signal::SIGCONT)?;
+            kill(pid, Signal::SIGTERM)?;
+        }
+        Ok(())
+    }
+
+    /// How long the game has been running, excluding time spent paused.
     pub fn play_time(&self) -> Duration {
-        Utc::now().signed_duration_since(self.start_time)
+        session_duration(
+            self.start_time,
+            Utc::now(),
+            Duration::milliseconds(self.paused_millis),
+            self.paused_at,
+        )
+    }
+
+    /// RetroArch core the game is played with, if it's played in RetroArch.
+    pub fn core(&self) -> Option<&str> {
+        self.args
+            .first()
+            .filter(|_| self.has_menu)
+            .map(String::as_str)
+    }
+}
+
+/// Whether a game is running, from the game info file and the process that should be running it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    /// No game is running.
+    NotRunning,
+    /// A game is running and its game info is up to date.
+    Running,
+    /// Something other than the launcher is running, but there is no game info for it.
+    Unknown,
+    /// The game info is left over from a game that is no longer running.
+    Stale,
+}
+
+impl GameStatus {
+    /// Detects the status from the loaded game info, if any. `main_pid` is alliumd's main process,
+    /// which is the game if it is no longer the launcher.
+    pub fn detect(game_info: Option<&GameInfo>, main_pid: Option<u32>) -> Self {
+        match game_info {
+            // Game info saved before process IDs were recorded can't be checked
+            Some(GameInfo { pid: None, .. }) => Self::Running,
+            Some(game_info) if game_info.is_running() => Self::Running,
+            Some(_) => Self::Stale,
+            None if main_pid.is_some_and(is_game_process) => Self::Unknown,
+            None => Self::NotRunning,
+        }
+    }
+
+    /// Whether a game is running, with or without game info.
+    pub fn is_ingame(self) -> bool {
+        matches!(self, Self::Running | Self::Unknown)
+    }
+}
+
+/// Whether the process is alive and no longer the launcher. The launcher execs games, so its
+/// process becomes the game.
+fn is_game_process(pid: u32) -> bool {
+    match fs::read_link(format!("/proc/{}/exe", pid)) {
+        Ok(exe) => exe.file_name() != ALLIUM_LAUNCHER.file_name(),
+        Err(_) => false,
+    }
+}
+
+/// Length of a session from `start` to `end`, excluding the time spent `paused` and the pause in
+/// progress since `paused_at`, if any. Never negative, even if the clock went backwards.
+pub fn session_duration(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    paused: Duration,
+    paused_at: Option<DateTime<Utc>>,
+) -> Duration {
+    let mut duration = end.signed_duration_since(start) - paused;
+    if let Some(paused_at) = paused_at {
+        duration -= end.signed_duration_since(paused_at).max(Duration::zero());
+    }
+    duration.max(Duration::zero())
+}
+
+/// A game picked in the ingame menu to switch to, passed on to alliumd through a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwitchRequest {
+    /// Path to the game rom to switch to.
+    pub path: PathBuf,
+    /// Save the current game's state to the auto slot before quitting it.
+    pub save_state: bool,
+}
+
+impl SwitchRequest {
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_SWITCH_REQUEST.as_path())?;
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+
+    /// Loads and removes the request, so that it is acted on only once.
+    pub fn take() -> Result<Option<Self>> {
+        if !ALLIUM_SWITCH_REQUEST.exists() {
+            return Ok(None);
+        }
+        let request = fs::read_to_string(ALLIUM_SWITCH_REQUEST.as_path())?;
+        fs::remove_file(ALLIUM_SWITCH_REQUEST.as_path())?;
+        Ok(serde_json::from_str(&request)
+            .map_err(|e| warn!("invalid switch request: {}", e))
+            .ok())
     }
 }
 
@@ -136,3 +383,240 @@ pub fn find_guide(path: &Path) -> Option<PathBuf> {
     }
     guide
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    fn game_info(start: DateTime<Utc>) -> GameInfo {
+        GameInfo {
+            start_time: start,
+            ..Default::default()
+        }
+    }
+
+    fn play_time(game_info: &GameInfo, end: DateTime<Utc>) -> Duration {
+        session_duration(
+            game_info.start_time,
+            end,
+            Duration::milliseconds(game_info.paused_millis),
+            game_info.paused_at,
+        )
+    }
+
+    #[test]
+    fn test_session_without_pauses() {
+        let game_info = game_info(at(0));
+        assert_eq!(play_time(&game_info, at(30)), Duration::minutes(30));
+    }
+
+    #[test]
+    fn test_multiple_pause_resume_cycles() {
+        let mut game_info = game_info(at(0));
+        game_info.pause_at(at(10));
+        game_info.resume_at(at(20));
+        game_info.pause_at(at(25));
+        game_info.resume_at(at(30));
+        assert_eq!(play_time(&game_info, at(40)), Duration::minutes(25));
+    }
+
+    #[test]
+    fn test_repeated_pause_and_resume_are_ignored() {
+        let mut game_info = game_info(at(0));
+        game_info.pause_at(at(10));
+        game_info.pause_at(at(15));
+        game_info.resume_at(at(20));
+        game_info.resume_at(at(25));
+        assert_eq!(play_time(&game_info, at(30)), Duration::minutes(20));
+    }
+
+    #[test]
+    fn test_pause_in_progress_is_excluded() {
+        // e.g. the menu was killed or the device shut down while paused
+        let mut game_info = game_info(at(0));
+        game_info.pause_at(at(10));
+        assert_eq!(play_time(&game_info, at(600)), Duration::minutes(10));
+    }
+
+    #[test]
+    fn test_pause_across_suspend() {
+        // Wall-clock time keeps running while suspended, so it all counts as paused
+        let mut game_info = game_info(at(0));
+        game_info.pause_at(at(5));
+        game_info.resume_at(at(8 * 60));
+        assert_eq!(play_time(&game_info, at(8 * 60 + 5)), Duration::minutes(10));
+    }
+
+    #[test]
+    fn test_clock_going_backwards() {
+        let mut game_info = game_info(at(0));
+        game_info.pause_at(at(10));
+        game_info.resume_at(at(5));
+        assert_eq!(game_info.paused_millis, 0);
+        assert_eq!(play_time(&game_info, at(-5)), Duration::zero());
+    }
+
+    #[test]
+    fn test_paused_time_survives_serialization() -> Result<()> {
+        let mut game_info = game_info(at(0));
+        game_info.pause_at(at(10));
+        game_info.resume_at(at(20));
+        game_info.pause_at(at(30));
+
+        let game_info: GameInfo = serde_json::from_str(&serde_json::to_string(&game_info)?)?;
+        assert_eq!(play_time(&game_info, at(40)), Duration::minutes(20));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_running() -> Result<()> {
+        let mut game_info = GameInfo::default();
+        assert!(!game_info.is_running());
+
+        let mut child = Command::new("sleep").arg("10").spawn()?;
+        game_info.pid = Some(child.id());
+        assert!(game_info.is_running());
+
+        game_info.request_quit()?;
+        child.wait()?;
+        assert!(!game_info.is_running());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_runs_in_working_dir() -> Result<()> {
+        let dir = temp_dir("game-info-working-dir").canonicalize()?;
+        let game_info = GameInfo {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "pwd".to_string()],
+            working_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+
+        let output = game_info.command().output()?;
+        assert_eq!(
+            String::from_utf8(output.stdout)?.trim_end(),
+            dir.display().to_string()
+        );
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_playlist_of_multi_disc_game() -> Result<()> {
+        let game_info = |path: &str| {
+            GameInfo::new(
+                "Game".to_string(),
+                PathBuf::from(path),
+                None,
+                "launch.sh".to_string(),
+                Vec::new(),
+                true,
+            )
+        };
+        assert_eq!(
+            game_info("/Roms/PS/Game.m3u").playlist,
+            Some(PathBuf::from("/Roms/PS/Game.m3u"))
+        );
+        assert_eq!(game_info("/Roms/PS/Game.cue").playlist, None);
+
+        // Game info written before discs were tracked
+        let game_info: GameInfo = serde_json::from_str(
+            r#"{
+                "name": "Game",
+                "path": "/Roms/PS/Game.m3u",
+                "command": "launch.sh",
+                "args": [],
+                "has_menu": true,
+                "image": null,
+                "guide": null,
+                "start_time": "2024-01-01T00:00:00Z"
+            }"#,
+        )?;
+        assert_eq!(game_info.playlist, None);
+        assert_eq!(game_info.disc, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_loads_state_slot() {
+        let args = |state_slot| -> Vec<String> {
+            let game_info = GameInfo {
+                command: "launch.sh".to_string(),
+                args: vec!["gambatte".to_string(), "Tetris.gb".to_string()],
+                state_slot,
+                ..Default::default()
+            };
+            game_info
+                .command()
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect()
+        };
+
+        assert_eq!(args(None), ["gambatte", "Tetris.gb"]);
+        assert_eq!(args(Some(2)), ["gambatte", "Tetris.gb", "--entryslot=2"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_game_status() -> Result<()> {
+        let mut child = Command::new("sleep").arg("10").spawn()?;
+        let alive = Some(child.id());
+        let mut exited = Command::new("true").spawn()?;
+        exited.wait()?;
+        let dead = Some(exited.id());
+
+        let with_pid = |pid| GameInfo {
+            pid,
+            ..Default::default()
+        };
+
+        // Game info present, process alive
+        assert_eq!(
+            GameStatus::detect(Some(&with_pid(alive)), alive),
+            GameStatus::Running
+        );
+        // Game info present, process dead
+        assert_eq!(
+            GameStatus::detect(Some(&with_pid(dead)), dead),
+            GameStatus::Stale
+        );
+        // Game info missing, process alive
+        assert_eq!(GameStatus::detect(None, alive), GameStatus::Unknown);
+        // Game info missing, process dead
+        assert_eq!(GameStatus::detect(None, dead), GameStatus::NotRunning);
+        assert_eq!(GameStatus::detect(None, None), GameStatus::NotRunning);
+
+        assert!(GameStatus::Running.is_ingame());
+        assert!(GameStatus::Unknown.is_ingame());
+        assert!(!GameStatus::Stale.is_ingame());
+        assert!(!GameStatus::NotRunning.is_ingame());
+
+        child.kill()?;
+        child.wait()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_switch_request_is_taken_once() -> Result<()> {
+        let request = SwitchRequest {
+            path: PathBuf::from("/mnt/SDCARD/Roms/GBA/Game.gba"),
+            save_state: true,
+        };
+        request.save()?;
+
+        assert_eq!(SwitchRequest::take()?, Some(request));
+        assert_eq!(SwitchRequest::take()?, None);
+        Ok(())
+    }
+}
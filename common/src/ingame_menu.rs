@@ -0,0 +1,271 @@
+use std::fs::{self, File};
+use std::str::FromStr;
+
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, EnumString, IntoEnumIterator, IntoStaticStr};
+
+use crate::constants::ALLIUM_INGAME_MENU_SETTINGS;
+use crate::locale::Locale;
+
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter, EnumString, IntoStaticStr,
+)]
+pub enum MenuEntry {
+    Continue,
+    Save,
+    Load,
+    SaveStates,
+    ChangeDisc,
+    Reset,
+    Guide,
+    Notes,
+    Cheats,
+    Screenshot,
+    Settings,
+    Launcher,
+    SwitchGame,
+    Quit,
+}
+
+impl MenuEntry {
+    pub fn as_str(&self, locale: &Locale) -> String {
+        match self {
+            MenuEntry::Continue => locale.t("ingame-menu-continue"),
+            MenuEntry::Save => locale.t("ingame-menu-save"),
+            MenuEntry::Load => locale.t("ingame-menu-load"),
+            MenuEntry::SaveStates => locale.t("ingame-menu-save-states"),
+            MenuEntry::ChangeDisc => locale.t("ingame-menu-change-disc"),
+            MenuEntry::Reset => locale.t("ingame-menu-reset"),
+            MenuEntry::Guide => locale.t("ingame-menu-guide"),
+            MenuEntry::Notes => locale.t("ingame-menu-notes"),
+            MenuEntry::Cheats => locale.t("ingame-menu-cheats"),
+            MenuEntry::Screenshot => locale.t("ingame-menu-screenshot"),
+            MenuEntry::Settings => locale.t("ingame-menu-settings"),
+            MenuEntry::Launcher => locale.t("ingame-menu-launcher"),
+            MenuEntry::SwitchGame => locale.t("ingame-menu-switch-game"),
+            MenuEntry::Quit => locale.t("ingame-menu-quit"),
+        }
+    }
+
+    /// Required entries can't be hidden and always stay at the top or bottom of the menu.
+    pub fn is_required(&self) -> bool {
+        matches!(self, MenuEntry::Continue | MenuEntry::Quit)
+    }
+}
+
+/// Order and visibility of the ingame menu entries. Entries are stored by name so that
+/// settings written by a newer version with unknown entries can still be read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngameMenuSettings {
+    #[serde(default)]
+    pub order: Vec<String>,
+    #[serde(default)]
+    pub hidden: Vec<String>,
+    /// Back up save states before they are overwritten.
+    #[serde(default)]
+    pub backup_states: bool,
+    /// Save the game's state to the auto slot before switching to another game.
+    #[serde(default = "IngameMenuSettings::default_save_on_switch")]
+    pub save_on_switch: bool,
+}
+
+impl Default for IngameMenuSettings {
+    fn default() -> Self {
+        Self {
+            order: Vec::new(),
+            hidden: Vec::new(),
+            backup_states: false,
+            save_on_switch: Self::default_save_on_switch(),
+        }
+    }
+}
+
+impl IngameMenuSettings {
+    fn default_save_on_switch() -> bool {
+        true
+    }
+
+    pub fn new() -> Self {
+        Self::from_entries(&MenuEntry::iter().map(|e| (e, true)).collect::<Vec<_>>())
+    }
+
+    pub fn load() -> Result<Self> {
+        if ALLIUM_INGAME_MENU_SETTINGS.exists() {
+            debug!("found state, loading from file");
+            let file = File::open(ALLIUM_INGAME_MENU_SETTINGS.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(json);
+            }
+            warn!("failed to read ingame menu settings, removing");
+            fs::remove_file(ALLIUM_INGAME_MENU_SETTINGS.as_path())?;
+        }
+        Ok(Self::new())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_INGAME_MENU_SETTINGS.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+
+    /// Builds settings from entries in order with their visibility.
+    pub fn from_entries(entries: &[(MenuEntry, bool)]) -> Self {
+        Self {
+            order: entries
+                .iter()
+                .map(|(e, _)| <&'static str>::from(e).to_string())
+                .collect(),
+            hidden: entries
+                .iter()
+                .filter(|(_, visible)| !visible)
+                .map(|(e, _)| <&'static str>::from(e).to_string())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// All known entries in order with their visibility. Unknown and duplicate entries are
+    /// skipped, entries missing from the settings are added at the end, and required entries
+    /// are always visible and pinned to the top and bottom.
+    pub fn entries(&self) -> Vec<(MenuEntry, bool)> {
+        let mut order: Vec<MenuEntry> = Vec::new();
+        for entry in self
+            .order
+            .iter()
+            .filter_map(|name| MenuEntry::from_str(name).ok())
+        {
+            if !order.contains(&entry) {
+                order.push(entry);
+            }
+        }
+
+        for entry in MenuEntry::iter() {
+            if !order.contains(&entry) {
+                order.push(entry);
+            }
+        }
+
+        order.retain(|e| !e.is_required());
+        order.insert(0, MenuEntry::Continue);
+        order.push(MenuEntry::Quit);
+
+        order
+            .into_iter()
+            .map(|entry| {
+                let hidden = self
+                    .hidden
+                    .iter()
+                    .any(|name| name == <&'static str>::from(entry));
+                (entry, entry.is_required() || !hidden)
+            })
+            .collect()
+    }
+
+    /// Visible entries in order.
+    pub fn visible_entries(&self) -> Vec<MenuEntry> {
+        self.entries()
+            .into_iter()
+            .filter_map(|(entry, visible)| visible.then_some(entry))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(order: &[&str], hidden: &[&str]) -> IngameMenuSettings {
+        IngameMenuSettings {
+            order: order.iter().map(|s| s.to_string()).collect(),
+            hidden: hidden.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_order() {
+        assert_eq!(
+            IngameMenuSettings::default().visible_entries(),
+            MenuEntry::iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            IngameMenuSettings::new().visible_entries(),
+            MenuEntry::iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_custom_order_and_hidden() {
+        let settings = settings(
+            &[
+                "Continue", "Guide", "Load", "Save", "Reset", "Settings", "Quit",
+            ],
+            &["Reset", "Settings"],
+        );
+        assert_eq!(
+            settings.visible_entries(),
+            vec![
+                MenuEntry::Continue,
+                MenuEntry::Guide,
+                MenuEntry::Load,
+                MenuEntry::Save,
+                MenuEntry::SaveStates,
+                MenuEntry::ChangeDisc,
+                MenuEntry::Notes,
+                MenuEntry::Cheats,
+                MenuEntry::Screenshot,
+                MenuEntry::Launcher,
+                MenuEntry::SwitchGame,
+                MenuEntry::Quit
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_and_duplicate_entries() {
+        let settings = settings(
+            &[
+                "Guide", "Removed", "Guide", "Save", "Save", "Quit", "Continue",
+            ],
+            &["Removed"],
+        );
+        assert_eq!(
+            settings.visible_entries(),
+            vec![
+                MenuEntry::Continue,
+                MenuEntry::Guide,
+                MenuEntry::Save,
+                MenuEntry::Load,
+                MenuEntry::SaveStates,
+                MenuEntry::ChangeDisc,
+                MenuEntry::Reset,
+                MenuEntry::Notes,
+                MenuEntry::Cheats,
+                MenuEntry::Screenshot,
+                MenuEntry::Settings,
+                MenuEntry::Launcher,
+                MenuEntry::SwitchGame,
+                MenuEntry::Quit
+            ]
+        );
+    }
+
+    #[test]
+    fn test_required_entries_cannot_be_hidden() {
+        let settings = settings(&["Quit", "Save", "Continue"], &["Continue", "Quit", "Save"]);
+        let entries = settings.visible_entries();
+        assert_eq!(entries.first(), Some(&MenuEntry::Continue));
+        assert_eq!(entries.last(), Some(&MenuEntry::Quit));
+        assert!(!entries.contains(&MenuEntry::Save));
+    }
+
+    #[test]
+    fn test_save_on_switch_defaults_to_enabled() -> Result<()> {
+        let settings: IngameMenuSettings = serde_json::from_str(r#"{"backup_states":true}"#)?;
+        assert!(settings.save_on_switch);
+        assert!(IngameMenuSettings::new().save_on_switch);
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+//! Games that exit right after being launched, e.g. because the core is missing a BIOS or the ROM
+//! is corrupt. alliumd detects these when the game exits and passes them on to the launcher,
+//! which explains what happened instead of silently returning to the game list.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::game_info::GameInfo;
+
+/// Environment variable with the launch failure for the launcher to show, as JSON.
+pub const ALLIUM_LAUNCH_FAILURE_ENV: &str = "ALLIUM_LAUNCH_FAILURE";
+
+/// Games that exit within this many seconds are assumed to have failed to launch.
+pub const LAUNCH_FAILURE_SECS: i64 = 5;
+
+/// Number of lines of the game's output that are kept.
+pub const STDERR_TAIL_LINES: usize = 8;
+
+/// Longest output that is read from the end of the output file, in bytes.
+const STDERR_TAIL_BYTES: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchFailure {
+    /// Display name of the game.
+    pub name: String,
+    /// Exit code, if the game exited on its own.
+    pub code: Option<i32>,
+    /// Signal that terminated the game, if any.
+    pub signal: Option<i32>,
+    /// Last lines the game wrote to stderr.
+    pub stderr: String,
+    /// Why the game wasn't started at all, if it wasn't.
+    #[serde(default)]
+    pub error: Option<LaunchError>,
+}
+
+/// Why a game couldn't be started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LaunchError {
+    /// No console takes files with the game's extension, or in its folder.
+    NoConsole { extension: String, folder: String },
+    /// The console has neither a program nor cores to launch games with.
+    NoCore { console: String },
+    /// The core or program that the game would be launched with isn't installed.
+    CoreMissing { console: String, path: PathBuf },
+}
+
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LaunchError::NoConsole { extension, folder } => write!(
+                f,
+                "no console takes .{} files or the {} folder",
+                extension, folder
+            ),
+            LaunchError::NoCore { console } => write!(f, "{} has no path or cores", console),
+            LaunchError::CoreMissing { console, path } => {
+                write!(f, "{} needs {}, which is missing", console, path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LaunchError {}
+
+impl LaunchFailure {
+    /// Checks how the game exited after running for `ran_for`. Games that exit with an error or
+    /// crash are failures, as are games that exit within `LAUNCH_FAILURE_SECS` of launching,
+    /// unless they were asked to quit, as `quit_requested` says. `stderr` is the file the game's
+    /// output went to.
+    pub fn detect(
+        game_info: &GameInfo,
+        status: ExitStatus,
+        ran_for: Duration,
+        quit_requested: bool,
+        stderr: &Path,
+    ) -> Option<Self> {
+        let signal = exit_signal(status);
+        let exited_early = ran_for < Duration::seconds(LAUNCH_FAILURE_SECS) && !quit_requested;
+        let failed = match (status.code(), signal) {
+            (Some(0), _) => exited_early,
+            (Some(_), _) => true,
+            // Asked to quit, e.g. by alliumd or the menu
+            (None, Some(signal)) if is_quit_signal(signal) => exited_early,
+            (None, _) => true,
+        };
+        if !failed {
+            return None;
+        }
+
+        Some(Self {
+            name: game_info.name.clone(),
+            code: status.code(),
+            signal,
+            stderr: stderr_tail(stderr, STDERR_TAIL_LINES),
+            error: None,
+        })
+    }
+
+    /// The game named `name` couldn't be started because of `error`.
+    pub fn not_started(name: String, error: LaunchError) -> Self {
+        Self {
+            name,
+            code: None,
+            signal: None,
+            stderr: String::new(),
+            error: Some(error),
+        }
+    }
+
+    /// The launch failure passed on by alliumd, if any.
+    pub fn from_env() -> Option<Self> {
+        let json = std::env::var(ALLIUM_LAUNCH_FAILURE_ENV).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+#[cfg(unix)]
+fn exit_signal(status: ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: ExitStatus) -> Option<i32> {
+    None
+}
+
+#[cfg(unix)]
+fn is_quit_signal(signal: i32) -> bool {
+    use nix::sys::signal::Signal;
+    signal == Signal::SIGTERM as i32 || signal == Signal::SIGKILL as i32
+}
+
+#[cfg(not(unix))]
+fn is_quit_signal(_signal: i32) -> bool {
+    false
+}
+
+/// The last `lines` lines of the file, or nothing if it can't be read.
+pub fn stderr_tail(path: &Path, lines: usize) -> String {
+    let Ok(bytes) = fs::read(path) else {
+        return String::new();
+    };
+    let bytes = &bytes[bytes.len().saturating_sub(STDERR_TAIL_BYTES)..];
+    let text = String::from_utf8_lossy(bytes);
+    let tail: Vec<&str> = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect();
+    tail[tail.len().saturating_sub(lines)..].join("\n")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::env;
+    use std::fs::File;
+    use std::path::PathBuf;
+    use std::process::{Command, Stdio};
+
+    use anyhow::Result;
+
+    use super::*;
+
+    fn stderr_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!(
+            "allium-launch-failure-{}-{}.log",
+            name,
+            std::process::id()
+        ))
+    }
+
+    /// Runs `script` as a stand-in for a game, with its stderr going to `path`.
+    fn run(script: &str, path: &Path) -> Result<ExitStatus> {
+        let stderr = File::create(path)?;
+        Ok(Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stderr(Stdio::from(stderr))
+            .status()?)
+    }
+
+    fn game_info() -> GameInfo {
+        GameInfo {
+            name: "Tetris".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fast_failure_with_error() -> Result<()> {
+        let path = stderr_path("error");
+        let status = run(
+            "echo 'loading core' >&2; echo 'missing bios' >&2; exit 1",
+            &path,
+        )?;
+
+        let failure = LaunchFailure::detect(&game_info(), status, Duration::zero(), false, &path);
+        assert_eq!(
+            failure,
+            Some(LaunchFailure {
+                name: "Tetris".to_string(),
+                code: Some(1),
+                signal: None,
+                stderr: "loading core\nmissing bios".to_string(),
+                error: None,
+            })
+        );
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_fast_exit_without_error() -> Result<()> {
+        let path = stderr_path("fast");
+        let status = run("exit 0", &path)?;
+
+        let failure =
+            LaunchFailure::detect(&game_info(), status, Duration::seconds(1), false, &path);
+        assert_eq!(failure.map(|f| f.code), Some(Some(0)));
+
+        // A game that was played for a while and quit normally is fine
+        assert_eq!(
+            LaunchFailure::detect(&game_info(), status, Duration::minutes(30), false, &path),
+            None
+        );
+        // So is one that was quit from the menu right away
+        assert_eq!(
+            LaunchFailure::detect(&game_info(), status, Duration::seconds(1), true, &path),
+            None
+        );
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_terminated_by_alliumd() -> Result<()> {
+        let path = stderr_path("terminated");
+        let status = run("kill -TERM $$", &path)?;
+
+        assert_eq!(
+            LaunchFailure::detect(&game_info(), status, Duration::minutes(30), false, &path),
+            None
+        );
+        // Terminated right after launching, which is only a failure if alliumd didn't ask
+        assert!(
+            LaunchFailure::detect(&game_info(), status, Duration::seconds(1), false, &path)
+                .is_some()
+        );
+        assert_eq!(
+            LaunchFailure::detect(&game_info(), status, Duration::seconds(1), true, &path),
+            None
+        );
+        let crashed = run("kill -SEGV $$", &path)?;
+        assert_eq!(
+            LaunchFailure::detect(&game_info(), crashed, Duration::minutes(30), false, &path)
+                .and_then(|f| f.signal),
+            Some(11)
+        );
+        // A crash is a failure even while quitting
+        assert!(
+            LaunchFailure::detect(&game_info(), crashed, Duration::seconds(1), true, &path)
+                .is_some()
+        );
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_stderr_tail() -> Result<()> {
+        let path = stderr_path("tail");
+        fs::write(
+            &path,
+            (1..=20)
+                .map(|i| format!("line {}\n", i))
+                .collect::<String>(),
+        )?;
+        assert_eq!(
+            stderr_tail(&path, 3),
+            "line 18\nline 19\nline 20".to_string()
+        );
+        assert_eq!(stderr_tail(&stderr_path("missing"), 3), "");
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_round_trip() {
+        let failure = LaunchFailure {
+            name: "Tetris".to_string(),
+            code: Some(1),
+            signal: None,
+            stderr: "missing bios".to_string(),
+        };
+        let json = failure.to_json();
+        assert_eq!(
+            serde_json::from_str::<LaunchFailure>(&json).ok(),
+            Some(failure)
+        );
+    }
+}
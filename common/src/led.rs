@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// What the power LED is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LedPattern {
+    Off,
+    Solid,
+    SlowBlink,
+    FastBlink,
+}
+
+impl LedPattern {
+    /// How long the LED stays on, and then off, in each blink. None if the LED doesn't blink.
+    pub fn blink_interval(&self) -> Option<Duration> {
+        match self {
+            LedPattern::Off | LedPattern::Solid => None,
+            LedPattern::SlowBlink => Some(Duration::from_millis(1000)),
+            LedPattern::FastBlink => Some(Duration::from_millis(200)),
+        }
+    }
+}
+
+/// Tells alliumd that a background task completed, so that the LED shows it.
+pub fn notify_task_completed() {
+    #[cfg(unix)]
+    {
+        use std::env;
+
+        use log::warn;
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        use crate::splash::ALLIUMD_PID_ENV;
+
+        let Some(pid) = env::var(ALLIUMD_PID_ENV)
+            .ok()
+            .and_then(|pid| pid.parse().ok())
+        else {
+            return;
+        };
+        if let Err(e) = kill(Pid::from_raw(pid), Signal::SIGHUP) {
+            warn!("failed to notify alliumd: {}", e);
+        }
+    }
+}
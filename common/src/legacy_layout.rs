@@ -0,0 +1,368 @@
+//! Detection and migration of ROM folders laid out for other firmware.
+//!
+//! Known layouts are listed in `legacy_layouts.toml`, which maps each legacy folder name to the
+//! folder name Allium expects. Folders can either be moved or symlinked to the Allium name, or
+//! kept where they are, in which case the mapping is recorded and consulted by the console mapper.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{ALLIUM_CONFIG_LEGACY_LAYOUTS, ALLIUM_LEGACY_STATE};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LegacyLayouts {
+    #[serde(default)]
+    pub layouts: Vec<LegacyLayout>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LegacyLayout {
+    /// Name of the firmware this layout comes from.
+    pub name: String,
+    /// Legacy folder name to Allium folder name.
+    #[serde(default)]
+    pub folders: BTreeMap<String, String>,
+}
+
+/// A folder in the games directory that belongs to a legacy layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyFolder {
+    /// Name of the layout it was found in.
+    pub layout: String,
+    /// Current folder name.
+    pub name: String,
+    /// Folder name Allium expects.
+    pub target: String,
+}
+
+/// What to do with the legacy folders that were found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationMode {
+    /// Move the folders to their Allium names.
+    Move,
+    /// Create symlinks with the Allium names pointing at the folders.
+    Symlink,
+    /// Keep the folders as they are and remember which console they belong to.
+    Map,
+    /// Do nothing, and don't ask again.
+    Skip,
+}
+
+impl LegacyLayouts {
+    pub fn load() -> Result<Self> {
+        let config = fs::read_to_string(ALLIUM_CONFIG_LEGACY_LAYOUTS.as_path())
+            .with_context(|| format!("{:?}", *ALLIUM_CONFIG_LEGACY_LAYOUTS))?;
+        Self::parse(&config)
+    }
+
+    pub fn parse(config: &str) -> Result<Self> {
+        toml::from_str(config).context("Failed to parse legacy_layouts.toml.")
+    }
+
+    /// Finds folders in `games_dir` that belong to a legacy layout. Folders that `is_known`
+    /// already recognises, or that have already been symlinked, are skipped.
+    pub fn detect(
+        &self,
+        games_dir: &Path,
+        is_known: impl Fn(&str) -> bool,
+    ) -> Result<Vec<LegacyFolder>> {
+        let mut found = Vec::new();
+        for entry in fs::read_dir(games_dir)? {
+            let entry = entry?;
+            if !entry.path().is_dir() {
+                continue;
+            }
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if is_known(&name) {
+                continue;
+            }
+
+            let Some((layout, target)) = self.layouts.iter().find_map(|layout| {
+                layout
+                    .folders
+                    .get(&name)
+                    .map(|target| (layout.name.clone(), target.clone()))
+            }) else {
+                continue;
+            };
+            if target == name || is_link_to(&games_dir.join(&target), &name) {
+                continue;
+            }
+
+            found.push(LegacyFolder {
+                layout,
+                name,
+                target,
+            });
+        }
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(found)
+    }
+}
+
+/// Remembers whether the user has been asked about legacy folders, and which folders were kept
+/// in place.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegacyState {
+    #[serde(default)]
+    pub handled: bool,
+    /// Legacy folder name to Allium folder name.
+    #[serde(default)]
+    pub mappings: BTreeMap<String, String>,
+}
+
+impl LegacyState {
+    pub fn load() -> Result<Self> {
+        if ALLIUM_LEGACY_STATE.exists() {
+            debug!("found state, loading from file");
+            let file = File::open(ALLIUM_LEGACY_STATE.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(json);
+            }
+            warn!("failed to read legacy layout state, removing");
+            fs::remove_file(ALLIUM_LEGACY_STATE.as_path())?;
+        }
+        Ok(Self::default())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_LEGACY_STATE.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+
+    /// Migrates `folders` in `games_dir`. Running the same migration again does nothing.
+    pub fn migrate(
+        &mut self,
+        games_dir: &Path,
+        folders: &[LegacyFolder],
+        mode: MigrationMode,
+    ) -> Result<()> {
+        for folder in folders {
+            let src = games_dir.join(&folder.name);
+            let dst = games_dir.join(&folder.target);
+            match mode {
+                MigrationMode::Move => {
+                    if src.exists() {
+                        move_dir(&src, &dst)?;
+                    }
+                }
+                MigrationMode::Symlink => {
+                    if is_link_to(&dst, &folder.name) {
+                        continue;
+                    }
+                    if dst.symlink_metadata().is_ok() {
+                        // The Allium folder is already in use, so fall back to a mapping
+                        warn!("{:?} already exists, mapping {} instead", dst, folder.name);
+                        self.mappings
+                            .insert(folder.name.clone(), folder.target.clone());
+                        continue;
+                    }
+                    symlink(&folder.name, &dst)?;
+                }
+                MigrationMode::Map => {
+                    self.mappings
+                        .insert(folder.name.clone(), folder.target.clone());
+                }
+                MigrationMode::Skip => {}
+            }
+        }
+        self.handled = true;
+        Ok(())
+    }
+}
+
+/// Moves `src` to `dst`. If `dst` already exists, the contents of `src` are merged into it,
+/// leaving behind anything that would overwrite an existing file.
+fn move_dir(src: &Path, dst: &Path) -> Result<()> {
+    if !dst.exists() {
+        fs::rename(src, dst)?;
+        return Ok(());
+    }
+
+    if is_same_file(src, dst) {
+        // Case-insensitive file system and only the case differs, so rename through a temporary
+        // name
+        let tmp = src.with_file_name(format!(
+            ".{}.migrating",
+            src.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        fs::rename(src, &tmp)?;
+        fs::rename(&tmp, dst)?;
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let to = dst.join(entry.file_name());
+        if to.symlink_metadata().is_ok() {
+            warn!("{:?} already exists, leaving {:?}", to, entry.path());
+            continue;
+        }
+        fs::rename(entry.path(), to)?;
+    }
+    if fs::read_dir(src)?.next().is_none() {
+        fs::remove_dir(src)?;
+    }
+    Ok(())
+}
+
+/// Whether `path` is a symlink pointing at the sibling folder `name`.
+fn is_link_to(path: &Path, name: &str) -> bool {
+    fs::read_link(path).is_ok_and(|target| target == Path::new(name))
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_a: &Path, _b: &Path) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn symlink(name: &str, dst: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(name, dst)
+        .with_context(|| format!("failed to link {:?} to {}", dst, name))
+}
+
+#[cfg(not(unix))]
+fn symlink(_name: &str, _dst: &Path) -> Result<()> {
+    anyhow::bail!("symlinks are not supported on this platform")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    const LAYOUTS: &str = r#"
+        [[layouts]]
+        name = "EmulationStation"
+        [layouts.folders]
+        gba = "GBA"
+        megadrive = "MD"
+        snes = "SFC"
+    "#;
+
+    fn games_dir(name: &str, folders: &[&str]) -> PathBuf {
+        let dir = temp_dir(&format!("legacy-{}", name));
+        for folder in folders {
+            fs::create_dir_all(dir.join(folder)).unwrap();
+            fs::write(dir.join(folder).join("Game.rom"), folder).unwrap();
+        }
+        dir
+    }
+
+    fn detect(dir: &Path) -> Vec<LegacyFolder> {
+        LegacyLayouts::parse(LAYOUTS)
+            .unwrap()
+            .detect(dir, |name| ["GBA", "MD", "SFC"].contains(&name))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_detect() {
+        let dir = games_dir("detect", &["gba", "megadrive", "GBA", "Other"]);
+        assert_eq!(
+            detect(&dir),
+            vec![
+                LegacyFolder {
+                    layout: "EmulationStation".to_string(),
+                    name: "gba".to_string(),
+                    target: "GBA".to_string(),
+                },
+                LegacyFolder {
+                    layout: "EmulationStation".to_string(),
+                    name: "megadrive".to_string(),
+                    target: "MD".to_string(),
+                },
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_move_is_idempotent() {
+        let dir = games_dir("move", &["megadrive", "snes", "SFC"]);
+        let folders = detect(&dir);
+
+        let mut state = LegacyState::default();
+        state.migrate(&dir, &folders, MigrationMode::Move).unwrap();
+        state.migrate(&dir, &folders, MigrationMode::Move).unwrap();
+
+        assert!(state.handled);
+        assert!(state.mappings.is_empty());
+        assert!(!dir.join("megadrive").exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("MD/Game.rom")).unwrap(),
+            "megadrive"
+        );
+        // Conflicting files are left behind instead of being overwritten
+        assert_eq!(fs::read_to_string(dir.join("SFC/Game.rom")).unwrap(), "SFC");
+        assert_eq!(
+            fs::read_to_string(dir.join("snes/Game.rom")).unwrap(),
+            "snes"
+        );
+        assert!(detect(&dir).iter().all(|f| f.name != "megadrive"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_is_idempotent() {
+        let dir = games_dir("symlink", &["gba", "snes", "SFC"]);
+        let folders = detect(&dir);
+
+        let mut state = LegacyState::default();
+        state
+            .migrate(&dir, &folders, MigrationMode::Symlink)
+            .unwrap();
+        state
+            .migrate(&dir, &folders, MigrationMode::Symlink)
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join("GBA/Game.rom")).unwrap(), "gba");
+        // SFC already exists, so snes is mapped instead
+        assert_eq!(
+            state.mappings,
+            [("snes".to_string(), "SFC".to_string())]
+                .into_iter()
+                .collect()
+        );
+        assert!(detect(&dir).iter().all(|f| f.name != "gba"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_map_is_idempotent() {
+        let dir = games_dir("map", &["gba"]);
+        let folders = detect(&dir);
+
+        let mut state = LegacyState::default();
+        state.migrate(&dir, &folders, MigrationMode::Map).unwrap();
+        let first = state.clone();
+        state.migrate(&dir, &folders, MigrationMode::Map).unwrap();
+
+        assert_eq!(state, first);
+        assert_eq!(state.mappings.get("gba").map(String::as_str), Some("GBA"));
+        assert!(dir.join("gba/Game.rom").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
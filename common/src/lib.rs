@@ -1,17 +1,51 @@
 #![deny(clippy::all, unsafe_op_in_unsafe_fn)]
 #![warn(rust_2018_idioms)]
 
+pub mod archive;
 pub mod battery;
+pub mod cheats;
 pub mod command;
 pub mod constants;
 pub mod database;
+pub mod diagnostics;
 pub mod display;
+pub mod emergency_exit;
+pub mod filename_rules;
+pub mod fingerprint;
+pub mod format;
 pub mod game_info;
 pub mod geom;
+pub mod ingame_menu;
+pub mod launch_failure;
+pub mod led;
+pub mod legacy_layout;
+pub mod library_export;
+pub mod list_settings;
 pub mod locale;
+pub mod maintenance;
+pub mod natural_order;
+pub mod notification;
+pub mod persisted;
 pub mod platform;
+pub mod play_time_import;
+pub mod playlist;
+pub mod profile;
+pub mod quit_prompt;
+pub mod remote_token;
 pub mod resources;
 pub mod retroarch;
+pub mod retry;
+pub mod rom_header;
+pub mod save_state;
+pub mod sort_order;
+pub mod splash;
 pub mod stylesheet;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod text_edit;
+pub mod theme_schedule;
+pub mod trash;
 pub mod view;
+pub mod volume;
 pub mod wifi;
+pub mod write_activity;
@@ -0,0 +1,199 @@
+//! Machine-readable export of the game library for companion tools.
+//!
+//! The export is a single JSON object:
+//!
+//! ```json
+//! {"version":2,"root":"/mnt/SDCARD/Roms","games":[
+//! {"path":"GBA/Game.gba","name":"Game","core":null,"play_count":1,"play_time":60,"last_played":3,"favorite":false,"completed":true}
+//! ]}
+//! ```
+//!
+//! - `version` is bumped whenever an existing field changes meaning or is removed.
+//!   New fields may be added without a version bump, so readers should ignore unknown fields.
+//! - `root` is the absolute games directory at the time of export.
+//! - `path` is relative to `root`, or absolute if the game lives outside of it.
+//! - `play_time` is in seconds.
+//! - `last_played` is an ordering key, higher means more recent. `0` means never played.
+//! - `favorite` and `completed` mirror the game's favorite and completed marks on the device.
+//! - `note` is the note written for the game on the device, and is left out if there is none.
+//!
+//! Games are written one at a time in path order, so memory use does not grow with the library.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::constants::ALLIUM_LIBRARY_EXPORT;
+use crate::database::Database;
+
+/// Version of the export format.
+pub const LIBRARY_EXPORT_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize)]
+struct ExportHeader<'a> {
+    version: u32,
+    root: &'a Path,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportedGame<'a> {
+    path: &'a Path,
+    name: &'a str,
+    core: Option<&'a str>,
+    play_count: i64,
+    play_time: i64,
+    last_played: i64,
+    favorite: bool,
+    completed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<&'a str>,
+}
+
+/// Writes the library export for `root` to `writer`.
+pub fn export_library<W: Write>(database: &Database, root: &Path, mut writer: W) -> Result<()> {
+    let header = serde_json::to_string(&ExportHeader {
+        version: LIBRARY_EXPORT_VERSION,
+        root,
+    })?;
+    // Reopen the header object to append the games array.
+    writer.write_all(&header.as_bytes()[..header.len() - 1])?;
+    writer.write_all(b",\"games\":[")?;
+
+    let notes = database.notes()?;
+    let favorites: HashSet<_> = database.favorites()?.into_iter().collect();
+    let completed = database.completed_games()?;
+    let mut first = true;
+    database.for_each_game(|game| {
+        writer.write_all(if first { b"\n" } else { b",\n" })?;
+        first = false;
+        serde_json::to_writer(
+            &mut writer,
+            &ExportedGame {
+                path: game.path.strip_prefix(root).unwrap_or(&game.path),
+                name: &game.name,
+                core: game.core.as_deref(),
+                play_count: game.play_count,
+                play_time: game.play_time.num_seconds(),
+                last_played: game.last_played,
+                favorite: favorites.contains(&game.path),
+                completed: completed.contains(&game.path),
+                note: notes.get(&game.path).map(String::as_str),
+            },
+        )?;
+        Ok(())
+    })?;
+
+    writer.write_all(b"\n]}\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Exports the library to the SD card root, returning the path of the written file.
+pub fn export_library_to_file(database: &Database, root: &Path) -> Result<&'static Path> {
+    let file = File::create(ALLIUM_LIBRARY_EXPORT.as_path())?;
+    export_library(database, root, BufWriter::new(file))?;
+    Ok(ALLIUM_LIBRARY_EXPORT.as_path())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::Duration;
+
+    use super::*;
+    use crate::database::NewGame;
+
+    #[test]
+    fn test_export_format_is_stable() -> Result<()> {
+        let database = Database::in_memory()?;
+        let root = Path::new("/mnt/SDCARD/Roms");
+
+        let games = vec![
+            NewGame {
+                name: "Game Two".to_string(),
+                path: root.join("GBA/Game Two.gba"),
+                image: Some(root.join("GBA/Imgs/Game Two.png")),
+                core: Some("mgba".to_string()),
+            },
+            NewGame {
+                name: "Game \"One\"".to_string(),
+                path: root.join("GB/Game One.gb"),
+                image: None,
+                core: None,
+            },
+            NewGame {
+                name: "Elsewhere".to_string(),
+                path: PathBuf::from("/mnt/other/Elsewhere.nes"),
+                image: None,
+                core: None,
+            },
+        ];
+        database.update_games(&games)?;
+        database.increment_play_count(&games[0].name, &games[0].path, None)?;
+        database.add_play_time(&games[0].path, Duration::seconds(90))?;
+        database.set_completed(&games[0].path, true)?;
+        database.set_favorite(&games[1].path, true)?;
+
+        let mut output = Vec::new();
+        export_library(&database, root, &mut output)?;
+
+        assert_eq!(
+            String::from_utf8(output)?,
+            include_str!("../testdata/library-export-v2.json")
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(include_str!("../testdata/library-export-v2.json"))?;
+        assert_eq!(json["version"], LIBRARY_EXPORT_VERSION);
+        assert_eq!(json["games"].as_array().unwrap().len(), games.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_keeps_v1_fields() -> Result<()> {
+        let v1: serde_json::Value =
+            serde_json::from_str(include_str!("../testdata/library-export-v1.json"))?;
+        let v2: serde_json::Value =
+            serde_json::from_str(include_str!("../testdata/library-export-v2.json"))?;
+
+        assert_eq!(v1["root"], v2["root"]);
+        let v1_games = v1["games"].as_array().unwrap();
+        let v2_games = v2["games"].as_array().unwrap();
+        assert_eq!(v1_games.len(), v2_games.len());
+        for (old, new) in v1_games.iter().zip(v2_games) {
+            for (key, value) in old.as_object().unwrap() {
+                assert_eq!(&new[key], value, "{key} changed for {}", old["path"]);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_notes() -> Result<()> {
+        let database = Database::in_memory()?;
+        let root = Path::new("/mnt/SDCARD/Roms");
+        let path = root.join("GBA/Game.gba");
+        database.update_games(&[NewGame {
+            name: "Game".to_string(),
+            path: path.clone(),
+            image: None,
+            core: None,
+        }])?;
+        database.set_note(&path, "door code\nis 4512")?;
+
+        let mut output = Vec::new();
+        export_library(&database, root, &mut output)?;
+
+        let json: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(json["games"][0]["note"], "door code\nis 4512");
+
+        Ok(())
+    }
+}
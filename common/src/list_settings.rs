@@ -0,0 +1,43 @@
+//! How lists behave, kept in `state/list-settings.json`.
+//!
+//! Games and folders the user hid can be listed greyed out, so that they can be unhidden.
+//!
+//! Paging through a list with L and R stops at its ends, or wraps around them like Up and Down do.
+
+use std::fs::{self, File};
+
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::ALLIUM_LIST_SETTINGS;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListSettings {
+    /// Whether games and folders the user hid are listed.
+    pub show_hidden: bool,
+    /// Whether paging from one end of a list goes to the other end.
+    pub wrap_pages: bool,
+}
+
+impl ListSettings {
+    pub fn load() -> Result<Self> {
+        if ALLIUM_LIST_SETTINGS.exists() {
+            debug!("found list settings, loading from file");
+            let file = File::open(ALLIUM_LIST_SETTINGS.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(json);
+            }
+            warn!("failed to read list settings, removing");
+            fs::remove_file(ALLIUM_LIST_SETTINGS.as_path())?;
+        }
+        Ok(Self::default())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_LIST_SETTINGS.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+//! Chores that are put off until the device is idle and charging overnight, so that they never
+//! run while a game is being played. alliumd decides when to run them; this module defines the
+//! tasks, their settings, and the report of the last run.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, NaiveTime};
+use log::{debug, warn};
+use rusqlite::InterruptHandle;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{ALLIUM_MAINTENANCE_REPORT, ALLIUM_MAINTENANCE_SETTINGS};
+use crate::database::Database;
+use crate::display::image::DISK_IMAGE_CACHE;
+
+/// A chore that runs during the maintenance window.
+#[async_trait(?Send)]
+pub trait MaintenanceTask: fmt::Debug {
+    fn name(&self) -> &str;
+
+    /// Rough estimate of how long the task takes.
+    fn estimated_cost(&self) -> Duration;
+
+    /// How long the task may run before it is abandoned.
+    fn timeout(&self) -> Duration {
+        self.estimated_cost() * 4
+    }
+
+    /// Runs the task. The task may be dropped at any await point when maintenance is aborted, so
+    /// it must leave things in a consistent state if it is.
+    async fn run(&mut self) -> Result<()>;
+}
+
+/// The tasks run by alliumd, in order of priority.
+pub fn tasks() -> Vec<Box<dyn MaintenanceTask>> {
+    vec![Box::new(VacuumDatabase), Box::new(PruneImageCache)]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceSettings {
+    #[serde(default = "MaintenanceSettings::default_enabled")]
+    pub enabled: bool,
+    /// Maintenance only starts between these times. The window may wrap past midnight.
+    #[serde(default = "MaintenanceSettings::default_window_start")]
+    pub window_start: NaiveTime,
+    #[serde(default = "MaintenanceSettings::default_window_end")]
+    pub window_end: NaiveTime,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            window_start: Self::default_window_start(),
+            window_end: Self::default_window_end(),
+        }
+    }
+}
+
+impl MaintenanceSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_window_start() -> NaiveTime {
+        NaiveTime::from_hms_opt(2, 0, 0).unwrap()
+    }
+
+    fn default_window_end() -> NaiveTime {
+        NaiveTime::from_hms_opt(5, 0, 0).unwrap()
+    }
+
+    /// Returns when the window containing `now` started, or None if `now` is outside the window.
+    pub fn window_started(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let time = now.time();
+        let today = now.date().and_time(self.window_start);
+        if self.window_start <= self.window_end {
+            (self.window_start <= time && time < self.window_end).then_some(today)
+        } else if time >= self.window_start {
+            Some(today)
+        } else if time < self.window_end {
+            Some(today - chrono::Duration::days(1))
+        } else {
+            None
+        }
+    }
+
+    pub fn load() -> Result<Self> {
+        if ALLIUM_MAINTENANCE_SETTINGS.exists() {
+            debug!("found state, loading from file");
+            let file = File::open(ALLIUM_MAINTENANCE_SETTINGS.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(json);
+            }
+            warn!("failed to read maintenance settings, removing");
+            fs::remove_file(ALLIUM_MAINTENANCE_SETTINGS.as_path())?;
+        }
+        Ok(Self::new())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_MAINTENANCE_SETTINGS.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskOutcome {
+    Completed,
+    Failed(String),
+    TimedOut,
+    /// The task was stopped part way because maintenance was aborted.
+    Aborted,
+    /// The task didn't start because maintenance was aborted.
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskReport {
+    pub name: String,
+    pub outcome: TaskOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AbortReason {
+    Input,
+    ChargingStopped,
+}
+
+/// Summary of a maintenance run, shown in the settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub started: NaiveDateTime,
+    pub tasks: Vec<TaskReport>,
+    pub aborted: Option<AbortReason>,
+}
+
+impl MaintenanceReport {
+    /// Number of tasks that completed successfully.
+    pub fn completed(&self) -> usize {
+        self.tasks
+            .iter()
+            .filter(|t| t.outcome == TaskOutcome::Completed)
+            .count()
+    }
+
+    /// Loads the report of the last run, if maintenance has ever run.
+    pub fn load() -> Result<Option<Self>> {
+        if !ALLIUM_MAINTENANCE_REPORT.exists() {
+            return Ok(None);
+        }
+        let file = File::open(ALLIUM_MAINTENANCE_REPORT.as_path())?;
+        match serde_json::from_reader(file) {
+            Ok(report) => Ok(Some(report)),
+            Err(e) => {
+                warn!("failed to read maintenance report, removing: {}", e);
+                fs::remove_file(ALLIUM_MAINTENANCE_REPORT.as_path())?;
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_MAINTENANCE_REPORT.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+}
+
+/// Rebuilds the database to reclaim the space left behind by removed games and finished jobs.
+#[derive(Debug)]
+pub struct VacuumDatabase;
+
+#[async_trait(?Send)]
+impl MaintenanceTask for VacuumDatabase {
+    fn name(&self) -> &str {
+        "vacuum-database"
+    }
+
+    fn estimated_cost(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+
+    async fn run(&mut self) -> Result<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let vacuum = tokio::task::spawn_blocking(move || {
+            let database = Database::new()?;
+            let _ = tx.send(database.interrupt_handle());
+            database.vacuum()
+        });
+
+        // Interrupt the vacuum if this task is dropped before it finishes
+        let _interrupt = rx.await.ok().map(InterruptOnDrop);
+        vacuum.await?
+    }
+}
+
+/// Clears images left half written in the cache of scaled images, and trims it to its budget.
+#[derive(Debug)]
+pub struct PruneImageCache;
+
+#[async_trait(?Send)]
+impl MaintenanceTask for PruneImageCache {
+    fn name(&self) -> &str {
+        "prune-image-cache"
+    }
+
+    fn estimated_cost(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+
+    async fn run(&mut self) -> Result<()> {
+        tokio::task::spawn_blocking(|| {
+            DISK_IMAGE_CACHE
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .prune()
+        })
+        .await?
+    }
+}
+
+struct InterruptOnDrop(InterruptHandle);
+
+impl Drop for InterruptOnDrop {
+    fn drop(&mut self) {
+        self.0.interrupt();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_window() {
+        let settings = MaintenanceSettings::new();
+        assert_eq!(settings.window_started(at(2, 1, 59)), None);
+        assert_eq!(settings.window_started(at(2, 2, 0)), Some(at(2, 2, 0)));
+        assert_eq!(settings.window_started(at(2, 4, 59)), Some(at(2, 2, 0)));
+        assert_eq!(settings.window_started(at(2, 5, 0)), None);
+    }
+
+    #[test]
+    fn test_window_past_midnight() {
+        let settings = MaintenanceSettings {
+            window_start: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+            ..MaintenanceSettings::new()
+        };
+        assert_eq!(settings.window_started(at(2, 22, 59)), None);
+        assert_eq!(settings.window_started(at(2, 23, 30)), Some(at(2, 23, 0)));
+        assert_eq!(settings.window_started(at(3, 4, 0)), Some(at(2, 23, 0)));
+        assert_eq!(settings.window_started(at(3, 5, 0)), None);
+    }
+}
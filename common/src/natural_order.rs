@@ -0,0 +1,200 @@
+//! Natural ordering of names, so that "Disc 2" comes before "Disc 10".
+//!
+//! Runs of digits are compared by their numeric value, and everything else is compared one
+//! character at a time, ignoring case. Names that only differ in case or leading zeros are still
+//! ordered, so that sorting is stable across refreshes.
+
+use std::cmp::Ordering;
+
+/// Compares two names in natural order.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (left, right) = (a.as_bytes(), b.as_bytes());
+    // Names in a folder often share long prefixes, which compare equal either way, up to the
+    // start of a number that is cut off by where the names differ
+    let mut prefix = common_prefix(left, right);
+    while prefix > 0 && left[prefix - 1].is_ascii_digit() {
+        prefix -= 1;
+    }
+    while !a.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let (mut i, mut j) = (prefix, prefix);
+    // Leading zeros are only used to break ties, e.g. "07" comes after "7"
+    let mut zeros = Ordering::Equal;
+
+    while i < left.len() && j < right.len() {
+        let (l, r) = (left[i], right[j]);
+
+        if l.is_ascii_digit() && r.is_ascii_digit() {
+            let (l_start, l_end) = number(left, i);
+            let (r_start, r_end) = number(right, j);
+            // Without leading zeros, the longer number is the larger one
+            let ordering = (l_end - l_start)
+                .cmp(&(r_end - r_start))
+                .then_with(|| left[l_start..l_end].cmp(&right[r_start..r_end]));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            zeros = zeros.then((l_start - i).cmp(&(r_start - j)));
+            (i, j) = (l_end, r_end);
+        } else if l.is_ascii() && r.is_ascii() {
+            let ordering = l.to_ascii_lowercase().cmp(&r.to_ascii_lowercase());
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            (i, j) = (i + 1, j + 1);
+        } else {
+            // Indices only ever stop at character boundaries
+            let l = a[i..].chars().next().unwrap();
+            let r = b[j..].chars().next().unwrap();
+            let ordering = l.to_lowercase().cmp(r.to_lowercase());
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            (i, j) = (i + l.len_utf8(), j + r.len_utf8());
+        }
+    }
+
+    (left.len() - i)
+        .cmp(&(right.len() - j))
+        .then(zeros)
+        .then_with(|| a.cmp(b))
+}
+
+/// Length of the common prefix of two byte strings, compared a word at a time.
+fn common_prefix(left: &[u8], right: &[u8]) -> usize {
+    const WORD: usize = std::mem::size_of::<u64>();
+    let len = left.len().min(right.len());
+    let mut prefix = 0;
+    while prefix + WORD <= len && left[prefix..prefix + WORD] == right[prefix..prefix + WORD] {
+        prefix += WORD;
+    }
+    while prefix < len && left[prefix] == right[prefix] {
+        prefix += 1;
+    }
+    prefix
+}
+
+/// Finds the number starting at `start`, returning where its digits start after any leading
+/// zeros, and where it ends.
+fn number(s: &[u8], start: usize) -> (usize, usize) {
+    let mut digits = start;
+    while digits < s.len() && s[digits] == b'0' {
+        digits += 1;
+    }
+    let mut end = digits;
+    while end < s.len() && s[end].is_ascii_digit() {
+        end += 1;
+    }
+    (digits, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(names: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = names.iter().map(|s| s.to_string()).collect();
+        names.sort_unstable_by(|a, b| natural_cmp(a, b));
+        names
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(
+            sorted(&["Disc 10", "Disc 2", "Disc 1", "Disc 9"]),
+            vec!["Disc 1", "Disc 2", "Disc 9", "Disc 10"]
+        );
+        assert_eq!(
+            sorted(&["Track 10", "Track 9", "Track 100"]),
+            vec!["Track 9", "Track 10", "Track 100"]
+        );
+        // Longer than any integer type
+        assert_eq!(
+            natural_cmp("99999999999999999999999", "100000000000000000000000"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_leading_zeros() {
+        assert_eq!(natural_cmp("Disc 02", "Disc 10"), Ordering::Less);
+        assert_eq!(natural_cmp("Disc 002", "Disc 2a"), Ordering::Less);
+        assert_eq!(natural_cmp("0", "00"), Ordering::Less);
+        // Equal numbers are ordered by their leading zeros only if nothing else differs
+        assert_eq!(
+            sorted(&["07 b", "7 a", "007 a"]),
+            vec!["7 a", "007 a", "07 b"]
+        );
+        assert_eq!(natural_cmp("7", "07"), Ordering::Less);
+        assert_eq!(natural_cmp("07", "7"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_mixed() {
+        assert_eq!(
+            sorted(&[
+                "Hack v1.10",
+                "Hack v1.9",
+                "Hack v1.9b",
+                "Hack",
+                "Hack v2",
+                "Hack 2",
+            ]),
+            vec![
+                "Hack",
+                "Hack 2",
+                "Hack v1.9",
+                "Hack v1.9b",
+                "Hack v1.10",
+                "Hack v2",
+            ]
+        );
+        // Digits come before letters, as in ASCII
+        assert_eq!(natural_cmp("1up", "a"), Ordering::Less);
+        assert_eq!(natural_cmp("x2", "xa"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_case() {
+        assert_eq!(
+            sorted(&["banana", "Apple", "cherry", "apple"]),
+            vec!["Apple", "apple", "banana", "cherry"]
+        );
+        assert_eq!(natural_cmp("abc", "ABC"), Ordering::Greater);
+        assert_eq!(natural_cmp("abc", "abc"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_unicode() {
+        assert_eq!(natural_cmp("Éclair 2", "éclair 10"), Ordering::Less);
+        assert_eq!(natural_cmp("Ölfeld", "öLFELD"), Ordering::Less);
+        assert_eq!(
+            sorted(&["ポケモン 10", "ポケモン 2", "Zelda"]),
+            vec!["Zelda", "ポケモン 2", "ポケモン 10"]
+        );
+        // Non-ASCII digits aren't numbers
+        assert_eq!(natural_cmp("٢", "10"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_total_order() {
+        let names = [
+            "a", "A", "a1", "a01", "a001", "a1b", "a10", "a2", "", "1", "01", "É", "é", "e",
+        ];
+        for a in names {
+            assert_eq!(natural_cmp(a, a), Ordering::Equal);
+            for b in names {
+                assert_eq!(natural_cmp(a, b), natural_cmp(b, a).reverse(), "{a} {b}");
+                if a != b {
+                    assert_ne!(natural_cmp(a, b), Ordering::Equal, "{a} {b}");
+                }
+                for c in names {
+                    if natural_cmp(a, b) == Ordering::Less && natural_cmp(b, c) == Ordering::Less {
+                        assert_eq!(natural_cmp(a, c), Ordering::Less, "{a} {b} {c}");
+                    }
+                }
+            }
+        }
+    }
+}
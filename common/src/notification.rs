@@ -0,0 +1,355 @@
+//! Notifications from background services, e.g. a failed sync, that the launcher shows when it
+//! next starts. The launcher may not be running when they happen, such as while a game is in the
+//! foreground, so they are queued by alliumd instead of shown right away.
+//!
+//! Services post notifications to a spool file, which alliumd moves into the queue kept in its
+//! state. Whenever alliumd starts the launcher, it passes the queued notifications on in
+//! `ALLIUM_NOTIFICATIONS_ENV`, and drops them once the launcher tells it that it's ready. If the
+//! launcher doesn't get that far, they are passed on again the next time.
+
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::ALLIUM_NOTIFICATIONS;
+
+/// Environment variable with the notifications for the launcher to show, as JSON.
+pub const ALLIUM_NOTIFICATIONS_ENV: &str = "ALLIUM_NOTIFICATIONS";
+
+/// Most notifications that are queued. Less severe ones are dropped first.
+pub const MAX_NOTIFICATIONS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    /// Identifies what the notification is about. A notification replaces a queued one with the
+    /// same key, so that repeated failures are only shown once.
+    pub key: String,
+    pub severity: Severity,
+    pub text: String,
+    /// When the notification is no longer worth showing, if ever.
+    pub expires: Option<DateTime<Utc>>,
+}
+
+impl Notification {
+    pub fn new(key: impl Into<String>, severity: Severity, text: String) -> Self {
+        Self {
+            key: key.into(),
+            severity,
+            text,
+            expires: None,
+        }
+    }
+
+    /// Drops the notification if it hasn't been shown within `duration`.
+    pub fn expires_in(mut self, duration: Duration) -> Self {
+        self.expires = Some(Utc::now() + duration);
+        self
+    }
+
+    pub fn has_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| now >= expires)
+    }
+
+    /// Posts the notification for alliumd to pass on to the launcher.
+    pub fn post(&self) -> Result<()> {
+        self.post_to(&ALLIUM_NOTIFICATIONS)
+    }
+
+    fn post_to(&self, spool: &Path) -> Result<()> {
+        // Appending a single line keeps concurrent posts from interleaving
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(spool)?
+            .write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// The notifications passed on by alliumd, if any.
+    pub fn from_env() -> Vec<Self> {
+        std::env::var(ALLIUM_NOTIFICATIONS_ENV)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Queued {
+    /// Increases with every notification, so that newer ones aren't acknowledged by mistake.
+    id: u64,
+    notification: Notification,
+}
+
+/// Notifications waiting to be shown by the launcher, oldest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationQueue {
+    queued: VecDeque<Queued>,
+    next_id: u64,
+    /// Last notification passed on to the launcher, until it acknowledges them.
+    delivered: Option<u64>,
+}
+
+impl NotificationQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    /// Queues `notification`, replacing one with the same key. If the queue is full, the oldest of
+    /// the least severe notifications is dropped, unless the new one is even less severe.
+    pub fn push(&mut self, notification: Notification, now: DateTime<Utc>) {
+        self.remove_expired(now);
+        if notification.has_expired(now) {
+            return;
+        }
+        self.queued
+            .retain(|queued| queued.notification.key != notification.key);
+
+        if self.queued.len() >= MAX_NOTIFICATIONS {
+            let (i, least) = self
+                .queued
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, queued)| (queued.notification.severity, queued.id))
+                .unwrap();
+            if notification.severity < least.notification.severity {
+                return;
+            }
+            self.queued.remove(i);
+        }
+
+        self.queued.push_back(Queued {
+            id: self.next_id,
+            notification,
+        });
+        self.next_id += 1;
+    }
+
+    /// Moves the notifications posted to the spool into the queue. Returns whether there were any.
+    pub fn collect(&mut self, now: DateTime<Utc>) -> Result<bool> {
+        self.collect_from(&ALLIUM_NOTIFICATIONS, now)
+    }
+
+    fn collect_from(&mut self, spool: &Path, now: DateTime<Utc>) -> Result<bool> {
+        // Posts made while reading go to a new spool instead of being lost
+        let taken = spool.with_extension("taken");
+        match fs::rename(spool, &taken) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        let text = fs::read_to_string(&taken)?;
+        fs::remove_file(&taken)?;
+
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(notification) => self.push(notification, now),
+                Err(e) => warn!("ignoring invalid notification {:?}: {}", line, e),
+            }
+        }
+        Ok(true)
+    }
+
+    /// The notifications to pass on to the launcher. They stay queued until acknowledged.
+    pub fn replay(&mut self, now: DateTime<Utc>) -> Vec<Notification> {
+        self.remove_expired(now);
+        self.delivered = self.queued.back().map(|queued| queued.id);
+        self.queued
+            .iter()
+            .map(|queued| queued.notification.clone())
+            .collect()
+    }
+
+    /// Drops the notifications that were last replayed, now that the launcher has received them.
+    /// Notifications queued since are kept.
+    pub fn acknowledge(&mut self) -> bool {
+        let Some(delivered) = self.delivered.take() else {
+            return false;
+        };
+        self.queued.retain(|queued| queued.id > delivered);
+        true
+    }
+
+    fn remove_expired(&mut self, now: DateTime<Utc>) {
+        self.queued
+            .retain(|queued| !queued.notification.has_expired(now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    fn notification(key: &str, severity: Severity) -> Notification {
+        Notification::new(key, severity, format!("{} happened", key))
+    }
+
+    fn keys(notifications: &[Notification]) -> Vec<&str> {
+        notifications.iter().map(|n| n.key.as_str()).collect()
+    }
+
+    #[test]
+    fn test_deduplicates_by_key() {
+        let now = Utc::now();
+        let mut queue = NotificationQueue::new();
+        for _ in 0..50 {
+            queue.push(notification("sync-failed", Severity::Error), now);
+        }
+        queue.push(notification("scrape-done", Severity::Info), now);
+        queue.push(
+            Notification::new("sync-failed", Severity::Error, "again".to_string()),
+            now,
+        );
+
+        let replayed = queue.replay(now);
+        assert_eq!(keys(&replayed), ["scrape-done", "sync-failed"]);
+        assert_eq!(replayed[1].text, "again");
+    }
+
+    #[test]
+    fn test_bounded() {
+        let now = Utc::now();
+        let mut queue = NotificationQueue::new();
+        queue.push(notification("warning", Severity::Warning), now);
+        for i in 0..MAX_NOTIFICATIONS {
+            queue.push(notification(&format!("info-{}", i), Severity::Info), now);
+        }
+        assert_eq!(queue.len(), MAX_NOTIFICATIONS);
+        assert_eq!(keys(&queue.replay(now)[..2]), ["warning", "info-1"]);
+
+        // The oldest info makes room, the warning is kept
+        queue.push(notification("error", Severity::Error), now);
+        let replayed = queue.replay(now);
+        assert_eq!(replayed.len(), MAX_NOTIFICATIONS);
+        assert_eq!(keys(&replayed[..2]), ["warning", "info-2"]);
+        assert_eq!(replayed.last().unwrap().key, "error");
+
+        // A new info drops the oldest one
+        queue.push(notification("info-new", Severity::Info), now);
+        let replayed = queue.replay(now);
+        assert!(!keys(&replayed).contains(&"info-2"));
+        assert_eq!(replayed.last().unwrap().key, "info-new");
+    }
+
+    #[test]
+    fn test_less_severe_dropped_when_full() {
+        let now = Utc::now();
+        let mut queue = NotificationQueue::new();
+        for i in 0..MAX_NOTIFICATIONS {
+            queue.push(notification(&format!("error-{}", i), Severity::Error), now);
+        }
+        queue.push(notification("info", Severity::Info), now);
+        assert!(!keys(&queue.replay(now)).contains(&"info"));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let now = Utc::now();
+        let mut queue = NotificationQueue::new();
+        queue.push(
+            notification("update", Severity::Info).expires_in(Duration::hours(1)),
+            now,
+        );
+        queue.push(notification("sync-failed", Severity::Error), now);
+        assert_eq!(queue.replay(now).len(), 2);
+
+        let later = now + Duration::hours(2);
+        assert_eq!(keys(&queue.replay(later)), ["sync-failed"]);
+
+        // Already expired notifications aren't queued at all
+        queue.push(
+            notification("stale", Severity::Info).expires_in(Duration::hours(1)),
+            later,
+        );
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn test_replay_handshake() {
+        let now = Utc::now();
+        let mut queue = NotificationQueue::new();
+        queue.push(notification("a", Severity::Info), now);
+        queue.push(notification("b", Severity::Warning), now);
+
+        // Nothing was replayed yet, so there is nothing to acknowledge
+        assert!(!queue.acknowledge());
+        assert_eq!(queue.len(), 2);
+
+        // A launcher that didn't get ready gets the same notifications again
+        assert_eq!(keys(&queue.replay(now)), ["a", "b"]);
+        assert_eq!(keys(&queue.replay(now)), ["a", "b"]);
+
+        // Notifications queued after the replay survive the acknowledgement, including a renewed
+        // one with the key of a replayed notification
+        queue.push(notification("c", Severity::Info), now);
+        queue.push(notification("a", Severity::Info), now);
+        assert!(queue.acknowledge());
+        assert_eq!(keys(&queue.replay(now)), ["c", "a"]);
+
+        assert!(queue.acknowledge());
+        assert!(queue.is_empty());
+        assert!(queue.replay(now).is_empty());
+    }
+
+    #[test]
+    fn test_persisted() -> Result<()> {
+        let now = Utc::now();
+        let mut queue = NotificationQueue::new();
+        queue.push(notification("a", Severity::Info), now);
+        queue.replay(now);
+
+        let mut queue: NotificationQueue = serde_json::from_str(&serde_json::to_string(&queue)?)?;
+        queue.push(notification("b", Severity::Info), now);
+        assert!(queue.acknowledge());
+        assert_eq!(keys(&queue.replay(now)), ["b"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_spool() -> Result<()> {
+        let dir = temp_dir("notifications");
+        let spool = dir.join("notifications");
+
+        let now = Utc::now();
+        let mut queue = NotificationQueue::new();
+        assert!(!queue.collect_from(&spool, now)?);
+
+        notification("a", Severity::Info).post_to(&spool)?;
+        notification("b", Severity::Error).post_to(&spool)?;
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&spool)?
+            .write_all(b"not json\n")?;
+        assert!(queue.collect_from(&spool, now)?);
+        assert!(!spool.exists());
+        assert_eq!(keys(&queue.replay(now)), ["a", "b"]);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
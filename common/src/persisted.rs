@@ -0,0 +1,186 @@
+//! Versioned envelope for state that is persisted between runs.
+//!
+//! State is written as `{"version": N, "state": ...}`. Files written before the envelope was
+//! introduced are read as version 0. When the format of a state struct changes in a way that
+//! `#[serde(default)]` can't cover, bump its [`Versioned::VERSION`] and convert the old JSON in
+//! [`Versioned::upgrade`] instead of discarding the user's state.
+//!
+//! State written by earlier releases must keep loading, so each crate's `testdata/state` has a
+//! fixture per version that its tests load. Don't edit the fixtures; add one for each new version.
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// Version of the format that is currently written.
+    const VERSION: u32;
+
+    /// Converts `state` from `version` to `version + 1`. The default leaves it unchanged, which is
+    /// only correct while the format hasn't changed since `version`.
+    fn upgrade(version: u32, state: Value) -> Result<Value> {
+        let _ = version;
+        Ok(state)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Persisted<T> {
+    version: u32,
+    state: T,
+}
+
+/// Deserializes versioned state, upgrading it from older versions if needed.
+pub fn from_str<T: Versioned>(json: &str) -> Result<T> {
+    from_value(serde_json::from_str(json)?)
+}
+
+/// Deserializes versioned state from a reader, upgrading it from older versions if needed.
+pub fn from_reader<T: Versioned>(reader: impl Read) -> Result<T> {
+    from_value(serde_json::from_reader(reader)?)
+}
+
+fn from_value<T: Versioned>(value: Value) -> Result<T> {
+    let (mut version, mut state) = match value {
+        Value::Object(mut map)
+            if map.len() == 2 && map.contains_key("version") && map.contains_key("state") =>
+        {
+            let version = map
+                .remove("version")
+                .and_then(|v| v.as_u64())
+                .context("state version is not a number")?;
+            (version as u32, map.remove("state").unwrap())
+        }
+        value => (0, value),
+    };
+
+    if version > T::VERSION {
+        bail!(
+            "state was written by a newer version ({} > {})",
+            version,
+            T::VERSION
+        );
+    }
+    while version < T::VERSION {
+        state = T::upgrade(version, state)
+            .with_context(|| format!("failed to upgrade state from version {}", version))?;
+        version += 1;
+    }
+
+    Ok(serde_json::from_value(state)?)
+}
+
+/// Serializes state in the versioned envelope.
+pub fn to_string<T: Versioned>(state: &T) -> Result<String> {
+    Ok(serde_json::to_string(&Persisted {
+        version: T::VERSION,
+        state,
+    })?)
+}
+
+/// Serializes state in the versioned envelope to a writer.
+pub fn to_writer<T: Versioned>(writer: impl Write, state: &T) -> Result<()> {
+    serde_json::to_writer(
+        writer,
+        &Persisted {
+            version: T::VERSION,
+            state,
+        },
+    )?;
+    Ok(())
+}
+
+/// Loads state from `path`. Returns None if there is no state, or if it can't be read, in which
+/// case the file is removed.
+pub fn load<T: Versioned>(path: &Path) -> Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file = File::open(path)?;
+    match from_reader(file) {
+        Ok(state) => Ok(Some(state)),
+        Err(e) => {
+            warn!("failed to deserialize {:?}, deleting: {}", path, e);
+            fs::remove_file(path)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Saves state to `path`.
+pub fn save<T: Versioned>(path: &Path, state: &T) -> Result<()> {
+    let file = File::create(path)?;
+    to_writer(file, state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct State {
+        name: String,
+        #[serde(default)]
+        count: u32,
+    }
+
+    impl Versioned for State {
+        const VERSION: u32 = 2;
+
+        fn upgrade(version: u32, mut state: Value) -> Result<Value> {
+            // Version 1 renamed `title` to `name`
+            if version == 0 {
+                let title = state["title"].take();
+                state["name"] = title;
+            }
+            Ok(state)
+        }
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let state = State {
+            name: "Allium".to_string(),
+            count: 3,
+        };
+        let json = to_string(&state)?;
+        assert_eq!(json, r#"{"version":2,"state":{"name":"Allium","count":3}}"#);
+        assert_eq!(from_str::<State>(&json)?, state);
+        Ok(())
+    }
+
+    #[test]
+    fn test_upgrade_unversioned() -> Result<()> {
+        assert_eq!(
+            from_str::<State>(r#"{"title":"Allium"}"#)?,
+            State {
+                name: "Allium".to_string(),
+                count: 0,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_upgrade_from_intermediate_version() -> Result<()> {
+        assert_eq!(
+            from_str::<State>(r#"{"version":1,"state":{"name":"Allium","count":1}}"#)?,
+            State {
+                name: "Allium".to_string(),
+                count: 1,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        assert!(from_str::<State>(r#"{"version":3,"state":{"name":"Allium"}}"#).is_err());
+    }
+}
@@ -43,6 +43,18 @@ impl EvdevKeys {
         })
     }
 
+    /// Grabs the device exclusively, so that no other process receives its events until it is
+    /// released.
+    pub fn grab(&mut self, grab: bool) -> Result<()> {
+        let device = self.events.device_mut();
+        if grab {
+            device.grab()?;
+        } else {
+            device.ungrab()?;
+        }
+        Ok(())
+    }
+
     pub async fn poll(&mut self) -> KeyEvent {
         loop {
             let event = self.events.next_event().await.unwrap();
@@ -62,6 +74,9 @@ impl EvdevKeys {
                         _ => unreachable!(),
                     };
                 }
+                EventType::SWITCH if event.code() == evdev::SwitchType::SW_LID.0 => {
+                    return KeyEvent::Lid(event.value() != 0);
+                }
                 _ => {}
             }
         }
@@ -1,14 +1,20 @@
+use std::fs::OpenOptions;
+
 use anyhow::{anyhow, bail, Result};
 use embedded_graphics::prelude::*;
 use embedded_graphics::primitives::Rectangle;
 use embedded_graphics::Pixel;
 use framebuffer::Framebuffer;
+use image::{Rgb, RgbImage};
 use log::{trace, warn};
 
 use crate::display::color::Color;
+use crate::display::greyscale;
 use crate::display::Display;
 use crate::geom::Rect;
 
+const FRAMEBUFFER_DEVICE: &str = "/dev/fb0";
+
 pub struct Buffer {
     buffer: Vec<u8>,
     size: Size,
@@ -23,7 +29,13 @@ pub struct FramebufferDisplay {
 
 impl FramebufferDisplay {
     pub fn new() -> Result<FramebufferDisplay> {
-        let iface = Framebuffer::new("/dev/fb0")?;
+        // The framebuffer crate only keeps the message of IO errors, so open the device first to
+        // tell a busy framebuffer apart from a missing one
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(FRAMEBUFFER_DEVICE)?;
+        let iface = Framebuffer::new(FRAMEBUFFER_DEVICE)?;
         trace!(
             "init fb: var_screen_info: {:?}, fix_screen_info: {:?}",
             iface.var_screen_info,
@@ -81,9 +93,17 @@ impl Display for FramebufferDisplay {
             self.iface.var_screen_info.yoffset as usize,
         );
         let width = self.framebuffer.size.width as usize;
-        let location = (yoffset * width + xoffset) * self.framebuffer.bytes_per_pixel as usize;
-        self.iface.frame[location..location + self.framebuffer.buffer.len()]
-            .copy_from_slice(&self.framebuffer.buffer);
+        let bytes_per_pixel = self.framebuffer.bytes_per_pixel as usize;
+        let location = (yoffset * width + xoffset) * bytes_per_pixel;
+        let frame = &mut self.iface.frame[location..location + self.framebuffer.buffer.len()];
+        // Only what reaches the screen is greyscale, so that blending and restoring the saved
+        // screen still work with the theme's colors
+        match greyscale::greyscale() {
+            Some(greyscale) => {
+                greyscale.transform(&self.framebuffer.buffer, frame, width, bytes_per_pixel)
+            }
+            None => frame.copy_from_slice(&self.framebuffer.buffer),
+        }
         Ok(())
     }
 
@@ -92,6 +112,17 @@ impl Display for FramebufferDisplay {
         Ok(())
     }
 
+    fn capture(&self) -> Result<RgbImage> {
+        let Size { width, height } = self.framebuffer.size;
+        let bytespp = self.framebuffer.bytes_per_pixel;
+        Ok(RgbImage::from_fn(width, height, |x, y| {
+            // rotate 180 degrees
+            let index = ((width - x - 1 + (height - y - 1) * width) * bytespp) as usize;
+            let raw = &self.framebuffer.buffer[index..index + 3];
+            Rgb([raw[2], raw[1], raw[0]])
+        }))
+    }
+
     fn load(&mut self, mut rect: Rect) -> Result<()> {
         let Some(ref saved) = self.saved else {
             bail!("No saved image");
@@ -187,18 +218,16 @@ impl DrawTarget for Buffer {
             if 0 <= x && x < width && 0 <= y && y < height {
                 let index: u32 = (x as u32 + y as u32 * width as u32) * bytespp;
 
-                let a = color.a() as u32;
-                let a_inv = 255 - a;
-
-                let b = (self.buffer[index as usize] as u32 * a_inv + color.b() as u32 * a) / 255;
-                let g =
-                    (self.buffer[index as usize + 1] as u32 * a_inv + color.g() as u32 * a) / 255;
-                let r =
-                    (self.buffer[index as usize + 2] as u32 * a_inv + color.r() as u32 * a) / 255;
+                let index = index as usize;
+                let color = color.over(Color::new(
+                    self.buffer[index + 2],
+                    self.buffer[index + 1],
+                    self.buffer[index],
+                ));
 
-                self.buffer[index as usize] = b as u8;
-                self.buffer[index as usize + 1] = g as u8;
-                self.buffer[index as usize + 2] = r as u8;
+                self.buffer[index] = color.b();
+                self.buffer[index + 1] = color.g();
+                self.buffer[index + 2] = color.r();
             }
         }
 
@@ -0,0 +1,31 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::led::LedPattern;
+
+/// The power LED, driven through the kernel's LED class.
+const LED: &str = "/sys/class/leds/led1";
+
+pub fn set_led(pattern: LedPattern) -> Result<()> {
+    let led = Path::new(LED);
+    match pattern.blink_interval() {
+        Some(interval) => {
+            fs::write(led.join("trigger"), "timer")?;
+            // The delays only exist once the timer trigger is set
+            let millis = interval.as_millis().to_string();
+            fs::write(led.join("delay_on"), &millis)?;
+            fs::write(led.join("delay_off"), &millis)?;
+        }
+        None => {
+            fs::write(led.join("trigger"), "none")?;
+            let brightness = match pattern {
+                LedPattern::Solid => fs::read_to_string(led.join("max_brightness"))?,
+                _ => "0".to_string(),
+            };
+            fs::write(led.join("brightness"), brightness.trim())?;
+        }
+    }
+    Ok(())
+}
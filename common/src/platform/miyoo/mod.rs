@@ -1,6 +1,7 @@
 mod battery;
 mod evdev;
 mod framebuffer;
+mod led;
 mod screen;
 mod volume;
 
@@ -13,10 +14,12 @@ use async_trait::async_trait;
 
 use crate::battery::Battery;
 use crate::display::settings::DisplaySettings;
+use crate::led::LedPattern;
 use crate::platform::miyoo::evdev::EvdevKeys;
 use crate::platform::miyoo::framebuffer::FramebufferDisplay;
 use crate::platform::KeyEvent;
 use crate::platform::Platform;
+use crate::volume::AudioOutput;
 
 use self::battery::{Miyoo283Battery, Miyoo354Battery};
 
@@ -92,6 +95,18 @@ impl Platform for MiyooPlatform {
         }
     }
 
+    fn get_volume(&self) -> Result<Option<i32>> {
+        match self.model {
+            MiyooDeviceModel::Miyoo283 => Ok(None),
+            MiyooDeviceModel::Miyoo354 => Ok(Some(volume::get_volume())),
+        }
+    }
+
+    fn audio_output(&self) -> AudioOutput {
+        // Headphones aren't detected yet, so the speaker limits always apply
+        AudioOutput::Speaker
+    }
+
     fn get_brightness(&self) -> Result<u8> {
         screen::get_brightness()
     }
@@ -100,10 +115,22 @@ impl Platform for MiyooPlatform {
         screen::set_brightness(brightness)
     }
 
+    fn set_backlight(&mut self, on: bool) -> Result<()> {
+        screen::set_backlight(on)
+    }
+
     fn set_display_settings(&mut self, settings: &DisplaySettings) -> Result<()> {
         screen::set_display_settings(settings)
     }
 
+    fn set_led(&mut self, pattern: LedPattern) -> Result<()> {
+        led::set_led(pattern)
+    }
+
+    fn grab_input(&mut self, grab: bool) -> Result<()> {
+        self.keys.grab(grab)
+    }
+
     fn device_model() -> String {
         detect_model().to_string()
     }
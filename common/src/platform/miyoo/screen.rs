@@ -19,6 +19,13 @@ pub fn set_brightness(brightness: u8) -> Result<()> {
     Ok(())
 }
 
+pub fn set_backlight(on: bool) -> Result<()> {
+    // The lowest brightness still lights the screen, so disable the PWM instead
+    let mut file = File::create("/sys/devices/soc0/soc/1f003400.pwm/pwm/pwmchip0/pwm0/enable")?;
+    file.write_all(if on { b"1" } else { b"0" })?;
+    Ok(())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct SystemConfig {
     vol: u8,
@@ -1,19 +1,38 @@
+use std::cell::Cell;
+use std::io;
+
 use anyhow::Result;
 use async_trait::async_trait;
 use embedded_graphics::prelude::*;
+use image::buffer::ConvertBuffer;
+use image::{RgbImage, Rgba, RgbaImage};
 
 use crate::battery::Battery;
 use crate::display::color::Color;
 use crate::display::settings::DisplaySettings;
 use crate::display::Display;
 use crate::geom::Rect;
+use crate::led::LedPattern;
 use crate::platform::{KeyEvent, Platform};
+use crate::volume::AudioOutput;
 
 pub const SCREEN_WIDTH: u32 = 640;
 pub const SCREEN_HEIGHT: u32 = 480;
 
 pub struct MockPlatform;
 
+thread_local! {
+    static DISPLAY_FAILURES: Cell<(u32, io::ErrorKind)> = const { Cell::new((0, io::ErrorKind::Other)) };
+}
+
+impl MockPlatform {
+    /// Makes the next `count` attempts to open the display on this thread fail with `kind`, such
+    /// as `ResourceBusy` for a display that the boot process is still holding.
+    pub fn fail_display(count: u32, kind: io::ErrorKind) {
+        DISPLAY_FAILURES.with(|failures| failures.set((count, kind)));
+    }
+}
+
 #[async_trait(?Send)]
 impl Platform for MockPlatform {
     type Display = MockDisplay;
@@ -28,7 +47,12 @@ impl Platform for MockPlatform {
     }
 
     fn display(&mut self) -> Result<Self::Display> {
-        Ok(MockDisplay)
+        let (count, kind) = DISPLAY_FAILURES.with(Cell::get);
+        if count > 0 {
+            DISPLAY_FAILURES.with(|failures| failures.set((count - 1, kind)));
+            return Err(io::Error::from(kind).into());
+        }
+        Ok(MockDisplay::new())
     }
 
     fn battery(&self) -> Result<Self::Battery> {
@@ -43,6 +67,14 @@ impl Platform for MockPlatform {
         Ok(())
     }
 
+    fn get_volume(&self) -> Result<Option<i32>> {
+        Ok(None)
+    }
+
+    fn audio_output(&self) -> AudioOutput {
+        AudioOutput::Speaker
+    }
+
     fn get_brightness(&self) -> Result<u8> {
         Ok(50)
     }
@@ -51,10 +83,22 @@ impl Platform for MockPlatform {
         Ok(())
     }
 
+    fn set_backlight(&mut self, _on: bool) -> Result<()> {
+        Ok(())
+    }
+
     fn set_display_settings(&mut self, _settings: &DisplaySettings) -> Result<()> {
         Ok(())
     }
 
+    fn set_led(&mut self, _pattern: LedPattern) -> Result<()> {
+        Ok(())
+    }
+
+    fn grab_input(&mut self, _grab: bool) -> Result<()> {
+        Ok(())
+    }
+
     fn device_model() -> String {
         "Mock".to_string()
     }
@@ -74,23 +118,59 @@ impl Default for MockPlatform {
     }
 }
 
-pub struct MockDisplay;
+/// A display that draws into memory, so that tests can check what was drawn.
+pub struct MockDisplay {
+    image: RgbaImage,
+    saved: RgbaImage,
+}
+
+impl MockDisplay {
+    /// A black screen, which is also what loading restores until something is saved.
+    pub fn new() -> Self {
+        let image = RgbaImage::from_pixel(SCREEN_WIDTH, SCREEN_HEIGHT, Rgba([0, 0, 0, 255]));
+        Self {
+            saved: image.clone(),
+            image,
+        }
+    }
+}
+
+impl Default for MockDisplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Display for MockDisplay {
-    fn map_pixels<F>(&mut self, _f: F) -> Result<()>
+    fn map_pixels<F>(&mut self, mut f: F) -> Result<()>
     where
         F: FnMut(Color) -> Color,
     {
+        for pixel in self.image.pixels_mut() {
+            let Rgba([r, g, b, a]) = *pixel;
+            *pixel = f(Color::rgba(r, g, b, a)).into();
+        }
         Ok(())
     }
 
     fn save(&mut self) -> Result<()> {
+        self.saved = self.image.clone();
         Ok(())
     }
 
-    fn load(&mut self, _area: Rect) -> Result<()> {
+    fn load(&mut self, area: Rect) -> Result<()> {
+        for y in area.y.max(0)..(area.y + area.h as i32).min(SCREEN_HEIGHT as i32) {
+            for x in area.x.max(0)..(area.x + area.w as i32).min(SCREEN_WIDTH as i32) {
+                let pixel = *self.saved.get_pixel(x as u32, y as u32);
+                self.image.put_pixel(x as u32, y as u32, pixel);
+            }
+        }
         Ok(())
     }
+
+    fn capture(&self) -> Result<RgbImage> {
+        Ok(self.image.convert())
+    }
 }
 
 impl DrawTarget for MockDisplay {
@@ -98,10 +178,20 @@ impl DrawTarget for MockDisplay {
 
     type Error = anyhow::Error;
 
-    fn draw_iter<I>(&mut self, _pixels: I) -> Result<()>
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<()>
     where
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
+        for Pixel(point, color) in pixels {
+            if point.x >= 0
+                && point.y >= 0
+                && (point.x as u32) < SCREEN_WIDTH
+                && (point.y as u32) < SCREEN_HEIGHT
+            {
+                self.image
+                    .put_pixel(point.x as u32, point.y as u32, color.into());
+            }
+        }
         Ok(())
     }
 }
@@ -11,9 +11,14 @@ use async_trait::async_trait;
 use enum_map::Enum;
 use serde::{Deserialize, Serialize};
 
+use log::warn;
+
 use crate::{
     battery::Battery,
     display::{settings::DisplaySettings, Display},
+    led::LedPattern,
+    retry::{self, RetryPolicy},
+    volume::AudioOutput,
 };
 
 #[cfg(feature = "miyoo")]
@@ -45,12 +50,27 @@ pub trait Platform {
 
     fn set_volume(&mut self, volume: i32) -> Result<()>;
 
+    /// Reads the volume level back from the mixer, or None if it can't be read.
+    fn get_volume(&self) -> Result<Option<i32>>;
+
+    /// Where sound is currently played. Platforms that can't detect headphones report the speaker.
+    fn audio_output(&self) -> AudioOutput;
+
     fn get_brightness(&self) -> Result<u8>;
 
     fn set_brightness(&mut self, brightness: u8) -> Result<()>;
 
+    /// Turns the backlight off while the lid is closed, and back on when it opens.
+    fn set_backlight(&mut self, on: bool) -> Result<()>;
+
     fn set_display_settings(&mut self, settings: &DisplaySettings) -> Result<()>;
 
+    fn set_led(&mut self, pattern: LedPattern) -> Result<()>;
+
+    /// Takes the keys for this process alone while `grab` is set, so that other processes, e.g.
+    /// the game, don't see them.
+    fn grab_input(&mut self, grab: bool) -> Result<()>;
+
     fn device_model() -> String;
 
     fn firmware() -> String;
@@ -58,14 +78,37 @@ pub trait Platform {
     fn has_wifi() -> bool;
 }
 
+/// Opens the display, trying again for a while if it's busy, such as while the boot process still
+/// holds the framebuffer. Fails right away if there is no display at all.
+pub fn open_display<P: Platform>(platform: &mut P) -> Result<P::Display> {
+    let policy = DisplaySettings::load().map_or_else(
+        |e| {
+            warn!("failed to load display settings: {}", e);
+            RetryPolicy::default()
+        },
+        |settings| settings.open_retry,
+    );
+    open_display_with(platform, &policy)
+}
+
+/// Like `open_display`, trying as `policy` says.
+pub fn open_display_with<P: Platform>(
+    platform: &mut P,
+    policy: &RetryPolicy,
+) -> Result<P::Display> {
+    policy.retry("opening display", || platform.display(), retry::is_busy)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyEvent {
     Pressed(Key),
     Released(Key),
     Autorepeat(Key),
+    /// The lid switch of a clamshell device was closed (true) or opened (false).
+    Lid(bool),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Enum)]
 pub enum Key {
     Up,
     Down,
@@ -11,23 +11,28 @@ use embedded_graphics::prelude::*;
 use embedded_graphics_simulator::{
     OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
 };
-use image::{buffer::ConvertBuffer, ImageBuffer, Rgba};
+use image::{buffer::ConvertBuffer, ImageBuffer, Rgb, RgbImage, Rgba};
 use itertools::iproduct;
-use log::{trace, warn};
+use log::{debug, trace, warn};
 use sdl2::keyboard::Keycode;
 
 use crate::battery::Battery;
 use crate::display::color::Color;
+use crate::display::greyscale;
 use crate::display::settings::DisplaySettings;
 use crate::display::Display;
 use crate::geom::Rect;
+use crate::led::LedPattern;
 use crate::platform::{Key, KeyEvent, Platform};
+use crate::volume::AudioOutput;
 
 pub const SCREEN_WIDTH: u32 = 640;
 pub const SCREEN_HEIGHT: u32 = 480;
 
 pub struct SimulatorPlatform {
     window: Rc<RefCell<Window>>,
+    /// C toggles a fake lid switch, as on clamshell devices.
+    lid_closed: bool,
 }
 
 #[async_trait(?Send)]
@@ -40,6 +45,7 @@ impl Platform for SimulatorPlatform {
         let window = Window::new("Allium Simulator", &output_settings);
         Ok(SimulatorPlatform {
             window: Rc::new(RefCell::new(window)),
+            lid_closed: false,
         })
     }
 
@@ -54,6 +60,13 @@ impl Platform for SimulatorPlatform {
                         if keycode == Keycode::Q {
                             process::exit(0);
                         }
+                        if keycode == Keycode::C {
+                            if repeat {
+                                continue;
+                            }
+                            self.lid_closed = !self.lid_closed;
+                            return KeyEvent::Lid(self.lid_closed);
+                        }
                         return if repeat {
                             KeyEvent::Autorepeat(Key::from(keycode))
                         } else {
@@ -101,6 +114,14 @@ impl Platform for SimulatorPlatform {
         Ok(())
     }
 
+    fn get_volume(&self) -> Result<Option<i32>> {
+        Ok(None)
+    }
+
+    fn audio_output(&self) -> AudioOutput {
+        AudioOutput::Speaker
+    }
+
     fn get_brightness(&self) -> Result<u8> {
         Ok(50)
     }
@@ -109,10 +130,25 @@ impl Platform for SimulatorPlatform {
         Ok(())
     }
 
+    fn set_backlight(&mut self, on: bool) -> Result<()> {
+        debug!("setting backlight: {}", on);
+        Ok(())
+    }
+
     fn set_display_settings(&mut self, _settings: &DisplaySettings) -> Result<()> {
         Ok(())
     }
 
+    fn set_led(&mut self, pattern: LedPattern) -> Result<()> {
+        debug!("setting LED: {:?}", pattern);
+        Ok(())
+    }
+
+    fn grab_input(&mut self, grab: bool) -> Result<()> {
+        debug!("grabbing input: {}", grab);
+        Ok(())
+    }
+
     fn device_model() -> String {
         "Simulator".to_string()
     }
@@ -156,10 +192,32 @@ impl Display for SimulatorWindow {
     }
 
     fn flush(&mut self) -> Result<()> {
-        self.window.borrow_mut().update(&self.display);
+        let Some(greyscale) = greyscale::greyscale() else {
+            self.window.borrow_mut().update(&self.display);
+            return Ok(());
+        };
+
+        let size = self.display.size();
+        let mut display = SimulatorDisplay::new(size);
+        display.draw_iter(iproduct!(0..size.height as i32, 0..size.width as i32).map(
+            |(y, x)| {
+                let point = Point::new(x, y);
+                let grey = greyscale.grey(self.display.get_pixel(point), x as usize, y as usize);
+                Pixel(point, Color::new(grey, grey, grey))
+            },
+        ))?;
+        self.window.borrow_mut().update(&display);
         Ok(())
     }
 
+    fn capture(&self) -> Result<RgbImage> {
+        let Size { width, height } = self.display.size();
+        Ok(RgbImage::from_fn(width, height, |x, y| {
+            let color = self.display.get_pixel(Point::new(x as i32, y as i32));
+            Rgb([color.r(), color.g(), color.b()])
+        }))
+    }
+
     fn save(&mut self) -> Result<()> {
         let image = self
             .display
@@ -212,16 +270,7 @@ impl DrawTarget for SimulatorWindow {
             .into_iter()
             .map(|p| {
                 let curr = self.display.get_pixel(p.0);
-                let color = p.1;
-
-                let a = color.a() as u32;
-                let a_inv = 255 - a;
-
-                let b = (curr.b() as u32 * a_inv + color.b() as u32 * a) / 255;
-                let g = (curr.g() as u32 * a_inv + color.g() as u32 * a) / 255;
-                let r = (curr.r() as u32 * a_inv + color.r() as u32 * a) / 255;
-
-                Pixel(p.0, Color::new(r as u8, g as u8, b as u8))
+                Pixel(p.0, p.1.over(curr))
             })
             .collect();
         Ok(self.display.draw_iter(pixels)?)
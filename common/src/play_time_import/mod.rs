@@ -0,0 +1,426 @@
+//! Imports play time recorded by other firmware, so that switching to Allium doesn't lose it.
+//!
+//! Each firmware's records are read by a [`Source`]. The games they recorded are matched to the
+//! library by the longest common path suffix, e.g. `GBA/Game.gba`, and by fingerprint when the
+//! suffix matches several games or none. Games that still match several are ambiguous, and are
+//! left out rather than guessed.
+//!
+//! The source rows that were imported are recorded, so importing again only adds what was played
+//! since.
+
+mod onion;
+
+pub use self::onion::Onion;
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+use chrono::Duration;
+use log::warn;
+
+use crate::database::{Database, ImportedGame};
+use crate::fingerprint::Fingerprint;
+
+/// A row of play time recorded by another firmware, e.g. a play session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignRow {
+    /// Identifies the row within its source.
+    pub id: i64,
+    /// Game as the source recorded it.
+    pub path: PathBuf,
+    pub play_time: Duration,
+    /// Unix timestamp of when the game was played.
+    pub last_played: i64,
+}
+
+/// Play time recorded by another firmware, in its own format.
+pub trait Source {
+    /// Identifies the source in the imported rows, so that they aren't imported twice.
+    fn id(&self) -> &'static str;
+
+    /// Name of the firmware, shown to the user.
+    fn name(&self) -> &'static str;
+
+    /// Every row with play time.
+    fn rows(&self) -> Result<Vec<ForeignRow>>;
+
+    /// Where the game the source recorded as `path` is on the SD card.
+    fn resolve(&self, path: &Path) -> PathBuf;
+}
+
+/// Sources of play time found on the SD card at `sd_root`.
+pub fn detect(sd_root: &Path) -> Vec<Box<dyn Source>> {
+    let mut sources: Vec<Box<dyn Source>> = Vec::new();
+    if let Some(onion) = Onion::detect(sd_root) {
+        sources.push(Box::new(onion));
+    }
+    sources
+}
+
+/// What importing from a source would do, worked out without changing anything so that it can be
+/// previewed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportPlan {
+    source: &'static str,
+    /// Matched games with play time that isn't imported yet, oldest played first.
+    games: Vec<ImportedGame>,
+    /// Games that match several games in the library.
+    pub ambiguous: usize,
+    /// Games that match nothing in the library.
+    pub unmatched: usize,
+}
+
+impl ImportPlan {
+    pub fn new(database: &Database, source: &dyn Source) -> Result<Self> {
+        let imported = database.imported_rows(source.id())?;
+        let mut foreign: HashMap<PathBuf, Vec<ForeignRow>> = HashMap::new();
+        for row in source.rows()? {
+            if !imported.contains(&row.id) {
+                foreign.entry(row.path.clone()).or_default().push(row);
+            }
+        }
+
+        let library: Vec<PathBuf> = database
+            .select_all_games()?
+            .into_iter()
+            .map(|game| game.path)
+            .collect();
+        let mut matcher = Matcher::new(database, &library);
+
+        // Several recorded paths can be the same game, e.g. before and after it was moved
+        let mut matched: HashMap<PathBuf, Vec<ForeignRow>> = HashMap::new();
+        let mut ambiguous = 0;
+        let mut unmatched = 0;
+        for (path, rows) in foreign {
+            match matcher.find(&path, &source.resolve(&path))? {
+                Match::Game(game) => matched.entry(game).or_default().extend(rows),
+                Match::Ambiguous => ambiguous += 1,
+                Match::None => unmatched += 1,
+            }
+        }
+
+        let mut games: Vec<(i64, ImportedGame)> = matched
+            .into_iter()
+            .map(|(path, rows)| {
+                let last_played = rows.iter().map(|row| row.last_played).max().unwrap_or(0);
+                let rows = rows.iter().map(|row| (row.id, row.play_time)).collect();
+                (last_played, ImportedGame { path, rows })
+            })
+            .collect();
+        games.sort_by(|(a, game_a), (b, game_b)| (a, &game_a.path).cmp(&(b, &game_b.path)));
+
+        Ok(Self {
+            source: source.id(),
+            games: games.into_iter().map(|(_, game)| game).collect(),
+            ambiguous,
+            unmatched,
+        })
+    }
+
+    /// Number of games that play time would be imported for.
+    pub fn matched(&self) -> usize {
+        self.games.len()
+    }
+
+    /// Total play time that would be imported.
+    pub fn play_time(&self) -> Duration {
+        self.games
+            .iter()
+            .flat_map(|game| game.rows.iter())
+            .fold(Duration::zero(), |total, (_, play_time)| total + *play_time)
+    }
+
+    /// Adds the play time to the library. Play time on both sides is summed, and games keep their
+    /// place in the recently played list if they were already played on Allium.
+    pub fn import(&self, database: &Database) -> Result<()> {
+        database.import_play_time(self.source, &self.games)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Match {
+    Game(PathBuf),
+    Ambiguous,
+    None,
+}
+
+/// Matches games recorded by another firmware to the library.
+struct Matcher<'a> {
+    database: &'a Database,
+    library: &'a [PathBuf],
+    /// Games by their stored fingerprint, loaded the first time a game matches no path.
+    fingerprints: Option<HashMap<Fingerprint, Vec<PathBuf>>>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(database: &'a Database, library: &'a [PathBuf]) -> Self {
+        Self {
+            database,
+            library,
+            fingerprints: None,
+        }
+    }
+
+    /// Finds the game recorded as `path`, whose file, if it's still on the SD card, is `file`.
+    fn find(&mut self, path: &Path, file: &Path) -> Result<Match> {
+        let candidates = suffix_matches(self.library, path);
+        if let [game] = candidates.as_slice() {
+            return Ok(Match::Game(game.to_path_buf()));
+        }
+
+        let fingerprint = if file.is_file() {
+            Fingerprint::of(file)
+                .map_err(|e| warn!("failed to fingerprint {}: {}", file.display(), e))
+                .ok()
+        } else {
+            None
+        };
+        let Some(fingerprint) = fingerprint else {
+            return Ok(if candidates.is_empty() {
+                Match::None
+            } else {
+                Match::Ambiguous
+            });
+        };
+
+        let same: Vec<PathBuf> = if candidates.is_empty() {
+            self.fingerprints()?
+                .get(&fingerprint)
+                .into_iter()
+                .flatten()
+                .filter(|game| same_file(&fingerprint, file, game))
+                .cloned()
+                .collect()
+        } else {
+            let mut same = Vec::new();
+            for &game in &candidates {
+                if self.fingerprint(game)? == Some(fingerprint)
+                    && same_file(&fingerprint, file, game)
+                {
+                    same.push(game.to_path_buf());
+                }
+            }
+            same
+        };
+
+        Ok(match same.as_slice() {
+            [game] => Match::Game(game.clone()),
+            [] if candidates.is_empty() => Match::None,
+            _ => Match::Ambiguous,
+        })
+    }
+
+    fn fingerprints(&mut self) -> Result<&HashMap<Fingerprint, Vec<PathBuf>>> {
+        if self.fingerprints.is_none() {
+            let mut fingerprints: HashMap<Fingerprint, Vec<PathBuf>> = HashMap::new();
+            for (path, fingerprint) in self.database.fingerprints()? {
+                fingerprints.entry(fingerprint).or_default().push(path);
+            }
+            self.fingerprints = Some(fingerprints);
+        }
+        Ok(self.fingerprints.as_ref().unwrap())
+    }
+
+    /// The game's stored fingerprint, or its file's if it has none yet.
+    fn fingerprint(&self, game: &Path) -> Result<Option<Fingerprint>> {
+        if let Some(fingerprint) = self.database.fingerprint(game)? {
+            return Ok(Some(fingerprint));
+        }
+        if !game.is_file() {
+            return Ok(None);
+        }
+        Ok(Fingerprint::of(game)
+            .map_err(|e| warn!("failed to fingerprint {}: {}", game.display(), e))
+            .ok())
+    }
+}
+
+/// Files too large to hash are only fingerprinted by size, so they must also keep their name.
+fn same_file(fingerprint: &Fingerprint, file: &Path, game: &Path) -> bool {
+    fingerprint.crc.is_some() || file.file_name() == game.file_name()
+}
+
+/// Games in the library whose paths end with the most components of `path`, at least its file
+/// name.
+fn suffix_matches<'a>(library: &'a [PathBuf], path: &Path) -> Vec<&'a Path> {
+    let mut best = 0;
+    let mut matches = Vec::new();
+    for game in library {
+        let common = game
+            .components()
+            .rev()
+            .zip(path.components().rev())
+            .take_while(|(a, b)| a == b && matches!(a, Component::Normal(_)))
+            .count();
+        if common == 0 || common < best {
+            continue;
+        }
+        if common > best {
+            best = common;
+            matches.clear();
+        }
+        matches.push(game.as_path());
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::test_utils::{temp_dir, write_game};
+
+    struct Fixture {
+        sd_root: PathBuf,
+        database: Database,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let sd_root = temp_dir(&format!("play-time-import-{}", name));
+            Self {
+                sd_root,
+                database: Database::in_memory().unwrap(),
+            }
+        }
+
+        /// Writes a game file and indexes it.
+        fn game(&self, path: &str, contents: &[u8]) -> PathBuf {
+            write_game(&self.database, &self.sd_root, path, contents)
+        }
+
+        fn plan(&self) -> ImportPlan {
+            let sources = detect(&self.sd_root);
+            ImportPlan::new(&self.database, sources[0].as_ref()).unwrap()
+        }
+
+        fn game_row(&self, path: &Path) -> (Duration, i64) {
+            let game = self
+                .database
+                .select_game(&path.display().to_string())
+                .unwrap()
+                .unwrap();
+            (game.play_time, game.last_played)
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.sd_root);
+        }
+    }
+
+    #[test]
+    fn test_suffix_matches() {
+        let library = vec![
+            PathBuf::from("/mnt/SDCARD/Roms/GBA/Game.gba"),
+            PathBuf::from("/mnt/SDCARD/Roms/GBC/Game.gba"),
+            PathBuf::from("/mnt/SDCARD/Roms/GBA/Other.gba"),
+        ];
+
+        assert_eq!(
+            suffix_matches(&library, Path::new("Roms/GBA/Game.gba")),
+            vec![library[0].as_path()]
+        );
+        assert_eq!(
+            suffix_matches(&library, Path::new("../../Roms/GBA/Game.gba")),
+            vec![library[0].as_path()]
+        );
+        assert_eq!(
+            suffix_matches(&library, Path::new("/media/Games/Game.gba")),
+            vec![library[0].as_path(), library[1].as_path()]
+        );
+        assert!(suffix_matches(&library, Path::new("Roms/GBA/Missing.gba")).is_empty());
+        assert!(suffix_matches(&library, Path::new("Roms/GBA")).is_empty());
+    }
+
+    #[test]
+    fn test_plan() {
+        let fixture = Fixture::new("plan");
+        fixture.game("Roms/GBA/Game.gba", b"game");
+        let tetris = fixture.game("Roms/GB/Tetris.gb", b"tetris");
+        fixture.game("Roms/GBC/Tetris.gb", b"tetris dx");
+        fixture.game("Roms/NES/Mario.nes", b"mario");
+        fixture.game("Roms/FDS/Mario.nes", b"mario disk");
+        // Left on the card by the other firmware, outside of the library
+        fs::write(fixture.sd_root.join("Tetris.gb"), b"tetris").unwrap();
+
+        onion::tests::write_fixture(
+            &fixture.sd_root,
+            &[
+                ("Roms/GBA/Game.gba", 3600, 100),
+                ("Roms/GBA/Game.gba", 1800, 300),
+                // Told apart by the file's fingerprint
+                ("Tetris.gb", 600, 200),
+                // Both match, and there is no file to tell them apart
+                ("Roms/FC/Mario.nes", 60, 400),
+                ("Roms/GBA/Missing.gba", 60, 500),
+            ],
+        );
+
+        let plan = fixture.plan();
+        assert_eq!(plan.matched(), 2);
+        assert_eq!(plan.ambiguous, 1);
+        assert_eq!(plan.unmatched, 1);
+        assert_eq!(plan.play_time(), Duration::seconds(3600 + 1800 + 600));
+        assert_eq!(plan.games[0].path, tetris);
+    }
+
+    #[test]
+    fn test_import() {
+        let fixture = Fixture::new("import");
+        let game = fixture.game("Roms/GBA/Game.gba", b"game");
+        let tetris = fixture.game("Roms/GB/Tetris.gb", b"tetris");
+        let played = fixture.game("Roms/GB/Played.gb", b"played");
+
+        // Played on Allium since the switch
+        fixture
+            .database
+            .increment_play_count("Played", &played, None)
+            .unwrap();
+        fixture
+            .database
+            .add_play_time(&played, Duration::seconds(10))
+            .unwrap();
+
+        onion::tests::write_fixture(
+            &fixture.sd_root,
+            &[
+                ("Roms/GBA/Game.gba", 3600, 300),
+                ("Roms/GB/Tetris.gb", 600, 200),
+                ("Roms/GB/Played.gb", 60, 400),
+            ],
+        );
+        fixture.plan().import(&fixture.database).unwrap();
+
+        // Play time is summed, and the game played on Allium stays the most recent
+        assert_eq!(fixture.game_row(&played), (Duration::seconds(70), 3));
+        assert_eq!(fixture.game_row(&game), (Duration::seconds(3600), 2));
+        assert_eq!(fixture.game_row(&tetris), (Duration::seconds(600), 1));
+
+        // Importing again changes nothing
+        let plan = fixture.plan();
+        assert_eq!(plan.matched(), 0);
+        assert_eq!(plan.play_time(), Duration::zero());
+        plan.import(&fixture.database).unwrap();
+        assert_eq!(fixture.game_row(&game), (Duration::seconds(3600), 2));
+
+        // Only what was played since is imported, once even if the plan is imported twice
+        onion::tests::write_fixture(
+            &fixture.sd_root,
+            &[
+                ("Roms/GBA/Game.gba", 3600, 300),
+                ("Roms/GB/Tetris.gb", 600, 200),
+                ("Roms/GB/Played.gb", 60, 400),
+                ("Roms/GB/Tetris.gb", 120, 500),
+            ],
+        );
+        let plan = fixture.plan();
+        assert_eq!(plan.matched(), 1);
+        plan.import(&fixture.database).unwrap();
+        plan.import(&fixture.database).unwrap();
+        assert_eq!(fixture.game_row(&tetris), (Duration::seconds(720), 1));
+    }
+}
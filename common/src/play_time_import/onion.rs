@@ -0,0 +1,177 @@
+//! Onion OS records a row for every play session in its play activity database, with the game it
+//! was for in the `rom` table. Game paths are relative to the root of the SD card.
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+use rusqlite::{Connection, OpenFlags};
+
+use crate::play_time_import::{ForeignRow, Source};
+
+/// Where Onion OS keeps the play activity database, relative to the root of the SD card.
+const PLAY_ACTIVITY_DB: &str = "Saves/CurrentProfile/play_activity/play_activity_db.sqlite";
+
+#[derive(Debug, Clone)]
+pub struct Onion {
+    sd_root: PathBuf,
+    path: PathBuf,
+}
+
+impl Onion {
+    /// Finds Onion OS's play activity on the SD card at `sd_root`.
+    pub fn detect(sd_root: &Path) -> Option<Self> {
+        let path = sd_root.join(PLAY_ACTIVITY_DB);
+        path.is_file().then(|| Self {
+            sd_root: sd_root.to_path_buf(),
+            path,
+        })
+    }
+}
+
+impl Source for Onion {
+    fn id(&self) -> &'static str {
+        "onion-play-activity"
+    }
+
+    fn name(&self) -> &'static str {
+        "Onion OS"
+    }
+
+    fn rows(&self) -> Result<Vec<ForeignRow>> {
+        let conn = Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut stmt = conn.prepare(
+            "
+SELECT play_activity.rowid, rom.file_path, play_activity.play_time,
+    COALESCE(play_activity.updated_at, play_activity.created_at, 0)
+FROM play_activity JOIN rom ON rom.id = play_activity.rom_id
+WHERE play_activity.play_time > 0 AND rom.file_path IS NOT NULL",
+        )?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(ForeignRow {
+                    id: row.get(0)?,
+                    path: PathBuf::from(row.get::<_, String>(1)?),
+                    play_time: chrono::Duration::seconds(row.get(2)?),
+                    last_played: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(rows)
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        // Older versions recorded paths relative to the emulator's folder, e.g. `../../Roms/...`
+        let relative: PathBuf = path
+            .components()
+            .skip_while(|component| {
+                matches!(
+                    component,
+                    Component::RootDir | Component::CurDir | Component::ParentDir
+                )
+            })
+            .collect();
+        if path.is_absolute() && path.starts_with(&self.sd_root) {
+            path.to_path_buf()
+        } else {
+            self.sd_root.join(relative)
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    /// Writes a play activity database with Onion OS's schema, with `(path, play_time,
+    /// updated_at)` sessions, under `sd_root`.
+    pub(crate) fn write_fixture(sd_root: &Path, sessions: &[(&str, i64, i64)]) {
+        let path = sd_root.join(PLAY_ACTIVITY_DB);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let _ = fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "
+CREATE TABLE rom(id INTEGER PRIMARY KEY, type TEXT, name TEXT, file_path TEXT, image_path TEXT,
+    created_at INTEGER DEFAULT (strftime('%s', 'now')), updated_at INTEGER);
+CREATE TABLE play_activity(rom_id INTEGER, play_time INTEGER,
+    created_at INTEGER DEFAULT (strftime('%s', 'now')), updated_at INTEGER,
+    FOREIGN KEY(rom_id) REFERENCES rom(id));",
+        )
+        .unwrap();
+        for (path, play_time, updated_at) in sessions {
+            conn.execute(
+                "INSERT INTO rom (type, name, file_path) SELECT 'ROM', ?1, ?1 WHERE NOT EXISTS (SELECT 1 FROM rom WHERE file_path = ?1)",
+                [path],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO play_activity (rom_id, play_time, created_at, updated_at) SELECT id, ?, 0, ? FROM rom WHERE file_path = ?",
+                rusqlite::params![play_time, updated_at, path],
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_rows() -> Result<()> {
+        let sd_root = temp_dir("onion-rows");
+        assert!(Onion::detect(&sd_root).is_none());
+
+        write_fixture(
+            &sd_root,
+            &[
+                ("Roms/GBA/Game.gba", 600, 1_700_000_000),
+                ("Roms/GB/Other.gb", 0, 1_700_000_100),
+                ("Roms/GBA/Game.gba", 120, 1_700_000_200),
+            ],
+        );
+        let onion = Onion::detect(&sd_root).unwrap();
+
+        let rows = onion.rows()?;
+        assert_eq!(
+            rows,
+            vec![
+                ForeignRow {
+                    id: 1,
+                    path: PathBuf::from("Roms/GBA/Game.gba"),
+                    play_time: chrono::Duration::seconds(600),
+                    last_played: 1_700_000_000,
+                },
+                ForeignRow {
+                    id: 3,
+                    path: PathBuf::from("Roms/GBA/Game.gba"),
+                    play_time: chrono::Duration::seconds(120),
+                    last_played: 1_700_000_200,
+                },
+            ]
+        );
+
+        fs::remove_dir_all(&sd_root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve() {
+        let onion = Onion {
+            sd_root: PathBuf::from("/mnt/SDCARD"),
+            path: PathBuf::from("/mnt/SDCARD").join(PLAY_ACTIVITY_DB),
+        };
+        for path in [
+            "Roms/GBA/Game.gba",
+            "../../Roms/GBA/Game.gba",
+            "/mnt/SDCARD/Roms/GBA/Game.gba",
+        ] {
+            assert_eq!(
+                onion.resolve(Path::new(path)),
+                PathBuf::from("/mnt/SDCARD/Roms/GBA/Game.gba"),
+                "{}",
+                path
+            );
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! `.m3u` playlists, which group the discs of a multi-disc game. The game is listed once through
+//! its playlist, and the playlist is what the core is given, so that RetroArch can switch discs.
+//! Discs may in turn be `.cue` sheets, which refer to the track files of the disc.
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::iter;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Result};
+
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("m3u"))
+}
+
+/// Discs listed in the playlist at `path`, relative to the playlist's folder unless they are
+/// absolute. Blank lines and `#` comments are skipped. Fails if the playlist lists no discs, or a
+/// disc that doesn't exist.
+pub fn discs(path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let discs: Vec<PathBuf> = fs::read_to_string(path)?
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        // Playlists made on Windows may use backslashes
+        .map(|line| normalize(&dir.join(line.replace('\\', "/"))))
+        .collect();
+
+    if discs.is_empty() {
+        bail!("playlist {} lists no discs", path.display());
+    }
+    if let Some(missing) = discs.iter().find(|disc| !disc.exists()) {
+        bail!(
+            "playlist {} lists missing disc {}",
+            path.display(),
+            missing.display()
+        );
+    }
+    Ok(discs)
+}
+
+/// Whether the file at `path` is a cue sheet.
+pub fn is_cue_sheet(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cue"))
+}
+
+/// Track files that the cue sheet at `path` refers to, relative to the cue sheet's folder.
+pub fn tracks(path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_start_matches('\u{feff}').trim();
+            let file = line.strip_prefix("FILE ")?.trim();
+            // The name may be quoted, and is followed by the file type
+            let name = match file.strip_prefix('"') {
+                Some(quoted) => &quoted[..quoted.find('"')?],
+                None => file.split_whitespace().next()?,
+            };
+            Some(normalize(&dir.join(name.replace('\\', "/"))))
+        })
+        .collect())
+}
+
+/// Other files that make up the game at `path`: the discs of a playlist, and the tracks of cue
+/// sheets. Only files that exist are included.
+pub fn files(path: &Path) -> Vec<PathBuf> {
+    let mut files = if is_playlist(path) {
+        discs(path).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let sheets: Vec<_> = iter::once(path)
+        .chain(files.iter().map(PathBuf::as_path))
+        .filter(|file| is_cue_sheet(file))
+        .map(Path::to_path_buf)
+        .collect();
+    for sheet in sheets {
+        files.extend(tracks(&sheet).unwrap_or_default());
+    }
+    let mut seen = HashSet::new();
+    files.retain(|file| file != path && file.exists() && seen.insert(file.clone()));
+    files
+}
+
+/// Resolves `.` and `..` without touching the file system, so that discs compare equal to the
+/// paths they are listed under.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    #[test]
+    fn test_is_playlist() {
+        assert!(is_playlist(Path::new("/Roms/PS/Game.m3u")));
+        assert!(is_playlist(Path::new("/Roms/PS/Game.M3U")));
+        assert!(!is_playlist(Path::new("/Roms/PS/Game (Disc 1).cue")));
+        assert!(!is_playlist(Path::new("/Roms/PS/m3u")));
+    }
+
+    #[test]
+    fn test_discs() -> Result<()> {
+        let dir = temp_dir("playlist-discs");
+        fs::create_dir_all(dir.join("discs"))?;
+        fs::write(dir.join("Game (Disc 1).cue"), "")?;
+        fs::write(dir.join("discs/Game (Disc 2).chd"), "")?;
+        fs::write(
+            dir.join("Game.m3u"),
+            "\u{feff}# Game\r\n\r\n./Game (Disc 1).cue\r\n  discs\\Game (Disc 2).chd  \r\n",
+        )?;
+
+        assert_eq!(
+            discs(&dir.join("Game.m3u"))?,
+            vec![
+                dir.join("Game (Disc 1).cue"),
+                dir.join("discs/Game (Disc 2).chd")
+            ]
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_playlists() -> Result<()> {
+        let dir = temp_dir("playlist-malformed");
+        fs::write(dir.join("Game (Disc 1).cue"), "")?;
+
+        fs::write(dir.join("Empty.m3u"), "# nothing here\n\n")?;
+        assert!(discs(&dir.join("Empty.m3u")).is_err());
+
+        fs::write(
+            dir.join("Missing.m3u"),
+            "Game (Disc 1).cue\nGame (Disc 2).cue\n",
+        )?;
+        assert!(discs(&dir.join("Missing.m3u")).is_err());
+
+        assert!(discs(&dir.join("Nonexistent.m3u")).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_files() -> Result<()> {
+        let dir = temp_dir("playlist-files");
+        fs::write(
+            dir.join("Game (Disc 1).cue"),
+            "FILE \"Game (Disc 1) (Track 1).bin\" BINARY\r\n  TRACK 01 MODE2/2352\r\n\
+             FILE \"Game (Disc 1) (Track 2).bin\" BINARY\r\n  TRACK 02 AUDIO\r\n",
+        )?;
+        fs::write(dir.join("Game (Disc 1) (Track 1).bin"), "")?;
+        fs::write(dir.join("Game (Disc 1) (Track 2).bin"), "")?;
+        fs::write(dir.join("Game (Disc 2).chd"), "")?;
+        fs::write(
+            dir.join("Game.m3u"),
+            "Game (Disc 1).cue\nGame (Disc 2).chd\n",
+        )?;
+        fs::write(
+            dir.join("Other.cue"),
+            "FILE Other.bin BINARY\nFILE Gone.bin BINARY\n",
+        )?;
+        fs::write(dir.join("Other.bin"), "")?;
+
+        assert_eq!(
+            files(&dir.join("Game.m3u")),
+            vec![
+                dir.join("Game (Disc 1).cue"),
+                dir.join("Game (Disc 2).chd"),
+                dir.join("Game (Disc 1) (Track 1).bin"),
+                dir.join("Game (Disc 1) (Track 2).bin"),
+            ]
+        );
+        assert_eq!(files(&dir.join("Other.cue")), vec![dir.join("Other.bin")]);
+        assert!(files(&dir.join("Game (Disc 2).chd")).is_empty());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
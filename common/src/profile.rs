@@ -0,0 +1,217 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{
+    ALLIUM_CONFIG_PROFILES, ALLIUM_GAMES_DIR, ALLIUM_LAST_PROFILE, ALLIUM_PROFILE,
+};
+
+/// Name of the profile that existing data belongs to.
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    /// Games directory visible in this profile. Relative paths are resolved against the games directory.
+    #[serde(default)]
+    pub games_dir: Option<PathBuf>,
+    /// Restricted profiles hide the settings tab and destructive actions.
+    #[serde(default)]
+    pub restricted: bool,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_PROFILE.to_string(),
+            games_dir: None,
+            restricted: false,
+        }
+    }
+}
+
+impl Profile {
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT_PROFILE
+    }
+
+    /// Root games directory of this profile.
+    pub fn games_dir(&self) -> PathBuf {
+        match &self.games_dir {
+            Some(dir) => ALLIUM_GAMES_DIR.join(dir),
+            None => ALLIUM_GAMES_DIR.clone(),
+        }
+    }
+
+    /// Returns a profile-specific variant of a state file path, e.g. `allium-launcher-kids.json`.
+    /// The default profile uses the path unchanged.
+    pub fn scoped_path(&self, path: &Path) -> PathBuf {
+        if self.is_default() {
+            return path.to_path_buf();
+        }
+        let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+        file_name.push("-");
+        file_name.push(&self.name);
+        if let Some(extension) = path.extension() {
+            file_name.push(".");
+            file_name.push(extension);
+        }
+        path.with_file_name(file_name)
+    }
+
+    /// Name of the active profile. The `ALLIUM_PROFILE` environment variable takes precedence
+    /// over the profile chosen at boot. Returns `None` if no profile has been chosen yet.
+    pub fn active_name() -> Option<String> {
+        if let Ok(name) = env::var("ALLIUM_PROFILE") {
+            return Some(name);
+        }
+        fs::read_to_string(ALLIUM_PROFILE.as_path())
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+    }
+
+    pub fn set_active(name: &str) -> Result<()> {
+        fs::write(ALLIUM_PROFILE.as_path(), name)?;
+        Ok(())
+    }
+
+    /// Forgets the chosen profile so that the launcher asks again. The profile is remembered as
+    /// the last one used.
+    pub fn clear_active() -> Result<()> {
+        if ALLIUM_PROFILE.exists() {
+            fs::rename(ALLIUM_PROFILE.as_path(), ALLIUM_LAST_PROFILE.as_path())?;
+        }
+        Ok(())
+    }
+
+    /// Name of the profile that was active before it was last cleared.
+    pub fn last_name() -> Option<String> {
+        fs::read_to_string(ALLIUM_LAST_PROFILE.as_path())
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profiles {
+    /// PIN needed to leave a restricted profile for an unrestricted one.
+    #[serde(default)]
+    pub pin: Option<String>,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+}
+
+impl Profiles {
+    /// Loads the configured profiles. The default profile is always available.
+    pub fn load() -> Result<Self> {
+        let mut this = if ALLIUM_CONFIG_PROFILES.exists() {
+            debug!("found profiles config, loading from file");
+            let config = fs::read_to_string(ALLIUM_CONFIG_PROFILES.as_path())?;
+            toml::from_str(&config).unwrap_or_else(|e| {
+                warn!("failed to parse profiles config: {}", e);
+                Self::default()
+            })
+        } else {
+            Self::default()
+        };
+
+        if !this.profiles.iter().any(Profile::is_default) {
+            this.profiles.insert(0, Profile::default());
+        }
+
+        Ok(this)
+    }
+
+    /// Whether the user has to pick a profile before the launcher can start.
+    pub fn needs_choice(&self) -> bool {
+        self.profiles.len() > 1 && Profile::active_name().is_none()
+    }
+
+    /// The `last` profile if it is restricted. Without the PIN, only restricted profiles can be
+    /// chosen after it.
+    pub fn locked_profile(&self, last: Option<&str>) -> Option<&Profile> {
+        last.and_then(|name| self.get(name))
+            .filter(|profile| profile.restricted)
+    }
+
+    /// Whether the profile chosen at boot should stay as it is: the active profile is restricted,
+    /// and there is no PIN that could unlock the others.
+    pub fn keeps_active(&self) -> bool {
+        self.pin.is_none()
+            && self
+                .locked_profile(Profile::active_name().as_deref())
+                .is_some()
+    }
+
+    /// Whether switching from the `last` profile to `profile` needs the PIN.
+    pub fn needs_pin(&self, last: Option<&str>, profile: &Profile) -> bool {
+        !profile.restricted && self.locked_profile(last).is_some()
+    }
+
+    pub fn check_pin(&self, pin: &str) -> bool {
+        self.pin.as_deref() == Some(pin)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// The active profile, falling back to the default profile.
+    pub fn active(&self) -> Profile {
+        Profile::active_name()
+            .and_then(|name| self.get(&name).cloned())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profiles(pin: Option<&str>) -> Profiles {
+        Profiles {
+            pin: pin.map(str::to_string),
+            profiles: vec![
+                Profile::default(),
+                Profile {
+                    name: "kids".to_string(),
+                    games_dir: Some(PathBuf::from("Kids")),
+                    restricted: true,
+                },
+                Profile {
+                    name: "guest".to_string(),
+                    games_dir: None,
+                    restricted: true,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_leaving_restricted_profile_needs_pin() {
+        let profiles = profiles(Some("4512"));
+        let default = profiles.get(DEFAULT_PROFILE).unwrap();
+        let guest = profiles.get("guest").unwrap();
+
+        assert!(profiles.needs_pin(Some("kids"), default));
+        // Other restricted profiles can be chosen freely
+        assert!(!profiles.needs_pin(Some("kids"), guest));
+        // Nothing is locked after an unrestricted or unknown profile
+        assert!(!profiles.needs_pin(Some(DEFAULT_PROFILE), default));
+        assert!(!profiles.needs_pin(Some("removed"), default));
+        assert!(!profiles.needs_pin(None, default));
+
+        assert!(profiles.check_pin("4512"));
+        assert!(!profiles.check_pin("1234"));
+        assert!(!profiles.check_pin(""));
+
+        // Without a PIN there is nothing that unlocks the profile
+        assert!(!self::profiles(None).check_pin(""));
+    }
+}
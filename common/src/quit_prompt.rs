@@ -0,0 +1,69 @@
+//! Confirmation drawn by alliumd over the stopped game before quitting it, as no other process is
+//! in the foreground to draw it.
+
+use anyhow::Result;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Primitive, PrimitiveStyle, Rectangle, RoundedRectangle};
+
+use crate::display::Display;
+use crate::geom::{Alignment, Point, Rect};
+use crate::platform::{DefaultPlatform, Key, Platform};
+use crate::stylesheet::{Styles, StylesheetColor};
+use crate::view::{ButtonHint, ButtonIcon, Label, Row, View};
+
+/// Saves what is on screen and draws the prompt over it, with `title` above the A and B hints.
+/// `clear_quit_prompt` restores the screen on the same display.
+pub fn draw_quit_prompt(
+    display: &mut <DefaultPlatform as Platform>::Display,
+    styles: &Styles,
+    title: String,
+    yes: String,
+    no: String,
+) -> Result<()> {
+    display.save()?;
+
+    let size = display.size();
+    let w = size.width * 2 / 3;
+    let h = 12 + styles.ui_font.size + 12 + ButtonIcon::diameter(styles) + 12;
+    let x = (size.width - w) as i32 / 2;
+    let y = (size.height - h) as i32 / 2;
+
+    RoundedRectangle::with_equal_corners(
+        Rectangle::new(Point::new(x, y).into(), Size::new(w, h)),
+        Size::new_equal(12),
+    )
+    .into_styled(PrimitiveStyle::with_fill(styles.background_color))
+    .draw(display)?;
+
+    let mut title = Label::new(
+        Point::new(x + w as i32 / 2, y + 12),
+        title,
+        Alignment::Center,
+        Some(w - 24),
+    );
+    title.color(StylesheetColor::Highlight);
+    title.draw(display, styles)?;
+
+    let mut button_hints = Row::new(
+        Point::new(
+            x + w as i32 - 12,
+            y + h as i32 - ButtonIcon::diameter(styles) as i32 - 12,
+        ),
+        vec![
+            ButtonHint::new(Point::zero(), Key::A, yes, Alignment::Right),
+            ButtonHint::new(Point::zero(), Key::B, no, Alignment::Right),
+        ],
+        Alignment::Right,
+        12,
+    );
+    button_hints.draw(display, styles)?;
+
+    display.flush()
+}
+
+/// Restores what was on screen before the prompt was drawn.
+pub fn clear_quit_prompt(display: &mut <DefaultPlatform as Platform>::Display) -> Result<()> {
+    let size = display.size();
+    display.load(Rect::new(0, 0, size.width, size.height))?;
+    display.flush()
+}
@@ -0,0 +1,107 @@
+//! Token that requests to alliumd's companion API must carry, kept in `state/remote-token`.
+//!
+//! The token is shown on the Wi-Fi settings page, where it can be replaced if it was shared with
+//! someone it shouldn't have been. It is short enough to type on a phone.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+
+use anyhow::{bail, Result};
+use log::{debug, info};
+
+use crate::constants::ALLIUM_REMOTE_TOKEN;
+
+/// Characters tokens are made of, without ones that are easily confused such as `0` and `o`.
+const ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+const LENGTH: usize = 10;
+
+/// The current token, created on first use.
+pub fn token() -> Result<String> {
+    if let Ok(token) = fs::read_to_string(ALLIUM_REMOTE_TOKEN.as_path()) {
+        let token = token.trim();
+        if is_valid(token) {
+            return Ok(token.to_string());
+        }
+        debug!("remote token is invalid, replacing");
+    }
+    rotate()
+}
+
+/// Replaces the token, so that the old one no longer works.
+pub fn rotate() -> Result<String> {
+    let token = generate()?;
+    fs::write(ALLIUM_REMOTE_TOKEN.as_path(), &token)?;
+    info!("created a new remote token");
+    Ok(token)
+}
+
+fn generate() -> Result<String> {
+    from_bytes(BufReader::new(File::open("/dev/urandom")?).bytes())
+}
+
+/// A token made from random `bytes`, using as many of them as it takes.
+fn from_bytes(bytes: impl Iterator<Item = io::Result<u8>>) -> Result<String> {
+    // Bytes past the last whole multiple of the alphabet's length would make its first characters
+    // likelier than the rest, so they are skipped
+    let limit = 256 - 256 % ALPHABET.len();
+    let mut token = String::with_capacity(LENGTH);
+    for byte in bytes {
+        let byte = byte? as usize;
+        if byte < limit {
+            token.push(ALPHABET[byte % ALPHABET.len()] as char);
+            if token.len() == LENGTH {
+                return Ok(token);
+            }
+        }
+    }
+    bail!("ran out of random bytes")
+}
+
+fn is_valid(token: &str) -> bool {
+    token.len() == LENGTH && token.bytes().all(|b| ALPHABET.contains(&b))
+}
+
+/// Compares a token that was sent with the current one, taking the same time wherever they
+/// differ, so that the token can't be guessed one character at a time.
+pub fn matches(sent: &str, token: &str) -> bool {
+    sent.len() == token.len()
+        && sent
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let a = generate().unwrap();
+        let b = generate().unwrap();
+        assert!(is_valid(&a), "{a}");
+        assert!(is_valid(&b), "{b}");
+        assert_ne!(a, b);
+        assert!(!is_valid("short"));
+        assert!(!is_valid("0000000000"));
+    }
+
+    #[test]
+    fn test_from_bytes_skips_biased_bytes() {
+        let bytes = [255, 248, 0, 31, 247, 1, 2, 3, 4, 5, 6, 7];
+        let token = from_bytes(bytes.into_iter().map(Ok)).unwrap();
+        assert_eq!(token, "aa9bcdefgh");
+
+        assert!(from_bytes([248; LENGTH].into_iter().map(Ok)).is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("abcdefghjk", "abcdefghjk"));
+        assert!(!matches("abcdefghjm", "abcdefghjk"));
+        assert!(!matches("abcdefghj", "abcdefghjk"));
+        assert!(!matches("", "abcdefghjk"));
+    }
+}
@@ -0,0 +1,202 @@
+//! Retrying operations that fail for a moment, such as opening a device that another process is
+//! still holding.
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// How many times to try in total, including the first.
+    #[serde(default = "RetryPolicy::default_attempts")]
+    pub attempts: u32,
+    /// How long to wait before the second try. Doubles after every try after that.
+    #[serde(default = "RetryPolicy::default_delay_ms")]
+    pub delay_ms: u64,
+    /// Longest to wait between two tries.
+    #[serde(default = "RetryPolicy::default_max_delay_ms")]
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: Self::default_attempts(),
+            delay_ms: Self::default_delay_ms(),
+            max_delay_ms: Self::default_max_delay_ms(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn default_attempts() -> u32 {
+        8
+    }
+
+    fn default_delay_ms() -> u64 {
+        100
+    }
+
+    fn default_max_delay_ms() -> u64 {
+        2000
+    }
+
+    /// How long to wait after each failed try, except the last.
+    pub fn delays(&self) -> impl Iterator<Item = Duration> + '_ {
+        let max = Duration::from_millis(self.max_delay_ms);
+        (0..self.attempts.saturating_sub(1)).map(move |i| {
+            Duration::from_millis(self.delay_ms)
+                .saturating_mul(2u32.saturating_pow(i))
+                .min(max)
+        })
+    }
+
+    /// Runs `op` until it succeeds, it fails with an error that `is_transient` says won't go
+    /// away by itself, or it runs out of tries. Each failed try is logged as doing `what`.
+    pub fn retry<T>(
+        &self,
+        what: &str,
+        op: impl FnMut() -> Result<T>,
+        is_transient: impl Fn(&anyhow::Error) -> bool,
+    ) -> Result<T> {
+        self.retry_with_sleep(what, op, is_transient, thread::sleep)
+    }
+
+    fn retry_with_sleep<T>(
+        &self,
+        what: &str,
+        mut op: impl FnMut() -> Result<T>,
+        is_transient: impl Fn(&anyhow::Error) -> bool,
+        mut sleep: impl FnMut(Duration),
+    ) -> Result<T> {
+        let mut delays = self.delays();
+        let mut attempt = 1;
+        loop {
+            let e = match op() {
+                Ok(value) => {
+                    if attempt > 1 {
+                        info!("{} succeeded on attempt {}", what, attempt);
+                    }
+                    return Ok(value);
+                }
+                Err(e) => e,
+            };
+            if !is_transient(&e) {
+                return Err(e);
+            }
+            let Some(delay) = delays.next() else {
+                warn!("{} failed on attempt {}, giving up: {}", what, attempt, e);
+                return Err(e);
+            };
+            warn!(
+                "{} failed on attempt {}, retrying in {:?}: {}",
+                what, attempt, delay, e
+            );
+            sleep(delay);
+            attempt += 1;
+        }
+    }
+}
+
+/// Whether `error` is because a device is busy, rather than missing or broken.
+pub fn is_busy(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|e| e.downcast_ref::<io::Error>())
+        .any(|e| e.kind() == io::ErrorKind::ResourceBusy)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use anyhow::anyhow;
+
+    use super::*;
+
+    fn policy(attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            attempts,
+            delay_ms: 100,
+            max_delay_ms: 300,
+        }
+    }
+
+    fn busy() -> anyhow::Error {
+        anyhow::Error::from(io::Error::from(io::ErrorKind::ResourceBusy)).context("opening device")
+    }
+
+    #[test]
+    fn test_delays() {
+        let delays: Vec<_> = policy(5).delays().map(|d| d.as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 300, 300]);
+        assert_eq!(policy(1).delays().count(), 0);
+        assert_eq!(policy(0).delays().count(), 0);
+    }
+
+    #[test]
+    fn test_retries_until_it_succeeds() {
+        let tries = Cell::new(0);
+        let mut slept = Vec::new();
+        let result = policy(4).retry_with_sleep(
+            "opening device",
+            || {
+                tries.set(tries.get() + 1);
+                if tries.get() < 3 {
+                    Err(busy())
+                } else {
+                    Ok(tries.get())
+                }
+            },
+            is_busy,
+            |d| slept.push(d.as_millis()),
+        );
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(slept, vec![100, 200]);
+    }
+
+    #[test]
+    fn test_gives_up_after_last_attempt() {
+        let tries = Cell::new(0);
+        let mut slept = 0;
+        let result: Result<()> = policy(3).retry_with_sleep(
+            "opening device",
+            || {
+                tries.set(tries.get() + 1);
+                Err(busy())
+            },
+            is_busy,
+            |_| slept += 1,
+        );
+        assert!(is_busy(&result.unwrap_err()));
+        assert_eq!(tries.get(), 3);
+        assert_eq!(slept, 2);
+    }
+
+    #[test]
+    fn test_fails_fast_on_other_errors() {
+        let tries = Cell::new(0);
+        let result: Result<()> = policy(3).retry_with_sleep(
+            "opening device",
+            || {
+                tries.set(tries.get() + 1);
+                Err(io::Error::from(io::ErrorKind::NotFound).into())
+            },
+            is_busy,
+            |_| panic!("shouldn't wait"),
+        );
+        assert!(result.is_err());
+        assert_eq!(tries.get(), 1);
+    }
+
+    #[test]
+    fn test_is_busy() {
+        assert!(is_busy(&busy()));
+        assert!(!is_busy(&anyhow!("no such device")));
+        assert!(!is_busy(&io::Error::from(io::ErrorKind::NotFound).into()));
+    }
+}
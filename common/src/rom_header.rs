@@ -0,0 +1,79 @@
+//! Recognizing games by the first bytes of their file, to tell which console a `.bin` or `.iso`
+//! is for when neither its name nor its folder says.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::Result;
+
+/// Bytes read from the start of a file, enough to reach the volume descriptor of a raw disc image.
+const HEADER_LEN: u64 = 0x9330;
+
+/// Where to look for what, and the folder pattern in `consoles.toml` of the console it means.
+/// Disc images are checked both as plain 2048 byte sectors and raw 2352 byte sectors.
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0x0, b"NES\x1a", "NES"),
+    (0x0, b"SEGADISCSYSTEM", "SEGACD"),
+    (0x10, b"SEGADISCSYSTEM", "SEGACD"),
+    (0x100, b"SEGA 32X", "THIRTYTWOX"),
+    (0x100, b"SEGA", "MD"),
+    (0x100, b" SEGA", "MD"),
+    // System identifier of the primary volume descriptor
+    (0x8008, b"PLAYSTATION", "PS"),
+    (0x9320, b"PLAYSTATION", "PS"),
+];
+
+/// The folder pattern of the console that the file at `path` is for, going by its header.
+pub fn console_pattern(path: &Path) -> Result<Option<&'static str>> {
+    let mut header = Vec::new();
+    File::open(path)?
+        .take(HEADER_LEN)
+        .read_to_end(&mut header)?;
+    Ok(detect(&header))
+}
+
+fn detect(header: &[u8]) -> Option<&'static str> {
+    SIGNATURES
+        .iter()
+        .find(|(offset, signature, _)| {
+            header
+                .get(*offset..offset + signature.len())
+                .is_some_and(|bytes| bytes == *signature)
+        })
+        .map(|(_, _, pattern)| *pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(offset: usize, bytes: &[u8]) -> Vec<u8> {
+        let mut header = vec![0; HEADER_LEN as usize];
+        header[offset..offset + bytes.len()].copy_from_slice(bytes);
+        header
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"NES\x1a\x10\x08"), Some("NES"));
+        assert_eq!(detect(&header(0x100, b"SEGA GENESIS    ")), Some("MD"));
+        assert_eq!(detect(&header(0x100, b" SEGA MEGA DRIVE")), Some("MD"));
+        assert_eq!(
+            detect(&header(0x100, b"SEGA 32X        ")),
+            Some("THIRTYTWOX")
+        );
+
+        // Sega CD discs also carry a Mega Drive header
+        let mut sega_cd = header(0x10, b"SEGADISCSYSTEM  ");
+        sega_cd[0x110..0x120].copy_from_slice(b"SEGA MEGA DRIVE ");
+        assert_eq!(detect(&sega_cd), Some("SEGACD"));
+
+        assert_eq!(detect(&header(0x8008, b"PLAYSTATION ")), Some("PS"));
+        assert_eq!(detect(&header(0x9320, b"PLAYSTATION ")), Some("PS"));
+
+        assert_eq!(detect(&header(0x8008, b"CD001")), None);
+        assert_eq!(detect(b"NES"), None);
+        assert_eq!(detect(b""), None);
+    }
+}
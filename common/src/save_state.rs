@@ -0,0 +1,499 @@
+//! Save state slots of a game, along with the metadata that the ingame menu shows for each slot.
+//!
+//! RetroArch names states after the game: `<game>.state` for slot 0, `<game>.state<n>` for slot n
+//! and `<game>.state.auto` for the auto slot, each with a `.png` thumbnail next to it. They are
+//! kept in one folder, unless RetroArch's settings sort them into folders named after the game's
+//! folder (`sort_savestates_by_content_enable`) and then after the core (`sort_savestates_enable`).
+//! Slot metadata is kept in `<game>.slots.json` in the same folder, so that slot info can be shown
+//! without reading the states themselves.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{ALLIUM_SAVE_STATES_DIR, RETROARCH_DIR};
+use crate::database::Database;
+use crate::game_info::GameInfo;
+use crate::write_activity;
+
+/// Number of overwritten states kept per slot.
+pub const BACKUPS_PER_SLOT: usize = 3;
+
+/// The state slot that RetroArch saves to automatically.
+pub const AUTO_SLOT: i8 = -1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotInfo {
+    /// When the state was saved.
+    pub timestamp: DateTime<Utc>,
+    /// Total play time of the game when the state was saved, in seconds.
+    pub play_time: i64,
+    /// Core that saved the state, if known.
+    #[serde(default)]
+    pub core: Option<String>,
+}
+
+impl SlotInfo {
+    /// Metadata for a state of the current game saved now, with its total play time so far.
+    pub fn now(database: &Database, game_info: &GameInfo) -> Self {
+        let play_time = database
+            .select_game(&game_info.path.display().to_string())
+            .ok()
+            .flatten()
+            .map_or(Duration::zero(), |game| game.play_time)
+            + game_info.play_time();
+        Self {
+            timestamp: Utc::now(),
+            play_time: play_time.num_seconds(),
+            core: database
+                .core_override(&game_info.path)
+                .ok()
+                .flatten()
+                .or_else(|| database.get_core(&game_info.path).ok().flatten()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SlotsFile {
+    #[serde(default)]
+    slots: BTreeMap<i8, SlotInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SaveStates {
+    dir: PathBuf,
+    name: String,
+}
+
+impl SaveStates {
+    pub fn new(dir: impl Into<PathBuf>, game: &Path) -> Self {
+        Self {
+            dir: dir.into(),
+            name: game
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+        }
+    }
+
+    /// States of a game in RetroArch's state folder.
+    /// The states of `game` played with the RetroArch core `core`, in the folder RetroArch's
+    /// settings put them in.
+    pub fn for_game(game: &Path, core: Option<&str>) -> Self {
+        Self::new(
+            states_dir(&ALLIUM_SAVE_STATES_DIR, &RETROARCH_DIR, game, core),
+            game,
+        )
+    }
+
+    /// The states of the running game.
+    pub fn for_game_info(game_info: &GameInfo) -> Self {
+        Self::for_game(&game_info.path, game_info.core())
+    }
+
+    pub fn state_path(&self, slot: i8) -> PathBuf {
+        self.dir.join(self.state_file_name(slot))
+    }
+
+    pub fn thumbnail_path(&self, slot: i8) -> PathBuf {
+        self.dir.join(format!("{}.png", self.state_file_name(slot)))
+    }
+
+    fn state_file_name(&self, slot: i8) -> String {
+        match slot {
+            AUTO_SLOT => format!("{}.state.auto", self.name),
+            0 => format!("{}.state", self.name),
+            slot => format!("{}.state{}", self.name, slot),
+        }
+    }
+
+    fn metadata_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.slots.json", self.name))
+    }
+
+    fn backup_dir(&self) -> PathBuf {
+        self.dir.join("backups")
+    }
+
+    /// Numbered slots that have a state saved, newest first, with when each was saved.
+    pub fn saved_slots(&self) -> Vec<(i8, DateTime<Utc>)> {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let info = self.load_slots().unwrap_or_default();
+        let mut slots: Vec<_> = dir
+            .filter_map(std::result::Result::ok)
+            .filter_map(|entry| {
+                let slot = self.slot_of(&entry.file_name().to_string_lossy())?;
+                let timestamp = match info.slots.get(&slot) {
+                    Some(info) => info.timestamp,
+                    None => entry.metadata().ok()?.modified().ok()?.into(),
+                };
+                Some((slot, timestamp))
+            })
+            .collect();
+        slots.sort_by(|a, b| b.1.cmp(&a.1));
+        slots
+    }
+
+    /// The numbered slot that the state file named `file_name` is in, if it's one of the game's.
+    fn slot_of(&self, file_name: &str) -> Option<i8> {
+        let slot = file_name
+            .strip_prefix(self.name.as_str())?
+            .strip_prefix(".state")?;
+        if slot.is_empty() {
+            Some(0)
+        } else if slot.bytes().all(|b| b.is_ascii_digit()) {
+            slot.parse().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Whether anything is saved in the slot.
+    pub fn is_occupied(&self, slot: i8) -> bool {
+        self.state_path(slot).exists()
+            || self.thumbnail_path(slot).exists()
+            || matches!(self.slot_info(slot), Ok(Some(_)))
+    }
+
+    pub fn slot_info(&self, slot: i8) -> Result<Option<SlotInfo>> {
+        Ok(self.load_slots()?.slots.remove(&slot))
+    }
+
+    pub fn set_slot_info(&self, slot: i8, info: SlotInfo) -> Result<()> {
+        let mut slots = self.load_slots()?;
+        slots.slots.insert(slot, info);
+        fs::create_dir_all(&self.dir)?;
+        let file = File::create(self.metadata_path())?;
+        serde_json::to_writer(file, &slots)?;
+        Ok(())
+    }
+
+    fn load_slots(&self) -> Result<SlotsFile> {
+        let path = self.metadata_path();
+        if !path.exists() {
+            return Ok(SlotsFile::default());
+        }
+        let json = fs::read_to_string(&path)?;
+        serde_json::from_str(&json).with_context(|| format!("invalid slot metadata: {:?}", path))
+    }
+
+    /// Moves the slot's state and thumbnail into the backup folder instead of letting them be
+    /// overwritten. Only the last `BACKUPS_PER_SLOT` backups of each slot are kept.
+    pub fn backup(&self, slot: i8) -> Result<()> {
+        let _guard = write_activity::begin("save state backup");
+        let backup_dir = self.backup_dir();
+        fs::create_dir_all(&backup_dir)?;
+        for path in [self.state_path(slot), self.thumbnail_path(slot)] {
+            if path.exists() {
+                rotate(&path, &backup_dir)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Backups of the slot's state, newest first.
+    pub fn backups(&self, slot: i8) -> Vec<PathBuf> {
+        let name = self.state_file_name(slot);
+        (1..=BACKUPS_PER_SLOT)
+            .map(|i| backup_path(&self.backup_dir(), &name, i))
+            .filter(|path| path.exists())
+            .collect()
+    }
+}
+
+/// Folder that RetroArch, set up in `retroarch_dir`, saves the states of `game` to when it's played
+/// with `core`. States go in `base`, or in folders in it if RetroArch sorts them.
+fn states_dir(base: &Path, retroarch_dir: &Path, game: &Path, core: Option<&str>) -> PathBuf {
+    let library = core.and_then(|core| library_name(retroarch_dir, core));
+    let content = game
+        .parent()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().to_string());
+
+    // Overrides for the core, then the game's folder, then the game itself replace the settings
+    let mut configs = vec![retroarch_dir.join("retroarch.cfg")];
+    if let Some(library) = &library {
+        let overrides = retroarch_dir.join("config").join(library);
+        configs.push(overrides.join(format!("{}.cfg", library)));
+        if let Some(content) = &content {
+            configs.push(overrides.join(format!("{}.cfg", content)));
+        }
+        if let Some(stem) = game.file_stem() {
+            configs.push(overrides.join(format!("{}.cfg", stem.to_string_lossy())));
+        }
+    }
+    let mut by_content = false;
+    let mut by_core = false;
+    for config in configs {
+        let Ok(text) = fs::read_to_string(&config) else {
+            continue;
+        };
+        for (key, value) in text.lines().filter_map(config_value) {
+            match key {
+                "sort_savestates_by_content_enable" => by_content = value == "true",
+                "sort_savestates_enable" => by_core = value == "true",
+                _ => {}
+            }
+        }
+    }
+
+    let mut dir = base.to_path_buf();
+    if let Some(content) = content.filter(|_| by_content) {
+        dir.push(content);
+    }
+    if by_core {
+        match library {
+            Some(library) => dir.push(library),
+            None => warn!("can't find the name of core {:?} to find its states", core),
+        }
+    }
+    dir
+}
+
+/// The name RetroArch gives the folders of `core`, from its core info file.
+fn library_name(retroarch_dir: &Path, core: &str) -> Option<String> {
+    let file_name = format!("{}_libretro.info", core);
+    ["info", "cores"]
+        .iter()
+        .filter_map(|dir| fs::read_to_string(retroarch_dir.join(dir).join(&file_name)).ok())
+        .find_map(|text| {
+            text.lines()
+                .filter_map(config_value)
+                .find(|(key, _)| *key == "corename")
+                .map(|(_, name)| name.to_string())
+        })
+}
+
+/// The key and unquoted value of a `key = "value"` line of a RetroArch config or info file.
+fn config_value(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once('=')?;
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value);
+    Some((key.trim(), value))
+}
+
+/// Path of the `i`th newest backup of a file, keeping the extension so that thumbnails are still
+/// recognised as images: `game.state` becomes `game.state.1`, `game.state.png` becomes
+/// `game.state.1.png`.
+fn backup_path(dir: &Path, file_name: &str, i: usize) -> PathBuf {
+    match file_name.strip_suffix(".png") {
+        Some(stem) => dir.join(format!("{}.{}.png", stem, i)),
+        None => dir.join(format!("{}.{}", file_name, i)),
+    }
+}
+
+/// Moves `path` into `dir` as the newest backup, shifting older backups back and dropping the
+/// oldest.
+fn rotate(path: &Path, dir: &Path) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .context("invalid state path")?
+        .to_string_lossy();
+
+    let oldest = backup_path(dir, &file_name, BACKUPS_PER_SLOT);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for i in (1..BACKUPS_PER_SLOT).rev() {
+        let from = backup_path(dir, &file_name, i);
+        if from.exists() {
+            fs::rename(&from, backup_path(dir, &file_name, i + 1))?;
+        }
+    }
+    fs::rename(path, backup_path(dir, &file_name, 1))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    #[test]
+    fn test_state_paths() {
+        let states = SaveStates::new("/states", Path::new("/Roms/GB/Tetris (World).gb"));
+        assert_eq!(
+            states.state_path(0),
+            PathBuf::from("/states/Tetris (World).state")
+        );
+        assert_eq!(
+            states.state_path(3),
+            PathBuf::from("/states/Tetris (World).state3")
+        );
+        assert_eq!(
+            states.thumbnail_path(AUTO_SLOT),
+            PathBuf::from("/states/Tetris (World).state.auto.png")
+        );
+    }
+
+    #[test]
+    fn test_states_dir_follows_retroarch_settings() -> Result<()> {
+        let retroarch = temp_dir("save-state-retroarch");
+        let base = Path::new("/states");
+        let game = Path::new("/Roms/GB/Tetris.gb");
+        fs::create_dir_all(retroarch.join("cores"))?;
+        fs::write(
+            retroarch.join("cores/gambatte_libretro.info"),
+            "display_name = \"Nintendo - Game Boy (Gambatte)\"\ncorename = \"Gambatte\"\n",
+        )?;
+
+        // One folder unless RetroArch is set to sort states
+        assert_eq!(states_dir(base, &retroarch, game, Some("gambatte")), base);
+
+        fs::write(
+            retroarch.join("retroarch.cfg"),
+            "sort_savestates_enable = \"true\"\nsort_savestates_by_content_enable = \"false\"\n",
+        )?;
+        assert_eq!(
+            states_dir(base, &retroarch, game, Some("gambatte")),
+            base.join("Gambatte")
+        );
+        // Cores without info can't be found
+        assert_eq!(states_dir(base, &retroarch, game, Some("mgba")), base);
+        assert_eq!(states_dir(base, &retroarch, game, None), base);
+
+        // Overrides for the core, the game's folder and the game take precedence in that order
+        let overrides = retroarch.join("config/Gambatte");
+        fs::create_dir_all(&overrides)?;
+        fs::write(
+            overrides.join("Gambatte.cfg"),
+            "sort_savestates_by_content_enable = \"true\"\n",
+        )?;
+        assert_eq!(
+            states_dir(base, &retroarch, game, Some("gambatte")),
+            base.join("GB/Gambatte")
+        );
+        fs::write(
+            overrides.join("GB.cfg"),
+            "sort_savestates_enable = \"false\"\n",
+        )?;
+        assert_eq!(
+            states_dir(base, &retroarch, game, Some("gambatte")),
+            base.join("GB")
+        );
+        fs::write(
+            overrides.join("Tetris.cfg"),
+            "sort_savestates_by_content_enable = \"false\"\n",
+        )?;
+        assert_eq!(states_dir(base, &retroarch, game, Some("gambatte")), base);
+
+        fs::remove_dir_all(&retroarch)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_round_trip() -> Result<()> {
+        let dir = temp_dir("save-state-metadata");
+        let states = SaveStates::new(&dir, Path::new("Tetris.gb"));
+        assert!(!states.is_occupied(1));
+        assert_eq!(states.slot_info(1)?, None);
+
+        let info = SlotInfo {
+            timestamp: Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap(),
+            play_time: 3600,
+            core: Some("gambatte".to_string()),
+        };
+        states.set_slot_info(1, info.clone())?;
+        states.set_slot_info(
+            AUTO_SLOT,
+            SlotInfo {
+                core: None,
+                ..info.clone()
+            },
+        )?;
+
+        let states = SaveStates::new(&dir, Path::new("Tetris.gb"));
+        assert_eq!(states.slot_info(1)?, Some(info));
+        assert_eq!(states.slot_info(AUTO_SLOT)?.unwrap().core, None);
+        assert!(states.is_occupied(1));
+        assert!(!states.is_occupied(2));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_saved_slots() -> Result<()> {
+        let dir = temp_dir("save-state-saved");
+        let states = SaveStates::new(&dir, Path::new("Tetris.gb"));
+        assert!(states.saved_slots().is_empty());
+
+        for file in [
+            "Tetris.state",
+            "Tetris.state3",
+            "Tetris.state3.png",
+            "Tetris.state.auto",
+            "Tetris DX.state2",
+        ] {
+            fs::write(dir.join(file), "")?;
+        }
+        let saved = Utc.with_ymd_and_hms(2099, 1, 1, 0, 0, 0).unwrap();
+        states.set_slot_info(
+            3,
+            SlotInfo {
+                timestamp: saved,
+                play_time: 0,
+                core: None,
+            },
+        )?;
+
+        let slots = states.saved_slots();
+        assert_eq!(
+            slots.iter().map(|(slot, _)| *slot).collect::<Vec<_>>(),
+            [3, 0]
+        );
+        assert_eq!(slots[0].1, saved);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_backup_rotation() -> Result<()> {
+        let dir = temp_dir("save-state-backup");
+        let states = SaveStates::new(&dir, Path::new("Tetris.gb"));
+
+        for i in 1..=BACKUPS_PER_SLOT + 2 {
+            fs::write(states.state_path(2), i.to_string())?;
+            fs::write(states.thumbnail_path(2), i.to_string())?;
+            states.backup(2)?;
+            assert!(!states.state_path(2).exists());
+            assert!(!states.thumbnail_path(2).exists());
+        }
+
+        // Only the newest backups are kept, newest first
+        let backups = states.backups(2);
+        assert_eq!(backups.len(), BACKUPS_PER_SLOT);
+        let contents = backups
+            .iter()
+            .map(fs::read_to_string)
+            .collect::<std::io::Result<Vec<_>>>()?;
+        assert_eq!(contents, vec!["5", "4", "3"]);
+        assert_eq!(
+            fs::read_to_string(dir.join("backups/Tetris.state2.1.png"))?,
+            "5"
+        );
+        assert!(!dir.join("backups/Tetris.state2.4").exists());
+
+        // Other slots are untouched
+        assert!(states.backups(1).is_empty());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
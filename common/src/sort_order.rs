@@ -0,0 +1,320 @@
+//! The order games are listed in by each sort.
+//!
+//! Many games share the same play time or last played time, so every sort falls back to the
+//! user's secondary preference, then to the name, then to the path. No two games are ever equal,
+//! so lists don't change order between refreshes. Queries sort with [`SortOrder::order_by`], which
+//! orders rows the same way as [`compare`], so the database and the launcher can't disagree.
+//!
+//! Both are passed the rules they sort by, rather than reading the active ones.
+
+use std::cmp::Ordering;
+use std::path::Path;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::database::Game;
+use crate::filename_rules::FilenameRules;
+use crate::natural_order::natural_cmp;
+
+/// Name of the SQLite collation that compares names in natural order. Strict order is SQLite's
+/// own `BINARY` collation.
+pub const NATURAL_COLLATION: &str = "natural";
+
+/// Adds the collations that [`SortOrder::order_by`] sorts names with to `conn`.
+pub fn create_collations(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_collation(NATURAL_COLLATION, natural_cmp)
+}
+
+/// How games that tie in a sort by play statistics are ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TieBreak {
+    #[default]
+    Name,
+    /// Games added to the library most recently first.
+    RecentlyAdded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Name,
+    LastPlayed,
+    MostPlayed,
+}
+
+/// What games are sorted by.
+#[derive(Debug, Clone, Copy)]
+pub struct SortKey<'a> {
+    pub name: &'a str,
+    pub path: &'a Path,
+    pub last_played: i64,
+    /// Play time in seconds.
+    pub play_time: i64,
+    /// Increases as games are added to the library.
+    pub added: i64,
+}
+
+impl<'a> SortKey<'a> {
+    /// Key of a game that isn't in the database yet, so it hasn't been played, and is newer than
+    /// any game that is.
+    pub fn new(name: &'a str, path: &'a Path) -> Self {
+        Self {
+            name,
+            path,
+            last_played: 0,
+            play_time: 0,
+            added: i64::MAX,
+        }
+    }
+}
+
+impl<'a> From<&'a Game> for SortKey<'a> {
+    fn from(game: &'a Game) -> Self {
+        Self {
+            name: &game.name,
+            path: &game.path,
+            last_played: game.last_played,
+            play_time: game.play_time.num_seconds(),
+            added: game.id,
+        }
+    }
+}
+
+impl SortOrder {
+    /// `ORDER BY` clause for the `games` table that matches [`compare`] with `rules`.
+    pub fn order_by(self, rules: &FilenameRules) -> String {
+        let primary = match self {
+            SortOrder::Name => "",
+            SortOrder::LastPlayed => "games.last_played DESC, ",
+            SortOrder::MostPlayed => "games.play_time DESC, ",
+        };
+        let secondary = match (self, rules.tie_break) {
+            (SortOrder::Name, _) | (_, TieBreak::Name) => "",
+            (_, TieBreak::RecentlyAdded) => "games.id DESC, ",
+        };
+        let collation = if rules.natural_order {
+            NATURAL_COLLATION
+        } else {
+            "BINARY"
+        };
+        format!("{primary}{secondary}games.name COLLATE {collation}, games.path")
+    }
+}
+
+/// Compares two games by `order`, breaking ties by `rules`.
+pub fn compare(
+    order: SortOrder,
+    rules: &FilenameRules,
+    a: &SortKey<'_>,
+    b: &SortKey<'_>,
+) -> Ordering {
+    let primary = match order {
+        SortOrder::Name => Ordering::Equal,
+        SortOrder::LastPlayed => b.last_played.cmp(&a.last_played),
+        SortOrder::MostPlayed => b.play_time.cmp(&a.play_time),
+    };
+    let secondary = match (order, rules.tie_break) {
+        (SortOrder::Name, _) | (_, TieBreak::Name) => Ordering::Equal,
+        (_, TieBreak::RecentlyAdded) => b.added.cmp(&a.added),
+    };
+    primary
+        .then(secondary)
+        .then_with(|| rules.compare_names(a.name, b.name))
+        // Paths are compared byte by byte like SQLite does, not by component like `Path` does
+        .then_with(|| a.path.as_os_str().cmp(b.path.as_os_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::Duration;
+
+    use crate::database::{Database, NewGame};
+
+    use super::*;
+
+    const ORDERS: [SortOrder; 3] = [
+        SortOrder::Name,
+        SortOrder::LastPlayed,
+        SortOrder::MostPlayed,
+    ];
+
+    /// Games with every combination of tied names and statistics.
+    fn games() -> Vec<Game> {
+        let mut games = Vec::new();
+        for name in ["Game 2", "game 10", "Game 2", "Other"] {
+            for dir in ["Roms/a", "Roms/a.b"] {
+                for last_played in [0, 5] {
+                    for play_time in [0, 60] {
+                        games.push(Game {
+                            id: games.len() as i64,
+                            name: name.to_string(),
+                            path: PathBuf::from(dir).join(format!("{}.gb", games.len())),
+                            image: None,
+                            play_count: 0,
+                            play_time: Duration::seconds(play_time),
+                            last_played,
+                            core: None,
+                        });
+                    }
+                }
+            }
+        }
+        games
+    }
+
+    /// Every combination of rules.
+    fn rules() -> impl Iterator<Item = FilenameRules> {
+        [true, false].into_iter().flat_map(|natural_order| {
+            [TieBreak::Name, TieBreak::RecentlyAdded]
+                .into_iter()
+                .map(move |tie_break| FilenameRules {
+                    natural_order,
+                    tie_break,
+                })
+        })
+    }
+
+    fn paths(games: &[Game]) -> Vec<PathBuf> {
+        games.iter().map(|g| g.path.clone()).collect()
+    }
+
+    #[test]
+    fn test_total_order() {
+        let games = games();
+        for rules in rules() {
+            let cmp = |order, a: &SortKey<'_>, b: &SortKey<'_>| cmp(order, &rules, a, b);
+            for order in ORDERS {
+                for a in &games {
+                    let a = SortKey::from(a);
+                    assert_eq!(cmp(order, &a, &a), Ordering::Equal);
+                    for b in &games {
+                        let b = SortKey::from(b);
+                        assert_eq!(cmp(order, &a, &b), cmp(order, &b, &a).reverse());
+                        if a.path != b.path {
+                            assert_ne!(cmp(order, &a, &b), Ordering::Equal);
+                        }
+                    }
+                }
+                // Sorting any permutation gives the same list, so the cursor stays put on refresh
+                let mut sorted: Vec<_> = games.iter().map(SortKey::from).collect();
+                sorted.sort_unstable_by(|a, b| cmp(order, a, b));
+                for window in sorted.windows(2) {
+                    assert_eq!(cmp(order, &window[0], &window[1]), Ordering::Less);
+                }
+                let mut resorted = sorted.clone();
+                resorted.reverse();
+                resorted.rotate_left(7);
+                resorted.sort_unstable_by(|a, b| cmp(order, a, b));
+                assert!(resorted
+                    .iter()
+                    .map(|k| k.path)
+                    .eq(sorted.iter().map(|k| k.path)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_ties_fall_back_to_name() {
+        let a = Game {
+            name: "Game 10".to_string(),
+            path: PathBuf::from("a"),
+            ..games()[0].clone()
+        };
+        let b = Game {
+            id: 100,
+            name: "Game 9".to_string(),
+            path: PathBuf::from("b"),
+            ..a.clone()
+        };
+        for rules in rules() {
+            let by_name = rules.compare_names("Game 9", "Game 10");
+            let expected = match rules.tie_break {
+                TieBreak::Name => by_name,
+                // Added later
+                TieBreak::RecentlyAdded => Ordering::Less,
+            };
+            for order in [SortOrder::LastPlayed, SortOrder::MostPlayed] {
+                assert_eq!(compare(order, &rules, &(&b).into(), &(&a).into()), expected);
+            }
+            assert_eq!(
+                compare(SortOrder::Name, &rules, &(&b).into(), &(&a).into()),
+                by_name
+            );
+        }
+    }
+
+    #[test]
+    fn test_query_agrees() -> rusqlite::Result<()> {
+        let conn = Connection::open_in_memory()?;
+        create_collations(&conn)?;
+        conn.execute(
+            "CREATE TABLE games (id INTEGER PRIMARY KEY, name TEXT, path TEXT, last_played INTEGER, play_time INTEGER)",
+            [],
+        )?;
+        let games = games();
+        for game in &games {
+            conn.execute(
+                "INSERT INTO games (id, name, path, last_played, play_time) VALUES (?, ?, ?, ?, ?)",
+                rusqlite::params![
+                    game.id,
+                    game.name,
+                    game.path.display().to_string(),
+                    game.last_played,
+                    game.play_time.num_seconds()
+                ],
+            )?;
+        }
+
+        for rules in rules() {
+            for order in ORDERS {
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT path FROM games ORDER BY {}",
+                    order.order_by(&rules)
+                ))?;
+                let listed = stmt
+                    .query_map([], |row| Ok(PathBuf::from(row.get::<_, String>(0)?)))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                let mut sorted = games.clone();
+                sorted.sort_unstable_by(|a, b| compare(order, &rules, &a.into(), &b.into()));
+                assert_eq!(listed, paths(&sorted), "{order:?} {rules:?}");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_agrees() -> anyhow::Result<()> {
+        let database = Database::in_memory()?;
+        let games = games();
+        database.update_games(
+            &games
+                .iter()
+                .map(|game| NewGame {
+                    name: game.name.clone(),
+                    path: game.path.clone(),
+                    image: None,
+                    core: None,
+                })
+                .collect::<Vec<_>>(),
+        )?;
+        for (i, game) in games.iter().enumerate() {
+            // Play every game so that they are all listed, with ties in both statistics
+            database.increment_play_count(&game.name, &game.path, None)?;
+            database.add_play_time(&game.path, Duration::seconds((i % 3) as i64 * 60))?;
+        }
+
+        // The database lists games by the active rules, which no test changes
+        let rules = FilenameRules::active();
+        for (order, listed) in [
+            (SortOrder::LastPlayed, database.select_last_played(100)?),
+            (SortOrder::MostPlayed, database.select_most_played(100)?),
+        ] {
+            let mut sorted = listed.clone();
+            sorted.sort_unstable_by(|a, b| compare(order, &rules, &a.into(), &b.into()));
+            assert_eq!(paths(&listed), paths(&sorted), "{order:?}");
+        }
+        Ok(())
+    }
+}
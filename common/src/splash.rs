@@ -0,0 +1,97 @@
+//! Boot splash drawn by alliumd until the launcher has drawn its first frame.
+//!
+//! Users can replace the built-in logo by placing a `splash.png` at the root of the SD card.
+
+use std::path::Path;
+
+use anyhow::Result;
+use embedded_graphics::image::ImageRaw;
+use embedded_graphics::prelude::*;
+use image::imageops::FilterType;
+use image::{GenericImageView, RgbaImage};
+use log::{debug, warn};
+
+use crate::constants::ALLIUM_SPLASH_IMAGE;
+use crate::display::color::Color;
+use crate::display::Display;
+use crate::geom::{Alignment, Point};
+use crate::platform::{DefaultPlatform, Platform};
+use crate::stylesheet::{Styles, StylesheetColor};
+use crate::view::{Label, View};
+
+/// Environment variable through which alliumd passes its PID to the processes it spawns.
+pub const ALLIUMD_PID_ENV: &str = "ALLIUMD_PID";
+
+/// Draws the user's splash image, or the built-in logo if there is none or it can't be decoded.
+pub fn draw_splash(
+    display: &mut <DefaultPlatform as Platform>::Display,
+    styles: &Styles,
+) -> Result<()> {
+    display.clear(styles.background_color)?;
+
+    let size = display.size();
+    match load_image(&ALLIUM_SPLASH_IMAGE, size) {
+        Some(image) => {
+            let top_left = Point::new(
+                (size.width - image.width()) as i32 / 2,
+                (size.height - image.height()) as i32 / 2,
+            );
+            let raw: ImageRaw<'_, Color> = ImageRaw::new(&image, image.width());
+            embedded_graphics::image::Image::new(&raw, top_left.into()).draw(display)?;
+        }
+        None => {
+            let mut logo = Label::new(
+                Point::new(
+                    size.width as i32 / 2,
+                    (size.height - styles.ui_font.size) as i32 / 2,
+                ),
+                "Allium".to_string(),
+                Alignment::Center,
+                None,
+            );
+            logo.color(StylesheetColor::Highlight);
+            logo.draw(display, styles)?;
+        }
+    }
+
+    display.flush()
+}
+
+/// Loads an image and scales it to fit within `size`, keeping its aspect ratio.
+fn load_image(path: &Path, size: Size) -> Option<RgbaImage> {
+    if !path.exists() {
+        debug!("no splash image found, using built-in logo");
+        return None;
+    }
+
+    let image = ::image::open(path)
+        .map_err(|e| warn!("failed to load splash image, using built-in logo: {}", e))
+        .ok()?;
+    let image = if image.width() != size.width || image.height() != size.height {
+        image.resize(size.width, size.height, FilterType::Triangle)
+    } else {
+        image
+    };
+    Some(image.to_rgba8())
+}
+
+/// Tells alliumd that the first frame has been drawn and the splash is no longer on screen.
+pub fn notify_ready() {
+    #[cfg(unix)]
+    {
+        use std::env;
+
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        let Some(pid) = env::var(ALLIUMD_PID_ENV)
+            .ok()
+            .and_then(|pid| pid.parse().ok())
+        else {
+            return;
+        };
+        if let Err(e) = kill(Pid::from_raw(pid), Signal::SIGUSR1) {
+            warn!("failed to notify alliumd: {}", e);
+        }
+    }
+}
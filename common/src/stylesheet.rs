@@ -1,19 +1,29 @@
 use std::fs::{self, File};
 use std::io::Write;
 use std::mem;
+use std::ops::{Deref, RangeInclusive};
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use log::{debug, error, warn};
 use rusttype::Font;
 use serde::{Deserialize, Serialize};
+use strum::{EnumIter, IntoEnumIterator};
 
 use crate::{
     constants::{ALLIUM_FONTS_DIR, ALLIUM_STYLESHEET},
-    display::color::Color,
+    display::{
+        assets,
+        atlas::IconAtlas,
+        color::{self, BlendMode, Color},
+        greyscale::{self, Dithering},
+    },
+    locale::Locale,
+    theme_schedule::{ThemePeriod, ThemeSchedule},
 };
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StylesheetColor {
     Foreground,
     Background,
@@ -24,10 +34,12 @@ pub enum StylesheetColor {
     ButtonX,
     ButtonY,
     BackgroundHighlightBlend,
+    /// Text about something that's wrong, in the A button's red.
+    Error,
 }
 
 impl StylesheetColor {
-    pub fn to_color(&self, stylesheet: &Stylesheet) -> Color {
+    pub fn to_color(&self, stylesheet: &StyleConfig) -> Color {
         match self {
             Self::Foreground => stylesheet.foreground_color,
             Self::Background => stylesheet.background_color,
@@ -40,40 +52,33 @@ impl StylesheetColor {
             Self::BackgroundHighlightBlend => stylesheet
                 .background_color
                 .blend(stylesheet.highlight_color, 128),
+            Self::Error => stylesheet.button_a_color,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StylesheetFont {
     pub path: PathBuf,
     pub size: u32,
-    #[serde(skip)]
-    pub font: Option<Font<'static>>,
 }
 
 impl StylesheetFont {
     pub fn new(path: PathBuf, size: u32) -> Self {
-        Self {
-            path,
-            size,
-            font: None,
-        }
-    }
-
-    /// Returns an owned font. Panics if the font has not been loaded.
-    pub fn font(&self) -> Font<'static> {
-        self.font.as_ref().unwrap().clone()
+        Self { path, size }
     }
 
-    /// Loads the font from disk if it has not already been loaded.
-    pub fn load(&mut self) -> Result<()> {
-        let bytes = fs::read(&self.path)?;
-        self.font = Font::try_from_vec(bytes);
-        if self.font.is_none() {
-            error!("failed to load font from {:?}", self.path);
-        }
-        Ok(())
+    /// Loads the font, falling back to `fallback` if it can't be loaded.
+    fn load(&self, name: &str, fallback: Self) -> Result<Arc<Font<'static>>> {
+        assets::load_font(&self.path).or_else(|e| {
+            error!(
+                "failed to load {} font: {}, {}",
+                name,
+                self.path.display(),
+                e
+            );
+            assets::load_font(&fallback.path)
+        })
     }
 
     pub fn available_fonts() -> Result<Vec<PathBuf>> {
@@ -119,24 +124,78 @@ impl StylesheetFont {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Stylesheet {
+/// Smallest gap between list rows, so that the selection pill doesn't touch the text.
+pub const MIN_ROW_SPACING: u32 = 4;
+
+/// Fewest list rows that must fit on screen.
+pub const MIN_VISIBLE_ROWS: u32 = 4;
+
+/// Smallest and largest font sizes a theme may use.
+pub const FONT_SIZES: RangeInclusive<u32> = 12..=96;
+
+/// Lowest contrast ratio of the text and highlight colors against the background, so that a theme
+/// can't make the menus unreadable.
+pub const MIN_CONTRAST: f32 = 1.5;
+
+/// Presets for the row spacing, selection padding and selection radius.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum Density {
+    Compact,
+    Normal,
+    Comfortable,
+}
+
+impl Density {
+    /// Row spacing, selection padding and selection radius of the preset.
+    fn metrics(self) -> (u32, u32, Option<u32>) {
+        match self {
+            Self::Compact => (4, 8, Some(8)),
+            Self::Normal => (8, 12, None),
+            Self::Comfortable => (16, 16, None),
+        }
+    }
+
+    pub fn as_str(self, locale: &Locale) -> String {
+        locale.t(match self {
+            Self::Compact => "settings-display-density-compact",
+            Self::Normal => "settings-display-density-normal",
+            Self::Comfortable => "settings-display-density-comfortable",
+        })
+    }
+}
+
+/// Where the text and selection pill of a list row are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowLayout {
+    /// Height of a row, including the spacing between rows.
+    pub height: u32,
+    /// Space above the text, so that it is centered in the row.
+    pub inset: u32,
+    /// Space left and right of the text within the selection pill.
+    pub padding: u32,
+    /// Corner radius of the selection pill.
+    pub radius: u32,
+}
+
+/// The theme as it is saved: colors, metrics, and the paths of its fonts and icons.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StyleConfig {
     pub enable_box_art: bool,
-    #[serde(default = "Stylesheet::default_foreground_color")]
+    #[serde(default = "StyleConfig::default_foreground_color")]
     pub foreground_color: Color,
-    #[serde(default = "Stylesheet::default_background_color")]
+    #[serde(default = "StyleConfig::default_background_color")]
     pub background_color: Color,
-    #[serde(default = "Stylesheet::default_highlight_color")]
+    #[serde(default = "StyleConfig::default_highlight_color")]
     pub highlight_color: Color,
-    #[serde(default = "Stylesheet::default_disabled_color")]
+    #[serde(default = "StyleConfig::default_disabled_color")]
     pub disabled_color: Color,
-    #[serde(default = "Stylesheet::default_button_a_color")]
+    #[serde(default = "StyleConfig::default_button_a_color")]
     pub button_a_color: Color,
-    #[serde(default = "Stylesheet::default_button_b_color")]
+    #[serde(default = "StyleConfig::default_button_b_color")]
     pub button_b_color: Color,
-    #[serde(default = "Stylesheet::default_button_x_color")]
+    #[serde(default = "StyleConfig::default_button_x_color")]
     pub button_x_color: Color,
-    #[serde(default = "Stylesheet::default_button_y_color")]
+    #[serde(default = "StyleConfig::default_button_y_color")]
     pub button_y_color: Color,
     #[serde(default = "StylesheetFont::ui_font")]
     pub ui_font: StylesheetFont,
@@ -144,79 +203,188 @@ pub struct Stylesheet {
     pub guide_font: StylesheetFont,
     #[serde(skip, default = "StylesheetFont::cjk_font")]
     pub cjk_font: StylesheetFont,
-
-    #[serde(default = "Stylesheet::default_alt_foreground_color")]
+    /// Icon atlas used for button hints instead of the built-in icons.
+    #[serde(default)]
+    pub button_icons: Option<PathBuf>,
+    /// Space between list rows, in addition to the UI font size.
+    #[serde(default = "StyleConfig::default_row_spacing")]
+    pub row_spacing: u32,
+    /// Space left and right of the selected text within the selection pill.
+    #[serde(default = "StyleConfig::default_selection_padding")]
+    pub selection_padding: u32,
+    /// Corner radius of the selection pill. Rounds the ends fully if unset.
+    #[serde(default)]
+    pub selection_radius: Option<u32>,
+    /// How strongly the screen behind the in-game menu is dimmed, from 0 to 255.
+    #[serde(default = "StyleConfig::default_menu_dim")]
+    pub menu_dim: u8,
+    /// How colors are blended, e.g. when dimming the screen or drawing translucent images.
+    #[serde(default)]
+    pub blend_mode: BlendMode,
+    /// Renders everything in high-contrast greyscale, for panels that can't show colors well.
+    /// Blended effects, such as dimming the screen behind menus, are replaced by solid colors.
+    #[serde(default)]
+    pub greyscale: bool,
+    /// How colors between grey levels are shown in greyscale.
+    #[serde(default)]
+    pub dithering: Dithering,
+
+    #[serde(default = "StyleConfig::default_alt_foreground_color")]
     alt_foreground_color: Color,
-    #[serde(default = "Stylesheet::default_alt_background_color")]
+    #[serde(default = "StyleConfig::default_alt_background_color")]
     alt_background_color: Color,
-    #[serde(default = "Stylesheet::default_alt_highlight_color")]
+    #[serde(default = "StyleConfig::default_alt_highlight_color")]
     alt_highlight_color: Color,
-    #[serde(default = "Stylesheet::default_alt_disabled_color")]
+    #[serde(default = "StyleConfig::default_alt_disabled_color")]
     alt_disabled_color: Color,
-    #[serde(default = "Stylesheet::default_alt_button_a_color")]
+    #[serde(default = "StyleConfig::default_alt_button_a_color")]
     alt_button_a_color: Color,
-    #[serde(default = "Stylesheet::default_alt_button_b_color")]
+    #[serde(default = "StyleConfig::default_alt_button_b_color")]
     alt_button_b_color: Color,
-    #[serde(default = "Stylesheet::default_alt_button_x_color")]
+    #[serde(default = "StyleConfig::default_alt_button_x_color")]
     alt_button_x_color: Color,
-    #[serde(default = "Stylesheet::default_alt_button_y_color")]
+    #[serde(default = "StyleConfig::default_alt_button_y_color")]
     alt_button_y_color: Color,
 }
 
-impl Stylesheet {
+impl StyleConfig {
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Loads the saved theme, or the default one if there is none or it's invalid, or the theme
+    /// that the theme schedule calls for.
     pub fn load() -> Result<Self> {
+        let styles = Self::load_saved()?;
+        let schedule = ThemeSchedule::load().unwrap_or_else(|e| {
+            warn!("failed to load theme schedule: {}", e);
+            ThemeSchedule::new()
+        });
+        Ok(match schedule.current() {
+            Some(period) => schedule.theme_for(period, styles),
+            None => styles,
+        })
+    }
+
+    fn load_saved() -> Result<Self> {
         if ALLIUM_STYLESHEET.exists() {
             debug!("found state, loading from file");
             if let Ok(json) = fs::read_to_string(ALLIUM_STYLESHEET.as_path()) {
-                if let Ok(mut styles) = serde_json::from_str::<Self>(&json) {
-                    styles.load_fonts()?;
-                    return Ok(styles);
+                if let Ok(styles) = serde_json::from_str::<Self>(&json) {
+                    match styles.validate() {
+                        Ok(()) => return Ok(styles),
+                        Err(e) => warn!("invalid theme: {}", e),
+                    }
                 }
             }
             warn!("failed to read state file, removing");
             fs::remove_file(ALLIUM_STYLESHEET.as_path())?;
         }
 
-        let mut styles = Self::default();
-        styles.load_fonts()?;
-        Ok(styles)
+        Ok(Self::default())
     }
 
-    pub fn load_fonts(&mut self) -> Result<()> {
-        if let Err(e) = self.ui_font.load() {
-            error!(
-                "failed to load UI font: {}, {}",
-                self.ui_font.path.display(),
-                e
-            );
-            self.ui_font = StylesheetFont::ui_font();
-            self.ui_font.load()?;
-        }
-        if let Err(e) = self.guide_font.load() {
-            error!(
-                "failed to load guide font: {} ({})",
-                self.guide_font.path.display(),
-                e
-            );
-            self.guide_font = StylesheetFont::guide_font();
-            self.guide_font.load()?;
+    /// Checks that the theme is usable: fonts are neither tiny nor huge, and text and highlights
+    /// stand out from the background. Metrics that are merely too large are fixed by
+    /// `clamp_metrics` instead.
+    pub fn validate(&self) -> Result<()> {
+        for (name, font) in [
+            ("UI", &self.ui_font),
+            ("guide", &self.guide_font),
+            ("CJK", &self.cjk_font),
+        ] {
+            if !FONT_SIZES.contains(&font.size) {
+                bail!(
+                    "{} font size {} is outside {}..={}",
+                    name,
+                    font.size,
+                    FONT_SIZES.start(),
+                    FONT_SIZES.end()
+                );
+            }
         }
-        if let Err(e) = self.cjk_font.load() {
-            error!(
-                "failed to load CJK font: {} ({})",
-                self.cjk_font.path.display(),
-                e
-            );
-            self.cjk_font = StylesheetFont::guide_font();
-            self.cjk_font.load()?;
+        for (name, color) in [
+            ("foreground", self.foreground_color),
+            ("highlight", self.highlight_color),
+        ] {
+            let contrast = color.contrast(self.background_color);
+            if contrast < MIN_CONTRAST {
+                bail!(
+                    "{} color has contrast {:.2} against the background, below {}",
+                    name,
+                    contrast,
+                    MIN_CONTRAST
+                );
+            }
         }
         Ok(())
     }
 
+    /// Makes the stylesheet's blend mode and greyscale setting the ones used for all drawing in
+    /// this process.
+    pub fn apply_render_mode(&self) {
+        color::set_blend_mode(self.blend_mode);
+        greyscale::set_greyscale(self.greyscale.then_some(self.dithering));
+    }
+
+    /// What `pixel` becomes behind a menu drawn over it: dimmed, or covered by the background in
+    /// greyscale, where the dimmed game would be hard to tell apart from the menu.
+    pub fn dim_behind_menu(&self, pixel: Color) -> Color {
+        if self.greyscale {
+            self.background_color
+        } else {
+            pixel.dim(self.background_color, self.menu_dim)
+        }
+    }
+
+    /// Layout of list rows with the current font and metrics.
+    pub fn row_layout(&self) -> RowLayout {
+        let height = self.ui_font.size + self.row_spacing;
+        RowLayout {
+            height,
+            inset: self.row_spacing / 2,
+            padding: self.selection_padding,
+            radius: self.selection_radius.unwrap_or(height / 2).min(height / 2),
+        }
+    }
+
+    /// Height left for lists on a screen of `screen_height`, below the tabs and above the button
+    /// hints.
+    pub fn list_height(&self, screen_height: u32) -> u32 {
+        screen_height.saturating_sub(2 * self.ui_font.size + 32)
+    }
+
+    /// Keeps the row metrics usable: rows leave room around the text, and at least
+    /// `MIN_VISIBLE_ROWS` of them fit on a screen of `screen_height`.
+    pub fn clamp_metrics(&mut self, screen_height: u32) {
+        let max_height = self.list_height(screen_height) / MIN_VISIBLE_ROWS;
+        let max_spacing = max_height
+            .saturating_sub(self.ui_font.size)
+            .max(MIN_ROW_SPACING);
+        self.row_spacing = self.row_spacing.clamp(MIN_ROW_SPACING, max_spacing);
+        self.selection_padding = self.selection_padding.min(self.ui_font.size);
+    }
+
+    /// The preset matching the current metrics, if any.
+    pub fn density(&self) -> Option<Density> {
+        Density::iter().find(|density| {
+            density.metrics()
+                == (
+                    self.row_spacing,
+                    self.selection_padding,
+                    self.selection_radius,
+                )
+        })
+    }
+
+    pub fn set_density(&mut self, density: Density) {
+        (
+            self.row_spacing,
+            self.selection_padding,
+            self.selection_radius,
+        ) = density.metrics();
+    }
+
     pub fn save(&self) -> Result<()> {
         let json = serde_json::to_string(&self).unwrap();
         File::create(ALLIUM_STYLESHEET.as_path())?.write_all(json.as_bytes())?;
@@ -226,6 +394,13 @@ impl Stylesheet {
         Ok(())
     }
 
+    /// Switches to the dark colors at night, and the light ones during the day.
+    pub fn set_period(&mut self, period: ThemePeriod) {
+        if self.background_color.is_dark() != (period == ThemePeriod::Night) {
+            self.toggle_dark_mode();
+        }
+    }
+
     pub fn toggle_dark_mode(&mut self) {
         mem::swap(&mut self.foreground_color, &mut self.alt_foreground_color);
         mem::swap(&mut self.background_color, &mut self.alt_background_color);
@@ -258,6 +433,21 @@ rgui_particle_color = "0xFF{highlight:X}"
         Ok(())
     }
 
+    #[inline]
+    fn default_row_spacing() -> u32 {
+        Density::Normal.metrics().0
+    }
+
+    #[inline]
+    fn default_selection_padding() -> u32 {
+        Density::Normal.metrics().1
+    }
+
+    #[inline]
+    fn default_menu_dim() -> u8 {
+        192
+    }
+
     #[inline]
     fn default_foreground_color() -> Color {
         Color::new(255, 255, 255)
@@ -339,7 +529,7 @@ rgui_particle_color = "0xFF{highlight:X}"
     }
 }
 
-impl Default for Stylesheet {
+impl Default for StyleConfig {
     fn default() -> Self {
         Self {
             enable_box_art: true,
@@ -354,6 +544,14 @@ impl Default for Stylesheet {
             ui_font: StylesheetFont::ui_font(),
             guide_font: StylesheetFont::guide_font(),
             cjk_font: StylesheetFont::cjk_font(),
+            button_icons: None,
+            row_spacing: Self::default_row_spacing(),
+            selection_padding: Self::default_selection_padding(),
+            selection_radius: None,
+            menu_dim: Self::default_menu_dim(),
+            blend_mode: BlendMode::default(),
+            greyscale: false,
+            dithering: Dithering::default(),
             alt_foreground_color: Self::default_alt_foreground_color(),
             alt_background_color: Self::default_alt_background_color(),
             alt_highlight_color: Self::default_alt_highlight_color(),
@@ -365,3 +563,270 @@ impl Default for Stylesheet {
         }
     }
 }
+
+/// Fonts and icons loaded from the paths in a [`StyleConfig`]. Themes that name the same files
+/// share them.
+#[derive(Debug, Clone)]
+pub struct StyleAssets {
+    pub ui_font: Arc<Font<'static>>,
+    pub guide_font: Arc<Font<'static>>,
+    pub cjk_font: Arc<Font<'static>>,
+    /// Icon atlas used for button hints instead of the built-in icons.
+    pub button_atlas: Option<Arc<IconAtlas>>,
+}
+
+impl StyleAssets {
+    /// Loads the assets of `config`. Fonts that can't be loaded are replaced by the default ones,
+    /// and button icons by the built-in ones.
+    pub fn load(config: &StyleConfig) -> Result<Self> {
+        let button_atlas =
+            config
+                .button_icons
+                .as_ref()
+                .and_then(|path| match assets::load_atlas(path) {
+                    Ok(atlas) => Some(atlas),
+                    Err(e) => {
+                        error!("failed to load button icons: {}, {}", path.display(), e);
+                        None
+                    }
+                });
+        Ok(Self {
+            ui_font: config.ui_font.load("UI", StylesheetFont::ui_font())?,
+            guide_font: config
+                .guide_font
+                .load("guide", StylesheetFont::guide_font())?,
+            cjk_font: config.cjk_font.load("CJK", StylesheetFont::guide_font())?,
+            button_atlas,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct StylesInner {
+    config: StyleConfig,
+    assets: StyleAssets,
+}
+
+/// A theme that is ready to draw with: its config and the assets loaded from it.
+///
+/// Cloning only copies a pointer, so views can keep the theme they were laid out with. Derefs to
+/// the [`StyleConfig`].
+#[derive(Debug, Clone)]
+pub struct Styles(Arc<StylesInner>);
+
+impl Styles {
+    pub fn new(config: StyleConfig, assets: StyleAssets) -> Self {
+        Self(Arc::new(StylesInner { config, assets }))
+    }
+
+    /// Loads the assets of `config`.
+    pub fn from_config(config: StyleConfig) -> Result<Self> {
+        let assets = StyleAssets::load(&config)?;
+        Ok(Self::new(config, assets))
+    }
+
+    /// Loads the saved theme and makes its render mode the one used by this process.
+    pub fn load() -> Result<Self> {
+        let styles = Self::from_config(StyleConfig::load()?)?;
+        styles.apply_render_mode();
+        Ok(styles)
+    }
+
+    /// Like `load`, with the row metrics fitted to a screen of `screen_height`.
+    pub fn load_fitted(screen_height: u32) -> Result<Self> {
+        let mut config = StyleConfig::load()?;
+        config.clamp_metrics(screen_height);
+        let styles = Self::from_config(config)?;
+        styles.apply_render_mode();
+        Ok(styles)
+    }
+
+    pub fn config(&self) -> &StyleConfig {
+        &self.0.config
+    }
+
+    pub fn assets(&self) -> &StyleAssets {
+        &self.0.assets
+    }
+
+    /// Returns an owned UI font.
+    pub fn ui_font(&self) -> Font<'static> {
+        Font::clone(&self.0.assets.ui_font)
+    }
+
+    /// Returns an owned guide font.
+    pub fn guide_font(&self) -> Font<'static> {
+        Font::clone(&self.0.assets.guide_font)
+    }
+
+    /// Returns an owned CJK font.
+    pub fn cjk_font(&self) -> Font<'static> {
+        Font::clone(&self.0.assets.cjk_font)
+    }
+
+    pub fn button_atlas(&self) -> Option<&IconAtlas> {
+        self.0.assets.button_atlas.as_deref()
+    }
+}
+
+impl Deref for Styles {
+    type Target = StyleConfig;
+
+    fn deref(&self) -> &StyleConfig {
+        &self.0.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+    use rusttype::Scale;
+
+    use super::*;
+    use crate::display::golden;
+    use crate::test_utils::temp_dir;
+
+    /// Rows that fit in a list on a screen of `screen_height` with `density`.
+    fn visible_rows(density: Density, screen_height: u32) -> u32 {
+        let mut styles = StyleConfig::default();
+        styles.set_density(density);
+        styles.clamp_metrics(screen_height);
+        styles.list_height(screen_height) / styles.row_layout().height
+    }
+
+    #[test]
+    fn test_density_rows_640x480() {
+        assert_eq!(visible_rows(Density::Compact, 480), 9);
+        assert_eq!(visible_rows(Density::Normal, 480), 8);
+        assert_eq!(visible_rows(Density::Comfortable, 480), 7);
+    }
+
+    #[test]
+    fn test_density_rows_752x560() {
+        assert_eq!(visible_rows(Density::Compact, 560), 11);
+        assert_eq!(visible_rows(Density::Normal, 560), 10);
+        assert_eq!(visible_rows(Density::Comfortable, 560), 8);
+    }
+
+    #[test]
+    fn test_default_is_normal_density() {
+        let styles = StyleConfig::default();
+        assert_eq!(styles.density(), Some(Density::Normal));
+        // The selection pill is fully rounded, as before metrics were themable
+        assert_eq!(styles.row_layout().radius, (styles.ui_font.size + 8) / 2);
+    }
+
+    #[test]
+    fn test_clamp_metrics() {
+        let mut styles = StyleConfig {
+            row_spacing: 200,
+            selection_padding: 100,
+            selection_radius: Some(100),
+            ..Default::default()
+        };
+        styles.clamp_metrics(480);
+        assert!(styles.list_height(480) / styles.row_layout().height >= MIN_VISIBLE_ROWS);
+        assert_eq!(styles.selection_padding, styles.ui_font.size);
+        assert_eq!(styles.row_layout().radius, styles.row_layout().height / 2);
+        assert_eq!(styles.density(), None);
+
+        styles.row_spacing = 0;
+        styles.clamp_metrics(480);
+        assert_eq!(styles.row_spacing, MIN_ROW_SPACING);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut styles = StyleConfig::default();
+        assert!(styles.validate().is_ok());
+        styles.toggle_dark_mode();
+        assert!(styles.validate().is_ok());
+
+        let mut tiny = StyleConfig::default();
+        tiny.ui_font.size = FONT_SIZES.start() - 1;
+        assert!(tiny.validate().is_err());
+
+        let mut huge = StyleConfig::default();
+        huge.guide_font.size = FONT_SIZES.end() + 1;
+        assert!(huge.validate().is_err());
+
+        // Text the same color as the background
+        let mut unreadable = StyleConfig::default();
+        unreadable.foreground_color = unreadable.background_color;
+        assert!(unreadable.validate().is_err());
+
+        let mut hidden = StyleConfig::default();
+        hidden.highlight_color = hidden.background_color.blend(Color::new(128, 128, 128), 16);
+        assert!(hidden.validate().is_err());
+    }
+
+    #[test]
+    fn test_set_period() {
+        let mut config = StyleConfig::default();
+        config.set_period(ThemePeriod::Night);
+        assert!(config.background_color.is_dark());
+        // Already dark, so not toggled back
+        config.set_period(ThemePeriod::Night);
+        assert!(config.background_color.is_dark());
+        config.set_period(ThemePeriod::Day);
+        assert!(!config.background_color.is_dark());
+    }
+
+    #[test]
+    fn test_rebuild_is_deterministic() {
+        let config = golden::style_config();
+        let a = Styles::from_config(config.clone()).unwrap();
+        let b = Styles::from_config(config.clone()).unwrap();
+        // Fonts from the same file are loaded once and shared
+        assert!(Arc::ptr_eq(&a.assets().ui_font, &b.assets().ui_font));
+        assert!(Arc::ptr_eq(&a.assets().ui_font, &b.assets().cjk_font));
+
+        let scale = Scale::uniform(config.ui_font.size as f32);
+        let metrics = a.ui_font().v_metrics(scale);
+        let glyphs = a.ui_font().glyph_count();
+        drop((a, b));
+
+        // Loading them again after they were freed gives the same fonts
+        let c = Styles::from_config(config).unwrap();
+        assert_eq!(c.ui_font().v_metrics(scale), metrics);
+        assert_eq!(c.ui_font().glyph_count(), glyphs);
+    }
+
+    #[test]
+    fn test_swap_frees_old_assets() {
+        let dir = temp_dir("style-assets");
+        let font = dir.join("Theme.ttf");
+        fs::copy(golden::style_config().ui_font.path, &font).unwrap();
+        RgbaImage::new(8, 8).save(dir.join("icons.png")).unwrap();
+        let icons = dir.join("icons.toml");
+        fs::write(
+            &icons,
+            "image = \"icons.png\"\n[sprites]\nA = { x = 0, y = 0, w = 8, h = 8 }\n",
+        )
+        .unwrap();
+
+        let mut config = golden::style_config();
+        config.ui_font.path = font.clone();
+        config.button_icons = Some(icons.clone());
+        let mut current = Styles::from_config(config).unwrap();
+        let view = current.clone();
+        assert!(current.button_atlas().is_some());
+        assert!(assets::is_loaded(&font));
+        assert!(assets::is_loaded(&icons));
+        let font_bytes = fs::metadata(&font).unwrap().len() as usize;
+        assert!(assets::usage().bytes >= font_bytes + 8 * 8 * 4);
+
+        // A view still drawing with the old theme keeps its assets alive
+        let old = mem::replace(&mut current, golden::styles());
+        drop(old);
+        assert!(assets::is_loaded(&font));
+        assert!(assets::is_loaded(&icons));
+
+        drop(view);
+        assert!(!assets::is_loaded(&font));
+        assert!(!assets::is_loaded(&icons));
+        assert!(assets::is_loaded(&current.ui_font.path));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,33 @@
+//! Helpers shared by the tests of Allium's crates. Other crates get them through the `test-utils`
+//! feature, which only their dev-dependency on `common` turns on.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::database::{Database, NewGame};
+
+/// An empty folder for the test named `name`, made anew if an earlier run left it behind. Each
+/// test process gets its own, so that names only need to be unique within a crate.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("allium-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes a game file at `path` within `root` and indexes it, named after the file.
+pub fn write_game(database: &Database, root: &Path, path: &str, contents: &[u8]) -> PathBuf {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, contents).unwrap();
+    database
+        .update_games(&[NewGame {
+            name: path.file_stem().unwrap().to_string_lossy().to_string(),
+            path: path.clone(),
+            image: None,
+            core: None,
+        }])
+        .unwrap();
+    path
+}
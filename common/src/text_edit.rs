@@ -0,0 +1,411 @@
+//! Editing text that is wrapped to a width, such as notes typed on the on-screen keyboard.
+//!
+//! Text is wrapped after spaces, within words that are too long for a line, and after newlines.
+//! Lines cover the text without gaps, each one ending after the space or newline it was broken
+//! at, so that every cursor position belongs to exactly one line. A cursor at a soft wrap is on
+//! the start of the following line.
+
+use std::ops::Range;
+
+/// Wraps `text` into lines no wider than `width`, as measured by `measure`. Spaces at the end of
+/// a line don't count towards its width. There is always at least one line.
+pub fn wrap(text: &str, width: u32, measure: impl Fn(&str) -> u32) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let end = line_end(text, start, width, &measure);
+        lines.push(start..end);
+        start = end;
+    }
+    // The cursor needs somewhere to go after a trailing newline
+    if text.is_empty() || text.ends_with('\n') {
+        lines.push(text.len()..text.len());
+    }
+    lines
+}
+
+/// Finds where the line starting at `start` ends.
+fn line_end(text: &str, start: usize, width: u32, measure: &impl Fn(&str) -> u32) -> usize {
+    let newline = text[start..].find('\n').map(|i| start + i);
+    let limit = newline.unwrap_or(text.len());
+    if measure(&text[start..limit]) <= width {
+        return newline.map_or(text.len(), |i| i + 1);
+    }
+
+    // Break after the last space that keeps the words before it within the width
+    let mut end = None;
+    for (i, _) in text[start..limit].match_indices(' ') {
+        if measure(text[start..start + i].trim_end_matches(' ')) > width {
+            break;
+        }
+        end = Some(start + i + 1);
+    }
+    if let Some(end) = end {
+        return end;
+    }
+
+    // The first word alone is too long, so break it where it stops fitting, keeping at least one
+    // character so that wrapping always makes progress
+    let mut boundaries = text[start..limit].char_indices().map(|(i, _)| start + i);
+    boundaries.next();
+    let mut end = boundaries.next().unwrap_or(limit);
+    for i in boundaries {
+        if measure(&text[start..i]) > width {
+            break;
+        }
+        end = i;
+    }
+    end
+}
+
+/// Index of the line that `position` is on.
+pub fn line_of(lines: &[Range<usize>], position: usize) -> usize {
+    lines
+        .iter()
+        .rposition(|line| line.start <= position)
+        .unwrap_or_default()
+}
+
+/// Text with a cursor, limited to a number of characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    text: String,
+    /// Byte offset of the cursor, always at a character boundary.
+    cursor: usize,
+    max_len: usize,
+    /// Horizontal position that moving up and down tries to keep, so that passing through a short
+    /// line doesn't lose the column.
+    goal_x: Option<u32>,
+}
+
+impl TextEdit {
+    /// Starts editing `text` with the cursor at its end. Text longer than `max_len` characters is
+    /// kept, but nothing more can be inserted.
+    pub fn new(text: String, max_len: usize) -> Self {
+        Self {
+            cursor: text.len(),
+            text,
+            max_len,
+            goal_x: None,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Length in characters.
+    pub fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    pub fn move_left(&mut self) {
+        self.goal_x = None;
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        self.goal_x = None;
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    /// Moves to the closest position on the line above, or to the start of the text from the
+    /// first line.
+    pub fn move_up(&mut self, lines: &[Range<usize>], measure: impl Fn(&str) -> u32) {
+        let line = line_of(lines, self.cursor);
+        if line == 0 {
+            self.goal_x = None;
+            self.cursor = 0;
+            return;
+        }
+        self.move_to_line(lines, line - 1, measure);
+    }
+
+    /// Moves to the closest position on the line below, or to the end of the text from the last
+    /// line.
+    pub fn move_down(&mut self, lines: &[Range<usize>], measure: impl Fn(&str) -> u32) {
+        let line = line_of(lines, self.cursor);
+        if line + 1 >= lines.len() {
+            self.goal_x = None;
+            self.cursor = self.text.len();
+            return;
+        }
+        self.move_to_line(lines, line + 1, measure);
+    }
+
+    fn move_to_line(&mut self, lines: &[Range<usize>], to: usize, measure: impl Fn(&str) -> u32) {
+        let from = &lines[line_of(lines, self.cursor)];
+        let x = *self
+            .goal_x
+            .get_or_insert_with(|| measure(&self.text[from.start..self.cursor]));
+
+        // The end of a line is the start of the next one, except on the last line
+        let line = &lines[to];
+        let end = if to + 1 == lines.len() {
+            line.end
+        } else {
+            self.text[..line.end]
+                .char_indices()
+                .next_back()
+                .map_or(line.start, |(i, _)| i)
+        };
+
+        let mut best = line.start;
+        for i in (line.start + 1..=end).filter(|&i| self.text.is_char_boundary(i)) {
+            let width = measure(&self.text[line.start..i]);
+            if width <= x {
+                best = i;
+            } else {
+                if width - x < x - measure(&self.text[line.start..best]) {
+                    best = i;
+                }
+                break;
+            }
+        }
+        self.cursor = best;
+    }
+
+    /// Inserts `s` at the cursor and moves past it, cutting it short at the length limit. Returns
+    /// whether all of it was inserted.
+    pub fn insert(&mut self, s: &str) -> bool {
+        self.goal_x = None;
+        let room = self.max_len.saturating_sub(self.len());
+        let end = s.char_indices().nth(room).map_or(s.len(), |(i, _)| i);
+        self.text.insert_str(self.cursor, &s[..end]);
+        self.cursor += end;
+        end == s.len()
+    }
+
+    /// Deletes the character before the cursor. Returns whether there was one.
+    pub fn backspace(&mut self) -> bool {
+        self.goal_x = None;
+        match self.text[..self.cursor].chars().next_back() {
+            Some(c) => {
+                self.cursor -= c.len_utf8();
+                self.text.remove(self.cursor);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every character is one unit wide.
+    fn measure(s: &str) -> u32 {
+        s.chars().count() as u32
+    }
+
+    fn lines(text: &str, width: u32) -> Vec<&str> {
+        wrap(text, width, measure)
+            .into_iter()
+            .map(|line| &text[line])
+            .collect()
+    }
+
+    /// Editor for `text`, with the cursor where `|` is.
+    fn editor(text: &str) -> TextEdit {
+        let cursor = text.find('|').unwrap();
+        let mut edit = TextEdit::new(text.replace('|', ""), 100);
+        edit.cursor = cursor;
+        edit
+    }
+
+    /// The text with `|` where the cursor is.
+    fn show(edit: &TextEdit) -> String {
+        let mut text = edit.text.clone();
+        text.insert(edit.cursor, '|');
+        text
+    }
+
+    fn up(edit: &mut TextEdit, width: u32) {
+        let lines = wrap(&edit.text, width, measure);
+        edit.move_up(&lines, measure);
+    }
+
+    fn down(edit: &mut TextEdit, width: u32) {
+        let lines = wrap(&edit.text, width, measure);
+        edit.move_down(&lines, measure);
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(lines("", 10), vec![""]);
+        assert_eq!(lines("short", 10), vec!["short"]);
+        assert_eq!(
+            lines("door code is 4512", 10),
+            vec!["door code ", "is 4512"]
+        );
+        // Spaces hang off the end of the line
+        assert_eq!(lines("abcde     fgh", 5), vec!["abcde     ", "fgh"]);
+        assert_eq!(lines("a\nb\n", 10), vec!["a\n", "b\n", ""]);
+        assert_eq!(lines("\n\n", 10), vec!["\n", "\n", ""]);
+        assert_eq!(
+            lines("one two\nthree fours five", 10),
+            vec!["one two\n", "three ", "fours five"]
+        );
+    }
+
+    #[test]
+    fn test_wrap_long_words() {
+        assert_eq!(lines("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(lines("ab cdefghij", 4), vec!["ab ", "cdef", "ghij"]);
+        assert_eq!(lines("ポケモンです", 4), vec!["ポケモン", "です"]);
+        // Narrower than a single character
+        assert_eq!(lines("abc", 0), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_line_of() {
+        let text = "door code is 4512";
+        let lines = wrap(text, 10, measure);
+        assert_eq!(line_of(&lines, 0), 0);
+        assert_eq!(line_of(&lines, 9), 0);
+        // At the wrap, the cursor is on the start of the next line
+        assert_eq!(line_of(&lines, 10), 1);
+        assert_eq!(line_of(&lines, text.len()), 1);
+
+        let lines = wrap("a\n", 10, measure);
+        assert_eq!(line_of(&lines, 1), 0);
+        assert_eq!(line_of(&lines, 2), 1);
+    }
+
+    #[test]
+    fn test_move_left_right() {
+        let mut edit = editor("é|ü");
+        edit.move_right();
+        assert_eq!(show(&edit), "éü|");
+        edit.move_right();
+        assert_eq!(show(&edit), "éü|");
+        edit.move_left();
+        edit.move_left();
+        assert_eq!(show(&edit), "|éü");
+        edit.move_left();
+        assert_eq!(show(&edit), "|éü");
+    }
+
+    #[test]
+    fn test_move_up_down_keeps_column() {
+        // Lines "abcdefgh\n", "ab\n", "abcdefgh"
+        let mut edit = editor("abcdefgh\nab\nabcdef|gh");
+        up(&mut edit, 10);
+        assert_eq!(show(&edit), "abcdefgh\nab|\nabcdefgh");
+        up(&mut edit, 10);
+        assert_eq!(show(&edit), "abcdef|gh\nab\nabcdefgh");
+        down(&mut edit, 10);
+        down(&mut edit, 10);
+        assert_eq!(show(&edit), "abcdefgh\nab\nabcdef|gh");
+
+        // Moving sideways forgets the column
+        up(&mut edit, 10);
+        edit.move_left();
+        up(&mut edit, 10);
+        assert_eq!(show(&edit), "a|bcdefgh\nab\nabcdefgh");
+    }
+
+    #[test]
+    fn test_move_up_down_across_wraps() {
+        // Lines "door code ", "is 4512"
+        let mut edit = editor("door code is 4|512");
+        up(&mut edit, 10);
+        assert_eq!(show(&edit), "door| code is 4512");
+        down(&mut edit, 10);
+        assert_eq!(show(&edit), "door code is 4|512");
+
+        // Past the end of a wrapped line, the cursor stops before the wrap, not at the start of
+        // the next line
+        let mut edit = editor("door code is 4512 b|");
+        assert_eq!(lines(edit.text(), 10), vec!["door code ", "is 4512 b"]);
+        up(&mut edit, 10);
+        assert_eq!(show(&edit), "door code| is 4512 b");
+        let lines = wrap(edit.text(), 10, measure);
+        assert_eq!(line_of(&lines, edit.cursor()), 0);
+
+        // From the start of a wrapped line
+        let mut edit = editor("door code |is 4512");
+        up(&mut edit, 10);
+        assert_eq!(show(&edit), "|door code is 4512");
+    }
+
+    #[test]
+    fn test_move_past_first_and_last_line() {
+        let mut edit = editor("ab|c\ndef");
+        up(&mut edit, 10);
+        assert_eq!(show(&edit), "|abc\ndef");
+        down(&mut edit, 10);
+        down(&mut edit, 10);
+        assert_eq!(show(&edit), "abc\ndef|");
+
+        // The empty line after a trailing newline can be reached
+        let mut edit = editor("ab|c\n");
+        down(&mut edit, 10);
+        assert_eq!(show(&edit), "abc\n|");
+    }
+
+    #[test]
+    fn test_insert_at_wrap() {
+        let mut edit = editor("door code |is 4512");
+        assert!(edit.insert("1 "));
+        assert_eq!(show(&edit), "door code 1 |is 4512");
+        assert_eq!(lines(edit.text(), 10), vec!["door code ", "1 is 4512"]);
+
+        // Typing a word that no longer fits moves it onto the next line with the cursor
+        let mut edit = editor("door cod|");
+        edit.insert("es");
+        let lines = wrap(edit.text(), 10, measure);
+        assert_eq!(lines.len(), 1);
+        edit.insert(" x");
+        let lines = wrap(edit.text(), 10, measure);
+        assert_eq!(show(&edit), "door codes x|");
+        assert_eq!(line_of(&lines, edit.cursor()), 1);
+    }
+
+    #[test]
+    fn test_insert_limit() {
+        let mut edit = TextEdit::new("abc".to_string(), 5);
+        assert!(!edit.insert("déf"));
+        assert_eq!(edit.text(), "abcdé");
+        assert_eq!(edit.len(), 5);
+        assert!(!edit.insert("g"));
+        assert_eq!(edit.text(), "abcdé");
+        assert!(edit.insert(""));
+    }
+
+    #[test]
+    fn test_backspace_joins_lines() {
+        // Deleting the space at a wrap joins the words again
+        let mut edit = editor("door code |is 4512");
+        assert!(edit.backspace());
+        assert_eq!(show(&edit), "door code|is 4512");
+        assert_eq!(lines(edit.text(), 10), vec!["door ", "codeis ", "4512"]);
+
+        let mut edit = editor("abc\n|def");
+        assert!(edit.backspace());
+        assert_eq!(show(&edit), "abc|def");
+
+        let mut edit = editor("|abc");
+        assert!(!edit.backspace());
+        let mut edit = editor("ポ|");
+        assert!(edit.backspace());
+        assert_eq!(show(&edit), "|");
+    }
+}
@@ -0,0 +1,474 @@
+//! Switches between a day and a night theme by the time of day.
+//!
+//! Day starts and ends either at fixed times or at sunrise and sunset where the user lives. While
+//! the schedule is on, the theme kept in the settings becomes the theme of the current period.
+//! A period without a theme of its own uses the other theme in its light or dark colors. Every
+//! process applies the schedule when it loads the theme, so a launcher started after a switch, or
+//! a menu opened during a game, already has the right theme. alliumd watches the clock, and posts
+//! a [`ThemeChanged`] notification to a running launcher or menu when the schedule switches, so
+//! that it applies the theme of the new period.
+use std::f64::consts::PI;
+use std::fs::{self, File};
+
+use anyhow::Result;
+use chrono::{Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, Offset};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{ALLIUM_THEME_CHANGED, ALLIUM_THEME_SCHEDULE};
+use crate::stylesheet::StyleConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemePeriod {
+    /// The day theme, or light colors.
+    Day,
+    /// The night theme, or dark colors.
+    Night,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScheduleMode {
+    /// The theme is only switched by hand.
+    Off,
+    /// Day starts at `light_from`, and night at `dark_from`.
+    Times,
+    /// Day starts at sunrise, and night at sunset.
+    Sunset,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThemeSchedule {
+    #[serde(default = "ThemeSchedule::default_mode")]
+    pub mode: ScheduleMode,
+    #[serde(default = "ThemeSchedule::default_light_from")]
+    pub light_from: NaiveTime,
+    /// May be before `light_from`, if night ends after midnight.
+    #[serde(default = "ThemeSchedule::default_dark_from")]
+    pub dark_from: NaiveTime,
+    /// Where sunrise and sunset are calculated for, in whole degrees. North is positive.
+    #[serde(default)]
+    pub latitude: i32,
+    /// East is positive.
+    #[serde(default)]
+    pub longitude: i32,
+    /// Theme used during the day, once one was kept during the day.
+    #[serde(default)]
+    pub day_theme: Option<StyleConfig>,
+    /// Theme used at night, once one was kept at night.
+    #[serde(default)]
+    pub night_theme: Option<StyleConfig>,
+}
+
+impl Default for ThemeSchedule {
+    fn default() -> Self {
+        Self {
+            mode: Self::default_mode(),
+            light_from: Self::default_light_from(),
+            dark_from: Self::default_dark_from(),
+            latitude: 0,
+            longitude: 0,
+            day_theme: None,
+            night_theme: None,
+        }
+    }
+}
+
+impl ThemeSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn default_mode() -> ScheduleMode {
+        ScheduleMode::Off
+    }
+
+    fn default_light_from() -> NaiveTime {
+        NaiveTime::from_hms_opt(7, 0, 0).unwrap()
+    }
+
+    fn default_dark_from() -> NaiveTime {
+        NaiveTime::from_hms_opt(19, 0, 0).unwrap()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.mode != ScheduleMode::Off
+    }
+
+    /// The theme to use during `period`: the day or night theme if there is one, or `current` in
+    /// the light or dark colors otherwise. A saved theme that is no longer valid is skipped.
+    pub fn theme_for(&self, period: ThemePeriod, mut current: StyleConfig) -> StyleConfig {
+        let theme = match period {
+            ThemePeriod::Day => self.day_theme.as_ref(),
+            ThemePeriod::Night => self.night_theme.as_ref(),
+        };
+        match theme.map(|theme| (theme, theme.validate())) {
+            Some((theme, Ok(()))) => return theme.clone(),
+            Some((_, Err(e))) => warn!("invalid {:?} theme: {}", period, e),
+            None => {}
+        }
+        current.set_period(period);
+        current
+    }
+
+    /// Makes `theme` the theme used during `period`.
+    pub fn set_theme(&mut self, period: ThemePeriod, theme: StyleConfig) {
+        match period {
+            ThemePeriod::Day => self.day_theme = Some(theme),
+            ThemePeriod::Night => self.night_theme = Some(theme),
+        }
+    }
+
+    /// The period right now, or None if the schedule is off.
+    pub fn current(&self) -> Option<ThemePeriod> {
+        let now = Local::now();
+        self.period_at(now.naive_local(), now.offset().fix())
+            .map(|(period, _)| period)
+    }
+
+    /// The period at `now`, a local time at `offset` from UTC, and when the next change is due.
+    /// The change may leave the period as it is, such as where the sun doesn't set for days.
+    /// Returns None if the schedule is off.
+    pub fn period_at(
+        &self,
+        now: NaiveDateTime,
+        offset: FixedOffset,
+    ) -> Option<(ThemePeriod, NaiveDateTime)> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        // A change today may be up to a day away from now, in either direction
+        let mut changes: Vec<_> = (-1..=2)
+            .flat_map(|days| self.changes(now.date() + Duration::days(days), offset))
+            .collect();
+        changes.sort_by_key(|(time, _)| *time);
+
+        let period = changes
+            .iter()
+            .rev()
+            .find(|(time, _)| *time <= now)
+            .map_or(ThemePeriod::Day, |(_, period)| *period);
+        let next = changes
+            .iter()
+            .find(|(time, _)| *time > now)
+            .map_or(now + Duration::days(1), |(time, _)| *time);
+        Some((period, next))
+    }
+
+    /// The changes of period on `date`, in order. Where two changes happen at once, the last one
+    /// wins.
+    fn changes(&self, date: NaiveDate, offset: FixedOffset) -> Vec<(NaiveDateTime, ThemePeriod)> {
+        match self.mode {
+            ScheduleMode::Off => Vec::new(),
+            ScheduleMode::Times => {
+                let mut changes = vec![
+                    (date.and_time(self.dark_from), ThemePeriod::Night),
+                    (date.and_time(self.light_from), ThemePeriod::Day),
+                ];
+                // Stable, so that day wins if both start at the same time
+                changes.sort_by_key(|(time, _)| *time);
+                changes
+            }
+            ScheduleMode::Sunset => {
+                let local =
+                    |time: NaiveDateTime| time + Duration::seconds(offset.local_minus_utc() as i64);
+                match sun(date, self.latitude as f64, self.longitude as f64) {
+                    Sun::Rises { sunrise, sunset } => vec![
+                        (local(sunrise), ThemePeriod::Day),
+                        (local(sunset), ThemePeriod::Night),
+                    ],
+                    Sun::AlwaysUp => vec![(date.and_time(NaiveTime::MIN), ThemePeriod::Day)],
+                    Sun::AlwaysDown => vec![(date.and_time(NaiveTime::MIN), ThemePeriod::Night)],
+                }
+            }
+        }
+    }
+
+    pub fn load() -> Result<Self> {
+        if ALLIUM_THEME_SCHEDULE.exists() {
+            debug!("found state, loading from file");
+            let file = File::open(ALLIUM_THEME_SCHEDULE.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(json);
+            }
+            warn!("failed to read theme schedule, removing");
+            fs::remove_file(ALLIUM_THEME_SCHEDULE.as_path())?;
+        }
+        Ok(Self::new())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_THEME_SCHEDULE.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+}
+
+/// Notification from alliumd that the schedule switched to another period. alliumd writes it to
+/// a state file and then sends `SIGUSR1` to the launcher and menu, which read it back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThemeChanged {
+    pub period: ThemePeriod,
+}
+
+impl ThemeChanged {
+    pub fn post(&self) -> Result<()> {
+        let file = File::create(ALLIUM_THEME_CHANGED.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+
+    /// The last notification posted, if any. It's left in place, as both the launcher and the
+    /// menu read it.
+    pub fn receive() -> Result<Option<Self>> {
+        if !ALLIUM_THEME_CHANGED.exists() {
+            return Ok(None);
+        }
+        let file = File::open(ALLIUM_THEME_CHANGED.as_path())?;
+        Ok(Some(serde_json::from_reader(file)?))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sun {
+    /// Sunrise and sunset in UTC.
+    Rises {
+        sunrise: NaiveDateTime,
+        sunset: NaiveDateTime,
+    },
+    /// Polar day.
+    AlwaysUp,
+    /// Polar night.
+    AlwaysDown,
+}
+
+/// When the sun rises and sets on `date` at `latitude` and `longitude`, by the sunrise equation.
+/// Accurate to a few minutes, which is plenty for picking colors.
+fn sun(date: NaiveDate, latitude: f64, longitude: f64) -> Sun {
+    let sin = |degrees: f64| (degrees * PI / 180.0).sin();
+    let cos = |degrees: f64| (degrees * PI / 180.0).cos();
+
+    // Days since noon on 2000-01-01, when the sun is due south of `longitude` on `date`
+    let j2000 = NaiveDate::from_ymd_opt(2000, 1, 1)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap();
+    let mean_noon = (date - j2000.date()).num_days() as f64 - longitude / 360.0;
+
+    let anomaly = (357.5291 + 0.98560028 * mean_noon).rem_euclid(360.0);
+    let center = 1.9148 * sin(anomaly) + 0.02 * sin(2.0 * anomaly) + 0.0003 * sin(3.0 * anomaly);
+    let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372).rem_euclid(360.0);
+    let noon = mean_noon + 0.0053 * sin(anomaly) - 0.0069 * sin(2.0 * ecliptic_longitude);
+
+    let sin_declination = sin(ecliptic_longitude) * sin(23.4397);
+    let cos_declination = (1.0 - sin_declination * sin_declination).sqrt();
+    // The sun's upper edge touches the horizon, after refraction
+    let cos_hour_angle =
+        (sin(-0.833) - sin(latitude) * sin_declination) / (cos(latitude) * cos_declination);
+    if cos_hour_angle > 1.0 {
+        return Sun::AlwaysDown;
+    }
+    if cos_hour_angle < -1.0 {
+        return Sun::AlwaysUp;
+    }
+    let hour_angle = cos_hour_angle.acos() * 180.0 / PI;
+
+    let at = |days: f64| j2000 + Duration::seconds((days * 86400.0).round() as i64);
+    Sun::Rises {
+        sunrise: at(noon - hour_angle / 360.0),
+        sunset: at(noon + hour_angle / 360.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%F %R").unwrap()
+    }
+
+    fn times(light_from: &str, dark_from: &str) -> ThemeSchedule {
+        ThemeSchedule {
+            mode: ScheduleMode::Times,
+            light_from: NaiveTime::parse_from_str(light_from, "%R").unwrap(),
+            dark_from: NaiveTime::parse_from_str(dark_from, "%R").unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn utc() -> FixedOffset {
+        FixedOffset::east_opt(0).unwrap()
+    }
+
+    #[test]
+    fn test_off() {
+        assert_eq!(
+            ThemeSchedule::new().period_at(at("2024-03-01", "12:00"), utc()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_times() {
+        let schedule = times("07:00", "19:30");
+        for (now, period, next) in [
+            ("06:59", ThemePeriod::Night, at("2024-03-01", "07:00")),
+            ("07:00", ThemePeriod::Day, at("2024-03-01", "19:30")),
+            ("12:00", ThemePeriod::Day, at("2024-03-01", "19:30")),
+            ("19:30", ThemePeriod::Night, at("2024-03-02", "07:00")),
+            ("23:59", ThemePeriod::Night, at("2024-03-02", "07:00")),
+        ] {
+            assert_eq!(
+                schedule.period_at(at("2024-03-01", now), utc()),
+                Some((period, next)),
+                "{now}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_times_across_midnight() {
+        // Light from the evening until the early morning, for someone who works nights
+        let schedule = times("22:00", "06:00");
+        for (now, period, next) in [
+            ("00:00", ThemePeriod::Day, at("2024-03-01", "06:00")),
+            ("05:59", ThemePeriod::Day, at("2024-03-01", "06:00")),
+            ("06:00", ThemePeriod::Night, at("2024-03-01", "22:00")),
+            ("21:59", ThemePeriod::Night, at("2024-03-01", "22:00")),
+            ("22:00", ThemePeriod::Day, at("2024-03-02", "06:00")),
+        ] {
+            assert_eq!(
+                schedule.period_at(at("2024-03-01", now), utc()),
+                Some((period, next)),
+                "{now}"
+            );
+        }
+
+        // Dark from midnight
+        let schedule = times("07:00", "00:00");
+        assert_eq!(
+            schedule.period_at(at("2024-03-01", "00:00"), utc()),
+            Some((ThemePeriod::Night, at("2024-03-01", "07:00")))
+        );
+        assert_eq!(
+            schedule.period_at(at("2024-03-01", "23:59"), utc()),
+            Some((ThemePeriod::Day, at("2024-03-02", "00:00")))
+        );
+    }
+
+    #[test]
+    fn test_same_times_stay_light() {
+        let schedule = times("08:00", "08:00");
+        for now in ["00:00", "08:00", "20:00"] {
+            assert_eq!(
+                schedule
+                    .period_at(at("2024-03-01", now), utc())
+                    .map(|(period, _)| period),
+                Some(ThemePeriod::Day)
+            );
+        }
+    }
+
+    #[test]
+    fn test_sun() {
+        // London on the summer solstice: sunrise 04:43 and sunset 21:21 local time
+        let Sun::Rises { sunrise, sunset } =
+            sun(NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(), 51.5, -0.13)
+        else {
+            panic!("the sun sets in London");
+        };
+        let close = |a: NaiveDateTime, b: NaiveDateTime| (a - b).num_minutes().abs() <= 5;
+        assert!(close(sunrise, at("2024-06-21", "03:43")), "{sunrise}");
+        assert!(close(sunset, at("2024-06-21", "20:21")), "{sunset}");
+
+        assert_eq!(
+            sun(NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(), 78.0, 15.0),
+            Sun::AlwaysUp
+        );
+        assert_eq!(
+            sun(NaiveDate::from_ymd_opt(2024, 12, 21).unwrap(), 78.0, 15.0),
+            Sun::AlwaysDown
+        );
+    }
+
+    #[test]
+    fn test_sunset() {
+        let schedule = ThemeSchedule {
+            mode: ScheduleMode::Sunset,
+            latitude: 35,
+            longitude: 139,
+            ..Default::default()
+        };
+        // Tokyo, where sunset in late June is around 19:00 local time
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        let (period, next) = schedule
+            .period_at(at("2024-06-21", "18:00"), tokyo)
+            .unwrap();
+        assert_eq!(period, ThemePeriod::Day);
+        assert!(
+            (next - at("2024-06-21", "19:00")).num_minutes().abs() < 20,
+            "{next}"
+        );
+
+        let (period, next) = schedule
+            .period_at(at("2024-06-21", "23:00"), tokyo)
+            .unwrap();
+        assert_eq!(period, ThemePeriod::Night);
+        // Sunrise the next morning, after midnight
+        assert_eq!(next.date(), NaiveDate::from_ymd_opt(2024, 6, 22).unwrap());
+        assert!(next.time() < NaiveTime::from_hms_opt(5, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_theme_for() {
+        let mut schedule = times("07:00", "19:00");
+        let current = StyleConfig::default();
+
+        // Without themes of their own, day and night use the light and dark colors
+        let night = schedule.theme_for(ThemePeriod::Night, current.clone());
+        assert!(night.background_color.is_dark());
+        assert_eq!(night.ui_font, current.ui_font);
+        let day = schedule.theme_for(ThemePeriod::Day, night.clone());
+        assert_eq!(day.background_color, current.background_color);
+
+        let mut night_theme = night.clone();
+        night_theme.ui_font.size = 24;
+        schedule.set_theme(ThemePeriod::Night, night_theme.clone());
+        assert_eq!(
+            schedule.theme_for(ThemePeriod::Night, current.clone()),
+            night_theme
+        );
+        // The day still follows the current theme
+        assert_eq!(schedule.theme_for(ThemePeriod::Day, current.clone()), day);
+
+        // A saved theme that became invalid is skipped
+        let mut unreadable = StyleConfig::default();
+        unreadable.foreground_color = unreadable.background_color;
+        schedule.set_theme(ThemePeriod::Day, unreadable);
+        assert_eq!(schedule.theme_for(ThemePeriod::Day, current.clone()), day);
+    }
+
+    #[test]
+    fn test_schedule_without_themes_still_loads() {
+        let json = r#"{"mode":"Times","light_from":"07:00:00","dark_from":"19:00:00","latitude":0,"longitude":0}"#;
+        let schedule: ThemeSchedule = serde_json::from_str(json).unwrap();
+        assert_eq!(schedule.mode, ScheduleMode::Times);
+        assert_eq!(schedule.day_theme, None);
+        assert_eq!(schedule.night_theme, None);
+    }
+
+    #[test]
+    fn test_polar_night_is_checked_again() {
+        let schedule = ThemeSchedule {
+            mode: ScheduleMode::Sunset,
+            latitude: 78,
+            longitude: 15,
+            ..Default::default()
+        };
+        let now = at("2024-12-21", "12:00");
+        let (period, next) = schedule.period_at(now, utc()).unwrap();
+        assert_eq!(period, ThemePeriod::Night);
+        assert_eq!(next, at("2024-12-22", "00:00"));
+    }
+}
@@ -0,0 +1,561 @@
+//! Undoing destructive actions on the library: deleting a game, and removing games from the
+//! recently played list.
+//!
+//! Deleted games are moved into a trash folder on the SD card rather than removed, and every
+//! action records what it removed from the database, so that it can be put back. The launcher
+//! offers to undo an action right after it, and the trash can be restored from or emptied in the
+//! settings. Actions are forgotten, and their trashed files deleted, once they are older than
+//! `RETENTION`, or sooner if the SD card is running out of space.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use strum::{EnumString, IntoStaticStr};
+
+use crate::constants::ALLIUM_TRASH_DIR;
+use crate::database::{Database, GameSnapshot};
+
+/// How long an action can be undone from the toast shown after it.
+pub const UNDO_WINDOW: Duration = Duration::from_secs(10);
+
+/// How long actions can be undone from the trash before they are purged.
+pub const RETENTION: chrono::Duration = chrono::Duration::days(7);
+
+/// Free space below which the oldest trashed files are purged early.
+pub const MIN_FREE_SPACE: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, IntoStaticStr)]
+pub enum UndoKind {
+    DeleteGame,
+    RemoveFromRecents,
+    ClearRecents,
+}
+
+/// A file moved into the trash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashedFile {
+    pub original: PathBuf,
+    pub trashed: PathBuf,
+    pub size: u64,
+}
+
+/// An action that can be undone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoEntry {
+    pub id: i64,
+    pub kind: UndoKind,
+    /// Unix timestamp of when the action was taken.
+    pub created: i64,
+    pub files: Vec<TrashedFile>,
+    /// Games as they were recorded before the action.
+    pub games: Vec<GameSnapshot>,
+}
+
+impl UndoEntry {
+    pub fn has_expired(&self, now: DateTime<Utc>) -> bool {
+        now.timestamp() - self.created >= RETENTION.num_seconds()
+    }
+
+    /// Bytes freed by purging the entry.
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+}
+
+/// The folder deleted games are moved into. It lives on the SD card next to the games, so moving
+/// a game there is a rename rather than a copy.
+#[derive(Debug, Clone)]
+pub struct Trash {
+    dir: PathBuf,
+}
+
+impl Default for Trash {
+    fn default() -> Self {
+        Self::at(ALLIUM_TRASH_DIR.to_path_buf())
+    }
+}
+
+impl Trash {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn at(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Moves `path` into the trash, numbering its name if the trash already has a file by that
+    /// name.
+    pub fn move_in(&self, path: &Path) -> Result<TrashedFile> {
+        let size = fs::metadata(path)?.len();
+        let name = path
+            .file_name()
+            .with_context(|| format!("{} has no file name", path.display()))?;
+        fs::create_dir_all(&self.dir)?;
+
+        let mut trashed = self.dir.join(name);
+        let stem = Path::new(name)
+            .file_stem()
+            .unwrap_or(name)
+            .to_string_lossy();
+        let extension = Path::new(name).extension().map(|e| e.to_string_lossy());
+        let mut n = 1;
+        while trashed.exists() {
+            let name = match &extension {
+                Some(extension) => format!("{} ({}).{}", stem, n, extension),
+                None => format!("{} ({})", stem, n),
+            };
+            trashed = self.dir.join(name);
+            n += 1;
+        }
+
+        fs::rename(path, &trashed)?;
+        debug!("moved {} to {}", path.display(), trashed.display());
+        Ok(TrashedFile {
+            original: path.to_path_buf(),
+            trashed,
+            size,
+        })
+    }
+
+    /// Moves a trashed file back to where it was. Fails rather than overwrite a file that has
+    /// taken its place.
+    pub fn restore(&self, file: &TrashedFile) -> Result<()> {
+        if file.original.exists() {
+            bail!("{} already exists", file.original.display());
+        }
+        if let Some(parent) = file.original.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&file.trashed, &file.original)?;
+        Ok(())
+    }
+
+    /// Deletes a trashed file for good.
+    pub fn purge(&self, file: &TrashedFile) -> Result<()> {
+        match fs::remove_file(&file.trashed) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Bytes available on the filesystem the trash is on.
+    pub fn available_space(&self) -> Result<u64> {
+        let dir = self
+            .dir
+            .ancestors()
+            .find(|dir| dir.exists())
+            .unwrap_or(&self.dir);
+        let stat = nix::sys::statvfs::statvfs(dir)?;
+        // The field types differ between targets
+        #[allow(clippy::unnecessary_cast)]
+        Ok(stat.blocks_available() as u64 * stat.fragment_size() as u64)
+    }
+}
+
+/// Moves a game into the trash along with the `related` files that go with it, such as its discs
+/// and box art, and removes it and any related files listed as games from the database, along
+/// with everything else recorded about them. If any file can't be moved, those already moved are
+/// put back.
+pub fn delete_game(
+    database: &Database,
+    trash: &Trash,
+    path: &Path,
+    related: &[PathBuf],
+) -> Result<i64> {
+    let mut games = Vec::new();
+    for file in iter::once(path).chain(related.iter().map(PathBuf::as_path)) {
+        if let Some(mut game) = database.snapshot_game(file)? {
+            game.related = Some(database.snapshot_related(file)?);
+            games.push(game);
+        }
+    }
+    let mut files = Vec::with_capacity(related.len() + 1);
+    for file in iter::once(path).chain(related.iter().map(PathBuf::as_path)) {
+        match trash.move_in(file) {
+            Ok(file) => files.push(file),
+            Err(e) => {
+                for file in &files {
+                    if let Err(e) = trash.restore(file) {
+                        warn!("failed to restore {}: {}", file.original.display(), e);
+                    }
+                }
+                return Err(e);
+            }
+        }
+    }
+    for file in &files {
+        database.purge_game(&file.original)?;
+    }
+    database.add_undo(UndoKind::DeleteGame, &files, &games)
+}
+
+/// Removes a game from the recently played list.
+pub fn remove_from_recents(database: &Database, path: &Path) -> Result<i64> {
+    let games: Vec<_> = database.snapshot_game(path)?.into_iter().collect();
+    database.remove_from_recents(path)?;
+    database.add_undo(UndoKind::RemoveFromRecents, &[], &games)
+}
+
+/// Removes every game from the recently played list. Returns None if it was already empty.
+pub fn clear_recents(database: &Database) -> Result<Option<i64>> {
+    let games = database.snapshot_recents()?;
+    if games.is_empty() {
+        return Ok(None);
+    }
+    for game in &games {
+        database.remove_from_recents(&game.path)?;
+    }
+    database
+        .add_undo(UndoKind::ClearRecents, &[], &games)
+        .map(Some)
+}
+
+/// Undoes an action: moves its files back out of the trash, and puts back what it removed from
+/// the database.
+pub fn undo(database: &Database, trash: &Trash, id: i64) -> Result<()> {
+    let entry = database
+        .undo_entry(id)?
+        .with_context(|| format!("no undo entry {}", id))?;
+    info!("undoing {:?} of {} games", entry.kind, entry.games.len());
+
+    for (i, file) in entry.files.iter().enumerate() {
+        if let Err(e) = trash.restore(file) {
+            // Put the files that were already restored back, so the entry can be tried again
+            for file in &entry.files[..i] {
+                if let Err(e) = fs::rename(&file.original, &file.trashed) {
+                    warn!("failed to trash {} again: {}", file.original.display(), e);
+                }
+            }
+            return Err(e);
+        }
+    }
+    database.restore_games(&entry.games)?;
+    database.remove_undo(id)
+}
+
+/// Forgets an action, deleting its trashed files.
+pub fn purge(database: &Database, trash: &Trash, entry: &UndoEntry) -> Result<()> {
+    for file in &entry.files {
+        trash.purge(file)?;
+    }
+    database.remove_undo(entry.id)
+}
+
+/// Purges the actions of all profiles that are older than `RETENTION`, then the oldest trashed
+/// files until `available` bytes plus those freed reach `MIN_FREE_SPACE`. Returns the number of
+/// actions purged.
+pub fn purge_old(
+    database: &Database,
+    trash: &Trash,
+    now: DateTime<Utc>,
+    available: u64,
+) -> Result<usize> {
+    let mut purged = 0;
+    let mut available = available;
+    for entry in database.all_undo_entries()? {
+        if entry.has_expired(now) {
+            debug!("purging expired undo entry {}", entry.id);
+        } else if available < MIN_FREE_SPACE && !entry.files.is_empty() {
+            debug!("purging undo entry {} to free space", entry.id);
+            available += entry.size();
+        } else {
+            continue;
+        }
+        purge(database, trash, &entry)?;
+        purged += 1;
+    }
+    Ok(purged)
+}
+
+/// Purges old actions, and trashed files if the SD card is running low on space.
+pub fn auto_purge(database: &Database, trash: &Trash) -> Result<usize> {
+    let available = trash.available_space().unwrap_or_else(|e| {
+        warn!("failed to check free space: {}", e);
+        u64::MAX
+    });
+    let purged = purge_old(database, trash, Utc::now(), available)?;
+    if purged > 0 {
+        info!("purged {} undo entries", purged);
+    }
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{temp_dir, write_game};
+
+    struct Fixture {
+        dir: PathBuf,
+        database: Database,
+        trash: Trash,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let dir = temp_dir(&format!("trash-{}", name));
+            Self {
+                trash: Trash::at(dir.join(".trash")),
+                dir,
+                database: Database::in_memory().unwrap(),
+            }
+        }
+
+        /// Writes a game file, indexes it, and plays it.
+        fn game(&self, path: &str, contents: &[u8]) -> PathBuf {
+            let path = write_game(&self.database, &self.dir, path, contents);
+            self.database
+                .increment_play_count("game", &path, None)
+                .unwrap();
+            path
+        }
+
+        fn recents(&self) -> Vec<PathBuf> {
+            self.database
+                .select_last_played(10)
+                .unwrap()
+                .into_iter()
+                .map(|game| game.path)
+                .collect()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn test_delete_game_with_related_files() {
+        let fixture = Fixture::new("related");
+        let game = fixture.game("Roms/PS/Game.cue", b"FILE \"Game.bin\" BINARY");
+        let track = fixture.dir.join("Roms/PS/Game.bin");
+        let art = fixture.dir.join("Roms/PS/Imgs/Game.png");
+        fs::write(&track, b"track").unwrap();
+        fs::create_dir_all(art.parent().unwrap()).unwrap();
+        fs::write(&art, b"art").unwrap();
+
+        // A related file that can't be moved leaves everything in place
+        let missing = fixture.dir.join("Roms/PS/Missing.bin");
+        let related = [track.clone(), missing];
+        assert!(delete_game(&fixture.database, &fixture.trash, &game, &related).is_err());
+        assert!(game.exists() && track.exists());
+        assert!(fixture.database.snapshot_game(&game).unwrap().is_some());
+
+        let related = [track.clone(), art.clone()];
+        let id = delete_game(&fixture.database, &fixture.trash, &game, &related).unwrap();
+        assert!(!game.exists() && !track.exists() && !art.exists());
+        assert!(fixture.database.snapshot_game(&game).unwrap().is_none());
+
+        undo(&fixture.database, &fixture.trash, id).unwrap();
+        assert_eq!(fs::read(&track).unwrap(), b"track");
+        assert_eq!(fs::read(&art).unwrap(), b"art");
+        assert!(fixture.database.snapshot_game(&game).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_undo_delete_game() {
+        let fixture = Fixture::new("delete");
+        let a = fixture.game("Roms/GB/a/tetris.gb", b"tetris a");
+        let b = fixture.game("Roms/GB/b/tetris.gb", b"tetris b");
+        let before = fixture.database.snapshot_game(&a).unwrap();
+
+        let id_a = delete_game(&fixture.database, &fixture.trash, &a, &[]).unwrap();
+        let id_b = delete_game(&fixture.database, &fixture.trash, &b, &[]).unwrap();
+        assert!(!a.exists());
+        assert!(fixture.database.snapshot_game(&a).unwrap().is_none());
+
+        // Files with the same name don't overwrite each other in the trash
+        let entries = fixture.database.undo_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].files[0].trashed,
+            fixture.trash.dir().join("tetris (1).gb")
+        );
+        assert_eq!(
+            entries[1].files[0].trashed,
+            fixture.trash.dir().join("tetris.gb")
+        );
+
+        undo(&fixture.database, &fixture.trash, id_a).unwrap();
+        assert_eq!(fs::read(&a).unwrap(), b"tetris a");
+        assert_eq!(fixture.database.snapshot_game(&a).unwrap(), before);
+        assert!(fixture.database.undo_entry(id_a).unwrap().is_none());
+
+        // A file that took the game's place isn't overwritten, and the entry is kept
+        fs::write(&b, b"new").unwrap();
+        assert!(undo(&fixture.database, &fixture.trash, id_b).is_err());
+        assert_eq!(fs::read(&b).unwrap(), b"new");
+        assert!(fixture.database.undo_entry(id_b).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_delete_game_purges_related_rows() {
+        let fixture = Fixture::new("purge");
+        let database = &fixture.database;
+        let a = fixture.game("Roms/GB/a.gb", b"a");
+        let b = fixture.game("Roms/GB/b.gb", b"b");
+        database.set_favorite(&a, true).unwrap();
+        database.set_favorite(&b, true).unwrap();
+        database.set_note(&a, "Beat the first boss").unwrap();
+        database.set_game_name(&a, "Alpha").unwrap();
+        database.set_core_override(&a, Some("gambatte")).unwrap();
+        database.set_completed(&a, true).unwrap();
+        let collection = database.create_collection("Handhelds").unwrap();
+        database.add_to_collection(collection.id, &a).unwrap();
+        database
+            .add_play_time(&a, chrono::Duration::minutes(5))
+            .unwrap();
+        let yesterday = Utc::now() - chrono::Duration::days(1);
+
+        let id = delete_game(database, &fixture.trash, &a, &[]).unwrap();
+        assert_eq!(database.favorites().unwrap(), vec![b.clone()]);
+        assert_eq!(database.note(&a).unwrap(), None);
+        assert!(database.game_names().unwrap().is_empty());
+        assert_eq!(database.core_override(&a).unwrap(), None);
+        assert!(!database.is_completed(&a).unwrap());
+        assert!(database.collection_games(collection.id).unwrap().is_empty());
+        assert_eq!(
+            database.play_time_since(yesterday).unwrap().num_minutes(),
+            0
+        );
+
+        undo(database, &fixture.trash, id).unwrap();
+        assert_eq!(database.favorites().unwrap(), vec![a.clone(), b]);
+        assert_eq!(
+            database.note(&a).unwrap().as_deref(),
+            Some("Beat the first boss")
+        );
+        assert_eq!(database.game_names().unwrap().get(&a).unwrap(), "Alpha");
+        assert_eq!(
+            database.core_override(&a).unwrap().as_deref(),
+            Some("gambatte")
+        );
+        assert!(database.is_completed(&a).unwrap());
+        assert_eq!(
+            database.collection_games(collection.id).unwrap(),
+            vec![a.clone()]
+        );
+        assert_eq!(
+            database.play_time_since(yesterday).unwrap().num_minutes(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_undo_remove_from_recents() {
+        let fixture = Fixture::new("recents");
+        let a = fixture.game("Roms/GB/a.gb", b"a");
+        let b = fixture.game("Roms/GB/b.gb", b"b");
+        fixture
+            .database
+            .add_play_time(&a, chrono::Duration::minutes(5))
+            .unwrap();
+        assert_eq!(fixture.recents(), vec![b.clone(), a.clone()]);
+
+        let id = remove_from_recents(&fixture.database, &a).unwrap();
+        assert_eq!(fixture.recents(), vec![b.clone()]);
+
+        undo(&fixture.database, &fixture.trash, id).unwrap();
+        assert_eq!(fixture.recents(), vec![b, a.clone()]);
+        let game = fixture
+            .database
+            .select_game(&a.display().to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(game.play_time, chrono::Duration::minutes(5));
+    }
+
+    #[test]
+    fn test_undo_clear_recents() {
+        let fixture = Fixture::new("clear");
+        let a = fixture.game("Roms/GB/a.gb", b"a");
+        let b = fixture.game("Roms/GB/b.gb", b"b");
+        // The file of a recently played game is gone, so it is removed from the database
+        let gone = fixture.game("Roms/GB/gone.gb", b"gone");
+        fs::remove_file(&gone).unwrap();
+        let recents = fixture.recents();
+
+        let id = clear_recents(&fixture.database).unwrap().unwrap();
+        assert!(fixture.recents().is_empty());
+        assert!(fixture.database.snapshot_game(&gone).unwrap().is_none());
+        assert!(fixture.database.snapshot_game(&a).unwrap().is_some());
+        assert_eq!(clear_recents(&fixture.database).unwrap(), None);
+
+        undo(&fixture.database, &fixture.trash, id).unwrap();
+        assert_eq!(fixture.recents(), recents);
+        assert_eq!(recents, vec![gone, b, a]);
+    }
+
+    #[test]
+    fn test_auto_purge() {
+        let fixture = Fixture::new("purge");
+        let a = fixture.game("Roms/GB/a.gb", &[0; 100]);
+        let b = fixture.game("Roms/GB/b.gb", &[0; 200]);
+        let c = fixture.game("Roms/GB/c.gb", &[0; 300]);
+        delete_game(&fixture.database, &fixture.trash, &a, &[]).unwrap();
+        delete_game(&fixture.database, &fixture.trash, &b, &[]).unwrap();
+        remove_from_recents(&fixture.database, &c).unwrap();
+        delete_game(&fixture.database, &fixture.trash, &c, &[]).unwrap();
+        let trashed = |i: usize| {
+            fixture.database.all_undo_entries().unwrap()[i].files[0]
+                .trashed
+                .clone()
+        };
+        let a_trashed = trashed(0);
+
+        // Nothing is purged while there is space and the entries are recent
+        let now = Utc::now();
+        assert_eq!(
+            purge_old(&fixture.database, &fixture.trash, now, MIN_FREE_SPACE).unwrap(),
+            0
+        );
+
+        // Running low on space purges the oldest trashed files until there is enough, and leaves
+        // entries without files alone
+        let purged =
+            purge_old(&fixture.database, &fixture.trash, now, MIN_FREE_SPACE - 150).unwrap();
+        assert_eq!(purged, 2);
+        assert!(!a_trashed.exists());
+        let entries = fixture.database.all_undo_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].kind, UndoKind::RemoveFromRecents);
+        assert!(entries[1].files[0].trashed.exists());
+
+        // Entries of every profile expire after a week
+        let other = fixture.database.with_profile("other");
+        other.add_undo(UndoKind::ClearRecents, &[], &[]).unwrap();
+        let purged = purge_old(
+            &fixture.database,
+            &fixture.trash,
+            now + RETENTION - chrono::Duration::hours(1),
+            MIN_FREE_SPACE,
+        )
+        .unwrap();
+        assert_eq!(purged, 0);
+        let purged = purge_old(
+            &fixture.database,
+            &fixture.trash,
+            now + RETENTION + chrono::Duration::minutes(1),
+            MIN_FREE_SPACE,
+        )
+        .unwrap();
+        assert_eq!(purged, 3);
+        assert!(fixture.database.all_undo_entries().unwrap().is_empty());
+        assert!(fs::read_dir(fixture.trash.dir()).unwrap().next().is_none());
+    }
+}
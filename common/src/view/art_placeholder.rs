@@ -0,0 +1,283 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use embedded_graphics::prelude::{Dimensions, Size};
+use embedded_graphics::primitives::{Primitive, PrimitiveStyle, RoundedRectangle};
+use embedded_graphics::text::Text;
+use embedded_graphics::Drawable;
+use tokio::sync::mpsc::Sender;
+
+use crate::command::Command;
+use crate::display::color::Color;
+use crate::display::font::{FontTextStyle, FontTextStyleBuilder};
+use crate::display::Display;
+use crate::geom::{Alignment, Point, Rect};
+use crate::platform::{DefaultPlatform, KeyEvent, Platform};
+use crate::stylesheet::{Styles, StylesheetColor};
+use crate::text_edit::wrap;
+use crate::view::{LastDrawn, View};
+
+const PADDING: u32 = 12;
+
+/// Drawn in place of box art that a game doesn't have: the game's name in large text, and a hint
+/// at the bottom saying how to add art.
+#[derive(Debug, Clone)]
+pub struct ArtPlaceholder {
+    rect: Rect,
+    name: String,
+    hint: String,
+    hint_color: StylesheetColor,
+    drawn: LastDrawn,
+    dirty: bool,
+}
+
+impl ArtPlaceholder {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            name: String::new(),
+            hint: String::new(),
+            hint_color: StylesheetColor::Disabled,
+            drawn: LastDrawn::default(),
+            dirty: true,
+        }
+    }
+
+    pub fn set_name(&mut self, name: &str) -> &mut Self {
+        if self.name != name {
+            self.name = name.to_string();
+            self.dirty = true;
+        }
+        self
+    }
+
+    pub fn set_hint(&mut self, hint: String, color: StylesheetColor) -> &mut Self {
+        if self.hint != hint {
+            self.hint = hint;
+            self.dirty = true;
+        }
+        self.hint_color = color;
+        self
+    }
+
+    fn draw_placeholder<D: Display>(&mut self, display: &mut D, styles: &Styles) -> Result<()> {
+        let background = StylesheetColor::BackgroundHighlightBlend.to_color(styles);
+        self.drawn.clear(display)?;
+        display.load(self.rect)?;
+        self.drawn.set(self.rect);
+        RoundedRectangle::with_equal_corners(self.rect.into(), Size::new_equal(12))
+            .into_styled(PrimitiveStyle::with_fill(background))
+            .draw(display)?;
+
+        let width = self.rect.w.saturating_sub(2 * PADDING);
+        let center = self.rect.x + self.rect.w as i32 / 2;
+
+        // The hint goes at the bottom, and the name gets whatever room is left above it
+        let hint_style = text_style(
+            styles.guide_font(),
+            styles.guide_font.size,
+            self.hint_color.to_color(styles),
+            background,
+        );
+        let hint_lines = if self.hint.is_empty() {
+            Vec::new()
+        } else {
+            wrap(&self.hint, width, |s| measure(&hint_style, s))
+        };
+        let hint_height = hint_lines.len() as u32 * styles.guide_font.size;
+        let mut y = self.rect.y + self.rect.h.saturating_sub(PADDING + hint_height) as i32;
+        for line in &hint_lines {
+            draw_line(display, &self.hint[line.clone()], center, y, &hint_style)?;
+            y += styles.guide_font.size as i32;
+        }
+
+        let name_style = text_style(
+            styles.ui_font(),
+            styles.ui_font.size,
+            styles.foreground_color,
+            background,
+        );
+        let room = self
+            .rect
+            .h
+            .saturating_sub(3 * PADDING + hint_height)
+            .checked_div(styles.ui_font.size)
+            .unwrap_or_default() as usize;
+        let mut y = self.rect.y + PADDING as i32;
+        for line in name_lines(&self.name, width, room, |s| measure(&name_style, s)) {
+            draw_line(display, &line, center, y, &name_style)?;
+            y += styles.ui_font.size as i32;
+        }
+
+        Ok(())
+    }
+}
+
+/// Wraps `name` into at most `max_lines` lines no wider than `width`, ending the last line with
+/// an ellipsis if the name doesn't fit.
+fn name_lines(
+    name: &str,
+    width: u32,
+    max_lines: usize,
+    measure: impl Fn(&str) -> u32,
+) -> Vec<String> {
+    let lines: Vec<Range<usize>> = wrap(name, width, &measure);
+    let mut shown: Vec<String> = lines
+        .iter()
+        .take(max_lines)
+        .map(|line| name[line.clone()].trim_end().to_string())
+        .collect();
+    if lines.len() > max_lines {
+        if let Some(last) = shown.last_mut() {
+            while !last.is_empty() && measure(&format!("{}…", last)) > width {
+                last.pop();
+            }
+            *last = format!("{}…", last.trim_end());
+        }
+    }
+    shown
+}
+
+fn text_style(
+    font: rusttype::Font<'static>,
+    size: u32,
+    color: Color,
+    background: Color,
+) -> FontTextStyle<Color> {
+    FontTextStyleBuilder::new(font)
+        .font_size(size)
+        .text_color(color)
+        .background_color(background)
+        .build()
+}
+
+fn measure(style: &FontTextStyle<Color>, text: &str) -> u32 {
+    Text::new(text, Point::zero().into(), style.clone())
+        .bounding_box()
+        .size
+        .width
+}
+
+fn draw_line<D: Display>(
+    display: &mut D,
+    text: &str,
+    x: i32,
+    y: i32,
+    style: &FontTextStyle<Color>,
+) -> Result<()> {
+    Text::with_alignment(
+        text,
+        Point::new(x, y).into(),
+        style.clone(),
+        Alignment::Center.into(),
+    )
+    .draw(display)?;
+    Ok(())
+}
+
+#[async_trait(?Send)]
+impl View for ArtPlaceholder {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        self.draw_placeholder(display, styles)?;
+        self.dirty = false;
+        Ok(true)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        _event: KeyEvent,
+        _commands: Sender<Command>,
+        _bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        Vec::new()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        Vec::new()
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.rect.top_left(), point);
+        self.rect.x = point.x;
+        self.rect.y = point.y;
+        self.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::golden::{assert_golden, styles, Framebuffer};
+
+    const NAME: &str = "The Legend of Zelda - A Link to the Past & Four Swords";
+
+    fn draw(name: &str, hint: &str, height: u32) -> Framebuffer {
+        let styles = styles();
+        let mut display = Framebuffer::new(250, height, styles.background_color);
+        display.save().unwrap();
+        let mut placeholder = ArtPlaceholder::new(Rect::new(0, 0, 250, height));
+        placeholder
+            .set_name(name)
+            .set_hint(hint.to_string(), StylesheetColor::Disabled);
+        placeholder.draw_placeholder(&mut display, &styles).unwrap();
+        display
+    }
+
+    #[test]
+    fn test_long_name_wraps() {
+        let display = draw(NAME, "Put a PNG at Roms/GBA/Imgs/Zelda.png", 360);
+        assert_golden("art_placeholder_long_name", &display);
+    }
+
+    #[test]
+    fn test_name_is_truncated_above_hint() {
+        let display = draw(
+            &format!("{NAME} {NAME}"),
+            "Press Start to search for art",
+            200,
+        );
+        assert_golden("art_placeholder_truncated", &display);
+    }
+
+    #[test]
+    fn test_name_lines() {
+        // Every character is 10 wide
+        let measure = |s: &str| s.chars().count() as u32 * 10;
+        assert_eq!(
+            name_lines("Super Mario Land", 100, 3, measure),
+            ["Super", "Mario Land"]
+        );
+        assert_eq!(
+            name_lines("Super Mario Land 2 - 6 Golden Coins", 100, 2, measure),
+            ["Super", "Mario Lan…"]
+        );
+        // A word longer than the width is broken
+        assert_eq!(
+            name_lines("Supercalifragilistic", 100, 3, measure),
+            ["Supercalif", "ragilistic"]
+        );
+        assert!(name_lines("Tetris", 100, 0, measure).is_empty());
+    }
+}
@@ -16,8 +16,8 @@ use crate::constants::BATTERY_UPDATE_INTERVAL;
 use crate::display::Display;
 use crate::geom::{Point, Rect};
 use crate::platform::{DefaultPlatform, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
-use crate::view::{Command, View};
+use crate::stylesheet::Styles;
+use crate::view::{Command, LastDrawn, View};
 
 #[derive(Debug, Clone)]
 pub struct BatteryIndicator<B>
@@ -27,6 +27,7 @@ where
     point: Point,
     last_updated: Instant,
     battery: B,
+    drawn: LastDrawn,
     dirty: bool,
 }
 
@@ -40,6 +41,7 @@ where
             point,
             last_updated: Instant::now(),
             battery,
+            drawn: LastDrawn::default(),
             dirty: true,
         }
     }
@@ -64,12 +66,15 @@ where
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
         if self.dirty {
-            display.load(self.bounding_box(styles))?;
+            let rect = self.bounding_box(styles);
+            self.drawn.clear(display)?;
+            display.load(rect)?;
+            self.drawn.set(rect);
 
             let w = styles.ui_font.size;
             let h = styles.ui_font.size * 3 / 5;
@@ -236,18 +241,20 @@ where
         vec![]
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
         let w = styles.ui_font.size * 2;
         let h = w * 3 / 5;
         Rect::new(
             self.point.x - w as i32,
-            styles.ui_font.size as i32 / 6,
+            self.point.y + styles.ui_font.size as i32 / 6,
             w,
             h,
         )
     }
 
     fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.point, point);
         self.point = point;
+        self.dirty = true;
     }
 }
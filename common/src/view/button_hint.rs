@@ -6,9 +6,9 @@ use async_trait::async_trait;
 use tokio::sync::mpsc::Sender;
 
 use crate::display::Display;
-use crate::geom::{Alignment, Point, Rect};
+use crate::geom::{Alignment, Point, Rect, Size};
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
+use crate::stylesheet::Styles;
 use crate::view::{ButtonIcon, Command, Label, View};
 
 #[derive(Debug, Clone)]
@@ -42,13 +42,17 @@ where
         }
     }
 
+    pub fn text(&self) -> &str {
+        self.label.text()
+    }
+
     pub fn set_text(&mut self, text: S) {
         self.label.set_text(text);
         self.has_layout = false;
         self.dirty = true;
     }
 
-    fn layout(&mut self, styles: &Stylesheet) {
+    fn layout(&mut self, styles: &Styles) {
         match self.alignment {
             Alignment::Left => self.layout_left(styles),
             Alignment::Center => unimplemented!("alignment should be Left or Right"),
@@ -57,15 +61,15 @@ where
         self.has_layout = true;
     }
 
-    fn layout_left(&mut self, styles: &Stylesheet) {
+    fn layout_left(&mut self, styles: &Styles) {
         self.button.set_position(self.point);
         self.label.set_position(Point::new(
-            self.point.x + ButtonIcon::diameter(styles) as i32 + 8,
+            self.point.x + self.button.bounding_box(styles).w as i32 + 8,
             self.point.y + 2,
         ));
     }
 
-    fn layout_right(&mut self, styles: &Stylesheet) {
+    fn layout_right(&mut self, styles: &Styles) {
         self.label
             .set_position(Point::new(self.point.x, self.point.y + 2));
         self.button.set_position(Point::new(
@@ -83,7 +87,7 @@ where
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         if !self.has_layout {
             self.layout(styles);
@@ -129,7 +133,7 @@ where
         vec![&mut self.button, &mut self.label]
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
         if !self.has_layout {
             self.layout(styles);
         }
@@ -139,8 +143,60 @@ where
             .union(&self.label.bounding_box(styles))
     }
 
+    fn size_hint(&mut self, styles: &Styles) -> Size {
+        let button = self.button.size_hint(styles);
+        let label = self.label.size_hint(styles);
+        Size::new(button.w + 8 + label.w, button.h.max(label.h + 2))
+    }
+
     fn set_position(&mut self, point: Point) {
         self.point = point;
         self.has_layout = false;
+        self.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use image::RgbaImage;
+
+    use super::*;
+    use crate::display::atlas::IconAtlas;
+    use crate::display::golden::{styles, with_button_atlas};
+    use crate::view::Row;
+
+    #[test]
+    fn test_layout_uses_sprite_width() {
+        let styles = with_button_atlas(
+            &styles(),
+            Arc::new(
+                IconAtlas::new(
+                    RgbaImage::new(60, 36),
+                    [(Key::A, Rect::new(0, 0, 60, 36))].into_iter().collect(),
+                )
+                .unwrap(),
+            ),
+        );
+
+        let mut hint = ButtonHint::new(Point::new(0, 0), Key::A, "OK", Alignment::Left);
+        hint.layout(&styles);
+        assert_eq!(hint.label.bounding_box(&styles).x, 68);
+
+        let mut row = Row::new(
+            Point::new(600, 0),
+            vec![
+                ButtonHint::new(Point::zero(), Key::A, "OK", Alignment::Right),
+                ButtonHint::new(Point::zero(), Key::B, "Back", Alignment::Right),
+            ],
+            Alignment::Right,
+            12,
+        );
+        row.bounding_box(&styles);
+        let a = row.get_mut(0).unwrap().bounding_box(&styles);
+        let b = row.get_mut(1).unwrap().bounding_box(&styles);
+        assert_eq!(row.get_mut(0).unwrap().button.bounding_box(&styles).w, 60);
+        assert_eq!(b.x + b.w as i32 + 12, a.x);
     }
 }
@@ -2,7 +2,7 @@ use std::collections::VecDeque;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use embedded_graphics::prelude::{Dimensions, Size};
+use embedded_graphics::prelude::{Dimensions, DrawTarget, Size};
 use embedded_graphics::primitives::{
     Circle, CornerRadii, CornerRadiiBuilder, Primitive, PrimitiveStyle, Rectangle, RoundedRectangle,
 };
@@ -11,17 +11,20 @@ use embedded_graphics::Drawable;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
+use crate::display::color::Color;
 use crate::display::font::FontTextStyleBuilder;
 use crate::geom::{Alignment, Point, Rect};
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
-use crate::view::{Command, View};
+use crate::stylesheet::Styles;
+use crate::view::{Command, LastDrawn, View};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct ButtonIcon {
     point: Point,
     button: Key,
     alignment: Alignment,
+    #[serde(skip)]
+    drawn: LastDrawn,
     dirty: bool,
 }
 
@@ -31,22 +34,43 @@ impl ButtonIcon {
             point,
             button,
             alignment,
+            drawn: LastDrawn::default(),
             dirty: true,
         }
     }
 
-    pub fn diameter(styles: &Stylesheet) -> u32 {
+    pub fn diameter(styles: &Styles) -> u32 {
         styles.ui_font.size
     }
-}
 
-#[async_trait(?Send)]
-impl View for ButtonIcon {
-    fn draw(
-        &mut self,
-        display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
-    ) -> Result<bool> {
+    /// Where the theme's sprite for this button is drawn, if it has one. Sprites are centered
+    /// vertically on the built-in icon.
+    fn sprite_rect(&self, styles: &Styles) -> Option<Rect> {
+        let size = styles.button_atlas()?.sprite_size(self.button)?;
+        let x = match self.alignment {
+            Alignment::Left => self.point.x,
+            Alignment::Center => self.point.x - (size.width / 2) as i32,
+            Alignment::Right => self.point.x - size.width as i32,
+        };
+        let y = self.point.y + (Self::diameter(styles) as i32 - size.height as i32) / 2;
+        Some(Rect::new(x, y, size.width, size.height))
+    }
+
+    pub(crate) fn draw_icon<D>(&mut self, display: &mut D, styles: &Styles) -> Result<()>
+    where
+        D: DrawTarget<Color = Color, Error = anyhow::Error>,
+    {
+        if let (Some(rect), Some(atlas)) = (self.sprite_rect(styles), styles.button_atlas()) {
+            atlas.draw(
+                display,
+                self.button,
+                Point::new(rect.x, rect.y).into(),
+                styles.background_color,
+            )?;
+            self.dirty = false;
+            return Ok(());
+        }
+
         let (color, text) = match self.button {
             Key::A => (styles.button_a_color, "A"),
             Key::B => (styles.button_b_color, "B"),
@@ -82,8 +106,8 @@ impl View for ButtonIcon {
             }
         };
 
-        let text_style = FontTextStyleBuilder::new(styles.ui_font.font())
-            .font_fallback(styles.cjk_font.font())
+        let text_style = FontTextStyleBuilder::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
             .font_size(diameter * 3 / 4)
             .text_color(styles.foreground_color)
             .build();
@@ -244,6 +268,21 @@ impl View for ButtonIcon {
 
         self.dirty = false;
 
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl View for ButtonIcon {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        self.drawn.clear(display)?;
+        self.draw_icon(display, styles)?;
+        let rect = self.bounding_box(styles);
+        self.drawn.set(rect);
         Ok(true)
     }
 
@@ -272,7 +311,11 @@ impl View for ButtonIcon {
         Vec::new()
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
+        if let Some(rect) = self.sprite_rect(styles) {
+            return rect;
+        }
+
         let diameter = Self::diameter(styles);
         let x = match self.alignment {
             Alignment::Left => self.point.x,
@@ -330,8 +373,8 @@ impl View for ButtonIcon {
             | Key::Left
             | Key::R2 => Rect::new(point.x, point.y, diameter, diameter),
             _ => {
-                let text_style = FontTextStyleBuilder::new(styles.ui_font.font())
-                    .font_fallback(styles.cjk_font.font())
+                let text_style = FontTextStyleBuilder::new(styles.ui_font())
+                    .font_fallback(styles.cjk_font())
                     .font_size(diameter * 3 / 4)
                     .text_color(styles.background_color)
                     .build();
@@ -356,11 +399,77 @@ impl View for ButtonIcon {
             }
         };
 
-        Rect::new(x, self.point.y - 1, rect.w, rect.h)
+        Rect::new(x, self.point.y, rect.w, rect.h)
     }
 
     fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.point, point);
         self.point = point;
         self.dirty = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::display::atlas::IconAtlas;
+    use crate::display::golden::{assert_golden, styles, with_button_atlas, Framebuffer};
+
+    fn atlas() -> Arc<IconAtlas> {
+        // A wide chip for A with a translucent border
+        let mut image = RgbaImage::from_pixel(48, 24, Rgba([255, 255, 255, 128]));
+        for y in 2..22 {
+            for x in 2..46 {
+                image.put_pixel(x, y, Rgba([0, 200, 0, 255]));
+            }
+        }
+        Arc::new(
+            IconAtlas::new(
+                image,
+                [(Key::A, Rect::new(0, 0, 48, 24))].into_iter().collect(),
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_draw_builtin() {
+        let styles = styles();
+        let mut display = Framebuffer::new(40, 40, styles.background_color);
+        let mut icon = ButtonIcon::new(Point::new(2, 2), Key::A, Alignment::Left);
+        icon.draw_icon(&mut display, &styles).unwrap();
+        assert_golden("button_icon_builtin", &display);
+    }
+
+    #[test]
+    fn test_draw_atlas() {
+        let styles = with_button_atlas(&styles(), atlas());
+        let mut display = Framebuffer::new(52, 40, styles.background_color);
+        let mut icon = ButtonIcon::new(Point::new(2, 2), Key::A, Alignment::Left);
+        icon.draw_icon(&mut display, &styles).unwrap();
+        assert_golden("button_icon_atlas", &display);
+    }
+
+    #[test]
+    fn test_atlas_missing_key_falls_back() {
+        let styles = with_button_atlas(&styles(), atlas());
+        let mut display = Framebuffer::new(40, 40, styles.background_color);
+        let mut icon = ButtonIcon::new(Point::new(2, 2), Key::B, Alignment::Left);
+        icon.draw_icon(&mut display, &styles).unwrap();
+        assert_golden("button_icon_fallback", &display);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let styles = styles();
+        let mut icon = ButtonIcon::new(Point::new(100, 10), Key::A, Alignment::Right);
+        assert_eq!(icon.bounding_box(&styles), Rect::new(64, 10, 36, 36));
+
+        let styles = with_button_atlas(&styles, atlas());
+        assert_eq!(icon.bounding_box(&styles), Rect::new(52, 16, 48, 24));
+    }
+}
@@ -9,9 +9,9 @@ use tokio::sync::mpsc::Sender;
 
 use crate::constants::CLOCK_UPDATE_INTERVAL;
 use crate::display::Display;
-use crate::geom::{Alignment, Point, Rect};
+use crate::geom::{Alignment, Point, Rect, Size};
 use crate::platform::{DefaultPlatform, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
+use crate::stylesheet::Styles;
 use crate::view::{Command, Label, View};
 
 #[derive(Debug, Clone)]
@@ -45,7 +45,7 @@ impl View for Clock {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         display.load(self.bounding_box(styles))?;
         self.label.draw(display, styles)
@@ -76,10 +76,14 @@ impl View for Clock {
         vec![&mut self.label]
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
         self.label.bounding_box(styles)
     }
 
+    fn size_hint(&mut self, styles: &Styles) -> Size {
+        self.label.size_hint(styles)
+    }
+
     fn set_position(&mut self, point: Point) {
         self.point = point;
         self.label.set_position(point);
@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::command::{Command, Value};
+use crate::display::Display;
+use crate::geom::{Alignment, Point, Rect};
+use crate::locale::Locale;
+use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use crate::resources::Resources;
+use crate::stylesheet::{Styles, StylesheetColor};
+use crate::view::{ButtonHint, ButtonIcon, Image, ImageMode, Label, LastDrawn, Row, View};
+
+/// Asks to confirm an action, showing images side by side to compare what will change, e.g. the
+/// state that is about to be overwritten and the new one.
+///
+/// Confirming bubbles `ValueChanged(0, Value::Bool(true))`, then `CloseView`. Cancelling only
+/// bubbles `CloseView`.
+#[derive(Debug)]
+pub struct ConfirmDialog {
+    rect: Rect,
+    title: Label<String>,
+    images: Vec<Image>,
+    captions: Vec<Label<String>>,
+    button_hints: Row<ButtonHint<String>>,
+    /// Where each child is relative to the dialog, in the order of [`View::children`].
+    offsets: Vec<Point>,
+    dirty: bool,
+    drawn: LastDrawn,
+}
+
+impl ConfirmDialog {
+    /// `images` are shown left to right, each with a caption below it. Images that don't exist
+    /// leave their space empty.
+    pub fn new(
+        rect: Rect,
+        res: Resources,
+        title: String,
+        images: Vec<(Option<PathBuf>, String)>,
+    ) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Styles>();
+
+        let font_size = styles.ui_font.size as i32;
+        let button_height = ButtonIcon::diameter(&styles) as i32 + 16;
+
+        let title_offset = Point::new(w as i32 / 2, 8);
+        let mut title = Label::new(
+            Point::new(x + title_offset.x, y + title_offset.y),
+            title,
+            Alignment::Center,
+            Some(w - 24),
+        );
+        title.color(StylesheetColor::Highlight);
+
+        let count = images.len().max(1) as i32;
+        let image_y = y + 8 + font_size + 16;
+        let image_w = (w as i32 - 12 * (count + 1)) / count;
+        let image_h = h as i32 - (image_y - y) - font_size - 16 - button_height;
+        let image_offset_x = |i: usize| 12 + i as i32 * (image_w + 12);
+
+        let (images, captions): (Vec<_>, Vec<_>) = images
+            .into_iter()
+            .enumerate()
+            .map(|(i, (path, caption))| {
+                let image_x = x + image_offset_x(i);
+                let mut image = Image::empty(
+                    Rect::new(image_x, image_y, image_w as u32, image_h.max(1) as u32),
+                    ImageMode::Contain,
+                );
+                image.set_path(path);
+                let caption = Label::new(
+                    Point::new(image_x + image_w / 2, image_y + image_h + 8),
+                    caption,
+                    Alignment::Center,
+                    Some(image_w as u32),
+                );
+                (image, caption)
+            })
+            .unzip();
+
+        let mut offsets = vec![title_offset];
+        offsets.extend((0..images.len()).map(|i| Point::new(image_offset_x(i), image_y - y)));
+        offsets.extend(
+            (0..captions.len())
+                .map(|i| Point::new(image_offset_x(i) + image_w / 2, image_y - y + image_h + 8)),
+        );
+
+        let button_hints_offset = Point::new(
+            w as i32 - 12,
+            h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+        );
+        let button_hints = Row::new(
+            Point::new(x + button_hints_offset.x, y + button_hints_offset.y),
+            vec![
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-confirm"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+        offsets.push(button_hints_offset);
+
+        Self {
+            rect,
+            title,
+            images,
+            captions,
+            button_hints,
+            offsets,
+            dirty: true,
+            drawn: LastDrawn::default(),
+        }
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.title.set_text(title);
+        self.dirty = true;
+    }
+}
+
+#[async_trait(?Send)]
+impl View for ConfirmDialog {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            self.drawn.clear(display)?;
+            display.load(self.rect)?;
+            self.drawn.set(self.rect);
+            for child in self.children_mut() {
+                child.set_should_draw();
+            }
+            self.dirty = false;
+            drawn = true;
+        }
+
+        for child in self.children_mut() {
+            drawn |= child.should_draw() && child.draw(display, styles)?;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty || self.children().iter().any(|c| c.should_draw())
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                bubble.push_back(Command::ValueChanged(0, Value::Bool(true)));
+                bubble.push_back(Command::CloseView);
+                commands.send(Command::Redraw).await?;
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                commands.send(Command::Redraw).await?;
+            }
+            _ => {}
+        }
+        // The dialog is modal
+        Ok(true)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        let mut children: Vec<&dyn View> = vec![&self.title];
+        children.extend(self.images.iter().map(|v| v as &dyn View));
+        children.extend(self.captions.iter().map(|v| v as &dyn View));
+        children.push(&self.button_hints);
+        children
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        let mut children: Vec<&mut dyn View> = vec![&mut self.title];
+        children.extend(self.images.iter_mut().map(|v| v as &mut dyn View));
+        children.extend(self.captions.iter_mut().map(|v| v as &mut dyn View));
+        children.push(&mut self.button_hints);
+        children
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.rect.top_left(), point);
+        self.rect.x = point.x;
+        self.rect.y = point.y;
+        let offsets = self.offsets.clone();
+        for (child, offset) in self.children_mut().into_iter().zip(offsets) {
+            child.set_position(Point::new(point.x + offset.x, point.y + offset.y));
+        }
+        self.dirty = true;
+    }
+}
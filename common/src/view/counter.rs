@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use embedded_graphics::prelude::Dimensions;
+use embedded_graphics::text::Text;
+use embedded_graphics::Drawable;
+use tokio::sync::mpsc::Sender;
+
+use crate::command::Command;
+use crate::display::color::Color;
+use crate::display::font::FontTextStyleBuilder;
+use crate::display::Display;
+use crate::geom::{Alignment, Point, Rect};
+use crate::platform::{DefaultPlatform, KeyEvent, Platform};
+use crate::stylesheet::{Styles, StylesheetColor};
+use crate::view::View;
+
+/// Short text right-aligned against a point, such as how far down a list the selection is. Its
+/// width changes with the text, so only the area the last text covered is restored before the next
+/// is drawn, leaving everything around it alone.
+#[derive(Debug, Clone)]
+pub struct Counter {
+    point: Point,
+    text: String,
+    /// Area covered by the last draw.
+    drawn: Option<Rect>,
+    dirty: bool,
+}
+
+impl Counter {
+    pub fn new(point: Point) -> Self {
+        Self {
+            point,
+            text: String::new(),
+            drawn: None,
+            dirty: true,
+        }
+    }
+
+    pub fn set_text(&mut self, text: String) -> &mut Self {
+        if text != self.text {
+            self.text = text;
+            self.dirty = true;
+        }
+        self
+    }
+
+    /// Makes `other` responsible for clearing what this counter last drew, such as when another
+    /// list takes over the same spot.
+    pub fn hand_over(&mut self, other: &mut Counter) {
+        if let Some(drawn) = self.drawn.take() {
+            other.drawn = Some(match other.drawn {
+                Some(rect) => rect.union(&drawn),
+                None => drawn,
+            });
+            other.dirty = true;
+        }
+    }
+
+    /// Draws the text, returning the area it covers.
+    fn draw_counter<D: Display>(&mut self, display: &mut D, styles: &Styles) -> Result<Rect> {
+        if let Some(rect) = self.drawn.take() {
+            display.load(rect)?;
+        }
+
+        let text_style = FontTextStyleBuilder::<Color>::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
+            .text_color(StylesheetColor::Disabled.to_color(styles))
+            .background_color(StylesheetColor::Background.to_color(styles))
+            .font_size(styles.ui_font.size)
+            .build();
+        let text = Text::with_alignment(
+            &self.text,
+            self.point.into(),
+            text_style,
+            Alignment::Right.into(),
+        );
+        text.draw(display)?;
+
+        let rect = text.bounding_box().into();
+        self.drawn = Some(rect);
+        self.dirty = false;
+        Ok(rect)
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Counter {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        self.draw_counter(display, styles)?;
+        Ok(true)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        _event: KeyEvent,
+        _command: Sender<Command>,
+        _bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        Vec::new()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        Vec::new()
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.drawn
+            .unwrap_or_else(|| Rect::new(self.point.x, self.point.y, 0, 0))
+    }
+
+    fn set_position(&mut self, point: Point) {
+        if point != self.point {
+            self.point = point;
+            self.dirty = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::golden::{assert_golden, styles, Framebuffer};
+
+    const WIDTH: u32 = 320;
+
+    fn framebuffer(styles: &Styles) -> Framebuffer {
+        let mut display = Framebuffer::new(WIDTH, 40, styles.background_color);
+        display.save().unwrap();
+        display
+    }
+
+    /// Draws each text in turn, returning the display and the right edge of the last text.
+    fn draw(texts: &[&str]) -> (Framebuffer, i32) {
+        let styles = styles();
+        let mut display = framebuffer(&styles);
+        let mut counter = Counter::new(Point::new(WIDTH as i32 - 8, 8));
+        let mut right = 0;
+        for text in texts {
+            counter.set_text(text.to_string());
+            let rect = counter.draw_counter(&mut display, &styles).unwrap();
+            right = rect.x + rect.w as i32;
+        }
+        (display, right)
+    }
+
+    #[test]
+    fn test_alignment() {
+        let mut rights = Vec::new();
+        for (name, text) in [
+            ("short", "1 / 9"),
+            ("medium", "37 / 214"),
+            ("long", "1024 / 10240"),
+            ("filtered", "12 of 214 shown"),
+        ] {
+            let (display, right) = draw(&[text]);
+            assert_golden(&format!("counter_{name}"), &display);
+            rights.push(right);
+        }
+        assert!(rights.iter().all(|&right| right == rights[0]), "{rights:?}");
+    }
+
+    #[test]
+    fn test_narrower_text_leaves_nothing_behind() {
+        let (expected, _) = draw(&["9 / 214"]);
+        let (display, _) = draw(&["12 of 214 shown", "214 / 214", "9 / 214"]);
+        assert_eq!(display.capture().unwrap(), expected.capture().unwrap());
+    }
+
+    #[test]
+    fn test_hand_over() {
+        let styles = styles();
+        let mut display = framebuffer(&styles);
+        let point = Point::new(WIDTH as i32 - 8, 8);
+
+        let mut parent = Counter::new(point);
+        parent.set_text("12 of 214 shown".to_string());
+        parent.draw_counter(&mut display, &styles).unwrap();
+
+        let mut child = Counter::new(point);
+        child.set_text("1 / 3".to_string());
+        parent.hand_over(&mut child);
+        child.draw_counter(&mut display, &styles).unwrap();
+
+        let (expected, _) = draw(&["1 / 3"]);
+        assert_eq!(display.capture().unwrap(), expected.capture().unwrap());
+        assert!(parent.drawn.is_none());
+    }
+}
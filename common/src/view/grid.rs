@@ -0,0 +1,426 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use embedded_graphics::prelude::Size;
+use embedded_graphics::primitives::{Primitive, PrimitiveStyle, Rectangle, RoundedRectangle};
+use embedded_graphics::Drawable;
+use tokio::sync::mpsc::Sender;
+
+use crate::command::Command;
+use crate::display::Display;
+use crate::geom::{Alignment, Point, Rect};
+use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use crate::stylesheet::{StyleConfig, Styles, StylesheetColor};
+use crate::view::{ArtPlaceholder, Image, ImageMode, Label, LastDrawn, View};
+
+/// Space between cells.
+const GAP: u32 = 8;
+/// Space between the edge of a cell and its contents.
+const PADDING: u32 = 6;
+
+/// Items laid out in a grid of cells, each showing the item's image with its name below, or a
+/// placeholder tile with the name if it has no image. Scrolls by whole rows to keep the selected
+/// cell in view, whose name scrolls if it's too long to read. Images are only asked for by the visible cells, and are decoded in the
+/// background, scaled down to the cell, through the shared image cache.
+#[derive(Debug)]
+pub struct Grid {
+    rect: Rect,
+    columns: usize,
+    rows: usize,
+    names: Vec<String>,
+    images: Vec<Option<PathBuf>>,
+    selected: usize,
+    /// First visible row.
+    top: usize,
+    cells: Vec<Cell>,
+    drawn: LastDrawn,
+    dirty: bool,
+}
+
+#[derive(Debug)]
+struct Cell {
+    rect: Rect,
+    /// Where the image goes, above the name.
+    art: Rect,
+    image: Image,
+    placeholder: ArtPlaceholder,
+    label: Label<String>,
+}
+
+impl Grid {
+    pub fn new(rect: Rect, columns: usize, rows: usize, styles: &StyleConfig) -> Self {
+        let columns = columns.max(1);
+        let rows = rows.max(1);
+        let cell_w = rect.w.saturating_sub(GAP * (columns as u32 - 1)) / columns as u32;
+        let cell_h = rect.h.saturating_sub(GAP * (rows as u32 - 1)) / rows as u32;
+        let label_h = styles.ui_font.size;
+
+        let cells = (0..columns * rows)
+            .map(|i| {
+                let cell = Rect::new(
+                    rect.x + ((i % columns) as u32 * (cell_w + GAP)) as i32,
+                    rect.y + ((i / columns) as u32 * (cell_h + GAP)) as i32,
+                    cell_w,
+                    cell_h,
+                );
+                let art = Rect::new(
+                    cell.x + PADDING as i32,
+                    cell.y + PADDING as i32,
+                    cell_w.saturating_sub(2 * PADDING),
+                    cell_h.saturating_sub(3 * PADDING + label_h),
+                );
+                let mut image = Image::empty(art, ImageMode::Contain);
+                image.decode_in_background();
+                Cell {
+                    rect: cell,
+                    art,
+                    image,
+                    placeholder: ArtPlaceholder::new(art),
+                    label: Label::new(
+                        Point::new(
+                            cell.x + cell_w as i32 / 2,
+                            art.y + art.h as i32 + PADDING as i32,
+                        ),
+                        String::new(),
+                        Alignment::Center,
+                        Some(art.w),
+                    ),
+                }
+            })
+            .collect();
+
+        Self {
+            rect,
+            columns,
+            rows,
+            names: Vec::new(),
+            images: Vec::new(),
+            selected: 0,
+            top: 0,
+            cells,
+            drawn: LastDrawn::default(),
+            dirty: true,
+        }
+    }
+
+    /// Replaces the items, which start out without images. The selection is kept where it was if
+    /// `preserve_selection` is set, as far as the new items reach.
+    pub fn set_items(&mut self, names: Vec<String>, preserve_selection: bool) {
+        self.images = vec![None; names.len()];
+        self.names = names;
+        if !preserve_selection {
+            self.selected = 0;
+            self.top = 0;
+        }
+        self.selected = self.selected.min(self.names.len().saturating_sub(1));
+        self.scroll_to_selected();
+        self.refresh_cells();
+    }
+
+    /// Items in the visible rows, which are the ones that need their images.
+    pub fn visible(&self) -> Range<usize> {
+        let start = (self.top * self.columns).min(self.names.len());
+        let end = ((self.top + self.rows) * self.columns).min(self.names.len());
+        start..end
+    }
+
+    /// Sets the image of the item at `index`, or shows it as a placeholder if there is none.
+    pub fn set_image(&mut self, index: usize, image: Option<PathBuf>) {
+        if self.images.get(index).is_none_or(|i| *i == image) {
+            return;
+        }
+        self.images[index] = image;
+        if self.visible().contains(&index) {
+            self.refresh_cells();
+        }
+    }
+
+    /// Number of cells, which is how far a page goes.
+    pub fn page_size(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Selects the item at `index`, scrolling to its row if it isn't visible.
+    pub fn select(&mut self, index: usize) {
+        let index = index.min(self.names.len().saturating_sub(1));
+        if index == self.selected {
+            return;
+        }
+        self.selected = index;
+        let top = self.top;
+        self.scroll_to_selected();
+        if self.top != top {
+            self.refresh_cells();
+        } else {
+            self.scroll_selected_name();
+        }
+        self.dirty = true;
+    }
+
+    /// Scrolls the name of the selected cell if it's too long to read, starting it over.
+    fn scroll_selected_name(&mut self) {
+        let selected = self.selected.checked_sub(self.visible().start);
+        for (i, cell) in self.cells.iter_mut().enumerate() {
+            cell.label.scroll(Some(i) == selected);
+        }
+    }
+
+    /// The selected cell, if any is visible.
+    fn selected_cell(&mut self) -> Option<&mut Cell> {
+        let i = self.selected.checked_sub(self.visible().start)?;
+        self.cells.get_mut(i)
+    }
+
+    fn scroll_to_selected(&mut self) {
+        let row = self.selected / self.columns;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + self.rows {
+            self.top = row + 1 - self.rows;
+        }
+    }
+
+    /// Shows the visible items in the cells.
+    fn refresh_cells(&mut self) {
+        let visible = self.visible();
+        for (i, cell) in self.cells.iter_mut().enumerate() {
+            let index = visible.start + i;
+            if index < visible.end {
+                let name = &self.names[index];
+                cell.image.set_path(self.images[index].clone());
+                cell.placeholder.set_name(name);
+                cell.label.set_text(name.clone());
+            } else {
+                cell.image.set_path(None);
+            }
+        }
+        self.scroll_selected_name();
+        self.dirty = true;
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Grid {
+    fn update(&mut self, dt: Duration) {
+        if let Some(cell) = self.selected_cell() {
+            cell.label.update(dt);
+        }
+    }
+
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let visible = self.visible();
+        if !self.dirty {
+            // Only images that finished decoding since the last draw
+            let mut drawn = false;
+            for (cell, index) in self.cells.iter_mut().zip(visible) {
+                if self.images[index].is_some() && cell.image.should_draw() {
+                    drawn |= cell.image.draw(display, styles)?;
+                }
+            }
+            // and the name scrolling in the selected cell, over the highlight
+            if let Some(cell) = self.selected_cell().filter(|c| c.label.should_draw()) {
+                let label = cell.label.bounding_box(styles);
+                Rectangle::new(
+                    Point::new(cell.art.x, label.y).into(),
+                    Size::new(cell.art.w, label.h),
+                )
+                .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+                .draw(display)?;
+                drawn |= cell.label.draw(display, styles)?;
+            }
+            return Ok(drawn);
+        }
+
+        self.drawn.clear(display)?;
+        display.load(self.rect)?;
+        self.drawn.set(self.rect);
+        for (cell, index) in self.cells.iter_mut().zip(visible) {
+            let background = if index == self.selected {
+                RoundedRectangle::with_equal_corners(cell.rect.into(), Size::new_equal(12))
+                    .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+                    .draw(display)?;
+                StylesheetColor::Highlight
+            } else {
+                StylesheetColor::Background
+            };
+
+            if self.images[index].is_some() {
+                cell.image.set_should_draw();
+                cell.image.draw(display, styles)?;
+            } else {
+                cell.placeholder.set_should_draw();
+                cell.placeholder.draw(display, styles)?;
+            }
+            cell.label.background_color(background);
+            cell.label.set_should_draw();
+            cell.label.draw(display, styles)?;
+        }
+        self.dirty = false;
+        Ok(true)
+    }
+
+    fn should_draw(&self) -> bool {
+        let selected = self.selected.checked_sub(self.visible().start);
+        self.dirty
+            || selected
+                .and_then(|i| self.cells.get(i))
+                .is_some_and(|c| c.label.should_draw())
+            || self
+                .cells
+                .iter()
+                .zip(self.visible())
+                .any(|(cell, index)| self.images[index].is_some() && cell.image.should_draw())
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        _commands: Sender<Command>,
+        _bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self.names.is_empty() {
+            return Ok(false);
+        }
+        let column = self.selected % self.columns;
+        let last = self.names.len() - 1;
+        match event {
+            KeyEvent::Pressed(Key::Up) | KeyEvent::Autorepeat(Key::Up) => {
+                if self.selected >= self.columns {
+                    self.select(self.selected - self.columns);
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::Down) | KeyEvent::Autorepeat(Key::Down) => {
+                // Onto the last item if the row below is shorter
+                if self.selected / self.columns < last / self.columns {
+                    self.select((self.selected + self.columns).min(last));
+                }
+                Ok(true)
+            }
+            // Moving off the edge of a row is left to the parent, e.g. to switch tabs
+            KeyEvent::Pressed(Key::Left) | KeyEvent::Autorepeat(Key::Left) if column > 0 => {
+                self.select(self.selected - 1);
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::Right) | KeyEvent::Autorepeat(Key::Right)
+                if column + 1 < self.columns && self.selected < last =>
+            {
+                self.select(self.selected + 1);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        Vec::new()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        Vec::new()
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.rect.top_left(), point);
+        let (dx, dy) = (point.x - self.rect.x, point.y - self.rect.y);
+        self.rect.x = point.x;
+        self.rect.y = point.y;
+        for cell in &mut self.cells {
+            cell.rect.x += dx;
+            cell.rect.y += dy;
+            cell.art.x += dx;
+            cell.art.y += dy;
+            cell.image.set_position(cell.art.top_left());
+            cell.placeholder.set_position(cell.art.top_left());
+            cell.label.set_position(Point::new(
+                cell.rect.x + cell.rect.w as i32 / 2,
+                cell.art.y + cell.art.h as i32 + PADDING as i32,
+            ));
+        }
+        self.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn grid(len: usize) -> Grid {
+        let mut grid = Grid::new(Rect::new(0, 0, 600, 400), 3, 2, &StyleConfig::default());
+        grid.set_items((0..len).map(|i| i.to_string()).collect(), false);
+        grid
+    }
+
+    async fn press(grid: &mut Grid, key: Key) -> bool {
+        let (tx, _rx) = mpsc::channel(1);
+        grid.handle_key_event(KeyEvent::Pressed(key), tx, &mut VecDeque::new())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_navigation() {
+        let mut grid = grid(8);
+
+        // Off the left and right edges is left to the parent
+        assert!(!press(&mut grid, Key::Left).await);
+        assert!(press(&mut grid, Key::Right).await);
+        assert!(press(&mut grid, Key::Right).await);
+        assert_eq!(grid.selected(), 2);
+        assert!(!press(&mut grid, Key::Right).await);
+
+        press(&mut grid, Key::Down).await;
+        assert_eq!(grid.selected(), 5);
+        // The last row only has two items
+        press(&mut grid, Key::Down).await;
+        assert_eq!(grid.selected(), 7);
+        press(&mut grid, Key::Down).await;
+        assert_eq!(grid.selected(), 7);
+        assert!(!press(&mut grid, Key::Right).await);
+
+        press(&mut grid, Key::Up).await;
+        assert_eq!(grid.selected(), 4);
+    }
+
+    #[test]
+    fn test_scrolls_by_rows() {
+        let mut grid = grid(20);
+        assert_eq!(grid.visible(), 0..6);
+
+        grid.select(5);
+        assert_eq!(grid.visible(), 0..6);
+        grid.select(7);
+        assert_eq!(grid.visible(), 3..9);
+        grid.select(19);
+        assert_eq!(grid.visible(), 15..20);
+        grid.select(4);
+        assert_eq!(grid.visible(), 3..9);
+
+        // Fewer items keep the selection within them
+        grid.set_items(vec!["a".to_string(), "b".to_string()], true);
+        assert_eq!(grid.selected(), 1);
+        assert_eq!(grid.visible(), 0..2);
+    }
+}
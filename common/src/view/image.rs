@@ -1,25 +1,31 @@
 use std::collections::VecDeque;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::SystemTime;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use embedded_graphics::image::ImageRaw;
 use embedded_graphics::Drawable;
 use image::{GenericImageView, RgbaImage};
+use lazy_static::lazy_static;
 use log::{error, trace};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
+use tokio::sync::Notify;
 
 use crate::command::Command;
+use crate::display::cache::{CacheKind, ImageCache};
 use crate::display::color::Color;
-use crate::display::image::round;
+use crate::display::image::{contain, fit_within, round, DISK_IMAGE_CACHE};
 use crate::display::Display;
-use crate::geom::{Point, Rect};
+use crate::geom::{Point, Rect, Size};
 use crate::platform::{DefaultPlatform, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
+use crate::stylesheet::Styles;
 use crate::view::View;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ImageMode {
     /// Don't scale the image
     Raw,
@@ -29,12 +35,106 @@ pub enum ImageMode {
     Contain,
 }
 
+lazy_static! {
+    /// Images decoded by image views, shared so that going back to an image doesn't decode it
+    /// again.
+    pub static ref IMAGE_CACHE: Mutex<ImageCache<ImageKey>> = Mutex::new(ImageCache::from_env());
+
+    /// Notified when an image decoded in the background is ready to be drawn, so that the event
+    /// loop can wake up to draw it.
+    pub static ref IMAGE_DECODED: Notify = Notify::new();
+}
+
+/// Identifies an image as it is drawn: the same file scaled differently is a different image, as is
+/// the file once it changes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageKey {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    size: (u32, u32),
+    mode: ImageMode,
+    border_radius: u32,
+}
+
+impl ImageKey {
+    fn kind(&self) -> CacheKind {
+        match self.mode {
+            ImageMode::Raw => CacheKind::Images,
+            ImageMode::Cover | ImageMode::Contain => CacheKind::Thumbnails,
+        }
+    }
+
+    /// Decodes the image, giving up if it is `cancelled` by the time it would be decoded.
+    fn decode(&self, cancelled: impl Fn() -> bool) -> Option<RgbaImage> {
+        let rect = Rect::new(0, 0, self.size.0, self.size.1);
+        let decode = || {
+            if cancelled() {
+                return None;
+            }
+            image(&self.path, rect, self.mode, self.border_radius)
+        };
+        match self.mode {
+            ImageMode::Raw => decode(),
+            // Scaling is what's slow, so scaled images are kept on disk too
+            ImageMode::Cover | ImageMode::Contain => scaled(self, decode),
+        }
+    }
+}
+
+/// An image being decoded on a blocking task.
+#[derive(Debug, Clone)]
+struct Decoding {
+    /// Set once the image is decoded. The task only holds on to it weakly, so dropping it cancels
+    /// the decode if the task hasn't got to it yet.
+    result: Arc<OnceLock<Option<Arc<RgbaImage>>>>,
+}
+
+impl Decoding {
+    fn spawn(key: ImageKey) -> Self {
+        let result = Arc::new(OnceLock::new());
+        let weak = Arc::downgrade(&result);
+        tokio::task::spawn_blocking(move || {
+            let cancelled = || weak.strong_count() == 0;
+            let image = key.decode(cancelled).map(|image| {
+                IMAGE_CACHE
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get_or_insert_with(key.clone(), key.kind(), || Some(image))
+            });
+            // Whatever was scrolled past is dropped rather than drawn
+            if let Some(result) = weak.upgrade() {
+                let _ = result.set(image.flatten());
+                IMAGE_DECODED.notify_one();
+            } else {
+                trace!("discarding decoded image: {}", key.path.display());
+            }
+        });
+        Self { result }
+    }
+
+    fn is_done(&self) -> bool {
+        self.result.get().is_some()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Image {
     rect: Rect,
     path: Option<PathBuf>,
+    /// The decoded image. Holding it keeps the cache from evicting it while it's drawn.
+    #[serde(skip)]
+    image: Option<Arc<RgbaImage>>,
+    /// Dimensions of the source image, read without decoding it.
+    #[serde(skip)]
+    source_size: Option<(u32, u32)>,
+    /// Area covered by the last draw.
+    #[serde(skip)]
+    drawn: Option<Rect>,
+    /// Whether images that aren't cached are decoded on a blocking task rather than while drawing.
     #[serde(skip)]
-    image: Option<RgbaImage>,
+    background: bool,
+    #[serde(skip)]
+    decoding: Option<Decoding>,
     mode: ImageMode,
     border_radius: u32,
     dirty: bool,
@@ -46,6 +146,10 @@ impl Image {
             rect,
             path: Some(path),
             image: None,
+            source_size: None,
+            drawn: None,
+            background: false,
+            decoding: None,
             mode,
             border_radius: 0,
             dirty: true,
@@ -63,49 +167,162 @@ impl Image {
             rect,
             path: None,
             image: None,
+            source_size: None,
+            drawn: None,
+            background: false,
+            decoding: None,
             mode,
             border_radius: 0,
             dirty: true,
         }
     }
 
+    /// Decodes images on a blocking task, so that drawing doesn't wait for them. Until an image is
+    /// decoded, its area is left cleared. Needs a tokio runtime.
+    pub fn decode_in_background(&mut self) -> &mut Self {
+        self.background = true;
+        self
+    }
+
     pub fn set_path(&mut self, path: Option<PathBuf>) -> &mut Self {
         if path != self.path {
             self.image = None;
+            self.decoding = None;
+            self.source_size = None;
             self.dirty = true;
             self.path = path;
         }
         self
     }
-}
 
-#[async_trait(?Send)]
-impl View for Image {
-    fn draw(
-        &mut self,
-        display: &mut <DefaultPlatform as Platform>::Display,
-        _styles: &Stylesheet,
-    ) -> Result<bool> {
+    pub fn mode(&self) -> ImageMode {
+        self.mode
+    }
+
+    /// Changes how the image is scaled. The image is scaled again on the next draw.
+    pub fn set_mode(&mut self, mode: ImageMode) -> &mut Self {
+        if mode != self.mode {
+            self.mode = mode;
+            self.image = None;
+            self.decoding = None;
+            self.dirty = true;
+        }
+        self
+    }
+
+    /// Area the scaled image covers. This is known before the image is decoded, so that layout
+    /// doesn't change once it is. Falls back to the whole rect if the image can't be read.
+    fn image_rect(&mut self) -> Rect {
+        let size = match self.image {
+            Some(ref image) => Some(image.dimensions()),
+            None => {
+                if self.source_size.is_none() {
+                    if let Some(ref path) = self.path {
+                        self.source_size = crate::display::image::dimensions(path).ok();
+                    }
+                }
+                self.source_size
+                    .map(|size| scaled_size(size, self.rect, self.mode))
+            }
+        };
+        match size {
+            // Images narrower than the rect are centered in it, with the background either side
+            Some((w, h)) if self.mode == ImageMode::Contain => Rect::new(
+                self.rect.x + (self.rect.w.saturating_sub(w) / 2) as i32,
+                self.rect.y,
+                w,
+                h,
+            ),
+            Some((w, h)) => Rect::new(self.rect.x, self.rect.y, w, h),
+            None => self.rect,
+        }
+    }
+
+    fn key(&self) -> Option<ImageKey> {
+        let path = self.path.as_ref()?;
+        Some(ImageKey {
+            path: path.clone(),
+            modified: modified(path),
+            size: (self.rect.w, self.rect.h),
+            mode: self.mode,
+            border_radius: self.border_radius,
+        })
+    }
+
+    /// The decoded image, from `cache` if it was decoded before.
+    fn decode(&self, cache: &mut ImageCache<ImageKey>) -> Option<Arc<RgbaImage>> {
+        let key = self.key()?;
+        cache.get_or_insert_with(key.clone(), key.kind(), || key.decode(|| false))
+    }
+
+    /// Takes the image once it's decoded in the background, starting to decode it if it isn't
+    /// cached.
+    fn poll_decode(&mut self) {
+        if let Some(decoding) = self.decoding.as_ref() {
+            if let Some(image) = decoding.result.get() {
+                self.image = image.clone();
+                self.decoding = None;
+            }
+            return;
+        }
+        let Some(key) = self.key() else {
+            return;
+        };
+        let cached = IMAGE_CACHE
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key);
+        match cached {
+            Some(image) => self.image = Some(image),
+            None => self.decoding = Some(Decoding::spawn(key)),
+        }
+    }
+
+    fn draw_image<D: Display>(&mut self, display: &mut D) -> Result<()> {
         if self.image.is_none() {
-            if let Some(ref path) = self.path {
-                self.image = image(path, self.rect, self.mode, self.border_radius);
+            if self.background {
+                self.poll_decode();
+            } else {
+                let mut cache = IMAGE_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+                self.image = self.decode(&mut cache);
             }
         }
 
-        display.load(self.rect)?;
+        // The previous image may have covered more, e.g. before switching from Cover to Contain
+        let rect = self.image_rect();
+        if let Some(drawn) = self.drawn.take() {
+            if drawn != rect {
+                display.load(drawn)?;
+            }
+        }
+        display.load(rect)?;
+
         if let Some(ref image) = self.image {
-            let image: ImageRaw<'_, Color> = ImageRaw::new(image, self.rect.w);
-            let image = embedded_graphics::image::Image::new(&image, self.rect.top_left().into());
-            trace!("drawing image: {:?}", self.rect);
+            let raw: ImageRaw<'_, Color> = ImageRaw::new(image.as_raw(), image.width());
+            let image = embedded_graphics::image::Image::new(&raw, rect.top_left().into());
+            trace!("drawing image: {:?}", rect);
             image.draw(display)?;
         }
+        self.drawn = Some(rect);
+
+        Ok(())
+    }
+}
 
+#[async_trait(?Send)]
+impl View for Image {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        _styles: &Styles,
+    ) -> Result<bool> {
+        self.draw_image(display)?;
         self.dirty = false;
         Ok(true)
     }
 
     fn should_draw(&self) -> bool {
-        self.dirty
+        self.dirty || self.decoding.as_ref().is_some_and(Decoding::is_done)
     }
 
     fn set_should_draw(&mut self) {
@@ -129,8 +346,13 @@ impl View for Image {
         Vec::new()
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
-        self.rect
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.image_rect()
+    }
+
+    /// The whole target rect, so that the layout doesn't depend on which image is shown.
+    fn size_hint(&mut self, _styles: &Styles) -> Size {
+        self.rect.size()
     }
 
     fn set_position(&mut self, point: Point) {
@@ -140,23 +362,303 @@ impl View for Image {
     }
 }
 
+/// The image at `path` scaled down to fit within `size`, through the cache that image views
+/// share.
+pub fn thumbnail(path: &Path, size: Size) -> Option<Arc<RgbaImage>> {
+    let key = ImageKey {
+        path: path.to_path_buf(),
+        modified: modified(path),
+        size: (size.w, size.h),
+        mode: ImageMode::Contain,
+        border_radius: 0,
+    };
+    let mut cache = IMAGE_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    cache.get_or_insert_with(key.clone(), key.kind(), || key.decode(|| false))
+}
+
+/// When the file at `path` was last modified, if it can be told.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The scaled image for `key` from the disk cache, scaling it with `scale` if it isn't there.
+fn scaled(key: &ImageKey, scale: impl FnOnce() -> Option<RgbaImage>) -> Option<RgbaImage> {
+    DISK_IMAGE_CACHE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_insert_with(key, scale)
+}
+
 fn image(path: &Path, rect: Rect, mode: ImageMode, border_radius: u32) -> Option<RgbaImage> {
-    let mut image = ::image::open(path)
+    let decoded = match mode {
+        ImageMode::Raw => crate::display::image::open(path).map(|image| {
+            let (w, h) = scaled_size(image.dimensions(), rect, mode);
+            image.crop_imm(0, 0, w, h).to_rgba8()
+        }),
+        ImageMode::Cover => crate::display::image::open(path).map(|image| {
+            image
+                .resize_to_fill(rect.w, rect.h, image::imageops::FilterType::Nearest)
+                .to_rgba8()
+        }),
+        ImageMode::Contain => contain(path, (rect.w, rect.h)),
+    };
+    let mut image = decoded
         .map_err(|e| error!("Failed to load image at {}: {}", path.display(), e))
         .ok()?;
+    if border_radius != 0 {
+        round(&mut image, border_radius);
+    }
+    Some(image)
+}
+
+/// Size of an image of `size` once it is scaled into `rect`.
+fn scaled_size((w, h): (u32, u32), rect: Rect, mode: ImageMode) -> (u32, u32) {
     match mode {
-        ImageMode::Raw => {}
-        ImageMode::Cover => {
-            image = image.resize_to_fill(rect.w, rect.h, image::imageops::FilterType::Nearest);
+        // Anything outside of the rect is cropped
+        ImageMode::Raw => (w.min(rect.w), h.min(rect.h)),
+        ImageMode::Cover => (rect.w, rect.h),
+        ImageMode::Contain => fit_within((w, h), (rect.w, rect.h)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use image::Rgba;
+
+    use super::*;
+    use crate::display::golden::{assert_golden, styles, Framebuffer};
+    use crate::test_utils::temp_dir;
+
+    const MODES: [ImageMode; 3] = [ImageMode::Raw, ImageMode::Cover, ImageMode::Contain];
+
+    /// Writes a 40x20 image with a differently coloured quadrant in each corner.
+    fn source(name: &str) -> PathBuf {
+        let image = RgbaImage::from_fn(40, 20, |x, y| match (x < 20, y < 10) {
+            (true, true) => Rgba([220, 40, 40, 255]),
+            (false, true) => Rgba([40, 220, 40, 255]),
+            (true, false) => Rgba([40, 40, 220, 255]),
+            (false, false) => Rgba([220, 220, 40, 255]),
+        });
+        let path =
+            std::env::temp_dir().join(format!("allium-image-{}-{}.png", name, std::process::id()));
+        image.save(&path).unwrap();
+        path
+    }
+
+    fn framebuffer() -> Framebuffer {
+        let mut display = Framebuffer::new(40, 40, Color::new(60, 60, 60));
+        display.save().unwrap();
+        display
+    }
+
+    #[test]
+    fn test_bounding_box_before_decode() {
+        let path = source("bounding-box");
+        let rect = Rect::new(5, 5, 30, 30);
+        let styles = styles();
+
+        let mut image = Image::new(rect, path.clone(), ImageMode::Contain);
+        assert_eq!(image.bounding_box(&styles), Rect::new(5, 5, 30, 15));
+        image.set_mode(ImageMode::Cover);
+        assert_eq!(image.bounding_box(&styles), Rect::new(5, 5, 30, 30));
+        image.set_mode(ImageMode::Raw);
+        assert_eq!(image.bounding_box(&styles), Rect::new(5, 5, 30, 20));
+
+        // The bounding box doesn't change once the image is decoded
+        image.draw_image(&mut framebuffer()).unwrap();
+        assert_eq!(image.bounding_box(&styles), Rect::new(5, 5, 30, 20));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_tall_image_is_letterboxed() {
+        let path =
+            std::env::temp_dir().join(format!("allium-image-tall-{}.png", std::process::id()));
+        RgbaImage::from_pixel(10, 40, Rgba([220, 40, 40, 255]))
+            .save(&path)
+            .unwrap();
+        let mut display = framebuffer();
+        let mut image = Image::new(Rect::new(5, 5, 30, 30), path.clone(), ImageMode::Contain);
+
+        // Fits within the rect, centered across it
+        assert_eq!(image.bounding_box(&styles()), Rect::new(16, 5, 7, 30));
+        image.draw_image(&mut display).unwrap();
+        let frame = display.capture().unwrap();
+        assert_eq!(frame.get_pixel(19, 20).0, [220, 40, 40]);
+        assert_eq!(frame.get_pixel(10, 20).0, [60, 60, 60]);
+        assert_eq!(frame.get_pixel(19, 36).0, [60, 60, 60]);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_set_mode() {
+        let path = source("set-mode");
+        for from in MODES {
+            for to in MODES {
+                if from == to {
+                    continue;
+                }
+                let mut display = framebuffer();
+                let mut image = Image::new(Rect::new(5, 5, 30, 30), path.clone(), from);
+                image.draw_image(&mut display).unwrap();
+
+                image.set_mode(to);
+                assert!(image.should_draw());
+                image.draw_image(&mut display).unwrap();
+                assert_golden(
+                    &format!("image_{:?}_to_{:?}", from, to).to_lowercase(),
+                    &display,
+                );
+            }
         }
-        ImageMode::Contain => {
-            let new_height = rect.h.min(rect.w * image.height() / image.width());
-            image = image.resize_to_fill(rect.w, new_height, image::imageops::FilterType::Nearest);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_changed_source_is_decoded_again() {
+        let path = source("changed");
+        let rect = Rect::new(0, 0, 40, 20);
+        let mut cache = ImageCache::new(1024 * 1024);
+
+        let image = Image::new(rect, path.clone(), ImageMode::Cover);
+        let before = image.decode(&mut cache).unwrap();
+        assert_eq!(before.get_pixel(0, 0).0, [220, 40, 40, 255]);
+
+        RgbaImage::from_pixel(40, 20, Rgba([10, 20, 30, 255]))
+            .save(&path)
+            .unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        let after = image.decode(&mut cache).unwrap();
+        assert_eq!(after.get_pixel(0, 0).0, [10, 20, 30, 255]);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    /// Draws `image` once it's decoded in the background.
+    async fn draw_decoded(image: &mut Image, display: &mut Framebuffer) {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !image.should_draw() {
+            assert!(std::time::Instant::now() < deadline, "image wasn't decoded");
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
         }
+        image.draw_image(display).unwrap();
     }
-    let mut image = image.to_rgba8();
-    if border_radius != 0 {
-        round(&mut image, border_radius);
+
+    #[tokio::test]
+    async fn test_decode_in_background() {
+        let path = source("background");
+        let mut display = framebuffer();
+        let mut image = Image::new(Rect::new(0, 0, 40, 20), path.clone(), ImageMode::Cover);
+        image.decode_in_background();
+
+        // The area is cleared without waiting for the image
+        image.draw_image(&mut display).unwrap();
+        image.dirty = false;
+        assert!(image.image.is_none());
+        assert_eq!(display.capture().unwrap().get_pixel(0, 0).0, [60, 60, 60]);
+
+        draw_decoded(&mut image, &mut display).await;
+        assert_eq!(display.capture().unwrap().get_pixel(0, 0).0, [220, 40, 40]);
+
+        // Decoded images are cached, so drawing it again doesn't wait
+        let mut again = Image::new(Rect::new(0, 0, 40, 20), path.clone(), ImageMode::Cover);
+        again.decode_in_background();
+        again.draw_image(&mut framebuffer()).unwrap();
+        assert!(again.image.is_some());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stale_decodes_are_discarded() {
+        let paths: Vec<_> = (0..10)
+            .map(|i| {
+                let path = std::env::temp_dir().join(format!(
+                    "allium-image-stale-{}-{}.png",
+                    i,
+                    std::process::id()
+                ));
+                RgbaImage::from_pixel(40, 20, Rgba([i * 20, 0, 0, 255]))
+                    .save(&path)
+                    .unwrap();
+                path
+            })
+            .collect();
+
+        // Scrolling past every image before any is decoded
+        let mut display = framebuffer();
+        let mut image = Image::empty(Rect::new(0, 0, 40, 20), ImageMode::Cover);
+        image.decode_in_background();
+        for path in &paths {
+            image.set_path(Some(path.clone()));
+            image.draw_image(&mut display).unwrap();
+            image.dirty = false;
+        }
+
+        draw_decoded(&mut image, &mut display).await;
+        assert_eq!(display.capture().unwrap().get_pixel(0, 0).0, [180, 0, 0]);
+        assert!(image.decoding.is_none());
+        assert!(!image.should_draw());
+
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_cache_budget() {
+        let dir = temp_dir("image-cache");
+        let color = |i: u32| Rgba([(i % 256) as u8, (i / 256) as u8 * 64, 128, 255]);
+        let paths: Vec<_> = (0..300)
+            .map(|i| {
+                let path = dir.join(format!("{}.png", i));
+                RgbaImage::from_pixel(32, 32, color(i)).save(&path).unwrap();
+                path
+            })
+            .collect();
+
+        let rect = Rect::new(5, 5, 30, 30);
+        let key = |path: &PathBuf| ImageKey {
+            path: path.clone(),
+            modified: modified(path),
+            size: (rect.w, rect.h),
+            mode: ImageMode::Cover,
+            border_radius: 0,
+        };
+
+        // Room for 20 of the 30x30 thumbnails
+        let mut cache = ImageCache::new(20 * (30 * 30 * 4 + 128));
+        let mut display = framebuffer();
+        let mut drawn = Image::new(rect, paths[0].clone(), ImageMode::Cover);
+        drawn.image = drawn.decode(&mut cache);
+        for (i, path) in paths.iter().enumerate().skip(1) {
+            let mut image = Image::new(rect, path.clone(), ImageMode::Cover);
+            image.image = image.decode(&mut cache);
+            image.draw_image(&mut display).unwrap();
+            assert!(cache.used() <= cache.budget(), "over budget at {}", i);
+            // The first image is still held by a view, so it's never evicted
+            assert!(cache.contains(&key(&paths[0])));
+        }
+        assert_eq!(cache.usage(CacheKind::Thumbnails).entries, 20);
+
+        // Evicted images are decoded again
+        assert!(!cache.contains(&key(&paths[1])));
+        let mut image = Image::new(rect, paths[1].clone(), ImageMode::Cover);
+        image.image = image.decode(&mut cache);
+        image.draw_image(&mut display).unwrap();
+        let pixel = display.capture().unwrap().get_pixel(20, 20).0;
+        assert_eq!(pixel, [1, 0, 128]);
+        assert!(cache.used() <= cache.budget());
+
+        fs::remove_dir_all(&dir).unwrap();
     }
-    Some(image)
 }
@@ -6,9 +6,9 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
 use crate::command::Value;
-use crate::geom::{Point, Rect};
+use crate::geom::{Point, Rect, Size};
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
+use crate::stylesheet::Styles;
 use crate::view::{Command, View};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,7 +36,7 @@ where
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         self.view.draw(display, styles)
     }
@@ -72,10 +72,14 @@ where
         vec![&mut self.view]
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
         self.view.bounding_box(styles)
     }
 
+    fn size_hint(&mut self, styles: &Styles) -> Size {
+        self.view.size_hint(styles)
+    }
+
     fn set_position(&mut self, point: Point) {
         self.view.set_position(point)
     }
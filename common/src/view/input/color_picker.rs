@@ -15,8 +15,8 @@ use crate::display::color::Color;
 use crate::display::font::{FontTextStyle, FontTextStyleBuilder};
 use crate::geom::{Alignment, Point, Rect};
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use crate::stylesheet::{Stylesheet, StylesheetColor};
-use crate::view::{Command, View};
+use crate::stylesheet::{Styles, StylesheetColor};
+use crate::view::{Command, LastDrawn, View};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColorPicker {
@@ -27,6 +27,8 @@ pub struct ColorPicker {
     #[serde(skip)]
     edit_state: Option<EditState>,
     background_color: StylesheetColor,
+    #[serde(skip)]
+    drawn: LastDrawn,
 }
 
 #[derive(Debug, Clone)]
@@ -44,6 +46,7 @@ impl ColorPicker {
             dirty: true,
             edit_state: None,
             background_color: StylesheetColor::Background,
+            drawn: LastDrawn::default(),
         }
     }
 
@@ -62,12 +65,13 @@ impl View for ColorPicker {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         if !self.dirty {
             return Ok(false);
         }
         self.dirty = false;
+        self.drawn.clear(display)?;
 
         let color = self
             .edit_state
@@ -95,21 +99,21 @@ impl View for ColorPicker {
         .into_styled(fill_style)
         .draw(display)?;
 
-        let text_style = FontTextStyleBuilder::new(styles.ui_font.font())
-            .font_fallback(styles.cjk_font.font())
+        let text_style = FontTextStyleBuilder::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
             .font_size(styles.ui_font.size)
             .text_color(styles.foreground_color)
             .background_color(self.background_color.to_color(styles))
             .build();
 
-        let focused_style = FontTextStyleBuilder::new(styles.ui_font.font())
+        let focused_style = FontTextStyleBuilder::new(styles.ui_font())
             .font_size(styles.ui_font.size)
             .text_color(styles.foreground_color)
             .background_color(styles.highlight_color)
             .draw_background()
             .build();
 
-        let selected_style = FontTextStyleBuilder::new(styles.ui_font.font())
+        let selected_style = FontTextStyleBuilder::new(styles.ui_font())
             .font_size(styles.ui_font.size)
             .text_color(styles.foreground_color)
             .background_color(styles.highlight_color)
@@ -154,6 +158,9 @@ impl View for ColorPicker {
             Alignment::Left => todo!(),
         }
 
+        let rect = self.bounding_box(styles);
+        self.drawn.set(rect);
+
         Ok(true)
     }
 
@@ -259,13 +266,15 @@ impl View for ColorPicker {
                 }
                 _ => Ok(false),
             }
-        } else {
+        } else if let KeyEvent::Pressed(Key::A) = event {
             self.edit_state = Some(EditState {
                 value: self.value,
                 selected: 0,
             });
             bubble.push_back(Command::TrapFocus);
             Ok(true)
+        } else {
+            Ok(false)
         }
     }
 
@@ -277,14 +286,14 @@ impl View for ColorPicker {
         vec![]
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
-        let text_style: FontTextStyle<Color> = FontTextStyleBuilder::new(styles.ui_font.font())
-            .font_fallback(styles.cjk_font.font())
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
+        let text_style: FontTextStyle<Color> = FontTextStyleBuilder::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
             .font_size(styles.ui_font.size)
             .draw_background()
             .build();
 
-        let mut x = self.point.x - 30 - 12;
+        let mut x = self.point.x - styles.ui_font.size as i32 - 12;
         for i in (0..6).rev() {
             let c = self.value.char(i);
             let text = Text::with_alignment(
@@ -314,6 +323,8 @@ impl View for ColorPicker {
     }
 
     fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.point, point);
         self.point = point;
+        self.dirty = true;
     }
 }
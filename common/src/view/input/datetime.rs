@@ -15,8 +15,8 @@ use crate::display::color::Color;
 use crate::display::font::{FontTextStyle, FontTextStyleBuilder};
 use crate::geom::{Alignment, Point, Rect};
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
-use crate::view::{Command, View};
+use crate::stylesheet::Styles;
+use crate::view::{Command, LastDrawn, View};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DateTime {
@@ -26,6 +26,8 @@ pub struct DateTime {
     dirty: bool,
     #[serde(skip)]
     edit_state: Option<EditState>,
+    #[serde(skip)]
+    drawn: LastDrawn,
 }
 
 #[derive(Debug, Clone)]
@@ -42,6 +44,7 @@ impl DateTime {
             alignment,
             dirty: true,
             edit_state: None,
+            drawn: LastDrawn::default(),
         }
     }
 
@@ -60,7 +63,7 @@ impl View for DateTime {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         if !self.dirty {
             return Ok(false);
@@ -74,34 +77,28 @@ impl View for DateTime {
             .unwrap_or(self.value);
         let edit_index = self.edit_state.as_ref().map(|s| s.selected);
 
-        let text_style = FontTextStyleBuilder::new(styles.ui_font.font())
-            .font_fallback(styles.cjk_font.font())
+        let text_style = FontTextStyleBuilder::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
             .font_size(styles.ui_font.size)
             .text_color(styles.foreground_color)
             .build();
 
-        let focused_style = FontTextStyleBuilder::new(styles.ui_font.font())
+        let focused_style = FontTextStyleBuilder::new(styles.ui_font())
             .font_size(styles.ui_font.size)
             .text_color(styles.foreground_color)
             .draw_background()
             .build();
 
-        let selected_style = FontTextStyleBuilder::new(styles.ui_font.font())
+        let selected_style = FontTextStyleBuilder::new(styles.ui_font())
             .font_size(styles.ui_font.size)
             .text_color(styles.foreground_color)
             .underline()
             .draw_background()
             .build();
 
-        let year = datetime.format("%Y").to_string();
-        let month = datetime.format("%m").to_string();
-        let day = datetime.format("%d").to_string();
-        let hour = datetime.format("%H").to_string();
-        let minute = datetime.format("%M").to_string();
-        let second = datetime.format("%S").to_string();
-        let fields = [
-            &year, "-", &month, "-", &day, " ", &hour, ":", &minute, ":", &second,
-        ];
+        self.drawn.clear(display)?;
+
+        let fields = fields(datetime);
         let mut x = self.point.x;
         match self.alignment {
             Alignment::Right => {
@@ -126,6 +123,9 @@ impl View for DateTime {
             Alignment::Left => todo!(),
         }
 
+        let rect = self.bounding_box(styles);
+        self.drawn.set(rect);
+
         Ok(true)
     }
 
@@ -253,13 +253,15 @@ impl View for DateTime {
                 }
                 _ => Ok(false),
             }
-        } else {
+        } else if let KeyEvent::Pressed(Key::A) = event {
             self.edit_state = Some(EditState {
                 value: self.value,
                 selected: 6,
             });
             bubble.push_back(Command::TrapFocus);
             Ok(true)
+        } else {
+            Ok(false)
         }
     }
 
@@ -271,45 +273,56 @@ impl View for DateTime {
         vec![]
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
-        let text_style: FontTextStyle<Color> = FontTextStyleBuilder::new(styles.ui_font.font())
-            .font_fallback(styles.cjk_font.font())
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
+        let text_style: FontTextStyle<Color> = FontTextStyleBuilder::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
             .font_size(styles.ui_font.size)
             .draw_background()
             .build();
 
-        let mut x = self.point.x - 30 - 12;
-        let datetime_str = self.value.format("%Y-%m-%d %H:%M:%S").to_string();
-        let mut datetime_str = datetime_str.chars().map(|c| c.to_string()).rev();
-        for _ in 0..19 {
-            let c = datetime_str.next().unwrap();
+        let datetime = self
+            .edit_state
+            .as_ref()
+            .map(|s| s.value)
+            .unwrap_or(self.value);
+
+        let mut x = self.point.x;
+        let mut rect = Rect::new(self.point.x, self.point.y, 0, 0);
+        for field in fields(datetime).iter().rev() {
             let text = Text::with_alignment(
-                &c,
+                field,
                 Point::new(x, self.point.y).into(),
                 text_style.clone(),
                 Alignment::Right.into(),
             );
-            x = text.bounding_box().top_left.x - 1;
+            let text_rect: Rect = text.bounding_box().into();
+            rect = rect.union(&text_rect);
+            x = text_rect.x - 1;
         }
 
-        let rect: Rect = Text::with_alignment(
-            "#",
-            Point::new(x, self.point.y).into(),
-            text_style,
-            Alignment::Right.into(),
-        )
-        .bounding_box()
-        .into();
-
-        Rect::new(
-            rect.x,
-            self.point.y,
-            (self.point.x - rect.x) as u32,
-            rect.h + 1,
-        )
+        rect
     }
 
     fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.point, point);
         self.point = point;
+        self.dirty = true;
     }
 }
+
+/// The fields of `datetime` as they are drawn, with the separators between them.
+fn fields(datetime: NaiveDateTime) -> [String; 11] {
+    [
+        datetime.format("%Y").to_string(),
+        "-".to_string(),
+        datetime.format("%m").to_string(),
+        "-".to_string(),
+        datetime.format("%d").to_string(),
+        " ".to_string(),
+        datetime.format("%H").to_string(),
+        ":".to_string(),
+        datetime.format("%M").to_string(),
+        ":".to_string(),
+        datetime.format("%S").to_string(),
+    ]
+}
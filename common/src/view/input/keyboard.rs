@@ -1,4 +1,7 @@
 use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -17,15 +20,27 @@ use crate::geom::{self, Alignment, Point, Rect};
 use crate::locale::Locale;
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use crate::resources::Resources;
-use crate::stylesheet::Stylesheet;
+use crate::stylesheet::Styles;
 use crate::view::{ButtonHint, ButtonIcon, Row, View};
 
+/// Maximum number of suggestions shown above the keys.
+const MAX_SUGGESTIONS: usize = 5;
+/// How long typing has to pause before suggestions are looked up again.
+const SUGGESTION_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Source of suggestions for the text being typed on a [`Keyboard`].
+pub trait SuggestionProvider: fmt::Debug {
+    /// Returns up to `limit` suggestions for `value`, best first.
+    fn suggestions(&self, value: &str, limit: usize) -> Vec<String>;
+}
+
 #[derive(Debug, Clone)]
 pub struct Keyboard {
     value: String,
     cursor: rusttype::Point<usize>,
     mode: KeyboardMode,
     is_password: bool,
+    suggestions: Option<Suggestions>,
     button_hints: Row<ButtonHint<String>>,
     dirty: bool,
 }
@@ -35,7 +50,7 @@ impl Keyboard {
         let geom::Size { w, h } = res.get::<geom::Size>().to_owned();
 
         let locale = res.get::<Locale>();
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let button_hints = Row::new(
             Point::new(
@@ -71,14 +86,220 @@ impl Keyboard {
             cursor: rusttype::Point { x: 5, y: 2 },
             mode: KeyboardMode::Lowercase,
             is_password,
+            suggestions: None,
             button_hints,
             dirty: true,
         }
     }
 
+    /// Shows a row of suggestions from `provider` above the keys.
+    pub fn with_suggestions(mut self, provider: Rc<dyn SuggestionProvider>) -> Self {
+        self.suggestions = Some(Suggestions::new(provider));
+        self
+    }
+
     pub fn value(&self) -> &str {
         &self.value
     }
+
+    fn value_changed(&mut self) {
+        self.dirty = true;
+        if let Some(suggestions) = &mut self.suggestions {
+            suggestions.invalidate();
+        }
+    }
+
+    /// Whether the cursor is on the suggestion row instead of the keys.
+    fn suggestion_focused(&self) -> bool {
+        self.suggestions
+            .as_ref()
+            .is_some_and(|s| s.selected.is_some())
+    }
+
+    /// Top of the keyboard panel.
+    fn panel_top(&self, display_height: i32, styles: &Styles) -> i32 {
+        let h = styles.ui_font.size as i32 * KEYBOARD_ROWS;
+        display_height
+            - h
+            - ButtonIcon::diameter(styles) as i32
+            - 16
+            - styles.ui_font.size as i32
+            - 8
+    }
+
+    fn draw_suggestions(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<()> {
+        let panel_top = self.panel_top(display.size().height as i32, styles);
+        let Some(suggestions) = &mut self.suggestions else {
+            return Ok(());
+        };
+
+        let height = styles.ui_font.size + 8;
+        let rect = Rect::new(
+            8,
+            panel_top - height as i32 - 8,
+            display.size().width - 16,
+            height,
+        );
+        display.load(rect)?;
+        suggestions.dirty = false;
+
+        if suggestions.items.is_empty() {
+            return Ok(());
+        }
+
+        let text_style = FontTextStyleBuilder::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
+            .font_size(styles.ui_font.size)
+            .text_color(styles.foreground_color)
+            .background_color(styles.background_color)
+            .build();
+        let selected_text_style = FontTextStyleBuilder::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
+            .font_size(styles.ui_font.size)
+            .text_color(styles.foreground_color)
+            .background_color(styles.highlight_color)
+            .build();
+
+        // Scroll so that the selected suggestion is visible
+        let widths: Vec<i32> = suggestions
+            .items
+            .iter()
+            .map(|item| {
+                Text::new(item, Point::zero().into(), text_style.clone())
+                    .bounding_box()
+                    .size
+                    .width as i32
+                    + 24
+            })
+            .collect();
+        let mut first = suggestions.selected.unwrap_or(0);
+        let mut used = widths[first];
+        while first > 0 && used + 8 + widths[first - 1] <= rect.w as i32 {
+            first -= 1;
+            used += 8 + widths[first];
+        }
+
+        let mut x = rect.x;
+        for (i, item) in suggestions.items.iter().enumerate().skip(first) {
+            if x + widths[i] > rect.x + rect.w as i32 {
+                break;
+            }
+            let selected = suggestions.selected == Some(i);
+            RoundedRectangle::with_equal_corners(
+                Rect::new(x, rect.y, widths[i] as u32, height).into(),
+                Size::new_equal(12),
+            )
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .fill_color(if selected {
+                        styles.highlight_color
+                    } else {
+                        styles.background_color
+                    })
+                    .build(),
+            )
+            .draw(display)?;
+
+            Text::with_alignment(
+                item,
+                Point::new(x + widths[i] / 2, rect.y + 4).into(),
+                if selected {
+                    selected_text_style.clone()
+                } else {
+                    text_style.clone()
+                },
+                Alignment::Center.into(),
+            )
+            .draw(display)?;
+
+            x += widths[i] + 8;
+        }
+
+        Ok(())
+    }
+}
+
+/// Suggestions shown above the keys. Looking them up is debounced, so that typing quickly doesn't
+/// query the provider on every key press.
+#[derive(Debug, Clone)]
+struct Suggestions {
+    provider: Rc<dyn SuggestionProvider>,
+    items: Vec<String>,
+    /// Selected suggestion, if the suggestion row is focused.
+    selected: Option<usize>,
+    /// Time left until the suggestions are looked up again.
+    pending: Option<Duration>,
+    dirty: bool,
+}
+
+impl Suggestions {
+    fn new(provider: Rc<dyn SuggestionProvider>) -> Self {
+        Self {
+            provider,
+            items: Vec::new(),
+            selected: None,
+            // Look up the initial suggestions straight away
+            pending: Some(Duration::ZERO),
+            dirty: true,
+        }
+    }
+
+    fn invalidate(&mut self) {
+        self.pending = Some(SUGGESTION_DEBOUNCE);
+    }
+
+    /// Advances the debounce timer, looking up new suggestions for `value` once it expires.
+    fn update(&mut self, dt: Duration, value: &str) {
+        let Some(pending) = self.pending else {
+            return;
+        };
+        if pending > dt {
+            self.pending = Some(pending - dt);
+            return;
+        }
+
+        self.pending = None;
+        let mut items = self.provider.suggestions(value, MAX_SUGGESTIONS);
+        items.truncate(MAX_SUGGESTIONS);
+        if items != self.items {
+            self.items = items;
+            self.selected = self
+                .selected
+                .filter(|_| !self.items.is_empty())
+                .map(|i| i.min(self.items.len() - 1));
+            self.dirty = true;
+        }
+    }
+
+    fn focus(&mut self) -> bool {
+        if self.items.is_empty() {
+            return false;
+        }
+        self.selected = Some(0);
+        self.dirty = true;
+        true
+    }
+
+    fn unfocus(&mut self) {
+        self.selected = None;
+        self.dirty = true;
+    }
+
+    fn select_offset(&mut self, offset: i32) {
+        if let Some(selected) = self.selected {
+            let len = self.items.len() as i32;
+            self.selected = Some((selected as i32 + offset).rem_euclid(len) as usize);
+            self.dirty = true;
+        }
+    }
+
+    fn selected_item(&self) -> Option<&str> {
+        self.selected.map(|i| self.items[i].as_str())
+    }
 }
 
 #[async_trait(?Send)]
@@ -86,19 +307,19 @@ impl View for Keyboard {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
         if self.dirty {
-            let text_style = FontTextStyleBuilder::new(styles.ui_font.font())
-                .font_fallback(styles.cjk_font.font())
+            let text_style = FontTextStyleBuilder::new(styles.ui_font())
+                .font_fallback(styles.cjk_font())
                 .font_size(styles.ui_font.size)
                 .text_color(styles.foreground_color)
                 .background_color(styles.background_color)
                 .build();
 
-            let selected_text_style = FontTextStyleBuilder::new(styles.ui_font.font())
-                .font_fallback(styles.cjk_font.font())
+            let selected_text_style = FontTextStyleBuilder::new(styles.ui_font())
+                .font_fallback(styles.cjk_font())
                 .font_size(styles.ui_font.size)
                 .text_color(styles.foreground_color)
                 .background_color(styles.highlight_color)
@@ -116,6 +337,7 @@ impl View for Keyboard {
 
             let key_size = styles.ui_font.size;
             let key_padding = 0;
+            let focused = self.suggestion_focused();
 
             let w = key_size as i32 * KEYBOARD_COLUMNS + key_padding * 14;
             let h = key_size as i32 * KEYBOARD_ROWS + key_padding * 5;
@@ -140,8 +362,8 @@ impl View for Keyboard {
                 let x = i % KEYBOARD_COLUMNS * w / KEYBOARD_COLUMNS;
                 let y = i / KEYBOARD_COLUMNS * h / KEYBOARD_ROWS;
 
-                let selected =
-                    self.cursor.x + self.cursor.y * KEYBOARD_COLUMNS as usize == i as usize;
+                let selected = !focused
+                    && self.cursor.x + self.cursor.y * KEYBOARD_COLUMNS as usize == i as usize;
                 if self.cursor.y < 4 && selected {
                     RoundedRectangle::with_equal_corners(
                         Rect::new(x0 + x, y0 + y, key_size, key_size).into(),
@@ -171,7 +393,7 @@ impl View for Keyboard {
             // Spacebar
             {
                 let y = 4 * h / KEYBOARD_ROWS;
-                let selected = self.cursor.y == 4;
+                let selected = !focused && self.cursor.y == 4;
                 if selected {
                     RoundedRectangle::with_equal_corners(
                         Rect::new(x0, y0 + y, w as u32, key_size).into(),
@@ -212,6 +434,15 @@ impl View for Keyboard {
 
             self.dirty = false;
             drawn = true;
+
+            if let Some(suggestions) = &mut self.suggestions {
+                suggestions.dirty = true;
+            }
+        }
+
+        if self.suggestions.as_ref().is_some_and(|s| s.dirty) {
+            self.draw_suggestions(display, styles)?;
+            drawn = true;
         }
 
         if self.button_hints.should_draw() {
@@ -228,8 +459,22 @@ impl View for Keyboard {
         Ok(drawn)
     }
 
+    fn update(&mut self, dt: Duration) {
+        if let Some(suggestions) = &mut self.suggestions {
+            let focused = suggestions.selected.is_some();
+            suggestions.update(dt, &self.value);
+            if focused && suggestions.selected.is_none() {
+                // The suggestions ran out, so move the cursor back onto the keys
+                self.cursor.y = 0;
+                self.dirty = true;
+            }
+        }
+    }
+
     fn should_draw(&self) -> bool {
-        self.dirty || self.button_hints.should_draw()
+        self.dirty
+            || self.suggestions.as_ref().is_some_and(|s| s.dirty)
+            || self.button_hints.should_draw()
     }
 
     fn set_should_draw(&mut self) {
@@ -243,7 +488,42 @@ impl View for Keyboard {
         commands: Sender<Command>,
         bubble: &mut VecDeque<Command>,
     ) -> Result<bool> {
+        if self.suggestion_focused() {
+            let suggestions = self.suggestions.as_mut().unwrap();
+            match event {
+                KeyEvent::Pressed(Key::Left) | KeyEvent::Autorepeat(Key::Left) => {
+                    suggestions.select_offset(-1);
+                    return Ok(true);
+                }
+                KeyEvent::Pressed(Key::Right) | KeyEvent::Autorepeat(Key::Right) => {
+                    suggestions.select_offset(1);
+                    return Ok(true);
+                }
+                KeyEvent::Pressed(Key::Up) | KeyEvent::Autorepeat(Key::Up) => return Ok(true),
+                KeyEvent::Pressed(Key::Down) | KeyEvent::Autorepeat(Key::Down) => {
+                    suggestions.unfocus();
+                    self.cursor.y = 0;
+                    self.dirty = true;
+                    return Ok(true);
+                }
+                KeyEvent::Pressed(Key::A) => {
+                    // Fill in the suggestion and search for it straight away
+                    self.value = suggestions.selected_item().unwrap().to_owned();
+                    bubble.push_back(Command::ValueChanged(0, Value::String(self.value.clone())));
+                    bubble.push_back(Command::CloseView);
+                    commands.send(Command::Redraw).await?;
+                    return Ok(true);
+                }
+                _ => {}
+            }
+        }
+
         match event {
+            KeyEvent::Pressed(Key::Up)
+                if self.cursor.y == 0 && self.suggestions.as_mut().is_some_and(|s| s.focus()) =>
+            {
+                self.dirty = true;
+            }
             KeyEvent::Pressed(Key::Up) | KeyEvent::Autorepeat(Key::Up) => {
                 self.cursor.y = (self.cursor.y as i32 - 1).rem_euclid(KEYBOARD_ROWS) as usize;
                 self.dirty = true;
@@ -270,11 +550,11 @@ impl View for Keyboard {
                     .unwrap()
                     .key(self.mode)
                 }
-                self.dirty = true;
+                self.value_changed();
             }
             KeyEvent::Pressed(Key::R) | KeyEvent::Pressed(Key::L) => {
                 self.value.pop();
-                self.dirty = true;
+                self.value_changed();
             }
             KeyEvent::Pressed(Key::B) => {
                 bubble.push_back(Command::CloseView);
@@ -307,7 +587,7 @@ impl View for Keyboard {
         vec![]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         let key_size = 32_u32;
         let key_padding = 4;
 
@@ -515,3 +795,77 @@ fn masked_value(value: &str, is_password: bool) -> String {
         value.to_owned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Names;
+
+    impl SuggestionProvider for Names {
+        fn suggestions(&self, value: &str, limit: usize) -> Vec<String> {
+            if value.is_empty() {
+                return vec!["recent".to_string()];
+            }
+            [
+                "Mario Kart",
+                "Mario Party",
+                "Metroid",
+                "Mega Man",
+                "Mother",
+                "Myst",
+            ]
+            .into_iter()
+            .filter(|name| name.to_lowercase().starts_with(&value.to_lowercase()))
+            .take(limit)
+            .map(str::to_string)
+            .collect()
+        }
+    }
+
+    #[test]
+    fn test_suggestions_are_debounced() {
+        let mut suggestions = Suggestions::new(Rc::new(Names));
+        suggestions.update(Duration::ZERO, "");
+        assert_eq!(suggestions.items, vec!["recent"]);
+
+        suggestions.invalidate();
+        suggestions.update(Duration::from_millis(100), "mar");
+        assert_eq!(suggestions.items, vec!["recent"]);
+
+        suggestions.invalidate();
+        suggestions.update(Duration::from_millis(100), "mario");
+        assert_eq!(suggestions.items, vec!["recent"]);
+        suggestions.update(Duration::from_millis(100), "mario");
+        assert_eq!(suggestions.items, vec!["Mario Kart", "Mario Party"]);
+
+        suggestions.invalidate();
+        suggestions.update(SUGGESTION_DEBOUNCE, "m");
+        assert_eq!(suggestions.items.len(), MAX_SUGGESTIONS);
+    }
+
+    #[test]
+    fn test_suggestion_selection() {
+        let mut suggestions = Suggestions::new(Rc::new(Names));
+        suggestions.update(Duration::ZERO, "mario");
+        assert!(suggestions.focus());
+        assert_eq!(suggestions.selected_item(), Some("Mario Kart"));
+
+        suggestions.select_offset(1);
+        assert_eq!(suggestions.selected_item(), Some("Mario Party"));
+        suggestions.select_offset(1);
+        assert_eq!(suggestions.selected_item(), Some("Mario Kart"));
+        suggestions.select_offset(-1);
+        assert_eq!(suggestions.selected_item(), Some("Mario Party"));
+
+        // The selection stays in range when the suggestions shrink, and is dropped when they run out
+        suggestions.invalidate();
+        suggestions.update(SUGGESTION_DEBOUNCE, "mario k");
+        assert_eq!(suggestions.selected_item(), Some("Mario Kart"));
+        suggestions.invalidate();
+        suggestions.update(SUGGESTION_DEBOUNCE, "zelda");
+        assert_eq!(suggestions.selected_item(), None);
+        assert!(!suggestions.focus());
+    }
+}
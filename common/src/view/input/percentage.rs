@@ -8,7 +8,7 @@ use tokio::sync::mpsc::Sender;
 use crate::command::Value;
 use crate::geom::{Alignment, Point, Rect};
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
+use crate::stylesheet::Styles;
 use crate::view::{Command, Label, View};
 
 #[derive(Debug, Clone)]
@@ -44,6 +44,14 @@ impl Percentage {
         self.value = value;
         self.label.set_text(format!("{}%", self.value));
     }
+
+    fn step(&mut self, delta: i32, bubble: &mut VecDeque<Command>) {
+        let value = (self.value + delta).clamp(0, 100);
+        if value != self.value {
+            self.set_value(value);
+            bubble.push_back(Command::ValueChanged(0, Value::Int(value)));
+        }
+    }
 }
 
 #[async_trait(?Send)]
@@ -51,7 +59,7 @@ impl View for Percentage {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         self.label.draw(display, styles)
     }
@@ -108,9 +116,23 @@ impl View for Percentage {
                 _ => Ok(false),
             }
         } else {
-            self.edit_state = Some(self.value);
-            bubble.push_back(Command::TrapFocus);
-            Ok(true)
+            match event {
+                KeyEvent::Pressed(Key::A) => {
+                    self.edit_state = Some(self.value);
+                    bubble.push_back(Command::TrapFocus);
+                    Ok(true)
+                }
+                // Adjusts the value without editing it first, by as much as while editing
+                KeyEvent::Pressed(Key::Left) => {
+                    self.step(-5, bubble);
+                    Ok(true)
+                }
+                KeyEvent::Pressed(Key::Right) => {
+                    self.step(5, bubble);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            }
         }
     }
 
@@ -122,7 +144,7 @@ impl View for Percentage {
         vec![&mut self.label]
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
         self.label.bounding_box(styles)
     }
 
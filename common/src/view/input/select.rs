@@ -9,7 +9,7 @@ use tokio::sync::mpsc::Sender;
 use crate::command::Value;
 use crate::geom::{Alignment, Point, Rect};
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
+use crate::stylesheet::Styles;
 use crate::view::{Command, Label, View};
 
 #[derive(Debug, Clone)]
@@ -47,6 +47,14 @@ impl Select {
         self.value = selected;
         self.label.set_text(self.values[self.value].clone());
     }
+
+    fn step(&mut self, delta: isize, bubble: &mut VecDeque<Command>) {
+        let value = (self.value as isize + delta).rem_euclid(self.values.len() as isize) as usize;
+        if value != self.value {
+            self.set_value(value);
+            bubble.push_back(Command::ValueChanged(0, Value::Int(value as i32)));
+        }
+    }
 }
 
 #[async_trait(?Send)]
@@ -54,7 +62,7 @@ impl View for Select {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         self.label.draw(display, styles)
     }
@@ -104,9 +112,23 @@ impl View for Select {
                 _ => Ok(false),
             }
         } else {
-            self.edit_state = Some(self.value);
-            bubble.push_back(Command::TrapFocus);
-            Ok(true)
+            match event {
+                KeyEvent::Pressed(Key::A) => {
+                    self.edit_state = Some(self.value);
+                    bubble.push_back(Command::TrapFocus);
+                    Ok(true)
+                }
+                // Picks the previous or next value without editing it first
+                KeyEvent::Pressed(Key::Left) => {
+                    self.step(-1, bubble);
+                    Ok(true)
+                }
+                KeyEvent::Pressed(Key::Right) => {
+                    self.step(1, bubble);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            }
         }
     }
 
@@ -118,7 +140,7 @@ impl View for Select {
         vec![&mut self.label]
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
         self.label.bounding_box(styles)
     }
 
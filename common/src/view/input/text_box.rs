@@ -5,9 +5,9 @@ use async_trait::async_trait;
 use tokio::sync::mpsc::Sender;
 
 use crate::geom::{Alignment, Point, Rect};
-use crate::platform::{DefaultPlatform, KeyEvent, Platform};
+use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use crate::resources::Resources;
-use crate::stylesheet::Stylesheet;
+use crate::stylesheet::Styles;
 use crate::view::input::keyboard::Keyboard;
 use crate::view::{Command, Label, View};
 
@@ -62,7 +62,7 @@ impl View for TextBox {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -112,7 +112,7 @@ impl View for TextBox {
             } else {
                 Ok(false)
             }
-        } else {
+        } else if let KeyEvent::Pressed(Key::A) = event {
             self.keyboard = Some(Keyboard::new(
                 self.res.clone(),
                 self.value.clone(),
@@ -120,6 +120,8 @@ impl View for TextBox {
             ));
             bubble.push_back(Command::TrapFocus);
             Ok(true)
+        } else {
+            Ok(false)
         }
     }
 
@@ -131,7 +133,7 @@ impl View for TextBox {
         vec![&mut self.label]
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
         self.label.bounding_box(styles)
     }
 
@@ -11,14 +11,16 @@ use tokio::sync::mpsc::Sender;
 use crate::command::Value;
 use crate::geom::{Alignment, Point, Rect};
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
-use crate::view::{Command, View};
+use crate::stylesheet::Styles;
+use crate::view::{Command, LastDrawn, View};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Toggle {
     point: Point,
     value: bool,
     alignment: Alignment,
+    #[serde(skip)]
+    drawn: LastDrawn,
     dirty: bool,
 }
 
@@ -28,6 +30,7 @@ impl Toggle {
             point,
             value,
             alignment,
+            drawn: LastDrawn::default(),
             dirty: true,
         }
     }
@@ -40,6 +43,17 @@ impl Toggle {
         self.value = value;
         self.dirty = true;
     }
+
+    fn rect(&self, styles: &Styles) -> Rect {
+        let h = styles.ui_font.size;
+        let w = h * 3 / 2;
+        Rect::new(
+            self.point.x - (w as i32 * (1 - self.alignment.sign()) / 2),
+            self.point.y,
+            w,
+            h,
+        )
+    }
 }
 
 #[async_trait(?Send)]
@@ -47,31 +61,23 @@ impl View for Toggle {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
-        let h = styles.ui_font.size;
-        let w = h * 3 / 2;
+        let rect = self.rect(styles);
+        let Rect { w, h, .. } = rect;
         let margin = h as i32 / 6;
 
-        RoundedRectangle::with_equal_corners(
-            Rect::new(
-                self.point.x - (w as i32 * (1 - self.alignment.sign()) / 2),
-                self.point.y,
-                w,
-                h,
-            )
-            .into(),
-            Size::new_equal(h),
-        )
-        .into_styled(PrimitiveStyle::with_fill(match self.value {
-            true => styles.highlight_color,
-            false => styles.disabled_color,
-        }))
-        .draw(display)?;
+        self.drawn.clear(display)?;
+        RoundedRectangle::with_equal_corners(rect.into(), Size::new_equal(h))
+            .into_styled(PrimitiveStyle::with_fill(match self.value {
+                true => styles.highlight_color,
+                false => styles.disabled_color,
+            }))
+            .draw(display)?;
 
         Circle::new(
             Point::new(
-                self.point.x - (w as i32 * (1 - self.alignment.sign()) / 2)
+                rect.x
                     + match self.value {
                         true => w as i32 - h as i32 + margin,
                         false => margin,
@@ -84,6 +90,7 @@ impl View for Toggle {
         .into_styled(PrimitiveStyle::with_fill(styles.foreground_color))
         .draw(display)?;
 
+        self.drawn.set(rect);
         self.dirty = false;
 
         Ok(true)
@@ -110,6 +117,15 @@ impl View for Toggle {
                 bubble.push_back(Command::ValueChanged(0, Value::Bool(self.value)));
                 return Ok(true);
             }
+            // Left is off and right is on, like the switch is drawn
+            KeyEvent::Pressed(key @ (Key::Left | Key::Right)) => {
+                let value = key == Key::Right;
+                if value != self.value {
+                    self.set_value(value);
+                    bubble.push_back(Command::ValueChanged(0, Value::Bool(value)));
+                }
+                Ok(true)
+            }
             _ => Ok(false),
         }
     }
@@ -122,16 +138,12 @@ impl View for Toggle {
         Vec::new()
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
-        Rect::new(
-            self.point.x - (44 * (1 - self.alignment.sign())),
-            self.point.y,
-            44,
-            24,
-        )
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
+        self.rect(styles)
     }
 
     fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.point, point);
         self.point = point;
         self.dirty = true;
     }
@@ -2,7 +2,7 @@ use std::collections::VecDeque;
 use std::time::Duration;
 
 use crate::command::Command;
-use crate::geom::{Alignment, Point, Rect};
+use crate::geom::{Alignment, Point, Rect, Size};
 use anyhow::Result;
 use async_trait::async_trait;
 use embedded_graphics::prelude::Dimensions;
@@ -14,8 +14,8 @@ use tokio::sync::mpsc::Sender;
 use crate::display::color::Color;
 use crate::display::font::FontTextStyleBuilder;
 use crate::platform::{DefaultPlatform, KeyEvent, Platform};
-use crate::stylesheet::{Stylesheet, StylesheetColor};
-use crate::view::View;
+use crate::stylesheet::{Styles, StylesheetColor};
+use crate::view::{LastDrawn, View};
 
 #[derive(Debug, Clone)]
 struct Scrolling {
@@ -37,6 +37,7 @@ where
     color: StylesheetColor,
     background_color: StylesheetColor,
     scrolling: Option<Scrolling>,
+    drawn: LastDrawn,
     dirty: bool,
 }
 
@@ -58,6 +59,7 @@ where
             color: StylesheetColor::Foreground,
             background_color: StylesheetColor::Background,
             scrolling: None,
+            drawn: LastDrawn::default(),
             dirty: true,
         }
     }
@@ -77,7 +79,15 @@ where
     }
 
     pub fn color(&mut self, color: StylesheetColor) -> &mut Self {
-        self.color = color;
+        if self.color != color {
+            self.color = color;
+            self.dirty = true;
+        }
+        self
+    }
+
+    pub fn background_color(&mut self, color: StylesheetColor) -> &mut Self {
+        self.background_color = color;
         self.dirty = true;
         self
     }
@@ -86,6 +96,16 @@ where
         self.text.as_ref()
     }
 
+    /// Changes the width that longer text is truncated or scrolled to.
+    pub fn set_width(&mut self, width: Option<u32>) -> &mut Self {
+        if self.width != width {
+            self.width = width;
+            self.truncated_text = None;
+            self.dirty = true;
+        }
+        self
+    }
+
     pub fn set_text(&mut self, text: S) -> &mut Self {
         if self.text != text {
             self.text = text;
@@ -96,15 +116,15 @@ where
         self
     }
 
-    fn layout(&mut self, styles: &Stylesheet) {
+    fn layout(&mut self, styles: &Styles) {
         if self.truncated_text.is_some() {
             return;
         }
 
         self.dirty = true;
 
-        let text_style = FontTextStyleBuilder::<Color>::new(styles.ui_font.font())
-            .font_fallback(styles.cjk_font.font())
+        let text_style = FontTextStyleBuilder::<Color>::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
             .font_size(styles.ui_font.size)
             .build();
 
@@ -219,10 +239,10 @@ where
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
-        let text_style = FontTextStyleBuilder::new(styles.ui_font.font())
-            .font_fallback(styles.cjk_font.font())
+        let text_style = FontTextStyleBuilder::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
             .text_color(self.color.to_color(styles))
             .background_color(self.background_color.to_color(styles))
             .font_size(styles.ui_font.size)
@@ -239,7 +259,9 @@ where
             self.alignment.into(),
         );
 
+        self.drawn.clear(display)?;
         text.draw(display)?;
+        self.drawn.set(text.bounding_box().into());
 
         self.dirty = false;
         Ok(true)
@@ -270,9 +292,9 @@ where
         Vec::new()
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
-        let text_style = FontTextStyleBuilder::<Color>::new(styles.ui_font.font())
-            .font_fallback(styles.cjk_font.font())
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
+        let text_style = FontTextStyleBuilder::<Color>::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
             .font_size(styles.ui_font.size)
             .build();
 
@@ -294,7 +316,14 @@ where
         rect
     }
 
+    fn size_hint(&mut self, styles: &Styles) -> Size {
+        // Text wider than the label is truncated or scrolled
+        let Rect { w, h, .. } = self.bounding_box(styles);
+        Size::new(self.width.map_or(w, |width| w.min(width)), h)
+    }
+
     fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.point, point);
         self.point = point;
         self.dirty = true;
     }
@@ -12,8 +12,8 @@ use crate::command::Command;
 use crate::display::Display;
 use crate::geom::{Alignment, Point, Rect};
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
-use crate::view::View;
+use crate::stylesheet::Styles;
+use crate::view::{LastDrawn, View};
 
 /// A listing of selectable entries. Assumes that all entries have the same size.
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,6 +28,8 @@ where
     selected: usize,
     dirty: bool,
     has_layout: bool,
+    #[serde(skip)]
+    drawn: LastDrawn,
 }
 
 impl<V> List<V>
@@ -43,6 +45,7 @@ where
             selected: 0,
             dirty: true,
             has_layout: false,
+            drawn: LastDrawn::default(),
         }
     }
 
@@ -67,33 +70,38 @@ where
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         if !self.has_layout {
             let mut y = self.rect.y + 8;
             for child in &mut self.children {
-                let rect = child.bounding_box(styles);
                 child.set_position(Point::new(self.rect.x + 12, y));
-                y += rect.h as i32 + self.margin as i32 + 8;
+                y += child.size_hint(styles).h as i32 + self.margin as i32 + 8;
             }
             self.has_layout = true;
             self.dirty = true;
         }
 
         if self.dirty {
-            display.load(self.bounding_box(styles))?;
+            self.drawn.clear(display)?;
+            display.load(self.rect)?;
+            self.drawn.set(self.rect);
 
             let selected = &mut self.children[self.selected];
 
             let rect = selected.bounding_box(styles);
 
+            let layout = styles.row_layout();
             let fill_style = PrimitiveStyle::with_fill(styles.highlight_color);
             RoundedRectangle::with_equal_corners(
                 Rectangle::new(
-                    embedded_graphics::prelude::Point::new(rect.x - 12, rect.y - 4),
-                    Size::new(rect.w + 24, rect.h + 8),
+                    embedded_graphics::prelude::Point::new(
+                        rect.x - layout.padding as i32,
+                        rect.y - layout.inset as i32,
+                    ),
+                    Size::new(rect.w + 2 * layout.padding, rect.h + 2 * layout.inset),
                 ),
-                Size::new_equal(rect.h),
+                Size::new_equal(layout.radius),
             )
             .into_styled(fill_style)
             .draw(display)?;
@@ -168,11 +176,12 @@ where
             .collect()
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
     fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.rect.top_left(), point);
         self.rect.x = point.x;
         self.rect.y = point.y;
         self.has_layout = false;
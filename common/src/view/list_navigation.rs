@@ -0,0 +1,149 @@
+/// Where the selection of a list moves to, and which item is at the top of its visible rows.
+/// Single steps wrap around the ends of the list. Pages stop at the ends, unless they're set to
+/// wrap too, in which case a page from the last item goes to the first and the other way around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListNavigation {
+    pub top: usize,
+    pub selected: usize,
+    pub len: usize,
+    /// Number of rows that fit, which is also how far a page goes.
+    pub visible: usize,
+}
+
+impl ListNavigation {
+    /// Selects `index`, clamped to the items, scrolling as little as it takes to show it.
+    pub fn select(&mut self, index: usize) {
+        if self.len == 0 {
+            self.selected = 0;
+            self.top = 0;
+            return;
+        }
+        let visible = self.visible.clamp(1, self.len);
+        self.selected = index.min(self.len - 1);
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + visible {
+            self.top = self.selected + 1 - visible;
+        }
+        // A list shorter than the rows that fit doesn't scroll, nor leaves empty rows at the end
+        self.top = self.top.min(self.len - visible);
+    }
+
+    /// Moves to the next or previous item, wrapping around the ends.
+    pub fn step(&mut self, forward: bool) {
+        if self.len == 0 {
+            return;
+        }
+        let selected = if forward {
+            (self.selected + 1) % self.len
+        } else {
+            (self.selected + self.len - 1) % self.len
+        };
+        self.select(selected);
+    }
+
+    /// Moves a page forward or back, stopping at the ends. If `wrap` is set, paging from an end
+    /// goes to the other end.
+    pub fn page(&mut self, forward: bool, wrap: bool) {
+        if self.len == 0 {
+            return;
+        }
+        let page = self.visible.max(1);
+        let last = self.len - 1;
+        let selected = if forward {
+            if wrap && self.selected == last {
+                0
+            } else {
+                (self.selected + page).min(last)
+            }
+        } else if wrap && self.selected == 0 {
+            last
+        } else {
+            self.selected.saturating_sub(page)
+        };
+        self.select(selected);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nav(len: usize, visible: usize) -> ListNavigation {
+        ListNavigation {
+            top: 0,
+            selected: 0,
+            len,
+            visible,
+        }
+    }
+
+    #[test]
+    fn test_step_wraps() {
+        let mut nav = nav(10, 4);
+        nav.step(false);
+        assert_eq!((nav.selected, nav.top), (9, 6));
+        nav.step(true);
+        assert_eq!((nav.selected, nav.top), (0, 0));
+        nav.step(true);
+        assert_eq!((nav.selected, nav.top), (1, 0));
+    }
+
+    #[test]
+    fn test_page_clamps() {
+        let mut nav = nav(10, 4);
+        nav.page(true, false);
+        assert_eq!((nav.selected, nav.top), (4, 1));
+        nav.page(true, false);
+        assert_eq!((nav.selected, nav.top), (8, 5));
+        nav.page(true, false);
+        assert_eq!((nav.selected, nav.top), (9, 6));
+        nav.page(true, false);
+        assert_eq!((nav.selected, nav.top), (9, 6));
+
+        nav.page(false, false);
+        assert_eq!((nav.selected, nav.top), (5, 5));
+        nav.page(false, false);
+        nav.page(false, false);
+        assert_eq!((nav.selected, nav.top), (0, 0));
+        nav.page(false, false);
+        assert_eq!((nav.selected, nav.top), (0, 0));
+    }
+
+    #[test]
+    fn test_page_wraps_from_the_ends() {
+        let mut nav = nav(10, 4);
+        nav.page(false, true);
+        assert_eq!((nav.selected, nav.top), (9, 6));
+        nav.page(true, true);
+        assert_eq!((nav.selected, nav.top), (0, 0));
+        // Only from the very end, so that no items are skipped over
+        nav.select(7);
+        nav.page(true, true);
+        assert_eq!(nav.selected, 9);
+        nav.page(true, true);
+        assert_eq!(nav.selected, 0);
+    }
+
+    #[test]
+    fn test_short_list() {
+        // Fewer items than rows that fit never scroll
+        let mut nav = nav(3, 5);
+        nav.page(false, false);
+        assert_eq!((nav.selected, nav.top), (0, 0));
+        nav.page(true, false);
+        assert_eq!((nav.selected, nav.top), (2, 0));
+        nav.step(false);
+        assert_eq!((nav.selected, nav.top), (1, 0));
+        nav.top = 2;
+        nav.select(0);
+        assert_eq!((nav.selected, nav.top), (0, 0));
+
+        let mut empty = ListNavigation { selected: 3, ..nav };
+        empty.len = 0;
+        empty.step(true);
+        empty.page(true, true);
+        empty.select(4);
+        assert_eq!((empty.selected, empty.top), (0, 0));
+    }
+}
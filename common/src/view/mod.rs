@@ -1,29 +1,42 @@
+mod art_placeholder;
 mod battery_indicator;
 mod button_hint;
 mod button_icon;
 mod clock;
+mod confirm_dialog;
+mod counter;
+mod grid;
 mod image;
 mod input;
 mod label;
 mod list;
+mod list_navigation;
+mod notes;
 mod null;
+mod progress_bar;
 mod row;
 mod scroll_list;
 mod settings_list;
+mod toast;
+mod write_indicator;
 
 use std::collections::VecDeque;
 use std::fmt;
 use std::time::Duration;
 
+pub use self::art_placeholder::ArtPlaceholder;
 pub use self::battery_indicator::BatteryIndicator;
 pub use self::button_hint::ButtonHint;
 pub use self::button_icon::ButtonIcon;
 pub use self::clock::Clock;
-pub use self::image::{Image, ImageMode};
+pub use self::confirm_dialog::ConfirmDialog;
+pub use self::counter::Counter;
+pub use self::grid::Grid;
+pub use self::image::{thumbnail, Image, ImageKey, ImageMode, IMAGE_CACHE, IMAGE_DECODED};
 pub use self::input::button::Button;
 pub use self::input::color_picker::ColorPicker;
 pub use self::input::datetime::DateTime;
-pub use self::input::keyboard::Keyboard;
+pub use self::input::keyboard::{Keyboard, SuggestionProvider};
 pub use self::input::number::Number;
 pub use self::input::percentage::Percentage;
 pub use self::input::select::Select;
@@ -31,19 +44,25 @@ pub use self::input::text_box::TextBox;
 pub use self::input::toggle::Toggle;
 pub use self::label::Label;
 pub use self::list::List;
+pub use self::list_navigation::ListNavigation;
+pub use self::notes::Notes;
 pub use self::null::NullView;
+pub use self::progress_bar::ProgressBar;
 pub use self::row::Row;
 pub use self::scroll_list::ScrollList;
 pub use self::settings_list::SettingsList;
+pub use self::toast::Toast;
+pub use self::write_indicator::WriteIndicator;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use tokio::sync::mpsc::Sender;
 
 use crate::command::Command;
-use crate::geom::{Point, Rect};
+use crate::display::Display;
+use crate::geom::{Point, Rect, Size};
 use crate::platform::{DefaultPlatform, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
+use crate::stylesheet::Styles;
 
 #[async_trait(?Send)]
 pub trait View {
@@ -56,7 +75,7 @@ pub trait View {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool>;
 
     /// Returns true if the view should be drawn.
@@ -82,17 +101,90 @@ pub trait View {
     fn children_mut(&mut self) -> Vec<&mut dyn View>;
 
     /// Get the bounding box of the view.
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
         self.children_mut()
             .iter_mut()
             .map(|c| c.bounding_box(styles))
             .fold(Rect::zero(), |acc, r| acc.union(&r))
     }
 
-    /// Sets the position of the view.
+    /// Size the view takes up wherever it's placed. Containers lay out their children from this,
+    /// so it must be known before the view is first drawn.
+    fn size_hint(&mut self, styles: &Styles) -> Size {
+        self.bounding_box(styles).size()
+    }
+
+    /// Moves the view's origin to `point`. Children keep their offsets from the origin, and
+    /// [`View::bounding_box`] moves with the view. The view asks to be drawn again, and clears
+    /// where it was drawn before when it is.
     fn set_position(&mut self, point: Point);
 }
 
+/// Where a view was last drawn, so that it can clear that area once it has moved.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LastDrawn {
+    rect: Option<Rect>,
+    moved: bool,
+}
+
+impl LastDrawn {
+    /// Remembers that the view was just drawn within `rect`.
+    pub fn set(&mut self, rect: Rect) {
+        self.rect = Some(rect);
+        self.moved = false;
+    }
+
+    /// Notes that the view moved from `from` to `to`. Moving to where it already is, as
+    /// containers do when they lay out again, keeps what was drawn.
+    pub fn moved(&mut self, from: Point, to: Point) {
+        self.moved |= from != to;
+    }
+
+    /// Restores the background where the view was drawn, if it has moved since.
+    pub fn clear(&mut self, display: &mut impl Display) -> Result<()> {
+        if self.moved {
+            if let Some(rect) = self.rect.take() {
+                display.load(rect)?;
+            }
+            self.moved = false;
+        }
+        Ok(())
+    }
+}
+
+/// A region that something else is about to be shown in. Clearing it once before drawing what is
+/// now shown in full means nothing drawn by what was there before survives partial redraws.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Transition {
+    rect: Option<Rect>,
+}
+
+impl Transition {
+    /// Starts a transition within `rect`, along with any that hasn't been cleared yet.
+    pub fn start(&mut self, rect: Rect) {
+        self.rect = Some(match self.rect {
+            Some(pending) => pending.union(&rect),
+            None => rect,
+        });
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.rect.is_some()
+    }
+
+    /// Restores the background of the region if a transition was started. Returns whether it
+    /// did, in which case whatever is shown there must be drawn in full.
+    pub fn clear(&mut self, display: &mut impl Display) -> Result<bool> {
+        match self.rect.take() {
+            Some(rect) => {
+                display.load(rect)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
 impl fmt::Debug for dyn View {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "View")
@@ -108,7 +200,7 @@ impl View for Box<dyn View> {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         (**self).draw(display, styles)
     }
@@ -144,12 +236,390 @@ impl View for Box<dyn View> {
     }
 
     /// Get the bounding box of the view.
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
         (**self).bounding_box(styles)
     }
 
+    fn size_hint(&mut self, styles: &Styles) -> Size {
+        (**self).size_hint(styles)
+    }
+
     /// Sets the position of the view.
     fn set_position(&mut self, point: Point) {
         (**self).set_position(point)
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::{Path, PathBuf};
+
+    use ::image::{Rgb, RgbImage};
+    use chrono::NaiveDate;
+
+    use type_map::TypeMap;
+
+    use super::*;
+    use crate::battery::Battery;
+    use crate::database::Database;
+    use crate::display::color::Color;
+    use crate::display::golden::styles;
+    use crate::geom::Alignment;
+    use crate::locale::Locale;
+    use crate::resources::Resources;
+
+    /// Area that differs from `background`, if anything does.
+    fn drawn_area(image: &RgbImage, background: &RgbImage) -> Option<Rect> {
+        let mut area: Option<(u32, u32, u32, u32)> = None;
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if pixel == background.get_pixel(x, y) {
+                continue;
+            }
+            area = Some(match area {
+                Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                None => (x, y, x, y),
+            });
+        }
+        area.map(|(x0, y0, x1, y1)| Rect::new(x0 as i32, y0 as i32, x1 - x0 + 1, y1 - y0 + 1))
+    }
+
+    fn contains(outer: Rect, inner: Rect) -> bool {
+        inner.x >= outer.x
+            && inner.y >= outer.y
+            && inner.right() <= outer.right()
+            && inner.bottom() <= outer.bottom()
+    }
+
+    /// Draws `view` at `from`, moves it to `to` and draws it again. Both times, everything on
+    /// screen must be within its bounding box, so that nothing is left where it was.
+    pub(crate) fn assert_moves_cleanly(name: &str, view: &mut dyn View, from: Point, to: Point) {
+        let styles = styles();
+        let mut display = DefaultPlatform::new().unwrap().display().unwrap();
+        // A background that views don't draw with, so that filling with their own shows up
+        display.map_pixels(|_| Color::new(1, 2, 3)).unwrap();
+        display.save().unwrap();
+        let background = display.capture().unwrap();
+
+        for point in [from, to] {
+            view.set_position(point);
+            assert!(view.should_draw(), "{name} doesn't redraw at {point:?}");
+            view.draw(&mut display, &styles).unwrap();
+
+            let rect = view.bounding_box(&styles);
+            let drawn = drawn_area(&display.capture().unwrap(), &background)
+                .unwrap_or_else(|| panic!("{name} drew nothing at {point:?}"));
+            assert!(
+                contains(rect, drawn),
+                "{name} at {point:?} drew {drawn:?} outside of its bounding box {rect:?}"
+            );
+        }
+    }
+
+    struct FullBattery;
+
+    impl Battery for FullBattery {
+        fn update(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn percentage(&self) -> i32 {
+            100
+        }
+
+        fn charging(&self) -> bool {
+            true
+        }
+    }
+
+    fn image_path() -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("allium-view-move-{}.png", std::process::id()));
+        RgbImage::from_pixel(40, 20, Rgb([220, 40, 40]))
+            .save(&path)
+            .unwrap();
+        path
+    }
+
+    fn label(text: &str) -> Label<String> {
+        Label::new(Point::zero(), text.to_string(), Alignment::Left, None)
+    }
+
+    /// What views that show text of their own need, with Allium's English translations.
+    fn resources() -> Resources {
+        let mut res = TypeMap::new();
+        res.insert(Database::in_memory().unwrap());
+        res.insert(Locale::from_dir(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/root/.allium/locales"),
+            "en-US",
+        ));
+        res.insert(styles());
+        Resources::new(res)
+    }
+
+    fn hint(key: crate::platform::Key, text: &str) -> ButtonHint<String> {
+        ButtonHint::new(Point::zero(), key, text.to_string(), Alignment::Left)
+    }
+
+    #[test]
+    fn test_views_move_cleanly() {
+        use crate::platform::Key;
+
+        let (from, to) = (Point::new(300, 40), Point::new(340, 300));
+        let path = image_path();
+        let date = NaiveDate::from_ymd_opt(2024, 2, 29)
+            .unwrap()
+            .and_hms_opt(13, 37, 0)
+            .unwrap();
+
+        let mut views: Vec<(&str, Box<dyn View>)> = vec![
+            ("Label", Box::new(label("Label"))),
+            (
+                "Label (truncated)",
+                Box::new(Label::new(
+                    Point::zero(),
+                    "A label that is too long".to_string(),
+                    Alignment::Left,
+                    Some(120),
+                )),
+            ),
+            (
+                "Label (right)",
+                Box::new(Label::new(
+                    Point::zero(),
+                    "Right".to_string(),
+                    Alignment::Right,
+                    None,
+                )),
+            ),
+            (
+                "ButtonIcon",
+                Box::new(ButtonIcon::new(Point::zero(), Key::A, Alignment::Left)),
+            ),
+            ("ButtonHint", Box::new(hint(Key::B, "Back"))),
+            (
+                "Row",
+                Box::new(Row::new(
+                    Point::zero(),
+                    vec![hint(Key::A, "Select"), hint(Key::Y, "Sort")],
+                    Alignment::Left,
+                    12,
+                )),
+            ),
+            (
+                "Clock",
+                Box::new(Clock::new(Point::zero(), Alignment::Left)),
+            ),
+            (
+                "BatteryIndicator",
+                Box::new(BatteryIndicator::new(Point::zero(), FullBattery)),
+            ),
+            (
+                "Image",
+                Box::new(Image::new(
+                    Rect::new(0, 0, 80, 80),
+                    path.clone(),
+                    ImageMode::Contain,
+                )),
+            ),
+            (
+                "ArtPlaceholder",
+                Box::new({
+                    let mut art = ArtPlaceholder::new(Rect::new(0, 0, 200, 150));
+                    art.set_name("Name");
+                    art
+                }),
+            ),
+            (
+                "ProgressBar",
+                Box::new(ProgressBar::new(Point::zero(), 0.5, Alignment::Left)),
+            ),
+            (
+                "Toggle",
+                Box::new(Toggle::new(Point::zero(), true, Alignment::Left)),
+            ),
+            (
+                "Number",
+                Box::new(Number::new(Point::zero(), 5, 0, 10, Alignment::Left)),
+            ),
+            (
+                "Percentage",
+                Box::new(Percentage::new(Point::zero(), 50, Alignment::Left)),
+            ),
+            (
+                "Select",
+                Box::new(Select::new(
+                    Point::zero(),
+                    0,
+                    vec!["One".to_string()],
+                    Alignment::Left,
+                )),
+            ),
+            (
+                "ColorPicker",
+                Box::new(ColorPicker::new(
+                    Point::zero(),
+                    Color::new(200, 100, 50),
+                    Alignment::Right,
+                )),
+            ),
+            (
+                "DateTime",
+                Box::new(DateTime::new(Point::zero(), date, Alignment::Right)),
+            ),
+            ("Button", Box::new(Button::new(label("Button")))),
+            (
+                "List",
+                Box::new(List::new(
+                    Rect::new(0, 0, 200, 150),
+                    vec![label("One"), label("Two")],
+                    Alignment::Left,
+                    8,
+                )),
+            ),
+            (
+                "ScrollList",
+                Box::new(ScrollList::new(
+                    Rect::new(0, 0, 200, 150),
+                    vec!["One".to_string(), "Two".to_string()],
+                    Alignment::Left,
+                    styles().row_layout(),
+                )),
+            ),
+            (
+                "SettingsList",
+                Box::new(SettingsList::new(
+                    Rect::new(0, 0, 280, 150),
+                    vec!["One".to_string(), "Two".to_string()],
+                    vec![
+                        Box::new(Toggle::new(Point::zero(), true, Alignment::Right)),
+                        Box::new(Label::new(
+                            Point::zero(),
+                            "Two".to_string(),
+                            Alignment::Right,
+                            None,
+                        )),
+                    ],
+                    styles().row_layout(),
+                )),
+            ),
+            (
+                "Grid",
+                Box::new({
+                    let mut grid = Grid::new(Rect::new(0, 0, 260, 150), 2, 1, styles().config());
+                    grid.set_items(vec!["One".to_string(), "Two".to_string()], false);
+                    grid
+                }),
+            ),
+            (
+                "Notes",
+                Box::new(Notes::new(
+                    Rect::new(0, 0, 280, 170),
+                    resources(),
+                    PathBuf::from("Game.gba"),
+                    "Game".to_string(),
+                    true,
+                )),
+            ),
+            ("Toast", Box::new(Toast::new("Saved".to_string(), None))),
+        ];
+
+        for (name, view) in &mut views {
+            assert_moves_cleanly(name, view.as_mut(), from, to);
+        }
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    const CONTENT: Rect = Rect::new(0, 48, 640, 432);
+
+    /// What a tab of the launcher draws below the header: a list of games, with the selected
+    /// game's art beside it if there is any.
+    fn tab(games: &[&str], art: Option<&Path>) -> Vec<Box<dyn View>> {
+        let mut views: Vec<Box<dyn View>> = vec![Box::new(ScrollList::new(
+            Rect::new(12, CONTENT.y + 8, 320, 300),
+            games.iter().map(|s| s.to_string()).collect(),
+            Alignment::Left,
+            styles().row_layout(),
+        ))];
+        if let Some(art) = art {
+            views.push(Box::new(Image::new(
+                Rect::new(360, CONTENT.y + 8, 250, 360),
+                art.to_path_buf(),
+                ImageMode::Contain,
+            )));
+        }
+        views
+    }
+
+    type Tab<'a> = dyn Fn() -> Vec<Box<dyn View>> + 'a;
+
+    fn screen() -> <DefaultPlatform as Platform>::Display {
+        let mut display = DefaultPlatform::new().unwrap().display().unwrap();
+        display.map_pixels(|_| Color::new(1, 2, 3)).unwrap();
+        display.save().unwrap();
+        let mut header = label("Recents   Games");
+        header.set_position(Point::new(12, 8));
+        header.draw(&mut display, &styles()).unwrap();
+        display
+    }
+
+    fn draw_all(display: &mut <DefaultPlatform as Platform>::Display, views: &mut [Box<dyn View>]) {
+        for view in views {
+            view.set_should_draw();
+            view.draw(display, &styles()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_transition_leaves_nothing_behind() {
+        let art =
+            std::env::temp_dir().join(format!("allium-transition-{}.png", std::process::id()));
+        RgbImage::from_pixel(250, 360, Rgb([220, 40, 40]))
+            .save(&art)
+            .unwrap();
+        let games = || tab(&["Advance Wars", "Pokemon Emerald"], Some(&art));
+        let recents = || tab(&["Tetris"], None);
+
+        // Each tab as it looks when the launcher starts on it
+        let golden = |mut views: Vec<Box<dyn View>>| {
+            let mut display = screen();
+            draw_all(&mut display, &mut views);
+            display.capture().unwrap()
+        };
+
+        let tabs: [(&str, &Tab<'_>, &Tab<'_>); 2] = [
+            ("games to recents", &games, &recents),
+            ("recents to games", &recents, &games),
+        ];
+        for (name, from, to) in tabs {
+            let expected = golden(to());
+            let mut display = screen();
+            draw_all(&mut display, &mut from());
+
+            let mut transition = Transition::default();
+            transition.start(CONTENT);
+            assert!(transition.is_pending());
+            assert!(transition.clear(&mut display).unwrap(), "{name}");
+            assert!(!transition.is_pending());
+            draw_all(&mut display, &mut to());
+
+            let image = display.capture().unwrap();
+            if let Some((x, y, _)) = image
+                .enumerate_pixels()
+                .find(|(x, y, pixel)| expected.get_pixel(*x, *y) != *pixel)
+            {
+                panic!("{name} left a stale pixel at ({x}, {y})");
+            }
+            // The region is only cleared once
+            assert!(!transition.clear(&mut display).unwrap());
+        }
+
+        // Without it, the art is still there after switching to a tab without any
+        let mut display = screen();
+        draw_all(&mut display, &mut games());
+        draw_all(&mut display, &mut recents());
+        assert_ne!(display.capture().unwrap(), golden(recents()));
+
+        let _ = std::fs::remove_file(art);
+    }
+}
@@ -0,0 +1,419 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use embedded_graphics::prelude::{Dimensions, Size};
+use embedded_graphics::primitives::{Primitive, PrimitiveStyle, Rectangle, RoundedRectangle};
+use embedded_graphics::text::Text;
+use embedded_graphics::Drawable;
+use log::error;
+use tokio::sync::mpsc::Sender;
+
+use crate::command::Command;
+use crate::constants::NOTE_LENGTH_LIMIT;
+use crate::database::Database;
+use crate::display::color::Color;
+use crate::display::font::{FontTextStyle, FontTextStyleBuilder};
+use crate::display::Display;
+use crate::geom::{Alignment, Point, Rect};
+use crate::locale::Locale;
+use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use crate::resources::Resources;
+use crate::stylesheet::{Styles, StylesheetColor};
+use crate::text_edit::{line_of, wrap, TextEdit};
+use crate::view::{ButtonHint, ButtonIcon, Keyboard, Label, LastDrawn, Row, View};
+
+/// A game's note, shown read-only until it is edited. Editing types with the on-screen keyboard
+/// at a cursor that the d-pad moves through the wrapped text.
+#[derive(Debug)]
+pub struct Notes {
+    rect: Rect,
+    res: Resources,
+    path: PathBuf,
+    editable: bool,
+    editing: bool,
+    edit: TextEdit,
+    lines: Vec<Range<usize>>,
+    /// First visible line.
+    scroll: usize,
+    title: Label<String>,
+    length: Label<String>,
+    button_hints: Row<ButtonHint<String>>,
+    keyboard: Option<Keyboard>,
+    drawn: LastDrawn,
+    dirty: bool,
+}
+
+impl Notes {
+    pub fn new(rect: Rect, res: Resources, path: PathBuf, name: String, editable: bool) -> Self {
+        let note = res
+            .get::<Database>()
+            .note(&path)
+            .map_err(|e| error!("failed to load note from database: {}", e))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        let Rect { x, y, w, .. } = rect;
+        let title = Label::new(
+            Point::new(x + 12, y + 8),
+            name,
+            Alignment::Left,
+            Some(w * 2 / 3),
+        );
+        let mut length = Label::new(
+            Point::new(x + w as i32 - 12, y + 8),
+            String::new(),
+            Alignment::Right,
+            None,
+        );
+        length.color(StylesheetColor::Disabled);
+
+        let mut notes = Self {
+            rect,
+            res,
+            path,
+            editable,
+            editing: false,
+            edit: TextEdit::new(note, NOTE_LENGTH_LIMIT),
+            lines: Vec::new(),
+            scroll: 0,
+            title,
+            length,
+            button_hints: Row::new(Point::zero(), Vec::new(), Alignment::Right, 12),
+            keyboard: None,
+            drawn: LastDrawn::default(),
+            dirty: true,
+        };
+        notes.update_button_hints();
+        notes.relayout();
+        notes
+    }
+
+    /// Area the text is drawn in, inside its box.
+    fn text_rect(&self, styles: &Styles) -> Rect {
+        let Rect { x, y, w, h } = self.rect;
+        let top = 8 + styles.ui_font.size + 8;
+        let bottom = 8 + ButtonIcon::diameter(styles) + 8;
+        Rect::new(
+            x + 12 + 12,
+            y + top as i32 + 8,
+            w - 48,
+            h - top - bottom - 16,
+        )
+    }
+
+    fn visible_lines(&self, styles: &Styles) -> usize {
+        (self.text_rect(styles).h / styles.guide_font.size).max(1) as usize
+    }
+
+    fn relayout(&mut self) {
+        let styles = self.res.get::<Styles>();
+        let style = text_style(&styles);
+        let width = self.text_rect(&styles).w;
+        self.lines = wrap(self.edit.text(), width, |s| measure(&style, s));
+
+        if self.editing {
+            // Keep the cursor in view
+            let line = line_of(&self.lines, self.edit.cursor());
+            let visible = self.visible_lines(&styles);
+            self.scroll = self
+                .scroll
+                .min(line)
+                .max((line + 1).saturating_sub(visible));
+        }
+        self.scroll = self
+            .scroll
+            .min(self.lines.len().saturating_sub(self.visible_lines(&styles)));
+        drop(styles);
+
+        self.length
+            .set_text(format!("{}/{}", self.edit.len(), self.edit.max_len()));
+        self.dirty = true;
+    }
+
+    fn move_up(&mut self) {
+        let styles = self.res.get::<Styles>();
+        let style = text_style(&styles);
+        self.edit.move_up(&self.lines, |s| measure(&style, s));
+    }
+
+    fn move_down(&mut self) {
+        let styles = self.res.get::<Styles>();
+        let style = text_style(&styles);
+        self.edit.move_down(&self.lines, |s| measure(&style, s));
+    }
+
+    fn scroll_by(&mut self, lines: isize) {
+        let visible = self.visible_lines(&self.res.get::<Styles>());
+        let max = self.lines.len().saturating_sub(visible);
+        self.scroll = self.scroll.saturating_add_signed(lines).min(max);
+        self.dirty = true;
+    }
+
+    fn start_editing(&mut self) {
+        self.editing = true;
+        self.update_button_hints();
+        self.relayout();
+    }
+
+    fn finish_editing(&mut self) {
+        let note = self.edit.text();
+        let note = if note.trim().is_empty() { "" } else { note };
+        if let Err(e) = self.res.get::<Database>().set_note(&self.path, note) {
+            error!("failed to save note to database: {}", e);
+        }
+        self.editing = false;
+        self.update_button_hints();
+        self.relayout();
+    }
+
+    fn update_button_hints(&mut self) {
+        let locale = self.res.get::<Locale>();
+        let styles = self.res.get::<Styles>();
+        let hints = if self.editing {
+            vec![
+                (Key::A, locale.t("notes-button-type")),
+                (Key::X, locale.t("notes-button-delete")),
+                (Key::Y, locale.t("notes-button-new-line")),
+                (Key::B, locale.t("notes-button-done")),
+            ]
+        } else if self.editable {
+            vec![
+                (Key::A, locale.t("button-edit")),
+                (Key::B, locale.t("button-back")),
+            ]
+        } else {
+            vec![(Key::B, locale.t("button-back"))]
+        };
+
+        let Rect { x, y, w, h } = self.rect;
+        self.button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            hints
+                .into_iter()
+                .map(|(key, text)| ButtonHint::new(Point::zero(), key, text, Alignment::Right))
+                .collect(),
+            Alignment::Right,
+            12,
+        );
+    }
+
+    fn draw_text(
+        &self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<()> {
+        let rect = self.text_rect(styles);
+        RoundedRectangle::with_equal_corners(
+            Rect::new(rect.x - 12, rect.y - 8, rect.w + 24, rect.h + 16).into(),
+            Size::new_equal(8),
+        )
+        .into_styled(PrimitiveStyle::with_fill(styles.background_color))
+        .draw(display)?;
+
+        if self.edit.is_empty() && !self.editing {
+            let style = FontTextStyleBuilder::new(styles.guide_font())
+                .font_fallback(styles.cjk_font())
+                .font_size(styles.guide_font.size)
+                .background_color(styles.background_color)
+                .text_color(styles.disabled_color)
+                .build();
+            let text = self.res.get::<Locale>().t("notes-empty");
+            Text::new(&text, Point::new(rect.x, rect.y).into(), style).draw(display)?;
+            return Ok(());
+        }
+
+        let style = text_style(styles);
+        let text = self.edit.text();
+        let cursor_line = line_of(&self.lines, self.edit.cursor());
+        let mut y = rect.y;
+        for (i, line) in self
+            .lines
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(self.visible_lines(styles))
+        {
+            let line_text = text[line.clone()].trim_end_matches('\n');
+            Text::new(line_text, Point::new(rect.x, y).into(), style.clone()).draw(display)?;
+
+            if self.editing && i == cursor_line {
+                let x = measure(&style, &text[line.start..self.edit.cursor()]);
+                Rectangle::new(
+                    Point::new(rect.x + x as i32, y).into(),
+                    Size::new(2, styles.guide_font.size),
+                )
+                .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+                .draw(display)?;
+            }
+            y += styles.guide_font.size as i32;
+        }
+
+        Ok(())
+    }
+}
+
+fn text_style(styles: &Styles) -> FontTextStyle<Color> {
+    FontTextStyleBuilder::new(styles.guide_font())
+        .font_fallback(styles.cjk_font())
+        .font_size(styles.guide_font.size)
+        .background_color(styles.background_color)
+        .text_color(styles.foreground_color)
+        .build()
+}
+
+fn measure(style: &FontTextStyle<Color>, text: &str) -> u32 {
+    Text::new(text, Point::zero().into(), style.clone())
+        .bounding_box()
+        .size
+        .width
+}
+
+#[async_trait(?Send)]
+impl View for Notes {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            self.drawn.clear(display)?;
+            display.load(self.rect)?;
+            self.drawn.set(self.rect);
+            self.title.set_should_draw();
+            self.title.draw(display, styles)?;
+            self.length.set_should_draw();
+            self.length.draw(display, styles)?;
+            self.draw_text(display, styles)?;
+            self.button_hints.set_should_draw();
+            self.button_hints.draw(display, styles)?;
+            if let Some(keyboard) = self.keyboard.as_mut() {
+                keyboard.set_should_draw();
+            }
+            self.dirty = false;
+            drawn = true;
+        }
+
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            drawn |= keyboard.should_draw() && keyboard.draw(display, styles)?;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty || self.keyboard.as_ref().is_some_and(View::should_draw)
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            keyboard.set_should_draw();
+        }
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            keyboard.handle_key_event(event, commands, bubble).await?;
+            let mut changed = false;
+            bubble.retain_mut(|cmd| match cmd {
+                Command::CloseView => {
+                    self.keyboard = None;
+                    changed = true;
+                    false
+                }
+                Command::ValueChanged(_, value) => {
+                    if let Some(value) = std::mem::take(value).as_string() {
+                        self.edit.insert(&value);
+                        changed = true;
+                    }
+                    false
+                }
+                _ => true,
+            });
+            if changed {
+                self.relayout();
+            }
+            return Ok(true);
+        }
+
+        if self.editing {
+            match event {
+                KeyEvent::Pressed(Key::Left) | KeyEvent::Autorepeat(Key::Left) => {
+                    self.edit.move_left();
+                }
+                KeyEvent::Pressed(Key::Right) | KeyEvent::Autorepeat(Key::Right) => {
+                    self.edit.move_right();
+                }
+                KeyEvent::Pressed(Key::Up) | KeyEvent::Autorepeat(Key::Up) => self.move_up(),
+                KeyEvent::Pressed(Key::Down) | KeyEvent::Autorepeat(Key::Down) => {
+                    self.move_down();
+                }
+                KeyEvent::Pressed(Key::A) => {
+                    self.keyboard = Some(Keyboard::new(self.res.clone(), String::new(), false));
+                    return Ok(true);
+                }
+                KeyEvent::Pressed(Key::X) | KeyEvent::Autorepeat(Key::X) => {
+                    self.edit.backspace();
+                }
+                KeyEvent::Pressed(Key::Y) | KeyEvent::Autorepeat(Key::Y) => {
+                    self.edit.insert("\n");
+                }
+                KeyEvent::Pressed(Key::B) => {
+                    self.finish_editing();
+                    return Ok(true);
+                }
+                _ => return Ok(true),
+            }
+            self.relayout();
+        } else {
+            match event {
+                KeyEvent::Pressed(Key::Up) | KeyEvent::Autorepeat(Key::Up) => self.scroll_by(-1),
+                KeyEvent::Pressed(Key::Down) | KeyEvent::Autorepeat(Key::Down) => {
+                    self.scroll_by(1);
+                }
+                KeyEvent::Pressed(Key::A) if self.editable => self.start_editing(),
+                KeyEvent::Pressed(Key::B) => bubble.push_back(Command::CloseView),
+                _ => {}
+            }
+        }
+        Ok(true)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![]
+    }
+
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.rect.top_left(), point);
+        self.rect.x = point.x;
+        self.rect.y = point.y;
+        let Rect { x, y, w, .. } = self.rect;
+        self.title.set_position(Point::new(x + 12, y + 8));
+        self.length
+            .set_position(Point::new(x + w as i32 - 12, y + 8));
+        self.update_button_hints();
+        self.dirty = true;
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
     command::Command,
     geom::Point,
     platform::{DefaultPlatform, KeyEvent, Platform},
-    stylesheet::Stylesheet,
+    stylesheet::Styles,
     view::View,
 };
 
@@ -19,7 +19,7 @@ impl View for NullView {
     fn draw(
         &mut self,
         _display: &mut <DefaultPlatform as Platform>::Display,
-        _styles: &Stylesheet,
+        _styles: &Styles,
     ) -> Result<bool> {
         Ok(false)
     }
@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use embedded_graphics::prelude::Size;
+use embedded_graphics::primitives::{Primitive, PrimitiveStyle, RoundedRectangle};
+use embedded_graphics::Drawable;
+use tokio::sync::mpsc::Sender;
+
+use crate::geom::{self, Alignment, Point, Rect};
+use crate::platform::{DefaultPlatform, KeyEvent, Platform};
+use crate::stylesheet::Styles;
+use crate::view::{Command, LastDrawn, View};
+
+/// A bar that fills up as a long task makes progress.
+#[derive(Debug, Clone)]
+pub struct ProgressBar {
+    point: Point,
+    /// How much of the task is done, from 0 to 1.
+    progress: f32,
+    alignment: Alignment,
+    drawn: LastDrawn,
+    dirty: bool,
+}
+
+impl ProgressBar {
+    pub fn new(point: Point, progress: f32, alignment: Alignment) -> Self {
+        Self {
+            point,
+            progress: progress.clamp(0.0, 1.0),
+            alignment,
+            drawn: LastDrawn::default(),
+            dirty: true,
+        }
+    }
+
+    pub fn set_progress(&mut self, progress: f32) {
+        let progress = progress.clamp(0.0, 1.0);
+        if progress != self.progress {
+            self.progress = progress;
+            self.dirty = true;
+        }
+    }
+
+    fn rect(&self, styles: &Styles) -> Rect {
+        let h = styles.ui_font.size;
+        let w = h * 4;
+        Rect::new(
+            self.point.x - (w as i32 * (1 - self.alignment.sign()) / 2),
+            self.point.y,
+            w,
+            h,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl View for ProgressBar {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let rect = self.rect(styles);
+        self.drawn.clear(display)?;
+        // The bar is half as tall as the text, and centered on it
+        let h = rect.h / 2;
+        let y = rect.y + (rect.h - h) as i32 / 2;
+
+        RoundedRectangle::with_equal_corners(
+            Rect::new(rect.x, y, rect.w, h).into(),
+            Size::new_equal(h / 2),
+        )
+        .into_styled(PrimitiveStyle::with_fill(styles.disabled_color))
+        .draw(display)?;
+
+        let filled = (rect.w as f32 * self.progress) as u32;
+        if filled > 0 {
+            RoundedRectangle::with_equal_corners(
+                Rect::new(rect.x, y, filled.max(h), h).into(),
+                Size::new_equal(h / 2),
+            )
+            .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+            .draw(display)?;
+        }
+
+        self.drawn.set(rect);
+        self.dirty = false;
+        Ok(true)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        _event: KeyEvent,
+        _commands: Sender<Command>,
+        _bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        Vec::new()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        Vec::new()
+    }
+
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
+        self.rect(styles)
+    }
+
+    fn size_hint(&mut self, styles: &Styles) -> geom::Size {
+        self.rect(styles).size()
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.point, point);
+        self.point = point;
+        self.dirty = true;
+    }
+}
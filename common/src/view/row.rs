@@ -7,9 +7,9 @@ use tokio::sync::mpsc::Sender;
 
 use crate::command::Command;
 use crate::display::Display;
-use crate::geom::{Alignment, Point, Rect};
+use crate::geom::{Alignment, Point, Rect, Size};
 use crate::platform::{DefaultPlatform, KeyEvent, Platform};
-use crate::stylesheet::Stylesheet;
+use crate::stylesheet::Styles;
 use crate::view::View;
 
 /// A horizontal row of views.
@@ -86,7 +86,7 @@ where
         self.has_layout = false;
     }
 
-    fn layout(&mut self, styles: &Stylesheet) {
+    fn layout(&mut self, styles: &Styles) {
         match self.alignment {
             Alignment::Left => self.layout_left(styles),
             Alignment::Center => unimplemented!("alignment should be Left or Right"),
@@ -96,21 +96,19 @@ where
         self.dirty = true;
     }
 
-    fn layout_left(&mut self, styles: &Stylesheet) {
+    fn layout_left(&mut self, styles: &Styles) {
         let mut x = self.point.x;
         for entry in &mut self.children {
-            let rect = entry.bounding_box(styles);
             entry.set_position(Point::new(x, self.point.y));
-            x += rect.w as i32 + self.margin;
+            x += entry.size_hint(styles).w as i32 + self.margin;
         }
     }
 
-    fn layout_right(&mut self, styles: &Stylesheet) {
+    fn layout_right(&mut self, styles: &Styles) {
         let mut x = self.point.x;
         for entry in self.children.iter_mut() {
             entry.set_position(Point::new(x, self.point.y));
-            let rect = entry.bounding_box(styles);
-            x -= rect.w as i32 + self.margin;
+            x -= entry.size_hint(styles).w as i32 + self.margin;
         }
     }
 }
@@ -126,7 +124,7 @@ where
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         if !self.has_layout {
             self.layout(styles);
@@ -179,7 +177,7 @@ where
             .collect()
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
         if !self.has_layout {
             self.layout(styles);
         }
@@ -191,6 +189,19 @@ where
             .unwrap_or_default()
     }
 
+    fn size_hint(&mut self, styles: &Styles) -> Size {
+        let sizes: Vec<_> = self
+            .children
+            .iter_mut()
+            .map(|c| c.size_hint(styles))
+            .collect();
+        let margins = self.margin * sizes.len().saturating_sub(1) as i32;
+        Size::new(
+            sizes.iter().map(|s| s.w).sum::<u32>() + margins.max(0) as u32,
+            sizes.iter().map(|s| s.h).max().unwrap_or_default(),
+        )
+    }
+
     fn set_position(&mut self, point: Point) {
         self.point = point;
         self.has_layout = false;
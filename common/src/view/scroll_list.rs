@@ -1,20 +1,25 @@
 use std::collections::VecDeque;
+use std::ops::Range;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use embedded_graphics::prelude::Size;
+use embedded_graphics::prelude::{Dimensions, Size};
 use embedded_graphics::primitives::{
     CornerRadii, Primitive, PrimitiveStyle, Rectangle, RoundedRectangle,
 };
+use embedded_graphics::text::Text;
 use embedded_graphics::Drawable;
 
 use tokio::sync::mpsc::Sender;
 
+use crate::display::color::Color;
+use crate::display::font::{FontTextStyle, FontTextStyleBuilder};
 use crate::display::Display;
 use crate::geom::{Alignment, Point, Rect};
+use crate::list_settings::ListSettings;
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use crate::stylesheet::{Stylesheet, StylesheetColor};
-use crate::view::{Command, Label, View};
+use crate::stylesheet::{RowLayout, Styles, StylesheetColor};
+use crate::view::{Command, Label, LastDrawn, ListNavigation, View};
 
 /// A listing of selectable entries. Assumes that all entries have the same size.
 #[derive(Debug, Clone)]
@@ -22,14 +27,24 @@ pub struct ScrollList {
     rect: Rect,
     /// All entries.
     items: Vec<String>,
+    /// Whether each entry is greyed out.
+    disabled: Vec<bool>,
+    /// Text color of each entry, if not the usual one. Greying out takes priority.
+    colors: Vec<Option<StylesheetColor>>,
+    /// Text right-aligned at the end of each entry's row, such as a count.
+    details: Vec<Option<String>>,
     /// Visible entries.
     children: Vec<Label<String>>,
     alignment: Alignment,
-    entry_height: u32,
+    layout: RowLayout,
     top: usize,
     selected: usize,
     background_color: Option<StylesheetColor>,
+    settings: ListSettings,
     dirty: bool,
+    /// First visible row that needs to be redrawn, if only part of the list changed.
+    dirty_from: Option<usize>,
+    drawn: LastDrawn,
 }
 
 impl ScrollList {
@@ -37,7 +52,7 @@ impl ScrollList {
         mut rect: Rect,
         items: Vec<String>,
         alignment: Alignment,
-        entry_height: u32,
+        layout: RowLayout,
     ) -> Self {
         match alignment {
             Alignment::Left => {}
@@ -51,13 +66,19 @@ impl ScrollList {
         let mut this = Self {
             rect,
             items: Vec::new(),
+            disabled: Vec::new(),
+            colors: Vec::new(),
+            details: Vec::new(),
             children: Vec::new(),
             alignment,
-            entry_height,
+            layout,
             top: 0,
             selected: 0,
             background_color: None,
+            settings: ListSettings::default(),
             dirty: true,
+            dirty_from: None,
+            drawn: LastDrawn::default(),
         };
 
         this.set_items(items, false);
@@ -70,6 +91,11 @@ impl ScrollList {
         self.dirty = true;
     }
 
+    /// Sets how the list behaves, such as whether paging wraps around its ends.
+    pub fn set_list_settings(&mut self, settings: ListSettings) {
+        self.settings = settings;
+    }
+
     pub fn set_item(&mut self, index: usize, item: String) {
         if index >= self.items.len() {
             return;
@@ -81,6 +107,9 @@ impl ScrollList {
     }
 
     pub fn set_items(&mut self, items: Vec<String>, preserve_selection: bool) {
+        self.disabled = vec![false; items.len()];
+        self.colors = vec![None; items.len()];
+        self.details = vec![None; items.len()];
         if items.is_empty() {
             self.items = items;
             self.children.clear();
@@ -99,15 +128,18 @@ impl ScrollList {
         self.items = items;
 
         self.children.clear();
-        let mut y = self.rect.y + 4;
+        let mut y = self.rect.y + self.layout.inset as i32;
         for i in 0..self.visible_count() {
             self.children.push(Label::new(
-                Point::new(self.rect.x + 12 * self.alignment.sign(), y),
+                Point::new(
+                    self.rect.x + self.layout.padding as i32 * self.alignment.sign(),
+                    y,
+                ),
                 self.items[i].to_owned(),
                 self.alignment,
-                Some(self.rect.w - 24),
+                Some(self.rect.w - 2 * self.layout.padding),
             ));
-            y += self.entry_height as i32;
+            y += self.layout.height as i32;
         }
 
         self.select(selected);
@@ -116,7 +148,249 @@ impl ScrollList {
         self.dirty = true;
     }
 
-    pub fn select(&mut self, mut index: usize) {
+    /// Inserts an item at `index`, keeping the same item selected.
+    pub fn insert(&mut self, index: usize, item: String) {
+        let index = index.min(self.items.len());
+        self.items.insert(index, item);
+        self.disabled.insert(index, false);
+        self.colors.insert(index, None);
+        self.details.insert(index, None);
+
+        let (selected, top) = indices_after_insert(
+            self.selected,
+            self.top,
+            index,
+            self.items.len(),
+            self.capacity(),
+        );
+        self.update_indices(selected, top, index);
+    }
+
+    /// Removes the item at `index`. If it was selected, the next item is selected instead.
+    pub fn remove(&mut self, index: usize) -> Option<String> {
+        if index >= self.items.len() {
+            return None;
+        }
+        let item = self.items.remove(index);
+        self.disabled.remove(index);
+        self.colors.remove(index);
+        self.details.remove(index);
+
+        let (selected, top) = indices_after_remove(
+            self.selected,
+            self.top,
+            index,
+            self.items.len(),
+            self.capacity(),
+        );
+        self.update_indices(selected, top, index);
+
+        Some(item)
+    }
+
+    /// Replaces the item at `index`.
+    pub fn replace(&mut self, index: usize, item: String) {
+        if index >= self.items.len() {
+            return;
+        }
+        self.items[index] = item;
+        self.mark_rows_dirty(index, self.top);
+        self.update_children();
+    }
+
+    /// Greys out the item at `index`, or shows it normally again.
+    pub fn set_disabled(&mut self, index: usize, disabled: bool) {
+        if self.disabled.get(index).is_none_or(|d| *d == disabled) {
+            return;
+        }
+        self.disabled[index] = disabled;
+        self.mark_rows_dirty(index, self.top);
+        self.update_children();
+    }
+
+    /// Whether the item at `index` is greyed out.
+    pub fn is_disabled(&self, index: usize) -> bool {
+        self.disabled.get(index).copied().unwrap_or_default()
+    }
+
+    /// Shows the item at `index` in `color`, or the usual color if `None`.
+    pub fn set_color(&mut self, index: usize, color: Option<StylesheetColor>) {
+        if self.colors.get(index).is_none_or(|c| *c == color) {
+            return;
+        }
+        self.colors[index] = color;
+        self.mark_rows_dirty(index, self.top);
+        self.update_children();
+    }
+
+    /// Shows `detail` right-aligned in the row of the item at `index`. Only left-aligned lists
+    /// show details.
+    pub fn set_detail(&mut self, index: usize, detail: Option<String>) {
+        if self.details.get(index).is_none_or(|d| *d == detail) {
+            return;
+        }
+        self.details[index] = detail;
+        self.mark_rows_dirty(index, self.top);
+    }
+
+    /// Text shown at the end of the row of the item at `index`.
+    pub fn detail(&self, index: usize) -> Option<&str> {
+        self.details.get(index)?.as_deref()
+    }
+
+    fn update_indices(&mut self, selected: usize, top: usize, changed: usize) {
+        let old_top = self.top;
+        let old_selected = self.selected;
+        self.top = top;
+        self.selected = selected;
+
+        // Add or remove labels if the number of visible rows changed
+        self.children.truncate(self.visible_count());
+        let mut y = self.rect.y
+            + self.layout.inset as i32
+            + self.children.len() as i32 * self.layout.height as i32;
+        while self.children.len() < self.visible_count() {
+            self.children.push(Label::new(
+                Point::new(
+                    self.rect.x + self.layout.padding as i32 * self.alignment.sign(),
+                    y,
+                ),
+                String::new(),
+                self.alignment,
+                Some(self.rect.w - 2 * self.layout.padding),
+            ));
+            y += self.layout.height as i32;
+        }
+        self.update_children();
+
+        let scrolled = if changed < old_top {
+            self.top.abs_diff(old_top) != 1
+        } else {
+            self.top != old_top
+        };
+        if scrolled || self.selected - self.top != old_selected - old_top {
+            // Rows moved on screen, or the selection highlight did
+            let selected = self.selected - self.top;
+            for (i, child) in self.children.iter_mut().enumerate() {
+                child.scroll(i == selected);
+            }
+            self.dirty = true;
+        } else if changed >= old_top {
+            self.mark_rows_dirty(changed, self.top);
+        }
+    }
+
+    /// Marks the rows from item `index` to the bottom of the list as needing a redraw.
+    fn mark_rows_dirty(&mut self, index: usize, top: usize) {
+        if index < top || index >= top + self.capacity() {
+            return;
+        }
+        if self.background_color.is_some() || index == self.selected {
+            self.dirty = true;
+            return;
+        }
+        let row = index - top;
+        self.dirty_from = Some(self.dirty_from.map_or(row, |r| r.min(row)));
+    }
+
+    fn detail_style(styles: &Styles) -> FontTextStyle<Color> {
+        FontTextStyleBuilder::<Color>::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
+            .text_color(StylesheetColor::Disabled.to_color(styles))
+            .background_color(StylesheetColor::Background.to_color(styles))
+            .font_size(styles.ui_font.size)
+            .build()
+    }
+
+    /// Narrows the visible rows that have a detail, so that their text stops short of it.
+    fn layout_details(&mut self, styles: &Styles) {
+        if self.alignment != Alignment::Left {
+            return;
+        }
+        let right = self.rect.x + self.rect.w as i32 - self.layout.padding as i32;
+        for (i, child) in self.children.iter_mut().enumerate() {
+            let detail_width = self.details[self.top + i].as_ref().map_or(0, |detail| {
+                Text::with_alignment(
+                    detail,
+                    Point::new(right, 0).into(),
+                    Self::detail_style(styles),
+                    Alignment::Right.into(),
+                )
+                .bounding_box()
+                .size
+                .width
+                    + self.layout.padding
+            });
+            child.set_width(Some(
+                (self.rect.w - 2 * self.layout.padding).saturating_sub(detail_width),
+            ));
+        }
+    }
+
+    /// Draws the details of the visible rows in `rows`.
+    fn draw_details<D: Display>(
+        &self,
+        display: &mut D,
+        styles: &Styles,
+        rows: Range<usize>,
+    ) -> Result<()> {
+        if self.alignment != Alignment::Left {
+            return Ok(());
+        }
+        let right = self.rect.x + self.rect.w as i32 - self.layout.padding as i32;
+        for i in rows {
+            if let Some(detail) = self.details[self.top + i].as_ref() {
+                let y = self.rect.y
+                    + self.layout.inset as i32
+                    + (i * self.layout.height as usize) as i32;
+                Text::with_alignment(
+                    detail,
+                    Point::new(right, y).into(),
+                    Self::detail_style(styles),
+                    Alignment::Right.into(),
+                )
+                .draw(display)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the highlight behind the selected row, which also covers what its label last drew.
+    fn draw_highlight(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<()> {
+        let Some(selected) = self.children.get_mut(self.selected - self.top) else {
+            return Ok(());
+        };
+        let rect = selected.bounding_box(styles);
+
+        let fill_style = PrimitiveStyle::with_fill(styles.highlight_color);
+        RoundedRectangle::with_equal_corners(
+            Rectangle::new(
+                embedded_graphics::prelude::Point::new(
+                    rect.x - self.layout.padding as i32,
+                    rect.y - self.layout.inset as i32,
+                ),
+                Size::new(
+                    rect.w + 2 * self.layout.padding,
+                    rect.h + 2 * self.layout.inset,
+                ),
+            ),
+            Size::new_equal(self.layout.radius),
+        )
+        .into_styled(fill_style)
+        .draw(display)?;
+        Ok(())
+    }
+
+    /// Number of rows that fit in the list.
+    fn capacity(&self) -> usize {
+        self.rect.h as usize / self.layout.height as usize
+    }
+
+    pub fn select(&mut self, index: usize) {
         if self.visible_count() == 0 {
             return;
         }
@@ -125,13 +399,10 @@ impl ScrollList {
             .get_mut(self.selected - self.top)
             .map(|v| v.scroll(false));
 
-        index = index.clamp(0, self.items.len() - 1);
-        if index >= self.top + self.visible_count() {
-            self.top = (index - self.visible_count() + 1).min(self.items.len() - 1);
-        } else if index < self.top {
-            self.top = index;
-        }
-        self.selected = index;
+        let mut navigation = self.navigation();
+        navigation.select(index);
+        self.top = navigation.top;
+        self.selected = navigation.selected;
         self.update_children();
 
         self.children
@@ -145,25 +416,155 @@ impl ScrollList {
         self.selected
     }
 
+    fn navigation(&self) -> ListNavigation {
+        ListNavigation {
+            top: self.top,
+            selected: self.selected,
+            len: self.items.len(),
+            visible: self.capacity(),
+        }
+    }
+
+    /// Index of the first visible row.
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    /// Selects `index` with the list scrolled so that `top` is the first visible row, as far as
+    /// that still keeps the selection visible. Both are clamped to the items, which may have
+    /// changed since they were saved.
+    pub fn select_at(&mut self, index: usize, top: usize) {
+        self.select(index);
+        let visible = self.visible_count();
+        if visible == 0 {
+            return;
+        }
+        let top = top
+            .clamp((self.selected + 1).saturating_sub(visible), self.selected)
+            .min(self.items.len() - visible);
+        if top == self.top {
+            return;
+        }
+
+        self.children
+            .get_mut(self.selected - self.top)
+            .map(|v| v.scroll(false));
+        self.top = top;
+        self.update_children();
+        self.children
+            .get_mut(self.selected - self.top)
+            .map(|v| v.scroll(true));
+    }
+
     pub fn visible_count(&self) -> usize {
-        (self.rect.h as usize / self.entry_height as usize).min(self.items.len())
+        (self.rect.h as usize / self.layout.height as usize).min(self.items.len())
     }
 
     fn update_children(&mut self) {
         for (i, child) in self.children.iter_mut().enumerate() {
             child.set_text(self.items[self.top + i].to_owned());
+            child.color(if self.disabled[self.top + i] {
+                StylesheetColor::Disabled
+            } else {
+                self.colors[self.top + i].unwrap_or(StylesheetColor::Foreground)
+            });
         }
     }
 }
 
+/// Returns the selected and top indices after an item was inserted at `index`, so that the same
+/// item stays selected and the list doesn't scroll unless it has to. `len` is the new length.
+pub(super) fn indices_after_insert(
+    selected: usize,
+    top: usize,
+    index: usize,
+    len: usize,
+    capacity: usize,
+) -> (usize, usize) {
+    if len <= 1 {
+        return (0, 0);
+    }
+    let selected = if index <= selected {
+        selected + 1
+    } else {
+        selected
+    };
+    let top = if index < top { top + 1 } else { top };
+    (selected, scroll_to(selected, top, capacity))
+}
+
+/// Returns the selected and top indices after the item at `index` was removed. If the selected
+/// item was removed, the item after it is selected. `len` is the new length.
+pub(super) fn indices_after_remove(
+    selected: usize,
+    top: usize,
+    index: usize,
+    len: usize,
+    capacity: usize,
+) -> (usize, usize) {
+    if len == 0 {
+        return (0, 0);
+    }
+    let selected = if index < selected {
+        selected - 1
+    } else {
+        selected.min(len - 1)
+    };
+    let top = if index < top { top - 1 } else { top };
+    // Don't leave empty rows at the bottom if there are items above the top
+    let top = top.min(len.saturating_sub(capacity));
+    (selected, scroll_to(selected, top, capacity))
+}
+
+/// Adjusts `top` so that `selected` is visible.
+fn scroll_to(selected: usize, top: usize, capacity: usize) -> usize {
+    if selected < top {
+        selected
+    } else if capacity > 0 && selected >= top + capacity {
+        selected + 1 - capacity
+    } else {
+        top
+    }
+}
+
 #[async_trait(?Send)]
 impl View for ScrollList {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
-        if self.should_draw() {
+        self.layout_details(styles);
+
+        // A long name scrolling in the highlighted row only redraws that row, so that the rest of
+        // the list doesn't flicker
+        let selected = self.selected - self.top;
+        if !self.dirty
+            && self.dirty_from.is_none()
+            && selected < self.children.len()
+            && self
+                .children
+                .iter()
+                .enumerate()
+                .all(|(i, v)| v.should_draw() == (i == selected))
+        {
+            self.draw_highlight(display, styles)?;
+            self.children[selected].draw(display, styles)?;
+            self.draw_details(display, styles, selected..selected + 1)?;
+            return Ok(true);
+        }
+
+        // Rows from `dirty_from` are cleared and redrawn below, anything above them needs the
+        // whole list to be redrawn
+        let first = self
+            .dirty_from
+            .unwrap_or(usize::MAX)
+            .min(self.children.len());
+        if self.dirty || self.children[..first].iter().any(|v| v.should_draw()) {
+            self.drawn.clear(display)?;
+            let rect = self.bounding_box(styles);
+            self.drawn.set(rect);
+
             if let Some(color) = self.background_color {
                 let mut rect = self
                     .children_mut()
@@ -171,56 +572,66 @@ impl View for ScrollList {
                     .map(|v| v.bounding_box(styles))
                     .reduce(|acc, r| acc.union(&r))
                     .unwrap_or_default();
-                rect.x -= 12;
-                rect.w += 24;
-                rect.y -= 4;
-                rect.h += 8;
+                rect.x -= self.layout.padding as i32;
+                rect.w += 2 * self.layout.padding;
+                rect.y -= self.layout.inset as i32;
+                rect.h += 2 * self.layout.inset;
                 RoundedRectangle::new(
                     rect.into(),
-                    CornerRadii::new(Size::new_equal((styles.ui_font.size + 8) / 2)),
+                    CornerRadii::new(Size::new_equal(self.layout.radius)),
                 )
                 .into_styled(PrimitiveStyle::with_fill(color.to_color(styles)))
                 .draw(display)?;
             } else {
-                display.load(self.bounding_box(styles))?;
+                display.load(rect)?;
             }
 
-            if let Some(selected) = self.children.get_mut(self.selected - self.top) {
-                let rect = selected.bounding_box(styles);
-
-                let fill_style = PrimitiveStyle::with_fill(styles.highlight_color);
-                RoundedRectangle::with_equal_corners(
-                    Rectangle::new(
-                        embedded_graphics::prelude::Point::new(rect.x - 12, rect.y - 4),
-                        Size::new(rect.w + 24, rect.h + 8),
-                    ),
-                    Size::new_equal(rect.h),
-                )
-                .into_styled(fill_style)
-                .draw(display)?;
-            }
+            self.draw_highlight(display, styles)?;
 
             for child in self.children.iter_mut() {
                 child.draw(display, styles)?;
             }
+            self.draw_details(display, styles, 0..self.children.len())?;
 
             self.dirty = false;
+            self.dirty_from = None;
 
             return Ok(true);
         }
 
+        let dirty_from = self.dirty_from.take();
+        if let Some(first) = dirty_from {
+            let rect = self.bounding_box(styles);
+            for i in first..self.capacity() {
+                display.load(Rect::new(
+                    rect.x,
+                    rect.y + (i * self.layout.height as usize) as i32,
+                    rect.w,
+                    self.layout.height,
+                ))?;
+                if let Some(child) = self.children.get_mut(i) {
+                    child.set_should_draw();
+                }
+            }
+        }
+
         let mut drawn = false;
         for child in self.children.iter_mut() {
             if child.should_draw() && child.draw(display, styles)? {
                 drawn = true;
             }
         }
+        // After the rows, which clear what they last drew
+        if let Some(first) = dirty_from {
+            self.draw_details(display, styles, first..self.children.len())?;
+            drawn = true;
+        }
 
         Ok(drawn)
     }
 
     fn should_draw(&self) -> bool {
-        self.dirty || self.children.iter().any(|v| v.should_draw())
+        self.dirty || self.dirty_from.is_some() || self.children.iter().any(|v| v.should_draw())
     }
 
     fn set_should_draw(&mut self) {
@@ -238,28 +649,19 @@ impl View for ScrollList {
     ) -> Result<bool> {
         if !self.items.is_empty() {
             match event {
-                KeyEvent::Pressed(Key::Up) | KeyEvent::Autorepeat(Key::Up) => {
-                    self.select(
-                        (self.selected as isize - 1).rem_euclid(self.items.len() as isize) as usize,
-                    );
-                    self.dirty = true;
-                    Ok(true)
-                }
-                KeyEvent::Pressed(Key::Down) | KeyEvent::Autorepeat(Key::Down) => {
-                    self.select((self.selected + 1).rem_euclid(self.items.len()));
-                    self.dirty = true;
-                    Ok(true)
-                }
-                KeyEvent::Pressed(Key::L) | KeyEvent::Autorepeat(Key::L) => {
-                    self.select(
-                        (self.selected as isize - 5).clamp(0, self.items.len() as isize - 1)
-                            as usize,
-                    );
+                KeyEvent::Pressed(key @ (Key::Up | Key::Down))
+                | KeyEvent::Autorepeat(key @ (Key::Up | Key::Down)) => {
+                    let mut navigation = self.navigation();
+                    navigation.step(key == Key::Down);
+                    self.select(navigation.selected);
                     self.dirty = true;
                     Ok(true)
                 }
-                KeyEvent::Pressed(Key::R) | KeyEvent::Autorepeat(Key::R) => {
-                    self.select((self.selected + 5).clamp(0, self.items.len() - 1));
+                KeyEvent::Pressed(key @ (Key::L | Key::R))
+                | KeyEvent::Autorepeat(key @ (Key::L | Key::R)) => {
+                    let mut navigation = self.navigation();
+                    navigation.page(key == Key::R, self.settings.wrap_pages);
+                    self.select(navigation.selected);
                     self.dirty = true;
                     Ok(true)
                 }
@@ -281,7 +683,7 @@ impl View for ScrollList {
             .collect()
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         match self.alignment {
             Alignment::Left => self.rect,
             Alignment::Center => Rect::new(
@@ -300,15 +702,250 @@ impl View for ScrollList {
     }
 
     fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.rect.top_left(), point);
         self.rect.x = point.x;
         self.rect.y = point.y;
         for (i, child) in self.children.iter_mut().enumerate() {
             child.set_position(Point::new(
-                point.x + 12,
-                point.y + 8 + i as i32 * self.entry_height as i32,
+                point.x + self.layout.padding as i32 * self.alignment.sign(),
+                point.y + self.layout.inset as i32 + i as i32 * self.layout.height as i32,
             ));
         }
 
         self.dirty = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_list(len: usize) -> ScrollList {
+        // Fits 5 rows
+        ScrollList::new(
+            Rect::new(0, 0, 100, 100),
+            (0..len).map(|i| i.to_string()).collect(),
+            Alignment::Left,
+            RowLayout {
+                height: 20,
+                inset: 4,
+                padding: 12,
+                radius: 10,
+            },
+        )
+    }
+
+    fn selected_item(list: &ScrollList) -> &str {
+        &list.items[list.selected]
+    }
+
+    #[test]
+    fn test_insert_above_selection() {
+        assert_eq!(indices_after_insert(3, 0, 1, 11, 5), (4, 0));
+        assert_eq!(indices_after_insert(7, 5, 2, 11, 5), (8, 6));
+
+        let mut list = new_list(10);
+        list.select(7);
+        list.insert(2, "new".to_string());
+        assert_eq!(selected_item(&list), "7");
+        assert_eq!(list.top, 4);
+    }
+
+    #[test]
+    fn test_insert_at_selection() {
+        assert_eq!(indices_after_insert(3, 0, 3, 11, 5), (4, 0));
+        // The selected row is pushed off the bottom, so scroll to keep it visible
+        assert_eq!(indices_after_insert(4, 0, 4, 11, 5), (5, 1));
+
+        let mut list = new_list(10);
+        list.select(3);
+        list.insert(3, "new".to_string());
+        assert_eq!(selected_item(&list), "3");
+        assert_eq!(list.top, 0);
+    }
+
+    #[test]
+    fn test_insert_below_selection() {
+        assert_eq!(indices_after_insert(3, 0, 4, 11, 5), (3, 0));
+
+        let mut list = new_list(10);
+        list.select(1);
+        list.dirty = false;
+        list.insert(3, "new".to_string());
+        assert_eq!(selected_item(&list), "1");
+        assert_eq!(list.items[3], "new");
+        assert_eq!(list.children[3].text(), "new");
+        // Only the rows from the insertion down need to be redrawn
+        assert!(!list.dirty);
+        assert_eq!(list.dirty_from, Some(3));
+    }
+
+    #[test]
+    fn test_insert_into_empty_list() {
+        let mut list = new_list(0);
+        list.insert(0, "new".to_string());
+        assert_eq!(selected_item(&list), "new");
+        assert_eq!(list.children.len(), 1);
+    }
+
+    #[test]
+    fn test_disabled_follows_item() {
+        let mut list = new_list(10);
+        list.set_disabled(3, true);
+
+        list.insert(0, "new".to_string());
+        assert!(list.disabled[4]);
+        list.remove(1);
+        assert!(list.disabled[3]);
+        assert_eq!(list.disabled.iter().filter(|d| **d).count(), 1);
+
+        list.set_items(vec!["a".to_string()], false);
+        assert_eq!(list.disabled, [false]);
+    }
+
+    #[test]
+    fn test_color_follows_item() {
+        let mut list = new_list(10);
+        list.set_color(3, Some(StylesheetColor::Error));
+
+        list.insert(0, "new".to_string());
+        assert_eq!(list.colors[4], Some(StylesheetColor::Error));
+        list.remove(1);
+        assert_eq!(list.colors[3], Some(StylesheetColor::Error));
+        assert_eq!(list.colors.iter().flatten().count(), 1);
+
+        list.set_items(vec!["a".to_string()], false);
+        assert_eq!(list.colors, [None]);
+    }
+
+    #[test]
+    fn test_detail_follows_item() {
+        let mut list = new_list(10);
+        list.set_detail(3, Some("(12)".to_string()));
+        // Out of range details are ignored
+        list.set_detail(10, Some("(1)".to_string()));
+
+        list.insert(0, "new".to_string());
+        assert_eq!(list.details[4].as_deref(), Some("(12)"));
+        list.remove(1);
+        assert_eq!(list.details[3].as_deref(), Some("(12)"));
+        assert_eq!(list.details.iter().flatten().count(), 1);
+
+        list.set_items(vec!["a".to_string()], false);
+        assert_eq!(list.details, [None]);
+    }
+
+    #[test]
+    fn test_remove_selected() {
+        assert_eq!(indices_after_remove(3, 0, 3, 9, 5), (3, 0));
+        // Removing the last item selects the one before it
+        assert_eq!(indices_after_remove(9, 5, 9, 9, 5), (8, 4));
+
+        let mut list = new_list(10);
+        list.select(3);
+        assert_eq!(list.remove(3).as_deref(), Some("3"));
+        assert_eq!(selected_item(&list), "4");
+    }
+
+    #[test]
+    fn test_remove_above_and_below_selection() {
+        assert_eq!(indices_after_remove(3, 0, 1, 9, 5), (2, 0));
+        assert_eq!(indices_after_remove(7, 5, 2, 9, 5), (6, 4));
+        assert_eq!(indices_after_remove(3, 0, 4, 9, 5), (3, 0));
+
+        let mut list = new_list(10);
+        list.select(7);
+        list.remove(1);
+        assert_eq!(selected_item(&list), "7");
+    }
+
+    #[test]
+    fn test_remove_last_visible_row() {
+        // Scrolled to the end, so the list scrolls up to fill the gap
+        assert_eq!(indices_after_remove(6, 5, 9, 9, 5), (6, 4));
+
+        let mut list = new_list(10);
+        list.select(9);
+        list.select(5);
+        assert_eq!(list.top, 5);
+        list.remove(9);
+        assert_eq!(selected_item(&list), "5");
+        assert_eq!(list.top, 4);
+        assert_eq!(list.children.len(), 5);
+
+        // Short list, so the row just disappears
+        let mut short = new_list(3);
+        short.dirty = false;
+        short.remove(2);
+        assert_eq!(short.children.len(), 2);
+        assert!(!short.dirty);
+        assert_eq!(short.dirty_from, Some(2));
+    }
+
+    #[test]
+    fn test_select_at() {
+        let mut list = new_list(10);
+        list.select_at(6, 3);
+        assert_eq!((list.selected, list.top), (6, 3));
+
+        // The selection stays visible
+        list.select_at(6, 0);
+        assert_eq!((list.selected, list.top), (6, 2));
+        list.select_at(2, 4);
+        assert_eq!((list.selected, list.top), (2, 2));
+
+        // Fewer items than when the position was saved
+        let mut list = new_list(4);
+        list.select_at(8, 6);
+        assert_eq!((list.selected, list.top), (3, 0));
+        let mut list = new_list(0);
+        list.select_at(8, 6);
+        assert_eq!((list.selected, list.top), (0, 0));
+    }
+
+    #[test]
+    fn test_remove_only_item() {
+        let mut list = new_list(1);
+        list.remove(0);
+        assert_eq!(list.selected, 0);
+        assert!(list.children.is_empty());
+        assert_eq!(list.remove(0), None);
+    }
+
+    #[test]
+    fn test_scrolling_name_only_redraws_its_row() {
+        use std::time::Duration;
+
+        use embedded_graphics::Pixel;
+
+        use crate::display::golden::styles;
+
+        let styles = styles();
+        let mut display = DefaultPlatform::new().unwrap().display().unwrap();
+        let mut list = ScrollList::new(
+            Rect::new(0, 0, 200, 200),
+            vec![
+                "A name that is far too long to fit in its row".to_string(),
+                "Short".to_string(),
+            ],
+            Alignment::Left,
+            styles.row_layout(),
+        );
+        list.draw(&mut display, &styles).unwrap();
+
+        // A mark in the next row is only cleared if the whole list is drawn again
+        let mark =
+            embedded_graphics::prelude::Point::new(190, styles.row_layout().height as i32 * 3 / 2);
+        Pixel(mark, Color::new(255, 0, 0))
+            .draw(&mut display)
+            .unwrap();
+        let before = display.capture().unwrap();
+
+        list.update(Duration::from_secs(2));
+        assert!(list.should_draw());
+        list.draw(&mut display, &styles).unwrap();
+        let after = display.capture().unwrap();
+        assert_eq!(after.get_pixel(190, mark.y as u32).0, [255, 0, 0]);
+        assert_ne!(before, after);
+    }
+}
@@ -11,9 +11,11 @@ use tokio::sync::mpsc::Sender;
 
 use crate::display::Display;
 use crate::geom::{Alignment, Point, Rect};
+use crate::list_settings::ListSettings;
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use crate::stylesheet::{Stylesheet, StylesheetColor};
-use crate::view::{Command, Label, View};
+use crate::stylesheet::{RowLayout, Styles, StylesheetColor};
+use crate::view::scroll_list::{indices_after_insert, indices_after_remove};
+use crate::view::{Command, Label, LastDrawn, ListNavigation, View};
 
 /// A listing of selectable entries. Assumes that all entries have the same size.
 #[derive(Debug)]
@@ -22,13 +24,17 @@ pub struct SettingsList {
     labels: Vec<String>,
     left: Vec<Label<String>>,
     right: Vec<Box<dyn View>>,
-    entry_height: u32,
+    layout: RowLayout,
     top: usize,
     selected: usize,
     background_color: Option<StylesheetColor>,
+    settings: ListSettings,
     focused: bool,
     dirty: bool,
+    /// First visible row that needs to be redrawn, if only part of the list changed.
+    dirty_from: Option<usize>,
     has_layout: bool,
+    drawn: LastDrawn,
 }
 
 impl SettingsList {
@@ -36,20 +42,23 @@ impl SettingsList {
         rect: Rect,
         left: Vec<String>,
         right: Vec<Box<dyn View>>,
-        entry_height: u32,
+        layout: RowLayout,
     ) -> Self {
         let mut this = Self {
             rect,
             labels: Vec::new(),
             left: Vec::new(),
             right: Vec::new(),
-            entry_height,
+            layout,
             top: 0,
             selected: 0,
             focused: false,
             background_color: None,
+            settings: ListSettings::default(),
             dirty: true,
+            dirty_from: None,
             has_layout: false,
+            drawn: LastDrawn::default(),
         };
 
         this.set_items(left, right);
@@ -62,20 +71,25 @@ impl SettingsList {
         self.dirty = true;
     }
 
+    /// Sets how the list behaves, such as whether paging wraps around its ends.
+    pub fn set_list_settings(&mut self, settings: ListSettings) {
+        self.settings = settings;
+    }
+
     pub fn set_items(&mut self, left: Vec<String>, right: Vec<Box<dyn View>>) {
         self.labels = left;
         self.right = right;
         self.left.clear();
 
-        let mut y = self.rect.y + 4;
+        let mut y = self.rect.y + self.layout.inset as i32;
         for i in 0..self.visible_count() {
             self.left.push(Label::new(
-                Point::new(self.rect.x + 12, y),
+                Point::new(self.rect.x + self.layout.padding as i32, y),
                 self.labels[i].to_owned(),
                 Alignment::Left,
-                Some((self.rect.w - 24) * 2 / 3),
+                Some((self.rect.w - 2 * self.layout.padding) * 2 / 3),
             ));
-            y += self.entry_height as i32;
+            y += self.layout.height as i32;
         }
 
         self.top = 0;
@@ -95,9 +109,113 @@ impl SettingsList {
         self.dirty = true;
     }
 
+    /// Inserts a row at `index`, keeping the same row selected.
+    pub fn insert(&mut self, index: usize, left: String, right: Box<dyn View>) {
+        let index = index.min(self.labels.len());
+        self.labels.insert(index, left);
+        self.right.insert(index, right);
+
+        let (selected, top) = indices_after_insert(
+            self.selected,
+            self.top,
+            index,
+            self.labels.len(),
+            self.capacity(),
+        );
+        self.update_indices(selected, top, index);
+    }
+
+    /// Removes the row at `index`. If it was selected, the next row is selected instead.
+    pub fn remove(&mut self, index: usize) -> Option<(String, Box<dyn View>)> {
+        if index >= self.labels.len() {
+            return None;
+        }
+        let left = self.labels.remove(index);
+        let right = self.right.remove(index);
+
+        let (selected, top) = indices_after_remove(
+            self.selected,
+            self.top,
+            index,
+            self.labels.len(),
+            self.capacity(),
+        );
+        self.update_indices(selected, top, index);
+
+        Some((left, right))
+    }
+
+    /// Replaces the row at `index`.
+    pub fn replace(&mut self, index: usize, left: String, right: Box<dyn View>) {
+        if index >= self.labels.len() {
+            return;
+        }
+        self.labels[index] = left;
+        self.right[index] = right;
+        self.has_layout = false;
+        self.mark_rows_dirty(index, self.top);
+        self.update_children();
+    }
+
+    fn update_indices(&mut self, selected: usize, top: usize, changed: usize) {
+        let old_top = self.top;
+        let old_selected = self.selected;
+        self.top = top;
+        self.selected = selected;
+
+        // Add or remove labels if the number of visible rows changed
+        self.left.truncate(self.visible_count());
+        let mut y = self.rect.y
+            + self.layout.inset as i32
+            + self.left.len() as i32 * self.layout.height as i32;
+        while self.left.len() < self.visible_count() {
+            self.left.push(Label::new(
+                Point::new(self.rect.x + self.layout.padding as i32, y),
+                String::new(),
+                Alignment::Left,
+                Some((self.rect.w - 2 * self.layout.padding) * 2 / 3),
+            ));
+            y += self.layout.height as i32;
+        }
+        self.update_children();
+        self.has_layout = false;
+
+        let scrolled = if changed < old_top {
+            self.top.abs_diff(old_top) != 1
+        } else {
+            self.top != old_top
+        };
+        if scrolled || self.selected - self.top != old_selected - old_top {
+            // Rows moved on screen, or the selection highlight did
+            self.dirty = true;
+        } else if changed >= old_top {
+            self.mark_rows_dirty(changed, self.top);
+        }
+    }
+
+    /// Marks the rows from item `index` to the bottom of the list as needing a redraw.
+    fn mark_rows_dirty(&mut self, index: usize, top: usize) {
+        if index < top || index >= top + self.capacity() {
+            return;
+        }
+        if self.background_color.is_some() || index == self.selected {
+            self.dirty = true;
+            return;
+        }
+        let row = index - top;
+        self.dirty_from = Some(self.dirty_from.map_or(row, |r| r.min(row)));
+    }
+
+    /// Number of rows that fit in the list.
+    fn capacity(&self) -> usize {
+        self.rect.h as usize / self.layout.height as usize
+    }
+
     pub fn select(&mut self, index: usize) {
+        // A restored position may be out of range once the list or its layout changed
+        let index = index.min(self.labels.len().saturating_sub(1));
         if index >= self.top + self.visible_count() {
-            self.top = index - self.visible_count() + 1;
+            self.top = (index + 1).saturating_sub(self.visible_count().max(1));
             self.update_children();
             self.has_layout = false;
         } else if index < self.top {
@@ -132,11 +250,69 @@ impl SettingsList {
     }
 
     pub fn visible_count(&self) -> usize {
-        (self.rect.h as usize / self.entry_height as usize)
+        (self.rect.h as usize / self.layout.height as usize)
             .min(self.labels.len())
             .min(self.right.len())
     }
 
+    /// Moves the selection by a screenful of rows, or around to the other end if the settings say.
+    fn page(&mut self, forward: bool) {
+        let mut navigation = self.navigation();
+        navigation.page(forward, self.settings.wrap_pages);
+        self.select(navigation.selected);
+    }
+
+    fn navigation(&self) -> ListNavigation {
+        ListNavigation {
+            top: self.top,
+            selected: self.selected,
+            len: self.right.len(),
+            visible: self.visible_count(),
+        }
+    }
+
+    /// Passes `event` on to the selected row's widget, while the widget isn't focused.
+    async fn forward_key_event(
+        &mut self,
+        event: KeyEvent,
+        command: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        let Some(selected) = self.right.get_mut(self.selected) else {
+            return Ok(false);
+        };
+        if !selected.handle_key_event(event, command, bubble).await? {
+            return Ok(false);
+        }
+        bubble.retain_mut(|cmd| match cmd {
+            Command::TrapFocus => {
+                self.focused = true;
+                self.dirty = true;
+                false
+            }
+            Command::ValueChanged(i, _) => {
+                // The new value may be wider or narrower than the selection highlight
+                *i = self.selected;
+                self.dirty = true;
+                true
+            }
+            _ => true,
+        });
+        Ok(true)
+    }
+
+    /// Places the visible rows' widgets at the right edge of their rows.
+    fn layout(&mut self) {
+        for i in 0..self.visible_count() {
+            let child = &mut self.right[self.top + i];
+            child.set_position(Point::new(
+                self.rect.x + self.rect.w as i32 - self.layout.padding as i32 - 1,
+                self.rect.y + self.layout.inset as i32 + i as i32 * self.layout.height as i32,
+            ));
+        }
+        self.has_layout = true;
+    }
+
     fn update_children(&mut self) {
         for (i, child) in self.left.iter_mut().enumerate() {
             child.set_text(self.labels[self.top + i].to_owned());
@@ -149,19 +325,15 @@ impl View for SettingsList {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
+        if !self.has_layout {
+            self.layout();
+        }
+
         if self.dirty {
-            if !self.has_layout {
-                for i in 0..self.visible_count() {
-                    let child = &mut self.right[self.top + i];
-                    child.set_position(Point::new(
-                        self.rect.x + self.rect.w as i32 - 13,
-                        self.rect.y + 4 + i as i32 * self.entry_height as i32,
-                    ));
-                    self.has_layout = true;
-                }
-            }
+            self.drawn.clear(display)?;
+            self.drawn.set(self.rect);
 
             if let Some(color) = self.background_color {
                 let mut rect = self
@@ -170,18 +342,18 @@ impl View for SettingsList {
                     .map(|v| v.bounding_box(styles))
                     .reduce(|acc, r| acc.union(&r))
                     .unwrap_or_default();
-                rect.x -= 12;
-                rect.w += 24;
-                rect.y -= 4;
-                rect.h += 8;
+                rect.x -= self.layout.padding as i32;
+                rect.w += 2 * self.layout.padding;
+                rect.y -= self.layout.inset as i32;
+                rect.h += 2 * self.layout.inset;
                 RoundedRectangle::new(
                     rect.into(),
-                    CornerRadii::new(Size::new_equal((styles.ui_font.size + 8) / 2)),
+                    CornerRadii::new(Size::new_equal(self.layout.radius)),
                 )
                 .into_styled(PrimitiveStyle::with_fill(color.to_color(styles)))
                 .draw(display)?;
             } else {
-                display.load(self.bounding_box(styles))?;
+                display.load(self.rect)?;
             }
 
             let left = self
@@ -200,10 +372,13 @@ impl View for SettingsList {
                 let rect = left.union(&right);
                 RoundedRectangle::with_equal_corners(
                     Rectangle::new(
-                        embedded_graphics::prelude::Point::new(self.rect.x, rect.y - 4),
-                        Size::new(self.rect.w, rect.h + 8),
+                        embedded_graphics::prelude::Point::new(
+                            self.rect.x,
+                            rect.y - self.layout.inset as i32,
+                        ),
+                        Size::new(self.rect.w, rect.h + 2 * self.layout.inset),
                     ),
-                    Size::new_equal(rect.h),
+                    Size::new_equal(self.layout.radius),
                 )
                 .into_styled(PrimitiveStyle::with_fill(
                     styles.highlight_color.blend(styles.background_color, 128),
@@ -215,10 +390,16 @@ impl View for SettingsList {
             let rect = if self.focused { right } else { left };
             RoundedRectangle::with_equal_corners(
                 Rectangle::new(
-                    embedded_graphics::prelude::Point::new(rect.x - 12, rect.y - 4),
-                    Size::new(rect.w + 24, rect.h + 8),
+                    embedded_graphics::prelude::Point::new(
+                        rect.x - self.layout.padding as i32,
+                        rect.y - self.layout.inset as i32,
+                    ),
+                    Size::new(
+                        rect.w + 2 * self.layout.padding,
+                        rect.h + 2 * self.layout.inset,
+                    ),
                 ),
-                Size::new_equal(rect.h),
+                Size::new_equal(self.layout.radius),
             )
             .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
             .draw(display)?;
@@ -230,6 +411,22 @@ impl View for SettingsList {
             }
 
             self.dirty = false;
+            self.dirty_from = None;
+        } else if let Some(first) = self.dirty_from.take() {
+            for i in first..self.capacity() {
+                display.load(Rect::new(
+                    self.rect.x,
+                    self.rect.y + (i * self.layout.height as usize) as i32,
+                    self.rect.w,
+                    self.layout.height,
+                ))?;
+                if let Some(left) = self.left.get_mut(i) {
+                    left.set_should_draw();
+                }
+                if let Some(right) = self.right.get_mut(self.top + i) {
+                    right.set_should_draw();
+                }
+            }
         }
 
         let mut drawn = false;
@@ -258,10 +455,13 @@ impl View for SettingsList {
                 let rect = left_rect.union(&right_rect);
                 RoundedRectangle::with_equal_corners(
                     Rectangle::new(
-                        embedded_graphics::prelude::Point::new(self.rect.x, rect.y - 4),
-                        Size::new(self.rect.w, rect.h + 8),
+                        embedded_graphics::prelude::Point::new(
+                            self.rect.x,
+                            rect.y - self.layout.inset as i32,
+                        ),
+                        Size::new(self.rect.w, rect.h + 2 * self.layout.inset),
                     ),
-                    Size::new_equal(rect.h),
+                    Size::new_equal(self.layout.radius),
                 )
                 .into_styled(PrimitiveStyle::with_fill(
                     styles.highlight_color.blend(styles.background_color, 128),
@@ -272,10 +472,16 @@ impl View for SettingsList {
             // Highlight
             RoundedRectangle::with_equal_corners(
                 Rectangle::new(
-                    embedded_graphics::prelude::Point::new(right_rect.x - 12, right_rect.y - 4),
-                    Size::new(right_rect.w + 24, right_rect.h + 8),
+                    embedded_graphics::prelude::Point::new(
+                        right_rect.x - self.layout.padding as i32,
+                        right_rect.y - self.layout.inset as i32,
+                    ),
+                    Size::new(
+                        right_rect.w + 2 * self.layout.padding,
+                        right_rect.h + 2 * self.layout.inset,
+                    ),
                 ),
-                Size::new_equal(right_rect.h),
+                Size::new_equal(self.layout.radius),
             )
             .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
             .draw(display)?;
@@ -290,6 +496,7 @@ impl View for SettingsList {
 
     fn should_draw(&self) -> bool {
         self.dirty
+            || self.dirty_from.is_some()
             || self.left.iter().any(|c| c.should_draw())
             || self
                 .right
@@ -337,51 +544,33 @@ impl View for SettingsList {
             Ok(false)
         } else if !self.left.is_empty() {
             match event {
-                KeyEvent::Pressed(Key::Up) | KeyEvent::Autorepeat(Key::Up) => {
-                    self.select(
-                        (self.selected as isize - 1).rem_euclid(self.right.len() as isize) as usize,
-                    );
+                KeyEvent::Pressed(key @ (Key::Up | Key::Down))
+                | KeyEvent::Autorepeat(key @ (Key::Up | Key::Down)) => {
+                    let mut navigation = self.navigation();
+                    navigation.step(key == Key::Down);
+                    self.select(navigation.selected);
                     self.dirty = true;
                     Ok(true)
                 }
-                KeyEvent::Pressed(Key::Down) | KeyEvent::Autorepeat(Key::Down) => {
-                    self.select((self.selected + 1).rem_euclid(self.right.len()));
+                KeyEvent::Pressed(key @ (Key::L | Key::R))
+                | KeyEvent::Autorepeat(key @ (Key::L | Key::R)) => {
+                    self.page(key == Key::R);
                     self.dirty = true;
                     Ok(true)
                 }
-                KeyEvent::Pressed(Key::L) | KeyEvent::Autorepeat(Key::L) => {
-                    self.select(
-                        (self.selected as isize - 5).clamp(0, self.right.len() as isize - 1)
-                            as usize,
-                    );
-                    self.dirty = true;
+                KeyEvent::Pressed(Key::A) => self.forward_key_event(event, command, bubble).await,
+                // The selected row's widget gets to adjust its value first. Rows that have
+                // nothing to adjust, and holding the key, page through the list instead.
+                KeyEvent::Pressed(key @ (Key::Left | Key::Right)) => {
+                    if !self.forward_key_event(event, command, bubble).await? {
+                        self.page(key == Key::Right);
+                    }
                     Ok(true)
                 }
-                KeyEvent::Pressed(Key::R) | KeyEvent::Autorepeat(Key::R) => {
-                    self.select((self.selected + 5).clamp(0, self.right.len() - 1));
-                    self.dirty = true;
+                KeyEvent::Autorepeat(key @ (Key::Left | Key::Right)) => {
+                    self.page(key == Key::Right);
                     Ok(true)
                 }
-                KeyEvent::Pressed(Key::A) => {
-                    if let Some(selected) = self.right.get_mut(self.selected) {
-                        if selected.handle_key_event(event, command, bubble).await? {
-                            bubble.retain_mut(|cmd| match cmd {
-                                Command::TrapFocus => {
-                                    self.focused = true;
-                                    self.dirty = true;
-                                    false
-                                }
-                                Command::ValueChanged(i, _) => {
-                                    *i = self.selected;
-                                    true
-                                }
-                                _ => true,
-                            });
-                            return Ok(true);
-                        }
-                    }
-                    Ok(false)
-                }
                 _ => Ok(false),
             }
         } else {
@@ -405,20 +594,299 @@ impl View for SettingsList {
             .collect()
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
+        if !self.has_layout {
+            self.layout();
+        }
         self.rect
     }
 
     fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.rect.top_left(), point);
         self.rect.x = point.x;
         self.rect.y = point.y;
+        self.has_layout = false;
         for (i, child) in self.left.iter_mut().enumerate() {
             child.set_position(Point::new(
-                point.x + 12,
-                point.y + 8 + i as i32 * self.entry_height as i32,
+                point.x + self.layout.padding as i32,
+                point.y + self.layout.inset as i32 + i as i32 * self.layout.height as i32,
             ));
         }
 
         self.dirty = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::Value;
+    use crate::display::golden::styles;
+    use crate::view::{ButtonHint, Percentage, Row, Select, Toggle};
+
+    fn label(text: &str) -> Box<dyn View> {
+        Box::new(Label::new(
+            Point::zero(),
+            text.to_string(),
+            Alignment::Right,
+            None,
+        ))
+    }
+
+    fn new_list(len: usize) -> SettingsList {
+        // Fits 5 rows
+        SettingsList::new(
+            Rect::new(0, 0, 100, 100),
+            (0..len).map(|i| i.to_string()).collect(),
+            (0..len).map(|i| label(&i.to_string())).collect(),
+            RowLayout {
+                height: 20,
+                inset: 4,
+                padding: 12,
+                radius: 10,
+            },
+        )
+    }
+
+    #[test]
+    fn test_insert_keeps_selection() {
+        let mut list = new_list(10);
+        list.select(7);
+        list.insert(2, "new".to_string(), label("new"));
+        assert_eq!(list.left(list.selected()), "7");
+        assert_eq!(list.right.len(), 11);
+
+        list.insert(9, "below".to_string(), label("below"));
+        assert_eq!(list.left(list.selected()), "7");
+        assert_eq!(list.left(9), "below");
+    }
+
+    #[test]
+    fn test_remove_keeps_selection() {
+        let mut list = new_list(10);
+        list.select(9);
+        list.select(5);
+        let (left, _) = list.remove(9).unwrap();
+        assert_eq!(left, "9");
+        assert_eq!(list.left(list.selected()), "5");
+        assert_eq!(list.top, 4);
+        assert_eq!(list.left.len(), 5);
+
+        list.remove(5);
+        assert_eq!(list.left(list.selected()), "6");
+        assert!(list.remove(8).is_none());
+    }
+
+    /// A toggle, a slider, a choice, then rows with nothing to adjust.
+    fn mixed_list() -> SettingsList {
+        let right: Vec<Box<dyn View>> = vec![
+            Box::new(Toggle::new(Point::zero(), false, Alignment::Right)),
+            Box::new(Percentage::new(Point::zero(), 50, Alignment::Right)),
+            Box::new(Select::new(
+                Point::zero(),
+                0,
+                vec!["a".to_string(), "b".to_string(), "c".to_string()],
+                Alignment::Right,
+            )),
+            label("header"),
+            label("4"),
+            label("5"),
+            label("6"),
+            label("7"),
+        ];
+        SettingsList::new(
+            Rect::new(0, 0, 100, 100),
+            (0..right.len()).map(|i| i.to_string()).collect(),
+            right,
+            RowLayout {
+                height: 20,
+                inset: 4,
+                padding: 12,
+                radius: 10,
+            },
+        )
+    }
+
+    /// Sends `event` to `list`, returning whether it was consumed and the bubbled commands.
+    async fn press(list: &mut SettingsList, event: KeyEvent) -> (bool, Vec<Command>) {
+        let (tx, _rx) = tokio::sync::mpsc::channel(8);
+        let mut bubble = VecDeque::new();
+        let consumed = list.handle_key_event(event, tx, &mut bubble).await.unwrap();
+        (consumed, bubble.into_iter().collect())
+    }
+
+    #[tokio::test]
+    async fn test_left_right_adjusts_widget() {
+        let mut list = mixed_list();
+
+        let (consumed, bubble) = press(&mut list, KeyEvent::Pressed(Key::Right)).await;
+        assert!(consumed);
+        assert!(matches!(
+            bubble[..],
+            [Command::ValueChanged(0, Value::Bool(true))]
+        ));
+        assert_eq!(list.selected(), 0);
+
+        // Already on, so nothing changes, but the list doesn't page either
+        let (consumed, bubble) = press(&mut list, KeyEvent::Pressed(Key::Right)).await;
+        assert!(consumed && bubble.is_empty());
+        assert_eq!(list.selected(), 0);
+
+        list.select(1);
+        let (_, bubble) = press(&mut list, KeyEvent::Pressed(Key::Left)).await;
+        assert!(matches!(
+            bubble[..],
+            [Command::ValueChanged(1, Value::Int(45))]
+        ));
+
+        list.select(2);
+        let (_, bubble) = press(&mut list, KeyEvent::Pressed(Key::Left)).await;
+        assert!(matches!(
+            bubble[..],
+            [Command::ValueChanged(2, Value::Int(2))]
+        ));
+        assert_eq!(list.selected(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_left_right_pages_without_widget() {
+        let mut list = mixed_list();
+
+        // A label has nothing to adjust
+        list.select(3);
+        let (consumed, bubble) = press(&mut list, KeyEvent::Pressed(Key::Right)).await;
+        assert!(consumed && bubble.is_empty());
+        assert_eq!(list.selected(), 7);
+        let (_, bubble) = press(&mut list, KeyEvent::Pressed(Key::Left)).await;
+        assert!(bubble.is_empty());
+        assert_eq!(list.selected(), 2);
+
+        // Holding the key pages even over widgets, without changing them
+        list.select(0);
+        let (consumed, bubble) = press(&mut list, KeyEvent::Autorepeat(Key::Right)).await;
+        assert!(consumed && bubble.is_empty());
+        assert_eq!(list.selected(), 5);
+        let (_, bubble) = press(&mut list, KeyEvent::Autorepeat(Key::Left)).await;
+        assert!(bubble.is_empty());
+        assert_eq!(list.selected(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_paging_wraps_by_settings() {
+        let mut list = mixed_list();
+        list.select(7);
+        press(&mut list, KeyEvent::Pressed(Key::R)).await;
+        assert_eq!(list.selected(), 7);
+
+        list.set_list_settings(ListSettings {
+            wrap_pages: true,
+            ..ListSettings::default()
+        });
+        press(&mut list, KeyEvent::Pressed(Key::R)).await;
+        assert_eq!(list.selected(), 0);
+        press(&mut list, KeyEvent::Pressed(Key::L)).await;
+        assert_eq!(list.selected(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_focused_widget_gets_left_right() {
+        let mut list = mixed_list();
+        list.select(1);
+
+        let (consumed, bubble) = press(&mut list, KeyEvent::Pressed(Key::A)).await;
+        assert!(consumed && bubble.is_empty());
+        assert!(list.focused);
+
+        // While editing, the value only changes once confirmed, and the list doesn't move
+        let (_, bubble) = press(&mut list, KeyEvent::Pressed(Key::Right)).await;
+        assert!(bubble.is_empty());
+        let (_, bubble) = press(&mut list, KeyEvent::Autorepeat(Key::Right)).await;
+        assert!(bubble.is_empty());
+        assert_eq!(list.selected(), 1);
+
+        let (_, bubble) = press(&mut list, KeyEvent::Pressed(Key::A)).await;
+        assert!(matches!(
+            bubble[..],
+            [Command::ValueChanged(1, Value::Int(60))]
+        ));
+        assert!(!list.focused);
+    }
+
+    #[tokio::test]
+    async fn test_other_keys_navigate() {
+        let mut list = mixed_list();
+
+        // Widgets don't take keys other than A and Left/Right
+        let (consumed, bubble) = press(&mut list, KeyEvent::Pressed(Key::Down)).await;
+        assert!(consumed && bubble.is_empty());
+        assert_eq!(list.selected(), 1);
+        let (consumed, _) = press(&mut list, KeyEvent::Pressed(Key::X)).await;
+        assert!(!consumed);
+        assert!(!list.focused);
+    }
+
+    #[test]
+    fn test_replace_below_selection() {
+        let mut list = new_list(3);
+        list.dirty = false;
+        list.replace(2, "new".to_string(), label("new"));
+        assert_eq!(list.left[2].text(), "new");
+        assert!(!list.dirty);
+        assert_eq!(list.dirty_from, Some(2));
+    }
+
+    /// Bounding boxes of everything on a settings screen: the list, its rows, and the button hints.
+    fn boxes(
+        list: &mut SettingsList,
+        hints: &mut Row<ButtonHint<String>>,
+        styles: &Styles,
+    ) -> Vec<Rect> {
+        let mut boxes = vec![list.bounding_box(styles), hints.bounding_box(styles)];
+        for child in list.children_mut().into_iter().chain(hints.children_mut()) {
+            boxes.push(child.bounding_box(styles));
+        }
+        boxes
+    }
+
+    #[test]
+    fn test_layout_stable_across_draws() {
+        let styles = styles();
+        let mut list = SettingsList::new(
+            Rect::new(0, 0, 400, 200),
+            vec!["WiFi".to_string(), "Name".to_string()],
+            vec![
+                Box::new(Toggle::new(Point::zero(), true, Alignment::Right)),
+                label("Allium"),
+            ],
+            styles.row_layout(),
+        );
+        let mut hints = Row::new(
+            Point::new(400, 300),
+            vec![
+                ButtonHint::new(Point::zero(), Key::A, "Edit".to_string(), Alignment::Right),
+                ButtonHint::new(Point::zero(), Key::B, "Back".to_string(), Alignment::Right),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        // Widgets are in place before the first draw, and stay there
+        let before = boxes(&mut list, &mut hints, &styles);
+        let mut display = DefaultPlatform::new().unwrap().display().unwrap();
+        for _ in 0..2 {
+            list.set_should_draw();
+            hints.set_should_draw();
+            list.draw(&mut display, &styles).unwrap();
+            hints.draw(&mut display, &styles).unwrap();
+            assert_eq!(boxes(&mut list, &mut hints, &styles), before);
+        }
+
+        let name = list.right_mut(1).bounding_box(&styles);
+        assert_eq!(
+            name.x + name.w as i32,
+            400 - styles.row_layout().padding as i32
+        );
+        let row = hints.bounding_box(&styles);
+        assert_eq!(hints.size_hint(&styles).w, row.w);
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use embedded_graphics::prelude::{Dimensions, OriginDimensions, Size};
+use embedded_graphics::primitives::{CornerRadii, Primitive, PrimitiveStyle, RoundedRectangle};
+use embedded_graphics::text::{Alignment, Text};
+use embedded_graphics::Drawable;
+use tokio::sync::mpsc::Sender;
+
+use crate::command::Command;
+use crate::display::color::Color;
+use crate::display::font::{FontTextStyle, FontTextStyleBuilder};
+use crate::geom::{Point, Rect};
+use crate::platform::{DefaultPlatform, KeyEvent, Platform};
+use crate::stylesheet::Styles;
+use crate::view::{LastDrawn, View};
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    text: String,
+    expires: Option<Instant>,
+    /// Top left corner of the toast, which is centered on the screen until it is given one.
+    point: Option<Point>,
+    drawn: LastDrawn,
+}
+
+impl Toast {
+    pub fn new(text: String, duration: Option<Duration>) -> Self {
+        Self {
+            text,
+            expires: duration.map(|duration| Instant::now() + duration),
+            point: None,
+            drawn: LastDrawn::default(),
+        }
+    }
+
+    /// The text, centered horizontally on `anchor`, and the box around it.
+    fn layout(&self, styles: &Styles, anchor: Point) -> (Text<'_, FontTextStyle<Color>>, Rect) {
+        let text_style = FontTextStyleBuilder::new(styles.ui_font())
+            .font_fallback(styles.cjk_font())
+            .font_size(styles.ui_font.size)
+            .background_color(styles.highlight_color)
+            .text_color(styles.foreground_color)
+            .build();
+        let text = Text::with_alignment(&self.text, anchor.into(), text_style, Alignment::Center);
+
+        let rect = text.bounding_box();
+        let Size { width, height } = rect.size;
+        let rect = Rect::new(
+            rect.top_left.x - 12,
+            rect.top_left.y - 8,
+            width + 24,
+            height + 16,
+        );
+        (text, rect)
+    }
+
+    /// Where the text is anchored for the box to have its top left corner at `point`.
+    fn anchor_at(&self, styles: &Styles, point: Point) -> Point {
+        let (_, rect) = self.layout(styles, Point::zero());
+        Point::new(point.x - rect.x, point.y - rect.y)
+    }
+
+    pub fn has_expired(&self) -> bool {
+        if let Some(expires) = self.expires {
+            Instant::now() > expires
+        } else {
+            false
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Toast {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        let anchor = match self.point {
+            Some(point) => self.anchor_at(styles, point),
+            None => {
+                let w = display.size().width;
+                let h = display.size().height;
+                let lines = self.text.lines().count() as u32;
+                Point::new(w as i32 / 2, (h - styles.ui_font.size * lines) as i32 / 2)
+            }
+        };
+
+        self.drawn.clear(display)?;
+        let (text, rect) = self.layout(styles, anchor);
+        RoundedRectangle::new(rect.into(), CornerRadii::new(Size::new_equal(12)))
+            .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+            .draw(display)?;
+        text.draw(display)?;
+        self.drawn.set(rect);
+
+        Ok(true)
+    }
+
+    fn should_draw(&self) -> bool {
+        true
+    }
+
+    fn set_should_draw(&mut self) {}
+
+    async fn handle_key_event(
+        &mut self,
+        _event: KeyEvent,
+        _commands: Sender<Command>,
+        _bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![]
+    }
+
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
+        match self.point {
+            Some(point) => self.layout(styles, self.anchor_at(styles, point)).1,
+            None => Rect::zero(),
+        }
+    }
+
+    fn set_position(&mut self, point: Point) {
+        // A centered toast has no position to compare with, so it always counts as moved
+        self.drawn.moved(
+            self.point.unwrap_or(Point::new(point.x + 1, point.y)),
+            point,
+        );
+        self.point = Some(point);
+    }
+}
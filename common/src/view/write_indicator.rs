@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use embedded_graphics::prelude::OriginDimensions;
+use embedded_graphics::primitives::{Circle, Primitive, PrimitiveStyle};
+use embedded_graphics::Drawable;
+use tokio::sync::mpsc::Sender;
+
+use crate::constants::WRITE_ACTIVITY_UPDATE_INTERVAL;
+use crate::display::Display;
+use crate::geom::{Point, Rect};
+use crate::platform::{DefaultPlatform, KeyEvent, Platform};
+use crate::stylesheet::Styles;
+use crate::view::{Command, LastDrawn, View};
+use crate::write_activity;
+
+/// A dot that is shown while writes to the SD card are in progress, so that the user knows not to
+/// power off. Drawn to the left of `point`.
+#[derive(Debug, Clone)]
+pub struct WriteIndicator {
+    point: Point,
+    last_updated: Option<Instant>,
+    visible: bool,
+    dirty: bool,
+    drawn: LastDrawn,
+}
+
+impl WriteIndicator {
+    pub fn new(point: Point) -> Self {
+        Self {
+            point,
+            last_updated: None,
+            visible: false,
+            dirty: false,
+            drawn: LastDrawn::default(),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Draws the indicator over whatever is on screen, where the launcher's would be, for when
+    /// writes are in progress but no view is in the foreground to show them, e.g. in game.
+    pub fn draw_over(
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<()> {
+        display.save()?;
+        let width = display.size().width as i32;
+        let mut indicator = Self::new(Point::new(
+            width - 12 - styles.ui_font.size as i32 * 2 - 8,
+            8,
+        ));
+        indicator.visible = true;
+        indicator.dirty = true;
+        indicator.draw(display, styles)?;
+        display.flush()
+    }
+
+    fn diameter(styles: &Styles) -> u32 {
+        styles.ui_font.size / 3
+    }
+}
+
+#[async_trait(?Send)]
+impl View for WriteIndicator {
+    fn update(&mut self, _dt: Duration) {
+        if self
+            .last_updated
+            .is_some_and(|last| last.elapsed() < WRITE_ACTIVITY_UPDATE_INTERVAL)
+        {
+            return;
+        }
+        self.last_updated = Some(Instant::now());
+        let visible = write_activity::is_writing();
+        if visible != self.visible {
+            self.visible = visible;
+            self.dirty = true;
+        }
+    }
+
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Styles,
+    ) -> Result<bool> {
+        if !self.dirty {
+            return Ok(false);
+        }
+
+        self.drawn.clear(display)?;
+        let rect = self.bounding_box(styles);
+        display.load(rect)?;
+        self.drawn.set(rect);
+        if self.visible {
+            Circle::new(rect.top_left().into(), rect.w)
+                .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+                .draw(display)?;
+        }
+        self.dirty = false;
+        Ok(true)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        _event: KeyEvent,
+        _commands: Sender<Command>,
+        _bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![]
+    }
+
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
+        let diameter = Self::diameter(styles);
+        Rect::new(
+            self.point.x - diameter as i32,
+            self.point.y + (styles.ui_font.size - diameter) as i32 / 2,
+            diameter,
+            diameter,
+        )
+    }
+
+    fn set_position(&mut self, point: Point) {
+        self.drawn.moved(self.point, point);
+        self.point = point;
+        self.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view::tests::assert_moves_cleanly;
+
+    #[test]
+    fn test_moves_cleanly() {
+        let mut indicator = WriteIndicator::new(Point::zero());
+        indicator.visible = true;
+        assert_moves_cleanly(
+            "WriteIndicator",
+            &mut indicator,
+            Point::new(300, 40),
+            Point::new(340, 300),
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Volume levels and the protection against sudden jumps in loudness, e.g. when headphones are
+//! plugged in or a game leaves the mixer at a different level.
+
+use std::fs::{self, File};
+
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::ALLIUM_VOLUME_SETTINGS;
+
+/// Highest volume level. Levels go from 0 (muted) to this.
+pub const MAX_VOLUME: i32 = 20;
+
+/// Lowest raw mixer value, in dB.
+pub const MIN_RAW_VOLUME: i32 = -60;
+
+/// Highest raw mixer value, in dB.
+pub const MAX_RAW_VOLUME: i32 = 0;
+
+/// Where sound is played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioOutput {
+    Speaker,
+    Headphones,
+}
+
+/// What caused a volume change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeSource {
+    /// The volume buttons. Applied straight away.
+    User,
+    /// Anything else, such as switching outputs or restoring the volume after a game exits.
+    /// Increases are ramped up gradually.
+    Automatic,
+}
+
+// Volume curve:
+// |   0 |   1 |   2 |   3 |   4 |   5 |   6 |   7 |   8 |   9 |  10 |  11 |  12 |  13 |  14 |  15 |  16 |  17 |  18 |  19 |  20 |
+// | -60 | -46 | -38 | -32 | -28 | -24 | -21 | -19 | -16 | -14 | -12 | -11 |  -9 |  -7 |  -6 |  -5 |  -4 |  -3 |  -1 |   0 |   0 |
+/// Maps a volume level to the raw mixer value.
+pub fn volume_to_raw(volume: i32) -> i32 {
+    let volume = volume.clamp(0, MAX_VOLUME);
+    let raw = (volume as f32 + 1.0).log10() / ((MAX_VOLUME + 1) as f32).log10()
+        * (MAX_RAW_VOLUME - MIN_RAW_VOLUME) as f32
+        + MIN_RAW_VOLUME as f32;
+    raw as i32
+}
+
+/// Maps a raw mixer value back to the closest volume level. Levels that share a raw value can't be
+/// told apart, so compare raw values to check whether the mixer level changed.
+pub fn raw_to_volume(raw: i32) -> i32 {
+    (0..=MAX_VOLUME)
+        .min_by_key(|&volume| (volume_to_raw(volume) - raw).abs())
+        .unwrap()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VolumeSettings {
+    /// Highest volume level used while headphones are plugged in.
+    #[serde(default = "VolumeSettings::default_max_headphone_volume")]
+    pub max_headphone_volume: i32,
+    /// How many levels an automatic change may raise the volume by per ramp interval.
+    #[serde(default = "VolumeSettings::default_ramp_step")]
+    pub ramp_step: i32,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        Self {
+            max_headphone_volume: Self::default_max_headphone_volume(),
+            ramp_step: Self::default_ramp_step(),
+        }
+    }
+}
+
+impl VolumeSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn default_max_headphone_volume() -> i32 {
+        12
+    }
+
+    fn default_ramp_step() -> i32 {
+        1
+    }
+
+    /// The level that `volume` is actually played at on `output`.
+    pub fn limit(&self, volume: i32, output: AudioOutput) -> i32 {
+        let volume = volume.clamp(0, MAX_VOLUME);
+        match output {
+            AudioOutput::Speaker => volume,
+            AudioOutput::Headphones => volume.min(self.max_headphone_volume.clamp(0, MAX_VOLUME)),
+        }
+    }
+
+    pub fn load() -> Result<Self> {
+        if ALLIUM_VOLUME_SETTINGS.exists() {
+            debug!("found state, loading from file");
+            let file = File::open(ALLIUM_VOLUME_SETTINGS.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(json);
+            }
+            warn!("failed to read volume settings, removing");
+            fs::remove_file(ALLIUM_VOLUME_SETTINGS.as_path())?;
+        }
+        Ok(Self::new())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_VOLUME_SETTINGS.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+}
+
+/// Moves the output level towards a target. Decreases and changes made by the user are applied
+/// straight away, but automatic increases are spread out over several steps.
+#[derive(Debug, Clone)]
+pub struct VolumeRamp {
+    current: i32,
+    target: i32,
+    step: i32,
+}
+
+impl VolumeRamp {
+    pub fn new(current: i32, step: i32) -> Self {
+        Self {
+            current,
+            target: current,
+            step: step.max(1),
+        }
+    }
+
+    /// The level the output is currently at.
+    pub fn current(&self) -> i32 {
+        self.current
+    }
+
+    /// Returns true if the output hasn't reached the target yet.
+    pub fn is_ramping(&self) -> bool {
+        self.current < self.target
+    }
+
+    /// Records the level the output is actually at, e.g. after something else changed it.
+    pub fn set_current(&mut self, current: i32) {
+        self.current = current;
+    }
+
+    /// Sets the level to move towards, and returns the level to apply now.
+    pub fn set_target(&mut self, target: i32, source: VolumeSource) -> i32 {
+        self.target = target;
+        if source == VolumeSource::User || target <= self.current {
+            self.current = target;
+        } else {
+            self.current = (self.current + self.step).min(target);
+        }
+        self.current
+    }
+
+    /// Advances the ramp by one interval. Returns the new level if it changed.
+    pub fn tick(&mut self) -> Option<i32> {
+        if !self.is_ramping() {
+            return None;
+        }
+        self.current = (self.current + self.step).min(self.target);
+        Some(self.current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volume_curve() {
+        let expected = [
+            -60, -46, -38, -32, -28, -24, -21, -19, -16, -14, -12, -11, -9, -7, -6, -5, -4, -3, -1,
+            0, 0,
+        ];
+        for (volume, raw) in expected.into_iter().enumerate() {
+            assert_eq!(volume_to_raw(volume as i32), raw);
+            assert_eq!(volume_to_raw(raw_to_volume(raw)), raw);
+        }
+        assert_eq!(raw_to_volume(-9), 12);
+        assert_eq!(volume_to_raw(-5), MIN_RAW_VOLUME);
+        assert_eq!(volume_to_raw(30), MAX_RAW_VOLUME);
+        assert_eq!(raw_to_volume(-50), 1);
+    }
+
+    #[test]
+    fn test_headphone_limit() {
+        let settings = VolumeSettings::new();
+        assert_eq!(settings.limit(20, AudioOutput::Speaker), 20);
+        assert_eq!(settings.limit(20, AudioOutput::Headphones), 12);
+        assert_eq!(settings.limit(5, AudioOutput::Headphones), 5);
+        assert_eq!(
+            volume_to_raw(settings.limit(20, AudioOutput::Headphones)),
+            -9
+        );
+
+        // A game that left the mixer louder than the cap is brought back down to it
+        assert_eq!(
+            settings.limit(raw_to_volume(-3), AudioOutput::Headphones),
+            12
+        );
+
+        // A cap outside of the curve is clamped to it
+        let settings = VolumeSettings {
+            max_headphone_volume: 50,
+            ..VolumeSettings::new()
+        };
+        assert_eq!(
+            volume_to_raw(settings.limit(20, AudioOutput::Headphones)),
+            MAX_RAW_VOLUME
+        );
+        let settings = VolumeSettings {
+            max_headphone_volume: -1,
+            ..VolumeSettings::new()
+        };
+        assert_eq!(
+            volume_to_raw(settings.limit(20, AudioOutput::Headphones)),
+            MIN_RAW_VOLUME
+        );
+    }
+
+    #[test]
+    fn test_automatic_increase_is_ramped() {
+        let mut ramp = VolumeRamp::new(2, 3);
+        assert_eq!(ramp.set_target(10, VolumeSource::Automatic), 5);
+        assert!(ramp.is_ramping());
+        assert_eq!(ramp.tick(), Some(8));
+        assert_eq!(ramp.tick(), Some(10));
+        assert!(!ramp.is_ramping());
+        assert_eq!(ramp.tick(), None);
+    }
+
+    #[test]
+    fn test_user_and_decreases_are_immediate() {
+        let mut ramp = VolumeRamp::new(2, 1);
+        assert_eq!(ramp.set_target(10, VolumeSource::User), 10);
+        assert!(!ramp.is_ramping());
+
+        assert_eq!(ramp.set_target(4, VolumeSource::Automatic), 4);
+        assert!(!ramp.is_ramping());
+
+        // Lowering the target part way through a ramp stops it straight away
+        ramp.set_target(8, VolumeSource::Automatic);
+        assert_eq!(ramp.current(), 5);
+        assert_eq!(ramp.set_target(3, VolumeSource::Automatic), 3);
+        assert_eq!(ramp.tick(), None);
+    }
+
+    #[test]
+    fn test_ramp_from_external_level() {
+        // A game muted the mixer, so the ramp starts again from there
+        let mut ramp = VolumeRamp::new(10, 2);
+        ramp.set_current(0);
+        assert_eq!(ramp.set_target(10, VolumeSource::Automatic), 2);
+        assert_eq!(ramp.tick(), Some(4));
+    }
+}
@@ -17,6 +17,9 @@ pub struct WiFiSettings {
     pub ntp: bool,
     pub telnet: bool,
     pub ftp: bool,
+    /// Whether alliumd serves the companion API while Wi-Fi is connected.
+    #[serde(default)]
+    pub remote: bool,
 }
 
 impl WiFiSettings {
@@ -28,6 +31,7 @@ impl WiFiSettings {
             ntp: false,
             telnet: false,
             ftp: false,
+            remote: false,
         }
     }
 
@@ -188,6 +192,11 @@ network={{
         }
         Ok(())
     }
+
+    /// alliumd picks the change up on its own, see [`crate::constants::REMOTE_CHECK_INTERVAL`].
+    pub fn toggle_remote(&mut self, enabled: bool) {
+        self.remote = enabled;
+    }
 }
 
 impl Default for WiFiSettings {
@@ -337,7 +346,7 @@ pub fn ntp_sync() -> Result<()> {
         // Reset start time if time changed
         match crate::game_info::GameInfo::load() {
             Ok(Some(mut game_info)) => {
-                game_info.start_time = chrono::Utc::now();
+                game_info.reset_session();
                 game_info
                     .save()
                     .map_err(|e| {
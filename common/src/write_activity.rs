@@ -0,0 +1,227 @@
+//! Writes to the SD card that would leave corrupt files if the card were pulled or the device
+//! powered off halfway, such as save backups, batch operations and database maintenance.
+//!
+//! Such writes hold a [`WriteGuard`] while they run. The views show an indicator while any process
+//! holds one, and alliumd waits for them to finish before powering off. Each guard leaves a marker
+//! file named after its process, so that other processes can tell writes are in progress. Markers
+//! of processes that have exited are ignored, in case a process was killed before cleaning up.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+use log::{trace, warn};
+
+use crate::constants::ALLIUM_WRITE_ACTIVITY;
+
+#[cfg(unix)]
+use {nix::sys::signal::kill, nix::unistd::Pid};
+
+/// How often to check whether writes have finished while waiting for them.
+const WAIT_INTERVAL: Duration = Duration::from_millis(50);
+
+lazy_static! {
+    static ref GLOBAL: WriteActivity = WriteActivity::new(ALLIUM_WRITE_ACTIVITY.clone());
+}
+
+/// Writes in progress, counted in this process and marked in `dir` for other processes.
+#[derive(Debug)]
+pub struct WriteActivity {
+    dir: PathBuf,
+    active: AtomicUsize,
+    next_id: AtomicUsize,
+}
+
+impl WriteActivity {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            active: AtomicUsize::new(0),
+            next_id: AtomicUsize::new(0),
+        }
+    }
+
+    /// Marks a write as in progress until the returned guard is dropped.
+    pub fn begin(&self, what: &str) -> WriteGuard<'_> {
+        self.active.fetch_add(1, Ordering::SeqCst);
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let marker = self.dir.join(format!("{}-{}", std::process::id(), id));
+        let marker = match fs::create_dir_all(&self.dir).and_then(|_| fs::write(&marker, what)) {
+            Ok(()) => Some(marker),
+            Err(e) => {
+                warn!("failed to mark write as in progress: {}", e);
+                None
+            }
+        };
+        trace!("writing: {}", what);
+        WriteGuard {
+            activity: self,
+            marker,
+        }
+    }
+
+    /// Number of writes in progress in this process.
+    pub fn local(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Whether any process is writing.
+    pub fn is_writing(&self) -> bool {
+        self.local() > 0 || !self.writers().is_empty()
+    }
+
+    /// What the writes in progress in any process are.
+    pub fn writers(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| is_alive(&entry.path()))
+            .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+            .collect()
+    }
+
+    /// Waits until no process is writing, for at most `timeout`. Returns whether the writes
+    /// finished in time.
+    pub async fn wait_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if !self.is_writing() {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                warn!("writes still in progress: {:?}", self.writers());
+                return false;
+            }
+            tokio::time::sleep(WAIT_INTERVAL).await;
+        }
+    }
+}
+
+/// Whether the process that left a marker is still running.
+fn is_alive(marker: &Path) -> bool {
+    let Some(pid) = marker
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.split('-').next())
+        .and_then(|pid| pid.parse::<i32>().ok())
+    else {
+        return false;
+    };
+
+    #[cfg(unix)]
+    return kill(Pid::from_raw(pid), None).is_ok();
+
+    #[cfg(not(unix))]
+    return pid == std::process::id() as i32;
+}
+
+/// A write in progress, which ends when the guard is dropped, including when the write failed.
+#[derive(Debug)]
+pub struct WriteGuard<'a> {
+    activity: &'a WriteActivity,
+    marker: Option<PathBuf>,
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(marker) = self.marker.take() {
+            if let Err(e) = fs::remove_file(&marker) {
+                warn!("failed to clear write marker {}: {}", marker.display(), e);
+            }
+        }
+        self.activity.active.fetch_sub(1, Ordering::SeqCst);
+        trace!("write finished");
+    }
+}
+
+/// Marks a write as in progress until the returned guard is dropped.
+pub fn begin(what: &str) -> WriteGuard<'static> {
+    GLOBAL.begin(what)
+}
+
+/// Whether any process is writing.
+pub fn is_writing() -> bool {
+    GLOBAL.is_writing()
+}
+
+/// Waits until no process is writing, for at most `timeout`. Returns whether the writes finished
+/// in time.
+pub async fn wait_idle(timeout: Duration) -> bool {
+    GLOBAL.wait_idle(timeout).await
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{bail, Result};
+
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    fn activity(name: &str) -> WriteActivity {
+        let dir = temp_dir(&format!("write-activity-{}", name));
+        WriteActivity::new(dir)
+    }
+
+    fn failing_write(activity: &WriteActivity) -> Result<()> {
+        let _guard = activity.begin("failing");
+        assert!(activity.is_writing());
+        bail!("disk full");
+    }
+
+    #[test]
+    fn test_guards_end_on_error() {
+        let activity = activity("error");
+        assert!(!activity.is_writing());
+
+        {
+            let _outer = activity.begin("outer");
+            assert!(failing_write(&activity).is_err());
+            assert_eq!(activity.local(), 1);
+            assert_eq!(activity.writers(), vec!["outer".to_string()]);
+        }
+        assert_eq!(activity.local(), 0);
+        assert!(activity.writers().is_empty());
+
+        let result = std::panic::catch_unwind(|| {
+            let _guard = activity.begin("panicking");
+            panic!("write failed");
+        });
+        assert!(result.is_err());
+        assert_eq!(activity.local(), 0);
+        assert!(!activity.is_writing());
+
+        fs::remove_dir_all(&activity.dir).unwrap();
+    }
+
+    #[test]
+    fn test_markers_of_exited_processes_are_ignored() {
+        let activity = activity("exited");
+        fs::create_dir_all(&activity.dir).unwrap();
+        // No process has this id, as it is above the kernel's limit
+        fs::write(activity.dir.join(format!("{}-0", i32::MAX)), "stale").unwrap();
+        assert!(!activity.is_writing());
+
+        let guard = activity.begin("live");
+        assert_eq!(activity.writers(), vec!["live".to_string()]);
+        drop(guard);
+
+        fs::remove_dir_all(&activity.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_idle() {
+        let activity = activity("wait");
+        assert!(activity.wait_idle(Duration::ZERO).await);
+
+        let guard = activity.begin("slow");
+        assert!(!activity.wait_idle(Duration::from_millis(100)).await);
+        drop(guard);
+        assert!(activity.wait_idle(Duration::from_millis(100)).await);
+
+        let _ = fs::remove_dir_all(&activity.dir);
+    }
+}
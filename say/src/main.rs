@@ -5,7 +5,7 @@ use clap::Parser;
 use common::{
     display::{color::Color, font::FontTextStyleBuilder, Display},
     platform::{DefaultPlatform, Platform},
-    stylesheet::Stylesheet,
+    stylesheet::Styles,
 };
 use embedded_graphics::{
     prelude::{Dimensions, OriginDimensions, Point, Size},
@@ -39,11 +39,11 @@ async fn main() -> Result<()> {
 fn say(text: &str, bg: bool) -> Result<()> {
     let mut platform = DefaultPlatform::new()?;
     let mut display = platform.display()?;
-    let styles = Stylesheet::load()?;
+    let styles = Styles::load()?;
 
-    let text_style = FontTextStyleBuilder::<Color>::new(styles.ui_font.font())
+    let text_style = FontTextStyleBuilder::<Color>::new(styles.ui_font())
         .text_color(styles.foreground_color)
-        .font_fallback(styles.cjk_font.font())
+        .font_fallback(styles.cjk_font())
         .font_size(styles.ui_font.size)
         .build();
 
@@ -7,7 +7,7 @@ use common::display::Display;
 use common::locale::{Locale, LocaleSettings};
 use common::platform::{DefaultPlatform, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::View;
 use embedded_graphics::prelude::*;
 use log::warn;
@@ -34,7 +34,7 @@ impl App<DefaultPlatform> {
         let rect = display.bounding_box().into();
 
         let mut res = TypeMap::new();
-        res.insert(Stylesheet::load()?);
+        res.insert(Styles::load()?);
         res.insert(Locale::new(&LocaleSettings::load()?.lang));
         let res = Resources::new(res);
 
@@ -48,9 +48,9 @@ impl App<DefaultPlatform> {
 
     pub async fn run_event_loop(&mut self) -> Result<()> {
         {
-            let styles = self.res.get::<Stylesheet>();
+            let styles = self.res.get::<Styles>();
             self.display
-                .map_pixels(|pixel| pixel.blend(styles.background_color.overlay(pixel), 192))?;
+                .map_pixels(|pixel| styles.dim_behind_menu(pixel))?;
             self.display.save()?;
         }
 
@@ -9,7 +9,7 @@ use tokio::sync::mpsc::Sender;
 use common::display::Display;
 use common::geom::{Alignment, Point, Rect};
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::{ButtonIcon, Label, View};
 
 #[derive(Debug, Clone)]
@@ -57,7 +57,7 @@ where
         self.dirty = true;
     }
 
-    fn layout(&mut self, styles: &Stylesheet) {
+    fn layout(&mut self, styles: &Styles) {
         match self.alignment {
             Alignment::Left => self.layout_left(styles),
             Alignment::Center => unimplemented!("alignment should be Left or Right"),
@@ -66,7 +66,7 @@ where
         self.has_layout = true;
     }
 
-    fn layout_left(&mut self, styles: &Stylesheet) {
+    fn layout_left(&mut self, styles: &Styles) {
         let mut x = self.point.x;
         for i in 0..self.buttons.len() {
             let button = &mut self.buttons[i];
@@ -82,7 +82,7 @@ where
         self.label.set_position(Point::new(x, self.point.y + 2));
     }
 
-    fn layout_right(&mut self, styles: &Stylesheet) {
+    fn layout_right(&mut self, styles: &Styles) {
         self.label.set_position(self.point);
         let mut x = self.label.bounding_box(styles).w as i32 - 8;
         for button in &mut self.buttons {
@@ -100,7 +100,7 @@ where
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         if !self.has_layout {
             self.layout(styles);
@@ -157,7 +157,7 @@ where
             .collect()
     }
 
-    fn bounding_box(&mut self, styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, styles: &Styles) -> Rect {
         if !self.has_layout {
             self.layout(styles);
         }
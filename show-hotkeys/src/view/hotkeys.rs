@@ -7,7 +7,7 @@ use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::Styles;
 use common::view::{Label, View};
 use tokio::sync::mpsc::Sender;
 
@@ -25,7 +25,7 @@ pub struct Hotkeys {
 impl Hotkeys {
     pub fn new(rect: Rect, res: Resources) -> Self {
         let locale = res.get::<Locale>();
-        let styles = res.get::<Stylesheet>();
+        let styles = res.get::<Styles>();
 
         let x = 100;
         let mut y = 40;
@@ -100,7 +100,7 @@ impl View for Hotkeys {
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
-        styles: &Stylesheet,
+        styles: &Styles,
     ) -> Result<bool> {
         let mut drawn = false;
 
@@ -150,7 +150,7 @@ impl View for Hotkeys {
         vec![]
     }
 
-    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+    fn bounding_box(&mut self, _styles: &Styles) -> Rect {
         self.rect
     }
 
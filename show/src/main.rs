@@ -5,7 +5,8 @@ use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use clap::Parser;
-use common::{display::color::Color, stylesheet::Stylesheet};
+use common::display::{color::Color, greyscale};
+use common::stylesheet::Styles;
 use framebuffer::Framebuffer;
 use image::GenericImageView;
 
@@ -27,7 +28,7 @@ struct Cli {
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    let styles = Stylesheet::load()?;
+    let styles = Styles::load()?;
     let mut fb = Framebuffer::new("/dev/fb0")?;
 
     let vw = fb.var_screen_info.xres_virtual as usize;
@@ -49,13 +50,18 @@ fn main() -> Result<()> {
     };
 
     if cli.darken {
-        darken(&mut frame, styles.background_color, 192);
+        darken(&mut frame, &styles);
     }
 
     if let Some(path) = cli.path {
         show(&fb, &mut frame, path)?;
     }
 
+    if let Some(greyscale) = greyscale::greyscale() {
+        let src = frame.clone();
+        greyscale.transform(&src, &mut frame, vw, bpp);
+    }
+
     fb.write_frame(&frame);
 
     Ok(())
@@ -87,10 +93,10 @@ fn show(fb: &Framebuffer, frame: &mut [u8], path: impl AsRef<Path>) -> Result<()
     Ok(())
 }
 
-fn darken(frame: &mut [u8], color: Color, alpha: u8) {
+fn darken(frame: &mut [u8], styles: &Styles) {
     frame.iter_mut().array_chunks().for_each(|[b, g, r, _]| {
         let pixel = Color::new(*r, *g, *b);
-        let color = pixel.blend(color.overlay(pixel), alpha);
+        let color = styles.dim_behind_menu(pixel);
         *b = color.b();
         *g = color.g();
         *r = color.r();